@@ -0,0 +1,182 @@
+// Copyright 2024-2026 Andrey Vasilevsky <anvanster@gmail.com>
+// SPDX-License-Identifier: Apache-2.0
+
+//! Recall and latency on standard ANN datasets, comparable with
+//! ann-benchmarks results for other vector databases.
+
+use anyhow::{bail, Result};
+use clap::Parser;
+use indicatif::{ProgressBar, ProgressStyle};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use vectrust::*;
+
+mod datasets;
+
+use datasets::{recall_at_k, Dataset, StandardDataset};
+
+#[derive(Parser)]
+#[command(name = "ann-bench")]
+#[command(about = "Recall/latency on ann-benchmarks datasets")]
+struct Args {
+    /// Standard dataset name (e.g. sift-128-euclidean, glove-100-angular,
+    /// dbpedia-openai-1000k-angular), loaded from --data-dir
+    #[arg(short, long, conflicts_with_all = ["file", "texmex"])]
+    dataset: Option<String>,
+
+    /// Any ann-benchmarks HDF5 file
+    #[arg(long)]
+    file: Option<PathBuf>,
+
+    /// Directory with texmex <prefix>_base/_query.fvecs and _groundtruth.ivecs
+    #[arg(long)]
+    texmex: Option<PathBuf>,
+
+    #[arg(long, default_value = "sift")]
+    texmex_prefix: String,
+
+    #[arg(long, default_value = "../data")]
+    data_dir: PathBuf,
+
+    /// Index only the first N base vectors (ground truth is recomputed)
+    #[arg(long)]
+    train_limit: Option<usize>,
+
+    /// Run only the first N queries
+    #[arg(long, default_value = "1000")]
+    test_limit: usize,
+
+    #[arg(short = 'k', long, default_value = "10")]
+    top_k: usize,
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let args = Args::parse();
+
+    let mut dataset = match (&args.dataset, &args.file, &args.texmex) {
+        (Some(name), _, _) => {
+            let Some(standard) = StandardDataset::from_name(name) else {
+                let known: Vec<&str> = StandardDataset::ALL.iter().map(|d| d.name()).collect();
+                bail!("Unknown dataset {:?}; known: {}", name, known.join(", "));
+            };
+            Dataset::load_standard(standard, &args.data_dir)?
+        }
+        (None, Some(file), _) => Dataset::load_hdf5(file)?,
+        (None, None, Some(dir)) => Dataset::load_texmex(dir, &args.texmex_prefix)?,
+        (None, None, None) => bail!("Pass --dataset, --file or --texmex"),
+    };
+    dataset.truncate(args.train_limit, Some(args.test_limit));
+
+    println!("🦀 Vectra ANN Dataset Benchmark");
+    println!("===============================");
+    println!("Dataset: {}", dataset.name);
+    println!("Metric: {:?}", dataset.metric);
+    println!("Base vectors: {}", dataset.train.len());
+    println!("Queries: {}", dataset.test.len());
+    println!("Dimensions: {}", dataset.dimensions());
+    println!();
+
+    let temp_dir = tempfile::TempDir::new()?;
+    let index = LocalIndex::new(temp_dir.path(), None)?;
+    index
+        .create_index(Some(CreateIndexConfig {
+            distance_metric: dataset.metric.clone(),
+            delete_if_exists: true,
+            ..Default::default()
+        }))
+        .await?;
+
+    // Item ids map back to positions in the base set
+    let ids: Vec<uuid::Uuid> = (0..dataset.train.len())
+        .map(|_| uuid::Uuid::new_v4())
+        .collect();
+    let positions: std::collections::HashMap<uuid::Uuid, usize> =
+        ids.iter().enumerate().map(|(i, id)| (*id, i)).collect();
+
+    let progress = progress_bar(dataset.train.len(), "Inserting");
+    let start = Instant::now();
+    for (chunk_ids, chunk) in ids.chunks(10_000).zip(dataset.train.chunks(10_000)) {
+        let items = chunk_ids
+            .iter()
+            .zip(chunk)
+            .map(|(id, vector)| VectorItem {
+                id: *id,
+                vector: vector.clone(),
+                ..Default::default()
+            })
+            .collect();
+        index.insert_items(items).await?;
+        progress.inc(chunk.len() as u64);
+    }
+    let insert_time = start.elapsed();
+    progress.finish_and_clear();
+
+    let progress = progress_bar(dataset.test.len(), "Querying");
+    let mut latencies = Vec::with_capacity(dataset.test.len());
+    let mut recall_sum = 0.0;
+    for (query, truth) in dataset.test.iter().zip(&dataset.neighbors) {
+        let start = Instant::now();
+        let results = index
+            .query_items(query.clone(), Some(args.top_k as u32), None)
+            .await?;
+        latencies.push(start.elapsed());
+
+        let found: Vec<usize> = results
+            .iter()
+            .filter_map(|r| positions.get(&r.item.id).copied())
+            .collect();
+        recall_sum += recall_at_k(truth, &found, args.top_k);
+        progress.inc(1);
+    }
+    progress.finish_and_clear();
+
+    latencies.sort();
+    let total: Duration = latencies.iter().sum();
+    let queries = latencies.len().max(1);
+    let pct = |p: f64| latencies[((latencies.len().saturating_sub(1)) as f64 * p) as usize];
+
+    println!("📈 Results");
+    println!("==========");
+    println!(
+        "{:30} {:>10.1}s ({:.0} vectors/s)",
+        "Insert",
+        insert_time.as_secs_f64(),
+        dataset.train.len() as f64 / insert_time.as_secs_f64()
+    );
+    println!(
+        "{:30} {:>10.4}",
+        format!("Recall@{}", args.top_k),
+        recall_sum / queries as f64
+    );
+    println!(
+        "{:30} {:>10.1}",
+        "QPS",
+        queries as f64 / total.as_secs_f64()
+    );
+    if !latencies.is_empty() {
+        println!(
+            "{:30} {:>10.3}ms",
+            "Latency p50",
+            pct(0.50).as_secs_f64() * 1000.0
+        );
+        println!(
+            "{:30} {:>10.3}ms",
+            "Latency p99",
+            pct(0.99).as_secs_f64() * 1000.0
+        );
+    }
+
+    Ok(())
+}
+
+fn progress_bar(len: usize, message: &str) -> ProgressBar {
+    let pb = ProgressBar::new(len as u64);
+    pb.set_style(
+        ProgressStyle::default_bar()
+            .template("{msg} [{bar:40.cyan/blue}] {pos}/{len} ({eta})")
+            .unwrap(),
+    );
+    pb.set_message(message.to_string());
+    pb
+}
@@ -0,0 +1,301 @@
+// Copyright 2024-2026 Andrey Vasilevsky <anvanster@gmail.com>
+// SPDX-License-Identifier: Apache-2.0
+
+//! Loaders for standard ANN benchmark datasets.
+//!
+//! Supports the ann-benchmarks HDF5 layout (`train`, `test` and
+//! `neighbors` datasets; requires the `hdf5` feature and libhdf5) and the
+//! texmex `.fvecs`/`.ivecs` files SIFT1M is originally distributed as.
+
+use anyhow::{bail, Context, Result};
+use std::path::{Path, PathBuf};
+use vectrust::{DistanceMetric, VectorOps};
+
+/// Well-known datasets from ann-benchmarks.com
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StandardDataset {
+    Sift1M,
+    Glove25,
+    Glove100,
+    DbpediaOpenAi1M,
+}
+
+impl StandardDataset {
+    pub const ALL: [StandardDataset; 4] = [
+        StandardDataset::Sift1M,
+        StandardDataset::Glove25,
+        StandardDataset::Glove100,
+        StandardDataset::DbpediaOpenAi1M,
+    ];
+
+    /// ann-benchmarks name, also the HDF5 file stem
+    pub fn name(self) -> &'static str {
+        match self {
+            StandardDataset::Sift1M => "sift-128-euclidean",
+            StandardDataset::Glove25 => "glove-25-angular",
+            StandardDataset::Glove100 => "glove-100-angular",
+            StandardDataset::DbpediaOpenAi1M => "dbpedia-openai-1000k-angular",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|d| d.name() == name)
+    }
+
+    pub fn url(self) -> String {
+        format!("http://ann-benchmarks.com/{}.hdf5", self.name())
+    }
+
+    /// Expected location of the downloaded file under `data_dir`
+    pub fn path(self, data_dir: &Path) -> PathBuf {
+        data_dir.join(format!("{}.hdf5", self.name()))
+    }
+}
+
+/// Base vectors, queries and the true nearest neighbours of each query
+pub struct Dataset {
+    pub name: String,
+    pub metric: DistanceMetric,
+    pub train: Vec<Vec<f32>>,
+    pub test: Vec<Vec<f32>>,
+    /// Indices into `train`, nearest first
+    pub neighbors: Vec<Vec<usize>>,
+}
+
+impl Dataset {
+    /// Load an ann-benchmarks HDF5 file.
+    ///
+    /// The metric comes from the file name suffix (`-angular` or
+    /// `-euclidean`), matching ann-benchmarks naming.
+    #[cfg(feature = "hdf5")]
+    pub fn load_hdf5(path: &Path) -> Result<Self> {
+        let file = hdf5::File::open(path).with_context(|| format!("opening {:?}", path))?;
+        let rows_f32 = |name: &str| -> Result<Vec<Vec<f32>>> {
+            let data = file.dataset(name)?.read_2d::<f32>()?;
+            Ok(data.outer_iter().map(|row| row.to_vec()).collect())
+        };
+        let train = rows_f32("train")?;
+        let test = rows_f32("test")?;
+        let neighbors = file
+            .dataset("neighbors")?
+            .read_2d::<i32>()?
+            .outer_iter()
+            .map(|row| row.iter().map(|&i| i as usize).collect())
+            .collect();
+
+        let name = path
+            .file_stem()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        Ok(Self {
+            metric: metric_from_name(&name)?,
+            name,
+            train,
+            test,
+            neighbors,
+        })
+    }
+
+    #[cfg(not(feature = "hdf5"))]
+    pub fn load_hdf5(path: &Path) -> Result<Self> {
+        bail!(
+            "Reading {:?} needs HDF5 support; rebuild with `--features hdf5`",
+            path
+        )
+    }
+
+    /// Load texmex files: `<prefix>_base.fvecs`, `<prefix>_query.fvecs`
+    /// and `<prefix>_groundtruth.ivecs` (e.g. `sift/sift_base.fvecs`)
+    pub fn load_texmex(dir: &Path, prefix: &str) -> Result<Self> {
+        let train = read_fvecs(&dir.join(format!("{}_base.fvecs", prefix)))?;
+        let test = read_fvecs(&dir.join(format!("{}_query.fvecs", prefix)))?;
+        let neighbors = read_ivecs(&dir.join(format!("{}_groundtruth.ivecs", prefix)))?;
+        if neighbors.len() != test.len() {
+            bail!(
+                "{} queries but {} ground-truth rows",
+                test.len(),
+                neighbors.len()
+            );
+        }
+
+        Ok(Self {
+            name: prefix.to_string(),
+            metric: DistanceMetric::Euclidean,
+            train,
+            test,
+            neighbors,
+        })
+    }
+
+    /// Load a standard dataset from `data_dir`, or explain how to fetch it
+    pub fn load_standard(dataset: StandardDataset, data_dir: &Path) -> Result<Self> {
+        let path = dataset.path(data_dir);
+        if !path.exists() {
+            bail!(
+                "{:?} not found; download it with:\n  curl -o {:?} {}",
+                path,
+                path,
+                dataset.url()
+            );
+        }
+        Self::load_hdf5(&path)
+    }
+
+    /// Keep at most `max_train` base vectors and `max_test` queries.
+    ///
+    /// Dropping base vectors invalidates the published ground truth, so it
+    /// is recomputed by exact scan over the kept vectors.
+    pub fn truncate(&mut self, max_train: Option<usize>, max_test: Option<usize>) {
+        if let Some(max_test) = max_test {
+            self.test.truncate(max_test);
+            self.neighbors.truncate(max_test);
+        }
+        let Some(max_train) = max_train else {
+            return;
+        };
+        if max_train >= self.train.len() {
+            return;
+        }
+
+        self.train.truncate(max_train);
+        let k = self.neighbors.first().map_or(100, Vec::len);
+        self.neighbors = self
+            .test
+            .iter()
+            .map(|query| exact_neighbors(&self.train, query, &self.metric, k))
+            .collect();
+    }
+
+    pub fn dimensions(&self) -> usize {
+        self.train.first().map_or(0, Vec::len)
+    }
+}
+
+/// Fraction of the true top `k` present in `found`
+pub fn recall_at_k(truth: &[usize], found: &[usize], k: usize) -> f64 {
+    let truth = &truth[..k.min(truth.len())];
+    if truth.is_empty() {
+        return 1.0;
+    }
+    let hits = found.iter().take(k).filter(|i| truth.contains(i)).count();
+    hits as f64 / truth.len() as f64
+}
+
+fn exact_neighbors(
+    train: &[Vec<f32>],
+    query: &[f32],
+    metric: &DistanceMetric,
+    k: usize,
+) -> Vec<usize> {
+    let mut scored: Vec<(f32, usize)> = train
+        .iter()
+        .enumerate()
+        .map(|(i, v)| (VectorOps::calculate_similarity(query, v, metric), i))
+        .collect();
+    scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+    scored.into_iter().take(k).map(|(_, i)| i).collect()
+}
+
+#[cfg_attr(not(feature = "hdf5"), allow(dead_code))]
+fn metric_from_name(name: &str) -> Result<DistanceMetric> {
+    if name.ends_with("-angular") {
+        Ok(DistanceMetric::Cosine)
+    } else if name.ends_with("-euclidean") {
+        Ok(DistanceMetric::Euclidean)
+    } else {
+        bail!(
+            "Can't tell the metric of {:?}; expected an -angular or -euclidean suffix",
+            name
+        )
+    }
+}
+
+/// Read texmex vectors: each row is a little-endian `i32` dimension followed by its values
+fn read_vecs<T>(path: &Path, decode: fn([u8; 4]) -> T) -> Result<Vec<Vec<T>>> {
+    let bytes = std::fs::read(path).with_context(|| format!("reading {:?}", path))?;
+    let mut rows = Vec::new();
+    let mut offset = 0;
+    while offset < bytes.len() {
+        let word = |at: usize| -> Result<[u8; 4]> {
+            bytes
+                .get(at..at + 4)
+                .and_then(|b| b.try_into().ok())
+                .with_context(|| format!("truncated record in {:?}", path))
+        };
+        let dim = i32::from_le_bytes(word(offset)?) as usize;
+        offset += 4;
+        let row = (0..dim)
+            .map(|i| word(offset + i * 4).map(decode))
+            .collect::<Result<Vec<T>>>()?;
+        offset += dim * 4;
+        rows.push(row);
+    }
+    Ok(rows)
+}
+
+pub fn read_fvecs(path: &Path) -> Result<Vec<Vec<f32>>> {
+    read_vecs(path, f32::from_le_bytes)
+}
+
+pub fn read_ivecs(path: &Path) -> Result<Vec<Vec<usize>>> {
+    read_vecs(path, |b| i32::from_le_bytes(b) as usize)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_vecs(path: &Path, rows: &[Vec<[u8; 4]>]) {
+        let mut bytes = Vec::new();
+        for row in rows {
+            bytes.extend((row.len() as i32).to_le_bytes());
+            for value in row {
+                bytes.extend(value);
+            }
+        }
+        std::fs::write(path, bytes).unwrap();
+    }
+
+    #[test]
+    fn test_texmex_roundtrip_and_truncate() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let f = |v: f32| v.to_le_bytes();
+        let i = |v: i32| v.to_le_bytes();
+        write_vecs(
+            &dir.path().join("toy_base.fvecs"),
+            &[
+                vec![f(0.0), f(0.0)],
+                vec![f(1.0), f(0.0)],
+                vec![f(5.0), f(5.0)],
+            ],
+        );
+        write_vecs(&dir.path().join("toy_query.fvecs"), &[vec![f(4.0), f(4.0)]]);
+        write_vecs(
+            &dir.path().join("toy_groundtruth.ivecs"),
+            &[vec![i(2), i(1), i(0)]],
+        );
+
+        let mut dataset = Dataset::load_texmex(dir.path(), "toy").unwrap();
+        assert_eq!(dataset.dimensions(), 2);
+        assert_eq!(dataset.neighbors, vec![vec![2, 1, 0]]);
+
+        // Dropping the true nearest neighbour recomputes the ground truth
+        dataset.truncate(Some(2), None);
+        assert_eq!(dataset.neighbors, vec![vec![1, 0]]);
+        assert_eq!(recall_at_k(&dataset.neighbors[0], &[1, 0], 1), 1.0);
+        assert_eq!(recall_at_k(&dataset.neighbors[0], &[0, 1], 1), 0.0);
+    }
+
+    #[test]
+    fn test_standard_names() {
+        assert_eq!(
+            StandardDataset::from_name("glove-100-angular"),
+            Some(StandardDataset::Glove100)
+        );
+        assert!(matches!(
+            metric_from_name("sift-128-euclidean").unwrap(),
+            DistanceMetric::Euclidean
+        ));
+        assert!(metric_from_name("mystery").is_err());
+    }
+}
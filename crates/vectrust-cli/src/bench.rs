@@ -0,0 +1,335 @@
+// Copyright 2024-2026 Andrey Vasilevsky <anvanster@gmail.com>
+// SPDX-License-Identifier: Apache-2.0
+
+// Benchmark matrix across storage backends and index types
+
+use anyhow::Result;
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::Path;
+use std::time::{Duration, Instant};
+use vectrust_core::*;
+use vectrust_index::HnswIndex;
+use vectrust_storage::LegacyStorage;
+#[cfg(feature = "rocksdb")]
+use vectrust_storage::OptimizedStorage;
+#[cfg(feature = "redb")]
+use vectrust_storage::RedbStorage;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BenchBackend {
+    /// JSON file format
+    Legacy,
+    /// RocksDB metadata with memory-mapped vectors
+    Optimized,
+    /// Pure-Rust redb backend
+    Redb,
+}
+
+impl BenchBackend {
+    fn open(self, dir: &Path) -> Result<Box<dyn StorageBackend>> {
+        Ok(match self {
+            BenchBackend::Legacy => Box::new(LegacyStorage::new(dir, "index.json")?),
+            #[cfg(feature = "rocksdb")]
+            BenchBackend::Optimized => Box::new(OptimizedStorage::new(dir)?),
+            #[cfg(feature = "redb")]
+            BenchBackend::Redb => Box::new(RedbStorage::new(dir)?),
+            #[allow(unreachable_patterns)]
+            other => anyhow::bail!("{:?} backend is not compiled into this build", other),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BenchIndex {
+    /// Exact brute-force scan through the storage backend
+    Flat,
+    /// HNSW graph built from the stored vectors
+    Hnsw,
+    /// Scalar-quantized vectors
+    Quantized,
+}
+
+/// Workload shared by every cell of the matrix
+#[derive(Debug, Clone)]
+pub struct BenchConfig {
+    pub items: usize,
+    pub dimensions: usize,
+    pub queries: usize,
+    pub top_k: usize,
+    pub seed: u64,
+}
+
+/// Timings and recall for one backend/index combination
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BenchResult {
+    pub backend: BenchBackend,
+    pub index: BenchIndex,
+    pub items: usize,
+    pub insert_ms: f64,
+    /// Time to load vectors and build the index; zero for flat scans
+    pub build_ms: f64,
+    pub query_p50_ms: f64,
+    pub query_p95_ms: f64,
+    /// Fraction of the exact top-k found
+    pub recall: f64,
+}
+
+/// Run every backend/index combination under `root`, one subdirectory per backend
+pub async fn run_matrix(
+    root: &Path,
+    config: &BenchConfig,
+    backends: &[BenchBackend],
+    indexes: &[BenchIndex],
+) -> Result<Vec<BenchResult>> {
+    let mut rng = XorShift(config.seed.max(1));
+    let items: Vec<VectorItem> = (0..config.items)
+        .map(|i| VectorItem {
+            id: uuid::Uuid::new_v4(),
+            vector: rng.vector(config.dimensions),
+            metadata: serde_json::json!({ "n": i }),
+            ..Default::default()
+        })
+        .collect();
+    let queries: Vec<Vec<f32>> = (0..config.queries)
+        .map(|_| rng.vector(config.dimensions))
+        .collect();
+    let truth: Vec<HashSet<uuid::Uuid>> = queries
+        .iter()
+        .map(|q| exact_top_k(&items, q, config.top_k))
+        .collect();
+
+    let mut results = Vec::new();
+    for &backend in backends {
+        let dir = root.join(format!("{:?}", backend).to_lowercase());
+        if dir.exists() {
+            std::fs::remove_dir_all(&dir)?;
+        }
+        std::fs::create_dir_all(&dir)?;
+
+        let mut storage = backend.open(&dir)?;
+        storage.create_index(&CreateIndexConfig::default()).await?;
+        let start = Instant::now();
+        storage.insert_items(&items).await?;
+        storage.commit_transaction().await?;
+        let insert_ms = millis(start.elapsed());
+
+        for &index in indexes {
+            let (build_ms, timings, found) = match index {
+                BenchIndex::Flat => {
+                    let mut timings = Vec::with_capacity(queries.len());
+                    let mut found = Vec::with_capacity(queries.len());
+                    for vector in &queries {
+                        let query = Query {
+                            vector: Some(vector.clone()),
+                            top_k: config.top_k,
+                            ..Default::default()
+                        };
+                        let start = Instant::now();
+                        let hits = storage.query_items(&query).await?;
+                        timings.push(start.elapsed());
+                        found.push(hits.into_iter().map(|r| r.item.id).collect());
+                    }
+                    (0.0, timings, found)
+                }
+                BenchIndex::Hnsw => {
+                    let start = Instant::now();
+                    let mut hnsw = HnswIndex::new(HnswConfig {
+                        max_elements: config.items.max(1),
+                        random_seed: Some(config.seed),
+                        ..Default::default()
+                    })?;
+                    for item in storage.list_items(None).await? {
+                        hnsw.insert(item.id, &item.vector)?;
+                    }
+                    let build_ms = millis(start.elapsed());
+
+                    let mut timings = Vec::with_capacity(queries.len());
+                    let mut found = Vec::with_capacity(queries.len());
+                    for vector in &queries {
+                        let start = Instant::now();
+                        let hits = hnsw.search(vector, config.top_k)?;
+                        timings.push(start.elapsed());
+                        found.push(hits.into_iter().map(|(id, _)| id).collect());
+                    }
+                    (build_ms, timings, found)
+                }
+                BenchIndex::Quantized => {
+                    // QuantizedIndex doesn't quantize yet; there is nothing to measure
+                    eprintln!("Skipping quantized index on {:?}: not implemented", backend);
+                    continue;
+                }
+            };
+
+            results.push(BenchResult {
+                backend,
+                index,
+                items: config.items,
+                insert_ms,
+                build_ms,
+                query_p50_ms: percentile(&timings, 0.50),
+                query_p95_ms: percentile(&timings, 0.95),
+                recall: recall(&truth, &found),
+            });
+        }
+
+        drop(storage);
+        std::fs::remove_dir_all(&dir)?;
+    }
+    Ok(results)
+}
+
+/// Print results as an aligned comparison table
+pub fn print_table(results: &[BenchResult]) {
+    println!(
+        "{:<10} {:<10} {:>12} {:>10} {:>10} {:>10} {:>8}",
+        "backend", "index", "insert ms", "build ms", "p50 ms", "p95 ms", "recall"
+    );
+    for r in results {
+        println!(
+            "{:<10} {:<10} {:>12.1} {:>10.1} {:>10.3} {:>10.3} {:>8.3}",
+            format!("{:?}", r.backend).to_lowercase(),
+            format!("{:?}", r.index).to_lowercase(),
+            r.insert_ms,
+            r.build_ms,
+            r.query_p50_ms,
+            r.query_p95_ms,
+            r.recall
+        );
+    }
+}
+
+/// Describe each result that is worse than its baseline by more than `max_regression`.
+///
+/// Timings may grow by the given fraction (0.2 = 20%) and recall may drop
+/// by at most 0.01. Combinations missing from the baseline are not checked.
+pub fn regressions(
+    results: &[BenchResult],
+    baseline: &[BenchResult],
+    max_regression: f64,
+) -> Vec<String> {
+    let mut failures = Vec::new();
+    for r in results {
+        let Some(base) = baseline
+            .iter()
+            .find(|b| b.backend == r.backend && b.index == r.index)
+        else {
+            continue;
+        };
+        let label = format!("{:?}/{:?}", r.backend, r.index).to_lowercase();
+        let limit = 1.0 + max_regression;
+
+        for (metric, value, base_value) in [
+            ("insert ms", r.insert_ms, base.insert_ms),
+            ("build ms", r.build_ms, base.build_ms),
+            ("p95 ms", r.query_p95_ms, base.query_p95_ms),
+        ] {
+            if base_value > 0.0 && value > base_value * limit {
+                failures.push(format!(
+                    "{label}: {metric} {value:.3} exceeds baseline {base_value:.3}"
+                ));
+            }
+        }
+        if r.recall < base.recall - 0.01 {
+            failures.push(format!(
+                "{label}: recall {:.3} below baseline {:.3}",
+                r.recall, base.recall
+            ));
+        }
+    }
+    failures
+}
+
+fn exact_top_k(items: &[VectorItem], query: &[f32], k: usize) -> HashSet<uuid::Uuid> {
+    let mut scored: Vec<(f32, uuid::Uuid)> = items
+        .iter()
+        .map(|item| (VectorOps::cosine_similarity(query, &item.vector), item.id))
+        .collect();
+    scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+    scored.into_iter().take(k).map(|(_, id)| id).collect()
+}
+
+fn recall(truth: &[HashSet<uuid::Uuid>], found: &[Vec<uuid::Uuid>]) -> f64 {
+    let expected: usize = truth.iter().map(HashSet::len).sum();
+    if expected == 0 {
+        return 1.0;
+    }
+    let hits: usize = truth
+        .iter()
+        .zip(found)
+        .map(|(t, f)| f.iter().filter(|id| t.contains(id)).count())
+        .sum();
+    hits as f64 / expected as f64
+}
+
+pub(crate) fn percentile(timings: &[Duration], p: f64) -> f64 {
+    if timings.is_empty() {
+        return 0.0;
+    }
+    let mut sorted = timings.to_vec();
+    sorted.sort();
+    let rank = ((sorted.len() - 1) as f64 * p).round() as usize;
+    millis(sorted[rank])
+}
+
+pub(crate) fn millis(d: Duration) -> f64 {
+    d.as_secs_f64() * 1000.0
+}
+
+/// Small deterministic generator so runs compare like for like
+pub(crate) struct XorShift(pub(crate) u64);
+
+impl XorShift {
+    pub(crate) fn next_f32(&mut self) -> f32 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 >> 40) as f32 / (1u64 << 24) as f32 * 2.0 - 1.0
+    }
+
+    pub(crate) fn vector(&mut self, dimensions: usize) -> Vec<f32> {
+        (0..dimensions).map(|_| self.next_f32()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_matrix_and_regressions() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let config = BenchConfig {
+            items: 60,
+            dimensions: 8,
+            queries: 5,
+            top_k: 5,
+            seed: 7,
+        };
+        let results = run_matrix(
+            dir.path(),
+            &config,
+            &[BenchBackend::Legacy],
+            &[BenchIndex::Flat, BenchIndex::Hnsw, BenchIndex::Quantized],
+        )
+        .await
+        .unwrap();
+
+        // Quantized is skipped until it's implemented
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].index, BenchIndex::Flat);
+        assert_eq!(results[0].recall, 1.0);
+        assert!(regressions(&results, &results, 0.2).is_empty());
+
+        let mut faster = results.clone();
+        faster[0].query_p95_ms = results[0].query_p95_ms / 10.0;
+        faster[0].recall = 1.0;
+        faster[1].recall = results[1].recall + 0.5;
+        let failures = regressions(&results, &faster, 0.2);
+        assert!(failures.iter().any(|f| f.contains("legacy/hnsw: recall")));
+    }
+}
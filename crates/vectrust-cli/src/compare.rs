@@ -0,0 +1,222 @@
+// Copyright 2024-2026 Andrey Vasilevsky <anvanster@gmail.com>
+// SPDX-License-Identifier: Apache-2.0
+
+// A/B comparison of runtime configurations: the same queries against the
+// same index under a baseline and a candidate config, reporting how far
+// the candidate's results drift and what it does to latency
+
+use crate::bench::percentile;
+use crate::loadtest::WorkloadQuery;
+use anyhow::Result;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::time::{Duration, Instant};
+use vectrust::{LocalIndex, QueryResult, RuntimeConfig};
+
+/// Latency of one configuration over the query set
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SideReport {
+    pub errors: usize,
+    pub mean_ms: f64,
+    pub p50_ms: f64,
+    pub p99_ms: f64,
+}
+
+/// How the candidate's results and latency differ from the baseline's
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CompareReport {
+    /// Queries both configurations answered
+    pub queries: usize,
+    /// Share of the baseline's results the candidate also returned,
+    /// averaged over queries; its recall when the baseline is exact
+    pub mean_overlap: f64,
+    pub min_overlap: f64,
+    /// Queries whose results differ at all
+    pub changed_queries: usize,
+    /// Candidate minus baseline score at the same rank, averaged over ranks
+    pub mean_score_delta: f64,
+    pub max_abs_score_delta: f64,
+    pub baseline: SideReport,
+    pub candidate: SideReport,
+}
+
+/// Run `workload` on `index` under each config in turn, leaving the index
+/// on its original config. Each side runs all of its queries together, so
+/// a config whose HNSW settings differ builds its graph once; the first
+/// query of each side warms up and isn't timed.
+pub async fn run(
+    index: &LocalIndex,
+    workload: &[WorkloadQuery],
+    baseline: RuntimeConfig,
+    candidate: RuntimeConfig,
+) -> Result<CompareReport> {
+    if workload.is_empty() {
+        anyhow::bail!("workload has no queries");
+    }
+    let original = index.runtime_config();
+    let baseline = run_side(index, workload, baseline).await;
+    let candidate = run_side(index, workload, candidate).await;
+    index.set_runtime_config(original).await?;
+    let ((baseline_results, baseline_times), (candidate_results, candidate_times)) =
+        (baseline?, candidate?);
+
+    let mut overlaps = Vec::new();
+    let mut deltas = Vec::new();
+    let mut changed = 0;
+    for (a, b) in baseline_results.iter().zip(&candidate_results) {
+        let (Some(a), Some(b)) = (a, b) else {
+            continue;
+        };
+        let ids: HashSet<_> = b.iter().map(|r| r.item.id).collect();
+        let shared = a.iter().filter(|r| ids.contains(&r.item.id)).count();
+        overlaps.push(if a.is_empty() {
+            1.0
+        } else {
+            shared as f64 / a.len() as f64
+        });
+        let same = a.len() == b.len() && a.iter().zip(b).all(|(x, y)| x.item.id == y.item.id);
+        if !same {
+            changed += 1;
+        }
+        deltas.extend(a.iter().zip(b).map(|(x, y)| (y.score - x.score) as f64));
+    }
+
+    let mean = |values: &[f64]| {
+        if values.is_empty() {
+            0.0
+        } else {
+            values.iter().sum::<f64>() / values.len() as f64
+        }
+    };
+    Ok(CompareReport {
+        queries: overlaps.len(),
+        mean_overlap: mean(&overlaps),
+        min_overlap: overlaps.iter().copied().fold(1.0, f64::min),
+        changed_queries: changed,
+        mean_score_delta: mean(&deltas),
+        max_abs_score_delta: deltas.iter().fold(0.0, |max, d| d.abs().max(max)),
+        baseline: side_report(&baseline_results, &baseline_times),
+        candidate: side_report(&candidate_results, &candidate_times),
+    })
+}
+
+type SideRun = (Vec<Option<Vec<QueryResult>>>, Vec<Duration>);
+
+/// Results of every query under `config`, `None` where it failed, and
+/// the latency of each
+async fn run_side(
+    index: &LocalIndex,
+    workload: &[WorkloadQuery],
+    config: RuntimeConfig,
+) -> Result<SideRun> {
+    index.set_runtime_config(config).await?;
+    let warmup = &workload[0];
+    let _ = index
+        .query_items(warmup.vector.clone(), warmup.top_k, warmup.filter.clone())
+        .await;
+
+    let mut results = Vec::with_capacity(workload.len());
+    let mut times = Vec::with_capacity(workload.len());
+    for query in workload {
+        let started = Instant::now();
+        let outcome = index
+            .query_items(query.vector.clone(), query.top_k, query.filter.clone())
+            .await;
+        times.push(started.elapsed());
+        results.push(outcome.ok());
+    }
+    Ok((results, times))
+}
+
+fn side_report(results: &[Option<Vec<QueryResult>>], times: &[Duration]) -> SideReport {
+    SideReport {
+        errors: results.iter().filter(|r| r.is_none()).count(),
+        mean_ms: times.iter().sum::<Duration>().as_secs_f64() * 1000.0 / times.len().max(1) as f64,
+        p50_ms: percentile(times, 0.50),
+        p99_ms: percentile(times, 0.99),
+    }
+}
+
+/// Print a report as aligned lines
+pub fn print_report(report: &CompareReport) {
+    println!(
+        "{} queries, {} with different results",
+        report.queries, report.changed_queries
+    );
+    println!(
+        "overlap     mean {:.4}  min {:.4}",
+        report.mean_overlap, report.min_overlap
+    );
+    println!(
+        "score delta mean {:+.5}  max |{:.5}|",
+        report.mean_score_delta, report.max_abs_score_delta
+    );
+    for (name, side) in [
+        ("baseline", &report.baseline),
+        ("candidate", &report.candidate),
+    ] {
+        println!(
+            "{:<9}   mean {:>8.3}  p50 {:>8.3}  p99 {:>8.3} ms  errors {}",
+            name, side.mean_ms, side.p50_ms, side.p99_ms, side.errors
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_compare_configs() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let index = LocalIndex::new(dir.path(), None).unwrap();
+        index.create_index(None).await.unwrap();
+        let items = (0..50)
+            .map(|i| vectrust::VectorItem {
+                vector: vec![i as f32, 1.0, 0.5],
+                ..Default::default()
+            })
+            .collect();
+        index.insert_items(items).await.unwrap();
+        let workload = crate::loadtest::synthesize_workload(&index, 10, 0.05, 5, 7)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|query| WorkloadQuery {
+                top_k: None,
+                ..query
+            })
+            .collect::<Vec<_>>();
+
+        let same = run(
+            &index,
+            &workload,
+            RuntimeConfig::default(),
+            RuntimeConfig::default(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(same.queries, 10);
+        assert_eq!(same.mean_overlap, 1.0);
+        assert_eq!(same.changed_queries, 0);
+        assert_eq!(same.max_abs_score_delta, 0.0);
+
+        // Fewer results per query: only the first two of five are shared
+        let baseline = RuntimeConfig {
+            default_top_k: 5,
+            ..Default::default()
+        };
+        let candidate = RuntimeConfig {
+            default_top_k: 2,
+            ..Default::default()
+        };
+        let fewer = run(&index, &workload, baseline, candidate).await.unwrap();
+        assert!((fewer.mean_overlap - 0.4).abs() < 1e-9);
+        assert_eq!(fewer.changed_queries, 10);
+        assert_eq!(fewer.max_abs_score_delta, 0.0);
+        // The index is back on its own config
+        assert_eq!(index.runtime_config().default_top_k, 10);
+    }
+}
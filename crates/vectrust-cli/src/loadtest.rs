@@ -0,0 +1,276 @@
+// Copyright 2024-2026 Andrey Vasilevsky <anvanster@gmail.com>
+// SPDX-License-Identifier: Apache-2.0
+
+// Query load generator for sizing: replays or synthesizes queries against
+// a live index at a target rate and concurrency
+
+use crate::bench::{millis, percentile, XorShift};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::io::BufRead;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use vectrust::LocalIndex;
+
+/// Stored vectors sampled when synthesizing queries
+pub const SYNTHETIC_SAMPLES: usize = 1000;
+
+/// One query of a workload file line, e.g.
+/// `{"vector": [0.1, 0.2], "topK": 10, "filter": {"tenant": "acme"}}`
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkloadQuery {
+    pub vector: Vec<f32>,
+    #[serde(default)]
+    pub top_k: Option<u32>,
+    #[serde(default)]
+    pub filter: Option<serde_json::Value>,
+}
+
+/// How hard to drive the index
+#[derive(Debug, Clone)]
+pub struct LoadtestConfig {
+    /// Queries per second to issue; as fast as possible when `None`
+    pub qps: Option<f64>,
+    pub concurrency: usize,
+    pub duration: Duration,
+}
+
+/// Latency and error figures of a run
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LoadtestReport {
+    pub queries: usize,
+    pub errors: usize,
+    /// First error seen, if any
+    pub first_error: Option<String>,
+    pub elapsed_secs: f64,
+    pub achieved_qps: f64,
+    pub p50_ms: f64,
+    pub p90_ms: f64,
+    pub p99_ms: f64,
+    pub max_ms: f64,
+    /// Time the run's queries spent waiting for locks, by lock and operation
+    pub lock_waits: vectrust::LockWaitStats,
+}
+
+/// Read a JSONL workload, skipping blank lines
+pub fn read_workload(path: &Path) -> Result<Vec<WorkloadQuery>> {
+    let file = std::io::BufReader::new(std::fs::File::open(path)?);
+    let mut queries = Vec::new();
+    for (n, line) in file.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let query = serde_json::from_str(&line)
+            .map_err(|e| anyhow::anyhow!("{:?} line {}: {}", path, n + 1, e))?;
+        queries.push(query);
+    }
+    Ok(queries)
+}
+
+/// Queries made from up to `samples` stored vectors, each component moved
+/// by up to `noise` so they don't hit their source item exactly
+pub async fn synthesize_workload(
+    index: &LocalIndex,
+    samples: usize,
+    noise: f32,
+    top_k: u32,
+    seed: u64,
+) -> Result<Vec<WorkloadQuery>> {
+    let options = vectrust::ListOptions {
+        limit: Some(samples),
+        offset: None,
+        filter: None,
+    };
+    let mut rng = XorShift(seed.max(1));
+    Ok(index
+        .list_items(Some(options))
+        .await?
+        .into_iter()
+        .map(|item| WorkloadQuery {
+            vector: item
+                .vector
+                .iter()
+                .map(|x| x + rng.next_f32() * noise)
+                .collect(),
+            top_k: Some(top_k),
+            filter: None,
+        })
+        .collect())
+}
+
+/// Issue `workload` round robin from `config.concurrency` tasks until
+/// `config.duration` has passed.
+///
+/// With a target rate, query `i` is due at `i / qps` seconds and its
+/// latency is measured from then rather than from when a task got to it,
+/// so an index that falls behind shows up as latency instead of as a
+/// quietly lower rate.
+pub async fn run(
+    index: Arc<LocalIndex>,
+    workload: Arc<Vec<WorkloadQuery>>,
+    config: &LoadtestConfig,
+) -> Result<LoadtestReport> {
+    if workload.is_empty() {
+        anyhow::bail!("workload has no queries");
+    }
+    index.reset_lock_waits().await;
+    let start = tokio::time::Instant::now();
+    let end = start + config.duration;
+    let next = Arc::new(AtomicUsize::new(0));
+
+    let mut tasks = Vec::new();
+    for _ in 0..config.concurrency.max(1) {
+        let (index, workload, next) = (index.clone(), workload.clone(), next.clone());
+        let qps = config.qps;
+        tasks.push(tokio::spawn(async move {
+            let mut latencies = Vec::new();
+            let mut errors = Vec::new();
+            loop {
+                let i = next.fetch_add(1, Ordering::Relaxed);
+                let due = match qps {
+                    Some(qps) => start + Duration::from_secs_f64(i as f64 / qps),
+                    None => tokio::time::Instant::now(),
+                };
+                if due >= end {
+                    break;
+                }
+                tokio::time::sleep_until(due).await;
+
+                let query = &workload[i % workload.len()];
+                let outcome = index
+                    .query_items(query.vector.clone(), query.top_k, query.filter.clone())
+                    .await;
+                latencies.push(due.elapsed());
+                if let Err(e) = outcome {
+                    errors.push(e.to_string());
+                }
+            }
+            (latencies, errors)
+        }));
+    }
+
+    let mut latencies: Vec<Duration> = Vec::new();
+    let mut errors: Vec<String> = Vec::new();
+    for task in tasks {
+        let (task_latencies, task_errors) = task.await?;
+        latencies.extend(task_latencies);
+        errors.extend(task_errors);
+    }
+    let elapsed = start.elapsed().as_secs_f64();
+
+    Ok(LoadtestReport {
+        queries: latencies.len(),
+        errors: errors.len(),
+        first_error: errors.into_iter().next(),
+        elapsed_secs: elapsed,
+        achieved_qps: latencies.len() as f64 / elapsed.max(f64::EPSILON),
+        p50_ms: percentile(&latencies, 0.50),
+        p90_ms: percentile(&latencies, 0.90),
+        p99_ms: percentile(&latencies, 0.99),
+        max_ms: latencies.iter().max().copied().map_or(0.0, millis),
+        lock_waits: index.lock_waits().await,
+    })
+}
+
+/// Print a report as aligned lines
+pub fn print_report(report: &LoadtestReport) {
+    println!(
+        "{} queries in {:.1}s ({:.1} qps), {} error(s)",
+        report.queries, report.elapsed_secs, report.achieved_qps, report.errors
+    );
+    println!(
+        "latency ms  p50 {:>8.2}  p90 {:>8.2}  p99 {:>8.2}  max {:>8.2}",
+        report.p50_ms, report.p90_ms, report.p99_ms, report.max_ms
+    );
+    if let Some(error) = &report.first_error {
+        println!("first error: {}", error);
+    }
+    // Uncontended locks cost next to nothing, so only waits are listed
+    let mut contended = false;
+    for (lock, operations) in &report.lock_waits {
+        for (operation, wait) in operations.iter().filter(|(_, w)| w.contended > 0) {
+            contended = true;
+            println!(
+                "lock {}/{}: {} of {} acquisitions waited, {:.2} ms total, {:.2} ms max",
+                lock,
+                operation,
+                wait.contended,
+                wait.acquisitions,
+                wait.wait_micros as f64 / 1000.0,
+                wait.max_wait_micros as f64 / 1000.0
+            );
+        }
+    }
+    if !contended {
+        println!("no lock waits");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_loadtest_paced_run() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let index = LocalIndex::new(dir.path(), None).unwrap();
+        index.create_index(None).await.unwrap();
+        let items = (0..20)
+            .map(|i| vectrust::VectorItem {
+                vector: vec![i as f32, 1.0, 0.5],
+                ..Default::default()
+            })
+            .collect();
+        index.insert_items(items).await.unwrap();
+
+        let mut workload = synthesize_workload(&index, 5, 0.05, 3, 7).await.unwrap();
+        assert_eq!(workload.len(), 5);
+        workload.push(WorkloadQuery {
+            vector: vec![f32::NAN, 1.0, 0.5],
+            top_k: None,
+            filter: None,
+        });
+
+        let config = LoadtestConfig {
+            qps: Some(200.0),
+            concurrency: 2,
+            duration: Duration::from_millis(200),
+        };
+        let report = run(Arc::new(index), Arc::new(workload), &config)
+            .await
+            .unwrap();
+        // Queries are scheduled every 5ms up to the end of the run
+        assert_eq!(report.queries, 40);
+        // Every sixth query is invalid
+        assert_eq!(report.errors, 6);
+        assert!(report.first_error.is_some());
+        assert!(report.p50_ms <= report.p99_ms && report.p99_ms <= report.max_ms);
+        // Workload sampling before the run isn't counted
+        let storage = &report.lock_waits["storage"];
+        assert!(storage["query"].acquisitions > 0);
+        assert!(!storage.contains_key("list"));
+    }
+
+    #[test]
+    fn test_read_workload() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("workload.jsonl");
+        std::fs::write(
+            &path,
+            "{\"vector\": [0.1, 0.2], \"topK\": 5, \"filter\": {\"tenant\": \"acme\"}}\n\n{\"vector\": [1, 0]}\n",
+        )
+        .unwrap();
+        let workload = read_workload(&path).unwrap();
+        assert_eq!(workload.len(), 2);
+        assert_eq!(workload[0].top_k, Some(5));
+        assert_eq!(workload[1].vector, vec![1.0, 0.0]);
+
+        std::fs::write(&path, "{\"topK\": 5}\n").unwrap();
+        assert!(read_workload(&path).is_err());
+    }
+}
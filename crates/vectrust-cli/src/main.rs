@@ -5,6 +5,14 @@ use anyhow::Result;
 use clap::Parser;
 use std::path::PathBuf;
 
+mod bench;
+mod compare;
+mod loadtest;
+mod profile;
+mod soak;
+
+use bench::{BenchBackend, BenchConfig, BenchIndex};
+
 #[derive(Parser)]
 #[command(name = "vectrust")]
 #[command(about = "Vectrust graph+vector database CLI")]
@@ -12,6 +20,11 @@ use std::path::PathBuf;
 struct Cli {
     #[command(subcommand)]
     command: Commands,
+
+    /// Profile the command's CPU time into this file: a flamegraph for
+    /// .svg, otherwise a pprof profile (needs the `profile` feature)
+    #[arg(long, global = true)]
+    profile: Option<PathBuf>,
 }
 
 #[derive(Parser)]
@@ -24,23 +37,177 @@ enum Commands {
         #[arg(short, long, default_value = "v2")]
         format: String,
 
+        /// Rewrite metadata records as `json` or `cbor`
+        #[arg(long, value_parser = ["json", "cbor"])]
+        metadata_encoding: Option<String>,
+
         #[arg(long)]
         dry_run: bool,
     },
 
-    /// Verify index integrity
+    /// Verify index integrity and report garbage such as orphaned metadata files
     Verify {
         #[arg(short, long)]
         path: PathBuf,
+
+        /// Drop damaged items and remove the garbage found
+        #[arg(long)]
+        repair: bool,
+    },
+
+    /// Report items whose vectors differ in length from most of the index,
+    /// optionally re-embedding or removing them
+    FixDimensions {
+        #[arg(short, long)]
+        path: PathBuf,
+
+        /// Delete the mismatched items
+        #[arg(long, conflicts_with = "embed_command")]
+        remove: bool,
+
+        /// Shell command that re-embeds items. It reads one JSON item per
+        /// line on stdin and writes one JSON array of floats per line.
+        #[arg(long)]
+        embed_command: Option<String>,
     },
 
-    /// Benchmark performance
+    /// Benchmark storage backends and index types on the same workload
     Bench {
+        /// Scratch directory for the benchmark indexes
         #[arg(short, long)]
         path: PathBuf,
 
         #[arg(long, default_value = "1000")]
         items: usize,
+
+        #[arg(long, default_value = "128")]
+        dimensions: usize,
+
+        #[arg(long, default_value = "100")]
+        queries: usize,
+
+        #[arg(short = 'k', long, default_value = "10")]
+        top_k: usize,
+
+        #[arg(long, value_enum, value_delimiter = ',', default_values = ["legacy", "optimized"])]
+        backends: Vec<BenchBackend>,
+
+        #[arg(long, value_enum, value_delimiter = ',', default_values = ["flat", "hnsw"])]
+        indexes: Vec<BenchIndex>,
+
+        /// Fail if results regress against this saved baseline
+        #[arg(long)]
+        baseline: Option<PathBuf>,
+
+        /// Write results as a baseline for later runs
+        #[arg(long)]
+        save_baseline: Option<PathBuf>,
+
+        /// Allowed slowdown against the baseline (0.2 = 20%)
+        #[arg(long, default_value = "0.2")]
+        max_regression: f64,
+    },
+
+    /// Replay or synthesize queries against an index at a target rate and
+    /// concurrency, reporting latency percentiles and errors
+    Loadtest {
+        #[arg(short, long)]
+        path: PathBuf,
+
+        /// JSONL workload, one {"vector": [...], "topK": 10, "filter": {...}}
+        /// per line. Without it, queries are stored vectors with noise added.
+        #[arg(long)]
+        workload: Option<PathBuf>,
+
+        /// Target queries per second; as fast as possible when omitted
+        #[arg(long)]
+        qps: Option<f64>,
+
+        /// Queries in flight at once
+        #[arg(short, long, default_value = "4")]
+        concurrency: usize,
+
+        /// Run length in seconds
+        #[arg(short, long, default_value = "10")]
+        duration: f64,
+
+        /// Results per synthesized query
+        #[arg(short = 'k', long, default_value = "10")]
+        top_k: u32,
+
+        /// Most each component of a synthesized query moves from its stored vector
+        #[arg(long, default_value = "0.05")]
+        noise: f32,
+
+        /// Write the report as JSON
+        #[arg(long)]
+        output: Option<PathBuf>,
+    },
+
+    /// Run mixed read/write load for a long time, sampling latency and
+    /// memory into a time series to catch gradual degradation
+    Soak {
+        /// Index to load; created when missing
+        #[arg(short, long)]
+        path: PathBuf,
+
+        /// Run length in seconds
+        #[arg(short, long, default_value = "3600")]
+        duration: f64,
+
+        /// Seconds between samples
+        #[arg(short, long, default_value = "60")]
+        interval: f64,
+
+        /// Concurrent workers
+        #[arg(short, long, default_value = "4")]
+        concurrency: usize,
+
+        /// Fraction of operations that insert or delete rather than query
+        #[arg(long, default_value = "0.5")]
+        write_ratio: f32,
+
+        /// Vector length for new items when the index is empty
+        #[arg(long, default_value = "128")]
+        dimensions: usize,
+
+        /// Append each sample to this file as a JSON line
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+
+        /// Fail if latency or memory at the end of the run exceeds the
+        /// start by more than this factor (2.0 = twice as slow)
+        #[arg(long)]
+        max_degradation: Option<f64>,
+    },
+
+    /// Run the same queries under two runtime configurations and compare
+    /// result overlap, score deltas and latency
+    Compare {
+        #[arg(short, long)]
+        path: PathBuf,
+
+        /// Runtime config JSON for the baseline; defaults when omitted
+        #[arg(long)]
+        baseline: Option<PathBuf>,
+
+        /// Runtime config JSON to compare against the baseline, e.g.
+        /// {"ann": {"hnsw": {"ef_search": 64}}}
+        #[arg(long)]
+        candidate: PathBuf,
+
+        /// JSONL workload as for loadtest. Without it, queries are stored
+        /// vectors with noise added.
+        #[arg(long)]
+        workload: Option<PathBuf>,
+
+        /// Results per synthesized query
+        #[arg(short = 'k', long, default_value = "10")]
+        top_k: u32,
+
+        /// Write the report as JSON
+        #[arg(long)]
+        output: Option<PathBuf>,
     },
 
     /// Show index statistics (vector storage)
@@ -49,6 +216,95 @@ enum Commands {
         path: PathBuf,
     },
 
+    /// Build a composite index over several metadata fields on an existing
+    /// index, filing the items already stored under it
+    CompositeIndex {
+        #[arg(short, long)]
+        path: PathBuf,
+
+        /// Fields in key order, comma-separated (e.g., 'tenant,category')
+        #[arg(short, long, value_delimiter = ',', required = true)]
+        fields: Vec<String>,
+    },
+
+    /// Copy or move items matching a metadata filter into a new index
+    Split {
+        #[arg(short, long)]
+        path: PathBuf,
+
+        /// Directory for the new index
+        #[arg(short, long)]
+        target: PathBuf,
+
+        /// Metadata filter as JSON (e.g., '{"tenant": "acme"}')
+        #[arg(short, long)]
+        filter: String,
+
+        /// Remove matching items from the source index
+        #[arg(long = "move")]
+        move_items: bool,
+    },
+
+    /// Import embeddings from a .npy or .safetensors matrix with one JSONL
+    /// record ({"id": ..., "metadata": ...} or an id string) per row
+    Import {
+        #[arg(short, long)]
+        path: PathBuf,
+
+        /// Matrix file with one embedding per row
+        #[arg(short, long)]
+        matrix: PathBuf,
+
+        /// JSONL file with the id and metadata for each row
+        #[arg(short, long)]
+        records: PathBuf,
+
+        /// Tensor to read from a safetensors file holding several
+        #[arg(long)]
+        tensor: Option<String>,
+
+        /// Bulk-load a new index instead of inserting into an existing one
+        #[arg(long)]
+        bulk: bool,
+    },
+
+    /// Search items with a query string (e.g., 'tenant:acme AND year>=2024 "vector db"')
+    Query {
+        #[arg(short, long)]
+        path: PathBuf,
+
+        /// Field clauses become a metadata filter, other words a keyword query
+        #[arg(short, long, required_unless_present = "saved")]
+        query: Option<String>,
+
+        /// Run a query saved in the index instead of --query
+        #[arg(long, conflicts_with = "query")]
+        saved: Option<String>,
+
+        /// Also save --query in the index under this name
+        #[arg(long, requires = "query")]
+        save_as: Option<String>,
+
+        /// Query vector as JSON (e.g., '[0.1, 0.2, 0.3]')
+        #[arg(long)]
+        vector: Option<String>,
+
+        /// Number of results (defaults to the saved query's, or 10)
+        #[arg(short = 'k', long)]
+        top_k: Option<u32>,
+
+        /// Print how the query was planned and executed, as JSON, with its
+        /// results; needs --vector
+        #[arg(long, requires = "vector")]
+        explain: bool,
+    },
+
+    /// Manage alias names for index directories
+    Alias {
+        #[command(subcommand)]
+        command: AliasCommands,
+    },
+
     /// Graph database commands
     Graph {
         #[command(subcommand)]
@@ -56,6 +312,35 @@ enum Commands {
     },
 }
 
+#[derive(Parser)]
+enum AliasCommands {
+    /// Point an alias at an index directory, replacing any previous target
+    Set {
+        /// Directory holding the aliases and, usually, the indexes
+        #[arg(short, long)]
+        root: PathBuf,
+
+        alias: String,
+
+        /// Index directory, relative to --root unless absolute
+        target: PathBuf,
+    },
+
+    /// Remove an alias
+    Remove {
+        #[arg(short, long)]
+        root: PathBuf,
+
+        alias: String,
+    },
+
+    /// List aliases and their targets
+    List {
+        #[arg(short, long)]
+        root: PathBuf,
+    },
+}
+
 #[derive(Parser)]
 enum GraphCommands {
     /// Show graph statistics (node/edge/label counts)
@@ -123,29 +408,178 @@ async fn main() -> Result<()> {
     tracing_subscriber::fmt::init();
 
     let cli = Cli::parse();
+    let profiler = cli.profile.map(profile::Profiler::start).transpose()?;
 
     match cli.command {
         Commands::Migrate {
             path,
             format,
+            metadata_encoding,
             dry_run,
         } => {
-            migrate_index(path, format, dry_run).await?;
+            migrate_index(path, format, metadata_encoding, dry_run).await?;
         }
-        Commands::Verify { path } => {
-            verify_index(path).await?;
+        Commands::Verify { path, repair } => {
+            verify_index(path, repair).await?;
         }
-        Commands::Bench { path, items } => {
-            benchmark_index(path, items).await?;
+        Commands::FixDimensions {
+            path,
+            remove,
+            embed_command,
+        } => {
+            fix_dimensions(path, remove, embed_command).await?;
+        }
+        Commands::Bench {
+            path,
+            items,
+            dimensions,
+            queries,
+            top_k,
+            backends,
+            indexes,
+            baseline,
+            save_baseline,
+            max_regression,
+        } => {
+            let config = BenchConfig {
+                items,
+                dimensions,
+                queries,
+                top_k,
+                seed: 42,
+            };
+            benchmark_index(
+                path,
+                config,
+                backends,
+                indexes,
+                baseline,
+                save_baseline,
+                max_regression,
+            )
+            .await?;
+        }
+        Commands::Loadtest {
+            path,
+            workload,
+            qps,
+            concurrency,
+            duration,
+            top_k,
+            noise,
+            output,
+        } => {
+            let config = loadtest::LoadtestConfig {
+                qps,
+                concurrency,
+                duration: std::time::Duration::from_secs_f64(duration),
+            };
+            loadtest_index(path, workload, config, top_k, noise, output).await?;
+        }
+        Commands::Soak {
+            path,
+            duration,
+            interval,
+            concurrency,
+            write_ratio,
+            dimensions,
+            output,
+            max_degradation,
+        } => {
+            let config = soak::SoakConfig {
+                duration: std::time::Duration::from_secs_f64(duration),
+                interval: std::time::Duration::from_secs_f64(interval),
+                concurrency,
+                write_ratio,
+                dimensions,
+                top_k: 10,
+                seed: 42,
+            };
+            soak_index(path, config, output, max_degradation).await?;
+        }
+        Commands::Compare {
+            path,
+            baseline,
+            candidate,
+            workload,
+            top_k,
+            output,
+        } => {
+            compare_configs(path, baseline, candidate, workload, top_k, output).await?;
         }
         Commands::Stats { path } => {
             show_vector_stats(path).await?;
         }
+        Commands::CompositeIndex { path, fields } => {
+            build_composite_index(path, fields).await?;
+        }
+        Commands::Split {
+            path,
+            target,
+            filter,
+            move_items,
+        } => {
+            split_index(path, target, filter, move_items).await?;
+        }
+        Commands::Import {
+            path,
+            matrix,
+            records,
+            tensor,
+            bulk,
+        } => {
+            import_embeddings(path, matrix, records, tensor, bulk).await?;
+        }
+        Commands::Query {
+            path,
+            query,
+            saved,
+            save_as,
+            vector,
+            top_k,
+            explain,
+        } => {
+            query_index(path, query, saved, save_as, vector, top_k, explain).await?;
+        }
+        Commands::Alias { command } => {
+            handle_alias_command(command)?;
+        }
         Commands::Graph { command } => {
             handle_graph_command(command)?;
         }
     }
 
+    if let Some(profiler) = profiler {
+        profiler.finish()?;
+    }
+    Ok(())
+}
+
+fn handle_alias_command(command: AliasCommands) -> Result<()> {
+    match command {
+        AliasCommands::Set {
+            root,
+            alias,
+            target,
+        } => {
+            let aliases = vectrust::IndexAliases::new(&root);
+            match aliases.set(&alias, &target)? {
+                Some(previous) => println!("{} -> {:?} (was {:?})", alias, target, previous),
+                None => println!("{} -> {:?}", alias, target),
+            }
+        }
+        AliasCommands::Remove { root, alias } => {
+            match vectrust::IndexAliases::new(&root).remove(&alias)? {
+                Some(previous) => println!("Removed {} (was {:?})", alias, previous),
+                None => println!("No alias named {}", alias),
+            }
+        }
+        AliasCommands::List { root } => {
+            for (alias, target) in vectrust::IndexAliases::new(&root).list()? {
+                println!("{} -> {:?}", alias, target);
+            }
+        }
+    }
     Ok(())
 }
 
@@ -344,27 +778,526 @@ async fn show_vector_stats(path: PathBuf) -> Result<()> {
     println!("  Items: {}", stats.items);
     println!("  Dimensions: {:?}", stats.dimensions);
     println!("  Distance metric: {:?}", stats.distance_metric);
+    if let Some(model) = index.embedding_model().await? {
+        println!("  Embedding model: {}", model.name);
+    }
+    if let Some(limits) = index.limits().await? {
+        println!("  Limits: {}", serde_json::to_string(&limits)?);
+    }
+    if !stats.namespaces.is_empty() {
+        println!("  Namespaces:");
+        for (namespace, usage) in &stats.namespaces {
+            println!(
+                "    {}: {} items, ~{} bytes, {} queries",
+                namespace, usage.items, usage.bytes, usage.queries
+            );
+        }
+    }
+    let properties = index.get_index_metadata();
+    if !properties.is_empty() {
+        println!("  Properties:");
+        for (key, value) in properties {
+            println!("    {}: {}", key, value);
+        }
+    }
+    Ok(())
+}
+
+async fn build_composite_index(path: PathBuf, fields: Vec<String>) -> Result<()> {
+    let index = vectrust::LocalIndex::new(&path, None)?;
+    if !index.is_index_created().await {
+        anyhow::bail!("No vector index found at {:?}", path);
+    }
+    let started = std::time::Instant::now();
+    index
+        .add_composite_index(vectrust::CompositeIndex::new(fields.clone()))
+        .await?;
+    println!(
+        "Built composite index ({}) on {:?} in {:.2?}",
+        fields.join(", "),
+        path,
+        started.elapsed()
+    );
+    Ok(())
+}
+
+async fn split_index(
+    path: PathBuf,
+    target: PathBuf,
+    filter: String,
+    move_items: bool,
+) -> Result<()> {
+    let filter: serde_json::Value = serde_json::from_str(&filter)?;
+    let index = vectrust::LocalIndex::new(&path, None)?;
+    if !index.is_index_created().await {
+        anyhow::bail!("No vector index found at {:?}", path);
+    }
+
+    let mode = if move_items {
+        vectrust::SplitMode::Move
+    } else {
+        vectrust::SplitMode::Copy
+    };
+    let count = index.split_by_filter(&filter, &target, mode).await?;
+
+    println!(
+        "{} {} item(s) from {:?} to {:?}",
+        if move_items { "Moved" } else { "Copied" },
+        count,
+        path,
+        target
+    );
+    Ok(())
+}
+
+async fn import_embeddings(
+    path: PathBuf,
+    matrix: PathBuf,
+    records: PathBuf,
+    tensor: Option<String>,
+    bulk: bool,
+) -> Result<()> {
+    let import = vectrust::EmbeddingImport::open(&matrix, &records, tensor.as_deref())?;
+    println!(
+        "Importing {} embedding(s) of {} dimension(s) from {:?}",
+        import.rows(),
+        import.dimensions(),
+        matrix
+    );
+
+    let count = if bulk {
+        let mut loader = vectrust::BulkLoader::new(&path, None).await?;
+        let count = loader.import_embeddings(import).await?;
+        loader.finish().await?;
+        count
+    } else {
+        let index = vectrust::LocalIndex::new(&path, None)?;
+        if !index.is_index_created().await {
+            index.create_index(None).await?;
+        }
+        index.import_embeddings(import).await?
+    };
+
+    println!("Imported {} item(s) into {:?}", count, path);
+    Ok(())
+}
+
+async fn query_index(
+    path: PathBuf,
+    query: Option<String>,
+    saved: Option<String>,
+    save_as: Option<String>,
+    vector: Option<String>,
+    top_k: Option<u32>,
+    explain: bool,
+) -> Result<()> {
+    let vector: Option<Vec<f32>> = vector.map(|v| serde_json::from_str(&v)).transpose()?;
+    let index = vectrust::LocalIndex::new(&path, None)?;
+    if !index.is_index_created().await {
+        anyhow::bail!("No vector index found at {:?}", path);
+    }
+
+    let named = match (query, saved) {
+        (_, Some(name)) => index
+            .named_query(&name)
+            .ok_or_else(|| anyhow::anyhow!("No saved query named '{}'", name))?,
+        (Some(query), None) => {
+            let parsed = vectrust::QueryDsl::parse(&query)?;
+            vectrust::NamedQuery {
+                filter: parsed.filter,
+                text: parsed.text,
+                top_k: top_k.map(|k| k as usize),
+                ..Default::default()
+            }
+        }
+        (None, None) => anyhow::bail!("Either --query or --saved is required"),
+    };
+    if let Some(name) = save_as {
+        index.save_named_query(&name, named.clone())?;
+        println!("Saved query '{}'", name);
+    }
+
+    let query = named.to_query(vector, top_k.map(|k| k as usize));
+    if explain {
+        let explanation = index.explain_query(&query).await?;
+        println!("{}", serde_json::to_string_pretty(&explanation)?);
+        return Ok(());
+    }
+    let results = match (&query.vector, &query.text) {
+        (Some(_), _) => index.execute_query(&query, None).await?,
+        (None, Some(text)) => {
+            index
+                .text_search(text, Some(query.top_k as u32), query.filter)
+                .await?
+        }
+        (None, None) => {
+            // Filter only: list matches in storage order
+            index
+                .list_items(None)
+                .await?
+                .into_iter()
+                .filter(|item| {
+                    query
+                        .filter
+                        .as_ref()
+                        .is_none_or(|f| vectrust::MetadataFilter::matches(item, f))
+                })
+                .take(query.top_k)
+                .map(|item| vectrust::QueryResult {
+                    item,
+                    score: 1.0,
+                    highlights: Vec::new(),
+                    payload: None,
+                })
+                .collect()
+        }
+    };
+
+    for result in &results {
+        println!(
+            "{}  {:.4}  {}",
+            result.item.id, result.score, result.item.metadata
+        );
+    }
+    println!("\n{} result(s)", results.len());
     Ok(())
 }
 
-async fn migrate_index(path: PathBuf, format: String, dry_run: bool) -> Result<()> {
+async fn migrate_index(
+    path: PathBuf,
+    format: String,
+    metadata_encoding: Option<String>,
+    dry_run: bool,
+) -> Result<()> {
     println!("Migrating index at {:?} to format {}", path, format);
     if dry_run {
         println!("DRY RUN - no changes will be made");
     }
     // TODO: Implement migration logic
+    if let Some(encoding) = metadata_encoding {
+        println!("Rewriting metadata as {}", encoding);
+        if !dry_run {
+            let encoding = match encoding.as_str() {
+                "cbor" => vectrust::MetadataEncoding::Cbor,
+                _ => vectrust::MetadataEncoding::Json,
+            };
+            let index = vectrust::LocalIndex::new(&path, None)?;
+            let rewritten = index.set_metadata_encoding(encoding).await?;
+            println!("  Rewrote {} metadata records", rewritten);
+        }
+    }
     Ok(())
 }
 
-async fn verify_index(path: PathBuf) -> Result<()> {
+async fn verify_index(path: PathBuf, repair: bool) -> Result<()> {
     println!("Verifying index at {:?}", path);
-    // TODO: Implement verification logic
+    let index = vectrust::LocalIndex::open_degraded(&path, None).await?;
+    if !index.is_index_created().await {
+        println!("No vector index found at {:?}", path);
+        return Ok(());
+    }
+
+    let health = index.health().await?;
+    if health.is_degraded() {
+        println!("  Damaged: {} items", health.quarantined.len());
+        for damaged in &health.quarantined {
+            println!("    {}: {}", damaged.id, damaged.reason);
+        }
+        if !repair {
+            println!("  Run with --repair to drop them and remove garbage");
+            return Ok(());
+        }
+        let report = index.repair_quarantined(None).await?;
+        println!("  Dropped {} damaged items", report.dropped.len());
+    }
+
+    let divergence = index.verify_secondary_indexes().await?;
+    if !divergence.is_consistent() {
+        println!(
+            "  Secondary indexes: {} missing entries, {} stale",
+            divergence.missing, divergence.stale
+        );
+        if repair {
+            index.rebuild_secondary_indexes().await?;
+            println!("  Rebuilt secondary indexes");
+        } else {
+            println!("  Run with --repair to rebuild them");
+        }
+    }
+
+    let stats = index.compaction_stats().await?;
+    println!("  Live items: {}", stats.live_items);
+    if stats.deleted_items == 0 {
+        println!("  No garbage found");
+    } else {
+        println!(
+            "  Garbage: {} records, {} bytes reclaimable",
+            stats.deleted_items, stats.reclaimable_bytes
+        );
+        // Empty unless the index uses the legacy JSON format
+        let legacy = vectrust_storage::LegacyStorage::new(&path, "index.json")?;
+        for file in legacy.orphaned_metadata_files().await? {
+            println!("    orphaned metadata file {:?}", file);
+        }
+        if repair {
+            index.compact(None).await?;
+            println!("  Removed garbage");
+        } else {
+            println!("  Run with --repair to remove it");
+        }
+    }
+    Ok(())
+}
+
+async fn fix_dimensions(path: PathBuf, remove: bool, embed_command: Option<String>) -> Result<()> {
+    let index = vectrust::LocalIndex::new(&path, None)?;
+    let report = index.dimension_report().await?;
+    println!("Vector lengths in {:?}:", path);
+    for (len, count) in &report.counts {
+        let marker = if Some(*len) == report.expected {
+            " (expected)"
+        } else {
+            ""
+        };
+        println!("  {:>6} dimensions: {} items{}", len, count, marker);
+    }
+    if report.mismatched.is_empty() {
+        println!("All items have the same length");
+        return Ok(());
+    }
+
+    let fixed = match embed_command {
+        Some(command) => {
+            let embedder = command_embedder(command);
+            index.fix_dimensions(Some(&embedder)).await?
+        }
+        None if remove => index.fix_dimensions(None).await?,
+        None => {
+            println!(
+                "{} mismatched items; run with --embed-command to re-embed them or --remove to delete them",
+                report.mismatched.len()
+            );
+            return Ok(());
+        }
+    };
+    index.end_update().await?;
+    println!(
+        "Re-embedded {} items, removed {}",
+        fixed.restored.len(),
+        fixed.dropped.len()
+    );
+    Ok(())
+}
+
+/// Embedding function backed by a shell command speaking JSON lines
+fn command_embedder(
+    command: String,
+) -> impl Fn(&[vectrust::VectorItem]) -> vectrust::Result<Vec<Vec<f32>>> {
+    move |items| {
+        use std::io::Write;
+        use std::process::{Command, Stdio};
+
+        let mut input = Vec::new();
+        for item in items {
+            serde_json::to_writer(&mut input, item)?;
+            input.push(b'\n');
+        }
+        let mut child = Command::new("sh")
+            .arg("-c")
+            .arg(&command)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()?;
+        // Write from another thread so a command that streams its output
+        // can't fill the pipe and stall both sides
+        let mut stdin = child.stdin.take().expect("stdin is piped");
+        let writer = std::thread::spawn(move || stdin.write_all(&input));
+        let output = child.wait_with_output()?;
+        writer.join().expect("stdin writer panicked")?;
+        if !output.status.success() {
+            return Err(anyhow::anyhow!("embed command exited with {}", output.status).into());
+        }
+        String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| Ok(serde_json::from_str(line)?))
+            .collect()
+    }
+}
+
+async fn benchmark_index(
+    path: PathBuf,
+    config: BenchConfig,
+    backends: Vec<BenchBackend>,
+    indexes: Vec<BenchIndex>,
+    baseline: Option<PathBuf>,
+    save_baseline: Option<PathBuf>,
+    max_regression: f64,
+) -> Result<()> {
+    println!(
+        "Benchmarking {} items x {} dimensions, {} queries (top {}) in {:?}",
+        config.items, config.dimensions, config.queries, config.top_k, path
+    );
+    let results = bench::run_matrix(&path, &config, &backends, &indexes).await?;
+    bench::print_table(&results);
+
+    if let Some(file) = save_baseline {
+        std::fs::write(&file, serde_json::to_string_pretty(&results)?)?;
+        println!("Saved baseline to {:?}", file);
+    }
+
+    if let Some(file) = baseline {
+        let baseline: Vec<bench::BenchResult> = serde_json::from_slice(&std::fs::read(&file)?)?;
+        let failures = bench::regressions(&results, &baseline, max_regression);
+        if !failures.is_empty() {
+            for failure in &failures {
+                eprintln!("REGRESSION {}", failure);
+            }
+            anyhow::bail!("{} metric(s) regressed against {:?}", failures.len(), file);
+        }
+        println!("No regressions against {:?}", file);
+    }
     Ok(())
 }
 
-async fn benchmark_index(path: PathBuf, items: usize) -> Result<()> {
-    println!("Benchmarking index at {:?} with {} items", path, items);
-    // TODO: Implement benchmark logic
+async fn loadtest_index(
+    path: PathBuf,
+    workload: Option<PathBuf>,
+    config: loadtest::LoadtestConfig,
+    top_k: u32,
+    noise: f32,
+    output: Option<PathBuf>,
+) -> Result<()> {
+    let index = vectrust::LocalIndex::new(&path, None)?;
+    if !index.is_index_created().await {
+        anyhow::bail!("No vector index found at {:?}", path);
+    }
+    let queries = match workload {
+        Some(file) => loadtest::read_workload(&file)?,
+        None => {
+            loadtest::synthesize_workload(&index, loadtest::SYNTHETIC_SAMPLES, noise, top_k, 42)
+                .await?
+        }
+    };
+
+    println!(
+        "Running {} distinct queries against {:?} at {} with {} concurrent for {:.1}s",
+        queries.len(),
+        path,
+        config
+            .qps
+            .map_or("full speed".to_string(), |qps| format!("{} qps", qps)),
+        config.concurrency,
+        config.duration.as_secs_f64()
+    );
+    let report = loadtest::run(
+        std::sync::Arc::new(index),
+        std::sync::Arc::new(queries),
+        &config,
+    )
+    .await?;
+    loadtest::print_report(&report);
+
+    if let Some(file) = output {
+        std::fs::write(&file, serde_json::to_string_pretty(&report)?)?;
+        println!("Saved report to {:?}", file);
+    }
+    Ok(())
+}
+
+async fn compare_configs(
+    path: PathBuf,
+    baseline: Option<PathBuf>,
+    candidate: PathBuf,
+    workload: Option<PathBuf>,
+    top_k: u32,
+    output: Option<PathBuf>,
+) -> Result<()> {
+    let read_config = |file: &PathBuf| -> Result<vectrust::RuntimeConfig> {
+        serde_json::from_slice(&std::fs::read(file)?)
+            .map_err(|e| anyhow::anyhow!("{:?}: {}", file, e))
+    };
+    let baseline = baseline
+        .as_ref()
+        .map(read_config)
+        .transpose()?
+        .unwrap_or_default();
+    let candidate = read_config(&candidate)?;
+
+    let index = vectrust::LocalIndex::new(&path, None)?;
+    if !index.is_index_created().await {
+        anyhow::bail!("No vector index found at {:?}", path);
+    }
+    let queries = match workload {
+        Some(file) => loadtest::read_workload(&file)?,
+        None => {
+            loadtest::synthesize_workload(&index, loadtest::SYNTHETIC_SAMPLES, 0.05, top_k, 42)
+                .await?
+        }
+    };
+
+    println!(
+        "Comparing {} queries against {:?} under both configs",
+        queries.len(),
+        path
+    );
+    let report = compare::run(&index, &queries, baseline, candidate).await?;
+    compare::print_report(&report);
+
+    if let Some(file) = output {
+        std::fs::write(&file, serde_json::to_string_pretty(&report)?)?;
+        println!("Saved report to {:?}", file);
+    }
+    Ok(())
+}
+
+async fn soak_index(
+    path: PathBuf,
+    config: soak::SoakConfig,
+    output: Option<PathBuf>,
+    max_degradation: Option<f64>,
+) -> Result<()> {
+    let index = vectrust::LocalIndex::new(&path, None)?;
+    if !index.is_index_created().await {
+        index.create_index(None).await?;
+    }
+    let mut output = output
+        .map(|file| {
+            std::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(file)
+        })
+        .transpose()?;
+
+    println!(
+        "Soaking {:?} for {:.0}s with {} workers ({:.0}% writes), sampling every {:.0}s",
+        path,
+        config.duration.as_secs_f64(),
+        config.concurrency,
+        config.write_ratio * 100.0,
+        config.interval.as_secs_f64()
+    );
+    let samples = soak::run(std::sync::Arc::new(index), &config, |sample| {
+        soak::print_sample(sample);
+        if let Some(file) = output.as_mut() {
+            use std::io::Write;
+            writeln!(file, "{}", serde_json::to_string(sample)?)?;
+        }
+        Ok(())
+    })
+    .await?;
+
+    if let Some(max_ratio) = max_degradation {
+        let failures = soak::degradation(&samples, max_ratio);
+        if !failures.is_empty() {
+            for failure in &failures {
+                eprintln!("DEGRADED {}", failure);
+            }
+            anyhow::bail!("{} metric(s) degraded over the run", failures.len());
+        }
+        println!("No metric degraded by more than {}x", max_ratio);
+    }
     Ok(())
 }
 
@@ -375,17 +1308,26 @@ mod tests {
     #[tokio::test]
     async fn test_migrate_function() {
         let path = PathBuf::from("/tmp/test");
-        let result = migrate_index(path, "v2".to_string(), true).await;
+        let result = migrate_index(path, "v2".to_string(), None, true).await;
         assert!(result.is_ok());
     }
 
     #[tokio::test]
     async fn test_verify_function() {
         let path = PathBuf::from("/tmp/test");
-        let result = verify_index(path).await;
+        let result = verify_index(path, false).await;
         assert!(result.is_ok());
     }
 
+    #[cfg(unix)]
+    #[test]
+    fn test_command_embedder() {
+        let items = vec![vectrust::VectorItem::default(); 3];
+        let embed = command_embedder("while read -r item; do echo '[0.5, 1.0]'; done".into());
+        assert_eq!(embed(&items).unwrap(), vec![vec![0.5, 1.0]; 3]);
+        assert!(command_embedder("exit 3".into())(&items).is_err());
+    }
+
     #[test]
     fn test_cli_parsing() {
         use clap::Parser;
@@ -395,6 +1337,282 @@ mod tests {
         assert!(cli.is_ok());
     }
 
+    #[test]
+    fn test_migrate_cli_parsing() {
+        use clap::Parser;
+
+        let args = vec![
+            "vectrust",
+            "migrate",
+            "--path",
+            "/tmp/test",
+            "--metadata-encoding",
+            "cbor",
+        ];
+        let cli = Cli::try_parse_from(args).unwrap();
+        assert!(matches!(
+            cli.command,
+            Commands::Migrate {
+                metadata_encoding: Some(ref encoding),
+                ..
+            } if encoding == "cbor"
+        ));
+        let args = vec![
+            "vectrust",
+            "migrate",
+            "-p",
+            "/tmp/t",
+            "--metadata-encoding",
+            "xml",
+        ];
+        assert!(Cli::try_parse_from(args).is_err());
+    }
+
+    #[test]
+    fn test_split_cli_parsing() {
+        use clap::Parser;
+
+        let args = vec![
+            "vectrust",
+            "split",
+            "--path",
+            "/tmp/test",
+            "--target",
+            "/tmp/acme",
+            "--filter",
+            r#"{"tenant": "acme"}"#,
+            "--move",
+        ];
+        let cli = Cli::try_parse_from(args).unwrap();
+        assert!(matches!(
+            cli.command,
+            Commands::Split {
+                move_items: true,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_composite_index_cli_parsing() {
+        use clap::Parser;
+
+        let args = vec![
+            "vectrust",
+            "composite-index",
+            "--path",
+            "/tmp/test",
+            "--fields",
+            "tenant,category",
+        ];
+        let cli = Cli::try_parse_from(args).unwrap();
+        assert!(matches!(
+            cli.command,
+            Commands::CompositeIndex { ref fields, .. } if fields == &["tenant", "category"]
+        ));
+        assert!(Cli::try_parse_from(["vectrust", "composite-index", "-p", "/tmp/test"]).is_err());
+    }
+
+    #[test]
+    fn test_import_cli_parsing() {
+        use clap::Parser;
+
+        let args = vec![
+            "vectrust",
+            "import",
+            "--path",
+            "/tmp/test",
+            "--matrix",
+            "/tmp/embeddings.safetensors",
+            "--records",
+            "/tmp/records.jsonl",
+            "--tensor",
+            "embeddings",
+            "--bulk",
+        ];
+        let cli = Cli::try_parse_from(args).unwrap();
+        assert!(matches!(
+            cli.command,
+            Commands::Import {
+                tensor: Some(_),
+                bulk: true,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_loadtest_cli_parsing() {
+        use clap::Parser;
+
+        let args = vec![
+            "vectrust",
+            "loadtest",
+            "--path",
+            "/tmp/test",
+            "--qps",
+            "500",
+            "--concurrency",
+            "16",
+            "--duration",
+            "30",
+        ];
+        let cli = Cli::try_parse_from(args.into_iter().chain(["--profile", "/tmp/q.svg"])).unwrap();
+        assert!(cli.profile.is_some());
+        assert!(matches!(
+            cli.command,
+            Commands::Loadtest {
+                qps: Some(_),
+                concurrency: 16,
+                workload: None,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_compare_cli_parsing() {
+        use clap::Parser;
+
+        let args = vec![
+            "vectrust",
+            "compare",
+            "--path",
+            "/tmp/test",
+            "--candidate",
+            "ann.json",
+            "-k",
+            "20",
+        ];
+        let cli = Cli::try_parse_from(args).unwrap();
+        assert!(matches!(
+            cli.command,
+            Commands::Compare {
+                baseline: None,
+                top_k: 20,
+                ..
+            }
+        ));
+
+        let args = vec!["vectrust", "compare", "--path", "/tmp/test"];
+        assert!(Cli::try_parse_from(args).is_err());
+    }
+
+    #[test]
+    fn test_soak_cli_parsing() {
+        use clap::Parser;
+
+        let args = vec![
+            "vectrust",
+            "soak",
+            "--path",
+            "/tmp/soak",
+            "--duration",
+            "7200",
+            "--write-ratio",
+            "0.8",
+            "--max-degradation",
+            "1.5",
+        ];
+        let cli = Cli::try_parse_from(args).unwrap();
+        assert!(matches!(
+            cli.command,
+            Commands::Soak {
+                max_degradation: Some(_),
+                concurrency: 4,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_bench_cli_parsing() {
+        use clap::Parser;
+
+        let args = vec![
+            "vectrust",
+            "bench",
+            "--path",
+            "/tmp/bench",
+            "--backends",
+            "legacy,optimized",
+            "--indexes",
+            "flat,hnsw,quantized",
+            "--baseline",
+            "baseline.json",
+        ];
+        let cli = Cli::try_parse_from(args).unwrap();
+        let Commands::Bench {
+            backends, indexes, ..
+        } = cli.command
+        else {
+            panic!("expected bench command");
+        };
+        assert_eq!(
+            backends,
+            vec![BenchBackend::Legacy, BenchBackend::Optimized]
+        );
+        assert_eq!(indexes.len(), 3);
+    }
+
+    #[test]
+    fn test_query_cli_parsing() {
+        use clap::Parser;
+
+        let args = vec![
+            "vectrust",
+            "query",
+            "--path",
+            "/tmp/test",
+            "-q",
+            r#"category:fruit AND score>[0.5] "exact phrase""#,
+            "-k",
+            "5",
+        ];
+        let cli = Cli::try_parse_from(args).unwrap();
+        assert!(matches!(
+            cli.command,
+            Commands::Query { top_k: Some(5), .. }
+        ));
+
+        let args = vec![
+            "vectrust",
+            "query",
+            "--path",
+            "/tmp/test",
+            "--saved",
+            "acme",
+        ];
+        assert!(Cli::try_parse_from(args).is_ok());
+
+        let args = vec!["vectrust", "query", "--path", "/tmp/test"];
+        assert!(Cli::try_parse_from(args).is_err());
+
+        let args = vec![
+            "vectrust",
+            "query",
+            "--path",
+            "/tmp/test",
+            "-q",
+            "tenant:acme",
+            "--vector",
+            "[0.1, 0.2]",
+            "--explain",
+        ];
+        let cli = Cli::try_parse_from(args).unwrap();
+        assert!(matches!(cli.command, Commands::Query { explain: true, .. }));
+        let args = vec![
+            "vectrust",
+            "query",
+            "--path",
+            "/tmp/test",
+            "-q",
+            "x",
+            "--explain",
+        ];
+        assert!(Cli::try_parse_from(args).is_err());
+    }
+
     #[test]
     fn test_graph_cli_parsing() {
         use clap::Parser;
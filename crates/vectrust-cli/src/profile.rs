@@ -0,0 +1,82 @@
+// Copyright 2024-2026 Andrey Vasilevsky <anvanster@gmail.com>
+// SPDX-License-Identifier: Apache-2.0
+
+// CPU profiling for --profile: samples the whole process with pprof while a
+// command runs and writes a flamegraph (.svg) or a pprof profile (any other
+// extension, readable by `go tool pprof` and most profiling UIs)
+
+use anyhow::Result;
+use std::path::PathBuf;
+
+/// Samples per second; a prime so sampling doesn't line up with periodic work
+#[cfg(all(unix, feature = "profile"))]
+const SAMPLE_FREQUENCY: i32 = 997;
+
+/// A running profile, written out by [`Profiler::finish`]
+pub struct Profiler {
+    #[cfg(all(unix, feature = "profile"))]
+    guard: pprof::ProfilerGuard<'static>,
+    output: PathBuf,
+}
+
+impl Profiler {
+    /// Start sampling, or fail when this build can't profile
+    pub fn start(output: PathBuf) -> Result<Self> {
+        #[cfg(all(unix, feature = "profile"))]
+        {
+            let guard = pprof::ProfilerGuardBuilder::default()
+                .frequency(SAMPLE_FREQUENCY)
+                // Unwinding inside these while a signal lands can deadlock
+                .blocklist(&["libc", "libgcc", "pthread", "vdso"])
+                .build()?;
+            Ok(Self { guard, output })
+        }
+        #[cfg(not(all(unix, feature = "profile")))]
+        {
+            anyhow::bail!(
+                "--profile {:?} needs a Unix build with the `profile` feature",
+                output
+            )
+        }
+    }
+
+    /// Stop sampling and write the profile
+    pub fn finish(self) -> Result<()> {
+        #[cfg(all(unix, feature = "profile"))]
+        {
+            let report = self.guard.report().build()?;
+            let file = std::fs::File::create(&self.output)?;
+            if self.output.extension().is_some_and(|ext| ext == "svg") {
+                report.flamegraph(file)?;
+            } else {
+                use pprof::protos::Message;
+                use std::io::Write;
+                let profile = report.pprof()?;
+                std::io::BufWriter::new(file).write_all(&profile.encode_to_vec())?;
+            }
+        }
+        println!("Wrote profile to {:?}", self.output);
+        Ok(())
+    }
+}
+
+#[cfg(all(test, unix, feature = "profile"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_profile_outputs() {
+        let dir = tempfile::TempDir::new().unwrap();
+        for name in ["flame.svg", "profile.pb"] {
+            let output = dir.path().join(name);
+            let profiler = Profiler::start(output.clone()).unwrap();
+            let mut x = 0u64;
+            for i in 0..20_000_000u64 {
+                x = x.wrapping_mul(31).wrapping_add(i);
+            }
+            std::hint::black_box(x);
+            profiler.finish().unwrap();
+            assert!(std::fs::metadata(&output).unwrap().len() > 0);
+        }
+    }
+}
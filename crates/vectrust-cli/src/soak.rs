@@ -0,0 +1,281 @@
+// Copyright 2024-2026 Andrey Vasilevsky <anvanster@gmail.com>
+// SPDX-License-Identifier: Apache-2.0
+
+// Soak test: mixed read/write load for hours, sampled into a time series
+// so slow degradation (lock contention, growing manifests, leaks) shows up
+
+use crate::bench::{percentile, XorShift};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use vectrust::{LocalIndex, VectorItem};
+
+/// Share of writes that delete an item the worker inserted earlier
+const DELETE_SHARE: f32 = 0.2;
+
+/// Reads one metric of a sample, `None` when the sample has no value for it
+type Metric = fn(&SoakSample) -> Option<f64>;
+
+/// Samples averaged at each end of the run when checking for degradation
+const DEGRADATION_WINDOW: usize = 3;
+
+#[derive(Debug, Clone)]
+pub struct SoakConfig {
+    pub duration: Duration,
+    /// Time between samples
+    pub interval: Duration,
+    pub concurrency: usize,
+    /// Fraction of operations that write (insert or delete)
+    pub write_ratio: f32,
+    /// Vector length for new items when the index is empty
+    pub dimensions: usize,
+    pub top_k: u32,
+    pub seed: u64,
+}
+
+/// Operations completed in one interval and their latencies
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SoakSample {
+    pub elapsed_secs: f64,
+    pub items: usize,
+    pub writes: usize,
+    pub queries: usize,
+    pub errors: usize,
+    pub write_p50_ms: f64,
+    pub write_p99_ms: f64,
+    pub query_p50_ms: f64,
+    pub query_p99_ms: f64,
+    /// Resident memory of this process; only known on Linux
+    pub rss_bytes: Option<u64>,
+}
+
+#[derive(Default)]
+struct Window {
+    writes: Vec<Duration>,
+    queries: Vec<Duration>,
+    errors: usize,
+}
+
+/// Run the load on `index` for `config.duration`, calling `on_sample`
+/// with each sample as it is taken, and return the series
+pub async fn run(
+    index: Arc<LocalIndex>,
+    config: &SoakConfig,
+    mut on_sample: impl FnMut(&SoakSample) -> Result<()>,
+) -> Result<Vec<SoakSample>> {
+    let dimensions = index
+        .get_stats()
+        .await?
+        .dimensions
+        .unwrap_or(config.dimensions);
+    let window = Arc::new(Mutex::new(Window::default()));
+    let stop = Arc::new(AtomicBool::new(false));
+
+    let mut workers = Vec::new();
+    for worker in 0..config.concurrency.max(1) {
+        let (index, window, stop) = (index.clone(), window.clone(), stop.clone());
+        let config = config.clone();
+        workers.push(tokio::spawn(async move {
+            let mut rng = XorShift(config.seed.max(1) + worker as u64);
+            let mut inserted: Vec<uuid::Uuid> = Vec::new();
+            while !stop.load(Ordering::Relaxed) {
+                // next_f32 is in [-1, 1)
+                let roll = (rng.next_f32() + 1.0) / 2.0;
+                let write = roll < config.write_ratio;
+                let started = Instant::now();
+                let outcome = if !write {
+                    index
+                        .query_items(rng.vector(dimensions), Some(config.top_k), None)
+                        .await
+                        .map(|_| ())
+                } else if roll < config.write_ratio * DELETE_SHARE && !inserted.is_empty() {
+                    let id = inserted.swap_remove(inserted.len() / 2);
+                    index.delete_item(&id).await
+                } else {
+                    let item = VectorItem {
+                        vector: rng.vector(dimensions),
+                        metadata: serde_json::json!({ "soak": worker }),
+                        ..Default::default()
+                    };
+                    index
+                        .insert_item(item)
+                        .await
+                        .map(|item| inserted.push(item.id))
+                };
+                let elapsed = started.elapsed();
+
+                let mut window = window.lock().unwrap();
+                if write {
+                    window.writes.push(elapsed);
+                } else {
+                    window.queries.push(elapsed);
+                }
+                if outcome.is_err() {
+                    window.errors += 1;
+                }
+            }
+        }));
+    }
+
+    let start = Instant::now();
+    let mut samples = Vec::new();
+    let mut next_sample = config.interval;
+    let result = async {
+        while next_sample <= config.duration {
+            tokio::time::sleep_until((start + next_sample).into()).await;
+            next_sample += config.interval;
+            let window = std::mem::take(&mut *window.lock().unwrap());
+            let sample = SoakSample {
+                elapsed_secs: start.elapsed().as_secs_f64(),
+                items: index.get_stats().await?.items,
+                writes: window.writes.len(),
+                queries: window.queries.len(),
+                errors: window.errors,
+                write_p50_ms: percentile(&window.writes, 0.50),
+                write_p99_ms: percentile(&window.writes, 0.99),
+                query_p50_ms: percentile(&window.queries, 0.50),
+                query_p99_ms: percentile(&window.queries, 0.99),
+                rss_bytes: rss_bytes(),
+            };
+            on_sample(&sample)?;
+            samples.push(sample);
+        }
+        Ok::<_, anyhow::Error>(())
+    }
+    .await;
+
+    stop.store(true, Ordering::Relaxed);
+    for worker in workers {
+        worker.await?;
+    }
+    result.map(|_| samples)
+}
+
+/// Resident set size from /proc, where available
+fn rss_bytes() -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|line| line.starts_with("VmRSS:"))?;
+    let kb: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+    Some(kb * 1024)
+}
+
+/// Metrics whose average over the last samples is more than `max_ratio`
+/// times their average over the first, as one message each. Latencies
+/// and memory are compared; runs too short to have separate first and
+/// last windows report nothing.
+pub fn degradation(samples: &[SoakSample], max_ratio: f64) -> Vec<String> {
+    let window = DEGRADATION_WINDOW.min(samples.len() / 2);
+    if window == 0 {
+        return Vec::new();
+    }
+    let (first, last) = (&samples[..window], &samples[samples.len() - window..]);
+    let average = |samples: &[SoakSample], metric: Metric| {
+        let values: Vec<f64> = samples.iter().filter_map(metric).collect();
+        (!values.is_empty()).then(|| values.iter().sum::<f64>() / values.len() as f64)
+    };
+
+    let metrics: [(&str, Metric); 5] = [
+        ("write p50", |s| (s.writes > 0).then_some(s.write_p50_ms)),
+        ("write p99", |s| (s.writes > 0).then_some(s.write_p99_ms)),
+        ("query p50", |s| (s.queries > 0).then_some(s.query_p50_ms)),
+        ("query p99", |s| (s.queries > 0).then_some(s.query_p99_ms)),
+        ("rss", |s| s.rss_bytes.map(|b| b as f64)),
+    ];
+    let mut failures = Vec::new();
+    for (name, metric) in metrics {
+        let (Some(before), Some(after)) = (average(first, metric), average(last, metric)) else {
+            continue;
+        };
+        if before > 0.0 && after > before * max_ratio {
+            failures.push(format!(
+                "{}: {:.3} -> {:.3} ({:.1}x)",
+                name,
+                before,
+                after,
+                after / before
+            ));
+        }
+    }
+    failures
+}
+
+/// Print a sample as one aligned line
+pub fn print_sample(sample: &SoakSample) {
+    println!(
+        "{:>8.1}s  items {:>9}  writes {:>7} (p50 {:>7.2} p99 {:>7.2} ms)  queries {:>7} (p50 {:>7.2} p99 {:>7.2} ms)  errors {:>4}  rss {}",
+        sample.elapsed_secs,
+        sample.items,
+        sample.writes,
+        sample.write_p50_ms,
+        sample.write_p99_ms,
+        sample.queries,
+        sample.query_p50_ms,
+        sample.query_p99_ms,
+        sample.errors,
+        sample
+            .rss_bytes
+            .map_or("-".to_string(), |b| format!("{} MiB", b / (1024 * 1024)))
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_soak_samples() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let index = LocalIndex::new(dir.path(), None).unwrap();
+        index.create_index(None).await.unwrap();
+        let config = SoakConfig {
+            duration: Duration::from_millis(300),
+            interval: Duration::from_millis(100),
+            concurrency: 2,
+            write_ratio: 0.5,
+            dimensions: 8,
+            top_k: 5,
+            seed: 3,
+        };
+
+        let mut seen = 0;
+        let samples = run(Arc::new(index), &config, |_| {
+            seen += 1;
+            Ok(())
+        })
+        .await
+        .unwrap();
+        assert_eq!(samples.len(), 3);
+        assert_eq!(seen, 3);
+        assert!(samples.iter().all(|s| s.errors == 0));
+        assert!(samples.iter().map(|s| s.writes).sum::<usize>() > 0);
+        assert!(samples[2].items > 0);
+    }
+
+    #[test]
+    fn test_degradation() {
+        let sample = |write_p99_ms: f64| SoakSample {
+            elapsed_secs: 0.0,
+            items: 0,
+            writes: 10,
+            queries: 0,
+            errors: 0,
+            write_p50_ms: 1.0,
+            write_p99_ms,
+            query_p50_ms: 0.0,
+            query_p99_ms: 0.0,
+            rss_bytes: None,
+        };
+        let samples: Vec<SoakSample> = [1.0, 1.2, 0.8, 1.0, 3.0, 3.5, 2.5]
+            .into_iter()
+            .map(sample)
+            .collect();
+        let failures = degradation(&samples, 2.0);
+        assert_eq!(failures.len(), 1);
+        assert!(failures[0].starts_with("write p99"), "{:?}", failures);
+        assert!(degradation(&samples, 4.0).is_empty());
+        assert!(degradation(&samples[..1], 2.0).is_empty());
+    }
+}
@@ -10,12 +10,18 @@ pub enum VectraError {
     #[error("Item not found")]
     ItemNotFound,
 
+    #[error("Item already exists: {id}")]
+    ItemAlreadyExists { id: uuid::Uuid },
+
     #[error("Index not found at path: {path}")]
     IndexNotFound { path: String },
 
     #[error("Index already exists at path: {path}")]
     IndexAlreadyExists { path: String },
 
+    #[error("Index at {path} is damaged: {message}")]
+    IndexDamaged { path: String, message: String },
+
     #[error("Invalid vector dimensions: expected {expected}, got {actual}")]
     InvalidDimensions { expected: usize, actual: usize },
 
@@ -25,12 +31,25 @@ pub enum VectraError {
     #[error("Metadata validation failed: {message}")]
     MetadataValidation { message: String },
 
+    #[error("Quota exceeded: {message}")]
+    QuotaExceeded { message: String },
+
+    #[error("Not enough disk space at {path}: need about {needed} bytes, {available} available")]
+    DiskSpace {
+        path: String,
+        needed: u64,
+        available: u64,
+    },
+
     #[error("Storage error: {message}")]
     Storage { message: String },
 
     #[error("Storage error: {message}")]
     StorageError { message: String },
 
+    #[error("Query error: {message}")]
+    Query { message: String },
+
     #[error("Lock error: {message}")]
     Lock { message: String },
 
@@ -59,6 +78,7 @@ pub enum VectraError {
     Other(#[from] anyhow::Error),
 }
 
+#[cfg(feature = "rocksdb")]
 impl From<rocksdb::Error> for VectraError {
     fn from(err: rocksdb::Error) -> Self {
         VectraError::StorageError {
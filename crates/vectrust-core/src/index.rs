@@ -12,11 +12,24 @@ use uuid::Uuid;
 // We'll define a trait here to avoid cyclic dependencies
 use async_trait::async_trait;
 
+/// Test of an item's metadata, such as a compiled query filter, that
+/// backends can apply while scanning
+pub type MetadataPredicate = dyn Fn(&serde_json::Value) -> bool + Send + Sync;
+
 #[async_trait]
 pub trait StorageBackend: Send + Sync {
     async fn exists(&self) -> bool;
     async fn create_index(&mut self, config: &CreateIndexConfig) -> Result<()>;
     async fn get_item(&self, id: &uuid::Uuid) -> Result<Option<VectorItem>>;
+    /// Items with the given ids in the same order, `None` where an id is
+    /// missing; backends override this to fetch them in batches
+    async fn get_items(&self, ids: &[uuid::Uuid]) -> Result<Vec<Option<VectorItem>>> {
+        let mut items = Vec::with_capacity(ids.len());
+        for id in ids {
+            items.push(self.get_item(id).await?);
+        }
+        Ok(items)
+    }
     async fn insert_item(&mut self, item: &VectorItem) -> Result<()>;
     async fn insert_items(&mut self, items: &[VectorItem]) -> Result<()> {
         // Default implementation - can be overridden for better performance
@@ -26,14 +39,224 @@ pub trait StorageBackend: Send + Sync {
         Ok(())
     }
     async fn update_item(&mut self, item: &VectorItem) -> Result<()>;
+    /// Replace several existing items; backends override this to write
+    /// them in one batch
+    async fn update_items(&mut self, items: &[VectorItem]) -> Result<()> {
+        for item in items {
+            self.update_item(item).await?;
+        }
+        Ok(())
+    }
+    /// Replace the metadata of existing items whose vectors are unchanged;
+    /// backends override this to leave the stored vectors untouched
+    async fn update_metadata(&mut self, items: &[VectorItem]) -> Result<()> {
+        self.update_items(items).await
+    }
     async fn delete_item(&mut self, id: &uuid::Uuid) -> Result<()>;
+    /// Live items in ascending id order, so repeated listings agree and
+    /// `offset`/`limit` pages don't overlap
     async fn list_items(&self, options: Option<ListOptions>) -> Result<Vec<VectorItem>>;
+    /// Live items whose metadata `matches`, in ascending id order, with
+    /// `offset`/`limit` counting matching items only. `options.filter` is
+    /// left to `matches`. Backends storing metadata apart from vectors
+    /// override this to skip reading vectors of items that don't match.
+    async fn list_matching(
+        &self,
+        options: Option<ListOptions>,
+        matches: &MetadataPredicate,
+    ) -> Result<Vec<VectorItem>> {
+        let (offset, limit) = options.map_or((0, None), |o| (o.offset.unwrap_or(0), o.limit));
+        Ok(self
+            .list_items(None)
+            .await?
+            .into_iter()
+            .filter(|item| matches(&item.metadata))
+            .skip(offset)
+            .take(limit.unwrap_or(usize::MAX))
+            .collect())
+    }
+    /// Results ordered by [`QueryResult::rank_cmp`]
     async fn query_items(&self, query: &Query) -> Result<Vec<QueryResult>>;
     async fn begin_transaction(&mut self) -> Result<()>;
     async fn commit_transaction(&mut self) -> Result<()>;
     async fn rollback_transaction(&mut self) -> Result<()>;
     async fn delete_index(&mut self) -> Result<()>;
     async fn get_stats(&self) -> Result<IndexStats>;
+
+    /// Space that compaction could reclaim; backends that never fragment report nothing
+    async fn compaction_stats(&self) -> Result<CompactionStats> {
+        Ok(CompactionStats::default())
+    }
+
+    /// Rewrite storage without deleted records, returning the stats from before compaction
+    async fn compact(&mut self, _max_bytes_per_sec: Option<u64>) -> Result<CompactionStats> {
+        self.compaction_stats().await
+    }
+
+    /// Copy up to `max_bytes` of live data ahead of [`StorageBackend::compact`],
+    /// returning how much was copied; nothing once every record seen has
+    /// been copied. It runs alongside other calls, and `compact` only
+    /// redoes what changed since.
+    async fn prepare_compaction(&self, _max_bytes: u64) -> Result<u64> {
+        Ok(0)
+    }
+
+    /// Re-encode every item's stored metadata, returning how many items
+    /// were rewritten
+    async fn set_metadata_encoding(&mut self, _encoding: MetadataEncoding) -> Result<usize> {
+        Err(VectraError::Storage {
+            message: "This storage backend has a fixed metadata encoding".to_string(),
+        })
+    }
+
+    /// Note that these items were read, for [`StorageBackend::retier`].
+    /// Backends without tiers ignore this.
+    fn record_access(&self, _ids: &[uuid::Uuid]) {}
+
+    /// Move rarely read vectors to compressed cold storage and frequently
+    /// read cold ones back, then start counting reads afresh
+    async fn retier(&mut self, _policy: &TieringPolicy) -> Result<TieringStats> {
+        Err(VectraError::Storage {
+            message: "This storage backend has no cold tier".to_string(),
+        })
+    }
+
+    /// Auto-compaction policy persisted with the index, if any
+    async fn compaction_policy(&self) -> Result<Option<CompactionPolicy>> {
+        Ok(None)
+    }
+
+    /// Embedding model persisted with the index, if any
+    async fn embedding_model(&self) -> Result<Option<EmbeddingModel>> {
+        Ok(None)
+    }
+
+    /// Size and quota limits persisted with the index, if any
+    async fn limits(&self) -> Result<Option<IndexLimits>> {
+        Ok(None)
+    }
+
+    /// Transform applied to vectors before they are stored or queried,
+    /// persisted with the index, if any
+    async fn vector_transform(&self) -> Result<Option<VectorTransform>> {
+        Ok(None)
+    }
+
+    /// Validation policy for vectors written, persisted with the index,
+    /// if any
+    async fn vector_validation(&self) -> Result<Option<VectorValidation>> {
+        Ok(None)
+    }
+
+    /// Metadata field naming each item's namespace, if the index has one
+    async fn namespace_field(&self) -> Result<Option<String>> {
+        Ok(None)
+    }
+
+    /// Every item in `namespace`. Backends without a namespace key scan
+    /// all items for it.
+    async fn list_namespace(&self, namespace: &str) -> Result<Vec<VectorItem>> {
+        let Some(field) = self.namespace_field().await? else {
+            return Ok(Vec::new());
+        };
+        let mut items = self.list_items(None).await?;
+        items.retain(|item| namespace_of(&field, item).as_deref() == Some(namespace));
+        Ok(items)
+    }
+
+    /// Items whose numeric `field` lies within `range`, or `None` when the
+    /// backend doesn't index `field` and the caller must scan
+    async fn list_range(
+        &self,
+        _field: &str,
+        _range: &NumericRange,
+    ) -> Result<Option<Vec<VectorItem>>> {
+        Ok(None)
+    }
+
+    /// Numeric fields the backend keeps a range index on
+    async fn range_fields(&self) -> Result<Vec<String>> {
+        Ok(Vec::new())
+    }
+
+    /// Composite indexes the backend keeps
+    async fn composite_indexes(&self) -> Result<Vec<CompositeIndex>> {
+        Ok(Vec::new())
+    }
+
+    /// Add a composite index, filing every stored item under it
+    async fn add_composite_index(&mut self, _index: &CompositeIndex) -> Result<()> {
+        Err(VectraError::Storage {
+            message: "This storage backend doesn't keep composite indexes".to_string(),
+        })
+    }
+
+    /// Items whose fields hold the `pinned` values, read from the composite
+    /// index covering the longest leading run of them, or `None` when none
+    /// covers any and the caller must scan. Items read may hold other
+    /// values too, so callers still test them.
+    async fn list_composite(
+        &self,
+        _pinned: &[(String, serde_json::Value)],
+    ) -> Result<Option<Vec<VectorItem>>> {
+        Ok(None)
+    }
+
+    /// Whether the index was written by a newer version with features this
+    /// build can read but not write
+    async fn is_read_only(&self) -> Result<bool> {
+        Ok(false)
+    }
+
+    /// Change how many writes may pass between manifest saves; backends
+    /// that don't batch them ignore this
+    fn set_flush_interval(&self, _operations: u32) {}
+
+    /// Time spent waiting for the backend's internal locks since it was
+    /// opened. Backends without instrumented locks report none.
+    fn lock_waits(&self) -> LockWaitStats {
+        LockWaitStats::new()
+    }
+
+    /// Start counting lock waits afresh
+    fn reset_lock_waits(&self) {}
+
+    /// Check every live item can be read back, quarantining those that
+    /// can't so reads skip them instead of failing. Returns everything
+    /// now quarantined. Backends without per-item records find nothing.
+    async fn quarantine_damaged(&self) -> Result<Vec<QuarantinedItem>> {
+        Ok(Vec::new())
+    }
+
+    /// Items reads are skipping because they are damaged. Writing or
+    /// deleting an item lifts its quarantine.
+    fn quarantined(&self) -> Vec<QuarantinedItem> {
+        Vec::new()
+    }
+
+    /// Compare the backend's secondary indexes, such as namespace keys,
+    /// with the items they index. Backends without any find nothing.
+    async fn verify_secondary_indexes(&self) -> Result<IndexDivergence> {
+        Ok(IndexDivergence::default())
+    }
+
+    /// Rebuild the backend's secondary indexes from its items
+    async fn rebuild_secondary_indexes(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    /// Store each item's payload, or remove it where `None`. Payloads are
+    /// deleted with their item. Fails for items that don't exist.
+    async fn set_payloads(&mut self, _payloads: &[(uuid::Uuid, Option<Vec<u8>>)]) -> Result<()> {
+        Err(VectraError::Storage {
+            message: "This storage backend doesn't store payloads".to_string(),
+        })
+    }
+
+    /// Stored payloads of `ids`, in order, `None` for items without one
+    async fn get_payloads(&self, ids: &[uuid::Uuid]) -> Result<Vec<Option<Vec<u8>>>> {
+        Ok(vec![None; ids.len()])
+    }
 }
 
 /// Configuration matching Node.js CreateIndexConfig
@@ -53,6 +276,53 @@ pub struct CreateIndexConfig {
 
     #[serde(default)]
     pub hnsw_config: HnswConfig,
+
+    /// Enables background auto-compaction when set
+    #[serde(default)]
+    pub compaction: Option<CompactionPolicy>,
+
+    /// Model the index's vectors come from; enforced on every write when set
+    #[serde(default)]
+    pub embedding_model: Option<EmbeddingModel>,
+
+    /// Size and quota limits enforced on every write
+    #[serde(default)]
+    pub limits: Option<IndexLimits>,
+
+    /// Reduction applied to every vector written and queried, such as a
+    /// Matryoshka truncation or PCA projection
+    #[serde(default)]
+    pub vector_transform: Option<VectorTransform>,
+
+    /// Zero-vector, norm and normalization policy enforced on every
+    /// vector written
+    #[serde(default)]
+    pub vector_validation: Option<VectorValidation>,
+
+    /// Metadata field whose value names an item's namespace, such as a
+    /// tenant or collection. Enables per-namespace stats and quotas.
+    #[serde(default)]
+    pub namespace_field: Option<String>,
+
+    /// Numeric metadata fields, such as scores or timestamps, kept in an
+    /// ordered index so range filters on them read only the matching
+    /// items. Only RocksDB-backed formats honour it.
+    #[serde(default)]
+    pub range_fields: Vec<String>,
+
+    /// Store identical vectors once, shared by every item that carries them.
+    /// Only formats with a separate vector file honour it.
+    #[serde(default)]
+    pub dedup_vectors: bool,
+
+    /// Encoding of stored metadata. Only formats with a separate metadata
+    /// store honour it.
+    #[serde(default)]
+    pub metadata_encoding: MetadataEncoding,
+
+    /// RocksDB settings. Only RocksDB-backed formats honour it.
+    #[serde(default)]
+    pub rocksdb_tuning: RocksDbTuning,
 }
 
 fn default_version() -> u32 {
@@ -70,6 +340,16 @@ impl Default for CreateIndexConfig {
             distance_metric: default_distance_metric(),
             metadata_config: MetadataConfig::default(),
             hnsw_config: HnswConfig::default(),
+            compaction: None,
+            embedding_model: None,
+            limits: None,
+            vector_transform: None,
+            vector_validation: None,
+            namespace_field: None,
+            range_fields: Vec::new(),
+            dedup_vectors: false,
+            metadata_encoding: MetadataEncoding::default(),
+            rocksdb_tuning: RocksDbTuning::default(),
         }
     }
 }
@@ -257,6 +537,7 @@ impl LocalIndex {
             text: text_query,
             top_k: top_k.unwrap_or(10) as usize,
             filter,
+            ..Default::default()
         };
 
         storage.query_items(&query).await
@@ -351,6 +632,9 @@ impl StorageBackend for DummyStorage {
             size: 0,
             dimensions: None,
             distance_metric: DistanceMetric::Cosine,
+            namespaces: Default::default(),
+            ann_lag: Default::default(),
+            lock_waits: Default::default(),
         })
     }
 }
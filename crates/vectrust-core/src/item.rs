@@ -2,6 +2,7 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use chrono::{DateTime, Utc};
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
@@ -18,10 +19,10 @@ pub struct VectorItem {
     #[serde(default)]
     pub deleted: bool,
 
-    #[serde(default = "Utc::now")]
+    #[serde(default = "Utc::now", alias = "createdAt")]
     pub created_at: DateTime<Utc>,
 
-    #[serde(default = "Utc::now")]
+    #[serde(default = "Utc::now", alias = "updatedAt")]
     pub updated_at: DateTime<Utc>,
 
     #[serde(default)]
@@ -43,6 +44,68 @@ impl Default for VectorItem {
     }
 }
 
+impl VectorItem {
+    /// Deserializes this item's metadata into a user-defined type
+    pub fn metadata_as<T: DeserializeOwned>(&self) -> crate::Result<T> {
+        Ok(T::deserialize(&self.metadata)?)
+    }
+}
+
+/// A VectorItem whose metadata is a concrete serde type instead of a raw JSON value
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TypedItem<T> {
+    pub id: Uuid,
+    pub vector: Vec<f32>,
+    pub metadata: T,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub version: u32,
+}
+
+impl<T: Serialize> TypedItem<T> {
+    /// Creates a typed item with a fresh ID and default timestamps
+    pub fn new(vector: Vec<f32>, metadata: T) -> Self {
+        let now = Utc::now();
+        Self {
+            id: Uuid::new_v4(),
+            vector,
+            metadata,
+            created_at: now,
+            updated_at: now,
+            version: 1,
+        }
+    }
+
+    /// Converts into an untyped VectorItem for storage
+    pub fn to_item(&self) -> crate::Result<VectorItem> {
+        Ok(VectorItem {
+            id: self.id,
+            vector: self.vector.clone(),
+            metadata: serde_json::to_value(&self.metadata)?,
+            indexed: None,
+            deleted: false,
+            created_at: self.created_at,
+            updated_at: self.updated_at,
+            version: self.version,
+        })
+    }
+}
+
+impl<T: DeserializeOwned> TryFrom<VectorItem> for TypedItem<T> {
+    type Error = crate::VectraError;
+
+    fn try_from(item: VectorItem) -> crate::Result<Self> {
+        Ok(Self {
+            metadata: serde_json::from_value(item.metadata)?,
+            id: item.id,
+            vector: item.vector,
+            created_at: item.created_at,
+            updated_at: item.updated_at,
+            version: item.version,
+        })
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UpdateRequest {
     pub id: Uuid,
@@ -56,6 +119,26 @@ pub struct UpdateResult {
     pub version: u32,
 }
 
+/// What an insert does with an item whose id is already stored
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum OnConflict {
+    /// Fail with [`VectraError::ItemAlreadyExists`](crate::VectraError::ItemAlreadyExists)
+    #[default]
+    Error,
+    /// Replace the stored item, keeping its creation time and bumping its
+    /// version as an update would
+    Replace,
+}
+
+/// Progress of a chunked `insert_items`, reported after each chunk
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InsertProgress {
+    /// Items written so far, including this chunk
+    pub inserted: usize,
+    pub total: usize,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ListOptions {
     pub limit: Option<usize>,
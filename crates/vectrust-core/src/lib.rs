@@ -5,6 +5,7 @@ pub mod error;
 pub mod graph;
 pub mod index;
 pub mod item;
+pub mod lock_waits;
 pub mod types;
 pub mod vector_ops;
 
@@ -12,5 +13,6 @@ pub use error::*;
 pub use graph::*;
 pub use index::*;
 pub use item::*;
+pub use lock_waits::*;
 pub use types::*;
 pub use vector_ops::*;
@@ -0,0 +1,169 @@
+// Copyright 2024-2026 Andrey Vasilevsky <anvanster@gmail.com>
+// SPDX-License-Identifier: Apache-2.0
+
+//! Lock wait instrumentation.
+//!
+//! A [`TimedRwLock`] is a `tokio` read-write lock that records each
+//! acquisition in a [`LockWaits`] shared by the locks of one index. An
+//! acquisition that gets the lock straight away counts as uncontended and
+//! costs one `try_` call; only one that has to queue is timed. The totals
+//! come back as [`LockWaitStats`] in
+//! [`IndexStats::lock_waits`](crate::IndexStats::lock_waits), so a slow
+//! workload can be told apart as contention or IO.
+
+use crate::{LockWait, LockWaitStats};
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+/// Wait totals of the locks of one index, by lock and operation
+#[derive(Debug, Default)]
+pub struct LockWaits {
+    waits: Mutex<BTreeMap<(&'static str, &'static str), LockWait>>,
+}
+
+impl LockWaits {
+    /// Count an acquisition of `lock` by `operation`, which waited for
+    /// `waited` or not at all
+    pub fn record(&self, lock: &'static str, operation: &'static str, waited: Option<Duration>) {
+        let mut waits = self.waits.lock().unwrap();
+        let wait = waits.entry((lock, operation)).or_default();
+        wait.acquisitions += 1;
+        if let Some(waited) = waited {
+            let micros = waited.as_micros().min(u64::MAX as u128) as u64;
+            wait.contended += 1;
+            wait.wait_micros = wait.wait_micros.saturating_add(micros);
+            wait.max_wait_micros = wait.max_wait_micros.max(micros);
+        }
+    }
+
+    /// Totals recorded so far
+    pub fn snapshot(&self) -> LockWaitStats {
+        let mut stats = LockWaitStats::new();
+        for ((lock, operation), wait) in self.waits.lock().unwrap().iter() {
+            stats
+                .entry(lock.to_string())
+                .or_default()
+                .insert(operation.to_string(), *wait);
+        }
+        stats
+    }
+
+    /// Start counting afresh
+    pub fn reset(&self) {
+        self.waits.lock().unwrap().clear();
+    }
+}
+
+/// Fold `other` into `stats`, adding up waits both hold
+pub fn merge_lock_waits(stats: &mut LockWaitStats, other: LockWaitStats) {
+    for (lock, operations) in other {
+        let merged = stats.entry(lock).or_default();
+        for (operation, wait) in operations {
+            merged.entry(operation).or_default().merge(&wait);
+        }
+    }
+}
+
+/// A `tokio` read-write lock whose acquisitions are recorded under its name
+#[derive(Debug)]
+pub struct TimedRwLock<T> {
+    lock: RwLock<T>,
+    name: &'static str,
+    waits: Arc<LockWaits>,
+}
+
+impl<T> TimedRwLock<T> {
+    pub fn new(name: &'static str, value: T, waits: &Arc<LockWaits>) -> Self {
+        Self {
+            lock: RwLock::new(value),
+            name,
+            waits: waits.clone(),
+        }
+    }
+
+    /// Share the lock, recorded as a `read`
+    pub async fn read(&self) -> RwLockReadGuard<'_, T> {
+        self.read_for("read").await
+    }
+
+    /// Take the lock exclusively, recorded as a `write`
+    pub async fn write(&self) -> RwLockWriteGuard<'_, T> {
+        self.write_for("write").await
+    }
+
+    /// Share the lock on behalf of `operation`
+    pub async fn read_for(&self, operation: &'static str) -> RwLockReadGuard<'_, T> {
+        if let Ok(guard) = self.lock.try_read() {
+            self.waits.record(self.name, operation, None);
+            return guard;
+        }
+        let started = Instant::now();
+        let guard = self.lock.read().await;
+        self.waits
+            .record(self.name, operation, Some(started.elapsed()));
+        guard
+    }
+
+    /// Take the lock exclusively on behalf of `operation`
+    pub async fn write_for(&self, operation: &'static str) -> RwLockWriteGuard<'_, T> {
+        if let Ok(guard) = self.lock.try_write() {
+            self.waits.record(self.name, operation, None);
+            return guard;
+        }
+        let started = Instant::now();
+        let guard = self.lock.write().await;
+        self.waits
+            .record(self.name, operation, Some(started.elapsed()));
+        guard
+    }
+
+    /// Share the lock if that doesn't mean waiting, unrecorded
+    pub fn try_read(&self) -> Result<RwLockReadGuard<'_, T>, tokio::sync::TryLockError> {
+        self.lock.try_read()
+    }
+
+    /// Take the lock exclusively if that doesn't mean waiting, unrecorded
+    pub fn try_write(&self) -> Result<RwLockWriteGuard<'_, T>, tokio::sync::TryLockError> {
+        self.lock.try_write()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_contended_waits_are_recorded() {
+        let waits = Arc::new(LockWaits::default());
+        let lock = Arc::new(TimedRwLock::new("storage", 0u32, &waits));
+
+        drop(lock.read_for("query").await);
+        let held = lock.write_for("insert").await;
+        let waiter = {
+            let lock = lock.clone();
+            tokio::spawn(async move {
+                *lock.write_for("insert").await += 1;
+            })
+        };
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        drop(held);
+        waiter.await.unwrap();
+
+        let stats = waits.snapshot();
+        let query = stats["storage"]["query"];
+        assert_eq!((query.acquisitions, query.contended), (1, 0));
+        let insert = stats["storage"]["insert"];
+        assert_eq!((insert.acquisitions, insert.contended), (2, 1));
+        assert!(insert.max_wait_micros >= 10_000);
+        assert_eq!(insert.wait_micros, insert.max_wait_micros);
+
+        let mut merged = stats.clone();
+        merge_lock_waits(&mut merged, stats);
+        assert_eq!(merged["storage"]["insert"].acquisitions, 4);
+
+        waits.reset();
+        assert!(waits.snapshot().is_empty());
+    }
+}
@@ -2,8 +2,10 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::ops::{Bound, RangeBounds};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 #[derive(Default)]
 pub enum DistanceMetric {
@@ -11,9 +13,12 @@ pub enum DistanceMetric {
     Cosine,
     Euclidean,
     DotProduct,
+    /// A [`DistanceFn`](crate::DistanceFn) registered under this name in
+    /// the index's [`DistanceRegistry`](crate::DistanceRegistry)
+    Custom(String),
 }
 
-#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MetadataConfig {
     #[serde(default)]
     pub indexed: Vec<String>,
@@ -26,6 +31,21 @@ pub struct MetadataConfig {
 
     #[serde(default = "default_dynamic")]
     pub dynamic: bool,
+
+    /// Fields indexed for keyword search, each with its own analyzer.
+    /// Other string metadata is not text-searchable.
+    #[serde(default)]
+    pub text_fields: Vec<TextFieldConfig>,
+
+    /// Legacy storage keeps metadata larger than this many bytes of JSON in
+    /// a separate `{id}.json` file; `None` always keeps it inline
+    #[serde(default = "default_external_threshold")]
+    pub external_threshold: Option<usize>,
+
+    /// Secondary indexes over several fields at once, for filters that
+    /// commonly pin them together. Only RocksDB-backed formats honour them.
+    #[serde(default)]
+    pub composite_indexes: Vec<CompositeIndex>,
 }
 
 fn default_max_size() -> usize {
@@ -34,8 +54,156 @@ fn default_max_size() -> usize {
 fn default_dynamic() -> bool {
     true
 }
+fn default_external_threshold() -> Option<usize> {
+    Some(1024)
+}
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+impl Default for MetadataConfig {
+    fn default() -> Self {
+        Self {
+            indexed: Vec::new(),
+            reserved: Vec::new(),
+            max_size: default_max_size(),
+            dynamic: default_dynamic(),
+            text_fields: Vec::new(),
+            external_threshold: default_external_threshold(),
+            composite_indexes: Vec::new(),
+        }
+    }
+}
+
+impl MetadataConfig {
+    /// Whether metadata serialized to `size` bytes belongs in its own file
+    pub fn stores_externally(&self, size: usize) -> bool {
+        self.external_threshold.is_some_and(|limit| size > limit)
+    }
+}
+
+/// A secondary index over several metadata fields, such as
+/// `(tenant, category)`. It serves filters pinning a leading run of its
+/// fields to strings, numbers or booleans.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CompositeIndex {
+    /// Dotted metadata paths, in key order
+    pub fields: Vec<String>,
+}
+
+impl CompositeIndex {
+    pub fn new<S: Into<String>>(fields: impl IntoIterator<Item = S>) -> Self {
+        Self {
+            fields: fields.into_iter().map(Into::into).collect(),
+        }
+    }
+
+    /// Check the index names at least one field, and none twice
+    pub fn validate(&self) -> crate::Result<()> {
+        let mut seen = std::collections::HashSet::new();
+        if self.fields.is_empty() || !self.fields.iter().all(|field| seen.insert(field)) {
+            return Err(crate::VectraError::MetadataValidation {
+                message: format!(
+                    "A composite index needs distinct fields, got {:?}",
+                    self.fields
+                ),
+            });
+        }
+        Ok(())
+    }
+}
+
+/// A text-searchable metadata field and how to analyze it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TextFieldConfig {
+    /// Dotted metadata path holding a string or an array of strings
+    pub field: String,
+    #[serde(default)]
+    pub analyzer: TokenizerConfig,
+}
+
+impl TextFieldConfig {
+    pub fn new(field: impl Into<String>, analyzer: TokenizerConfig) -> Self {
+        Self {
+            field: field.into(),
+            analyzer,
+        }
+    }
+}
+
+/// How text is split into words before normalization
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TokenizerKind {
+    /// Split on whitespace only; punctuation stays attached
+    Whitespace,
+    /// Unicode word boundaries (UAX #29)
+    #[default]
+    Unicode,
+}
+
+/// Language-specific stemming and stopwords
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TextLanguage {
+    English,
+    German,
+    French,
+    Spanish,
+    /// Chinese, Japanese and Korean; implies CJK bigrams
+    Cjk,
+}
+
+/// Analyzer settings for a text field
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TokenizerConfig {
+    #[serde(default)]
+    pub kind: TokenizerKind,
+    #[serde(default = "default_lowercase")]
+    pub lowercase: bool,
+    /// Selects the stemmer and stopword list
+    #[serde(default)]
+    pub language: Option<TextLanguage>,
+    /// Strip common suffixes for the language (English when none is set)
+    #[serde(default)]
+    pub stem: bool,
+    /// Drop the language's stopwords at index and query time
+    #[serde(default)]
+    pub stopwords: bool,
+    /// Index runs of CJK characters as overlapping bigrams instead of single characters
+    #[serde(default)]
+    pub cjk_bigrams: bool,
+}
+
+fn default_lowercase() -> bool {
+    true
+}
+
+impl Default for TokenizerConfig {
+    fn default() -> Self {
+        Self {
+            kind: TokenizerKind::default(),
+            lowercase: true,
+            language: None,
+            stem: false,
+            stopwords: false,
+            cjk_bigrams: false,
+        }
+    }
+}
+
+impl TokenizerConfig {
+    /// Stemming and stopword removal for `language`
+    pub fn for_language(language: TextLanguage) -> Self {
+        Self {
+            language: Some(language),
+            stem: language != TextLanguage::Cjk,
+            stopwords: true,
+            cjk_bigrams: language == TextLanguage::Cjk,
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct HnswConfig {
     #[serde(default = "default_m")]
     pub m: usize,
@@ -109,18 +277,1290 @@ pub struct IndexStats {
     pub size: u64,
     pub dimensions: Option<usize>,
     pub distance_metric: DistanceMetric,
+    /// Breakdown by namespace, for indexes created with a namespace field
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub namespaces: BTreeMap<String, NamespaceStats>,
+    /// How far the HNSW graph trails writes, when one is built
+    #[serde(default)]
+    pub ann_lag: AnnLag,
+    /// Time spent waiting for locks since the index was opened
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub lock_waits: LockWaitStats,
+}
+
+/// Lock waits by lock name, then by the operation that took the lock
+pub type LockWaitStats = BTreeMap<String, BTreeMap<String, LockWait>>;
+
+/// How often one kind of operation took a lock, and how long it waited
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LockWait {
+    pub acquisitions: u64,
+    /// Acquisitions that found the lock held and had to wait
+    pub contended: u64,
+    /// Total time spent waiting, in microseconds
+    pub wait_micros: u64,
+    /// Longest single wait, in microseconds
+    pub max_wait_micros: u64,
+}
+
+impl LockWait {
+    /// Fold `other`'s counts into these
+    pub fn merge(&mut self, other: &LockWait) {
+        self.acquisitions += other.acquisitions;
+        self.contended += other.contended;
+        self.wait_micros += other.wait_micros;
+        self.max_wait_micros = self.max_wait_micros.max(other.max_wait_micros);
+    }
+}
+
+/// How far the HNSW graph trails the writes made to an index
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AnnLag {
+    /// Written items not yet added to the graph
+    pub pending: usize,
+    /// When the longest-waiting of them was written
+    pub oldest_pending: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// One namespace's share of an index
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NamespaceStats {
+    pub items: usize,
+    /// Approximate stored size: raw vectors plus metadata JSON
+    pub bytes: u64,
+    /// Queries filtered to the namespace since the index was opened
+    pub queries: u64,
+}
+
+/// Namespace `item` belongs to under the index's namespace `field`
+pub fn namespace_of(field: &str, item: &crate::VectorItem) -> Option<String> {
+    namespace_name(item.metadata.get(field)?)
+}
+
+/// Namespace a metadata value names. Strings name themselves; numbers and
+/// booleans are named by their JSON text.
+pub fn namespace_name(value: &serde_json::Value) -> Option<String> {
+    match value {
+        serde_json::Value::String(s) => Some(s.clone()),
+        serde_json::Value::Number(_) | serde_json::Value::Bool(_) => Some(value.to_string()),
+        _ => None,
+    }
+}
+
+/// Whether a split leaves the matching items in the source index
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SplitMode {
+    #[default]
+    Copy,
+    Move,
+}
+
+/// How a backend with its own metadata store encodes each item's metadata
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MetadataEncoding {
+    /// JSON text, readable by every version
+    #[default]
+    Json,
+    /// CBOR, a binary encoding that is smaller and cheaper to parse
+    Cbor,
+}
+
+/// Preset RocksDB settings for the machine and workload an index serves
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TuningProfile {
+    /// Small write buffers and files for indexes of a few thousand items
+    SmallIndex,
+    /// 64MB write buffers and four background jobs
+    #[default]
+    Balanced,
+    /// Large write buffers and a background job per core, for loading
+    /// millions of items on a big machine
+    BulkIngest,
+    /// Minimal buffers, one background job and few open files, for
+    /// embedded boards
+    LowMemory,
+}
+
+/// How a RocksDB-backed index configures RocksDB. Saved with the index
+/// and applied every time it is opened.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RocksDbTuning {
+    #[serde(default)]
+    pub profile: TuningProfile,
+
+    /// Bytes of blocks cached for reads of item metadata and vector
+    /// records, shared by both; the profile's size when unset
+    #[serde(default)]
+    pub block_cache_bytes: Option<u64>,
+
+    /// Bloom filter bits per key for item metadata and vector records, so
+    /// lookups of missing ids rarely read a block; 0 turns filters off
+    #[serde(default = "default_bloom_bits_per_key")]
+    pub bloom_bits_per_key: u32,
+
+    /// Raw overrides by RocksDB option name, such as
+    /// `"level0_file_num_compaction_trigger": "8"`, applied to every column
+    /// family over the profile. Only options RocksDB can change on an open
+    /// database are accepted; any other fails the open.
+    #[serde(default)]
+    pub options: BTreeMap<String, String>,
+}
+
+fn default_bloom_bits_per_key() -> u32 {
+    10
+}
+
+impl Default for RocksDbTuning {
+    fn default() -> Self {
+        Self {
+            profile: TuningProfile::default(),
+            block_cache_bytes: None,
+            bloom_bits_per_key: default_bloom_bits_per_key(),
+            options: BTreeMap::new(),
+        }
+    }
+}
+
+/// Thresholds for background compaction of space left behind by deletes and updates
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompactionPolicy {
+    /// Compact once this fraction of vector storage is dead (0.0 - 1.0)
+    #[serde(default = "default_max_deleted_ratio")]
+    pub max_deleted_ratio: f64,
+
+    /// Compact once this many bytes are reclaimable, regardless of ratio
+    #[serde(default = "default_max_fragmentation_bytes")]
+    pub max_fragmentation_bytes: u64,
+
+    #[serde(default = "default_check_interval_secs")]
+    pub check_interval_secs: u64,
+
+    /// IO throttle for the rewrite; unlimited when unset
+    #[serde(default)]
+    pub max_bytes_per_sec: Option<u64>,
+}
+
+fn default_max_deleted_ratio() -> f64 {
+    0.3
+}
+fn default_max_fragmentation_bytes() -> u64 {
+    64 * 1024 * 1024
+}
+fn default_check_interval_secs() -> u64 {
+    60
+}
+
+impl Default for CompactionPolicy {
+    fn default() -> Self {
+        Self {
+            max_deleted_ratio: default_max_deleted_ratio(),
+            max_fragmentation_bytes: default_max_fragmentation_bytes(),
+            check_interval_secs: default_check_interval_secs(),
+            max_bytes_per_sec: None,
+        }
+    }
+}
+
+impl CompactionPolicy {
+    pub fn should_compact(&self, stats: &CompactionStats) -> bool {
+        stats.reclaimable_bytes > 0
+            && (stats.deleted_ratio() > self.max_deleted_ratio
+                || stats.reclaimable_bytes > self.max_fragmentation_bytes)
+    }
+}
+
+/// Metadata key holding the embedding model an item's vector came from
+pub const EMBEDDING_MODEL_KEY: &str = "embedding_model";
+
+/// Embedding model an index's vectors come from.
+///
+/// Vectors from different models share no geometry, so mixing them
+/// silently ruins recall. Items tagged with another model under
+/// [`EMBEDDING_MODEL_KEY`] are rejected or kept out of queries; untagged
+/// items are assumed to match.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EmbeddingModel {
+    /// Model name, ideally with a version (e.g. `"text-embedding-3-small@1"`)
+    pub name: String,
+    #[serde(default)]
+    pub on_mismatch: ModelMismatch,
+}
+
+/// Handling of items embedded with a model other than the index's
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ModelMismatch {
+    /// Fail the write
+    #[default]
+    Reject,
+    /// Store the item but leave it out of vector queries
+    Segregate,
+}
+
+impl EmbeddingModel {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            on_mismatch: ModelMismatch::default(),
+        }
+    }
+
+    /// Model an item is tagged with, if any
+    pub fn of_item(item: &crate::VectorItem) -> Option<&str> {
+        item.metadata.get(EMBEDDING_MODEL_KEY)?.as_str()
+    }
+
+    /// Whether `item` is untagged or tagged with this model
+    pub fn matches(&self, item: &crate::VectorItem) -> bool {
+        Self::of_item(item).is_none_or(|model| model == self.name)
+    }
+}
+
+/// Hard limits checked on every write, so a runaway ingestion job fails
+/// instead of filling the disk. Unset limits are not enforced.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexLimits {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_dimensions: Option<usize>,
+    /// Size of an item's metadata serialized as JSON
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_metadata_bytes: Option<usize>,
+    /// Size of an item's stored payload, below [`MAX_PAYLOAD_BYTES`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_payload_bytes: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_items: Option<usize>,
+    /// Everything under the index folder, including sidecar files
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_disk_bytes: Option<u64>,
+    /// Quota for every namespace without its own entry in `namespaces`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub namespace: Option<NamespaceLimits>,
+    /// Quotas for individual namespaces
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub namespaces: BTreeMap<String, NamespaceLimits>,
+}
+
+/// Quota on one namespace's share of an index
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NamespaceLimits {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_items: Option<usize>,
+    /// Approximate stored size: raw vectors plus metadata JSON
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_bytes: Option<u64>,
+}
+
+impl IndexLimits {
+    /// Quota applying to `namespace`, if any
+    pub fn for_namespace(&self, namespace: &str) -> Option<&NamespaceLimits> {
+        self.namespaces.get(namespace).or(self.namespace.as_ref())
+    }
+
+    /// Whether any namespace has a quota
+    pub fn has_namespace_limits(&self) -> bool {
+        self.namespace.is_some() || !self.namespaces.is_empty()
+    }
+
+    /// Check one item's own size against the per-item limits
+    pub fn check_item(&self, item: &crate::VectorItem) -> crate::Result<()> {
+        if let Some(max) = self.max_dimensions {
+            if item.vector.len() > max {
+                return Err(crate::VectraError::VectorValidation {
+                    message: format!(
+                        "Item {} has {} dimensions, more than the index limit of {}",
+                        item.id,
+                        item.vector.len(),
+                        max
+                    ),
+                });
+            }
+        }
+        if let Some(max) = self.max_metadata_bytes {
+            let size = serde_json::to_vec(&item.metadata)?.len();
+            if size > max {
+                return Err(crate::VectraError::MetadataValidation {
+                    message: format!(
+                        "Item {} has {} bytes of metadata, more than the index limit of {}",
+                        item.id, size, max
+                    ),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Check a payload of `len` bytes for item `id` against the payload limit
+    pub fn check_payload(&self, id: &uuid::Uuid, len: usize) -> crate::Result<()> {
+        let max = self
+            .max_payload_bytes
+            .map_or(MAX_PAYLOAD_BYTES, |max| max.min(MAX_PAYLOAD_BYTES));
+        if len > max {
+            return Err(crate::VectraError::QuotaExceeded {
+                message: format!(
+                    "Item {} has a {} byte payload, more than the limit of {}",
+                    id, len, max
+                ),
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Largest payload an index stores for one item, whatever its limits.
+/// Documents bigger than this belong in a blob store of their own.
+pub const MAX_PAYLOAD_BYTES: usize = 16 * 1024 * 1024;
+
+/// Reduction applied to every vector before it is stored and to every
+/// query vector, so an index can serve lower-dimensional search than its
+/// embedding model produces. Fixed when the index is created and kept in
+/// its manifest.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VectorTransform {
+    /// Identifies this transform among others trained for the same model,
+    /// such as successive PCA fits
+    #[serde(default = "default_transform_version")]
+    pub version: u32,
+    pub kind: TransformKind,
+    /// Scale reduced vectors to unit length, as truncated Matryoshka
+    /// embeddings need for dot-product search
+    #[serde(default)]
+    pub normalize: bool,
+}
+
+/// How a [`VectorTransform`] reduces a vector
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TransformKind {
+    /// Keep the first `dimensions` components, as Matryoshka embeddings allow
+    Truncate { dimensions: usize },
+    /// Subtract `mean`, then take the dot product with each row of
+    /// `components`, as a learned PCA projection does
+    Project {
+        #[serde(default)]
+        mean: Vec<f32>,
+        components: Vec<Vec<f32>>,
+    },
+}
+
+fn default_transform_version() -> u32 {
+    1
+}
+
+impl VectorTransform {
+    /// Keep the first `dimensions` components of each vector
+    pub fn truncate(dimensions: usize) -> Self {
+        Self {
+            version: default_transform_version(),
+            kind: TransformKind::Truncate { dimensions },
+            normalize: false,
+        }
+    }
+
+    /// Project each vector, less `mean`, onto the rows of `components`
+    pub fn project(mean: Vec<f32>, components: Vec<Vec<f32>>) -> Self {
+        Self {
+            version: default_transform_version(),
+            kind: TransformKind::Project { mean, components },
+            normalize: false,
+        }
+    }
+
+    /// Length of the vectors this transform produces
+    pub fn output_dimensions(&self) -> usize {
+        match &self.kind {
+            TransformKind::Truncate { dimensions } => *dimensions,
+            TransformKind::Project { components, .. } => components.len(),
+        }
+    }
+
+    /// Check the transform can be applied at all
+    pub fn validate(&self) -> crate::Result<()> {
+        let invalid = |message: &str| {
+            Err(crate::VectraError::VectorValidation {
+                message: format!("Invalid vector transform: {}", message),
+            })
+        };
+        match &self.kind {
+            TransformKind::Truncate { dimensions: 0 } => invalid("truncates to zero dimensions"),
+            TransformKind::Truncate { .. } => Ok(()),
+            TransformKind::Project { mean, components } => {
+                let Some(width) = components.first().map(Vec::len) else {
+                    return invalid("projection has no components");
+                };
+                if width == 0 || components.iter().any(|row| row.len() != width) {
+                    return invalid("projection components differ in length");
+                }
+                if !mean.is_empty() && mean.len() != width {
+                    return invalid("projection mean doesn't match its components");
+                }
+                let values = mean.iter().chain(components.iter().flatten());
+                if !values.into_iter().all(|value| value.is_finite()) {
+                    return invalid("projection contains NaN or infinite values");
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Reduce `vector`, which must be at least as long as a truncation
+    /// and exactly as long as a projection's components
+    pub fn apply(&self, vector: &[f32]) -> crate::Result<Vec<f32>> {
+        let mut reduced = match &self.kind {
+            TransformKind::Truncate { dimensions } => {
+                if vector.len() < *dimensions {
+                    return Err(crate::VectraError::InvalidDimensions {
+                        expected: *dimensions,
+                        actual: vector.len(),
+                    });
+                }
+                vector[..*dimensions].to_vec()
+            }
+            TransformKind::Project { mean, components } => {
+                let width = components.first().map_or(0, Vec::len);
+                if vector.len() != width {
+                    return Err(crate::VectraError::InvalidDimensions {
+                        expected: width,
+                        actual: vector.len(),
+                    });
+                }
+                let centered: Vec<f32> = if mean.is_empty() {
+                    vector.to_vec()
+                } else {
+                    vector.iter().zip(mean).map(|(x, m)| x - m).collect()
+                };
+                components
+                    .iter()
+                    .map(|row| row.iter().zip(&centered).map(|(a, b)| a * b).sum())
+                    .collect()
+            }
+        };
+        if self.normalize {
+            crate::VectorOps::normalize(&mut reduced);
+        }
+        Ok(reduced)
+    }
+}
+
+/// Checks an index makes of every vector written, beyond rejecting NaN
+/// and infinite values. Applied after any [`VectorTransform`], fixed when
+/// the index is created and kept in its manifest.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VectorValidation {
+    /// Reject vectors whose components are all zero. They have no
+    /// direction, so under cosine they score 0 against every query.
+    #[serde(default)]
+    pub reject_zero: bool,
+    /// Reject vectors whose length is below this
+    #[serde(default)]
+    pub min_norm: Option<f32>,
+    /// Reject vectors whose length is above this
+    #[serde(default)]
+    pub max_norm: Option<f32>,
+    /// Scale vectors that pass to unit length before storing them
+    #[serde(default)]
+    pub normalize: bool,
+}
+
+impl VectorValidation {
+    /// Check the bounds make sense
+    pub fn validate(&self) -> crate::Result<()> {
+        let invalid = |message: &str| {
+            Err(crate::VectraError::VectorValidation {
+                message: format!("Invalid vector validation: {}", message),
+            })
+        };
+        let bounds = [self.min_norm, self.max_norm];
+        if bounds
+            .iter()
+            .flatten()
+            .any(|bound| !bound.is_finite() || *bound < 0.0)
+        {
+            return invalid("norm bounds must be finite and not negative");
+        }
+        if let (Some(min), Some(max)) = (self.min_norm, self.max_norm) {
+            if min > max {
+                return invalid("min_norm is above max_norm");
+            }
+        }
+        Ok(())
+    }
+
+    /// Check `vector` against the policy, normalizing it when asked
+    pub fn apply(&self, vector: &mut [f32]) -> crate::Result<()> {
+        let reject = |message: String| Err(crate::VectraError::VectorValidation { message });
+        let norm = vector.iter().map(|&x| x * x).sum::<f32>().sqrt();
+        if norm == 0.0 && (self.reject_zero || self.normalize) {
+            return reject("Vector is all zeros".to_string());
+        }
+        if let Some(min) = self.min_norm.filter(|&min| norm < min) {
+            return reject(format!(
+                "Vector norm {} is below the minimum of {}",
+                norm, min
+            ));
+        }
+        if let Some(max) = self.max_norm.filter(|&max| norm > max) {
+            return reject(format!(
+                "Vector norm {} is above the maximum of {}",
+                norm, max
+            ));
+        }
+        if self.normalize {
+            crate::VectorOps::normalize(vector);
+        }
+        Ok(())
+    }
+}
+
+/// Settings that can change while an index is open, applied by
+/// `LocalIndex::set_runtime_config` without reopening it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RuntimeConfig {
+    /// Results returned when a query doesn't set `top_k`
+    #[serde(default = "default_top_k")]
+    pub default_top_k: usize,
+
+    /// Background auto-compaction policy; `None` stops it
+    #[serde(default)]
+    pub compaction: Option<CompactionPolicy>,
+
+    /// Writes between manifest saves on backends that batch them. Lower
+    /// loses less bookkeeping on a crash, higher writes faster.
+    #[serde(default = "default_flush_interval")]
+    pub flush_interval: u32,
+
+    /// Items `insert_items` writes per hold of the write lock. Queries
+    /// waiting on the lock run between chunks.
+    #[serde(default = "default_insert_chunk_size")]
+    pub insert_chunk_size: usize,
+
+    /// Record queries slower than a threshold; off when unset
+    #[serde(default)]
+    pub slow_query_log: Option<SlowQueryLog>,
+
+    /// Cache query results until a write could change them; off when unset
+    #[serde(default)]
+    pub query_cache: Option<QueryCacheConfig>,
+
+    /// Walk an HNSW graph for queries with many candidates; off when
+    /// unset, so every query scores all of its candidates
+    #[serde(default)]
+    pub ann: Option<AnnConfig>,
+
+    /// Record every mutating operation; off when unset
+    #[serde(default)]
+    pub audit_log: Option<AuditLog>,
+
+    /// Free disk space to keep in reserve. Writes that would leave less
+    /// fail with `VectraError::DiskSpace`, and the index reports itself
+    /// read-only while free space is below it.
+    #[serde(default)]
+    pub min_free_disk_bytes: Option<u64>,
+
+    /// Threads in the pool queries score candidates and walk HNSW graphs
+    /// on, off the async runtime's threads; one per core when unset.
+    /// Indexes asking for the same number share a pool.
+    #[serde(default)]
+    pub scoring_threads: Option<usize>,
+
+    /// Fail queries, listings and deletes whose filter compares values of
+    /// different types, uses an unknown operator or has a malformed
+    /// operand, instead of treating them as not matching
+    #[serde(default)]
+    pub strict_filters: bool,
+
+    /// Sample metadata statistics for the query planner to estimate how
+    /// many items a filter passes; off when unset
+    #[serde(default)]
+    pub planner_stats: Option<PlannerStatsConfig>,
+
+    /// Most results a query may rank, counting the `offset` it skips as
+    /// well as its `top_k`. Larger queries fail with `VectraError::Query`
+    /// rather than rank most of the index; page through it with
+    /// `search_after` or `list_items` instead.
+    #[serde(default = "default_max_top_k")]
+    pub max_top_k: usize,
+
+    /// Sum query scores in `f64` in a fixed pairwise order, so the same
+    /// inputs give bit-identical scores on every platform, at some cost in
+    /// speed
+    #[serde(default)]
+    pub deterministic_scoring: bool,
+}
+
+/// Size and lifetime of cached query results.
+///
+/// Entries are dropped early when a write touches an item the query
+/// returned or an item its filter matches.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueryCacheConfig {
+    #[serde(default = "default_cache_entries")]
+    pub max_entries: usize,
+    #[serde(default = "default_cache_ttl_secs")]
+    pub ttl_secs: u64,
+}
+
+fn default_cache_entries() -> usize {
+    1024
+}
+fn default_cache_ttl_secs() -> u64 {
+    60
+}
+
+impl Default for QueryCacheConfig {
+    fn default() -> Self {
+        Self {
+            max_entries: default_cache_entries(),
+            ttl_secs: default_cache_ttl_secs(),
+        }
+    }
+}
+
+/// How the query planner samples metadata statistics.
+///
+/// Statistics cover the namespace, range and composite index fields plus
+/// `fields`, and are sampled from at most `sample_size` items. They are
+/// taken by the first filtered query and again once writes since then
+/// reach `refresh_ratio` of the items sampled from.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlannerStatsConfig {
+    #[serde(default = "default_stats_sample_size")]
+    pub sample_size: usize,
+    #[serde(default = "default_stats_refresh_ratio")]
+    pub refresh_ratio: f64,
+    #[serde(default)]
+    pub fields: Vec<String>,
+}
+
+fn default_stats_sample_size() -> usize {
+    10_000
+}
+fn default_stats_refresh_ratio() -> f64 {
+    0.2
+}
+
+impl Default for PlannerStatsConfig {
+    fn default() -> Self {
+        Self {
+            sample_size: default_stats_sample_size(),
+            refresh_ratio: default_stats_refresh_ratio(),
+            fields: Vec::new(),
+        }
+    }
+}
+
+/// When a query walks the HNSW graph instead of scoring every candidate.
+///
+/// Scoring a few hundred vectors is faster than walking the graph and
+/// exact, so queries whose filter leaves at most `exact_threshold`
+/// candidates, or fewer than `exact_k_factor` per result needed, are
+/// scored exactly. Other queries take a shortlist from the graph, widened
+/// by `oversample` and by how much of the index the filter excludes, and
+/// score that exactly. The graph is built on the first such query and
+/// kept up to date with writes.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AnnConfig {
+    #[serde(default)]
+    pub hnsw: HnswConfig,
+    #[serde(default = "default_exact_threshold")]
+    pub exact_threshold: usize,
+    #[serde(default = "default_exact_k_factor")]
+    pub exact_k_factor: usize,
+    #[serde(default = "default_ann_oversample")]
+    pub oversample: usize,
+}
+
+fn default_exact_threshold() -> usize {
+    1000
+}
+fn default_exact_k_factor() -> usize {
+    10
+}
+fn default_ann_oversample() -> usize {
+    4
+}
+
+impl Default for AnnConfig {
+    fn default() -> Self {
+        Self {
+            hnsw: HnswConfig::default(),
+            exact_threshold: default_exact_threshold(),
+            exact_k_factor: default_exact_k_factor(),
+            oversample: default_ann_oversample(),
+        }
+    }
+}
+
+/// How a query picked the items it scored
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SearchStrategy {
+    /// Every candidate that passed the filter
+    #[default]
+    Exact,
+    /// A shortlist from the HNSW graph
+    Ann,
+}
+
+/// Where and when to record slow queries.
+///
+/// Every query at or over the threshold is emitted as a `tracing` warning
+/// under the `vectrust::slow_query` target, and appended as a JSON line to
+/// `path` when one is given.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SlowQueryLog {
+    pub threshold_ms: u64,
+    #[serde(default)]
+    pub path: Option<std::path::PathBuf>,
+}
+
+/// Append-only JSON-lines record of mutating operations.
+///
+/// Each insert, update, delete, index creation and index deletion appends
+/// one entry with its time, actor, item ids and outcome. Once the file
+/// reaches `max_bytes` it is renamed to `path.1`, shifting older files up
+/// to `path.{max_files}` and dropping the oldest.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditLog {
+    pub path: std::path::PathBuf,
+    /// Who entries are attributed to, such as a service or user name
+    #[serde(default)]
+    pub actor: Option<String>,
+    /// Size at which the file is rotated; never rotated when unset
+    #[serde(default)]
+    pub max_bytes: Option<u64>,
+    /// Rotated files kept besides the current one
+    #[serde(default = "default_audit_files")]
+    pub max_files: usize,
+}
+
+impl AuditLog {
+    pub fn new(path: impl Into<std::path::PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            actor: None,
+            max_bytes: None,
+            max_files: default_audit_files(),
+        }
+    }
+}
+
+fn default_audit_files() -> usize {
+    5
+}
+
+fn default_top_k() -> usize {
+    10
+}
+fn default_flush_interval() -> u32 {
+    100
+}
+fn default_insert_chunk_size() -> usize {
+    1000
+}
+
+fn default_max_top_k() -> usize {
+    10_000
+}
+
+impl Default for RuntimeConfig {
+    fn default() -> Self {
+        Self {
+            default_top_k: default_top_k(),
+            compaction: None,
+            flush_interval: default_flush_interval(),
+            insert_chunk_size: default_insert_chunk_size(),
+            slow_query_log: None,
+            query_cache: None,
+            ann: None,
+            audit_log: None,
+            min_free_disk_bytes: None,
+            scoring_threads: None,
+            strict_filters: false,
+            planner_stats: None,
+            max_top_k: default_max_top_k(),
+            deterministic_scoring: false,
+        }
+    }
+}
+
+/// Space accounting used to decide whether compaction is worthwhile
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CompactionStats {
+    pub live_items: usize,
+    /// Tombstoned records still occupying storage
+    pub deleted_items: usize,
+    pub live_bytes: u64,
+    /// Bytes held by deleted or superseded vectors
+    pub reclaimable_bytes: u64,
+}
+
+impl CompactionStats {
+    /// Fraction of used storage that compaction would reclaim
+    pub fn deleted_ratio(&self) -> f64 {
+        let total = self.live_bytes + self.reclaimable_bytes;
+        if total == 0 {
+            0.0
+        } else {
+            self.reclaimable_bytes as f64 / total as f64
+        }
+    }
+}
+
+/// How [`StorageBackend::retier`](crate::StorageBackend::retier) sorts
+/// vectors between the hot tier, mapped uncompressed, and the compressed
+/// cold tier. Reads are counted in memory since the index was opened or
+/// last retiered, so a freshly opened index has read nothing yet.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TieringPolicy {
+    /// Hot vectors read at most this many times move to the cold tier
+    #[serde(default)]
+    pub demote_max_reads: u32,
+    /// Cold vectors read at least this many times move back to the hot tier
+    #[serde(default = "default_promote_min_reads")]
+    pub promote_min_reads: u32,
+}
+
+fn default_promote_min_reads() -> u32 {
+    1
+}
+
+impl Default for TieringPolicy {
+    fn default() -> Self {
+        Self {
+            demote_max_reads: 0,
+            promote_min_reads: default_promote_min_reads(),
+        }
+    }
+}
+
+/// Outcome of a retiering pass
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TieringStats {
+    pub hot_items: usize,
+    pub cold_items: usize,
+    /// Vectors moved from the hot tier to the cold one
+    pub demoted: usize,
+    /// Vectors moved from the cold tier back to the hot one
+    pub promoted: usize,
+    /// Size of the cold tier, compressed
+    pub cold_bytes: u64,
+}
+
+/// An item whose stored record can't be read back
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QuarantinedItem {
+    pub id: uuid::Uuid,
+    /// What was wrong with the record
+    pub reason: String,
+}
+
+/// Whether an index is serving all of its items
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexHealth {
+    /// Damaged items left out of reads until repaired, in id order
+    pub quarantined: Vec<QuarantinedItem>,
+    /// How far the HNSW graph trails writes, when one is built
+    #[serde(default)]
+    pub ann_lag: AnnLag,
+}
+
+impl IndexHealth {
+    /// Whether some items are quarantined
+    pub fn is_degraded(&self) -> bool {
+        !self.quarantined.is_empty()
+    }
+}
+
+/// Where a backend's secondary indexes disagree with the items they index
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexDivergence {
+    /// Items missing from an index they belong in
+    pub missing: usize,
+    /// Index entries for items that are gone or belong elsewhere
+    pub stale: usize,
+}
+
+impl IndexDivergence {
+    /// Whether the secondary indexes match the items
+    pub fn is_consistent(&self) -> bool {
+        self.missing == 0 && self.stale == 0
+    }
+}
+
+/// Bounds on a numeric metadata field, as range filters give them
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NumericRange {
+    pub min: Bound<f64>,
+    pub max: Bound<f64>,
+}
+
+impl Default for NumericRange {
+    fn default() -> Self {
+        Self {
+            min: Bound::Unbounded,
+            max: Bound::Unbounded,
+        }
+    }
+}
+
+impl NumericRange {
+    /// Whether `value` lies within the bounds
+    pub fn contains(&self, value: f64) -> bool {
+        (self.min, self.max).contains(&value)
+    }
+
+    /// The values within both ranges
+    pub fn intersect(self, other: NumericRange) -> Self {
+        Self {
+            min: tighter(self.min, other.min, |a, b| a > b),
+            max: tighter(self.max, other.max, |a, b| a < b),
+        }
+    }
+}
+
+/// The more restrictive of two bounds on the same side, where `beyond`
+/// says whether one value restricts more than another
+fn tighter(a: Bound<f64>, b: Bound<f64>, beyond: fn(f64, f64) -> bool) -> Bound<f64> {
+    match (a, b) {
+        (Bound::Unbounded, bound) | (bound, Bound::Unbounded) => bound,
+        (Bound::Included(x) | Bound::Excluded(x), Bound::Included(y) | Bound::Excluded(y))
+            if x != y =>
+        {
+            if beyond(x, y) {
+                a
+            } else {
+                b
+            }
+        }
+        (Bound::Excluded(_), _) => a,
+        _ => b,
+    }
+}
+
+/// Vector lengths found among an index's items
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DimensionReport {
+    /// Items per vector length
+    pub counts: BTreeMap<usize, usize>,
+    /// Length most items have, which the rest are measured against. Ties
+    /// go to the longer vectors.
+    pub expected: Option<usize>,
+    /// Items whose vectors have another length
+    pub mismatched: Vec<uuid::Uuid>,
+}
+
+impl DimensionReport {
+    pub fn from_items<'a>(items: impl IntoIterator<Item = &'a crate::VectorItem> + Clone) -> Self {
+        let mut counts: BTreeMap<usize, usize> = BTreeMap::new();
+        for item in items.clone() {
+            *counts.entry(item.vector.len()).or_default() += 1;
+        }
+        let expected = counts
+            .iter()
+            .max_by_key(|&(len, count)| (count, len))
+            .map(|(len, _)| *len);
+        let mismatched = items
+            .into_iter()
+            .filter(|item| Some(item.vector.len()) != expected)
+            .map(|item| item.id)
+            .collect();
+        Self {
+            counts,
+            expected,
+            mismatched,
+        }
+    }
+
+    /// Whether items have more than one vector length
+    pub fn is_mixed(&self) -> bool {
+        self.counts.len() > 1
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct QueryResult {
     pub item: crate::VectorItem,
     pub score: f32,
+    /// Matched query terms, present when keyword search contributed to the score
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub highlights: Vec<TextHighlight>,
+    /// The item's stored payload, when the query asked for payloads
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub payload: Option<Vec<u8>>,
+}
+
+impl QueryResult {
+    /// Order results rank in: by descending score, ties by ascending id
+    pub fn rank_cmp(&self, other: &Self) -> std::cmp::Ordering {
+        other
+            .score
+            .total_cmp(&self.score)
+            .then_with(|| self.item.id.cmp(&other.item.id))
+    }
+
+    /// Cursor for the page following this result
+    pub fn cursor(&self) -> QueryCursor {
+        QueryCursor {
+            score: self.score,
+            id: self.item.id,
+        }
+    }
+}
+
+/// Position of a result in a ranking, for fetching the results after it
+/// with [`Query::search_after`].
+///
+/// Unlike an offset, a cursor stays put when items are inserted or
+/// deleted ahead of it between pages, so no result is skipped or shown
+/// twice for that reason.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct QueryCursor {
+    pub score: f32,
+    pub id: uuid::Uuid,
+}
+
+impl QueryCursor {
+    /// Whether `result` ranks after the cursor position
+    pub fn precedes(&self, result: &QueryResult) -> bool {
+        self.precedes_rank(result.score, &result.item.id)
+    }
+
+    /// Whether a result with `score` and `id` would rank after the cursor
+    pub fn precedes_rank(&self, score: f32, id: &uuid::Uuid) -> bool {
+        match self.score.total_cmp(&score) {
+            std::cmp::Ordering::Greater => true,
+            std::cmp::Ordering::Less => false,
+            std::cmp::Ordering::Equal => self.id < *id,
+        }
+    }
+}
+
+/// A query term matched in an item's text field.
+///
+/// `start` and `end` are character (not byte) offsets into the field's
+/// string. Elements of array fields are addressed as `field.N`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TextHighlight {
+    pub field: String,
+    pub term: String,
+    pub start: usize,
+    pub end: usize,
+}
+
+/// Query result with metadata deserialized into a user-defined type
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TypedQueryResult<T> {
+    pub item: crate::TypedItem<T>,
+    pub score: f32,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub highlights: Vec<TextHighlight>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub payload: Option<Vec<u8>>,
+}
+
+impl<T: serde::de::DeserializeOwned> TryFrom<QueryResult> for TypedQueryResult<T> {
+    type Error = crate::VectraError;
+
+    fn try_from(result: QueryResult) -> crate::Result<Self> {
+        Ok(Self {
+            item: result.item.try_into()?,
+            score: result.score,
+            highlights: result.highlights,
+            payload: result.payload,
+        })
+    }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct Query {
     pub vector: Option<Vec<f32>>,
     pub text: Option<String>,
     pub top_k: usize,
     pub filter: Option<serde_json::Value>,
+    /// Metadata-driven score adjustments applied after similarity
+    pub boosts: Vec<ScoreBoost>,
+    /// Time decay blended into similarity before boosts
+    pub recency: Option<RecencyBoost>,
+    /// Results to skip before the first one returned
+    pub offset: usize,
+    /// Return only results ranked after this one, taken from the last
+    /// result of the previous page; `offset` then counts from there
+    pub search_after: Option<QueryCursor>,
+    /// Attach each result's stored payload
+    pub include_payloads: bool,
+    /// Drop results scoring below this, so a query with nothing relevant
+    /// returns nothing rather than its `top_k` least bad matches. Compared
+    /// with the vector score after recency, boosts and scoring hooks.
+    pub min_score: Option<f32>,
+    /// Whether walking the HNSW graph also scores items still queued for it
+    pub freshness: Freshness,
+}
+
+/// How current a query walking the HNSW graph must be while written items
+/// are still queued for it
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Freshness {
+    /// Score the queued items exactly alongside the graph's shortlist, so
+    /// every written item can be found
+    #[default]
+    Strict,
+    /// Take the graph's shortlist alone, missing items not yet added
+    Fast,
+}
+
+impl Query {
+    /// Cut the page this query asks for out of `ranked`, which must be
+    /// sorted by [`QueryResult::rank_cmp`]
+    pub fn paginate(&self, ranked: Vec<QueryResult>) -> Vec<QueryResult> {
+        let start = match self.search_after {
+            Some(ref cursor) => ranked.partition_point(|result| !cursor.precedes(result)),
+            None => 0,
+        };
+        ranked
+            .into_iter()
+            .skip(start.saturating_add(self.offset))
+            .take(self.top_k)
+            .collect()
+    }
+
+    /// Results that must be ranked to cut out this query's page
+    pub fn window(&self) -> usize {
+        if self.search_after.is_some() {
+            usize::MAX
+        } else {
+            self.offset.saturating_add(self.top_k)
+        }
+    }
+}
+
+/// Exponential time decay blended with the similarity score.
+///
+/// The final score is `(1 - weight) * similarity + weight * 0.5^(age / half_life)`.
+/// Items without a usable timestamp get no recency credit.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecencyBoost {
+    pub half_life_days: f64,
+
+    #[serde(default)]
+    pub field: RecencyField,
+
+    #[serde(default = "default_recency_weight")]
+    pub weight: f32,
+}
+
+fn default_recency_weight() -> f32 {
+    0.3
+}
+
+/// Timestamp an item's age is measured from
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum RecencyField {
+    #[default]
+    CreatedAt,
+    UpdatedAt,
+    /// Metadata field holding an RFC 3339 string or Unix seconds
+    Metadata(String),
+}
+
+/// Declarative score adjustment from a numeric metadata field.
+///
+/// Expressible as JSON (`{"field": "popularity", "weight": 0.1}`) so non-Rust
+/// callers can boost without a scoring hook. Items where the field is missing
+/// or non-numeric keep their score.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScoreBoost {
+    /// Metadata field, dotted for nested objects
+    pub field: String,
+
+    #[serde(default = "default_boost_weight")]
+    pub weight: f32,
+
+    #[serde(default)]
+    pub mode: BoostMode,
+}
+
+fn default_boost_weight() -> f32 {
+    1.0
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum BoostMode {
+    /// `score + weight * value`
+    #[default]
+    Add,
+    /// `score * (1 + weight * value)`
+    Multiply,
+}
+
+/// A query saved under a name and run later with [`NamedQuery::to_query`].
+///
+/// The vector is optional so a saved filter can be reused with a fresh
+/// embedding each time; a vector supplied at run time takes precedence.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NamedQuery {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub filter: Option<serde_json::Value>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vector: Option<Vec<f32>>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub top_k: Option<usize>,
+
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub boosts: Vec<ScoreBoost>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub recency: Option<RecencyBoost>,
+}
+
+impl NamedQuery {
+    /// Build the query to run, preferring the given vector and `top_k` over saved ones
+    pub fn to_query(&self, vector: Option<Vec<f32>>, top_k: Option<usize>) -> Query {
+        Query {
+            vector: vector.or_else(|| self.vector.clone()),
+            text: self.text.clone(),
+            top_k: top_k.or(self.top_k).unwrap_or(10),
+            filter: self.filter.clone(),
+            boosts: self.boosts.clone(),
+            recency: self.recency.clone(),
+            ..Default::default()
+        }
+    }
 }
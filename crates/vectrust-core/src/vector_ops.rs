@@ -2,8 +2,134 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use crate::*;
+use std::collections::HashMap;
+use std::ops::Range;
+use std::sync::{Arc, RwLock};
 
-/// Vector similarity calculations optimized for different distance metrics
+/// A user-defined distance metric, such as a weighted cosine or a
+/// Mahalanobis distance with a fixed covariance. Register it with an
+/// index's [`DistanceRegistry`] and create the index with
+/// [`DistanceMetric::Custom`] naming it; the name is what the manifest
+/// records, so the function must be registered again each time the index
+/// is opened.
+pub trait DistanceFn: Send + Sync {
+    /// Similarity of `a` and `b`, higher is closer. Query scores are this.
+    fn similarity(&self, a: &[f32], b: &[f32]) -> f32;
+
+    /// Dissimilarity of `a` and `b`, lower is closer, as used to build and
+    /// search the HNSW graph. Negated similarity unless overridden.
+    fn distance(&self, a: &[f32], b: &[f32]) -> f32 {
+        -self.similarity(a, b)
+    }
+}
+
+/// Distance functions one index can use for [`DistanceMetric::Custom`],
+/// by name. Each index has its own, so indexes in one process can give a
+/// name different functions.
+#[derive(Default)]
+pub struct DistanceRegistry {
+    distances: RwLock<HashMap<String, Arc<dyn DistanceFn>>>,
+}
+
+impl DistanceRegistry {
+    /// Register `distance` under `name`. Fails if a function is already
+    /// registered under it, rather than changing the scores of an index
+    /// that may already be using it.
+    pub fn register(&self, name: &str, distance: Arc<dyn DistanceFn>) -> Result<()> {
+        let mut distances = self.distances.write().unwrap();
+        if distances.contains_key(name) {
+            return Err(VectraError::Query {
+                message: format!("a distance function is already registered as `{}`", name),
+            });
+        }
+        distances.insert(name.to_string(), distance);
+        Ok(())
+    }
+
+    /// The distance function registered under `name`
+    pub fn get(&self, name: &str) -> Option<Arc<dyn DistanceFn>> {
+        self.distances.read().unwrap().get(name).cloned()
+    }
+
+    /// `metric` ready to compute, failing for a custom metric whose
+    /// function isn't registered
+    pub fn resolve(&self, metric: &DistanceMetric) -> Result<Distance> {
+        let custom = match metric {
+            DistanceMetric::Custom(name) => {
+                Some(self.get(name).ok_or_else(|| VectraError::Query {
+                    message: format!("no distance function registered as `{}`", name),
+                })?)
+            }
+            _ => None,
+        };
+        Ok(Distance {
+            metric: metric.clone(),
+            custom,
+        })
+    }
+}
+
+/// A distance metric together with its function when it is a custom one,
+/// from [`DistanceRegistry::resolve`]. Converting a bare
+/// [`DistanceMetric`] leaves a custom metric without one, scoring every
+/// pair 0.
+#[derive(Clone)]
+pub struct Distance {
+    metric: DistanceMetric,
+    custom: Option<Arc<dyn DistanceFn>>,
+}
+
+impl From<DistanceMetric> for Distance {
+    fn from(metric: DistanceMetric) -> Self {
+        Self {
+            metric,
+            custom: None,
+        }
+    }
+}
+
+impl Distance {
+    pub fn metric(&self) -> &DistanceMetric {
+        &self.metric
+    }
+
+    /// Similarity of `a` and `b`, higher is closer
+    pub fn similarity(&self, a: &[f32], b: &[f32]) -> f32 {
+        match self.custom {
+            Some(ref custom) => custom.similarity(a, b),
+            None => VectorOps::calculate_similarity(a, b, &self.metric),
+        }
+    }
+
+    /// [`similarity`](Self::similarity) in deterministic mode. A custom
+    /// metric's function is called as is.
+    pub fn deterministic_similarity(&self, a: &[f32], b: &[f32]) -> f32 {
+        match self.custom {
+            Some(ref custom) => custom.similarity(a, b),
+            None => VectorOps::deterministic_similarity(a, b, &self.metric),
+        }
+    }
+
+    /// Dissimilarity of `a` and `b`, lower is closer
+    pub fn distance(&self, a: &[f32], b: &[f32]) -> f32 {
+        match self.custom {
+            Some(ref custom) => custom.distance(a, b),
+            None => VectorOps::calculate_distance(a, b, &self.metric),
+        }
+    }
+}
+
+/// Terms summed in sequence at the leaves of a pairwise summation
+const PAIRWISE_BLOCK: usize = 8;
+
+/// Vector similarity calculations optimized for different distance metrics.
+///
+/// By default sums are accumulated in `f32` in whatever order is fastest,
+/// which can differ in the last bits between builds and platforms. The
+/// `deterministic_` functions instead sum in `f64` by pairwise summation
+/// in an order fixed by the vector length alone, and round to `f32` once
+/// at the end, so the same inputs give bit-identical scores everywhere,
+/// at some cost in speed.
 pub struct VectorOps;
 
 impl VectorOps {
@@ -60,20 +186,100 @@ impl VectorOps {
         product
     }
 
-    /// Calculate similarity based on the specified distance metric
+    /// [`cosine_similarity`](Self::cosine_similarity) in deterministic mode
+    pub fn deterministic_cosine(a: &[f32], b: &[f32]) -> f32 {
+        if a.len() != b.len() || a.is_empty() {
+            return 0.0;
+        }
+        let dot_product = pairwise_sum(0..a.len(), &|i| a[i] as f64 * b[i] as f64);
+        let norm_a = pairwise_sum(0..a.len(), &|i| a[i] as f64 * a[i] as f64);
+        let norm_b = pairwise_sum(0..b.len(), &|i| b[i] as f64 * b[i] as f64);
+        if norm_a == 0.0 || norm_b == 0.0 {
+            return 0.0;
+        }
+        (dot_product / (norm_a.sqrt() * norm_b.sqrt())) as f32
+    }
+
+    /// [`euclidean_distance`](Self::euclidean_distance) in deterministic mode
+    pub fn deterministic_euclidean_distance(a: &[f32], b: &[f32]) -> f32 {
+        if a.len() != b.len() {
+            return f32::INFINITY;
+        }
+        let sum_sq = pairwise_sum(0..a.len(), &|i| {
+            let diff = a[i] as f64 - b[i] as f64;
+            diff * diff
+        });
+        sum_sq.sqrt() as f32
+    }
+
+    /// [`dot_product`](Self::dot_product) in deterministic mode
+    pub fn deterministic_dot_product(a: &[f32], b: &[f32]) -> f32 {
+        if a.len() != b.len() {
+            return 0.0;
+        }
+        pairwise_sum(0..a.len(), &|i| a[i] as f64 * b[i] as f64) as f32
+    }
+
+    /// Calculate similarity based on the specified distance metric. A
+    /// custom metric's function is only known to a [`Distance`], so here
+    /// it scores every pair 0.
     pub fn calculate_similarity(a: &[f32], b: &[f32], metric: &DistanceMetric) -> f32 {
+        Self::similarity(a, b, metric, false)
+    }
+
+    /// [`calculate_similarity`](Self::calculate_similarity) in
+    /// deterministic mode
+    pub fn deterministic_similarity(a: &[f32], b: &[f32], metric: &DistanceMetric) -> f32 {
+        Self::similarity(a, b, metric, true)
+    }
+
+    fn similarity(a: &[f32], b: &[f32], metric: &DistanceMetric, deterministic: bool) -> f32 {
         match metric {
+            DistanceMetric::Cosine if deterministic => Self::deterministic_cosine(a, b),
             DistanceMetric::Cosine => Self::cosine_similarity(a, b),
             DistanceMetric::Euclidean => {
                 // Convert distance to similarity (higher is better)
-                let distance = Self::euclidean_distance(a, b);
+                let distance = if deterministic {
+                    Self::deterministic_euclidean_distance(a, b)
+                } else {
+                    Self::euclidean_distance(a, b)
+                };
                 if distance == 0.0 {
                     1.0
                 } else {
                     1.0 / (1.0 + distance)
                 }
             }
+            DistanceMetric::DotProduct if deterministic => Self::deterministic_dot_product(a, b),
             DistanceMetric::DotProduct => Self::dot_product(a, b),
+            DistanceMetric::Custom(_) => 0.0,
+        }
+    }
+
+    /// Calculate a dissimilarity based on the specified distance metric
+    /// (lower is closer). Dot product has no true distance, so its negation
+    /// is used; it orders items the same way. A custom metric puts every
+    /// pair infinitely far apart, as in
+    /// [`calculate_similarity`](Self::calculate_similarity).
+    pub fn calculate_distance(a: &[f32], b: &[f32], metric: &DistanceMetric) -> f32 {
+        match metric {
+            DistanceMetric::Cosine => 1.0 - Self::cosine_similarity(a, b),
+            DistanceMetric::Euclidean => Self::euclidean_distance(a, b),
+            DistanceMetric::DotProduct => -Self::dot_product(a, b),
+            DistanceMetric::Custom(_) => f32::INFINITY,
+        }
+    }
+
+    /// Largest [`calculate_distance`](Self::calculate_distance) at which a
+    /// pair still has `similarity`, or `None` when the metric can't tell
+    pub fn distance_bound(similarity: f32, metric: &DistanceMetric) -> Option<f32> {
+        match metric {
+            DistanceMetric::Cosine => Some(1.0 - similarity),
+            // Every distance scores above 0
+            DistanceMetric::Euclidean if similarity <= 0.0 => Some(f32::INFINITY),
+            DistanceMetric::Euclidean => Some(1.0 / similarity - 1.0),
+            DistanceMetric::DotProduct => Some(-similarity),
+            DistanceMetric::Custom(_) => None,
         }
     }
 
@@ -105,6 +311,16 @@ impl VectorOps {
     }
 }
 
+/// Sum `term` over `range`, halving the range until a block is short
+/// enough to add up in sequence
+fn pairwise_sum(range: Range<usize>, term: &impl Fn(usize) -> f64) -> f64 {
+    if range.len() <= PAIRWISE_BLOCK {
+        return range.map(term).fold(0.0, |sum, x| sum + x);
+    }
+    let middle = range.start + range.len() / 2;
+    pairwise_sum(range.start..middle, term) + pairwise_sum(middle..range.end, term)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -126,6 +342,70 @@ mod tests {
         assert!((VectorOps::euclidean_distance(&a, &b) - 5.0).abs() < 1e-6);
     }
 
+    #[test]
+    fn test_custom_distance() {
+        /// Dot product with the first dimension weighted double
+        struct Weighted;
+        impl DistanceFn for Weighted {
+            fn similarity(&self, a: &[f32], b: &[f32]) -> f32 {
+                2.0 * a[0] * b[0] + a[1] * b[1]
+            }
+        }
+
+        let metric = DistanceMetric::Custom("weighted".to_string());
+        let registry = DistanceRegistry::default();
+        assert!(registry.resolve(&metric).is_err());
+        assert_eq!(
+            Distance::from(metric.clone()).similarity(&[1.0, 1.0], &[1.0, 1.0]),
+            0.0
+        );
+
+        registry.register("weighted", Arc::new(Weighted)).unwrap();
+        let distance = registry.resolve(&metric).unwrap();
+        assert_eq!(distance.similarity(&[1.0, 1.0], &[1.0, 1.0]), 3.0);
+        assert_eq!(distance.distance(&[1.0, 1.0], &[1.0, 1.0]), -3.0);
+
+        // A name can't be taken over, and other registries don't see it
+        assert!(registry.register("weighted", Arc::new(Weighted)).is_err());
+        assert!(DistanceRegistry::default().resolve(&metric).is_err());
+        let cosine = registry.resolve(&DistanceMetric::Cosine).unwrap();
+        assert!((cosine.similarity(&[1.0, 0.0], &[1.0, 0.0]) - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_deterministic_mode() {
+        // Values whose f32 sums depend on the order they're added in
+        let a: Vec<f32> = (0..1000)
+            .map(|i| ((i * 37 % 101) as f32 - 50.0) * 1e-3)
+            .collect();
+        let b: Vec<f32> = (0..1000)
+            .map(|i| ((i * 53 % 97) as f32 - 48.0) * 1e3)
+            .collect();
+
+        let cosine = VectorOps::deterministic_cosine(&a, &b);
+        let dot = VectorOps::deterministic_dot_product(&a, &b);
+        let distance = VectorOps::deterministic_euclidean_distance(&a, &b);
+
+        let expected_dot: f64 = a.iter().zip(&b).map(|(x, y)| *x as f64 * *y as f64).sum();
+        assert!((dot as f64 - expected_dot).abs() <= expected_dot.abs() * 1e-7);
+        assert!((cosine - VectorOps::cosine_similarity(&a, &b)).abs() < 1e-4);
+        assert!((distance - VectorOps::euclidean_distance(&a, &b)).abs() / distance < 1e-4);
+        assert_eq!(
+            VectorOps::deterministic_similarity(&a, &b, &DistanceMetric::Cosine),
+            cosine
+        );
+
+        // Cancellation that f32 accumulation gets wrong
+        let a = [1e8, 1.0, -1e8];
+        let b = [1.0, 1.0, 1.0];
+        assert_eq!(VectorOps::dot_product(&a, &b), 0.0);
+        assert_eq!(VectorOps::deterministic_dot_product(&a, &b), 1.0);
+        assert_eq!(
+            VectorOps::deterministic_similarity(&a, &b, &DistanceMetric::DotProduct),
+            1.0
+        );
+    }
+
     #[test]
     fn test_normalization() {
         let mut vector = vec![3.0, 4.0, 0.0];
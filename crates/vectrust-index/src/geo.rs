@@ -0,0 +1,346 @@
+// Copyright 2024-2026 Andrey Vasilevsky <anvanster@gmail.com>
+// SPDX-License-Identifier: Apache-2.0
+
+// Geohash secondary index for location-constrained search
+
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use uuid::Uuid;
+
+const BASE32: &[u8; 32] = b"0123456789bcdefghjkmnpqrstuvwxyz";
+const EARTH_RADIUS_METERS: f64 = 6_371_008.8;
+/// Upper bound on geohash cells scanned per lookup before coarsening
+const MAX_COVER_CELLS: usize = 64;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GeoPoint {
+    pub lat: f64,
+    pub lon: f64,
+}
+
+impl GeoPoint {
+    /// Parse `{"lat": .., "lon": ..}` (`lng` is accepted for longitude)
+    pub fn from_json(value: &Value) -> Option<Self> {
+        let lat = value.get("lat")?.as_f64()?;
+        let lon = value.get("lon").or_else(|| value.get("lng"))?.as_f64()?;
+        ((-90.0..=90.0).contains(&lat) && (-180.0..=180.0).contains(&lon))
+            .then_some(Self { lat, lon })
+    }
+
+    /// Great-circle distance in meters
+    pub fn haversine_meters(&self, other: &GeoPoint) -> f64 {
+        let d_lat = (other.lat - self.lat).to_radians();
+        let d_lon = (other.lon - self.lon).to_radians();
+        let a = (d_lat / 2.0).sin().powi(2)
+            + self.lat.to_radians().cos()
+                * other.lat.to_radians().cos()
+                * (d_lon / 2.0).sin().powi(2);
+        2.0 * EARTH_RADIUS_METERS * a.sqrt().asin()
+    }
+}
+
+/// Latitude/longitude box; `min_lon > max_lon` wraps across the antimeridian
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GeoBounds {
+    pub min_lat: f64,
+    pub min_lon: f64,
+    pub max_lat: f64,
+    pub max_lon: f64,
+}
+
+impl GeoBounds {
+    /// Parse `{"minLat": .., "minLon": .., "maxLat": .., "maxLon": ..}`
+    pub fn from_json(value: &Value) -> Option<Self> {
+        Some(Self {
+            min_lat: value.get("minLat")?.as_f64()?,
+            min_lon: value.get("minLon")?.as_f64()?,
+            max_lat: value.get("maxLat")?.as_f64()?,
+            max_lon: value.get("maxLon")?.as_f64()?,
+        })
+    }
+
+    /// Box enclosing a circle, clamped at the poles
+    pub fn around(center: GeoPoint, radius_meters: f64) -> Self {
+        let d_lat = (radius_meters / EARTH_RADIUS_METERS).to_degrees();
+        let min_lat = (center.lat - d_lat).max(-90.0);
+        let max_lat = (center.lat + d_lat).min(90.0);
+
+        let widest = min_lat.abs().max(max_lat.abs());
+        if widest >= 90.0 {
+            return Self {
+                min_lat,
+                min_lon: -180.0,
+                max_lat,
+                max_lon: 180.0,
+            };
+        }
+
+        let d_lon = d_lat / widest.to_radians().cos();
+        if d_lon >= 180.0 {
+            return Self {
+                min_lat,
+                min_lon: -180.0,
+                max_lat,
+                max_lon: 180.0,
+            };
+        }
+
+        let wrap = |lon: f64| ((lon + 540.0) % 360.0) - 180.0;
+        Self {
+            min_lat,
+            min_lon: wrap(center.lon - d_lon),
+            max_lat,
+            max_lon: wrap(center.lon + d_lon),
+        }
+    }
+
+    pub fn contains(&self, point: &GeoPoint) -> bool {
+        let lat_ok = point.lat >= self.min_lat && point.lat <= self.max_lat;
+        let lon_ok = if self.min_lon <= self.max_lon {
+            point.lon >= self.min_lon && point.lon <= self.max_lon
+        } else {
+            point.lon >= self.min_lon || point.lon <= self.max_lon
+        };
+        lat_ok && lon_ok
+    }
+
+    /// Split antimeridian-crossing boxes into non-wrapping parts
+    fn parts(&self) -> Vec<GeoBounds> {
+        if self.min_lon <= self.max_lon {
+            vec![*self]
+        } else {
+            vec![
+                GeoBounds {
+                    max_lon: 180.0,
+                    ..*self
+                },
+                GeoBounds {
+                    min_lon: -180.0,
+                    ..*self
+                },
+            ]
+        }
+    }
+}
+
+/// Encode a point as a geohash of `precision` characters
+pub fn geohash_encode(point: &GeoPoint, precision: usize) -> String {
+    let (mut lat_range, mut lon_range) = ((-90.0, 90.0), (-180.0, 180.0));
+    let mut hash = String::with_capacity(precision);
+    let mut bits = 0u8;
+    let mut bit_count = 0;
+    let mut even = true;
+
+    while hash.len() < precision {
+        let (range, value) = if even {
+            (&mut lon_range, point.lon)
+        } else {
+            (&mut lat_range, point.lat)
+        };
+        let mid = (range.0 + range.1) / 2.0;
+        bits <<= 1;
+        if value >= mid {
+            bits |= 1;
+            range.0 = mid;
+        } else {
+            range.1 = mid;
+        }
+        even = !even;
+
+        bit_count += 1;
+        if bit_count == 5 {
+            hash.push(BASE32[bits as usize] as char);
+            bits = 0;
+            bit_count = 0;
+        }
+    }
+
+    hash
+}
+
+/// Cell height and width in degrees at a geohash precision
+fn cell_size(precision: usize) -> (f64, f64) {
+    let total_bits = 5 * precision as i32;
+    let lon_bits = (total_bits + 1) / 2;
+    let lat_bits = total_bits / 2;
+    (180.0 / 2f64.powi(lat_bits), 360.0 / 2f64.powi(lon_bits))
+}
+
+/// Geohash cells at `precision` that together cover a non-wrapping box
+fn cover(bounds: &GeoBounds, precision: usize) -> Option<HashSet<String>> {
+    let (height, width) = cell_size(precision);
+    let rows = ((bounds.max_lat - bounds.min_lat) / height).ceil() as usize + 1;
+    let cols = ((bounds.max_lon - bounds.min_lon) / width).ceil() as usize + 1;
+    if rows.saturating_mul(cols) > MAX_COVER_CELLS {
+        return None;
+    }
+
+    let mut cells = HashSet::new();
+    for row in 0..rows {
+        let lat = (bounds.min_lat + row as f64 * height).min(bounds.max_lat);
+        for col in 0..cols {
+            let lon = (bounds.min_lon + col as f64 * width).min(bounds.max_lon);
+            cells.insert(geohash_encode(&GeoPoint { lat, lon }, precision));
+        }
+    }
+    Some(cells)
+}
+
+/// In-memory geohash index over one metadata field.
+///
+/// Lookups return a superset of the matching IDs; callers still apply the
+/// exact `$near` / `$within` filter to the candidates.
+pub struct GeohashIndex {
+    field: String,
+    precision: usize,
+    cells: BTreeMap<String, HashSet<Uuid>>,
+    hashes: HashMap<Uuid, String>,
+}
+
+impl GeohashIndex {
+    /// Index `field` at `precision` characters (clamped to 1..=12; 6 is about 1km)
+    pub fn new(field: impl Into<String>, precision: usize) -> Self {
+        Self {
+            field: field.into(),
+            precision: precision.clamp(1, 12),
+            cells: BTreeMap::new(),
+            hashes: HashMap::new(),
+        }
+    }
+
+    pub fn field(&self) -> &str {
+        &self.field
+    }
+
+    pub fn len(&self) -> usize {
+        self.hashes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.hashes.is_empty()
+    }
+
+    /// Index an item from its metadata, replacing any previous entry
+    pub fn insert(&mut self, id: Uuid, metadata: &Value) {
+        self.remove(&id);
+
+        let point = self
+            .field
+            .split('.')
+            .try_fold(metadata, |value, segment| value.get(segment))
+            .and_then(GeoPoint::from_json);
+        if let Some(point) = point {
+            let hash = geohash_encode(&point, self.precision);
+            self.cells.entry(hash.clone()).or_default().insert(id);
+            self.hashes.insert(id, hash);
+        }
+    }
+
+    pub fn remove(&mut self, id: &Uuid) {
+        if let Some(hash) = self.hashes.remove(id) {
+            if let Some(ids) = self.cells.get_mut(&hash) {
+                ids.remove(id);
+                if ids.is_empty() {
+                    self.cells.remove(&hash);
+                }
+            }
+        }
+    }
+
+    /// Candidate IDs within `radius_meters` of `center`
+    pub fn candidates_near(&self, center: GeoPoint, radius_meters: f64) -> HashSet<Uuid> {
+        self.candidates_within(&GeoBounds::around(center, radius_meters))
+    }
+
+    /// Candidate IDs inside `bounds`
+    pub fn candidates_within(&self, bounds: &GeoBounds) -> HashSet<Uuid> {
+        let mut result = HashSet::new();
+
+        for part in bounds.parts() {
+            // Coarsen until the box is covered by a bounded number of cells
+            let prefixes = (1..=self.precision)
+                .rev()
+                .find_map(|precision| cover(&part, precision))
+                .unwrap_or_else(|| BASE32.iter().map(|c| (*c as char).to_string()).collect());
+
+            for prefix in prefixes {
+                for (_, ids) in self
+                    .cells
+                    .range(prefix.clone()..)
+                    .take_while(|(hash, _)| hash.starts_with(&prefix))
+                {
+                    result.extend(ids);
+                }
+            }
+        }
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const BERLIN: GeoPoint = GeoPoint {
+        lat: 52.52,
+        lon: 13.405,
+    };
+
+    #[test]
+    fn test_geohash_encode_known_value() {
+        let point = GeoPoint {
+            lat: 57.64911,
+            lon: 10.40744,
+        };
+        assert_eq!(geohash_encode(&point, 11), "u4pruydqqvj");
+    }
+
+    #[test]
+    fn test_haversine_distance() {
+        let paris = GeoPoint {
+            lat: 48.8566,
+            lon: 2.3522,
+        };
+        let km = BERLIN.haversine_meters(&paris) / 1000.0;
+        assert!((km - 878.0).abs() < 5.0, "got {km}");
+    }
+
+    #[test]
+    fn test_index_lookup_is_superset_of_matches() {
+        let mut index = GeohashIndex::new("loc", 6);
+        let near = Uuid::new_v4();
+        let far = Uuid::new_v4();
+        index.insert(near, &json!({"loc": {"lat": 52.53, "lon": 13.41}}));
+        index.insert(far, &json!({"loc": {"lat": 48.85, "lon": 2.35}}));
+        index.insert(Uuid::new_v4(), &json!({"other": 1}));
+        assert_eq!(index.len(), 2);
+
+        let candidates = index.candidates_near(BERLIN, 5_000.0);
+        assert!(candidates.contains(&near));
+        assert!(!candidates.contains(&far));
+
+        index.remove(&near);
+        assert!(index.candidates_near(BERLIN, 5_000.0).is_empty());
+    }
+
+    #[test]
+    fn test_antimeridian_bounds() {
+        let mut index = GeohashIndex::new("loc", 5);
+        let fiji = Uuid::new_v4();
+        index.insert(fiji, &json!({"loc": {"lat": -17.7, "lon": 178.0}}));
+
+        let bounds = GeoBounds {
+            min_lat: -20.0,
+            min_lon: 175.0,
+            max_lat: -15.0,
+            max_lon: -175.0,
+        };
+        assert!(bounds.contains(&GeoPoint {
+            lat: -17.7,
+            lon: 178.0
+        }));
+        assert!(index.candidates_within(&bounds).contains(&fiji));
+    }
+}
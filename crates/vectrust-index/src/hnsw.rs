@@ -28,11 +28,12 @@ impl Eq for SearchCandidate {}
 
 impl Ord for SearchCandidate {
     fn cmp(&self, other: &Self) -> Ordering {
-        // Reverse ordering for min-heap behavior
+        // Reverse ordering for min-heap behavior; equal distances by id so
+        // ties resolve the same way every run
         other
             .distance
-            .partial_cmp(&self.distance)
-            .unwrap_or(Ordering::Equal)
+            .total_cmp(&self.distance)
+            .then_with(|| other.id.cmp(&self.id))
     }
 }
 
@@ -44,6 +45,8 @@ impl PartialOrd for SearchCandidate {
 
 pub struct HnswIndex {
     config: HnswConfig,
+    /// The configured metric, with its function when it is a custom one
+    distance: Distance,
     nodes: HashMap<Uuid, HnswNode>,
     entry_point: Option<Uuid>,
     #[allow(dead_code)]
@@ -54,6 +57,7 @@ pub struct HnswIndex {
 impl HnswIndex {
     pub fn new(config: HnswConfig) -> Result<Self> {
         Ok(Self {
+            distance: config.distance_metric.clone().into(),
             config,
             nodes: HashMap::new(),
             entry_point: None,
@@ -62,6 +66,13 @@ impl HnswIndex {
         })
     }
 
+    /// Compute the configured metric with `distance`, which carries a
+    /// custom metric's function (see [`DistanceRegistry::resolve`])
+    pub fn with_distance(mut self, distance: Distance) -> Self {
+        self.distance = distance;
+        self
+    }
+
     /// Generate random level for new node using exponential decay
     fn get_random_level(&self) -> usize {
         let mut level = 0;
@@ -78,16 +89,19 @@ impl HnswIndex {
             DistanceMetric::Cosine => 1.0 - VectorOps::cosine_similarity(a, b), // Convert similarity to distance
             DistanceMetric::Euclidean => VectorOps::euclidean_distance(a, b),
             DistanceMetric::DotProduct => -VectorOps::dot_product(a, b), // Convert dot product to distance (negate)
+            DistanceMetric::Custom(_) => self.distance.distance(a, b),
         }
     }
 
-    /// Search for closest nodes at a specific level
+    /// Search for closest nodes at a specific level, expanding no node
+    /// farther than `max_distance`
     fn search_layer(
         &self,
         query: &[f32],
         entry_points: &[Uuid],
         num_closest: usize,
         level: usize,
+        max_distance: f32,
     ) -> Vec<SearchCandidate> {
         let mut visited = HashSet::new();
         let mut candidates = BinaryHeap::new();
@@ -103,6 +117,10 @@ impl HnswIndex {
         );
 
         while let Some(current) = candidates.pop() {
+            // Candidates come nearest first, so the rest are out of bounds too
+            if current.distance > max_distance {
+                break;
+            }
             // Check if we should stop searching
             if self.should_stop_search(&current, &w, num_closest) {
                 break;
@@ -223,7 +241,11 @@ impl HnswIndex {
     fn maintain_candidate_limit(&self, w: &mut BinaryHeap<SearchCandidate>, num_closest: usize) {
         if w.len() > num_closest {
             let mut temp_vec: Vec<_> = w.clone().into_iter().collect();
-            temp_vec.sort_by(|a, b| a.distance.partial_cmp(&b.distance).unwrap());
+            temp_vec.sort_by(|a, b| {
+                a.distance
+                    .total_cmp(&b.distance)
+                    .then_with(|| a.id.cmp(&b.id))
+            });
             temp_vec.truncate(num_closest);
             *w = temp_vec.into_iter().collect();
         }
@@ -235,62 +257,59 @@ impl HnswIndex {
         num_closest: usize,
     ) -> Vec<SearchCandidate> {
         let mut result: Vec<_> = w.into_iter().collect();
-        result.sort_by(|a, b| a.distance.partial_cmp(&b.distance).unwrap());
+        result.sort_by(|a, b| {
+            a.distance
+                .total_cmp(&b.distance)
+                .then_with(|| a.id.cmp(&b.id))
+        });
         result.truncate(num_closest);
         result
     }
 
-    /// Select diverse neighbors using heuristic
-    fn select_neighbors(&self, candidates: Vec<SearchCandidate>, m: usize) -> Vec<Uuid> {
+    /// Select up to `m` neighbors from `candidates`, whose distances are to
+    /// the node being connected. Closer candidates come first, but one is
+    /// skipped when it is nearer to an already selected neighbor than to the
+    /// node, so the links point in different directions; skipped ones fill
+    /// any remaining slots.
+    fn select_neighbors(&self, mut candidates: Vec<SearchCandidate>, m: usize) -> Vec<Uuid> {
         if candidates.len() <= m {
             return candidates.into_iter().map(|c| c.id).collect();
         }
-
-        let mut selected = Vec::new();
-        let mut remaining = candidates;
-
-        // Always select the closest
-        if let Some(closest) = remaining.first() {
-            selected.push(closest.id);
-            remaining.remove(0);
-        }
-
-        // Select diverse neighbors
-        while selected.len() < m && !remaining.is_empty() {
-            let mut best_idx = 0;
-            let mut best_score = f32::NEG_INFINITY;
-
-            for (i, candidate) in remaining.iter().enumerate() {
-                if let Some(candidate_node) = self.nodes.get(&candidate.id) {
-                    // Calculate diversity score (distance to already selected)
-                    let mut min_dist_to_selected = f32::INFINITY;
-                    for &selected_id in &selected {
-                        if let Some(selected_node) = self.nodes.get(&selected_id) {
-                            let dist = self
-                                .calculate_distance(&candidate_node.vector, &selected_node.vector);
-                            min_dist_to_selected = min_dist_to_selected.min(dist);
-                        }
-                    }
-
-                    // Score combines closeness to query and diversity
-                    let score = min_dist_to_selected - candidate.distance;
-                    if score > best_score {
-                        best_score = score;
-                        best_idx = i;
-                    }
-                }
+        candidates.sort_by(|a, b| {
+            a.distance
+                .total_cmp(&b.distance)
+                .then_with(|| a.id.cmp(&b.id))
+        });
+
+        let mut selected: Vec<Uuid> = Vec::with_capacity(m);
+        let mut skipped = Vec::new();
+        for candidate in candidates {
+            if selected.len() == m {
+                break;
+            }
+            let Some(node) = self.nodes.get(&candidate.id) else {
+                continue;
+            };
+            let diverse = selected.iter().all(|selected_id| {
+                self.nodes.get(selected_id).is_none_or(|selected_node| {
+                    self.calculate_distance(&node.vector, &selected_node.vector)
+                        > candidate.distance
+                })
+            });
+            if diverse {
+                selected.push(candidate.id);
+            } else {
+                skipped.push(candidate.id);
             }
-
-            let selected_candidate = remaining.remove(best_idx);
-            selected.push(selected_candidate.id);
         }
-
+        let missing = m - selected.len();
+        selected.extend(skipped.into_iter().take(missing));
         selected
     }
 
     pub fn insert(&mut self, id: Uuid, vector: &[f32]) -> Result<()> {
         let level = self.get_random_level();
-        let mut node = HnswNode {
+        let node = HnswNode {
             id,
             vector: vector.to_vec(),
             level,
@@ -311,25 +330,39 @@ impl HnswIndex {
         // Search from top level down to level+1
         for lc in (level + 1..=self.max_level).rev() {
             current_closest = self
-                .search_layer(vector, &current_closest, 1, lc)
+                .search_layer(vector, &current_closest, 1, lc, f32::INFINITY)
                 .into_iter()
                 .map(|c| c.id)
                 .collect();
         }
 
+        // Stored before linking, so pruning a neighbor's full list can
+        // weigh the new node against its other links. Searches at each
+        // level run before the node is linked on it, so none can reach it
+        // except through links left from an earlier insert of the same id.
+        self.nodes.insert(id, node);
+
         // Search and connect from level down to 0
         for lc in (0..=level).rev() {
-            let candidates =
-                self.search_layer(vector, &current_closest, self.config.ef_construction, lc);
+            let mut candidates = self.search_layer(
+                vector,
+                &current_closest,
+                self.config.ef_construction,
+                lc,
+                f32::INFINITY,
+            );
+            candidates.retain(|c| c.id != id);
             let m = if lc == 0 {
-                self.config.max_connections
-            } else {
                 self.config.max_connections_layer0
+            } else {
+                self.config.max_connections
             };
             let selected_neighbors = self.select_neighbors(candidates.clone(), m);
 
             // Connect new node to selected neighbors
-            node.connections[lc] = selected_neighbors.clone();
+            if let Some(node) = self.nodes.get_mut(&id) {
+                node.connections[lc] = selected_neighbors.clone();
+            }
 
             // Connect selected neighbors back to new node
             for &neighbor_id in &selected_neighbors {
@@ -380,14 +413,37 @@ impl HnswIndex {
             self.max_level = level;
         }
 
-        self.nodes.insert(id, node);
         Ok(())
     }
 
     pub fn search(&self, query: &[f32], k: usize) -> Result<Vec<(Uuid, f32)>> {
+        self.search_within(query, k, f32::INFINITY)
+    }
+
+    /// Up to `k` nearest nodes no farther than `max_distance`. The bottom
+    /// layer stops expanding at the first candidate beyond it, so a query
+    /// with nothing close finishes early; like any graph walk this is
+    /// approximate. Asking for every node skips the walk, which would
+    /// visit them all anyway, and ranks them exactly.
+    pub fn search_within(
+        &self,
+        query: &[f32],
+        k: usize,
+        max_distance: f32,
+    ) -> Result<Vec<(Uuid, f32)>> {
         if self.entry_point.is_none() {
             return Ok(Vec::new());
         }
+        if k >= self.nodes.len() {
+            let mut results: Vec<(Uuid, f32)> = self
+                .nodes
+                .values()
+                .map(|node| (node.id, self.calculate_distance(query, &node.vector)))
+                .filter(|(_, distance)| *distance <= max_distance)
+                .collect();
+            results.sort_by(|a, b| a.1.total_cmp(&b.1).then_with(|| a.0.cmp(&b.0)));
+            return Ok(results);
+        }
 
         let entry_point = self.entry_point.unwrap();
         let mut current_closest = vec![entry_point];
@@ -395,7 +451,7 @@ impl HnswIndex {
         // Search from top level down to level 1
         for lc in (1..=self.max_level).rev() {
             current_closest = self
-                .search_layer(query, &current_closest, 1, lc)
+                .search_layer(query, &current_closest, 1, lc, f32::INFINITY)
                 .into_iter()
                 .map(|c| c.id)
                 .collect();
@@ -403,16 +459,17 @@ impl HnswIndex {
 
         // Search level 0 with ef parameter
         let ef = self.config.ef_search.max(k);
-        let candidates = self.search_layer(query, &current_closest, ef, 0);
+        let candidates = self.search_layer(query, &current_closest, ef, 0, max_distance);
 
         let mut results: Vec<_> = candidates
             .into_iter()
+            .filter(|c| c.distance <= max_distance)
             .take(k)
             .map(|c| (c.id, c.distance))
             .collect();
 
         // Sort by distance (best first)
-        results.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
+        results.sort_by(|a, b| a.1.total_cmp(&b.1).then_with(|| a.0.cmp(&b.0)));
 
         Ok(results)
     }
@@ -483,4 +540,114 @@ mod tests {
             assert_eq!(closest_id, id1);
         }
     }
+
+    #[test]
+    fn test_hnsw_recall() {
+        let mut index = HnswIndex::new(HnswConfig::default()).unwrap();
+        let mut state = 0x9e37_79b9_7f4a_7c15u64;
+        let mut random_vector = || -> Vec<f32> {
+            (0..16)
+                .map(|_| {
+                    state ^= state << 13;
+                    state ^= state >> 7;
+                    state ^= state << 17;
+                    (state % 2000) as f32 / 1000.0 - 1.0
+                })
+                .collect()
+        };
+        let vectors: Vec<Vec<f32>> = (0..1000).map(|_| random_vector()).collect();
+        for (i, vector) in vectors.iter().enumerate() {
+            index.insert(Uuid::from_u128(i as u128), vector).unwrap();
+        }
+
+        // Most of each query's true 10 nearest neighbors come back
+        let k = 10;
+        let queries: Vec<Vec<f32>> = (0..50).map(|_| random_vector()).collect();
+        let mut found = 0;
+        for query in &queries {
+            let mut exact: Vec<(usize, f32)> = vectors
+                .iter()
+                .enumerate()
+                .map(|(i, v)| (i, 1.0 - VectorOps::cosine_similarity(query, v)))
+                .collect();
+            exact.sort_by(|a, b| a.1.total_cmp(&b.1));
+            let results = index.search(query, k).unwrap();
+            found += exact[..k]
+                .iter()
+                .filter(|(i, _)| {
+                    results
+                        .iter()
+                        .any(|(id, _)| *id == Uuid::from_u128(*i as u128))
+                })
+                .count();
+        }
+        let recall = found as f32 / (k * queries.len()) as f32;
+        assert!(recall >= 0.9, "recall@{} was {}", k, recall);
+    }
+
+    #[test]
+    fn test_hnsw_finds_every_item() {
+        let mut index = HnswIndex::new(HnswConfig::default()).unwrap();
+        let mut state = 0x2545_f491_4f6c_dd1du64;
+        let vectors: Vec<Vec<f32>> = (0..300)
+            .map(|_| {
+                (0..8)
+                    .map(|_| {
+                        state ^= state << 13;
+                        state ^= state >> 7;
+                        state ^= state << 17;
+                        (state % 2000) as f32 / 1000.0 - 1.0
+                    })
+                    .collect()
+            })
+            .collect();
+        for (i, vector) in vectors.iter().enumerate() {
+            index.insert(Uuid::from_u128(i as u128), vector).unwrap();
+        }
+
+        // Nodes whose neighbor lists filled up early must still be linked to
+        // from later ones, or searches can never reach them
+        for (i, vector) in vectors.iter().enumerate() {
+            let results = index.search(vector, 1).unwrap();
+            assert_eq!(results[0].0, Uuid::from_u128(i as u128));
+        }
+    }
+
+    #[test]
+    fn test_hnsw_search_within() {
+        let mut index = HnswIndex::new(HnswConfig::default()).unwrap();
+        for i in 0..50 {
+            let angle = i as f32 * 0.03;
+            index
+                .insert(Uuid::from_u128(i), &[angle.cos(), angle.sin()])
+                .unwrap();
+        }
+
+        // Only nodes within the bound come back, however many were asked for
+        let results = index.search_within(&[1.0, 0.0], 50, 0.01).unwrap();
+        assert!(!results.is_empty() && results.len() < 50);
+        assert!(results.iter().all(|(_, distance)| *distance <= 0.01));
+        assert_eq!(results[0].0, Uuid::from_u128(0));
+
+        // Nothing near the query means nothing at all
+        let results = index.search_within(&[-1.0, 0.0], 10, 0.5).unwrap();
+        assert!(results.is_empty());
+    }
+
+    #[test]
+    fn test_hnsw_large_k_ranks_every_node() {
+        let mut index = HnswIndex::new(HnswConfig::default()).unwrap();
+        for i in 0..50 {
+            let angle = i as f32 * 0.1;
+            index
+                .insert(Uuid::from_u128(i), &[angle.cos(), angle.sin()])
+                .unwrap();
+        }
+
+        let results = index.search(&[1.0, 0.0], 1000).unwrap();
+        assert_eq!(results.len(), 50);
+        assert!(results.windows(2).all(|w| w[0].1 <= w[1].1));
+        assert_eq!(results[0].0, Uuid::from_u128(0));
+        assert_eq!(index.search_within(&[1.0, 0.0], 50, 0.01).unwrap().len(), 2);
+    }
 }
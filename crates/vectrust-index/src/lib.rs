@@ -2,9 +2,13 @@
 // SPDX-License-Identifier: Apache-2.0
 
 pub mod flat;
+pub mod geo;
+#[cfg(feature = "ann")]
 pub mod hnsw;
 pub mod quantized;
 
 pub use flat::*;
+pub use geo::*;
+#[cfg(feature = "ann")]
 pub use hnsw::*;
 pub use quantized::*;
@@ -8,9 +8,27 @@ export declare class LocalIndex {
   constructor(folderPath: string, indexName?: string | undefined | null)
   createIndex(config?: string | undefined | null): Promise<void>
   isIndexCreated(): Promise<boolean>
-  insertItem(itemJson: string): Promise<string>
+  /**
+   * Insert an item; options '{"onConflict": "replace"}' overwrite one
+   * already stored under its id instead of failing
+   */
+  insertItem(itemJson: string, options?: string | undefined | null): Promise<string>
   getItem(id: string): Promise<string | null>
-  queryItems(vector: Array<number>, topK?: number | undefined | null, filter?: string | undefined | null): Promise<string>
+  queryItems(vector: Array<number>, topK?: number | undefined | null, filter?: string | undefined | null, options?: string | undefined | null): Promise<string>
+  /**
+   * Items for `ids` in one read, as a JSON array with `null` for each id
+   * that isn't stored
+   */
+  getItems(ids: Array<string>): Promise<string>
+  /** Build a keyword index, e.g. '{"fields": ["title"], "tokenizer": {"stem": true}}' */
+  enableTextIndex(config: string): Promise<void>
+  textSearch(text: string, topK?: number | undefined | null, filter?: string | undefined | null): Promise<string>
+  /** Save a query by name, e.g. '{"filter": {"tenant": "acme"}, "topK": 20}' */
+  saveNamedQuery(name: string, query: string): Promise<void>
+  /** Saved queries as a JSON object keyed by name */
+  listNamedQueries(): Promise<string>
+  deleteNamedQuery(name: string): Promise<boolean>
+  runNamedQuery(name: string, vector?: Array<number> | undefined | null, topK?: number | undefined | null): Promise<string>
   deleteItem(id: string): Promise<void>
   listItems(options?: string | undefined | null): Promise<string>
   beginUpdate(): Promise<void>
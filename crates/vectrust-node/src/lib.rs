@@ -8,10 +8,51 @@ use std::sync::Mutex as StdMutex;
 use tokio::sync::Mutex;
 use uuid::Uuid;
 use vectrust::{
-    CreateIndexConfig, GraphIndex as RustGraphIndex, GraphValue, ListOptions,
-    LocalIndex as RustLocalIndex, VectorItem,
+    CreateIndexConfig, Freshness, GraphIndex as RustGraphIndex, GraphValue, ListOptions,
+    LocalIndex as RustLocalIndex, NamedQuery, OnConflict, Query, RecencyBoost, ScoreBoost,
+    TextIndexConfig, VectorItem,
 };
 
+/// Score tuning accepted as JSON by `queryItems`
+#[derive(Default, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct QueryOptions {
+    #[serde(default)]
+    boosts: Vec<ScoreBoost>,
+    #[serde(default)]
+    recency: Option<RecencyBoost>,
+    /// Keyword query fused with vector similarity when a text index is enabled
+    #[serde(default)]
+    text: Option<String>,
+    /// Drop results scoring below this
+    #[serde(default)]
+    min_score: Option<f32>,
+    /// `"fast"` to skip items not yet added to the HNSW graph
+    #[serde(default)]
+    freshness: Freshness,
+    /// Return only each result's id and score, for `getItems` to fetch
+    /// the items the caller goes on to need
+    #[serde(default)]
+    ids_only: bool,
+}
+
+/// Write options accepted as JSON by `insertItem`
+#[derive(Default, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct InsertOptions {
+    /// `"replace"` to overwrite an item already stored under the id
+    /// instead of failing
+    #[serde(default)]
+    on_conflict: OnConflict,
+}
+
+/// A result of `queryItems` with `idsOnly` set
+#[derive(serde::Serialize)]
+struct QueryHit {
+    id: Uuid,
+    score: f32,
+}
+
 /// Node.js binding for LocalIndex
 #[napi]
 pub struct LocalIndex {
@@ -53,14 +94,22 @@ impl LocalIndex {
         Ok(index.is_index_created().await)
     }
 
+    /// Insert an item; options '{"onConflict": "replace"}' overwrite one
+    /// already stored under its id instead of failing
     #[napi]
-    pub async fn insert_item(&self, item_json: String) -> Result<String> {
+    pub async fn insert_item(&self, item_json: String, options: Option<String>) -> Result<String> {
         let vector_item: VectorItem =
             serde_json::from_str(&item_json).map_err(|e| Error::from_reason(e.to_string()))?;
 
+        let options: InsertOptions = if let Some(options_str) = options {
+            serde_json::from_str(&options_str).map_err(|e| Error::from_reason(e.to_string()))?
+        } else {
+            InsertOptions::default()
+        };
+
         let index = self.inner.lock().await;
         let result = index
-            .insert_item(vector_item)
+            .insert_item_on_conflict(vector_item, options.on_conflict)
             .await
             .map_err(|e| Error::from_reason(e.to_string()))?;
 
@@ -93,6 +142,7 @@ impl LocalIndex {
         vector: Vec<f64>,
         top_k: Option<u32>,
         filter: Option<String>,
+        options: Option<String>,
     ) -> Result<String> {
         // Convert f64 to f32 for compatibility
         let vector: Vec<f32> = vector.into_iter().map(|v| v as f32).collect();
@@ -105,9 +155,137 @@ impl LocalIndex {
             None
         };
 
+        // e.g. '{"boosts": [{"field": "popularity", "weight": 0.1}], "recency": {"halfLifeDays": 7}}'
+        let options: QueryOptions = if let Some(options_str) = options {
+            serde_json::from_str(&options_str).map_err(|e| Error::from_reason(e.to_string()))?
+        } else {
+            QueryOptions::default()
+        };
+
+        let query = Query {
+            vector: Some(vector),
+            top_k: top_k.unwrap_or(10) as usize,
+            filter,
+            text: options.text,
+            boosts: options.boosts,
+            recency: options.recency,
+            min_score: options.min_score,
+            freshness: options.freshness,
+            ..Default::default()
+        };
+
+        let index = self.inner.lock().await;
+        let results = index
+            .execute_query(&query, None)
+            .await
+            .map_err(|e| Error::from_reason(e.to_string()))?;
+
+        if options.ids_only {
+            let hits: Vec<QueryHit> = results
+                .iter()
+                .map(|result| QueryHit {
+                    id: result.item.id,
+                    score: result.score,
+                })
+                .collect();
+            return serde_json::to_string(&hits).map_err(|e| Error::from_reason(e.to_string()));
+        }
+        serde_json::to_string(&results).map_err(|e| Error::from_reason(e.to_string()))
+    }
+
+    /// Items for `ids` in one read, as a JSON array with `null` for each id
+    /// that isn't stored
+    #[napi]
+    pub async fn get_items(&self, ids: Vec<String>) -> Result<String> {
+        let ids = ids
+            .iter()
+            .map(|id| Uuid::parse_str(id).map_err(|e| Error::from_reason(e.to_string())))
+            .collect::<Result<Vec<_>>>()?;
+
+        let index = self.inner.lock().await;
+        let items = index
+            .get_items(&ids)
+            .await
+            .map_err(|e| Error::from_reason(e.to_string()))?;
+
+        serde_json::to_string(&items).map_err(|e| Error::from_reason(e.to_string()))
+    }
+
+    /// Build a keyword index, e.g. '{"fields": ["title"], "tokenizer": {"stem": true}}'
+    #[napi]
+    pub async fn enable_text_index(&self, config: String) -> Result<()> {
+        let config: TextIndexConfig =
+            serde_json::from_str(&config).map_err(|e| Error::from_reason(e.to_string()))?;
+
+        let index = self.inner.lock().await;
+        index
+            .enable_text_index(config)
+            .await
+            .map_err(|e| Error::from_reason(e.to_string()))
+    }
+
+    #[napi]
+    pub async fn text_search(
+        &self,
+        text: String,
+        top_k: Option<u32>,
+        filter: Option<String>,
+    ) -> Result<String> {
+        let filter = match filter {
+            Some(filter_str) => Some(
+                serde_json::from_str(&filter_str).map_err(|e| Error::from_reason(e.to_string()))?,
+            ),
+            None => None,
+        };
+
+        let index = self.inner.lock().await;
+        let results = index
+            .text_search(&text, top_k, filter)
+            .await
+            .map_err(|e| Error::from_reason(e.to_string()))?;
+
+        serde_json::to_string(&results).map_err(|e| Error::from_reason(e.to_string()))
+    }
+
+    /// Save a query by name, e.g. '{"filter": {"tenant": "acme"}, "topK": 20}'
+    #[napi]
+    pub async fn save_named_query(&self, name: String, query: String) -> Result<()> {
+        let query: NamedQuery =
+            serde_json::from_str(&query).map_err(|e| Error::from_reason(e.to_string()))?;
+
+        let index = self.inner.lock().await;
+        index
+            .save_named_query(&name, query)
+            .map_err(|e| Error::from_reason(e.to_string()))
+    }
+
+    /// Saved queries as a JSON object keyed by name
+    #[napi]
+    pub async fn list_named_queries(&self) -> Result<String> {
+        let index = self.inner.lock().await;
+        serde_json::to_string(&index.named_queries()).map_err(|e| Error::from_reason(e.to_string()))
+    }
+
+    #[napi]
+    pub async fn delete_named_query(&self, name: String) -> Result<bool> {
+        let index = self.inner.lock().await;
+        index
+            .delete_named_query(&name)
+            .map_err(|e| Error::from_reason(e.to_string()))
+    }
+
+    #[napi]
+    pub async fn run_named_query(
+        &self,
+        name: String,
+        vector: Option<Vec<f64>>,
+        top_k: Option<u32>,
+    ) -> Result<String> {
+        let vector: Option<Vec<f32>> = vector.map(|v| v.into_iter().map(|x| x as f32).collect());
+
         let index = self.inner.lock().await;
         let results = index
-            .query_items(vector, top_k, filter)
+            .run_named_query(&name, vector, top_k, None)
             .await
             .map_err(|e| Error::from_reason(e.to_string()))?;
 
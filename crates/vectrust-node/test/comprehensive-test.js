@@ -113,6 +113,17 @@ async function runComprehensiveTests() {
             const result = queryResults[i];
             console.log(`   ${i + 1}. ${result.item.metadata.name} (score: ${result.score.toFixed(4)})`);
         }
+
+        // Ids and scores only, with the items fetched in one batch
+        const hits = JSON.parse(await index.queryItems(queryVector, 5, null, JSON.stringify({ idsOnly: true })));
+        if (hits.length !== queryResults.length || hits[0].item !== undefined || hits[0].id !== queryResults[0].item.id) {
+            throw new Error('idsOnly query returned unexpected results');
+        }
+        const hydrated = JSON.parse(await index.getItems([...hits.map(hit => hit.id), uuidv4()]));
+        if (hydrated.length !== hits.length + 1 || hydrated[0].id !== hits[0].id || hydrated[hits.length] !== null) {
+            throw new Error('getItems returned unexpected items');
+        }
+        console.log(`✅ idsOnly query hydrated ${hits.length} items with one getItems call`);
         
         // Test 5: List operations with pagination
         console.log('\n5️⃣ Testing list operations...');
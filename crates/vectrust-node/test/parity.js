@@ -0,0 +1,182 @@
+// Copyright 2024-2026 Andrey Vasilevsky <anvanster@gmail.com>
+// SPDX-License-Identifier: Apache-2.0
+
+// Runs the shared parity script (tests/fixtures/parity/script.json) against
+// this binding and, when it is installed, vectra-enhanced, checks each step's
+// expected result and diffs the two implementations against each other.
+// The Rust API runs the same script in tests/integration/parity_test.rs.
+//
+//   npm run test:parity
+
+const fs = require('fs');
+const os = require('os');
+const path = require('path');
+
+const scriptPath = path.join(__dirname, '../../../tests/fixtures/parity/script.json');
+const script = JSON.parse(fs.readFileSync(scriptPath, 'utf8'));
+const epsilon = script.epsilon;
+
+// Both adapters return plain objects: items as { id, vector, metadata },
+// query results as [{ id, score }], listings as sorted id arrays
+function normalizeItem(item) {
+  if (!item) return null;
+  return { id: item.id, vector: Array.from(item.vector), metadata: item.metadata };
+}
+
+function vectrustAdapter(folder) {
+  const { LocalIndex } = require('../index.js');
+  const index = new LocalIndex(folder, 'index.json');
+  return {
+    name: 'vectrust',
+    legacy: false,
+    createIndex: () => index.createIndex(null),
+    beginUpdate: () => index.beginUpdate(),
+    endUpdate: () => index.endUpdate(),
+    insertItem: (item) => index.insertItem(JSON.stringify({ ...item, deleted: false })),
+    deleteItem: (id) => index.deleteItem(id),
+    getItem: async (id) => {
+      const item = await index.getItem(id);
+      return item ? normalizeItem(JSON.parse(item)) : null;
+    },
+    listItems: async () => JSON.parse(await index.listItems(null)).map((item) => item.id).sort(),
+    queryItems: async (vector, topK, filter) => {
+      const results = await index.queryItems(vector, topK, filter ? JSON.stringify(filter) : null, null);
+      return JSON.parse(results).map((r) => ({ id: r.item.id, score: r.score }));
+    },
+  };
+}
+
+function vectraAdapter(folder) {
+  let vectra;
+  try {
+    vectra = require('vectra-enhanced');
+  } catch (e) {
+    return null;
+  }
+  const index = new vectra.LocalIndex(folder);
+  return {
+    name: 'vectra-enhanced',
+    legacy: true,
+    createIndex: () => index.createIndex(),
+    beginUpdate: () => index.beginUpdate(),
+    endUpdate: () => index.endUpdate(),
+    insertItem: (item) => index.insertItem(item),
+    deleteItem: (id) => index.deleteItem(id),
+    getItem: async (id) => normalizeItem(await index.getItem(id)),
+    listItems: async () => (await index.listItems()).map((item) => item.id).sort(),
+    queryItems: async (vector, topK, filter) => {
+      // Newer releases take a text query for BM25 before topK
+      const results = index.queryItems.length >= 4
+        ? await index.queryItems(vector, '', topK, filter)
+        : await index.queryItems(vector, topK, filter);
+      return results.map((r) => ({ id: r.item.id, score: r.score }));
+    },
+  };
+}
+
+async function runStep(impl, step) {
+  switch (step.op) {
+    case 'createIndex': return impl.createIndex();
+    case 'beginUpdate': return impl.beginUpdate();
+    case 'endUpdate': return impl.endUpdate();
+    case 'insertItem': return void (await impl.insertItem(step.item));
+    case 'deleteItem': return impl.deleteItem(step.id);
+    case 'getItem': return impl.getItem(step.id);
+    case 'listItems': return impl.listItems();
+    case 'queryItems': return impl.queryItems(step.vector, step.topK, step.filter);
+    default: throw new Error(`unknown op ${step.op}`);
+  }
+}
+
+// Whether two step results agree: ids and metadata exactly, vectors and
+// scores within epsilon, listings as sets
+function same(op, a, b) {
+  if (a === null || b === null || a === undefined || b === undefined) return a == b;
+  switch (op) {
+    case 'getItem':
+      return a.id === b.id
+        && JSON.stringify(sortKeys(a.metadata)) === JSON.stringify(sortKeys(b.metadata))
+        && a.vector.length === b.vector.length
+        && a.vector.every((x, i) => Math.abs(x - b.vector[i]) <= epsilon);
+    case 'listItems':
+      return JSON.stringify([...a].sort()) === JSON.stringify([...b].sort());
+    case 'queryItems':
+      return a.length === b.length
+        && a.every((r, i) => r.id === b[i].id && Math.abs(r.score - b[i].score) <= epsilon);
+    default:
+      return true;
+  }
+}
+
+// Metadata is compared by serialization, so give both sides the same key order
+function sortKeys(value) {
+  if (Array.isArray(value)) return value.map(sortKeys);
+  if (value && typeof value === 'object') {
+    return Object.fromEntries(Object.keys(value).sort().map((k) => [k, sortKeys(value[k])]));
+  }
+  return value;
+}
+
+function describe(outcome) {
+  return outcome.error ? `error: ${outcome.error.message}` : JSON.stringify(outcome.value);
+}
+
+async function main() {
+  const root = fs.mkdtempSync(path.join(os.tmpdir(), 'vectrust-parity-'));
+  const impls = [vectrustAdapter(path.join(root, 'vectrust')), vectraAdapter(path.join(root, 'vectra'))]
+    .filter(Boolean);
+  if (impls.length === 1) {
+    console.log('vectra-enhanced is not installed; checking vectrust against the script only');
+  }
+
+  const failures = [];
+  try {
+    for (const [n, step] of script.steps.entries()) {
+      const outcomes = [];
+      for (const impl of impls) {
+        if (step.only === 'legacy' && !impl.legacy) continue;
+        let outcome;
+        try {
+          outcome = { value: await runStep(impl, step) };
+        } catch (error) {
+          outcome = { error };
+        }
+        outcomes.push({ impl, outcome });
+
+        if (step.expectError) {
+          if (!outcome.error) failures.push(`step ${n} (${step.op}): ${impl.name} expected an error`);
+        } else if (outcome.error) {
+          failures.push(`step ${n} (${step.op}): ${impl.name} ${describe(outcome)}`);
+        } else if ('expect' in step) {
+          const expected = step.op === 'getItem' ? normalizeItem(step.expect) : step.expect;
+          if (!same(step.op, outcome.value, expected)) {
+            failures.push(`step ${n} (${step.op}): ${impl.name} gave ${describe(outcome)}`);
+          }
+        }
+      }
+
+      if (outcomes.length === 2) {
+        const [a, b] = outcomes;
+        const agree = !!a.outcome.error === !!b.outcome.error
+          && (a.outcome.error || same(step.op, a.outcome.value, b.outcome.value));
+        if (!agree) {
+          failures.push(`step ${n} (${step.op}): ${a.impl.name} ${describe(a.outcome)}, `
+            + `${b.impl.name} ${describe(b.outcome)}`);
+        }
+      }
+    }
+  } finally {
+    fs.rmSync(root, { recursive: true, force: true });
+  }
+
+  if (failures.length > 0) {
+    failures.forEach((failure) => console.error(`✗ ${failure}`));
+    process.exit(1);
+  }
+  console.log(`✓ ${script.steps.length} parity steps passed (${impls.map((impl) => impl.name).join(', ')})`);
+}
+
+main().catch((error) => {
+  console.error(error);
+  process.exit(1);
+});
@@ -0,0 +1,323 @@
+// Copyright 2024-2026 Andrey Vasilevsky <anvanster@gmail.com>
+// SPDX-License-Identifier: Apache-2.0
+
+// Typed construction of MongoDB-style metadata filters
+
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+
+/// A metadata filter built in code rather than written as JSON.
+///
+/// Compiles to the format [`MetadataFilter`](crate::MetadataFilter)
+/// evaluates, so it can be passed anywhere a JSON filter is taken:
+///
+/// ```
+/// use vectrust_query::Filter;
+///
+/// let filter = Filter::field("category")
+///     .eq("fruit")
+///     .and(Filter::field("score").gt(0.5));
+/// assert_eq!(
+///     serde_json::Value::from(filter),
+///     serde_json::json!({"$and": [
+///         {"category": {"$eq": "fruit"}},
+///         {"score": {"$gt": 0.5}}
+///     ]})
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(transparent)]
+pub struct Filter(Value);
+
+impl Filter {
+    /// Start a condition on the metadata field `name`; dotted paths reach
+    /// into nested objects
+    pub fn field(name: impl Into<String>) -> FieldFilter {
+        FieldFilter { name: name.into() }
+    }
+
+    /// Match items matching every filter; an empty list matches everything
+    pub fn all(filters: impl IntoIterator<Item = Filter>) -> Self {
+        Self::logical("$and", filters)
+    }
+
+    /// Match items matching at least one filter; an empty list matches nothing
+    pub fn any(filters: impl IntoIterator<Item = Filter>) -> Self {
+        Self::logical("$or", filters)
+    }
+
+    /// Match items matching none of the filters
+    pub fn none(filters: impl IntoIterator<Item = Filter>) -> Self {
+        Self::logical("$nor", filters)
+    }
+
+    /// Match items matching both `self` and `other`. Chained calls
+    /// produce a single flat `$and`.
+    pub fn and(self, other: Filter) -> Self {
+        self.chain("$and", other)
+    }
+
+    /// Match items matching `self`, `other` or both. Chained calls
+    /// produce a single flat `$or`.
+    pub fn or(self, other: Filter) -> Self {
+        self.chain("$or", other)
+    }
+
+    /// Match items not matching `self`
+    #[allow(clippy::should_implement_trait)]
+    pub fn not(self) -> Self {
+        Self::none([self])
+    }
+
+    /// Wrap a filter already in JSON form, for conditions the builder
+    /// doesn't cover
+    pub fn raw(filter: Value) -> Self {
+        Self(filter)
+    }
+
+    /// The JSON filter
+    pub fn to_json(&self) -> Value {
+        self.0.clone()
+    }
+
+    /// Consume the builder, returning the JSON filter
+    pub fn into_json(self) -> Value {
+        self.0
+    }
+
+    fn logical(operator: &str, filters: impl IntoIterator<Item = Filter>) -> Self {
+        let clauses: Vec<Value> = filters.into_iter().map(Filter::into_json).collect();
+        Self(json!({ operator: clauses }))
+    }
+
+    /// Append `other` to `self` when it is already a lone `operator` clause
+    fn chain(self, operator: &str, other: Filter) -> Self {
+        match self.0 {
+            Value::Object(mut map) if map.len() == 1 => match map.get_mut(operator) {
+                Some(Value::Array(clauses)) => {
+                    clauses.push(other.0);
+                    Self(Value::Object(map))
+                }
+                _ => Self::logical(operator, [Self(Value::Object(map)), other]),
+            },
+            value => Self::logical(operator, [Self(value), other]),
+        }
+    }
+}
+
+impl From<Filter> for Value {
+    fn from(filter: Filter) -> Self {
+        filter.0
+    }
+}
+
+/// A metadata field awaiting its condition; see [`Filter::field`]
+#[derive(Debug, Clone)]
+pub struct FieldFilter {
+    name: String,
+}
+
+impl FieldFilter {
+    /// Field equals `value`
+    pub fn eq(self, value: impl Into<Value>) -> Filter {
+        self.operator("$eq", value.into())
+    }
+
+    /// Field is missing or differs from `value`
+    pub fn ne(self, value: impl Into<Value>) -> Filter {
+        self.operator("$ne", value.into())
+    }
+
+    /// Field is greater than `value`
+    pub fn gt(self, value: impl Into<Value>) -> Filter {
+        self.operator("$gt", value.into())
+    }
+
+    /// Field is greater than or equal to `value`
+    pub fn gte(self, value: impl Into<Value>) -> Filter {
+        self.operator("$gte", value.into())
+    }
+
+    /// Field is less than `value`
+    pub fn lt(self, value: impl Into<Value>) -> Filter {
+        self.operator("$lt", value.into())
+    }
+
+    /// Field is less than or equal to `value`
+    pub fn lte(self, value: impl Into<Value>) -> Filter {
+        self.operator("$lte", value.into())
+    }
+
+    /// Field is in `min..max`, including `min` and excluding `max`
+    pub fn between(self, min: impl Into<Value>, max: impl Into<Value>) -> Filter {
+        self.conditions(json!({ "$gte": min.into(), "$lt": max.into() }))
+    }
+
+    /// Field equals one of `values`
+    pub fn is_in<V: Into<Value>>(self, values: impl IntoIterator<Item = V>) -> Filter {
+        self.operator("$in", Self::list(values))
+    }
+
+    /// Field is missing or equals none of `values`
+    pub fn not_in<V: Into<Value>>(self, values: impl IntoIterator<Item = V>) -> Filter {
+        self.operator("$nin", Self::list(values))
+    }
+
+    /// Field is present, or absent when `exists` is false
+    pub fn exists(self, exists: bool) -> Filter {
+        self.operator("$exists", Value::Bool(exists))
+    }
+
+    /// Field is a string starting with `prefix`
+    pub fn starts_with(self, prefix: impl Into<String>) -> Filter {
+        self.operator("$startsWith", Value::String(prefix.into()))
+    }
+
+    /// Field is a string containing `text`
+    pub fn contains(self, text: impl Into<String>) -> Filter {
+        self.operator("$contains", Value::String(text.into()))
+    }
+
+    /// Field is a string matching the regular expression `pattern`
+    pub fn regex(self, pattern: impl Into<String>) -> Filter {
+        self.operator("$regex", Value::String(pattern.into()))
+    }
+
+    /// Field is an array holding every one of `values`
+    pub fn contains_all<V: Into<Value>>(self, values: impl IntoIterator<Item = V>) -> Filter {
+        self.operator("$all", Self::list(values))
+    }
+
+    /// Field is an array of `len` elements
+    pub fn size(self, len: usize) -> Filter {
+        self.operator("$size", Value::from(len))
+    }
+
+    /// Field is an array with an object element matching `filter`. For
+    /// arrays of plain values, pass operators on the element as
+    /// [`Filter::raw`], like `json!({"$gte": 0.8})`.
+    pub fn elem_match(self, filter: Filter) -> Filter {
+        self.operator("$elemMatch", filter.into_json())
+    }
+
+    /// Field is a `{"lat", "lon"}` point within `radius_meters` of the given one
+    pub fn near(self, lat: f64, lon: f64, radius_meters: f64) -> Filter {
+        self.operator(
+            "$near",
+            json!({ "lat": lat, "lon": lon, "radiusMeters": radius_meters }),
+        )
+    }
+
+    /// Field is a `{"lat", "lon"}` point inside the box; `min_lon > max_lon`
+    /// wraps across the antimeridian
+    pub fn within(self, min_lat: f64, min_lon: f64, max_lat: f64, max_lon: f64) -> Filter {
+        self.operator(
+            "$within",
+            json!({
+                "minLat": min_lat,
+                "minLon": min_lon,
+                "maxLat": max_lat,
+                "maxLon": max_lon,
+            }),
+        )
+    }
+
+    fn operator(self, operator: &str, operand: Value) -> Filter {
+        self.conditions(json!({ operator: operand }))
+    }
+
+    fn conditions(self, conditions: Value) -> Filter {
+        let mut filter = Map::new();
+        filter.insert(self.name, conditions);
+        Filter(Value::Object(filter))
+    }
+
+    fn list<V: Into<Value>>(values: impl IntoIterator<Item = V>) -> Value {
+        Value::Array(values.into_iter().map(Into::into).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MetadataFilter;
+
+    #[test]
+    fn test_builder_compiles_to_json() {
+        let filter = Filter::field("category")
+            .eq("fruit")
+            .and(Filter::field("score").gt(0.5))
+            .and(Filter::field("tags").is_in(["red", "green"]));
+        assert_eq!(
+            filter.into_json(),
+            json!({"$and": [
+                {"category": {"$eq": "fruit"}},
+                {"score": {"$gt": 0.5}},
+                {"tags": {"$in": ["red", "green"]}}
+            ]})
+        );
+
+        let filter = Filter::field("a")
+            .exists(true)
+            .or(Filter::field("b").lte(3))
+            .and(Filter::field("c").ne(Value::Null).not());
+        assert_eq!(
+            Value::from(filter),
+            json!({"$and": [
+                {"$or": [{"a": {"$exists": true}}, {"b": {"$lte": 3}}]},
+                {"$nor": [{"c": {"$ne": null}}]}
+            ]})
+        );
+
+        assert_eq!(
+            Filter::field("at").near(51.5, -0.1, 1000.0).into_json(),
+            json!({"at": {"$near": {"lat": 51.5, "lon": -0.1, "radiusMeters": 1000.0}}})
+        );
+        assert_eq!(
+            serde_json::to_value(Filter::field("year").between(2020, 2025)).unwrap(),
+            json!({"year": {"$gte": 2020, "$lt": 2025}})
+        );
+    }
+
+    #[test]
+    fn test_builder_filters_match() {
+        let metadata = json!({
+            "category": "fruit",
+            "score": 0.8,
+            "doc": {"lang": "en"},
+            "at": {"lat": 51.5, "lon": -0.12},
+            "tags": ["red", "ripe"],
+            "reviews": [{"stars": 4}, {"stars": 2}]
+        });
+        let matches = |filter: Filter| MetadataFilter::matches_value(&metadata, &filter.into());
+
+        assert!(matches(
+            Filter::field("category")
+                .eq("fruit")
+                .and(Filter::field("score").gt(0.5))
+        ));
+        assert!(!matches(Filter::field("score").between(0.0, 0.5)));
+        assert!(matches(Filter::field("doc.lang").not_in(["de", "fr"])));
+        assert!(matches(Filter::field("missing").exists(false)));
+        assert!(matches(Filter::field("category").eq("fruit").not().not()));
+        assert!(matches(Filter::any([
+            Filter::field("score").lt(0.1),
+            Filter::field("at").within(51.0, -1.0, 52.0, 1.0),
+        ])));
+        assert!(!matches(Filter::any([])));
+        assert!(matches(Filter::all([])));
+        assert!(matches(Filter::raw(json!({"category": "fruit"}))));
+        assert!(matches(Filter::field("category").starts_with("fr")));
+        assert!(matches(Filter::field("tags").contains_all(["ripe", "red"])));
+        assert!(!matches(Filter::field("tags").size(3)));
+        assert!(matches(
+            Filter::field("reviews").elem_match(Filter::field("stars").gte(4))
+        ));
+        assert!(!matches(
+            Filter::field("reviews").elem_match(Filter::raw(json!({"$gte": 4})))
+        ));
+        assert!(matches(Filter::field("category").contains("ui")));
+        assert!(matches(Filter::field("doc.lang").regex("^(en|de)$")));
+        assert!(!matches(Filter::field("score").contains("0.8")));
+    }
+}
@@ -0,0 +1,479 @@
+// Copyright 2024-2026 Andrey Vasilevsky <anvanster@gmail.com>
+// SPDX-License-Identifier: Apache-2.0
+
+// String query DSL for command lines and URLs
+
+use serde_json::{json, Map, Value};
+use vectrust_core::*;
+
+/// A one-line query split into a metadata filter and keyword text.
+///
+/// `field:value` clauses become filters and everything else is keyword
+/// text, so `category:fruit AND score>0.5 "exact phrase"` parses to the
+/// filter `{"$and": [{"category": "fruit"}, {"score": {"$gt": 0.5}}]}` and
+/// the text `"exact phrase"`.
+///
+/// Field clauses support `:` (or `=`), `!=`, `>`, `>=`, `<` and `<=`.
+/// Values are numbers, `true`/`false`/`null`, bare words or quoted
+/// strings; `field:[a, b]` matches any listed value and `field:*` matches
+/// when the field exists. Single values may also be bracketed, as in
+/// `score>[0.5]`.
+///
+/// Clauses combine with implicit `AND`, `OR`, `NOT`/`-` and parentheses.
+/// Groups under `OR` or `NOT` must be all field clauses or all text,
+/// since the filter and the keyword query are evaluated separately.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct QueryDsl {
+    pub filter: Option<Value>,
+    pub text: Option<String>,
+}
+
+impl QueryDsl {
+    pub fn parse(input: &str) -> Result<Self> {
+        let lexemes = lex(input)?;
+        let mut parser = Parser {
+            lexemes: &lexemes,
+            pos: 0,
+        };
+        let node = parser.parse_or()?;
+        if parser.pos < lexemes.len() {
+            return Err(query_error("Unbalanced ')' in query"));
+        }
+
+        let mut filters = Vec::new();
+        let mut text = Vec::new();
+        if let Some(node) = node {
+            node.split(&mut filters, &mut text)?;
+        }
+
+        Ok(Self {
+            filter: match filters.len() {
+                0 => None,
+                1 => filters.pop(),
+                _ => Some(json!({ "$and": filters })),
+            },
+            text: (!text.is_empty()).then(|| text.join(" ")),
+        })
+    }
+
+    /// Build a [`Query`] from the parsed filter and text
+    pub fn into_query(self, vector: Option<Vec<f32>>, top_k: usize) -> Query {
+        Query {
+            vector,
+            text: self.text,
+            top_k,
+            filter: self.filter,
+            ..Default::default()
+        }
+    }
+}
+
+fn query_error(message: impl Into<String>) -> VectraError {
+    VectraError::Query {
+        message: message.into(),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Lexeme {
+    /// A bare word or `field<op>value` clause, with any quotes kept
+    Word(String),
+    Phrase(String),
+    And,
+    Or,
+    Not,
+    Open,
+    Close,
+}
+
+fn lex(input: &str) -> Result<Vec<Lexeme>> {
+    let mut lexemes = Vec::new();
+    let mut chars = input.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '(' => {
+                chars.next();
+                lexemes.push(Lexeme::Open);
+            }
+            ')' => {
+                chars.next();
+                lexemes.push(Lexeme::Close);
+            }
+            '-' => {
+                chars.next();
+                lexemes.push(Lexeme::Not);
+            }
+            '"' => {
+                chars.next();
+                let phrase: String = chars.by_ref().take_while(|&c| c != '"').collect();
+                lexemes.push(Lexeme::Phrase(phrase));
+            }
+            _ => {
+                // Quotes and brackets inside a word belong to a field value
+                let mut word = String::new();
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() || matches!(c, '(' | ')') {
+                        break;
+                    }
+                    chars.next();
+                    word.push(c);
+                    let close = match c {
+                        '"' => '"',
+                        '[' => ']',
+                        _ => continue,
+                    };
+                    loop {
+                        let Some(c) = chars.next() else {
+                            return Err(query_error(format!("Missing '{close}' in query")));
+                        };
+                        word.push(c);
+                        if c == close {
+                            break;
+                        }
+                    }
+                }
+                lexemes.push(match word.as_str() {
+                    "AND" => Lexeme::And,
+                    "OR" => Lexeme::Or,
+                    "NOT" => Lexeme::Not,
+                    _ => Lexeme::Word(word),
+                });
+            }
+        }
+    }
+
+    Ok(lexemes)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    Filter(Value),
+    Text(String),
+    And(Vec<Node>),
+    Or(Vec<Node>),
+    Not(Box<Node>),
+}
+
+impl Node {
+    /// Collect top-level conjuncts into filter clauses and text fragments
+    fn split(self, filters: &mut Vec<Value>, text: &mut Vec<String>) -> Result<()> {
+        match self {
+            Node::And(children) => {
+                for child in children {
+                    child.split(filters, text)?;
+                }
+            }
+            Node::Filter(filter) => filters.push(filter),
+            Node::Text(fragment) => text.push(fragment),
+            node => match (node.is_filter(), node.is_text()) {
+                (true, _) => filters.push(node.into_filter()),
+                (_, true) => text.push(node.into_text()),
+                _ => {
+                    return Err(query_error(
+                        "Cannot mix field clauses and text under OR or NOT",
+                    ))
+                }
+            },
+        }
+        Ok(())
+    }
+
+    fn children(&self) -> &[Node] {
+        match self {
+            Node::And(children) | Node::Or(children) => children,
+            Node::Not(inner) => std::slice::from_ref(inner),
+            Node::Filter(_) | Node::Text(_) => &[],
+        }
+    }
+
+    fn is_filter(&self) -> bool {
+        match self {
+            Node::Filter(_) => true,
+            Node::Text(_) => false,
+            _ => self.children().iter().all(Node::is_filter),
+        }
+    }
+
+    fn is_text(&self) -> bool {
+        match self {
+            Node::Filter(_) => false,
+            Node::Text(_) => true,
+            _ => self.children().iter().all(Node::is_text),
+        }
+    }
+
+    fn into_filter(self) -> Value {
+        let all = |children: Vec<Node>| -> Vec<Value> {
+            children.into_iter().map(Node::into_filter).collect()
+        };
+        match self {
+            Node::Filter(filter) => filter,
+            Node::And(children) => json!({ "$and": all(children) }),
+            Node::Or(children) => json!({ "$or": all(children) }),
+            Node::Not(inner) => json!({ "$nor": [inner.into_filter()] }),
+            Node::Text(_) => unreachable!("text node in filter"),
+        }
+    }
+
+    /// Render back into [`crate::TextQuery`] syntax
+    fn into_text(self) -> String {
+        let all = |children: Vec<Node>, separator: &str| -> String {
+            let parts: Vec<String> = children.into_iter().map(Node::into_text).collect();
+            format!("({})", parts.join(separator))
+        };
+        match self {
+            Node::Text(fragment) => fragment,
+            Node::And(children) => all(children, " "),
+            Node::Or(children) => all(children, " OR "),
+            Node::Not(inner) => format!("-{}", inner.into_text()),
+            Node::Filter(_) => unreachable!("filter node in text"),
+        }
+    }
+}
+
+struct Parser<'a> {
+    lexemes: &'a [Lexeme],
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Lexeme> {
+        self.lexemes.get(self.pos)
+    }
+
+    fn parse_or(&mut self) -> Result<Option<Node>> {
+        let mut clauses: Vec<Node> = self.parse_and()?.into_iter().collect();
+        while self.peek() == Some(&Lexeme::Or) {
+            self.pos += 1;
+            clauses.extend(self.parse_and()?);
+        }
+        Ok(match clauses.len() {
+            0 => None,
+            1 => clauses.pop(),
+            _ => Some(Node::Or(clauses)),
+        })
+    }
+
+    fn parse_and(&mut self) -> Result<Option<Node>> {
+        let mut clauses = Vec::new();
+        loop {
+            match self.peek() {
+                None | Some(Lexeme::Or) | Some(Lexeme::Close) => break,
+                Some(Lexeme::And) => self.pos += 1,
+                _ => clauses.extend(self.parse_unary()?),
+            }
+        }
+        Ok(match clauses.len() {
+            0 => None,
+            1 => clauses.pop(),
+            _ => Some(Node::And(clauses)),
+        })
+    }
+
+    fn parse_unary(&mut self) -> Result<Option<Node>> {
+        let Some(lexeme) = self.peek().cloned() else {
+            return Ok(None);
+        };
+        self.pos += 1;
+
+        match lexeme {
+            Lexeme::Not => Ok(self.parse_unary()?.map(|n| Node::Not(Box::new(n)))),
+            Lexeme::Open => {
+                let inner = self.parse_or()?;
+                if self.peek() != Some(&Lexeme::Close) {
+                    return Err(query_error("Missing ')' in query"));
+                }
+                self.pos += 1;
+                Ok(inner)
+            }
+            Lexeme::Phrase(phrase) => Ok(Some(Node::Text(format!("\"{phrase}\"")))),
+            Lexeme::Word(word) => Ok(Some(match parse_clause(&word)? {
+                Some(filter) => Node::Filter(filter),
+                None => Node::Text(word),
+            })),
+            Lexeme::And | Lexeme::Or | Lexeme::Close => Ok(None),
+        }
+    }
+}
+
+/// Comparison operators, longest first so `>=` wins over `>`
+const OPERATORS: [&str; 7] = [">=", "<=", "!=", ":", "=", ">", "<"];
+
+/// Parse `field<op>value` into a filter, or `None` for a plain word
+fn parse_clause(word: &str) -> Result<Option<Value>> {
+    let Some(split) = word.find([':', '=', '!', '>', '<']) else {
+        return Ok(None);
+    };
+    let field = &word[..split];
+    let valid_field = !field.is_empty()
+        && field
+            .chars()
+            .all(|c| c.is_alphanumeric() || matches!(c, '_' | '.'));
+    let Some(op) = OPERATORS.iter().find(|op| word[split..].starts_with(**op)) else {
+        return Ok(None);
+    };
+    if !valid_field {
+        return Ok(None);
+    }
+
+    let raw = &word[split + op.len()..];
+    if raw.is_empty() {
+        return Err(query_error(format!("Missing value in '{word}'")));
+    }
+    let condition = match (*op, parse_value(raw)?) {
+        (":" | "=", _) if raw == "*" => json!({ "$exists": true }),
+        (":" | "=", Value::Array(values)) => json!({ "$in": values }),
+        (":" | "=", value) => value,
+        ("!=", Value::Array(values)) => json!({ "$nin": values }),
+        ("!=", value) => json!({ "$ne": value }),
+        (op, value) => {
+            let value = match value {
+                Value::Array(mut values) if values.len() == 1 => values.remove(0),
+                Value::Array(_) => {
+                    return Err(query_error(format!(
+                        "'{op}' takes a single value in '{word}'"
+                    )))
+                }
+                value => value,
+            };
+            let op = match op {
+                ">" => "$gt",
+                ">=" => "$gte",
+                "<" => "$lt",
+                _ => "$lte",
+            };
+            json!({ op: value })
+        }
+    };
+
+    let mut filter = Map::new();
+    filter.insert(field.to_string(), condition);
+    Ok(Some(Value::Object(filter)))
+}
+
+fn parse_value(raw: &str) -> Result<Value> {
+    if let Some(list) = raw.strip_prefix('[').and_then(|r| r.strip_suffix(']')) {
+        return split_list(list)
+            .into_iter()
+            .map(|item| parse_scalar(item.trim()))
+            .collect::<Result<Vec<_>>>()
+            .map(Value::Array);
+    }
+    parse_scalar(raw)
+}
+
+fn parse_scalar(raw: &str) -> Result<Value> {
+    if let Some(quoted) = raw.strip_prefix('"') {
+        return quoted
+            .strip_suffix('"')
+            .map(|s| Value::String(s.to_string()))
+            .ok_or_else(|| query_error(format!("Unterminated string {raw}")));
+    }
+    Ok(match raw {
+        "true" => Value::Bool(true),
+        "false" => Value::Bool(false),
+        "null" => Value::Null,
+        _ => raw
+            .parse::<i64>()
+            .map(Value::from)
+            .or_else(|_| raw.parse::<f64>().map(Value::from))
+            .unwrap_or_else(|_| Value::String(raw.to_string())),
+    })
+}
+
+/// Split a bracketed list on commas outside quotes
+fn split_list(list: &str) -> Vec<&str> {
+    if list.trim().is_empty() {
+        return Vec::new();
+    }
+    let mut items = Vec::new();
+    let mut start = 0;
+    let mut quoted = false;
+    for (i, c) in list.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            ',' if !quoted => {
+                items.push(&list[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    items.push(&list[start..]);
+    items
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(input: &str) -> QueryDsl {
+        QueryDsl::parse(input).unwrap()
+    }
+
+    #[test]
+    fn test_fields_and_text_split() {
+        let query = parse(r#"category:fruit AND score>[0.5] "exact phrase""#);
+        assert_eq!(
+            query.filter,
+            Some(json!({"$and": [{"category": "fruit"}, {"score": {"$gt": 0.5}}]}))
+        );
+        assert_eq!(query.text.as_deref(), Some("\"exact phrase\""));
+
+        let query = parse("rust embeddings");
+        assert_eq!(query.filter, None);
+        assert_eq!(query.text.as_deref(), Some("rust embeddings"));
+
+        assert_eq!(parse(""), QueryDsl::default());
+    }
+
+    #[test]
+    fn test_field_operators() {
+        let filter = |input: &str| parse(input).filter.unwrap();
+
+        assert_eq!(filter("year>=2024"), json!({"year": {"$gte": 2024}}));
+        assert_eq!(filter("year<2025"), json!({"year": {"$lt": 2025}}));
+        assert_eq!(
+            filter("tenant!=globex"),
+            json!({"tenant": {"$ne": "globex"}})
+        );
+        assert_eq!(
+            filter(r#"tenant:[acme, "big co"]"#),
+            json!({"tenant": {"$in": ["acme", "big co"]}})
+        );
+        assert_eq!(filter("doc.lang=en"), json!({"doc.lang": "en"}));
+        assert_eq!(filter("draft:false"), json!({"draft": false}));
+        assert_eq!(
+            filter(r#"title:"Hello World""#),
+            json!({"title": "Hello World"})
+        );
+        assert_eq!(filter("owner:*"), json!({"owner": {"$exists": true}}));
+
+        assert!(QueryDsl::parse("score>[1, 2]").is_err());
+        assert!(QueryDsl::parse("score>").is_err());
+        assert!(QueryDsl::parse("tags:[a, b").is_err());
+    }
+
+    #[test]
+    fn test_boolean_groups() {
+        let query = parse("(tenant:acme OR tenant:globex) -archived:true pasta -sauce");
+        assert_eq!(
+            query.filter,
+            Some(json!({"$and": [
+                {"$or": [{"tenant": "acme"}, {"tenant": "globex"}]},
+                {"$nor": [{"archived": true}]}
+            ]}))
+        );
+        assert_eq!(query.text.as_deref(), Some("pasta -sauce"));
+
+        let query = parse("rust OR go");
+        assert_eq!(query.text.as_deref(), Some("(rust OR go)"));
+
+        assert!(QueryDsl::parse("tenant:acme OR pasta").is_err());
+        assert!(QueryDsl::parse("(tenant:acme").is_err());
+    }
+}
@@ -2,17 +2,706 @@
 // SPDX-License-Identifier: Apache-2.0
 
 // MongoDB-style metadata filtering
-// TODO: Implement metadata filtering
 
+use regex::{Regex, RegexBuilder};
 use serde_json::Value;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock};
 use vectrust_core::*;
+use vectrust_index::{GeoBounds, GeoPoint};
 
+/// Evaluates MongoDB-style filters against item metadata.
+///
+/// Supports field equality shorthand (`{"tenant": "acme"}`), dotted paths
+/// into nested objects (`{"doc.lang": "en"}`), the logical operators
+/// `$and`, `$or`, `$nor`, and the field operators `$eq`, `$ne`, `$gt`,
+/// `$gte`, `$lt`, `$lte`, `$in`, `$nin` and `$exists`. Unknown operators
+/// never match.
+///
+/// String fields also take `$startsWith`, `$contains` and `$regex`. Regexes
+/// use the `regex` crate's syntax, with `(?i)` for case-insensitive
+/// matching; they run in linear time, and patterns longer than
+/// [`MAX_REGEX_LEN`] or compiling past [`MAX_REGEX_SIZE`] never match.
+///
+/// Array fields take `$all`, matching arrays holding every listed value,
+/// `$size`, matching arrays of that length, and `$elemMatch`, matching
+/// arrays with an element satisfying a sub-filter. The sub-filter is
+/// either operators applied to the element itself
+/// (`{"scores": {"$elemMatch": {"$gte": 0.8}}}`) or a filter on object
+/// elements (`{"authors": {"$elemMatch": {"role": "editor"}}}`).
+///
+/// Missing fields and nulls follow MongoDB: equality with `null` (and
+/// `null` in `$in`) matches fields that are null or missing, `$ne` and
+/// `$nin` match fields that are missing, and no other operator matches
+/// a missing or null field. Numbers compare by value, so `5` equals
+/// `5.0`. Values of different types, such as `"5"` and `5`, are never
+/// equal and never ordered; only numbers and strings are ordered at all.
+/// [`FilterCheck`] in strict mode reports those comparisons as errors
+/// instead.
+///
+/// Geo operators apply to `{"lat": .., "lon": ..}` field values:
+/// `{"$near": {"lat": .., "lon": .., "radiusMeters": ..}}` matches points
+/// within a great-circle radius, and `{"$within": {"minLat": .., "minLon": ..,
+/// "maxLat": .., "maxLon": ..}}` matches a bounding box (`minLon > maxLon`
+/// wraps across the antimeridian).
 pub struct MetadataFilter;
 
 impl MetadataFilter {
-    pub fn matches(_item: &VectorItem, _filter: &Value) -> bool {
-        // TODO: Implement MongoDB-style filtering
-        // Support for $eq, $ne, $in, $nin, $gt, $gte, $lt, $lte, etc.
-        true
+    pub fn matches(item: &VectorItem, filter: &Value) -> bool {
+        Self::matches_value(&item.metadata, filter)
+    }
+
+    /// Match a raw metadata value; a null filter matches everything
+    pub fn matches_value(metadata: &Value, filter: &Value) -> bool {
+        // Lenient evaluation has no errors to report
+        Self::evaluate(metadata, filter, false).unwrap_or(false)
+    }
+
+    /// Match a raw metadata value, failing on comparisons between values
+    /// of different types, unknown operators and malformed operands
+    pub fn matches_value_strict(metadata: &Value, filter: &Value) -> Result<bool> {
+        Self::evaluate(metadata, filter, true)
+    }
+
+    fn evaluate(metadata: &Value, filter: &Value, strict: bool) -> Result<bool> {
+        match filter {
+            Value::Null => Ok(true),
+            Value::Object(conditions) => {
+                for (key, condition) in conditions {
+                    if !Self::matches_clause(metadata, key, condition, strict)? {
+                        return Ok(false);
+                    }
+                }
+                Ok(true)
+            }
+            _ if strict => Err(filter_error("a filter must be an object".to_string())),
+            _ => Ok(false),
+        }
+    }
+
+    fn matches_clause(
+        metadata: &Value,
+        key: &str,
+        condition: &Value,
+        strict: bool,
+    ) -> Result<bool> {
+        let logical = |any: bool| -> Result<Option<bool>> {
+            let Some(filters) = condition.as_array() else {
+                return malformed(strict, key);
+            };
+            for filter in filters {
+                if Self::evaluate(metadata, filter, strict)? == any {
+                    return Ok(Some(any));
+                }
+            }
+            Ok(Some(!any))
+        };
+        let matched = match key {
+            "$and" => logical(false)?,
+            "$or" => logical(true)?,
+            "$nor" => logical(true)?.map(|any| !any),
+            _ if key.starts_with('$') => malformed(strict, key)?,
+            _ => Some(Self::matches_field(
+                key,
+                Self::lookup(metadata, key),
+                condition,
+                strict,
+            )?),
+        };
+        Ok(matched.unwrap_or(false))
+    }
+
+    /// Resolve a dotted path like `doc.lang` within the metadata
+    fn lookup<'a>(metadata: &'a Value, path: &str) -> Option<&'a Value> {
+        path.split('.')
+            .try_fold(metadata, |value, segment| value.get(segment))
+    }
+
+    fn matches_field(
+        field: &str,
+        value: Option<&Value>,
+        condition: &Value,
+        strict: bool,
+    ) -> Result<bool> {
+        match condition {
+            Value::Object(ops) if ops.keys().any(|k| k.starts_with('$')) => {
+                for (op, operand) in ops {
+                    if !Self::matches_operator(field, value, op, operand, strict)? {
+                        return Ok(false);
+                    }
+                }
+                Ok(true)
+            }
+            _ => Self::equals(field, value, condition, strict),
+        }
+    }
+
+    fn matches_operator(
+        field: &str,
+        value: Option<&Value>,
+        op: &str,
+        operand: &Value,
+        strict: bool,
+    ) -> Result<bool> {
+        let order = |accept: fn(Ordering) -> bool| -> Result<bool> {
+            Ok(Self::compare(field, value, operand, strict)?.is_some_and(accept))
+        };
+        let matched = match op {
+            "$eq" => Some(Self::equals(field, value, operand, strict)?),
+            "$ne" => Some(!Self::equals(field, value, operand, strict)?),
+            "$gt" => Some(order(Ordering::is_gt)?),
+            "$gte" => Some(order(Ordering::is_ge)?),
+            "$lt" => Some(order(Ordering::is_lt)?),
+            "$lte" => Some(order(Ordering::is_le)?),
+            "$in" => Self::is_in(field, value, op, operand, strict)?,
+            "$nin" => Self::is_in(field, value, op, operand, strict)?.map(|found| !found),
+            "$startsWith" | "$contains" | "$regex" => {
+                Self::matches_string(field, value, op, operand, strict)?
+            }
+            "$all" | "$size" | "$elemMatch" => {
+                Self::matches_array(field, value, op, operand, strict)?
+            }
+            "$exists" => operand
+                .as_bool()
+                .map(|should_exist| value.is_some() == should_exist),
+            "$near" => Self::parse_near(operand).map(|(center, radius)| {
+                value
+                    .and_then(GeoPoint::from_json)
+                    .is_some_and(|point| center.haversine_meters(&point) <= radius)
+            }),
+            "$within" => GeoBounds::from_json(operand).map(|bounds| {
+                value
+                    .and_then(GeoPoint::from_json)
+                    .is_some_and(|point| bounds.contains(&point))
+            }),
+            _ => None,
+        };
+        match matched {
+            Some(matched) => Ok(matched),
+            None => Ok(malformed(strict, op)?.unwrap_or(false)),
+        }
+    }
+
+    /// Whether `value` is one of the `operand` array's options, or `None`
+    /// when the operand isn't an array
+    fn is_in(
+        field: &str,
+        value: Option<&Value>,
+        op: &str,
+        operand: &Value,
+        strict: bool,
+    ) -> Result<Option<bool>> {
+        let Some(options) = operand.as_array() else {
+            return malformed(strict, op);
+        };
+        let present = value.filter(|v| !v.is_null());
+        if let Some(v) = present {
+            if strict && !options.iter().any(|o| o.is_null() || same_type(v, o)) {
+                if let Some(option) = options.iter().find(|o| !o.is_null()) {
+                    return Err(type_error(field, v, option));
+                }
+            }
+        }
+        Ok(Some(options.iter().any(|option| match present {
+            Some(v) => values_equal(v, option),
+            None => option.is_null(),
+        })))
+    }
+
+    /// String operators, or `None` when the operand isn't a usable pattern
+    fn matches_string(
+        field: &str,
+        value: Option<&Value>,
+        op: &str,
+        operand: &Value,
+        strict: bool,
+    ) -> Result<Option<bool>> {
+        let Some(pattern) = operand.as_str() else {
+            return malformed(strict, op);
+        };
+        let text = match value.filter(|v| !v.is_null()) {
+            Some(Value::String(text)) => text,
+            Some(other) if strict => return Err(type_error(field, other, operand)),
+            _ => return Ok(Some(false)),
+        };
+        Ok(Some(match op {
+            "$startsWith" => text.starts_with(pattern),
+            "$contains" => text.contains(pattern),
+            _ => match compiled_regex(pattern) {
+                Ok(regex) => regex.is_match(text),
+                Err(e) if strict => return Err(e),
+                Err(_) => return Ok(None),
+            },
+        }))
+    }
+
+    /// Array operators, or `None` when the operand is malformed
+    fn matches_array(
+        field: &str,
+        value: Option<&Value>,
+        op: &str,
+        operand: &Value,
+        strict: bool,
+    ) -> Result<Option<bool>> {
+        let well_formed = match op {
+            "$all" => operand.is_array(),
+            "$size" => operand.is_u64(),
+            _ => operand.is_object(),
+        };
+        if !well_formed {
+            return malformed(strict, op);
+        }
+        let elements = match value.filter(|v| !v.is_null()) {
+            Some(Value::Array(elements)) => elements,
+            Some(other) if strict => return Err(type_error(field, other, operand)),
+            _ => return Ok(Some(false)),
+        };
+        Ok(Some(match (op, operand) {
+            ("$all", Value::Array(wanted)) => wanted
+                .iter()
+                .all(|w| elements.iter().any(|element| values_equal(element, w))),
+            ("$size", size) => size.as_u64() == Some(elements.len() as u64),
+            (_, Value::Object(ops))
+                if !ops.is_empty() && ops.keys().all(|k| k.starts_with('$')) =>
+            {
+                // Operators applied to each element itself
+                any_element(elements.iter(), |element| {
+                    Self::matches_field(field, Some(element), operand, strict)
+                })?
+            }
+            _ => any_element(elements.iter().filter(|e| e.is_object()), |element| {
+                Self::evaluate(element, operand, strict)
+            })?,
+        }))
+    }
+
+    /// Equality, with a null operand also matching a missing field
+    fn equals(field: &str, value: Option<&Value>, operand: &Value, strict: bool) -> Result<bool> {
+        let Some(value) = value.filter(|v| !v.is_null()) else {
+            return Ok(operand.is_null());
+        };
+        if strict && !operand.is_null() && !same_type(value, operand) {
+            return Err(type_error(field, value, operand));
+        }
+        Ok(values_equal(value, operand))
+    }
+
+    /// Parse a `$near` operand into its center and radius in meters
+    pub fn parse_near(operand: &Value) -> Option<(GeoPoint, f64)> {
+        let center = GeoPoint::from_json(operand)?;
+        let radius = operand.get("radiusMeters")?.as_f64()?;
+        Some((center, radius))
+    }
+
+    /// Order numbers numerically and strings lexically; other pairs don't
+    /// compare, and missing or null fields don't either
+    fn compare(
+        field: &str,
+        value: Option<&Value>,
+        operand: &Value,
+        strict: bool,
+    ) -> Result<Option<Ordering>> {
+        let Some(value) = value.filter(|v| !v.is_null()) else {
+            return Ok(None);
+        };
+        match (value, operand) {
+            (Value::Number(a), Value::Number(b)) => Ok(a
+                .as_f64()
+                .zip(b.as_f64())
+                .and_then(|(a, b)| a.partial_cmp(&b))),
+            (Value::String(a), Value::String(b)) => Ok(Some(a.cmp(b))),
+            _ if strict => Err(type_error(field, value, operand)),
+            _ => Ok(None),
+        }
+    }
+}
+
+/// Whether two values are equal, comparing numbers by value
+fn values_equal(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::Number(a), Value::Number(b)) => match (a.as_i64(), b.as_i64()) {
+            (Some(a), Some(b)) => a == b,
+            _ => a.as_f64() == b.as_f64(),
+        },
+        (Value::Array(a), Value::Array(b)) => {
+            a.len() == b.len() && a.iter().zip(b).all(|(a, b)| values_equal(a, b))
+        }
+        (Value::Object(a), Value::Object(b)) => {
+            a.len() == b.len()
+                && a.iter()
+                    .all(|(key, a)| b.get(key).is_some_and(|b| values_equal(a, b)))
+        }
+        _ => a == b,
+    }
+}
+
+/// Whether `matches` holds for any of `elements`, stopping at the first
+/// error
+fn any_element<'a>(
+    elements: impl Iterator<Item = &'a Value>,
+    mut matches: impl FnMut(&'a Value) -> Result<bool>,
+) -> Result<bool> {
+    for element in elements {
+        if matches(element)? {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+fn same_type(a: &Value, b: &Value) -> bool {
+    std::mem::discriminant(a) == std::mem::discriminant(b)
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "a boolean",
+        Value::Number(_) => "a number",
+        Value::String(_) => "a string",
+        Value::Array(_) => "an array",
+        Value::Object(_) => "an object",
+    }
+}
+
+fn filter_error(message: String) -> VectraError {
+    VectraError::Query {
+        message: format!("Invalid filter: {}", message),
+    }
+}
+
+fn type_error(field: &str, value: &Value, operand: &Value) -> VectraError {
+    filter_error(format!(
+        "field '{}' holds {} but is compared with {}",
+        field,
+        type_name(value),
+        type_name(operand)
+    ))
+}
+
+/// An unknown operator or malformed operand: an error in strict mode,
+/// otherwise no match
+fn malformed(strict: bool, op: &str) -> Result<Option<bool>> {
+    if strict {
+        Err(filter_error(format!(
+            "unknown operator or bad operand for '{}'",
+            op
+        )))
+    } else {
+        Ok(None)
+    }
+}
+
+/// Longest `$regex` pattern accepted, in bytes
+pub const MAX_REGEX_LEN: usize = 1024;
+
+/// Most memory a compiled `$regex` may take, in bytes
+pub const MAX_REGEX_SIZE: usize = 1 << 20;
+
+/// Compiled patterns kept for reuse, since a filter is evaluated per item
+const REGEX_CACHE_SIZE: usize = 256;
+
+static REGEXES: OnceLock<Mutex<HashMap<String, Arc<Regex>>>> = OnceLock::new();
+
+/// Compile `pattern` within the size limits, reusing earlier compilations
+fn compiled_regex(pattern: &str) -> Result<Arc<Regex>> {
+    let cache = REGEXES.get_or_init(Default::default);
+    if let Some(regex) = cache.lock().unwrap().get(pattern) {
+        return Ok(regex.clone());
+    }
+    if pattern.len() > MAX_REGEX_LEN {
+        return Err(filter_error(format!(
+            "$regex pattern is {} bytes, more than the limit of {}",
+            pattern.len(),
+            MAX_REGEX_LEN
+        )));
+    }
+    let regex = RegexBuilder::new(pattern)
+        .size_limit(MAX_REGEX_SIZE)
+        .dfa_size_limit(MAX_REGEX_SIZE)
+        .build()
+        .map_err(|e| filter_error(format!("bad $regex pattern: {}", e)))?;
+    let regex = Arc::new(regex);
+    let mut cache = cache.lock().unwrap();
+    if cache.len() >= REGEX_CACHE_SIZE {
+        cache.clear();
+    }
+    cache.insert(pattern.to_string(), regex.clone());
+    Ok(regex)
+}
+
+/// A filter evaluated leniently or strictly, for callers that can only
+/// pass a `bool` predicate along. Strict evaluation failures make the
+/// item not match and are reported by [`finish`](Self::finish).
+pub struct FilterCheck {
+    filter: Value,
+    strict: bool,
+    error: Mutex<Option<VectraError>>,
+}
+
+impl FilterCheck {
+    pub fn new(filter: Value, strict: bool) -> Self {
+        Self {
+            filter,
+            strict,
+            error: Mutex::new(None),
+        }
+    }
+
+    pub fn matches(&self, item: &VectorItem) -> bool {
+        self.matches_value(&item.metadata)
+    }
+
+    pub fn matches_value(&self, metadata: &Value) -> bool {
+        if !self.strict {
+            return MetadataFilter::matches_value(metadata, &self.filter);
+        }
+        match MetadataFilter::matches_value_strict(metadata, &self.filter) {
+            Ok(matched) => matched,
+            Err(e) => {
+                self.error.lock().unwrap().get_or_insert(e);
+                false
+            }
+        }
+    }
+
+    /// The first error strict evaluation hit, if any
+    pub fn finish(&self) -> Result<()> {
+        match self.error.lock().unwrap().take() {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn meta() -> Value {
+        json!({
+            "tenant": "acme",
+            "year": 2024,
+            "doc": { "lang": "en" }
+        })
+    }
+
+    #[test]
+    fn test_equality_and_nested_paths() {
+        assert!(MetadataFilter::matches_value(
+            &meta(),
+            &json!({"tenant": "acme"})
+        ));
+        assert!(MetadataFilter::matches_value(
+            &meta(),
+            &json!({"doc.lang": "en"})
+        ));
+        assert!(!MetadataFilter::matches_value(
+            &meta(),
+            &json!({"tenant": "globex"})
+        ));
+        assert!(MetadataFilter::matches_value(&meta(), &Value::Null));
+        assert!(MetadataFilter::matches_value(&meta(), &json!({})));
+    }
+
+    #[test]
+    fn test_comparison_operators() {
+        let m = meta();
+        assert!(MetadataFilter::matches_value(
+            &m,
+            &json!({"year": {"$gte": 2024, "$lt": 2025}})
+        ));
+        assert!(!MetadataFilter::matches_value(
+            &m,
+            &json!({"year": {"$gt": 2024}})
+        ));
+        assert!(MetadataFilter::matches_value(
+            &m,
+            &json!({"tenant": {"$in": ["acme", "globex"]}})
+        ));
+        assert!(MetadataFilter::matches_value(
+            &m,
+            &json!({"tenant": {"$nin": ["globex"]}})
+        ));
+        assert!(MetadataFilter::matches_value(
+            &m,
+            &json!({"missing": {"$ne": 1}})
+        ));
+        assert!(MetadataFilter::matches_value(
+            &m,
+            &json!({"missing": {"$exists": false}})
+        ));
+        // Mismatched types never compare
+        assert!(!MetadataFilter::matches_value(
+            &m,
+            &json!({"tenant": {"$gt": 1}})
+        ));
+    }
+
+    #[test]
+    fn test_null_and_missing_fields() {
+        let m = json!({"tenant": "acme", "deleted": null, "year": 2024});
+        let matches = |filter: Value| MetadataFilter::matches_value(&m, &filter);
+        // Null equality matches null and missing fields alike
+        assert!(matches(json!({"deleted": null})));
+        assert!(matches(json!({"missing": null})));
+        assert!(!matches(json!({"tenant": null})));
+        assert!(matches(json!({"tenant": {"$ne": null}})));
+        assert!(!matches(json!({"deleted": {"$ne": null}})));
+        assert!(matches(json!({"missing": {"$in": [null, "x"]}})));
+        assert!(!matches(json!({"missing": {"$in": ["x"]}})));
+        assert!(matches(json!({"missing": {"$nin": ["x"]}})));
+        // Nulls are present but never ordered
+        assert!(matches(json!({"deleted": {"$exists": true}})));
+        assert!(!matches(json!({"deleted": {"$lt": 1}})));
+        assert!(!matches(json!({"missing": {"$gte": 0}})));
+        // Numbers compare by value, other types never meet
+        assert!(matches(json!({"year": 2024.0})));
+        assert!(matches(json!({"year": {"$in": [2023, 2024.0]}})));
+        assert!(!matches(json!({"year": "2024"})));
+        assert!(matches(json!({"year": {"$ne": "2024"}})));
+        assert!(!matches(json!({"year": {"$gt": "2000"}})));
+    }
+
+    #[test]
+    fn test_strict_filters() {
+        let m = json!({"tenant": "acme", "deleted": null, "year": 2024});
+        let strict = |filter: Value| MetadataFilter::matches_value_strict(&m, &filter);
+        assert!(strict(json!({"year": {"$gte": 2024}, "tenant": "acme"})).unwrap());
+        assert!(strict(json!({"deleted": null, "missing": {"$gt": 1}})).is_ok());
+        assert!(strict(json!({"year": {"$in": [null, 2024]}})).unwrap());
+        assert!(matches!(
+            strict(json!({"year": "2024"})),
+            Err(VectraError::Query { .. })
+        ));
+        assert!(strict(json!({"year": {"$gt": "2000"}})).is_err());
+        assert!(strict(json!({"tenant": {"$nin": [1, 2]}})).is_err());
+        assert!(strict(json!({"tenant": {"$in": "acme"}})).is_err());
+        assert!(strict(json!({"$unknown": 1})).is_err());
+        assert!(strict(json!({"$or": [{"tenant": "x"}, {"year": {"$bogus": 1}}]})).is_err());
+
+        // A check remembers the first error for callers that filter lazily
+        let check = FilterCheck::new(json!({"year": {"$lt": "now"}}), true);
+        assert!(!check.matches_value(&m));
+        assert!(check.finish().is_err());
+        assert!(check.finish().is_ok());
+    }
+
+    #[test]
+    fn test_string_operators() {
+        let m = json!({"url": "https://example.com/docs/Intro.md", "size": 12});
+        let matches = |filter: Value| MetadataFilter::matches_value(&m, &filter);
+        assert!(matches(
+            json!({"url": {"$startsWith": "https://example.com/"}})
+        ));
+        assert!(!matches(json!({"url": {"$startsWith": "http://"}})));
+        assert!(matches(json!({"url": {"$contains": "/docs/"}})));
+        assert!(matches(json!({"url": {"$regex": r"\.md$"}})));
+        assert!(matches(json!({"url": {"$regex": "(?i)intro"}})));
+        assert!(!matches(json!({"url": {"$regex": "intro"}})));
+        // Non-strings and missing fields never match
+        assert!(!matches(json!({"size": {"$contains": "1"}})));
+        assert!(!matches(json!({"missing": {"$startsWith": ""}})));
+
+        // Invalid and oversized patterns match nothing, or fail when strict
+        let oversized = "a".repeat(MAX_REGEX_LEN + 1);
+        for pattern in ["(unclosed", oversized.as_str(), "\\w{1000}{1000}"] {
+            let filter = json!({"url": {"$regex": pattern}});
+            assert!(!matches(filter.clone()));
+            assert!(MetadataFilter::matches_value_strict(&m, &filter).is_err());
+        }
+        assert!(
+            MetadataFilter::matches_value_strict(&m, &json!({"size": {"$regex": "1"}})).is_err()
+        );
+    }
+
+    #[test]
+    fn test_array_operators() {
+        let m = json!({
+            "tags": ["rust", "search", 3],
+            "scores": [0.2, 0.9],
+            "authors": [{"name": "ann", "role": "editor"}, {"name": "bo", "role": "writer"}],
+            "title": "guide"
+        });
+        let matches = |filter: Value| MetadataFilter::matches_value(&m, &filter);
+        assert!(matches(json!({"tags": {"$all": ["search", "rust"]}})));
+        assert!(matches(json!({"tags": {"$all": [3.0]}})));
+        assert!(!matches(json!({"tags": {"$all": ["rust", "go"]}})));
+        assert!(matches(json!({"tags": {"$size": 3}})));
+        assert!(!matches(json!({"scores": {"$size": 3}})));
+        assert!(matches(
+            json!({"scores": {"$elemMatch": {"$gte": 0.8, "$lt": 1}}})
+        ));
+        assert!(!matches(
+            json!({"scores": {"$elemMatch": {"$gt": 0.2, "$lt": 0.5}}})
+        ));
+        assert!(matches(
+            json!({"tags": {"$elemMatch": {"$startsWith": "sea"}}})
+        ));
+        assert!(matches(
+            json!({"authors": {"$elemMatch": {"role": "editor", "name": "ann"}}})
+        ));
+        assert!(!matches(
+            json!({"authors": {"$elemMatch": {"role": "editor", "name": "bo"}}})
+        ));
+        // Non-arrays and missing fields never match
+        assert!(!matches(json!({"title": {"$size": 5}})));
+        assert!(!matches(json!({"missing": {"$all": []}})));
+
+        let strict = |filter: Value| MetadataFilter::matches_value_strict(&m, &filter);
+        assert!(strict(json!({"tags": {"$all": ["rust"]}, "scores": {"$size": 2}})).unwrap());
+        assert!(strict(json!({"title": {"$all": ["guide"]}})).is_err());
+        assert!(strict(json!({"tags": {"$size": -1}})).is_err());
+        assert!(strict(json!({"tags": {"$elemMatch": "rust"}})).is_err());
+    }
+
+    #[test]
+    fn test_logical_operators() {
+        let m = meta();
+        assert!(MetadataFilter::matches_value(
+            &m,
+            &json!({"$or": [{"tenant": "globex"}, {"year": 2024}]})
+        ));
+        assert!(!MetadataFilter::matches_value(
+            &m,
+            &json!({"$and": [{"tenant": "acme"}, {"year": 2023}]})
+        ));
+        assert!(MetadataFilter::matches_value(
+            &m,
+            &json!({"$nor": [{"tenant": "globex"}]})
+        ));
+        assert!(!MetadataFilter::matches_value(&m, &json!({"$unknown": 1})));
+    }
+
+    #[test]
+    fn test_geo_operators() {
+        let m = json!({"loc": {"lat": 52.52, "lon": 13.405}});
+        assert!(MetadataFilter::matches_value(
+            &m,
+            &json!({"loc": {"$near": {"lat": 52.5, "lon": 13.4, "radiusMeters": 5000}}})
+        ));
+        assert!(!MetadataFilter::matches_value(
+            &m,
+            &json!({"loc": {"$near": {"lat": 48.85, "lon": 2.35, "radiusMeters": 5000}}})
+        ));
+        assert!(MetadataFilter::matches_value(
+            &m,
+            &json!({"loc": {"$within": {"minLat": 50, "minLon": 10, "maxLat": 55, "maxLon": 15}}})
+        ));
+        assert!(!MetadataFilter::matches_value(
+            &m,
+            &json!({"loc": {"$within": {"minLat": 50, "minLon": 170, "maxLat": 55, "maxLon": -170}}})
+        ));
+        // Malformed operands and non-point values never match
+        assert!(!MetadataFilter::matches_value(
+            &m,
+            &json!({"loc": {"$near": {"lat": 52.5, "lon": 13.4}}})
+        ));
+        assert!(!MetadataFilter::matches_value(
+            &json!({"loc": "Berlin"}),
+            &json!({"loc": {"$within": {"minLat": -90, "minLon": -180, "maxLat": 90, "maxLon": 180}}})
+        ));
     }
 }
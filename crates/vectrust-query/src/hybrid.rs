@@ -2,15 +2,76 @@
 // SPDX-License-Identifier: Apache-2.0
 
 // Hybrid search combining vector and text search
-// TODO: Implement hybrid search
 
+use std::collections::HashMap;
+use uuid::Uuid;
 use vectrust_core::*;
 
+/// Fuses vector similarity rankings with keyword rankings
 pub struct HybridSearch;
 
 impl HybridSearch {
-    pub fn search(_query: &Query) -> Result<Vec<QueryResult>> {
-        // TODO: Implement hybrid vector + text search
-        Ok(Vec::new())
+    /// Damping constant for reciprocal rank fusion
+    pub const RRF_K: f32 = 60.0;
+
+    /// Combine ranked vector results with keyword hits by reciprocal rank fusion.
+    ///
+    /// Each item scores `1 / (k + rank)` per list it appears in, so scores
+    /// on different scales fuse without normalization. Only items present
+    /// in `vector_results` are returned, which keeps metadata filters
+    /// applied to the vector side in effect.
+    pub fn fuse(
+        vector_results: Vec<QueryResult>,
+        text_hits: &[(Uuid, f32)],
+        top_k: usize,
+    ) -> Vec<QueryResult> {
+        let text_ranks: HashMap<Uuid, usize> = text_hits
+            .iter()
+            .enumerate()
+            .map(|(rank, (id, _))| (*id, rank))
+            .collect();
+
+        let mut fused: Vec<QueryResult> = vector_results
+            .into_iter()
+            .enumerate()
+            .map(|(rank, result)| {
+                let mut score = 1.0 / (Self::RRF_K + rank as f32 + 1.0);
+                if let Some(text_rank) = text_ranks.get(&result.item.id) {
+                    score += 1.0 / (Self::RRF_K + *text_rank as f32 + 1.0);
+                }
+                QueryResult { score, ..result }
+            })
+            .collect();
+
+        fused.sort_by(QueryResult::rank_cmp);
+        fused.truncate(top_k);
+        fused
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(score: f32) -> QueryResult {
+        QueryResult {
+            item: VectorItem {
+                id: Uuid::new_v4(),
+                ..Default::default()
+            },
+            score,
+            highlights: Vec::new(),
+            payload: None,
+        }
+    }
+
+    #[test]
+    fn test_keyword_match_lifts_lower_vector_rank() {
+        let vector_results = vec![result(0.9), result(0.8), result(0.7)];
+        let keyword_match = vector_results[2].item.id;
+
+        let fused = HybridSearch::fuse(vector_results, &[(keyword_match, 4.2)], 2);
+        assert_eq!(fused.len(), 2);
+        assert_eq!(fused[0].item.id, keyword_match);
     }
 }
@@ -1,13 +1,21 @@
 // Copyright 2024-2026 Andrey Vasilevsky <anvanster@gmail.com>
 // SPDX-License-Identifier: Apache-2.0
 
+pub mod builder;
+pub mod dsl;
 pub mod filter;
 pub mod hybrid;
 pub mod search;
+pub mod text_index;
+pub mod tokenizer;
 
+pub use builder::*;
+pub use dsl::*;
 pub use filter::*;
 pub use hybrid::*;
 pub use search::*;
+pub use text_index::*;
+pub use tokenizer::*;
 
 #[cfg(test)]
 mod tests {
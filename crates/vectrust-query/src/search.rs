@@ -1,16 +1,439 @@
 // Copyright 2024-2026 Andrey Vasilevsky <anvanster@gmail.com>
 // SPDX-License-Identifier: Apache-2.0
 
-// Vector search implementation
-// TODO: Implement vector search with filtering
+// Vector search with metadata filtering and score adjustment
 
+use crate::MetadataFilter;
+use chrono::{DateTime, Utc};
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
 use vectrust_core::*;
 
-pub struct VectorSearch;
+/// Scratch capacity kept between searches; a rare huge query's buffers
+/// are released rather than held by the thread
+const RETAINED_SCRATCH: usize = 1 << 16;
 
-impl VectorSearch {
-    pub fn search(_query: &Query) -> Result<Vec<QueryResult>> {
-        // TODO: Implement vector search with metadata filtering
-        Ok(Vec::new())
+thread_local! {
+    static SCRATCH: RefCell<Scratch> = RefCell::default();
+}
+
+/// Share of the candidates a page must cover before they are all ranked
+/// by one sort instead of through a bounded heap. Near that size the heap
+/// saves no memory and costs a second sort's worth of sifting.
+const FULL_SORT_DIVISOR: usize = 2;
+
+/// Buffers reused by every search on a thread
+#[derive(Default)]
+struct Scratch {
+    heap: BinaryHeap<Ranked>,
+    ranked: Vec<Ranked>,
+}
+
+impl Scratch {
+    fn release(&mut self) {
+        self.heap.clear();
+        self.ranked.clear();
+        if self.heap.capacity() > RETAINED_SCRATCH {
+            self.heap.shrink_to(RETAINED_SCRATCH);
+        }
+        if self.ranked.capacity() > RETAINED_SCRATCH {
+            self.ranked.shrink_to(RETAINED_SCRATCH);
+        }
+    }
+}
+
+/// A scored candidate, by position in the candidate list. Orders like
+/// [`QueryResult::rank_cmp`], so a max-heap's top is the lowest ranked.
+#[derive(Clone, Copy)]
+struct Ranked {
+    score: f32,
+    id: uuid::Uuid,
+    index: usize,
+}
+
+impl Ord for Ranked {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .score
+            .total_cmp(&self.score)
+            .then_with(|| self.id.cmp(&other.id))
+    }
+}
+
+impl PartialOrd for Ranked {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Ranked {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Ranked {}
+
+/// User-defined hook computing an item's final score from its similarity.
+///
+/// Runs after declarative boosts, so it sees the boosted score. Closures of
+/// the form `|item, score| ...` implement it directly.
+pub trait ScoringFn: Send + Sync {
+    fn score(&self, item: &VectorItem, similarity: f32) -> f32;
+}
+
+impl<F> ScoringFn for F
+where
+    F: Fn(&VectorItem, f32) -> f32 + Send + Sync,
+{
+    fn score(&self, item: &VectorItem, similarity: f32) -> f32 {
+        self(item, similarity)
+    }
+}
+
+/// Brute-force search over a candidate set: filter, score, rank, truncate
+pub struct VectorSearch<'a> {
+    distance: Distance,
+    scoring: Option<&'a dyn ScoringFn>,
+    /// Score with [`Distance::deterministic_similarity`]
+    deterministic: bool,
+    now: DateTime<Utc>,
+}
+
+impl<'a> VectorSearch<'a> {
+    /// A search scoring with `distance`, which must come from
+    /// [`DistanceRegistry::resolve`] for a custom metric to be computed
+    pub fn new(distance: impl Into<Distance>) -> Self {
+        Self {
+            distance: distance.into(),
+            scoring: None,
+            deterministic: false,
+            now: Utc::now(),
+        }
+    }
+
+    pub fn with_scoring(mut self, scoring: Option<&'a dyn ScoringFn>) -> Self {
+        self.scoring = scoring;
+        self
+    }
+
+    pub fn with_deterministic(mut self, deterministic: bool) -> Self {
+        self.deterministic = deterministic;
+        self
+    }
+
+    /// Rank `candidates` and return the page `query` asks for.
+    ///
+    /// Only the best `offset + top_k` candidates after the query's cursor
+    /// are kept while scoring, in a heap held in per-thread scratch space,
+    /// and only the returned items are moved out of `candidates`. A page
+    /// covering half the candidates or more ranks them all with one sort.
+    pub fn search(&self, query: &Query, candidates: Vec<VectorItem>) -> Result<Vec<QueryResult>> {
+        let Some(ref query_vector) = query.vector else {
+            return Ok(Vec::new());
+        };
+
+        SCRATCH.with(|scratch| match scratch.try_borrow_mut() {
+            Ok(mut scratch) => {
+                let results = self.rank(query, query_vector, candidates, &mut scratch);
+                scratch.release();
+                Ok(results)
+            }
+            // A scoring hook searching on the same thread gets its own buffers
+            Err(_) => Ok(self.rank(query, query_vector, candidates, &mut Scratch::default())),
+        })
+    }
+
+    fn rank(
+        &self,
+        query: &Query,
+        query_vector: &[f32],
+        mut candidates: Vec<VectorItem>,
+        scratch: &mut Scratch,
+    ) -> Vec<QueryResult> {
+        let window = query.offset.saturating_add(query.top_k);
+        if window == 0 {
+            return Vec::new();
+        }
+        let Scratch { heap, ranked } = scratch;
+        let full_sort = window >= candidates.len() / FULL_SORT_DIVISOR;
+        if full_sort {
+            ranked.reserve(candidates.len());
+        } else {
+            heap.reserve(window);
+        }
+
+        for (index, item) in candidates.iter().enumerate() {
+            if item.vector.len() != query_vector.len() {
+                continue;
+            }
+            if !query
+                .filter
+                .as_ref()
+                .is_none_or(|filter| MetadataFilter::matches(item, filter))
+            {
+                continue;
+            }
+            let similarity = if self.deterministic {
+                self.distance
+                    .deterministic_similarity(query_vector, &item.vector)
+            } else {
+                self.distance.similarity(query_vector, &item.vector)
+            };
+            let score = self.final_score(query, item, similarity);
+            if query.min_score.is_some_and(|min| score < min) {
+                continue;
+            }
+            if let Some(ref cursor) = query.search_after {
+                if !cursor.precedes_rank(score, &item.id) {
+                    continue;
+                }
+            }
+
+            let scored = Ranked {
+                score,
+                id: item.id,
+                index,
+            };
+            if full_sort {
+                ranked.push(scored);
+            } else if heap.len() < window {
+                heap.push(scored);
+            } else if let Some(mut worst) = heap.peek_mut() {
+                if scored < *worst {
+                    *worst = scored;
+                }
+            }
+        }
+
+        if full_sort {
+            if ranked.len() > window {
+                ranked.select_nth_unstable(window);
+                ranked.truncate(window);
+            }
+        } else {
+            ranked.extend(heap.drain());
+        }
+        ranked.sort_unstable();
+        let page = &ranked[query.offset.min(ranked.len())..];
+        let mut results = Vec::with_capacity(page.len());
+        for ranked in page {
+            results.push(QueryResult {
+                item: std::mem::take(&mut candidates[ranked.index]),
+                score: ranked.score,
+                highlights: Vec::new(),
+                payload: None,
+            });
+        }
+        results
+    }
+
+    fn final_score(&self, query: &Query, item: &VectorItem, similarity: f32) -> f32 {
+        let blended = match query.recency {
+            Some(ref recency) => self.apply_recency(similarity, item, recency),
+            None => similarity,
+        };
+        let boosted = query.boosts.iter().fold(blended, |score, boost| {
+            Self::apply_boost(score, item, boost)
+        });
+
+        match self.scoring {
+            Some(scoring) => scoring.score(item, boosted),
+            None => boosted,
+        }
+    }
+
+    fn apply_recency(&self, similarity: f32, item: &VectorItem, recency: &RecencyBoost) -> f32 {
+        let timestamp = match recency.field {
+            RecencyField::CreatedAt => Some(item.created_at),
+            RecencyField::UpdatedAt => Some(item.updated_at),
+            RecencyField::Metadata(ref field) => field
+                .split('.')
+                .try_fold(&item.metadata, |value, segment| value.get(segment))
+                .and_then(Self::parse_timestamp),
+        };
+
+        let decay = match timestamp {
+            Some(timestamp) if recency.half_life_days > 0.0 => {
+                // Future timestamps count as brand new
+                let age_days = (self.now - timestamp).num_seconds().max(0) as f64 / 86_400.0;
+                0.5f64.powf(age_days / recency.half_life_days) as f32
+            }
+            _ => 0.0,
+        };
+
+        (1.0 - recency.weight) * similarity + recency.weight * decay
+    }
+
+    fn parse_timestamp(value: &serde_json::Value) -> Option<DateTime<Utc>> {
+        match value {
+            serde_json::Value::String(s) => DateTime::parse_from_rfc3339(s)
+                .ok()
+                .map(|t| t.with_timezone(&Utc)),
+            serde_json::Value::Number(n) => DateTime::from_timestamp(n.as_f64()? as i64, 0),
+            _ => None,
+        }
+    }
+
+    fn apply_boost(score: f32, item: &VectorItem, boost: &ScoreBoost) -> f32 {
+        let value = boost
+            .field
+            .split('.')
+            .try_fold(&item.metadata, |value, segment| value.get(segment))
+            .and_then(|value| value.as_f64());
+
+        match (value, boost.mode) {
+            (Some(value), BoostMode::Add) => score + boost.weight * value as f32,
+            (Some(value), BoostMode::Multiply) => score * (1.0 + boost.weight * value as f32),
+            (None, _) => score,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn item(vector: Vec<f32>, metadata: serde_json::Value) -> VectorItem {
+        VectorItem {
+            vector,
+            metadata,
+            ..Default::default()
+        }
+    }
+
+    fn query(boosts: Vec<ScoreBoost>) -> Query {
+        Query {
+            vector: Some(vec![1.0, 0.0]),
+            top_k: 10,
+            boosts,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_filter_and_rank() {
+        let candidates = vec![
+            item(vec![1.0, 0.0], json!({"lang": "en"})),
+            item(vec![0.0, 1.0], json!({"lang": "en"})),
+            item(vec![1.0, 0.1], json!({"lang": "de"})),
+        ];
+        let mut q = query(Vec::new());
+        q.filter = Some(json!({"lang": "en"}));
+
+        let results = VectorSearch::new(DistanceMetric::Cosine)
+            .search(&q, candidates)
+            .unwrap();
+        assert_eq!(results.len(), 2);
+        assert!(results[0].score > results[1].score);
+        assert_eq!(results[0].item.vector, vec![1.0, 0.0]);
+    }
+
+    #[test]
+    fn test_bounded_ranking_matches_full_sort() {
+        let candidates: Vec<VectorItem> = (0..200)
+            .map(|i| item(vec![(i % 17) as f32, (i % 5) as f32 + 1.0], json!({})))
+            .collect();
+        let search = VectorSearch::new(DistanceMetric::Cosine);
+        let mut all = query(Vec::new());
+        all.top_k = usize::MAX;
+        let everything = search.search(&all, candidates.clone()).unwrap();
+        assert_eq!(everything.len(), 200);
+        assert!(everything
+            .windows(2)
+            .all(|w| w[0].rank_cmp(&w[1]) == std::cmp::Ordering::Less));
+
+        let ids = |results: &[QueryResult]| results.iter().map(|r| r.item.id).collect::<Vec<_>>();
+        let mut page = query(Vec::new());
+        page.offset = 30;
+        page.top_k = 25;
+        let results = search.search(&page, candidates.clone()).unwrap();
+        assert_eq!(ids(&results), ids(&everything[30..55]));
+        // Pages covering most candidates take the full sort instead
+        page.top_k = 130;
+        let results = search.search(&page, candidates.clone()).unwrap();
+        assert_eq!(ids(&results), ids(&everything[30..160]));
+        page.top_k = 25;
+
+        // After a cursor, in the middle of a run of tied scores
+        page.offset = 5;
+        page.search_after = Some(everything[40].cursor());
+        let results = search.search(&page, candidates).unwrap();
+        assert_eq!(ids(&results), ids(&everything[46..71]));
+    }
+
+    #[test]
+    fn test_declarative_boost_reorders() {
+        let candidates = vec![
+            item(vec![1.0, 0.0], json!({"popularity": 0})),
+            item(vec![0.8, 0.6], json!({"popularity": 10})),
+        ];
+        let boost = ScoreBoost {
+            field: "popularity".to_string(),
+            weight: 0.1,
+            mode: BoostMode::Add,
+        };
+
+        let results = VectorSearch::new(DistanceMetric::Cosine)
+            .search(&query(vec![boost]), candidates)
+            .unwrap();
+        assert_eq!(results[0].item.metadata["popularity"], 10);
+    }
+
+    #[test]
+    fn test_recency_boost_prefers_newer_items() {
+        let now = Utc::now();
+        let mut old = item(vec![1.0, 0.0], json!({}));
+        old.created_at = now - chrono::Duration::days(60);
+        let mut fresh = item(vec![0.9, 0.3], json!({}));
+        fresh.created_at = now - chrono::Duration::hours(1);
+
+        let mut q = query(Vec::new());
+        q.recency = Some(RecencyBoost {
+            half_life_days: 7.0,
+            field: RecencyField::CreatedAt,
+            weight: 0.5,
+        });
+        let results = VectorSearch::new(DistanceMetric::Cosine)
+            .search(&q, vec![old.clone(), fresh.clone()])
+            .unwrap();
+        assert_eq!(results[0].item.id, fresh.id);
+
+        // Metadata timestamps: RFC 3339 strings and Unix seconds
+        let published = item(
+            vec![1.0, 0.0],
+            json!({"published": (now - chrono::Duration::days(7)).to_rfc3339()}),
+        );
+        let epoch = item(vec![1.0, 0.0], json!({"published": now.timestamp()}));
+        q.recency = Some(RecencyBoost {
+            half_life_days: 7.0,
+            field: RecencyField::Metadata("published".to_string()),
+            weight: 1.0,
+        });
+        let results = VectorSearch::new(DistanceMetric::Cosine)
+            .search(&q, vec![published, epoch])
+            .unwrap();
+        assert!((results[0].score - 1.0).abs() < 0.01);
+        assert!((results[1].score - 0.5).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_scoring_hook_sees_boosted_score() {
+        let candidates = vec![item(vec![1.0, 0.0], json!({"pinned": true}))];
+        let hook = |item: &VectorItem, score: f32| {
+            if item.metadata["pinned"] == true {
+                score + 100.0
+            } else {
+                score
+            }
+        };
+
+        let results = VectorSearch::new(DistanceMetric::Cosine)
+            .with_scoring(Some(&hook))
+            .search(&query(Vec::new()), candidates)
+            .unwrap();
+        assert!(results[0].score > 100.0);
     }
 }
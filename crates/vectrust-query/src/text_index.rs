@@ -0,0 +1,883 @@
+// Copyright 2024-2026 Andrey Vasilevsky <anvanster@gmail.com>
+// SPDX-License-Identifier: Apache-2.0
+
+// Inverted keyword index with BM25 scoring, phrase and boolean queries
+
+use crate::{Analyzer, Tokenizer};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use uuid::Uuid;
+use vectrust_core::*;
+
+const BM25_K1: f32 = 1.2;
+const BM25_B: f32 = 0.75;
+/// Position gap between indexed fields so phrases never span two fields
+const FIELD_POSITION_GAP: u32 = 100;
+
+/// Which metadata fields to index and how to tokenize them
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TextIndexConfig {
+    /// Dotted metadata paths holding text (strings or arrays of strings)
+    pub fields: Vec<String>,
+    /// Analyzer for fields without an override
+    #[serde(default)]
+    pub tokenizer: TokenizerConfig,
+    /// Per-field analyzers, keyed by field path
+    #[serde(default)]
+    pub field_analyzers: BTreeMap<String, TokenizerConfig>,
+}
+
+impl TextIndexConfig {
+    /// Index exactly the given fields, each with its own analyzer
+    pub fn from_fields(fields: &[TextFieldConfig]) -> Self {
+        Self {
+            fields: fields.iter().map(|f| f.field.clone()).collect(),
+            tokenizer: TokenizerConfig::default(),
+            field_analyzers: fields
+                .iter()
+                .map(|f| (f.field.clone(), f.analyzer.clone()))
+                .collect(),
+        }
+    }
+
+    fn analyzer_for(&self, field: &str) -> &TokenizerConfig {
+        self.field_analyzers.get(field).unwrap_or(&self.tokenizer)
+    }
+}
+
+/// A parsed keyword query.
+///
+/// The syntax is a list of clauses joined by implicit AND: bare words,
+/// `"quoted phrases"`, `OR` between clauses, `NOT` or a leading `-` to
+/// exclude, and parentheses for grouping, e.g.
+/// `rust ("vector database" OR embeddings) -python`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TextQuery {
+    Term(String),
+    Phrase(Vec<String>),
+    And(Vec<TextQuery>),
+    Or(Vec<TextQuery>),
+    Not(Box<TextQuery>),
+}
+
+impl TextQuery {
+    /// Parse a query string, normalizing words with the index's tokenizer
+    pub fn parse(input: &str, tokenizer: &dyn Tokenizer) -> Result<TextQuery> {
+        let tokens = lex(input);
+        let mut parser = Parser {
+            tokens: &tokens,
+            pos: 0,
+            tokenizer,
+        };
+        let query = parser.parse_or()?;
+        if parser.pos < tokens.len() {
+            return Err(VectraError::Query {
+                message: "Unbalanced ')' in text query".to_string(),
+            });
+        }
+        Ok(query.unwrap_or(TextQuery::Or(Vec::new())))
+    }
+
+    /// Prefix every term with an analyzer group so it only matches that group's fields
+    fn scoped(self, group: usize) -> TextQuery {
+        let scope = |term: String| format!("{group}:{term}");
+        match self {
+            TextQuery::Term(term) => TextQuery::Term(scope(term)),
+            TextQuery::Phrase(terms) => TextQuery::Phrase(terms.into_iter().map(scope).collect()),
+            TextQuery::And(clauses) => {
+                TextQuery::And(clauses.into_iter().map(|c| c.scoped(group)).collect())
+            }
+            TextQuery::Or(clauses) => {
+                TextQuery::Or(clauses.into_iter().map(|c| c.scoped(group)).collect())
+            }
+            TextQuery::Not(inner) => TextQuery::Not(Box::new(inner.scoped(group))),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Lexeme {
+    Word(String),
+    Phrase(String),
+    And,
+    Or,
+    Not,
+    Open,
+    Close,
+}
+
+fn lex(input: &str) -> Vec<Lexeme> {
+    let mut lexemes = Vec::new();
+    let mut chars = input.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '(' => {
+                chars.next();
+                lexemes.push(Lexeme::Open);
+            }
+            ')' => {
+                chars.next();
+                lexemes.push(Lexeme::Close);
+            }
+            '-' => {
+                chars.next();
+                lexemes.push(Lexeme::Not);
+            }
+            '"' => {
+                chars.next();
+                let phrase: String = chars.by_ref().take_while(|&c| c != '"').collect();
+                lexemes.push(Lexeme::Phrase(phrase));
+            }
+            _ => {
+                let mut word = String::new();
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() || matches!(c, '(' | ')' | '"') {
+                        break;
+                    }
+                    word.push(c);
+                    chars.next();
+                }
+                lexemes.push(match word.as_str() {
+                    "AND" => Lexeme::And,
+                    "OR" => Lexeme::Or,
+                    "NOT" => Lexeme::Not,
+                    _ => Lexeme::Word(word),
+                });
+            }
+        }
+    }
+
+    lexemes
+}
+
+struct Parser<'a> {
+    tokens: &'a [Lexeme],
+    pos: usize,
+    tokenizer: &'a dyn Tokenizer,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Lexeme> {
+        self.tokens.get(self.pos)
+    }
+
+    fn parse_or(&mut self) -> Result<Option<TextQuery>> {
+        let mut clauses: Vec<TextQuery> = self.parse_and()?.into_iter().collect();
+        while self.peek() == Some(&Lexeme::Or) {
+            self.pos += 1;
+            clauses.extend(self.parse_and()?);
+        }
+        Ok(match clauses.len() {
+            0 => None,
+            1 => clauses.pop(),
+            _ => Some(TextQuery::Or(clauses)),
+        })
+    }
+
+    fn parse_and(&mut self) -> Result<Option<TextQuery>> {
+        let mut clauses = Vec::new();
+        loop {
+            match self.peek() {
+                None | Some(Lexeme::Or) | Some(Lexeme::Close) => break,
+                Some(Lexeme::And) => self.pos += 1,
+                _ => clauses.extend(self.parse_unary()?),
+            }
+        }
+        Ok(match clauses.len() {
+            0 => None,
+            1 => clauses.pop(),
+            _ => Some(TextQuery::And(clauses)),
+        })
+    }
+
+    fn parse_unary(&mut self) -> Result<Option<TextQuery>> {
+        let Some(lexeme) = self.peek().cloned() else {
+            return Ok(None);
+        };
+        self.pos += 1;
+
+        match lexeme {
+            Lexeme::Not => Ok(self.parse_unary()?.map(|q| TextQuery::Not(Box::new(q)))),
+            Lexeme::Open => {
+                let inner = self.parse_or()?;
+                if self.peek() != Some(&Lexeme::Close) {
+                    return Err(VectraError::Query {
+                        message: "Missing ')' in text query".to_string(),
+                    });
+                }
+                self.pos += 1;
+                Ok(inner)
+            }
+            Lexeme::Word(text) | Lexeme::Phrase(text) => {
+                let mut terms: Vec<String> = self
+                    .tokenizer
+                    .tokenize(&text)
+                    .into_iter()
+                    .map(|token| token.term)
+                    .collect();
+                Ok(match terms.len() {
+                    0 => None,
+                    1 => terms.pop().map(TextQuery::Term),
+                    _ => Some(TextQuery::Phrase(terms)),
+                })
+            }
+            Lexeme::And | Lexeme::Or | Lexeme::Close => Ok(None),
+        }
+    }
+}
+
+/// One occurrence of a term in an analyzed document
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TermOccurrence {
+    pub term: String,
+    pub position: u32,
+    /// Index into [`AnalyzedDocument::fields`]
+    pub field: u16,
+    /// Character offsets into the field's text
+    pub start: u32,
+    pub end: u32,
+}
+
+/// A document's terms with their positions and source offsets
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AnalyzedDocument {
+    /// Field paths the terms came from; array elements are `field.N`
+    pub fields: Vec<String>,
+    pub terms: Vec<TermOccurrence>,
+}
+
+/// Term postings with positions plus per-document lengths for BM25
+#[derive(Debug, Default)]
+pub struct InvertedIndex {
+    postings: HashMap<String, HashMap<Uuid, Vec<u32>>>,
+    documents: HashMap<Uuid, AnalyzedDocument>,
+    total_length: u64,
+}
+
+impl InvertedIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.documents.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.documents.is_empty()
+    }
+
+    /// Index an analyzed document, replacing any previous version
+    pub fn insert(&mut self, id: Uuid, document: AnalyzedDocument) {
+        self.remove(&id);
+        if document.terms.is_empty() {
+            return;
+        }
+
+        for occurrence in &document.terms {
+            self.postings
+                .entry(occurrence.term.clone())
+                .or_default()
+                .entry(id)
+                .or_default()
+                .push(occurrence.position);
+        }
+        self.total_length += document.terms.len() as u64;
+        self.documents.insert(id, document);
+    }
+
+    pub fn remove(&mut self, id: &Uuid) {
+        let Some(document) = self.documents.remove(id) else {
+            return;
+        };
+        self.total_length -= document.terms.len() as u64;
+        for occurrence in document.terms {
+            if let Some(docs) = self.postings.get_mut(&occurrence.term) {
+                docs.remove(id);
+                if docs.is_empty() {
+                    self.postings.remove(&occurrence.term);
+                }
+            }
+        }
+    }
+
+    /// Documents matching the query with their BM25 scores
+    pub fn search(&self, query: &TextQuery) -> HashMap<Uuid, f32> {
+        match query {
+            TextQuery::Term(term) => self.score_term(term, None),
+            TextQuery::Phrase(terms) => self.score_phrase(terms),
+            TextQuery::And(clauses) => {
+                let mut result: Option<HashMap<Uuid, f32>> = None;
+                for clause in clauses {
+                    let scores = self.search(clause);
+                    result = Some(match result {
+                        None => scores,
+                        Some(acc) => acc
+                            .into_iter()
+                            .filter_map(|(id, s)| scores.get(&id).map(|t| (id, s + t)))
+                            .collect(),
+                    });
+                }
+                result.unwrap_or_default()
+            }
+            TextQuery::Or(clauses) => {
+                let mut result = HashMap::new();
+                for clause in clauses {
+                    for (id, score) in self.search(clause) {
+                        *result.entry(id).or_insert(0.0) += score;
+                    }
+                }
+                result
+            }
+            TextQuery::Not(inner) => {
+                let excluded = self.search(inner);
+                self.documents
+                    .keys()
+                    .filter(|id| !excluded.contains_key(id))
+                    .map(|id| (*id, 0.0))
+                    .collect()
+            }
+        }
+    }
+
+    /// Locations of the terms that made a document match, in position order.
+    ///
+    /// Phrase terms are only reported where the whole phrase occurs, and
+    /// branches of the query the document doesn't satisfy contribute nothing.
+    pub fn highlights(&self, id: &Uuid, query: &TextQuery) -> Vec<TextHighlight> {
+        let Some(document) = self.documents.get(id) else {
+            return Vec::new();
+        };
+
+        let mut positions = HashSet::new();
+        if self.matches(id, query) {
+            self.matched_positions(id, query, &mut positions);
+        }
+
+        document
+            .terms
+            .iter()
+            .filter(|occurrence| positions.contains(&occurrence.position))
+            .map(|occurrence| TextHighlight {
+                field: document.fields[occurrence.field as usize].clone(),
+                term: occurrence.term.clone(),
+                start: occurrence.start as usize,
+                end: occurrence.end as usize,
+            })
+            .collect()
+    }
+
+    fn matches(&self, id: &Uuid, query: &TextQuery) -> bool {
+        match query {
+            TextQuery::Term(term) => self.positions(term, id).is_some(),
+            TextQuery::Phrase(terms) => !self.phrase_starts(id, terms).is_empty(),
+            TextQuery::And(clauses) => clauses.iter().all(|c| self.matches(id, c)),
+            TextQuery::Or(clauses) => clauses.iter().any(|c| self.matches(id, c)),
+            TextQuery::Not(inner) => !self.matches(id, inner),
+        }
+    }
+
+    /// Collect positions from the clauses of a query known to match
+    fn matched_positions(&self, id: &Uuid, query: &TextQuery, positions: &mut HashSet<u32>) {
+        match query {
+            TextQuery::Term(term) => {
+                positions.extend(self.positions(term, id).into_iter().flatten());
+            }
+            TextQuery::Phrase(terms) => {
+                for start in self.phrase_starts(id, terms) {
+                    positions.extend(start..start + terms.len() as u32);
+                }
+            }
+            TextQuery::And(clauses) | TextQuery::Or(clauses) => {
+                for clause in clauses.iter().filter(|c| self.matches(id, c)) {
+                    self.matched_positions(id, clause, positions);
+                }
+            }
+            TextQuery::Not(_) => {}
+        }
+    }
+
+    fn positions(&self, term: &str, id: &Uuid) -> Option<&Vec<u32>> {
+        self.postings.get(term).and_then(|docs| docs.get(id))
+    }
+
+    /// Positions where every phrase term follows the previous one
+    fn phrase_starts(&self, id: &Uuid, terms: &[String]) -> Vec<u32> {
+        let Some(starts) = terms.first().and_then(|t| self.positions(t, id)) else {
+            return Vec::new();
+        };
+
+        starts
+            .iter()
+            .copied()
+            .filter(|&start| {
+                terms.iter().enumerate().skip(1).all(|(offset, term)| {
+                    self.positions(term, id)
+                        .is_some_and(|positions| positions.contains(&(start + offset as u32)))
+                })
+            })
+            .collect()
+    }
+
+    /// BM25 score of one term, optionally restricted to some documents
+    fn score_term(&self, term: &str, only: Option<&HashSet<Uuid>>) -> HashMap<Uuid, f32> {
+        let Some(docs) = self.postings.get(term) else {
+            return HashMap::new();
+        };
+
+        let n = self.documents.len() as f32;
+        let df = docs.len() as f32;
+        let idf = (1.0 + (n - df + 0.5) / (df + 0.5)).ln();
+        let avg_len = self.total_length as f32 / n.max(1.0);
+
+        docs.iter()
+            .filter(|(id, _)| only.is_none_or(|only| only.contains(id)))
+            .map(|(id, positions)| {
+                let tf = positions.len() as f32;
+                let len = self.documents[id].terms.len() as f32;
+                let norm = BM25_K1 * (1.0 - BM25_B + BM25_B * len / avg_len);
+                (*id, idf * tf * (BM25_K1 + 1.0) / (tf + norm))
+            })
+            .collect()
+    }
+
+    fn score_phrase(&self, terms: &[String]) -> HashMap<Uuid, f32> {
+        let Some(first) = terms.first().and_then(|t| self.postings.get(t)) else {
+            return HashMap::new();
+        };
+
+        let matching: HashSet<Uuid> = first
+            .keys()
+            .filter(|id| !self.phrase_starts(id, terms).is_empty())
+            .copied()
+            .collect();
+
+        let mut scores = HashMap::new();
+        for term in terms {
+            for (id, score) in self.score_term(term, Some(&matching)) {
+                *scores.entry(id).or_insert(0.0) += score;
+            }
+        }
+        scores
+    }
+}
+
+/// Keyword index over item metadata, optionally persisted in RocksDB.
+///
+/// Postings live in memory; the persistent form stores each document's
+/// analyzed terms, positions and offsets so the index is rebuilt on open
+/// without re-tokenizing.
+///
+/// Fields sharing an analyzer form a group, and indexed terms are scoped
+/// to their group. Queries are analyzed once per group, so a stemmed
+/// English field and an unstemmed German field each see query terms
+/// normalized the way their own text was.
+pub struct TextIndex {
+    config: TextIndexConfig,
+    /// One tokenizer per analyzer group; group 0 is the default analyzer
+    analyzers: Vec<Box<dyn Tokenizer>>,
+    /// Analyzer group of each entry in `config.fields`
+    field_groups: Vec<usize>,
+    index: InvertedIndex,
+    #[cfg(feature = "rocksdb")]
+    db: Option<rocksdb::DB>,
+}
+
+#[cfg(feature = "rocksdb")]
+const CONFIG_KEY: &[u8] = b"config";
+#[cfg(feature = "rocksdb")]
+const DOC_PREFIX: &[u8] = b"doc:";
+
+impl TextIndex {
+    /// Create an index that lives only in memory
+    pub fn in_memory(config: TextIndexConfig) -> Self {
+        let mut groups = vec![config.tokenizer.clone()];
+        let field_groups = config
+            .fields
+            .iter()
+            .map(|field| {
+                let analyzer = config.analyzer_for(field);
+                groups
+                    .iter()
+                    .position(|g| g == analyzer)
+                    .unwrap_or_else(|| {
+                        groups.push(analyzer.clone());
+                        groups.len() - 1
+                    })
+            })
+            .collect();
+
+        Self {
+            analyzers: groups
+                .into_iter()
+                .map(|g| Box::new(Analyzer::new(g)) as Box<dyn Tokenizer>)
+                .collect(),
+            field_groups,
+            config,
+            index: InvertedIndex::new(),
+            #[cfg(feature = "rocksdb")]
+            db: None,
+        }
+    }
+
+    /// Create a persistent index at `path`, discarding any existing one there
+    #[cfg(feature = "rocksdb")]
+    pub fn create(path: &std::path::Path, config: TextIndexConfig) -> Result<Self> {
+        if path.exists() {
+            rocksdb::DB::destroy(&rocksdb::Options::default(), path)?;
+        }
+        let db = rocksdb::DB::open_default(path)?;
+        db.put(CONFIG_KEY, serde_json::to_vec(&config)?)?;
+
+        Ok(Self {
+            db: Some(db),
+            ..Self::in_memory(config)
+        })
+    }
+
+    /// Open a persistent index created with [`TextIndex::create`]
+    #[cfg(feature = "rocksdb")]
+    pub fn open(path: &std::path::Path) -> Result<Self> {
+        let db = rocksdb::DB::open_default(path)?;
+        let config: TextIndexConfig = match db.get(CONFIG_KEY)? {
+            Some(bytes) => serde_json::from_slice(&bytes)?,
+            None => {
+                return Err(VectraError::Storage {
+                    message: format!("No text index at {}", path.display()),
+                })
+            }
+        };
+
+        let mut index = InvertedIndex::new();
+        for entry in db.iterator(rocksdb::IteratorMode::From(
+            DOC_PREFIX,
+            rocksdb::Direction::Forward,
+        )) {
+            let (key, value) = entry?;
+            let Some(id) = key.strip_prefix(DOC_PREFIX) else {
+                break;
+            };
+            index.insert(Uuid::from_slice(id)?, bincode::deserialize(&value)?);
+        }
+
+        Ok(Self {
+            index,
+            db: Some(db),
+            ..Self::in_memory(config)
+        })
+    }
+
+    /// Replace the tokenizer for fields without a per-field analyzer.
+    ///
+    /// Custom tokenizers aren't persisted; set the same one again after
+    /// reopening or stored terms won't match new queries.
+    pub fn with_tokenizer(mut self, tokenizer: Box<dyn Tokenizer>) -> Self {
+        self.analyzers[0] = tokenizer;
+        self
+    }
+
+    pub fn config(&self) -> &TextIndexConfig {
+        &self.config
+    }
+
+    /// The tokenizer for fields without a per-field analyzer
+    pub fn tokenizer(&self) -> &dyn Tokenizer {
+        self.analyzers[0].as_ref()
+    }
+
+    pub fn len(&self) -> usize {
+        self.index.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.index.is_empty()
+    }
+
+    /// Tokenize the configured fields of an item's metadata
+    pub fn analyze(&self, metadata: &Value) -> AnalyzedDocument {
+        let mut document = AnalyzedDocument::default();
+        let mut base = 0u32;
+
+        for (field, &group) in self.config.fields.iter().zip(&self.field_groups) {
+            let value = field
+                .split('.')
+                .try_fold(metadata, |value, segment| value.get(segment));
+            let texts: Vec<(String, &str)> = match value {
+                Some(Value::String(s)) => vec![(field.clone(), s.as_str())],
+                Some(Value::Array(values)) => values
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(i, v)| Some((format!("{field}.{i}"), v.as_str()?)))
+                    .collect(),
+                _ => Vec::new(),
+            };
+
+            for (path, text) in texts {
+                let tokens = self.analyzers[group].tokenize(text);
+                if tokens.is_empty() {
+                    continue;
+                }
+
+                // Byte offset -> character offset, valid at char boundaries
+                let mut char_offsets = vec![0u32; text.len() + 1];
+                for (i, (byte, _)) in text.char_indices().enumerate() {
+                    char_offsets[byte] = i as u32;
+                }
+                char_offsets[text.len()] = text.chars().count() as u32;
+
+                let field_index = document.fields.len() as u16;
+                document.fields.push(path);
+                let count = tokens.len() as u32;
+                for (token, position) in tokens.into_iter().zip(base..) {
+                    document.terms.push(TermOccurrence {
+                        term: format!("{group}:{}", token.term),
+                        position,
+                        field: field_index,
+                        start: char_offsets[token.start],
+                        end: char_offsets[token.end],
+                    });
+                }
+                base += count + FIELD_POSITION_GAP;
+            }
+        }
+
+        document
+    }
+
+    /// Index or re-index an item
+    pub fn index_item(&mut self, item: &VectorItem) -> Result<()> {
+        let document = self.analyze(&item.metadata);
+
+        #[cfg(feature = "rocksdb")]
+        if let Some(ref db) = self.db {
+            let key = [DOC_PREFIX, item.id.as_bytes()].concat();
+            if document.terms.is_empty() {
+                db.delete(key)?;
+            } else {
+                db.put(key, bincode::serialize(&document)?)?;
+            }
+        }
+
+        self.index.insert(item.id, document);
+        Ok(())
+    }
+
+    pub fn remove_item(&mut self, id: &Uuid) -> Result<()> {
+        #[cfg(feature = "rocksdb")]
+        if let Some(ref db) = self.db {
+            db.delete([DOC_PREFIX, id.as_bytes()].concat())?;
+        }
+
+        self.index.remove(id);
+        Ok(())
+    }
+
+    /// Parse a query string once per analyzer group, matching any group
+    pub fn parse(&self, query: &str) -> Result<TextQuery> {
+        let mut groups = self.field_groups.clone();
+        groups.sort_unstable();
+        groups.dedup();
+
+        let mut queries = groups
+            .into_iter()
+            .map(|g| Ok(TextQuery::parse(query, self.analyzers[g].as_ref())?.scoped(g)))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(match queries.len() {
+            1 => queries.remove(0),
+            _ => TextQuery::Or(queries),
+        })
+    }
+
+    /// Run a parsed query, returning IDs by descending BM25 score
+    pub fn search_parsed(&self, query: &TextQuery, limit: usize) -> Vec<(Uuid, f32)> {
+        let mut hits: Vec<(Uuid, f32)> = self.index.search(query).into_iter().collect();
+        hits.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        hits.truncate(limit);
+        hits
+    }
+
+    /// Parse and run a keyword query, returning IDs by descending BM25 score
+    pub fn search(&self, query: &str, limit: usize) -> Result<Vec<(Uuid, f32)>> {
+        Ok(self.search_parsed(&self.parse(query)?, limit))
+    }
+
+    /// Where the query matched in an item's indexed fields
+    pub fn highlights(&self, id: &Uuid, query: &TextQuery) -> Vec<TextHighlight> {
+        let mut highlights = self.index.highlights(id, query);
+        for highlight in &mut highlights {
+            if let Some((_, term)) = highlight.term.split_once(':') {
+                highlight.term = term.to_string();
+            }
+        }
+        highlights
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn item(title: &str) -> VectorItem {
+        VectorItem {
+            id: Uuid::new_v4(),
+            metadata: json!({ "title": title }),
+            ..Default::default()
+        }
+    }
+
+    fn index(items: &[&VectorItem]) -> TextIndex {
+        let mut index = TextIndex::in_memory(TextIndexConfig {
+            fields: vec!["title".to_string()],
+            ..Default::default()
+        });
+        for item in items {
+            index.index_item(item).unwrap();
+        }
+        index
+    }
+
+    #[test]
+    fn test_query_parsing() {
+        let tokenizer = Analyzer::default();
+        let query =
+            TextQuery::parse(r#"Rust ("vector DB" OR embeddings) -python"#, &tokenizer).unwrap();
+        assert_eq!(
+            query,
+            TextQuery::And(vec![
+                TextQuery::Term("rust".to_string()),
+                TextQuery::Or(vec![
+                    TextQuery::Phrase(vec!["vector".to_string(), "db".to_string()]),
+                    TextQuery::Term("embeddings".to_string()),
+                ]),
+                TextQuery::Not(Box::new(TextQuery::Term("python".to_string()))),
+            ])
+        );
+        assert!(TextQuery::parse("(rust", &tokenizer).is_err());
+    }
+
+    #[test]
+    fn test_bm25_ranking_and_boolean_queries() {
+        let a = item("rust vector database");
+        let b = item("rust rust rust");
+        let c = item("python vector database");
+        let index = index(&[&a, &b, &c]);
+
+        let hits = index.search("rust", 10).unwrap();
+        assert_eq!(hits.len(), 2);
+        assert_eq!(hits[0].0, b.id);
+
+        let hits = index.search("vector -python", 10).unwrap();
+        assert_eq!(hits.iter().map(|h| h.0).collect::<Vec<_>>(), vec![a.id]);
+
+        let hits = index.search("python OR rust", 10).unwrap();
+        assert_eq!(hits.len(), 3);
+    }
+
+    #[test]
+    fn test_phrase_queries() {
+        let a = item("vector database for rust");
+        let b = item("database of vector graphics");
+        let index = index(&[&a, &b]);
+
+        let hits = index.search(r#""vector database""#, 10).unwrap();
+        assert_eq!(hits.iter().map(|h| h.0).collect::<Vec<_>>(), vec![a.id]);
+    }
+
+    #[test]
+    fn test_remove_and_reindex() {
+        let mut a = item("first title");
+        let mut index = index(&[&a]);
+        assert_eq!(index.search("first", 10).unwrap().len(), 1);
+
+        a.metadata = json!({ "title": "second title" });
+        index.index_item(&a).unwrap();
+        assert!(index.search("first", 10).unwrap().is_empty());
+
+        index.remove_item(&a.id).unwrap();
+        assert!(index.is_empty());
+    }
+
+    #[test]
+    fn test_per_field_analyzers() {
+        let a = VectorItem {
+            id: Uuid::new_v4(),
+            metadata: json!({
+                "title": "Running shoes",
+                "titel": "Laufende Schuhe",
+                "sku": "RUNNING-42"
+            }),
+            ..Default::default()
+        };
+        let mut index = TextIndex::in_memory(TextIndexConfig::from_fields(&[
+            TextFieldConfig::new(
+                "title",
+                TokenizerConfig::for_language(TextLanguage::English),
+            ),
+            TextFieldConfig::new("titel", TokenizerConfig::for_language(TextLanguage::German)),
+        ]));
+        index.index_item(&a).unwrap();
+
+        // Each field sees the query analyzed with its own language
+        assert_eq!(index.search("runs", 10).unwrap().len(), 1);
+        assert_eq!(index.search("schuh", 10).unwrap().len(), 1);
+        // Undeclared string metadata isn't indexed
+        assert!(index.search("42", 10).unwrap().is_empty());
+
+        let query = index.parse("run").unwrap();
+        let highlights = index.highlights(&a.id, &query);
+        assert_eq!(highlights.len(), 1);
+        assert_eq!(highlights[0].field, "title");
+        assert_eq!(highlights[0].term, "run");
+    }
+
+    #[test]
+    fn test_highlight_offsets() {
+        let a = VectorItem {
+            id: Uuid::new_v4(),
+            metadata: json!({
+                "title": "Crème brûlée and vector databases",
+                "tags": [1, "vector search"]
+            }),
+            ..Default::default()
+        };
+        let mut index = TextIndex::in_memory(TextIndexConfig {
+            fields: vec!["title".to_string(), "tags".to_string()],
+            ..Default::default()
+        });
+        index.index_item(&a).unwrap();
+
+        let query = index.parse(r#"brûlée OR "vector search""#).unwrap();
+        let highlights = index.highlights(&a.id, &query);
+        let spans: Vec<(&str, &str, usize, usize)> = highlights
+            .iter()
+            .map(|h| (h.field.as_str(), h.term.as_str(), h.start, h.end))
+            .collect();
+        // "vector" in the title isn't part of the phrase match
+        assert_eq!(
+            spans,
+            vec![
+                ("title", "brûlée", 6, 12),
+                ("tags.1", "vector", 0, 6),
+                ("tags.1", "search", 7, 13),
+            ]
+        );
+
+        // Unsatisfied branches and negated terms aren't highlighted
+        let query = index
+            .parse("(crème AND missing) OR databases -pasta")
+            .unwrap();
+        let terms: Vec<String> = index
+            .highlights(&a.id, &query)
+            .into_iter()
+            .map(|h| h.term)
+            .collect();
+        assert_eq!(terms, vec!["databases"]);
+    }
+}
@@ -0,0 +1,364 @@
+// Copyright 2024-2026 Andrey Vasilevsky <anvanster@gmail.com>
+// SPDX-License-Identifier: Apache-2.0
+
+// Text tokenization for the keyword index
+
+use unicode_segmentation::UnicodeSegmentation;
+use vectrust_core::*;
+
+/// A normalized term and the byte range of the text it came from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Token {
+    pub term: String,
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Token {
+    pub fn new(term: impl Into<String>, start: usize, end: usize) -> Self {
+        Self {
+            term: term.into(),
+            start,
+            end,
+        }
+    }
+}
+
+/// Splits text into index terms.
+///
+/// The position of a term is its index in the returned vector; phrase
+/// queries rely on adjacent terms having consecutive positions. Byte
+/// ranges are used for highlighting and must lie on char boundaries.
+pub trait Tokenizer: Send + Sync {
+    fn tokenize(&self, text: &str) -> Vec<Token>;
+}
+
+impl<F> Tokenizer for F
+where
+    F: Fn(&str) -> Vec<Token> + Send + Sync,
+{
+    fn tokenize(&self, text: &str) -> Vec<Token> {
+        self(text)
+    }
+}
+
+/// The configurable built-in tokenizer
+#[derive(Debug, Clone, Default)]
+pub struct Analyzer {
+    config: TokenizerConfig,
+}
+
+impl Analyzer {
+    pub fn new(config: TokenizerConfig) -> Self {
+        Self { config }
+    }
+
+    /// Words with their byte offsets, with adjacent CJK words merged into runs
+    fn words<'a>(&self, text: &'a str) -> Vec<(usize, &'a str)> {
+        let words: Vec<(usize, &str)> = match self.config.kind {
+            TokenizerKind::Whitespace => text
+                .split_whitespace()
+                .map(|w| (w.as_ptr() as usize - text.as_ptr() as usize, w))
+                .collect(),
+            TokenizerKind::Unicode => text.unicode_word_indices().collect(),
+        };
+
+        // UAX #29 splits ideographs into single characters; rejoin touching runs
+        let mut merged: Vec<(usize, &str)> = Vec::with_capacity(words.len());
+        for (start, word) in words {
+            if let Some(last) = merged.last_mut() {
+                let end = last.0 + last.1.len();
+                if end == start && is_cjk_word(last.1) && is_cjk_word(word) {
+                    last.1 = &text[last.0..start + word.len()];
+                    continue;
+                }
+            }
+            merged.push((start, word));
+        }
+        merged
+    }
+
+    /// Lowercase, drop stopwords and stem; `None` means the word isn't indexed
+    fn normalize(&self, word: &str) -> Option<String> {
+        let word = if self.config.lowercase {
+            word.to_lowercase()
+        } else {
+            word.to_string()
+        };
+
+        let language = self.config.language.unwrap_or(TextLanguage::English);
+        if self.config.stopwords && stopwords(language).contains(&word.to_lowercase().as_str()) {
+            return None;
+        }
+
+        Some(if self.config.stem {
+            stem(&word, language)
+        } else {
+            word
+        })
+    }
+
+    fn cjk_bigrams(&self) -> bool {
+        self.config.cjk_bigrams || self.config.language == Some(TextLanguage::Cjk)
+    }
+}
+
+impl Tokenizer for Analyzer {
+    fn tokenize(&self, text: &str) -> Vec<Token> {
+        let mut tokens = Vec::new();
+        for (start, word) in self.words(text) {
+            if is_cjk_word(word) {
+                let chars: Vec<(usize, char)> = word
+                    .char_indices()
+                    .map(|(offset, c)| (start + offset, c))
+                    .collect();
+                if self.cjk_bigrams() && chars.len() > 1 {
+                    tokens.extend(chars.windows(2).map(|pair| {
+                        let (a, b) = (pair[0], pair[1]);
+                        Token::new(format!("{}{}", a.1, b.1), a.0, b.0 + b.1.len_utf8())
+                    }));
+                } else {
+                    tokens.extend(
+                        chars
+                            .iter()
+                            .map(|&(offset, c)| Token::new(c, offset, offset + c.len_utf8())),
+                    );
+                }
+            } else if let Some(term) = self.normalize(word) {
+                tokens.push(Token::new(term, start, start + word.len()));
+            }
+        }
+        tokens
+    }
+}
+
+fn is_cjk(c: char) -> bool {
+    matches!(c as u32,
+        0x3040..=0x30FF       // Hiragana, Katakana
+        | 0x3400..=0x4DBF     // CJK Extension A
+        | 0x4E00..=0x9FFF     // CJK Unified Ideographs
+        | 0xAC00..=0xD7AF     // Hangul syllables
+        | 0xF900..=0xFAFF     // CJK Compatibility Ideographs
+        | 0x20000..=0x2A6DF) // CJK Extension B
+}
+
+fn is_cjk_word(word: &str) -> bool {
+    !word.is_empty() && word.chars().all(is_cjk)
+}
+
+fn stopwords(language: TextLanguage) -> &'static [&'static str] {
+    match language {
+        TextLanguage::English => &[
+            "a", "an", "and", "are", "as", "at", "be", "but", "by", "for", "if", "in", "into",
+            "is", "it", "no", "not", "of", "on", "or", "such", "that", "the", "their", "then",
+            "there", "these", "they", "this", "to", "was", "will", "with",
+        ],
+        TextLanguage::German => &[
+            "aber", "als", "am", "an", "auch", "auf", "aus", "bei", "das", "dass", "dem", "den",
+            "der", "des", "die", "ein", "eine", "einen", "einer", "es", "für", "im", "in", "ist",
+            "mit", "nicht", "oder", "sich", "sie", "und", "von", "zu", "zum", "zur",
+        ],
+        TextLanguage::French => &[
+            "au", "aux", "avec", "ce", "ces", "dans", "de", "des", "du", "elle", "en", "et", "il",
+            "je", "la", "le", "les", "leur", "lui", "mais", "ne", "nous", "ou", "par", "pas",
+            "pour", "qui", "que", "sa", "se", "son", "sur", "un", "une", "vous",
+        ],
+        TextLanguage::Spanish => &[
+            "a", "al", "como", "con", "de", "del", "el", "en", "es", "esta", "la", "las", "lo",
+            "los", "más", "no", "o", "para", "pero", "por", "que", "se", "sin", "su", "sus", "un",
+            "una", "y",
+        ],
+        TextLanguage::Cjk => &[],
+    }
+}
+
+/// Light suffix stemmer; short words are left alone
+fn stem(word: &str, language: TextLanguage) -> String {
+    match language {
+        TextLanguage::English => stem_english(word),
+        TextLanguage::German => stem_suffixes(
+            &word
+                .replace('ä', "a")
+                .replace('ö', "o")
+                .replace('ü', "u")
+                .replace('ß', "ss"),
+            &["ern", "em", "en", "er", "es", "e", "s", "n"],
+        ),
+        TextLanguage::French => stem_suffixes(
+            word,
+            &[
+                "issements",
+                "issement",
+                "ements",
+                "ement",
+                "ations",
+                "ation",
+                "euses",
+                "euse",
+                "ités",
+                "ité",
+                "eux",
+                "es",
+                "s",
+                "e",
+                "x",
+            ],
+        ),
+        TextLanguage::Spanish => stem_suffixes(
+            word,
+            &[
+                "aciones", "ación", "amente", "mente", "idades", "idad", "es", "os", "as", "s",
+                "a", "o", "e",
+            ],
+        ),
+        TextLanguage::Cjk => word.to_string(),
+    }
+}
+
+/// Strip the first (longest-listed) matching suffix, keeping at least three characters
+fn stem_suffixes(word: &str, suffixes: &[&str]) -> String {
+    suffixes
+        .iter()
+        .find_map(|suffix| {
+            word.strip_suffix(suffix)
+                .filter(|stem| stem.chars().count() >= 3)
+        })
+        .unwrap_or(word)
+        .to_string()
+}
+
+fn stem_english(word: &str) -> String {
+    if word.len() < 4 || !word.bytes().all(|b| b.is_ascii_lowercase()) {
+        return word.to_string();
+    }
+
+    let strip = |suffix: &str, replacement: &str, min_stem: usize| {
+        word.strip_suffix(suffix)
+            .filter(|stem| stem.len() >= min_stem)
+            .map(|stem| format!("{stem}{replacement}"))
+    };
+
+    let stemmed = strip("sses", "ss", 2)
+        .or_else(|| strip("ies", "y", 2))
+        .or_else(|| strip("ied", "y", 2))
+        .or_else(|| strip("ing", "", 3))
+        .or_else(|| strip("edly", "", 3))
+        .or_else(|| strip("ed", "", 3))
+        .or_else(|| strip("ly", "", 3))
+        .or_else(|| {
+            (!word.ends_with("ss") && !word.ends_with("us"))
+                .then(|| strip("s", "", 3))
+                .flatten()
+        });
+
+    match stemmed {
+        // "running" -> "runn" -> "run"
+        Some(s) if s.len() > 2 && s.as_bytes()[s.len() - 1] == s.as_bytes()[s.len() - 2] => {
+            let last = s.as_bytes()[s.len() - 1];
+            if b"lsz".contains(&last) {
+                s
+            } else {
+                s[..s.len() - 1].to_string()
+            }
+        }
+        Some(s) => s,
+        None => word.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn terms(config: TokenizerConfig, text: &str) -> Vec<String> {
+        Analyzer::new(config)
+            .tokenize(text)
+            .into_iter()
+            .map(|t| t.term)
+            .collect()
+    }
+
+    #[test]
+    fn test_whitespace_and_unicode_tokenizers() {
+        let whitespace = TokenizerConfig {
+            kind: TokenizerKind::Whitespace,
+            ..Default::default()
+        };
+        assert_eq!(terms(whitespace, "Hello, World"), vec!["hello,", "world"]);
+
+        assert_eq!(
+            terms(TokenizerConfig::default(), "Hello, World! Crème brûlée"),
+            vec!["hello", "world", "crème", "brûlée"]
+        );
+    }
+
+    #[test]
+    fn test_stemming() {
+        let stemming = TokenizerConfig {
+            stem: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            terms(stemming, "Running dogs quickly studied classes"),
+            vec!["run", "dog", "quick", "study", "class"]
+        );
+    }
+
+    #[test]
+    fn test_cjk_bigrams() {
+        assert_eq!(
+            terms(TokenizerConfig::default(), "東京都"),
+            vec!["東", "京", "都"]
+        );
+
+        let bigrams = TokenizerConfig {
+            cjk_bigrams: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            terms(bigrams, "東京都 tokyo"),
+            vec!["東京", "京都", "tokyo"]
+        );
+    }
+
+    #[test]
+    fn test_language_analyzers() {
+        assert_eq!(
+            terms(
+                TokenizerConfig::for_language(TextLanguage::English),
+                "The cats of the house"
+            ),
+            vec!["cat", "house"]
+        );
+        assert_eq!(
+            terms(
+                TokenizerConfig::for_language(TextLanguage::German),
+                "Die Häuser und Gärten"
+            ),
+            vec!["haus", "gart"]
+        );
+        assert_eq!(
+            terms(
+                TokenizerConfig::for_language(TextLanguage::Spanish),
+                "Las casas rápidamente"
+            ),
+            vec!["cas", "rápid"]
+        );
+        assert_eq!(
+            terms(TokenizerConfig::for_language(TextLanguage::Cjk), "東京都"),
+            vec!["東京", "京都"]
+        );
+    }
+
+    #[test]
+    fn test_token_offsets() {
+        let text = "Crème 東京 brûlée";
+        let tokens = Analyzer::new(TokenizerConfig {
+            cjk_bigrams: true,
+            ..Default::default()
+        })
+        .tokenize(text);
+        let spans: Vec<&str> = tokens.iter().map(|t| &text[t.start..t.end]).collect();
+        assert_eq!(spans, vec!["Crème", "東京", "brûlée"]);
+    }
+}
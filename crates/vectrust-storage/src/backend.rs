@@ -2,25 +2,153 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use std::path::Path;
+use std::sync::{Arc, Mutex};
 use vectrust_core::*;
 
+/// Whether the folder at `path` holds an index in a registered backend's format
+pub type DetectFn = dyn Fn(&Path, &str) -> bool + Send + Sync;
+
+/// Opens, or prepares to create, an index at `path` in a registered backend's format
+pub type OpenFn = dyn Fn(&Path, &str) -> Result<Box<dyn StorageBackend>> + Send + Sync;
+
+struct RegisteredBackend {
+    name: String,
+    detect: Box<DetectFn>,
+    open: Box<OpenFn>,
+}
+
+/// Backends registered by other crates, most recently registered last
+static BACKENDS: Mutex<Vec<Arc<RegisteredBackend>>> = Mutex::new(Vec::new());
+
 pub struct Storage;
 
 impl Storage {
+    /// Register a third-party backend under `name`. [`Storage::auto_detect`]
+    /// asks `detect` about each folder before trying the built-in formats and
+    /// opens the folder with `open` when it matches; later registrations are
+    /// asked first. Registering a name again replaces the earlier backend.
+    pub fn register_backend(
+        name: &str,
+        detect: impl Fn(&Path, &str) -> bool + Send + Sync + 'static,
+        open: impl Fn(&Path, &str) -> Result<Box<dyn StorageBackend>> + Send + Sync + 'static,
+    ) {
+        let mut backends = BACKENDS.lock().unwrap();
+        backends.retain(|backend| backend.name != name);
+        backends.push(Arc::new(RegisteredBackend {
+            name: name.to_string(),
+            detect: Box::new(detect),
+            open: Box::new(open),
+        }));
+    }
+
+    /// Remove the backend registered under `name`, returning whether there was one
+    pub fn unregister_backend(name: &str) -> bool {
+        let mut backends = BACKENDS.lock().unwrap();
+        let before = backends.len();
+        backends.retain(|backend| backend.name != name);
+        backends.len() != before
+    }
+
+    /// Names of the registered third-party backends
+    pub fn registered_backends() -> Vec<String> {
+        let backends = BACKENDS.lock().unwrap();
+        backends
+            .iter()
+            .map(|backend| backend.name.clone())
+            .collect()
+    }
+
+    /// Open the folder with the backend registered under `name`, whatever
+    /// its detection predicate says. This is how a new index is created in
+    /// a third-party format.
+    pub fn open_registered(
+        name: &str,
+        path: &Path,
+        index_name: &str,
+    ) -> Result<Box<dyn StorageBackend>> {
+        let backend = BACKENDS
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|backend| backend.name == name)
+            .cloned()
+            .ok_or_else(|| VectraError::Storage {
+                message: format!("no storage backend registered as `{}`", name),
+            })?;
+        (backend.open)(path, index_name)
+    }
+
     /// Auto-detect storage format and return appropriate backend
     pub fn auto_detect(path: &Path, index_name: &str) -> Result<Box<dyn StorageBackend>> {
         let index_path = path.join(index_name);
         let manifest_path = path.join("manifest.json");
 
+        // Copied out so a backend's callbacks can register others
+        let registered: Vec<_> = BACKENDS.lock().unwrap().clone();
+        if let Some(backend) = registered
+            .iter()
+            .rev()
+            .find(|backend| (backend.detect)(path, index_name))
+        {
+            return (backend.open)(path, index_name);
+        }
+
         if manifest_path.exists() {
-            // V2 optimized format
-            Ok(Box::new(crate::OptimizedStorage::new(path)?))
+            // V2 format - the manifest records which engine wrote it
+            let format = Self::manifest_format(&manifest_path)
+                .map_err(|e| crate::manifest::damaged_manifest(path, e))?;
+            match format.as_deref() {
+                Some("redb") => Self::redb(path),
+                _ => Self::optimized(path),
+            }
         } else if index_path.exists() {
             // V1 legacy format
             Ok(Box::new(crate::LegacyStorage::new(path, index_name)?))
-        } else {
+        } else if cfg!(feature = "rocksdb") {
             // New index - use optimized format
-            Ok(Box::new(crate::OptimizedStorage::new(path)?))
+            Self::optimized(path)
+        } else if cfg!(feature = "redb") {
+            // New index without RocksDB support - use the pure-Rust optimized format
+            Self::redb(path)
+        } else {
+            // New index without any v2 engine - fall back to legacy format
+            Ok(Box::new(crate::LegacyStorage::new(path, index_name)?))
         }
     }
+
+    fn manifest_format(manifest_path: &Path) -> Result<Option<String>> {
+        let content = std::fs::read_to_string(manifest_path)?;
+        let manifest: serde_json::Value = serde_json::from_str(&content)?;
+        Ok(manifest["format"].as_str().map(str::to_string))
+    }
+
+    #[cfg(feature = "rocksdb")]
+    fn optimized(path: &Path) -> Result<Box<dyn StorageBackend>> {
+        Ok(Box::new(crate::OptimizedStorage::new(path)?))
+    }
+
+    #[cfg(not(feature = "rocksdb"))]
+    fn optimized(path: &Path) -> Result<Box<dyn StorageBackend>> {
+        Err(VectraError::Storage {
+            message: format!(
+                "{} uses the optimized storage format, which requires the `rocksdb` feature",
+                path.display()
+            ),
+        })
+    }
+
+    #[cfg(feature = "redb")]
+    fn redb(path: &Path) -> Result<Box<dyn StorageBackend>> {
+        Ok(Box::new(crate::RedbStorage::new(path)?))
+    }
+
+    #[cfg(not(feature = "redb"))]
+    fn redb(path: &Path) -> Result<Box<dyn StorageBackend>> {
+        Err(VectraError::Storage {
+            message: format!(
+                "{} uses the redb storage format, which requires the `redb` feature",
+                path.display()
+            ),
+        })
+    }
 }
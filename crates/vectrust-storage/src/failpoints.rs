@@ -0,0 +1,128 @@
+// Copyright 2024-2026 Andrey Vasilevsky <anvanster@gmail.com>
+// SPDX-License-Identifier: Apache-2.0
+
+//! Failpoints for crash testing OptimizedStorage.
+//!
+//! Each write path passes named points between its manifest, RocksDB and
+//! memory-mapped steps. With the `failpoints` feature a test can make a
+//! point fail, or simulate the process dying there, then reopen the index
+//! and check that recovery left it consistent. Without the feature every
+//! point compiles to nothing.
+
+use vectrust_core::*;
+
+/// Vector bytes written to the mmap file, RocksDB records not yet written
+pub const INSERT_AFTER_VECTOR_WRITE: &str = "insert.after_vector_write";
+/// RocksDB records written, manifest count not yet updated
+pub const INSERT_AFTER_DB_WRITE: &str = "insert.after_db_write";
+/// Updated vectors written to their new slots, RocksDB records not yet moved
+pub const UPDATE_AFTER_VECTOR_WRITE: &str = "update.after_vector_write";
+/// Record marked deleted in RocksDB, manifest count not yet updated
+pub const DELETE_AFTER_DB_WRITE: &str = "delete.after_db_write";
+/// Live vectors copied to the next vector file, records not yet moved to it
+pub const COMPACT_BEFORE_SWAP: &str = "compact.before_swap";
+/// Records moved to the new vector file, the old one not yet removed
+pub const COMPACT_AFTER_SWAP: &str = "compact.after_swap";
+/// Cold vectors written to the next cold file, records not yet moved to it
+pub const RETIER_BEFORE_SWAP: &str = "retier.before_swap";
+/// Records moved to the new cold file, the old one not yet removed
+pub const RETIER_AFTER_SWAP: &str = "retier.after_swap";
+/// New manifest written to its temporary file, not yet renamed into place
+pub const MANIFEST_BEFORE_RENAME: &str = "manifest.before_rename";
+
+/// What happens when execution reaches an armed failpoint
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FailAction {
+    /// Return an error; the storage stays usable
+    Error,
+    /// Behave as if the process was killed: this and every later
+    /// failpoint errors, and dropping the storage persists nothing more
+    Crash,
+}
+
+/// Per-storage failpoint registry
+#[derive(Debug, Default)]
+pub struct Failpoints {
+    #[cfg(feature = "failpoints")]
+    armed: std::sync::Mutex<std::collections::HashMap<String, FailAction>>,
+    #[cfg(feature = "failpoints")]
+    crashed: std::sync::atomic::AtomicBool,
+}
+
+impl Failpoints {
+    /// Arm `name` to fire with `action` every time it is reached
+    #[cfg(feature = "failpoints")]
+    pub fn set(&self, name: &str, action: FailAction) {
+        self.armed.lock().unwrap().insert(name.to_string(), action);
+    }
+
+    /// Disarm every failpoint
+    #[cfg(feature = "failpoints")]
+    pub fn clear(&self) {
+        self.armed.lock().unwrap().clear();
+    }
+
+    /// Whether a `Crash` failpoint has fired
+    pub fn crashed(&self) -> bool {
+        #[cfg(feature = "failpoints")]
+        {
+            self.crashed.load(std::sync::atomic::Ordering::SeqCst)
+        }
+        #[cfg(not(feature = "failpoints"))]
+        {
+            false
+        }
+    }
+
+    /// Called by the storage at each point
+    #[cfg(feature = "failpoints")]
+    pub(crate) fn hit(&self, name: &str) -> Result<()> {
+        use std::sync::atomic::Ordering;
+
+        let action = self.armed.lock().unwrap().get(name).copied();
+        if action == Some(FailAction::Crash) {
+            self.crashed.store(true, Ordering::SeqCst);
+        }
+        if self.crashed.load(Ordering::SeqCst) {
+            return Err(VectraError::StorageError {
+                message: format!("Simulated crash at failpoint {}", name),
+            });
+        }
+        if action == Some(FailAction::Error) {
+            return Err(VectraError::StorageError {
+                message: format!("Injected error at failpoint {}", name),
+            });
+        }
+        Ok(())
+    }
+
+    #[cfg(not(feature = "failpoints"))]
+    #[inline(always)]
+    pub(crate) fn hit(&self, _name: &str) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "failpoints"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_and_crash_actions() {
+        let failpoints = Failpoints::default();
+        assert!(failpoints.hit(INSERT_AFTER_DB_WRITE).is_ok());
+
+        failpoints.set(INSERT_AFTER_DB_WRITE, FailAction::Error);
+        assert!(failpoints.hit(INSERT_AFTER_DB_WRITE).is_err());
+        assert!(failpoints.hit(DELETE_AFTER_DB_WRITE).is_ok());
+        failpoints.clear();
+        assert!(failpoints.hit(INSERT_AFTER_DB_WRITE).is_ok());
+
+        // A crash takes every later point down with it
+        failpoints.set(MANIFEST_BEFORE_RENAME, FailAction::Crash);
+        assert!(failpoints.hit(MANIFEST_BEFORE_RENAME).is_err());
+        failpoints.clear();
+        assert!(failpoints.hit(INSERT_AFTER_VECTOR_WRITE).is_err());
+        assert!(failpoints.crashed());
+    }
+}
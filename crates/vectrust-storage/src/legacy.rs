@@ -2,17 +2,43 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use async_trait::async_trait;
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::path::{Path, PathBuf};
 use tokio::fs;
 use uuid::Uuid;
 use vectrust_core::*;
 
+type JsonMap = serde_json::Map<String, serde_json::Value>;
+
+/// Item fields vectrust reads, under either naming convention; any other
+/// field is carried through unchanged
+const ITEM_FIELDS: &[&str] = &[
+    "id",
+    "vector",
+    "metadata",
+    "indexed",
+    "deleted",
+    "created_at",
+    "createdAt",
+    "updated_at",
+    "updatedAt",
+    "version",
+];
+/// Node's pointer from an item to the file holding its full metadata
+const METADATA_FILE_FIELD: &str = "metadataFile";
+/// Node caches each vector's norm next to it
+const NORM_FIELD: &str = "norm";
+
 /// Legacy storage format compatible with existing vectra-enhanced indexes
 pub struct LegacyStorage {
     path: PathBuf,
     index_name: String,
     cache: tokio::sync::RwLock<Option<LegacyIndexFile>>,
+    strict_compat: bool,
+    /// Items reads skip, with what was wrong with them
+    quarantine: std::sync::RwLock<BTreeMap<Uuid, String>>,
 }
 
 /// Exact format matching Node.js index.json structure
@@ -21,6 +47,172 @@ pub struct LegacyIndexFile {
     pub version: u32,
     pub metadata_config: MetadataConfig,
     pub items: Vec<VectorItem>,
+    /// Extension over the Node.js format, omitted when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub embedding_model: Option<EmbeddingModel>,
+    /// Extension over the Node.js format, omitted when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub limits: Option<IndexLimits>,
+    /// Extension over the Node.js format, omitted when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vector_transform: Option<VectorTransform>,
+    /// Extension over the Node.js format, omitted when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vector_validation: Option<VectorValidation>,
+    /// Extension over the Node.js format, omitted when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub namespace_field: Option<String>,
+    /// Top-level fields written by other versions, kept on round-trip
+    #[serde(flatten)]
+    pub extra: JsonMap,
+    /// Item fields written by other versions (such as Node's `norm` and
+    /// `metadataFile`), kept on round-trip
+    #[serde(skip)]
+    pub item_extra: HashMap<Uuid, JsonMap>,
+}
+
+impl LegacyIndexFile {
+    /// Parse an index file, setting aside item fields vectrust doesn't know
+    fn from_json(content: &str) -> Result<Self> {
+        let mut raw: JsonMap = serde_json::from_str(content)?;
+        let raw_items: Vec<serde_json::Value> =
+            serde_json::from_value(raw.remove("items").unwrap_or_default())?;
+
+        let mut items = Vec::with_capacity(raw_items.len());
+        let mut item_extra = HashMap::new();
+        for value in raw_items {
+            let extra: JsonMap = value
+                .as_object()
+                .into_iter()
+                .flatten()
+                .filter(|(key, _)| !ITEM_FIELDS.contains(&key.as_str()))
+                .map(|(key, value)| (key.clone(), value.clone()))
+                .collect();
+            let item: VectorItem = serde_json::from_value(value)?;
+            if !extra.is_empty() {
+                item_extra.insert(item.id, extra);
+            }
+            items.push(item);
+        }
+
+        raw.insert("items".to_string(), serde_json::Value::Array(Vec::new()));
+        let header: LegacyIndexFile = serde_json::from_value(serde_json::Value::Object(raw))?;
+        Ok(Self {
+            items,
+            item_extra,
+            ..header
+        })
+    }
+
+    /// Serialize with the fields other versions wrote put back. `strict`
+    /// names vectrust's own item fields in Node's camelCase.
+    fn to_json(&self, strict: bool) -> Result<String> {
+        let items = self
+            .items
+            .iter()
+            .map(|item| {
+                let mut extra = self.item_extra.get(&item.id).cloned().unwrap_or_default();
+                // A cached norm must follow vector updates
+                if extra.contains_key(NORM_FIELD) {
+                    extra.insert(NORM_FIELD.to_string(), norm(&item.vector).into());
+                }
+                ItemOut {
+                    native: (!strict).then_some(item),
+                    camel: strict.then(|| CamelItem::from(item)),
+                    extra,
+                }
+            })
+            .collect();
+
+        Ok(serde_json::to_string_pretty(&IndexOut {
+            version: self.version,
+            metadata_config: &self.metadata_config,
+            items,
+            embedding_model: self.embedding_model.as_ref(),
+            limits: self.limits.as_ref(),
+            vector_transform: self.vector_transform.as_ref(),
+            vector_validation: self.vector_validation.as_ref(),
+            namespace_field: self.namespace_field.as_deref(),
+            extra: &self.extra,
+        })?)
+    }
+
+    fn metadata_file(&self, id: &Uuid) -> Option<&str> {
+        self.item_extra.get(id)?.get(METADATA_FILE_FIELD)?.as_str()
+    }
+
+    /// Metadata files the items point at
+    fn referenced_metadata_files(&self) -> HashSet<String> {
+        self.items
+            .iter()
+            .filter_map(|item| match self.metadata_file(&item.id) {
+                Some(file) => Some(file.to_string()),
+                None if is_placeholder(&item.metadata) => Some(format!("{}.json", item.id)),
+                None => None,
+            })
+            .collect()
+    }
+}
+
+/// Index file layout on write, borrowing from [`LegacyIndexFile`]
+#[derive(Serialize)]
+struct IndexOut<'a> {
+    version: u32,
+    metadata_config: &'a MetadataConfig,
+    items: Vec<ItemOut<'a>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    embedding_model: Option<&'a EmbeddingModel>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    limits: Option<&'a IndexLimits>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    vector_transform: Option<&'a VectorTransform>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    vector_validation: Option<&'a VectorValidation>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    namespace_field: Option<&'a str>,
+    #[serde(flatten)]
+    extra: &'a JsonMap,
+}
+
+/// One item on write: its own fields under one naming convention, then
+/// the fields other versions wrote
+#[derive(Serialize)]
+struct ItemOut<'a> {
+    #[serde(flatten)]
+    native: Option<&'a VectorItem>,
+    #[serde(flatten)]
+    camel: Option<CamelItem<'a>>,
+    #[serde(flatten)]
+    extra: JsonMap,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CamelItem<'a> {
+    id: &'a Uuid,
+    vector: &'a [f32],
+    metadata: &'a serde_json::Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    indexed: Option<&'a serde_json::Value>,
+    deleted: bool,
+    created_at: &'a DateTime<Utc>,
+    updated_at: &'a DateTime<Utc>,
+    version: u32,
+}
+
+impl<'a> From<&'a VectorItem> for CamelItem<'a> {
+    fn from(item: &'a VectorItem) -> Self {
+        Self {
+            id: &item.id,
+            vector: &item.vector,
+            metadata: &item.metadata,
+            indexed: item.indexed.as_ref(),
+            deleted: item.deleted,
+            created_at: &item.created_at,
+            updated_at: &item.updated_at,
+            version: item.version,
+        }
+    }
 }
 
 impl LegacyStorage {
@@ -29,27 +221,34 @@ impl LegacyStorage {
             path: path.to_path_buf(),
             index_name: index_name.to_string(),
             cache: tokio::sync::RwLock::new(None),
+            strict_compat: false,
+            quarantine: std::sync::RwLock::new(BTreeMap::new()),
         })
     }
 
+    /// Write items the way the Node.js library does: camelCase field names
+    /// and a `metadataFile` pointer for externally stored metadata, so both
+    /// libraries can keep writing to the same directory. Fields either
+    /// version doesn't know are preserved in both modes.
+    pub fn with_strict_compat(mut self) -> Self {
+        self.strict_compat = true;
+        self
+    }
+
     async fn perform_vector_search(
         &self,
-        items: &[VectorItem],
+        index: &LegacyIndexFile,
         query_vector: &[f32],
         top_k: usize,
     ) -> Result<Vec<QueryResult>> {
-        let mut results = self.compute_similarity_scores(items, query_vector)?;
+        let mut results = self.compute_similarity_scores(&index.items, query_vector)?;
 
         // Sort by score descending and apply limit
-        results.sort_by(|a, b| {
-            b.score
-                .partial_cmp(&a.score)
-                .unwrap_or(std::cmp::Ordering::Equal)
-        });
+        results.sort_by(QueryResult::rank_cmp);
         results.truncate(top_k);
 
         // Load external metadata for results
-        self.load_results_metadata(&mut results).await?;
+        self.load_results_metadata(index, &mut results).await?;
 
         Ok(results)
     }
@@ -60,8 +259,12 @@ impl LegacyStorage {
         query_vector: &[f32],
     ) -> Result<Vec<QueryResult>> {
         let mut results = Vec::new();
+        let quarantine = self.quarantine.read().unwrap();
 
         for item in items {
+            if quarantine.contains_key(&item.id) {
+                continue;
+            }
             if let Some(result) = Self::evaluate_item_similarity(item, query_vector) {
                 results.push(result);
             }
@@ -90,25 +293,101 @@ impl LegacyStorage {
             Some(QueryResult {
                 item: item.clone(),
                 score: similarity,
+                highlights: Vec::new(),
+                payload: None,
             })
         } else {
             None
         }
     }
 
-    async fn load_results_metadata(&self, results: &mut Vec<QueryResult>) -> Result<()> {
+    async fn load_results_metadata(
+        &self,
+        index: &LegacyIndexFile,
+        results: &mut Vec<QueryResult>,
+    ) -> Result<()> {
         for result in results {
-            if let Some(external_metadata) = self.load_metadata(&result.item.id).await? {
-                result.item.metadata = external_metadata;
-            }
+            self.resolve_metadata(index, &mut result.item).await?;
+        }
+        Ok(())
+    }
+
+    /// Load the full metadata of an externally stored item. Items point at
+    /// their file with `metadataFile`; older vectrust items instead keep
+    /// an empty placeholder and use `{id}.json`. Non-empty inline metadata
+    /// without a pointer is authoritative, so a stale file can never shadow it.
+    async fn resolve_metadata(&self, index: &LegacyIndexFile, item: &mut VectorItem) -> Result<()> {
+        let file = match index.metadata_file(&item.id) {
+            Some(file) => file.to_string(),
+            None if is_placeholder(&item.metadata) => format!("{}.json", item.id),
+            None => return Ok(()),
+        };
+        if let Some(external_metadata) = self.load_metadata(&file).await? {
+            item.metadata = external_metadata;
         }
         Ok(())
     }
 
+    /// Split `item` into what goes in the index and the metadata file, if
+    /// any. Externally stored items keep their indexed fields inline, as
+    /// the Node.js library expects.
+    async fn store_metadata(
+        &self,
+        index: &mut LegacyIndexFile,
+        item: &VectorItem,
+    ) -> Result<VectorItem> {
+        let mut item_to_store = item.clone();
+        let metadata_size = serde_json::to_string(&item.metadata)?.len();
+        let current = index.metadata_file(&item.id).map(str::to_string);
+
+        if index.metadata_config.stores_externally(metadata_size) {
+            let file = current.unwrap_or_else(|| format!("{}.json", item.id));
+            self.save_metadata(&file, &item.metadata).await?;
+            item_to_store.metadata = serde_json::Value::Object(
+                index
+                    .metadata_config
+                    .indexed
+                    .iter()
+                    .filter_map(|key| Some((key.clone(), item.metadata.get(key)?.clone())))
+                    .collect(),
+            );
+            index
+                .item_extra
+                .entry(item.id)
+                .or_default()
+                .insert(METADATA_FILE_FIELD.to_string(), file.into());
+        } else {
+            // Remove external metadata file if it exists
+            if let Some(file) = current {
+                self.delete_metadata(&file).await?;
+                if let Some(extra) = index.item_extra.get_mut(&item.id) {
+                    extra.remove(METADATA_FILE_FIELD);
+                }
+            }
+            self.delete_metadata(&format!("{}.json", item.id)).await?;
+        }
+        Ok(item_to_store)
+    }
+
     fn index_path(&self) -> PathBuf {
         self.path.join(&self.index_name)
     }
 
+    /// Read one index-wide setting, `None` when there is no index
+    async fn index_setting<T>(
+        &self,
+        setting: impl Fn(&LegacyIndexFile) -> Option<T>,
+    ) -> Result<Option<T>> {
+        if !self.exists().await {
+            return Ok(None);
+        }
+        // Avoid cloning every item just to read one field
+        if let Some(ref index) = *self.cache.read().await {
+            return Ok(setting(index));
+        }
+        Ok(setting(&self.load_index().await?))
+    }
+
     async fn load_index(&self) -> Result<LegacyIndexFile> {
         // Check cache first
         {
@@ -127,7 +406,16 @@ impl LegacyStorage {
         }
 
         let content = fs::read_to_string(&path).await?;
-        let index: LegacyIndexFile = serde_json::from_str(&content)?;
+        let index = LegacyIndexFile::from_json(&content)?;
+        let dimensions = DimensionReport::from_items(&index.items);
+        if dimensions.is_mixed() {
+            // Queries only score items of the query's length
+            tracing::warn!(
+                "{} holds vectors of mixed lengths (length: items) {:?}",
+                path.display(),
+                dimensions.counts
+            );
+        }
 
         // Update cache
         {
@@ -148,7 +436,7 @@ impl LegacyStorage {
 
         // Write atomically via temp file
         let temp_path = path.with_extension("tmp");
-        let content = serde_json::to_string_pretty(index)?;
+        let content = index.to_json(self.strict_compat)?;
         fs::write(&temp_path, content).await?;
         fs::rename(&temp_path, &path).await?;
 
@@ -161,8 +449,29 @@ impl LegacyStorage {
         Ok(())
     }
 
-    async fn load_metadata(&self, id: &Uuid) -> Result<Option<serde_json::Value>> {
-        let metadata_path = self.path.join(format!("{}.json", id));
+    fn is_quarantined(&self, id: &Uuid) -> bool {
+        self.quarantine.read().unwrap().contains_key(id)
+    }
+
+    /// Lift the quarantine of an item that has been rewritten or deleted
+    fn release_quarantine(&self, id: &Uuid) {
+        self.quarantine.write().unwrap().remove(id);
+    }
+
+    /// Path of a metadata file named by an item. Names that could reach
+    /// outside the index directory are refused.
+    fn metadata_path(&self, file: &str) -> Result<PathBuf> {
+        let name = Path::new(file);
+        if name.file_name() != Some(name.as_os_str()) {
+            return Err(VectraError::Storage {
+                message: format!("Invalid metadata file name {:?}", file),
+            });
+        }
+        Ok(self.path.join(name))
+    }
+
+    async fn load_metadata(&self, file: &str) -> Result<Option<serde_json::Value>> {
+        let metadata_path = self.metadata_path(file)?;
 
         if !metadata_path.exists() {
             return Ok(None);
@@ -173,20 +482,88 @@ impl LegacyStorage {
         Ok(Some(metadata))
     }
 
-    async fn save_metadata(&self, id: &Uuid, metadata: &serde_json::Value) -> Result<()> {
-        let metadata_path = self.path.join(format!("{}.json", id));
+    async fn save_metadata(&self, file: &str, metadata: &serde_json::Value) -> Result<()> {
+        let metadata_path = self.metadata_path(file)?;
         let content = serde_json::to_string_pretty(metadata)?;
         fs::write(metadata_path, content).await?;
         Ok(())
     }
 
-    async fn delete_metadata(&self, id: &Uuid) -> Result<()> {
-        let metadata_path = self.path.join(format!("{}.json", id));
+    async fn delete_metadata(&self, file: &str) -> Result<()> {
+        let metadata_path = self.metadata_path(file)?;
         if metadata_path.exists() {
             fs::remove_file(metadata_path).await?;
         }
         Ok(())
     }
+
+    /// Every `{uuid}.json` metadata file in the index directory, by name
+    async fn metadata_files(&self) -> Result<Vec<(String, PathBuf)>> {
+        let index_path = self.index_path();
+        let mut dir = fs::read_dir(&self.path).await?;
+        let mut files = Vec::new();
+
+        while let Some(entry) = dir.next_entry().await? {
+            let path = entry.path();
+            if path.extension().is_none_or(|ext| ext != "json") || path == index_path {
+                continue;
+            }
+            // Only UUID filenames are metadata files
+            let is_uuid = path
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .is_some_and(|stem| Uuid::parse_str(stem).is_ok());
+            if is_uuid {
+                let name = entry.file_name().to_string_lossy().to_string();
+                files.push((name, path));
+            }
+        }
+        Ok(files)
+    }
+
+    /// Metadata files no stored item refers to, left behind when a write
+    /// was interrupted between saving the file and saving the index, or
+    /// shadowed by metadata the item now keeps inline
+    pub async fn orphaned_metadata_files(&self) -> Result<Vec<PathBuf>> {
+        if !self.exists().await {
+            return Ok(Vec::new());
+        }
+        let live = self.load_index().await?.referenced_metadata_files();
+        Ok(self
+            .metadata_files()
+            .await?
+            .into_iter()
+            .filter(|(name, _)| !live.contains(name))
+            .map(|(_, path)| path)
+            .collect())
+    }
+
+    /// Delete orphaned metadata files, returning the ones removed
+    pub async fn remove_orphaned_metadata_files(&mut self) -> Result<Vec<PathBuf>> {
+        // Writes need `&mut self`, so no insert can add a live file meanwhile
+        let orphans = self.orphaned_metadata_files().await?;
+        for path in &orphans {
+            match fs::remove_file(path).await {
+                Ok(()) => {}
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+        Ok(orphans)
+    }
+}
+
+fn norm(vector: &[f32]) -> f64 {
+    vector
+        .iter()
+        .map(|x| f64::from(*x) * f64::from(*x))
+        .sum::<f64>()
+        .sqrt()
+}
+
+/// Metadata an externally stored item leaves in the index
+fn is_placeholder(metadata: &serde_json::Value) -> bool {
+    metadata.as_object().is_some_and(|map| map.is_empty())
 }
 
 #[async_trait]
@@ -208,13 +585,24 @@ impl StorageBackend for LegacyStorage {
             version: config.version,
             metadata_config: config.metadata_config.clone(),
             items: Vec::new(),
+            embedding_model: config.embedding_model.clone(),
+            limits: config.limits.clone(),
+            vector_transform: config.vector_transform.clone(),
+            vector_validation: config.vector_validation.clone(),
+            namespace_field: config.namespace_field.clone(),
+            extra: JsonMap::new(),
+            item_extra: HashMap::new(),
         };
 
         self.save_index(&index).await?;
+        self.quarantine.write().unwrap().clear();
         Ok(())
     }
 
     async fn get_item(&self, id: &Uuid) -> Result<Option<VectorItem>> {
+        if self.is_quarantined(id) {
+            return Ok(None);
+        }
         let index = self.load_index().await?;
 
         // Find item in index
@@ -222,9 +610,7 @@ impl StorageBackend for LegacyStorage {
 
         if let Some(mut item) = item.cloned() {
             // Load external metadata if present
-            if let Some(external_metadata) = self.load_metadata(id).await? {
-                item.metadata = external_metadata;
-            }
+            self.resolve_metadata(&index, &mut item).await?;
             Ok(Some(item))
         } else {
             Ok(None)
@@ -232,28 +618,27 @@ impl StorageBackend for LegacyStorage {
     }
 
     async fn insert_item(&mut self, item: &VectorItem) -> Result<()> {
+        self.insert_items(std::slice::from_ref(item)).await
+    }
+
+    async fn insert_items(&mut self, items: &[VectorItem]) -> Result<()> {
         let mut index = self.load_index().await?;
 
-        // Check if item already exists
-        if index.items.iter().any(|existing| existing.id == item.id) {
-            return Err(VectraError::Storage {
-                message: format!("Item with ID {} already exists", item.id),
-            });
+        // Nothing is written if any id is already taken
+        let mut ids: HashSet<Uuid> = index.items.iter().map(|existing| existing.id).collect();
+        if let Some(item) = items.iter().find(|item| !ids.insert(item.id)) {
+            return Err(VectraError::ItemAlreadyExists { id: item.id });
         }
 
-        // Handle large metadata (save externally if > 1KB)
-        let mut item_to_store = item.clone();
-        let metadata_size = serde_json::to_string(&item.metadata)?.len();
-
-        if metadata_size > 1024 {
-            // Save metadata externally
-            self.save_metadata(&item.id, &item.metadata).await?;
-            item_to_store.metadata = serde_json::Value::Object(serde_json::Map::new());
+        for item in items {
+            // Large metadata goes to its own file, past the configured threshold
+            let item_to_store = self.store_metadata(&mut index, item).await?;
+            index.items.push(item_to_store);
         }
-
-        // Add to index
-        index.items.push(item_to_store);
         self.save_index(&index).await?;
+        for item in items {
+            self.release_quarantine(&item.id);
+        }
 
         Ok(())
     }
@@ -269,19 +654,31 @@ impl StorageBackend for LegacyStorage {
             .ok_or(VectraError::ItemNotFound)?;
 
         // Handle metadata storage
-        let mut item_to_store = item.clone();
-        let metadata_size = serde_json::to_string(&item.metadata)?.len();
-
-        if metadata_size > 1024 {
-            self.save_metadata(&item.id, &item.metadata).await?;
-            item_to_store.metadata = serde_json::Value::Object(serde_json::Map::new());
-        } else {
-            // Remove external metadata file if it exists
-            self.delete_metadata(&item.id).await?;
-        }
+        let item_to_store = self.store_metadata(&mut index, item).await?;
 
         index.items[position] = item_to_store;
         self.save_index(&index).await?;
+        self.release_quarantine(&item.id);
+
+        Ok(())
+    }
+
+    async fn update_items(&mut self, items: &[VectorItem]) -> Result<()> {
+        let mut index = self.load_index().await?;
+
+        // Rewrite the file once for the whole batch
+        for item in items {
+            let position = index
+                .items
+                .iter()
+                .position(|existing| existing.id == item.id)
+                .ok_or(VectraError::ItemNotFound)?;
+            index.items[position] = self.store_metadata(&mut index, item).await?;
+        }
+        self.save_index(&index).await?;
+        for item in items {
+            self.release_quarantine(&item.id);
+        }
 
         Ok(())
     }
@@ -298,21 +695,33 @@ impl StorageBackend for LegacyStorage {
         }
 
         // Delete external metadata if exists
-        self.delete_metadata(id).await?;
+        if let Some(extra) = index.item_extra.remove(id) {
+            if let Some(file) = extra.get(METADATA_FILE_FIELD).and_then(|f| f.as_str()) {
+                self.delete_metadata(file).await?;
+            }
+        }
+        self.delete_metadata(&format!("{}.json", id)).await?;
 
         self.save_index(&index).await?;
+        self.release_quarantine(id);
         Ok(())
     }
 
     async fn list_items(&self, options: Option<ListOptions>) -> Result<Vec<VectorItem>> {
         let index = self.load_index().await?;
         let mut items = index.items.clone();
+        {
+            let quarantine = self.quarantine.read().unwrap();
+            if !quarantine.is_empty() {
+                items.retain(|item| !quarantine.contains_key(&item.id));
+            }
+        }
+        // The file keeps insertion order; list by id like the other backends
+        items.sort_by_key(|item| item.id);
 
         // Load external metadata for all items
         for item in &mut items {
-            if let Some(external_metadata) = self.load_metadata(&item.id).await? {
-                item.metadata = external_metadata;
-            }
+            self.resolve_metadata(&index, item).await?;
         }
 
         // Apply pagination if specified
@@ -336,7 +745,7 @@ impl StorageBackend for LegacyStorage {
 
         if let Some(ref query_vector) = query.vector {
             let results = self
-                .perform_vector_search(&index.items, query_vector, query.top_k)
+                .perform_vector_search(&index, query_vector, query.top_k)
                 .await?;
             Ok(results)
         } else if let Some(ref _text_query) = query.text {
@@ -372,27 +781,7 @@ impl StorageBackend for LegacyStorage {
             fs::remove_file(&index_path).await?;
 
             // Remove all metadata files
-            let mut dir = fs::read_dir(&self.path).await?;
-            let mut metadata_files = Vec::new();
-
-            while let Some(entry) = dir.next_entry().await? {
-                let path = entry.path();
-                if let Some(ext) = path.extension() {
-                    if ext == "json" && path != index_path {
-                        // Check if it's a UUID filename (metadata file)
-                        if let Some(stem) = path.file_stem() {
-                            if let Some(stem_str) = stem.to_str() {
-                                if Uuid::parse_str(stem_str).is_ok() {
-                                    metadata_files.push(path);
-                                }
-                            }
-                        }
-                    }
-                }
-            }
-
-            // Delete metadata files
-            for metadata_file in metadata_files {
+            for (_, metadata_file) in self.metadata_files().await? {
                 fs::remove_file(metadata_file).await?;
             }
         }
@@ -402,10 +791,102 @@ impl StorageBackend for LegacyStorage {
             let mut cache = self.cache.write().await;
             *cache = None;
         }
+        self.quarantine.write().unwrap().clear();
 
         Ok(())
     }
 
+    /// Orphaned metadata files are the legacy format's only garbage
+    async fn compaction_stats(&self) -> Result<CompactionStats> {
+        if !self.exists().await {
+            return Ok(CompactionStats::default());
+        }
+
+        let index = self.load_index().await?;
+        let live = index.referenced_metadata_files();
+        let mut stats = CompactionStats {
+            live_items: index.items.len(),
+            live_bytes: fs::metadata(self.index_path()).await?.len(),
+            ..Default::default()
+        };
+        for (name, path) in self.metadata_files().await? {
+            let size = fs::metadata(&path).await?.len();
+            if live.contains(&name) {
+                stats.live_bytes += size;
+            } else {
+                stats.deleted_items += 1;
+                stats.reclaimable_bytes += size;
+            }
+        }
+        Ok(stats)
+    }
+
+    async fn compact(&mut self, _max_bytes_per_sec: Option<u64>) -> Result<CompactionStats> {
+        let stats = self.compaction_stats().await?;
+        self.remove_orphaned_metadata_files().await?;
+        Ok(stats)
+    }
+
+    async fn embedding_model(&self) -> Result<Option<EmbeddingModel>> {
+        self.index_setting(|index| index.embedding_model.clone())
+            .await
+    }
+
+    async fn limits(&self) -> Result<Option<IndexLimits>> {
+        self.index_setting(|index| index.limits.clone()).await
+    }
+
+    async fn vector_transform(&self) -> Result<Option<VectorTransform>> {
+        self.index_setting(|index| index.vector_transform.clone())
+            .await
+    }
+
+    async fn vector_validation(&self) -> Result<Option<VectorValidation>> {
+        self.index_setting(|index| index.vector_validation.clone())
+            .await
+    }
+
+    async fn namespace_field(&self) -> Result<Option<String>> {
+        self.index_setting(|index| index.namespace_field.clone())
+            .await
+    }
+
+    /// Quarantines items whose vectors differ in length from most of the
+    /// index, which older files can hold, along with non-finite vectors
+    async fn quarantine_damaged(&self) -> Result<Vec<QuarantinedItem>> {
+        let index = self.load_index().await?;
+        let expected = DimensionReport::from_items(&index.items).expected;
+        {
+            let mut quarantine = self.quarantine.write().unwrap();
+            for item in index.items.iter().filter(|item| !item.deleted) {
+                let len = item.vector.len();
+                if Some(len) != expected {
+                    let reason = format!(
+                        "vector has {} dimensions where most items have {}",
+                        len,
+                        expected.unwrap_or_default()
+                    );
+                    quarantine.insert(item.id, reason);
+                } else if !VectorOps::is_valid_vector(&item.vector) {
+                    quarantine.insert(item.id, "vector holds NaN or infinite values".to_string());
+                }
+            }
+        }
+        Ok(self.quarantined())
+    }
+
+    fn quarantined(&self) -> Vec<QuarantinedItem> {
+        self.quarantine
+            .read()
+            .unwrap()
+            .iter()
+            .map(|(id, reason)| QuarantinedItem {
+                id: *id,
+                reason: reason.clone(),
+            })
+            .collect()
+    }
+
     async fn get_stats(&self) -> Result<IndexStats> {
         if !self.exists().await {
             return Ok(IndexStats {
@@ -413,11 +894,14 @@ impl StorageBackend for LegacyStorage {
                 size: 0,
                 dimensions: None,
                 distance_metric: DistanceMetric::Cosine,
+                namespaces: Default::default(),
+                ann_lag: Default::default(),
+                lock_waits: Default::default(),
             });
         }
 
         let index = self.load_index().await?;
-        let dimensions = index.items.first().map(|item| item.vector.len());
+        let dimensions = DimensionReport::from_items(&index.items).expected;
 
         // Calculate total size
         let index_size = fs::metadata(self.index_path()).await?.len();
@@ -427,6 +911,99 @@ impl StorageBackend for LegacyStorage {
             size: index_size,
             dimensions,
             distance_metric: DistanceMetric::Cosine, // Legacy format always uses cosine
+            namespaces: Default::default(),
+            ann_lag: Default::default(),
+            lock_waits: Default::default(),
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn large_item() -> VectorItem {
+        VectorItem {
+            id: Uuid::new_v4(),
+            vector: vec![1.0, 0.0],
+            metadata: serde_json::json!({ "body": "x".repeat(2048) }),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_orphaned_metadata_cleanup() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut storage = LegacyStorage::new(temp_dir.path(), "index.json").unwrap();
+        storage
+            .create_index(&CreateIndexConfig::default())
+            .await
+            .unwrap();
+
+        let kept = large_item();
+        storage.insert_item(&kept).await.unwrap();
+        assert!(temp_dir.path().join(format!("{}.json", kept.id)).exists());
+
+        // A write that died after saving its metadata file, and an unrelated file
+        let orphan = temp_dir.path().join(format!("{}.json", Uuid::new_v4()));
+        std::fs::write(&orphan, "{}").unwrap();
+        std::fs::write(temp_dir.path().join("notes.json"), "{}").unwrap();
+
+        assert_eq!(
+            storage.orphaned_metadata_files().await.unwrap(),
+            vec![orphan.clone()]
+        );
+        let stats = storage.compaction_stats().await.unwrap();
+        assert_eq!((stats.live_items, stats.deleted_items), (1, 1));
+        assert_eq!(stats.reclaimable_bytes, 2);
+
+        storage.compact(None).await.unwrap();
+        assert!(!orphan.exists());
+        assert!(temp_dir.path().join("notes.json").exists());
+        assert_eq!(storage.compaction_stats().await.unwrap().deleted_items, 0);
+        let item = storage.get_item(&kept.id).await.unwrap().unwrap();
+        assert_eq!(item.metadata, kept.metadata);
+    }
+
+    #[tokio::test]
+    async fn test_external_metadata_threshold() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut storage = LegacyStorage::new(temp_dir.path(), "index.json").unwrap();
+        let mut config = CreateIndexConfig::default();
+        config.metadata_config.external_threshold = None;
+        storage.create_index(&config).await.unwrap();
+
+        // Always inline: no metadata file, however large
+        let item = large_item();
+        storage.insert_item(&item).await.unwrap();
+        let sidecar = temp_dir.path().join(format!("{}.json", item.id));
+        assert!(!sidecar.exists());
+
+        // A stale file never shadows inline metadata
+        std::fs::write(&sidecar, r#"{"stale": true}"#).unwrap();
+        let stored = storage.get_item(&item.id).await.unwrap().unwrap();
+        assert_eq!(stored.metadata, item.metadata);
+        let listed = storage.list_items(None).await.unwrap();
+        assert_eq!(listed[0].metadata, item.metadata);
+        assert_eq!(
+            storage.orphaned_metadata_files().await.unwrap(),
+            vec![sidecar]
+        );
+
+        // A low threshold externalizes small metadata too
+        let temp_dir = TempDir::new().unwrap();
+        let mut storage = LegacyStorage::new(temp_dir.path(), "index.json").unwrap();
+        let mut config = CreateIndexConfig::default();
+        config.metadata_config.external_threshold = Some(8);
+        storage.create_index(&config).await.unwrap();
+        let item = VectorItem {
+            metadata: serde_json::json!({ "title": "small" }),
+            ..large_item()
+        };
+        storage.insert_item(&item).await.unwrap();
+        assert!(temp_dir.path().join(format!("{}.json", item.id)).exists());
+        let stored = storage.get_item(&item.id).await.unwrap().unwrap();
+        assert_eq!(stored.metadata, item.metadata);
+    }
+}
@@ -2,14 +2,31 @@
 // SPDX-License-Identifier: Apache-2.0
 
 pub mod backend;
+#[cfg(feature = "rocksdb")]
+pub mod failpoints;
 pub mod legacy;
 pub mod lock;
+pub mod manifest;
+#[cfg(feature = "rocksdb")]
 pub mod optimized;
+#[cfg(feature = "redb")]
+pub mod redb_storage;
+pub mod resident;
+#[cfg(feature = "rocksdb")]
+mod tuning;
 pub mod wal;
+pub mod writer;
 
 pub use backend::*;
+#[cfg(feature = "rocksdb")]
+pub use failpoints::{FailAction, Failpoints};
 pub use legacy::*;
+#[cfg(feature = "rocksdb")]
 pub use optimized::*;
+#[cfg(feature = "redb")]
+pub use redb_storage::*;
+pub use resident::ResidentStorage;
+pub use writer::{Completion, SingleWriter, WriterFuture, WriterHandle};
 
 #[cfg(test)]
 mod tests {
@@ -23,6 +40,85 @@ mod tests {
         assert!(result.is_ok());
     }
 
+    #[cfg(not(any(feature = "rocksdb", feature = "redb")))]
+    #[tokio::test]
+    async fn test_auto_detect_falls_back_to_legacy() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut storage = Storage::auto_detect(temp_dir.path(), "index.json").unwrap();
+        storage
+            .create_index(&vectrust_core::CreateIndexConfig::default())
+            .await
+            .unwrap();
+        assert!(temp_dir.path().join("index.json").exists());
+
+        std::fs::write(temp_dir.path().join("manifest.json"), "{}").unwrap();
+        assert!(Storage::auto_detect(temp_dir.path(), "index.json").is_err());
+    }
+
+    #[cfg(feature = "redb")]
+    #[tokio::test]
+    async fn test_auto_detect_redb_manifest() {
+        use vectrust_core::StorageBackend;
+
+        let temp_dir = TempDir::new().unwrap();
+        let mut storage = RedbStorage::new(temp_dir.path()).unwrap();
+        storage
+            .create_index(&vectrust_core::CreateIndexConfig::default())
+            .await
+            .unwrap();
+        drop(storage);
+
+        let detected = Storage::auto_detect(temp_dir.path(), "index.json").unwrap();
+        assert!(detected.exists().await);
+        assert!(temp_dir.path().join("index.redb").exists());
+        assert_eq!(detected.get_stats().await.unwrap().items, 0);
+    }
+
+    #[test]
+    fn test_registered_backend_detection() {
+        let temp_dir = TempDir::new().unwrap();
+        let marker = temp_dir.path().join("custom.marker");
+        let detect_marker = marker.clone();
+        Storage::register_backend(
+            "test-custom",
+            move |path: &std::path::Path, _: &str| {
+                path.join("custom.marker") == detect_marker && detect_marker.exists()
+            },
+            |_: &std::path::Path, _: &str| {
+                Err(vectrust_core::VectraError::Storage {
+                    message: "custom backend".to_string(),
+                })
+            },
+        );
+        assert!(Storage::registered_backends().contains(&"test-custom".to_string()));
+
+        // Without the marker the built-in formats are used
+        assert!(Storage::auto_detect(temp_dir.path(), "index.json").is_ok());
+
+        std::fs::write(&marker, "").unwrap();
+        let message = |result: vectrust_core::Result<_>| match result {
+            Err(vectrust_core::VectraError::Storage { message }) => message,
+            _ => panic!("expected the custom backend's error"),
+        };
+        assert_eq!(
+            message(Storage::auto_detect(temp_dir.path(), "index.json")),
+            "custom backend"
+        );
+        assert_eq!(
+            message(Storage::open_registered(
+                "test-custom",
+                temp_dir.path(),
+                "index.json"
+            )),
+            "custom backend"
+        );
+
+        assert!(Storage::unregister_backend("test-custom"));
+        assert!(!Storage::unregister_backend("test-custom"));
+        assert!(Storage::open_registered("test-custom", temp_dir.path(), "index.json").is_err());
+        assert!(Storage::auto_detect(temp_dir.path(), "index.json").is_ok());
+    }
+
     #[test]
     fn test_legacy_storage_creation() {
         let temp_dir = TempDir::new().unwrap();
@@ -0,0 +1,290 @@
+// Copyright 2024-2026 Andrey Vasilevsky <anvanster@gmail.com>
+// SPDX-License-Identifier: Apache-2.0
+
+//! Format version negotiation for `manifest.json`.
+//!
+//! Every manifest carries a format version and two feature lists. A build
+//! refuses an index whose version is newer than it knows or that requires
+//! a feature it doesn't understand, and opens it read-only when only its
+//! write features are unknown, instead of misreading fields it can't
+//! interpret. Older versions are upgraded one step at a time on load.
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use vectrust_core::*;
+
+type JsonMap = serde_json::Map<String, serde_json::Value>;
+
+/// Manifest format version written by this build
+pub const FORMAT_VERSION: u32 = 3;
+
+/// Writers that ignore the index's embedding model would let mismatched
+/// vectors in, so they must not write
+pub const EMBEDDING_MODEL_FEATURE: &str = "embedding_model";
+
+/// Writers that ignore the index's limits could grow it past them
+pub const LIMITS_FEATURE: &str = "limits";
+
+/// Writers that don't share vector slots would copy them apart again when
+/// compacting, undoing the saving
+pub const VECTOR_DEDUP_FEATURE: &str = "vector_dedup";
+
+/// Readers that don't know the cold tier would take its flagged offsets
+/// for positions in vectors.dat
+pub const COLD_TIER_FEATURE: &str = "cold_tier";
+
+/// Readers that only parse JSON metadata can't read CBOR-encoded items
+pub const METADATA_CBOR_FEATURE: &str = "metadata_cbor";
+
+/// Writers that don't file items under their namespace would leave
+/// namespace listings incomplete
+pub const NAMESPACE_KEYS_FEATURE: &str = "namespace_keys";
+
+/// Writers that don't apply the index's vector transform would store
+/// vectors of the wrong length
+pub const VECTOR_TRANSFORM_FEATURE: &str = "vector_transform";
+
+/// Writers that don't apply the index's validation policy would store
+/// vectors it rejects
+pub const VECTOR_VALIDATION_FEATURE: &str = "vector_validation";
+
+/// Writers that don't file items under their range fields would leave
+/// range lookups incomplete
+pub const RANGE_KEYS_FEATURE: &str = "range_keys";
+
+/// Writers that don't file items under composite indexes would leave
+/// composite lookups incomplete
+pub const COMPOSITE_KEYS_FEATURE: &str = "composite_keys";
+
+/// Writers that don't clear the index's clean-shutdown mark before writing
+/// would leave it standing over a crash of theirs, so the next open would
+/// trust counts and offsets their batched saves never caught up on
+pub const CLEAN_SHUTDOWN_FEATURE: &str = "clean_shutdown";
+
+/// Features this build understands
+pub const SUPPORTED_FEATURES: &[&str] = &[
+    EMBEDDING_MODEL_FEATURE,
+    LIMITS_FEATURE,
+    VECTOR_DEDUP_FEATURE,
+    COLD_TIER_FEATURE,
+    METADATA_CBOR_FEATURE,
+    NAMESPACE_KEYS_FEATURE,
+    VECTOR_TRANSFORM_FEATURE,
+    VECTOR_VALIDATION_FEATURE,
+    RANGE_KEYS_FEATURE,
+    COMPOSITE_KEYS_FEATURE,
+    CLEAN_SHUTDOWN_FEATURE,
+];
+
+/// Features an index relies on beyond its format version
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FormatFeatures {
+    /// Builds that don't understand these must not open the index
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub required: Vec<String>,
+    /// Builds that don't understand these may only read the index
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub write: Vec<String>,
+}
+
+impl FormatFeatures {
+    /// Features implied by an index's settings
+    pub fn for_index(
+        embedding_model: Option<&EmbeddingModel>,
+        limits: Option<&IndexLimits>,
+    ) -> Self {
+        let mut features = Self::default();
+        if embedding_model.is_some() {
+            features.write.push(EMBEDDING_MODEL_FEATURE.to_string());
+        }
+        if limits.is_some() {
+            features.write.push(LIMITS_FEATURE.to_string());
+        }
+        features
+    }
+
+    fn unsupported(features: &[String]) -> Vec<String> {
+        features
+            .iter()
+            .filter(|f| !SUPPORTED_FEATURES.contains(&f.as_str()))
+            .cloned()
+            .collect()
+    }
+}
+
+/// How this build may use an index
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum Access {
+    #[default]
+    ReadWrite,
+    /// Opened read-only because of these unknown write features
+    ReadOnly(Vec<String>),
+}
+
+impl Access {
+    /// Error for a write attempted through read-only access
+    pub fn check_writable(&self, path: &std::path::Path) -> Result<()> {
+        match self {
+            Access::ReadWrite => Ok(()),
+            Access::ReadOnly(features) => Err(VectraError::Storage {
+                message: format!(
+                    "Index at {} is read-only: this build can't write its features ({})",
+                    path.display(),
+                    features.join(", ")
+                ),
+            }),
+        }
+    }
+}
+
+/// A manifest parsed by [`parse_manifest`]
+#[derive(Debug)]
+pub struct Negotiated<T> {
+    pub manifest: T,
+    pub access: Access,
+    /// Whether the manifest was upgraded from an older version and should
+    /// be saved back
+    pub upgraded: bool,
+}
+
+/// One upgrade step: from the version in the table to the next one
+type Upgrade = fn(&mut JsonMap) -> Result<()>;
+
+/// Upgrade steps by the version they start from
+const UPGRADES: &[(u32, Upgrade)] = &[(2, upgrade_v2)];
+
+/// Version 3 added feature lists; derive them from the settings
+fn upgrade_v2(manifest: &mut JsonMap) -> Result<()> {
+    let model: Option<EmbeddingModel> = match manifest.get("embedding_model") {
+        Some(value) => serde_json::from_value(value.clone())?,
+        None => None,
+    };
+    let features = FormatFeatures::for_index(model.as_ref(), None);
+    manifest.insert("features".to_string(), serde_json::to_value(features)?);
+    Ok(())
+}
+
+/// [`VectraError::IndexDamaged`] in place of a parse error for the
+/// manifest of the index at `path`; other errors are returned unchanged
+pub fn damaged_manifest(path: &std::path::Path, error: VectraError) -> VectraError {
+    match error {
+        VectraError::Serialization(e) => VectraError::IndexDamaged {
+            path: path.display().to_string(),
+            message: format!(
+                "manifest.json can't be parsed ({}); restore it from a snapshot or backup",
+                e
+            ),
+        },
+        e => e,
+    }
+}
+
+/// Parse manifest JSON: check this build can open it, then upgrade it to
+/// [`FORMAT_VERSION`]
+pub fn parse_manifest<T: DeserializeOwned>(content: &str) -> Result<Negotiated<T>> {
+    let mut raw: JsonMap = serde_json::from_str(content)?;
+    let version =
+        raw.get("version")
+            .and_then(|v| v.as_u64())
+            .ok_or_else(|| VectraError::Storage {
+                message: "Manifest has no format version".to_string(),
+            })? as u32;
+
+    if version > FORMAT_VERSION {
+        return Err(VectraError::Storage {
+            message: format!(
+                "Index format version {} is newer than this build supports ({}); upgrade vectrust to open it",
+                version, FORMAT_VERSION
+            ),
+        });
+    }
+
+    let mut current = version;
+    while current < FORMAT_VERSION {
+        let (_, upgrade) = UPGRADES
+            .iter()
+            .find(|(from, _)| *from == current)
+            .ok_or_else(|| VectraError::Storage {
+                message: format!("No upgrade path from index format version {}", current),
+            })?;
+        upgrade(&mut raw)?;
+        current += 1;
+        raw.insert("version".to_string(), current.into());
+    }
+
+    let features: FormatFeatures = match raw.get("features") {
+        Some(value) => serde_json::from_value(value.clone())?,
+        None => FormatFeatures::default(),
+    };
+    let required = FormatFeatures::unsupported(&features.required);
+    if !required.is_empty() {
+        return Err(VectraError::Storage {
+            message: format!(
+                "Index requires features this build doesn't support: {}",
+                required.join(", ")
+            ),
+        });
+    }
+    let write = FormatFeatures::unsupported(&features.write);
+    let access = if write.is_empty() {
+        Access::ReadWrite
+    } else {
+        Access::ReadOnly(write)
+    };
+
+    Ok(Negotiated {
+        manifest: serde_json::from_value(serde_json::Value::Object(raw))?,
+        access,
+        upgraded: version < FORMAT_VERSION,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn parse(manifest: serde_json::Value) -> Result<Negotiated<serde_json::Value>> {
+        parse_manifest(&manifest.to_string())
+    }
+
+    #[test]
+    fn test_version_negotiation() {
+        let current = parse(json!({ "version": FORMAT_VERSION })).unwrap();
+        assert_eq!(current.access, Access::ReadWrite);
+        assert!(!current.upgraded);
+
+        assert!(parse(json!({ "version": FORMAT_VERSION + 1 })).is_err());
+        assert!(parse(json!({ "version": 1 })).is_err());
+        assert!(parse(json!({ "format": "optimized" })).is_err());
+
+        let required = json!({ "version": FORMAT_VERSION, "features": { "required": ["sparse"] } });
+        assert!(parse(required).is_err());
+
+        let write = json!({ "version": FORMAT_VERSION, "features": { "write": ["sparse"] } });
+        let negotiated = parse(write).unwrap();
+        assert_eq!(
+            negotiated.access,
+            Access::ReadOnly(vec!["sparse".to_string()])
+        );
+        assert!(negotiated
+            .access
+            .check_writable(std::path::Path::new("idx"))
+            .is_err());
+    }
+
+    #[test]
+    fn test_upgrade_from_v2() {
+        let negotiated = parse(json!({
+            "version": 2,
+            "embedding_model": { "name": "minilm" },
+            "future_field": 7,
+        }))
+        .unwrap();
+        assert!(negotiated.upgraded);
+        let manifest = negotiated.manifest;
+        assert_eq!(manifest["version"], FORMAT_VERSION);
+        assert_eq!(manifest["features"]["write"][0], EMBEDDING_MODEL_FEATURE);
+        assert_eq!(manifest["future_field"], 7);
+    }
+}
@@ -1,31 +1,83 @@
 // Copyright 2024-2026 Andrey Vasilevsky <anvanster@gmail.com>
 // SPDX-License-Identifier: Apache-2.0
 
+use crate::failpoints::*;
+use crate::manifest::{
+    damaged_manifest, parse_manifest, Access, FormatFeatures, Negotiated, CLEAN_SHUTDOWN_FEATURE,
+    COLD_TIER_FEATURE, COMPOSITE_KEYS_FEATURE, FORMAT_VERSION, METADATA_CBOR_FEATURE,
+    NAMESPACE_KEYS_FEATURE, RANGE_KEYS_FEATURE, VECTOR_DEDUP_FEATURE, VECTOR_TRANSFORM_FEATURE,
+    VECTOR_VALIDATION_FEATURE,
+};
 use async_trait::async_trait;
 use bincode;
-use memmap2::{MmapMut, MmapOptions};
-use rocksdb::{Options, DB};
+use memmap2::{Mmap, MmapMut, MmapOptions};
+use rocksdb::DB;
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs::OpenOptions;
-use std::io::{Seek, SeekFrom, Write};
+use std::io::{Seek, Write};
+use std::ops::Bound;
 use std::path::{Path, PathBuf};
-use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, OnceLock};
 use tokio::fs;
-use tokio::sync::RwLock;
 use uuid::Uuid;
 use vectrust_core::*;
 
 /// Optimized storage format (v2) with better performance
 pub struct OptimizedStorage {
     path: PathBuf,
-    db: Arc<RwLock<Option<DB>>>,
-    vector_file: Arc<RwLock<Option<std::fs::File>>>,
-    vector_mmap: Arc<RwLock<Option<MmapMut>>>,
-    manifest: Arc<RwLock<Option<Manifest>>>,
-    dimensions: Arc<RwLock<Option<usize>>>,
+    /// The RocksDB store, read without locking once open. It is set only
+    /// after the manifest and vector file are loaded, so a reader that sees
+    /// it sees them too, and is dropped only by `&mut self` methods.
+    db: OnceLock<DB>,
+    /// Held while opening the store, so concurrent first reads open it once
+    opening: tokio::sync::Mutex<()>,
+    /// vectors.dat, mapped; the mapping keeps the file open
+    vector_mmap: TimedRwLock<Option<MmapMut>>,
+    /// Generation of vectors.dat the records point into. Compaction copies
+    /// live vectors into the next one and switches to it in the batch that
+    /// moves the records, so a crash leaves the records and their file
+    /// matched either way.
+    vector_generation: AtomicU64,
+    /// Vectors copied so far by [`StorageBackend::prepare_compaction`]
+    compaction_copy: std::sync::Mutex<Option<CompactionCopy>>,
+    /// The live manifest, including the index's dimensions once known
+    manifest: TimedRwLock<Option<Manifest>>,
+    /// End of the allocated part of vectors.dat. Inserts claim slots here
+    /// without touching the manifest, whose `next_vector_offset` catches up
+    /// whenever it is saved.
+    next_offset: AtomicU64,
+    /// Live items, counted the same way so writes never take the manifest
+    /// lock just to bump `total_items`
+    total_items: AtomicUsize,
     // Performance optimization: batch manifest updates
-    manifest_dirty: Arc<RwLock<bool>>,
-    operations_since_save: Arc<RwLock<u32>>,
+    manifest_dirty: AtomicBool,
+    /// Whether the manifest on disk records a clean shutdown, which the
+    /// first write after it clears
+    clean_on_disk: AtomicBool,
+    operations_since_save: AtomicU32,
+    manifest_save_interval: AtomicU32,
+    failpoints: Failpoints,
+    /// Whether this build may write the index, from the manifest's features
+    access: std::sync::RwLock<Access>,
+    /// Shared vector slots of a deduplicating index, derived from the live
+    /// records on first write
+    vector_slots: TimedRwLock<Option<VectorSlots>>,
+    /// Slots updates have moved vectors out of, by record size, for the
+    /// next update of that size to write into
+    spare_slots: std::sync::Mutex<HashMap<u64, Vec<u64>>>,
+    /// Damaged items reads skip, with what was wrong with them
+    quarantine: std::sync::RwLock<BTreeMap<Uuid, String>>,
+    /// Read-only mapping of vectors.cold, once it has been written
+    cold_mmap: TimedRwLock<Option<Mmap>>,
+    /// Generation of vectors.cold the cold records point into, switched
+    /// the same way as `vector_generation` when retiering rewrites the tier
+    cold_generation: AtomicU64,
+    /// Reads of each item since the index was opened or last retiered
+    access_counts: std::sync::Mutex<HashMap<Uuid, u32>>,
+    /// Time spent waiting for the locks above
+    lock_waits: Arc<LockWaits>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -38,106 +90,698 @@ pub struct Manifest {
     pub total_items: usize,
     pub vector_file_size: u64,
     pub next_vector_offset: u64,
+    #[serde(default)]
+    pub compaction: Option<CompactionPolicy>,
+    #[serde(default)]
+    pub embedding_model: Option<EmbeddingModel>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub limits: Option<IndexLimits>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vector_transform: Option<VectorTransform>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vector_validation: Option<VectorValidation>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub namespace_field: Option<String>,
+    /// Numeric fields items are filed under in the range index
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub range_fields: Vec<String>,
+    /// Multi-field indexes items are filed under
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub composite_indexes: Vec<CompositeIndex>,
+    /// Identical vectors share one slot in vectors.dat
+    #[serde(default)]
+    pub dedup_vectors: bool,
+    /// Encoding of newly written metadata records
+    #[serde(default)]
+    pub metadata_encoding: MetadataEncoding,
+    /// RocksDB settings applied whenever the index is opened
+    #[serde(default)]
+    pub rocksdb_tuning: RocksDbTuning,
+    #[serde(default)]
+    pub features: FormatFeatures,
+    /// Whether everything written had reached disk when the index was last
+    /// closed, so its counts and offsets here can be trusted on open
+    /// without reconciling them against every vector record
+    #[serde(default)]
+    pub clean_shutdown: bool,
+    /// Fields from newer versions, kept when this build saves the manifest
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct VectorRecord {
     pub id: Uuid,
+    /// Position in vectors.dat, or in vectors.cold when [`COLD_TIER`] is set
     pub offset: u64,
     pub dimensions: usize,
     pub deleted: bool,
 }
 
+/// Content hash of a vector, including its length
+type VectorHash = [u8; 32];
+
+fn vector_hash(vector: &[f32]) -> VectorHash {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(&(vector.len() as u64).to_le_bytes());
+    for x in vector {
+        hasher.update(&x.to_le_bytes());
+    }
+    *hasher.finalize().as_bytes()
+}
+
+/// Which slot holds each distinct vector, and how many live records point
+/// at it. A slot whose last record goes is left for compaction to reclaim.
+#[derive(Debug, Default)]
+struct VectorSlots {
+    by_hash: HashMap<VectorHash, u64>,
+    refs: HashMap<u64, (VectorHash, usize)>,
+}
+
+impl VectorSlots {
+    fn find(&self, hash: &VectorHash) -> Option<u64> {
+        self.by_hash.get(hash).copied()
+    }
+
+    fn acquire(&mut self, hash: VectorHash, offset: u64) {
+        self.by_hash.insert(hash, offset);
+        self.refs.entry(offset).or_insert((hash, 0)).1 += 1;
+    }
+
+    fn release(&mut self, offset: u64) {
+        if let Some((hash, refs)) = self.refs.get_mut(&offset) {
+            *refs -= 1;
+            if *refs == 0 {
+                let hash = *hash;
+                self.refs.remove(&offset);
+                self.by_hash.remove(&hash);
+            }
+        }
+    }
+}
+
+/// Live vectors copied into the next vector file ahead of compaction
+#[derive(Debug)]
+struct CompactionCopy {
+    /// Generation being copied from; the copy is stale once it changes
+    source: u64,
+    generation: u64,
+    file: std::fs::File,
+    /// End of what has been written to `file`
+    written: u64,
+    /// Slots still to copy, as of when the copy began, by offset and length
+    pending: Vec<(u64, usize)>,
+    /// Where each copied slot landed. Writes to a slot drop it from here.
+    copied: HashMap<u64, u64>,
+}
+
+impl CompactionCopy {
+    /// Append the slots of `map` not copied yet, taking slots until about
+    /// `max_bytes` are written, and return how many bytes were
+    fn append(
+        &mut self,
+        map: &[u8],
+        mut slots: impl Iterator<Item = (u64, usize)>,
+        max_bytes: u64,
+    ) -> Result<u64> {
+        let mut out = Vec::new();
+        let mut landed = HashMap::new();
+        while (out.len() as u64) < max_bytes {
+            let Some((offset, len)) = slots.next() else {
+                break;
+            };
+            if self.copied.contains_key(&offset) || landed.contains_key(&offset) {
+                continue;
+            }
+            landed.insert(offset, self.written + out.len() as u64);
+            out.extend_from_slice(&map[map_range(offset, len, map.len())?]);
+        }
+
+        self.file.seek(std::io::SeekFrom::Start(self.written))?;
+        self.file.write_all(&out)?;
+        self.written += out.len() as u64;
+        self.copied.extend(landed);
+        Ok(out.len() as u64)
+    }
+}
+
 const METADATA_CF: &str = "metadata";
 const VECTOR_INDEX_CF: &str = "vector_index";
+/// Every item with a namespace, keyed by a hash prefix of the namespace
+/// and then the item's id, so one namespace's items are a prefix scan
+const NAMESPACE_CF: &str = "namespace_items";
+/// Every item with a number in a range field, keyed by a hash prefix of
+/// the field, the number in an order-preserving encoding and then the
+/// item's id, so a field's values between two bounds are a range scan
+const RANGE_CF: &str = "range_items";
+/// Every item holding values in all of a composite index's fields, keyed by
+/// a hash prefix of the index, a hash of each value and then the item's id,
+/// so the items pinning a leading run of the fields are a prefix scan
+const COMPOSITE_CF: &str = "composite_items";
+/// Each item's stored document text or blob, keyed by id
+const PAYLOAD_CF: &str = "payloads";
+const NAMESPACE_PREFIX_LEN: usize = 8;
+/// Range and composite keys start with the same hash prefix as namespace keys
+const RANGE_PREFIX_LEN: usize = NAMESPACE_PREFIX_LEN;
+const COMPOSITE_PREFIX_LEN: usize = NAMESPACE_PREFIX_LEN;
+/// Column families holding secondary keys
+const SECONDARY_CFS: [&str; 3] = [NAMESPACE_CF, RANGE_CF, COMPOSITE_CF];
+/// Key in the default column family holding the vector file generation
+const VECTOR_GENERATION_KEY: &[u8] = b"vector_generation";
+/// Key in the default column family holding the cold tier's file generation
+const COLD_GENERATION_KEY: &[u8] = b"cold_generation";
+/// Every column family an index writes to
+const COLUMN_FAMILIES: [&str; 6] = [
+    METADATA_CF,
+    VECTOR_INDEX_CF,
+    NAMESPACE_CF,
+    RANGE_CF,
+    COMPOSITE_CF,
+    PAYLOAD_CF,
+];
 const VECTOR_HEADER_SIZE: usize = 8; // u64 for dimensions count
 
-const MANIFEST_SAVE_INTERVAL: u32 = 100; // Save manifest every N operations
+/// Set on a record's offset when its vector is in the cold tier: an lz4
+/// block in vectors.cold, prefixed with its u32 length
+const COLD_TIER: u64 = 1 << 63;
+const COLD_BLOCK_HEADER_SIZE: usize = 4;
+
+const MANIFEST_SAVE_INTERVAL: u32 = 100; // Default for saving the manifest every N operations
+const COMPACTION_CHUNK_SIZE: usize = 1024 * 1024; // Throttle granularity for compaction IO
+const MULTI_GET_BATCH: usize = 1024; // Keys per RocksDB multi-get when hydrating items
+
+/// Largest vector file this build can map. Mappings are addressed with
+/// `usize`, so 32-bit targets top out far below what a `u64` offset allows.
+const MAX_MAP_LEN: u64 = isize::MAX as u64;
 
 impl OptimizedStorage {
     pub fn new(path: &Path) -> Result<Self> {
+        let lock_waits = Arc::new(LockWaits::default());
         Ok(Self {
             path: path.to_path_buf(),
-            db: Arc::new(RwLock::new(None)),
-            vector_file: Arc::new(RwLock::new(None)),
-            vector_mmap: Arc::new(RwLock::new(None)),
-            manifest: Arc::new(RwLock::new(None)),
-            dimensions: Arc::new(RwLock::new(None)),
-            manifest_dirty: Arc::new(RwLock::new(false)),
-            operations_since_save: Arc::new(RwLock::new(0)),
+            db: OnceLock::new(),
+            opening: tokio::sync::Mutex::new(()),
+            vector_mmap: TimedRwLock::new("vector_mmap", None, &lock_waits),
+            vector_generation: AtomicU64::new(0),
+            compaction_copy: std::sync::Mutex::new(None),
+            manifest: TimedRwLock::new("manifest", None, &lock_waits),
+            next_offset: AtomicU64::new(0),
+            total_items: AtomicUsize::new(0),
+            manifest_dirty: AtomicBool::new(false),
+            clean_on_disk: AtomicBool::new(false),
+            operations_since_save: AtomicU32::new(0),
+            manifest_save_interval: AtomicU32::new(MANIFEST_SAVE_INTERVAL),
+            failpoints: Failpoints::default(),
+            access: std::sync::RwLock::new(Access::ReadWrite),
+            vector_slots: TimedRwLock::new("vector_slots", None, &lock_waits),
+            spare_slots: std::sync::Mutex::new(HashMap::new()),
+            quarantine: std::sync::RwLock::new(BTreeMap::new()),
+            cold_mmap: TimedRwLock::new("cold_mmap", None, &lock_waits),
+            cold_generation: AtomicU64::new(0),
+            access_counts: std::sync::Mutex::new(HashMap::new()),
+            lock_waits,
         })
     }
 
+    /// Failpoints on this storage's write paths, for crash testing
+    pub fn failpoints(&self) -> &Failpoints {
+        &self.failpoints
+    }
+
+    /// Error for an index whose files disagree with its manifest in a way
+    /// `vectrust verify --repair` can fix
+    fn damaged(&self, problem: String) -> VectraError {
+        VectraError::IndexDamaged {
+            path: self.path.display().to_string(),
+            message: format!(
+                "{}. Run `vectrust verify --path {} --repair` to quarantine and drop the affected items",
+                problem,
+                self.path.display()
+            ),
+        }
+    }
+
+    fn is_quarantined(&self, id: &Uuid) -> bool {
+        self.quarantine.read().unwrap().contains_key(id)
+    }
+
+    /// Lift the quarantine of items that have been rewritten or deleted
+    fn release_quarantine<'a>(&self, ids: impl IntoIterator<Item = &'a Uuid>) {
+        let mut quarantine = self.quarantine.write().unwrap();
+        if !quarantine.is_empty() {
+            for id in ids {
+                quarantine.remove(id);
+            }
+        }
+    }
+
     async fn initialize_storage(&self) -> Result<()> {
+        self.open_storage(true).await
+    }
+
+    /// Open the RocksDB store and vector file, unless another call already
+    /// has. With `check_integrity` an existing index whose files don't match
+    /// its manifest fails here with [`VectraError::IndexDamaged`], rather
+    /// than with out-of-range reads later.
+    async fn open_storage(&self, check_integrity: bool) -> Result<()> {
+        let _opening = self.opening.lock().await;
+        if self.db.get().is_some() {
+            return Ok(());
+        }
+
         // Create directory if it doesn't exist
         if !self.path.exists() {
             std::fs::create_dir_all(&self.path)?;
         }
 
-        // Open RocksDB with optimized settings for vector workloads
-        let db_path = self.path.join("metadata");
-        let mut db_opts = Options::default();
-        db_opts.create_if_missing(true);
-        db_opts.create_missing_column_families(true);
-
-        // Performance optimizations for vector operations
-        db_opts.set_max_write_buffer_number(4);
-        db_opts.set_write_buffer_size(64 * 1024 * 1024); // 64MB
-        db_opts.set_target_file_size_base(64 * 1024 * 1024); // 64MB
-        db_opts.set_level_compaction_dynamic_level_bytes(true);
-        db_opts.set_max_bytes_for_level_base(256 * 1024 * 1024); // 256MB
-        db_opts.set_max_background_jobs(4);
-        db_opts.set_bytes_per_sync(64 * 1024 * 1024); // 64MB - sync less frequently
+        // The manifest carries the RocksDB settings, so it is read first
+        let negotiated = self.read_manifest().await?;
+        let tuning = negotiated
+            .as_ref()
+            .map(|n| n.manifest.rocksdb_tuning.clone())
+            .unwrap_or_default();
 
         // Note: We're not disabling auto-compactions as it can cause issues
-
-        let cf_names = vec![METADATA_CF, VECTOR_INDEX_CF];
-        let db = DB::open_cf(&db_opts, db_path, cf_names)?;
-
-        *self.db.write().await = Some(db);
+        let db_path = self.path.join("metadata");
+        let cf_names = [METADATA_CF, VECTOR_INDEX_CF];
+        let db_opts = crate::tuning::db_options(&tuning);
+        // Opening would quietly create a missing store, so an index that
+        // has held items must still have one
+        let written = negotiated
+            .as_ref()
+            .is_some_and(|n| n.manifest.total_items > 0 || n.manifest.next_vector_offset > 0);
+        if check_integrity && written {
+            let present = DB::list_cf(&db_opts, &db_path)
+                .map_err(|e| self.damaged(format!("the metadata store can't be read ({})", e)))?;
+            if let Some(missing) = cf_names.iter().find(|cf| !present.iter().any(|p| p == *cf)) {
+                return Err(self.damaged(format!(
+                    "the metadata store has no '{}' column family",
+                    missing
+                )));
+            }
+        }
+        let mut column_families =
+            crate::tuning::lookup_column_families(&tuning, &db_opts, &cf_names);
+        column_families.push(crate::tuning::prefix_column_family(
+            &tuning,
+            &db_opts,
+            NAMESPACE_CF,
+            NAMESPACE_PREFIX_LEN,
+        ));
+        column_families.push(crate::tuning::prefix_column_family(
+            &tuning,
+            &db_opts,
+            RANGE_CF,
+            RANGE_PREFIX_LEN,
+        ));
+        column_families.push(crate::tuning::prefix_column_family(
+            &tuning,
+            &db_opts,
+            COMPOSITE_CF,
+            COMPOSITE_PREFIX_LEN,
+        ));
+        column_families.push(crate::tuning::blob_column_family(&db_opts, PAYLOAD_CF));
+        let db = DB::open_cf_descriptors(&db_opts, db_path, column_families)?;
+        crate::tuning::apply_overrides(&db, &tuning, &COLUMN_FAMILIES)?;
+        self.vector_generation.store(
+            read_generation(&db, VECTOR_GENERATION_KEY)?,
+            Ordering::Release,
+        );
+        self.cold_generation.store(
+            read_generation(&db, COLD_GENERATION_KEY)?,
+            Ordering::Release,
+        );
 
         // Load or create manifest
-        if let Some(manifest) = self.load_manifest().await? {
-            *self.manifest.write().await = Some(manifest.clone());
-            *self.dimensions.write().await = manifest.dimensions;
+        if let Some(negotiated) = negotiated {
+            let mut manifest = negotiated.manifest;
+            // Only a clean shutdown leaves the batched saves caught up
+            let mut changed = !manifest.clean_shutdown && reconcile_manifest(&db, &mut manifest)?;
+            self.clean_on_disk
+                .store(manifest.clean_shutdown, Ordering::Release);
+            self.next_offset
+                .store(manifest.next_vector_offset, Ordering::Release);
+            self.total_items
+                .store(manifest.total_items, Ordering::Release);
+            let vector_path = self.vector_path();
+            let vector_file_len = std::fs::metadata(&vector_path).map_or(0, |m| m.len());
+            if check_integrity && vector_file_len < manifest.next_vector_offset {
+                return Err(self.damaged(format!(
+                    "{} is {} bytes but its vectors run to byte {}",
+                    vector_path.display(),
+                    vector_file_len,
+                    manifest.next_vector_offset
+                )));
+            }
+            if negotiated.access == Access::ReadWrite {
+                remove_stale_generations(
+                    &self.path,
+                    "dat",
+                    self.vector_generation.load(Ordering::Acquire),
+                )?;
+                remove_stale_generations(
+                    &self.path,
+                    "cold",
+                    self.cold_generation.load(Ordering::Acquire),
+                )?;
+            }
+            // Indexes from before namespace keys get them on first open
+            if negotiated.access == Access::ReadWrite
+                && manifest.namespace_field.is_some()
+                && !has_feature(&manifest.features.write, NAMESPACE_KEYS_FEATURE)
+            {
+                set_feature(&mut manifest.features.write, NAMESPACE_KEYS_FEATURE, true);
+                build_secondary_keys(&db, &KeyFields::of(&manifest))?;
+                changed = true;
+            }
+            if (negotiated.upgraded || changed) && negotiated.access == Access::ReadWrite {
+                self.save_manifest_to_disk(&manifest).await?;
+            }
+            *self.manifest.write().await = Some(manifest);
 
             // Open existing vector file
-            let vector_path = self.path.join("vectors.dat");
             if vector_path.exists() {
                 let file = OpenOptions::new()
                     .read(true)
                     .write(true)
                     .open(&vector_path)?;
+                *self.vector_mmap.write().await = Some(map_vector_file(&file)?);
+            }
+            self.map_cold_file().await?;
+        }
+
+        // Published last, so readers that find it open find the rest loaded
+        let _ = self.db.set(db);
+        Ok(())
+    }
+
+    /// Fields items are filed under in the secondary indexes
+    async fn key_fields(&self) -> KeyFields {
+        self.manifest
+            .read()
+            .await
+            .as_ref()
+            .map(KeyFields::of)
+            .unwrap_or_default()
+    }
+
+    /// Fail if any of `items` is already stored or repeats an id earlier in
+    /// the batch, so an insert never overwrites a record and strands its
+    /// vector slot
+    async fn check_new_ids(&self, items: &[VectorItem]) -> Result<()> {
+        let Some(db) = self.db.get() else {
+            return Ok(());
+        };
+        let vector_index_cf = db.cf_handle(VECTOR_INDEX_CF).unwrap();
+        let mut ids = HashSet::new();
+        for item in items {
+            let stored = match db.get_cf(&vector_index_cf, item.id.as_bytes())? {
+                Some(bytes) => match bincode::deserialize::<VectorRecord>(&bytes) {
+                    Ok(record) => !record.deleted,
+                    // Quarantined items may be written afresh
+                    Err(_) if self.is_quarantined(&item.id) => false,
+                    Err(e) => return Err(e.into()),
+                },
+                None => false,
+            };
+            if stored || !ids.insert(item.id) {
+                return Err(VectraError::ItemAlreadyExists { id: item.id });
+            }
+        }
+        Ok(())
+    }
+
+    /// Append `items`, deleting `stale_keys` from the secondary indexes in
+    /// the same write batch as their records
+    async fn write_items(&self, items: &[VectorItem], stale_keys: Vec<SecondaryKey>) -> Result<()> {
+        if items.is_empty() {
+            return Ok(());
+        }
+
+        // Ensure storage is initialized
+        self.ensure_writable().await?;
+
+        // Validate all items have same dimensions
+        let first_dimensions = items[0].vector.len();
+        for item in items {
+            if item.vector.len() != first_dimensions {
+                return Err(VectraError::VectorValidation {
+                    message: format!(
+                        "All vectors must have same dimensions. Expected {}, got {}",
+                        first_dimensions,
+                        item.vector.len()
+                    ),
+                });
+            }
+        }
+
+        self.claim_dimensions(first_dimensions).await?;
+
+        // In a deduplicating index, vectors already stored and repeats
+        // within the batch don't take a new slot
+        let hashes: Vec<Option<VectorHash>> = if self.dedup_vectors().await {
+            self.load_vector_slots().await?;
+            items
+                .iter()
+                .map(|item| Some(vector_hash(&item.vector)))
+                .collect()
+        } else {
+            vec![None; items.len()]
+        };
+        let mut slot_offsets: HashMap<VectorHash, u64> = HashMap::new();
+        let mut new_slots = 0;
+        {
+            let slots_guard = self.vector_slots.read().await;
+            let mut batch_hashes = std::collections::HashSet::new();
+            for hash in &hashes {
+                match hash {
+                    Some(hash) => {
+                        if let Some(offset) = slots_guard.as_ref().and_then(|s| s.find(hash)) {
+                            slot_offsets.insert(*hash, offset);
+                        } else if batch_hashes.insert(*hash) {
+                            new_slots += 1;
+                        }
+                    }
+                    None => new_slots += 1,
+                }
+            }
+        }
+
+        // Claim slots for all new vectors at once, then grow the file to
+        // hold them before any writes
+        let record_size = (VECTOR_HEADER_SIZE + first_dimensions * 4) as u64;
+        let first_offset = self.allocate_vector_slots(record_size * new_slots as u64);
+        let final_size = first_offset + record_size * new_slots as u64;
+
+        // Only print for large batches
+        if items.len() >= 2500 {
+            println!(
+                "DEBUG: Batch size: {}, Current offset: {}, Final size needed: {}",
+                items.len(),
+                first_offset,
+                final_size
+            );
+        }
+
+        self.ensure_vector_file_capacity(final_size).await?;
+        let offsets: Vec<u64> = (0..new_slots as u64)
+            .map(|i| first_offset + i * record_size)
+            .collect();
+
+        // Now write vectors and prepare data without repeated lock acquisition
+        let encoding = self.metadata_encoding().await;
+        let key_fields = self.key_fields().await;
+        let mut prepared_data = Vec::with_capacity(items.len());
+        let mut fresh_offsets = offsets.into_iter();
+        let mut acquired = Vec::new();
+        for (item, hash) in items.iter().zip(&hashes) {
+            let shared = hash.and_then(|hash| slot_offsets.get(&hash).copied());
+            let vector_offset = match shared {
+                Some(offset) => offset,
+                None => {
+                    let offset = fresh_offsets
+                        .next()
+                        .ok_or_else(|| VectraError::StorageError {
+                            message: "Ran out of allocated vector slots".to_string(),
+                        })?;
+                    self.write_vector_to_file(&item.vector, offset).await?;
+                    self.failpoints.hit(INSERT_AFTER_VECTOR_WRITE)?;
+                    if let Some(hash) = hash {
+                        slot_offsets.insert(*hash, offset);
+                    }
+                    offset
+                }
+            };
+            if let Some(hash) = hash {
+                acquired.push((*hash, vector_offset));
+            }
+
+            // Prepare metadata (without vector data)
+            let mut metadata_item = item.clone();
+            metadata_item.vector = Vec::new();
+            let metadata_bytes = encode_metadata(&metadata_item, encoding)?;
+
+            // Prepare vector record
+            let vector_record = VectorRecord {
+                id: item.id,
+                offset: vector_offset,
+                dimensions: first_dimensions,
+                deleted: false,
+            };
+            let vector_record_bytes = bincode::serialize(&vector_record)?;
+
+            prepared_data.push((
+                item.id.as_bytes().to_vec(),
+                metadata_bytes,
+                vector_record_bytes,
+                key_fields.keys(item),
+            ));
+        }
+
+        // Bulk write to database
+        let total_items_added = prepared_data.len();
+        {
+            if let Some(db) = self.db.get() {
+                let metadata_cf = db.cf_handle(METADATA_CF).unwrap();
+                let vector_index_cf = db.cf_handle(VECTOR_INDEX_CF).unwrap();
+
+                // Use RocksDB write batch for better performance
+                let mut batch = rocksdb::WriteBatch::default();
+
+                delete_keys(db, &mut batch, stale_keys);
+                for (id_bytes, metadata_bytes, vector_record_bytes, secondary_keys) in prepared_data
+                {
+                    batch.put_cf(&metadata_cf, &id_bytes, metadata_bytes);
+                    batch.put_cf(&vector_index_cf, &id_bytes, vector_record_bytes);
+                    put_keys(db, &mut batch, secondary_keys);
+                }
 
-                let mmap = unsafe { MmapOptions::new().map_mut(&file)? };
+                // Execute batch write
+                db.write(batch)?;
+            }
+        }
+        self.failpoints.hit(INSERT_AFTER_DB_WRITE)?;
 
-                *self.vector_file.write().await = Some(file);
-                *self.vector_mmap.write().await = Some(mmap);
+        if let Some(ref mut slots) = *self.vector_slots.write().await {
+            for (hash, offset) in acquired {
+                slots.acquire(hash, offset);
             }
         }
 
+        self.total_items
+            .fetch_add(total_items_added, Ordering::AcqRel);
+
+        // Mark manifest dirty for batched saving
+        self.mark_manifest_dirty().await?;
+        self.release_quarantine(items.iter().map(|item| &item.id));
+
         Ok(())
     }
 
+    /// Live items in id order, paged by `options`. Metadata is tested
+    /// against `matches` as it is read, so the vector records and vectors
+    /// of items that fail are never fetched.
+    async fn scan_items(
+        &self,
+        options: Option<ListOptions>,
+        matches: Option<&MetadataPredicate>,
+    ) -> Result<Vec<VectorItem>> {
+        // Ensure storage is initialized for read operations
+        if self.db.get().is_none() {
+            self.initialize_storage().await?;
+        }
+        let (mut skip, limit) = options.map_or((0, usize::MAX), |o| {
+            (o.offset.unwrap_or(0), o.limit.unwrap_or(usize::MAX))
+        });
+
+        // Walk the metadata in key order, fetching each run of keys' vector
+        // records with one multi-get, without holding DB references past it
+        let records = {
+            let mut records = Vec::new();
+            if let Some(db) = self.db.get() {
+                let metadata_cf = db.cf_handle(METADATA_CF).unwrap();
+                let vector_index_cf = db.cf_handle(VECTOR_INDEX_CF).unwrap();
+                let quarantine = self.quarantine.read().unwrap();
+                let mut iter = db.iterator_cf(&metadata_cf, rocksdb::IteratorMode::Start);
+                let mut batch = Vec::with_capacity(MULTI_GET_BATCH);
+
+                'scan: while records.len() < limit {
+                    batch.clear();
+                    for entry in iter.by_ref() {
+                        let (key, value) = entry?;
+                        if !quarantine.is_empty()
+                            && Uuid::from_slice(&key).is_ok_and(|id| quarantine.contains_key(&id))
+                        {
+                            continue;
+                        }
+                        let metadata_item = decode_metadata(&value)?;
+                        if matches.is_some_and(|matches| !matches(&metadata_item.metadata)) {
+                            continue;
+                        }
+                        batch.push((key, metadata_item));
+                        if batch.len() == MULTI_GET_BATCH {
+                            break;
+                        }
+                    }
+                    if batch.is_empty() {
+                        break;
+                    }
+
+                    let vector_records = db.batched_multi_get_cf(
+                        &vector_index_cf,
+                        batch.iter().map(|(k, _)| k),
+                        true,
+                    );
+                    for ((_, metadata_item), vector_record) in batch.drain(..).zip(vector_records) {
+                        let Some(vector_record_bytes) = vector_record? else {
+                            continue;
+                        };
+                        let vector_record: VectorRecord =
+                            bincode::deserialize(&vector_record_bytes)?;
+                        if !vector_record.deleted {
+                            if skip > 0 {
+                                skip -= 1;
+                                continue;
+                            }
+                            records.push((metadata_item, vector_record));
+                            if records.len() >= limit {
+                                break 'scan;
+                            }
+                        }
+                    }
+                }
+            }
+            records
+        };
+
+        // Now load vectors without holding DB guard
+        let (mut items, vector_records): (Vec<VectorItem>, Vec<VectorRecord>) =
+            records.into_iter().unzip();
+        let vectors = self.read_vectors(&vector_records).await?;
+        for (item, vector) in items.iter_mut().zip(vectors) {
+            item.vector = vector;
+        }
+
+        Ok(items)
+    }
+
     async fn create_vector_file(&self, initial_size: u64) -> Result<()> {
-        let vector_path = self.path.join("vectors.dat");
+        let vector_path = self.vector_path();
 
-        let mut file = OpenOptions::new()
+        let file = OpenOptions::new()
             .read(true)
             .write(true)
             .create(true)
             .truncate(true)
             .open(&vector_path)?;
 
-        // Pre-allocate space
-        file.seek(SeekFrom::Start(initial_size - 1))?;
-        file.write_all(&[0])?;
-        file.flush()?;
-
-        let mmap = unsafe { MmapOptions::new().map_mut(&file)? };
+        // Extend without writing, so the space stays sparse where the
+        // filesystem supports it instead of being zero-filled up front
+        file.set_len(initial_size)?;
 
-        *self.vector_file.write().await = Some(file);
+        let mmap = map_vector_file(&file)?;
         *self.vector_mmap.write().await = Some(mmap);
+        *self.compaction_copy.lock().unwrap() = None;
 
         Ok(())
     }
@@ -145,20 +789,8 @@ impl OptimizedStorage {
     async fn write_vector_to_file(&self, vector: &[f32], offset: u64) -> Result<()> {
         let mut mmap_guard = self.vector_mmap.write().await;
         if let Some(ref mut mmap) = *mmap_guard {
-            let start = offset as usize;
             let dimensions = vector.len();
-
-            // Check bounds
-            let end_pos = start + VECTOR_HEADER_SIZE + (dimensions * 4);
-            let mmap_len = mmap.len();
-            if end_pos > mmap_len {
-                return Err(VectraError::StorageError {
-                    message: format!(
-                        "Memory map too small: need {} bytes, have {} bytes",
-                        end_pos, mmap_len
-                    ),
-                });
-            }
+            let start = map_range(offset, VECTOR_HEADER_SIZE + dimensions * 4, mmap.len())?.start;
 
             // Write dimensions count first (8 bytes)
             let dim_bytes = (dimensions as u64).to_le_bytes();
@@ -174,54 +806,133 @@ impl OptimizedStorage {
 
             // Don't flush on every write - let OS handle it for better performance
             // mmap.flush()?;
+
+            // A copy made for compaction no longer matches the slot
+            if let Some(copy) = self.compaction_copy.lock().unwrap().as_mut() {
+                copy.copied.remove(&offset);
+            }
         }
 
         Ok(())
     }
 
     async fn read_vector_from_file(&self, offset: u64, expected_dims: usize) -> Result<Vec<f32>> {
+        if offset & COLD_TIER != 0 {
+            let record = self.read_cold_record(offset).await?;
+            return decode_vector(&record, 0, expected_dims);
+        }
         let mmap_guard = self.vector_mmap.read().await;
         if let Some(ref mmap) = *mmap_guard {
-            let start = offset as usize;
-
-            // Read dimensions count
-            let mut dim_bytes = [0u8; 8];
-            dim_bytes.copy_from_slice(&mmap[start..start + 8]);
-            let dimensions = u64::from_le_bytes(dim_bytes) as usize;
+            decode_vector(mmap, offset, expected_dims)
+        } else {
+            Err(VectraError::StorageError {
+                message: "Vector file not initialized".to_string(),
+            })
+        }
+    }
 
-            if dimensions != expected_dims {
-                return Err(VectraError::VectorValidation {
-                    message: format!(
-                        "Dimension mismatch: expected {}, got {}",
-                        expected_dims, dimensions
-                    ),
-                });
+    /// Vectors of `records` in their order, read in ascending offset order
+    /// so large batches sweep the file instead of seeking around it
+    async fn read_vectors(&self, records: &[VectorRecord]) -> Result<Vec<Vec<f32>>> {
+        let mut order: Vec<usize> = (0..records.len()).collect();
+        order.sort_by_key(|&i| records[i].offset);
+        // Cold offsets carry the top bit, so they sort after every hot one
+        let hot = order.partition_point(|&i| records[i].offset & COLD_TIER == 0);
+
+        let mut vectors = vec![Vec::new(); records.len()];
+        if hot > 0 {
+            let mmap_guard = self.vector_mmap.read().await;
+            let mmap = mmap_guard
+                .as_ref()
+                .ok_or_else(|| VectraError::StorageError {
+                    message: "Vector file not initialized".to_string(),
+                })?;
+            for &i in &order[..hot] {
+                vectors[i] = decode_vector(mmap, records[i].offset, records[i].dimensions)?;
             }
+        }
+        for &i in &order[hot..] {
+            vectors[i] = self
+                .read_vector_from_file(records[i].offset, records[i].dimensions)
+                .await?;
+        }
+        Ok(vectors)
+    }
 
-            // Read vector data
-            let mut vector = Vec::with_capacity(dimensions);
-            let vector_start = start + VECTOR_HEADER_SIZE;
+    /// Decompress the cold block at flagged `offset` into the layout of a
+    /// vectors.dat record
+    async fn read_cold_record(&self, offset: u64) -> Result<Vec<u8>> {
+        let block = self.read_cold_block(offset).await?;
+        lz4::block::decompress(&block, None).map_err(|e| VectraError::StorageError {
+            message: format!(
+                "Cold vector at offset {} is unreadable: {}",
+                offset & !COLD_TIER,
+                e
+            ),
+        })
+    }
 
-            for i in 0..dimensions {
-                let pos = vector_start + (i * 4);
-                let mut value_bytes = [0u8; 4];
-                value_bytes.copy_from_slice(&mmap[pos..pos + 4]);
-                vector.push(f32::from_le_bytes(value_bytes));
-            }
+    /// The compressed block at flagged `offset` of vectors.cold
+    async fn read_cold_block(&self, offset: u64) -> Result<Vec<u8>> {
+        let cold_guard = self.cold_mmap.read().await;
+        let cold = cold_guard
+            .as_ref()
+            .ok_or_else(|| VectraError::StorageError {
+                message: "Cold vector file not initialized".to_string(),
+            })?;
+        let offset = offset & !COLD_TIER;
+        let header = map_range(offset, COLD_BLOCK_HEADER_SIZE, cold.len())?;
+        let len = u32::from_le_bytes(cold[header].try_into().unwrap()) as usize;
+        let block = map_range(offset + COLD_BLOCK_HEADER_SIZE as u64, len, cold.len())?;
+        Ok(cold[block].to_vec())
+    }
 
-            Ok(vector)
-        } else {
-            Err(VectraError::StorageError {
+    /// The hot record at `offset` of vectors.dat, compressed for the cold tier
+    async fn compress_hot_record(&self, offset: u64, dimensions: usize) -> Result<Vec<u8>> {
+        let mmap_guard = self.vector_mmap.read().await;
+        let mmap = mmap_guard
+            .as_ref()
+            .ok_or_else(|| VectraError::StorageError {
                 message: "Vector file not initialized".to_string(),
-            })
-        }
+            })?;
+        let record = map_range(offset, VECTOR_HEADER_SIZE + dimensions * 4, mmap.len())?;
+        Ok(lz4::block::compress(&mmap[record], None, true)?)
+    }
+
+    /// Map vectors.cold if it holds anything
+    async fn map_cold_file(&self) -> Result<()> {
+        let mmap = match std::fs::File::open(self.cold_path()) {
+            Ok(file) if file.metadata()?.len() > 0 => {
+                check_map_len(file.metadata()?.len(), MAX_MAP_LEN)?;
+                Some(unsafe { MmapOptions::new().map(&file)? })
+            }
+            Ok(_) => None,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+            Err(e) => return Err(e.into()),
+        };
+        *self.cold_mmap.write().await = mmap;
+        Ok(())
+    }
+
+    /// The generation of vectors.dat the records point into
+    fn vector_path(&self) -> PathBuf {
+        let generation = self.vector_generation.load(Ordering::Acquire);
+        self.path.join(generation_file_name("dat", generation))
+    }
+
+    /// The generation of vectors.cold the cold records point into
+    fn cold_path(&self) -> PathBuf {
+        let generation = self.cold_generation.load(Ordering::Acquire);
+        self.path.join(generation_file_name("cold", generation))
     }
 
     fn manifest_path(&self) -> PathBuf {
         self.path.join("manifest.json")
     }
 
-    async fn load_manifest(&self) -> Result<Option<Manifest>> {
+    /// Parse the manifest on disk, upgrading it and recording whether this
+    /// build may write the index
+    async fn read_manifest(&self) -> Result<Option<Negotiated<Manifest>>> {
         let manifest_path = self.manifest_path();
 
         if !manifest_path.exists() {
@@ -229,8 +940,67 @@ impl OptimizedStorage {
         }
 
         let content = fs::read_to_string(manifest_path).await?;
-        let manifest: Manifest = serde_json::from_str(&content)?;
-        Ok(Some(manifest))
+        let negotiated: Negotiated<Manifest> =
+            parse_manifest(&content).map_err(|e| damaged_manifest(&self.path, e))?;
+        *self.access.write().unwrap() = negotiated.access.clone();
+        Ok(Some(negotiated))
+    }
+
+    async fn load_manifest(&self) -> Result<Option<Manifest>> {
+        Ok(self.read_manifest().await?.map(|n| n.manifest))
+    }
+
+    /// Open the index if needed and fail unless this build may write it.
+    /// Every write path calls this before touching disk, so the first one
+    /// after a clean shutdown clears its mark here.
+    async fn ensure_writable(&self) -> Result<()> {
+        if self.db.get().is_none() {
+            self.initialize_storage().await?;
+        }
+        self.access.read().unwrap().check_writable(&self.path)?;
+        if self.clean_on_disk.load(Ordering::Acquire) {
+            self.set_clean_shutdown(false).await?;
+        }
+        Ok(())
+    }
+
+    /// Save the manifest with its clean-shutdown mark set or cleared. It
+    /// is set only once the vector file and RocksDB have been flushed.
+    async fn set_clean_shutdown(&self, clean: bool) -> Result<()> {
+        let mut guard = self.manifest.write().await;
+        let Some(manifest) = guard.as_mut() else {
+            return Ok(());
+        };
+        // The live manifest's mark is the one on disk
+        if manifest.clean_shutdown == clean {
+            return Ok(());
+        }
+        if clean {
+            // Saved below with the counters as they stand
+            self.manifest_dirty.store(false, Ordering::Release);
+            self.operations_since_save.store(0, Ordering::Release);
+        }
+        manifest.clean_shutdown = clean;
+        set_feature(&mut manifest.features.write, CLEAN_SHUTDOWN_FEATURE, clean);
+        if let Err(e) = self.save_manifest_to_disk(manifest).await {
+            manifest.clean_shutdown = !clean;
+            set_feature(&mut manifest.features.write, CLEAN_SHUTDOWN_FEATURE, !clean);
+            if clean {
+                self.manifest_dirty.store(true, Ordering::Release);
+            }
+            return Err(e);
+        }
+        self.clean_on_disk.store(clean, Ordering::Release);
+        Ok(())
+    }
+
+    /// The live manifest, which runs ahead of the copy on disk between batched saves
+    async fn current_manifest(&self) -> Result<Option<Manifest>> {
+        if let Some(mut manifest) = self.manifest.read().await.clone() {
+            self.reconcile_counters(&mut manifest);
+            return Ok(Some(manifest));
+        }
+        self.load_manifest().await
     }
 
     async fn save_manifest_to_disk(&self, manifest: &Manifest) -> Result<()> {
@@ -239,7 +1009,7 @@ impl OptimizedStorage {
         // Debug: Track manifest saves
         static SAVE_COUNT: std::sync::atomic::AtomicU32 = std::sync::atomic::AtomicU32::new(0);
         let count = SAVE_COUNT.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
-        if count % 10 == 0 {
+        if count.is_multiple_of(10) {
             println!(
                 "DEBUG: Manifest save #{}, total_items: {}",
                 count + 1,
@@ -253,14 +1023,21 @@ impl OptimizedStorage {
         }
 
         let start = std::time::Instant::now();
-        let content = serde_json::to_string_pretty(manifest)?;
+        let mut manifest = manifest.clone();
+        self.reconcile_counters(&mut manifest);
+        let content = serde_json::to_string_pretty(&manifest)?;
         let json_time = start.elapsed();
 
+        // Write then rename, so a crash never leaves a torn manifest.
+        // On Windows rename replaces the target too, as long as it isn't open.
         let start = std::time::Instant::now();
-        fs::write(manifest_path, content).await?;
+        let temp_path = manifest_path.with_extension("json.tmp");
+        fs::write(&temp_path, content).await?;
+        self.failpoints.hit(MANIFEST_BEFORE_RENAME)?;
+        fs::rename(&temp_path, &manifest_path).await?;
         let write_time = start.elapsed();
 
-        if count % 10 == 0 {
+        if count.is_multiple_of(10) {
             println!(
                 "  JSON serialize: {} µs, Disk write: {} µs",
                 json_time.as_micros(),
@@ -275,21 +1052,61 @@ impl OptimizedStorage {
         self.save_manifest_to_disk(manifest).await?;
 
         // Update in-memory manifest
-        *self.manifest.write().await = Some(manifest.clone());
+        let mut manifest = manifest.clone();
+        self.reconcile_counters(&mut manifest);
+        *self.manifest.write().await = Some(manifest);
 
         Ok(())
     }
 
-    /// Mark manifest as dirty and potentially save it based on batching interval
-    async fn mark_manifest_dirty(&self) -> Result<()> {
-        *self.manifest_dirty.write().await = true;
-
-        let mut ops_count = self.operations_since_save.write().await;
-        *ops_count += 1;
-
-        // Save manifest every N operations for crash safety vs performance balance
-        if *ops_count >= MANIFEST_SAVE_INTERVAL {
-            drop(ops_count); // Release lock before calling save
+    /// Check `dimensions` against the index's, fixing them on the first
+    /// write. Only that first write takes the manifest exclusively, and
+    /// writes fail here before an index has been created.
+    async fn claim_dimensions(&self, dimensions: usize) -> Result<()> {
+        let existing = self
+            .manifest
+            .read()
+            .await
+            .as_ref()
+            .and_then(|m| m.dimensions);
+        let existing = match existing {
+            Some(existing) => existing,
+            None => {
+                let mut manifest_guard = self.manifest.write().await;
+                let Some(manifest) = manifest_guard.as_mut() else {
+                    return Err(VectraError::StorageError {
+                        message: "Manifest not initialized".to_string(),
+                    });
+                };
+                match manifest.dimensions {
+                    Some(existing) => existing,
+                    None => {
+                        manifest.dimensions = Some(dimensions);
+                        self.save_manifest_to_disk(manifest).await?;
+                        return Ok(());
+                    }
+                }
+            }
+        };
+
+        if existing != dimensions {
+            return Err(VectraError::VectorValidation {
+                message: format!(
+                    "Vector dimension mismatch: expected {}, got {}",
+                    existing, dimensions
+                ),
+            });
+        }
+        Ok(())
+    }
+
+    /// Mark manifest as dirty and potentially save it based on batching interval
+    async fn mark_manifest_dirty(&self) -> Result<()> {
+        self.manifest_dirty.store(true, Ordering::Release);
+        let ops_count = self.operations_since_save.fetch_add(1, Ordering::AcqRel) + 1;
+
+        // Save manifest every N operations for crash safety vs performance balance
+        if ops_count >= self.manifest_save_interval.load(Ordering::Relaxed) {
             self.flush_manifest_if_dirty().await?;
         }
 
@@ -298,21 +1115,21 @@ impl OptimizedStorage {
 
     /// Save manifest to disk if it's been modified, reset dirty flag
     async fn flush_manifest_if_dirty(&self) -> Result<()> {
-        let is_dirty = {
-            let dirty = self.manifest_dirty.read().await;
-            *dirty
-        };
-
-        if is_dirty {
-            let manifest = {
-                let manifest_guard = self.manifest.read().await;
-                manifest_guard.clone()
-            };
-
-            if let Some(manifest) = manifest {
-                self.save_manifest_to_disk(&manifest).await?;
-                *self.manifest_dirty.write().await = false;
-                *self.operations_since_save.write().await = 0;
+        // Cleared before the copy is taken, so changes made while it is
+        // saved mark the manifest dirty again
+        if !self.manifest_dirty.swap(false, Ordering::AcqRel) {
+            return Ok(());
+        }
+        self.operations_since_save.store(0, Ordering::Release);
+
+        let manifest = self.manifest.write().await.as_mut().map(|manifest| {
+            self.reconcile_counters(manifest);
+            manifest.clone()
+        });
+        if let Some(manifest) = manifest {
+            if let Err(e) = self.save_manifest_to_disk(&manifest).await {
+                self.manifest_dirty.store(true, Ordering::Release);
+                return Err(e);
             }
         }
 
@@ -320,7 +1137,7 @@ impl OptimizedStorage {
     }
 
     async fn ensure_vector_file_capacity(&self, needed_size: u64) -> Result<()> {
-        let vector_path = self.path.join("vectors.dat");
+        let vector_path = self.vector_path();
 
         // Check if file exists first
         if !vector_path.exists() {
@@ -334,11 +1151,15 @@ impl OptimizedStorage {
         let current_size = metadata.len();
 
         if needed_size > current_size {
-            // Calculate new size with some headroom (grow by at least 50% or to needed size + 10MB)
+            check_map_len(needed_size, MAX_MAP_LEN)?;
+
+            // Calculate new size with some headroom (grow by at least 50% or to needed size + 10MB),
+            // capped at what this target can map
             let new_size = std::cmp::max(
                 (current_size as f64 * 1.5) as u64,
                 needed_size + (10 * 1024 * 1024), // Add 10MB buffer
-            );
+            )
+            .min(MAX_MAP_LEN);
 
             println!(
                 "    📈 Growing vector file from {} MB to {} MB",
@@ -356,12 +1177,6 @@ impl OptimizedStorage {
                 *mmap_guard = None; // Drop the old mmap
             }
 
-            // Also drop the file handle
-            {
-                let mut file_guard = self.vector_file.write().await;
-                *file_guard = None;
-            }
-
             // Resize the file
             let file = OpenOptions::new()
                 .read(true)
@@ -372,10 +1187,8 @@ impl OptimizedStorage {
             file.sync_all()?; // Ensure the resize is flushed
 
             // Create new memory map
-            let mmap = unsafe { MmapOptions::new().map_mut(&file)? };
+            let mmap = map_vector_file(&file)?;
 
-            // Update the handles
-            *self.vector_file.write().await = Some(file);
             *self.vector_mmap.write().await = Some(mmap);
 
             println!("    ✅ Vector file resized successfully");
@@ -384,24 +1197,44 @@ impl OptimizedStorage {
         Ok(())
     }
 
+    /// Claim `len` bytes at the end of vectors.dat, returning where they start
+    fn allocate_vector_slots(&self, len: u64) -> u64 {
+        self.next_offset.fetch_add(len, Ordering::AcqRel)
+    }
+
+    /// Bring `manifest`'s vector file end and item count up to the slots
+    /// claimed and items written so far
+    fn reconcile_counters(&self, manifest: &mut Manifest) {
+        let next_offset = self.next_offset.load(Ordering::Acquire);
+        manifest.next_vector_offset = next_offset;
+        manifest.vector_file_size = next_offset;
+        manifest.total_items = self.total_items.load(Ordering::Acquire);
+    }
+
     async fn get_next_vector_offset(&self, vector_size: usize) -> Result<u64> {
-        let current_offset = {
-            let mut manifest_guard = self.manifest.write().await;
-            if let Some(ref mut manifest) = *manifest_guard {
-                let current_offset = manifest.next_vector_offset;
-                let record_size = VECTOR_HEADER_SIZE + (vector_size * 4); // header + 4 bytes per f32
-                manifest.next_vector_offset += record_size as u64;
-                manifest.vector_file_size = manifest.next_vector_offset;
-                current_offset
-            } else {
-                return Err(VectraError::StorageError {
-                    message: "Manifest not initialized".to_string(),
-                });
-            }
-        };
+        // Header + 4 bytes per f32. Don't mark dirty here - let the caller
+        // decide when to mark dirty
+        Ok(self.allocate_vector_slots((VECTOR_HEADER_SIZE + vector_size * 4) as u64))
+    }
 
-        // Don't mark dirty here - let the caller decide when to mark dirty
-        Ok(current_offset)
+    /// A slot for a vector of `dimensions`: one an update left spare if
+    /// there is one, otherwise a new one
+    async fn spare_slot(&self, dimensions: usize) -> Result<u64> {
+        let size = (VECTOR_HEADER_SIZE + dimensions * 4) as u64;
+        let spare = self
+            .spare_slots
+            .lock()
+            .unwrap()
+            .get_mut(&size)
+            .and_then(Vec::pop);
+        if let Some(offset) = spare {
+            return Ok(offset);
+        }
+        let offset = self
+            .get_next_vector_offset_and_mark_dirty(dimensions)
+            .await?;
+        self.ensure_vector_file_capacity(offset + size).await?;
+        Ok(offset)
     }
 
     async fn get_next_vector_offset_and_mark_dirty(&self, vector_size: usize) -> Result<u64> {
@@ -410,23 +1243,589 @@ impl OptimizedStorage {
         Ok(offset)
     }
 
-    /// Ensure all pending changes are flushed to disk
-    pub async fn flush(&self) -> Result<()> {
-        // Flush manifest
-        self.flush_manifest_if_dirty().await?;
+    /// Read every vector record, split into live records and tombstones
+    async fn scan_vector_records(&self) -> Result<(Vec<VectorRecord>, Vec<VectorRecord>)> {
+        if self.db.get().is_none() {
+            self.initialize_storage().await?;
+        }
+
+        match self.db.get() {
+            Some(db) => read_vector_records(db),
+            None => Ok((Vec::new(), Vec::new())),
+        }
+    }
+
+    /// Copy up to `max_bytes` of the live vectors into the next
+    /// generation's file, starting over if the copy so far was made from
+    /// another generation. Slots are copied with the mapping read-locked,
+    /// so a write to one lands either before its copy or after, dropping it.
+    async fn copy_for_compaction(&self, max_bytes: u64) -> Result<u64> {
+        let source = self.vector_generation.load(Ordering::Acquire);
+        let started = self
+            .compaction_copy
+            .lock()
+            .unwrap()
+            .as_ref()
+            .is_some_and(|copy| copy.source == source);
+        if !started {
+            let (live, _) = self.scan_vector_records().await?;
+            // Popped from the back, so slots are copied in file order
+            let mut pending: Vec<(u64, usize)> = live
+                .iter()
+                .filter(|r| r.offset & COLD_TIER == 0)
+                .map(|r| (r.offset, VECTOR_HEADER_SIZE + r.dimensions * 4))
+                .collect();
+            pending.sort_unstable_by(|a, b| b.cmp(a));
+            pending.dedup_by_key(|(offset, _)| *offset);
+
+            let generation = source + 1;
+            let file = OpenOptions::new()
+                .write(true)
+                .create(true)
+                .truncate(true)
+                .open(self.path.join(generation_file_name("dat", generation)))?;
+            *self.compaction_copy.lock().unwrap() = Some(CompactionCopy {
+                source,
+                generation,
+                file,
+                written: 0,
+                pending,
+                copied: HashMap::new(),
+            });
+        }
+
+        let mmap_guard = self.vector_mmap.read().await;
+        let mmap = mmap_guard
+            .as_ref()
+            .ok_or_else(|| VectraError::StorageError {
+                message: "Vector file not initialized".to_string(),
+            })?;
+        let mut copy_guard = self.compaction_copy.lock().unwrap();
+        let Some(copy) = copy_guard.as_mut() else {
+            return Ok(0);
+        };
+        let mut pending = std::mem::take(&mut copy.pending);
+        let copied = copy.append(mmap, std::iter::from_fn(|| pending.pop()), max_bytes);
+        copy.pending = pending;
+        copied
+    }
 
+    async fn dedup_vectors(&self) -> bool {
+        self.manifest
+            .read()
+            .await
+            .as_ref()
+            .is_some_and(|m| m.dedup_vectors)
+    }
+
+    async fn metadata_encoding(&self) -> MetadataEncoding {
+        self.manifest
+            .read()
+            .await
+            .as_ref()
+            .map_or_else(MetadataEncoding::default, |m| m.metadata_encoding)
+    }
+
+    /// Hash every live vector into the slot map, unless that's been done
+    async fn load_vector_slots(&self) -> Result<()> {
+        if self.vector_slots.read().await.is_some() {
+            return Ok(());
+        }
+        let (live, _) = self.scan_vector_records().await?;
+        let mut slots = VectorSlots::default();
+        for record in live {
+            let vector = self
+                .read_vector_from_file(record.offset, record.dimensions)
+                .await?;
+            slots.acquire(vector_hash(&vector), record.offset);
+        }
+        *self.vector_slots.write().await = Some(slots);
+        Ok(())
+    }
+
+    /// Ensure all pending changes are flushed to disk, recording a clean
+    /// shutdown so the next open needn't reconcile the manifest
+    pub async fn flush(&self) -> Result<()> {
         // Flush memory-mapped file if it exists
         if let Some(ref mmap) = *self.vector_mmap.read().await {
             mmap.flush()?;
         }
 
         // Flush RocksDB
-        if let Some(ref db) = *self.db.read().await {
-            db.flush()?;
+        if let Some(db) = self.db.get() {
+            flush_column_families(db)?;
         }
 
-        Ok(())
+        // Flush manifest, last, as it vouches for the rest
+        if self
+            .access
+            .read()
+            .unwrap()
+            .check_writable(&self.path)
+            .is_ok()
+        {
+            self.set_clean_shutdown(true).await?;
+        }
+        self.flush_manifest_if_dirty().await
+    }
+}
+
+/// Bring a manifest loaded from disk in line with the vector records in `db`.
+///
+/// Manifest saves are batched, so after a crash the saved copy can lag
+/// RocksDB: the item count may be off and, worse, the next vector offset
+/// may point at slots that committed items already use, or past the end of
+/// a file a compaction switched to. Returns whether anything changed.
+fn reconcile_manifest(db: &DB, manifest: &mut Manifest) -> Result<bool> {
+    let (live, deleted) = read_vector_records(db)?;
+    let end_of_records = live
+        .iter()
+        .chain(&deleted)
+        .filter(|r| r.offset & COLD_TIER == 0)
+        .map(|r| r.offset + (VECTOR_HEADER_SIZE + r.dimensions * 4) as u64)
+        .max()
+        .unwrap_or(0);
+
+    let mut changed = false;
+    if manifest.total_items != live.len() {
+        manifest.total_items = live.len();
+        changed = true;
+    }
+    // Slots past the last record are unreferenced, and after a compaction
+    // the offset saved for the old file can lie past the end of the new one
+    if manifest.next_vector_offset != end_of_records {
+        manifest.next_vector_offset = end_of_records;
+        manifest.vector_file_size = end_of_records;
+        changed = true;
+    }
+    if manifest.dimensions.is_none() {
+        if let Some(record) = live.first() {
+            manifest.dimensions = Some(record.dimensions);
+            changed = true;
+        }
+    }
+    Ok(changed)
+}
+
+/// Write every column family's memtable out to disk. `DB::flush` only
+/// covers the default column family, so a copy of the folder taken after
+/// it alone could miss recent writes.
+fn flush_column_families(db: &DB) -> Result<()> {
+    db.flush()?;
+    for name in COLUMN_FAMILIES {
+        if let Some(cf) = db.cf_handle(name) {
+            db.flush_cf(&cf)?;
+        }
+    }
+    Ok(())
+}
+
+/// File every item under `fields` in the secondary indexes, replacing
+/// whatever their column families held
+fn build_secondary_keys(db: &DB, fields: &KeyFields) -> Result<()> {
+    let mut batch = rocksdb::WriteBatch::default();
+    for cf in SECONDARY_CFS {
+        let handle = db.cf_handle(cf).unwrap();
+        let last = db
+            .iterator_cf(&handle, rocksdb::IteratorMode::End)
+            .next()
+            .transpose()?;
+        if let Some((last, _)) = last {
+            // Just past the last key
+            let mut end = last.to_vec();
+            end.push(0);
+            batch.delete_range_cf(&handle, [].as_slice(), end.as_slice());
+        }
+    }
+    db.write(batch)?;
+    file_secondary_keys(db, fields)
+}
+
+/// File every item under `fields` in the secondary indexes
+fn file_secondary_keys(db: &DB, fields: &KeyFields) -> Result<()> {
+    let metadata_cf = db.cf_handle(METADATA_CF).unwrap();
+    let mut batch = rocksdb::WriteBatch::default();
+    for entry in db.iterator_cf(&metadata_cf, rocksdb::IteratorMode::Start) {
+        let (_, value) = entry?;
+        let item = decode_metadata(&value)?;
+        put_keys(db, &mut batch, fields.keys(&item));
+        if batch.len() >= MULTI_GET_BATCH {
+            db.write(std::mem::take(&mut batch))?;
+        }
+    }
+    db.write(batch)?;
+    Ok(())
+}
+
+/// Byte range `offset..offset + len` of a mapping `map_len` long, checked so a
+/// corrupt or unaddressable offset is an error rather than a wrap or a panic
+fn map_range(offset: u64, len: usize, map_len: usize) -> Result<std::ops::Range<usize>> {
+    usize::try_from(offset)
+        .ok()
+        .and_then(|start| Some(start..start.checked_add(len)?))
+        .filter(|range| range.end <= map_len)
+        .ok_or_else(|| VectraError::StorageError {
+            message: format!(
+                "Vector record at offset {} ({} bytes) lies outside the {} byte vector file",
+                offset, len, map_len
+            ),
+        })
+}
+
+fn has_feature(features: &[String], feature: &str) -> bool {
+    features.iter().any(|f| f == feature)
+}
+
+/// Key range start of the keys filed under `name`, a namespace in
+/// [`NAMESPACE_CF`] or a field in [`RANGE_CF`]
+fn hash_prefix(name: &str) -> [u8; NAMESPACE_PREFIX_LEN] {
+    let hash = blake3::hash(name.as_bytes());
+    let mut prefix = [0; NAMESPACE_PREFIX_LEN];
+    prefix.copy_from_slice(&hash.as_bytes()[..NAMESPACE_PREFIX_LEN]);
+    prefix
+}
+
+/// The smallest key past every key starting with `prefix`, if any is
+fn prefix_successor(prefix: &[u8]) -> Option<Vec<u8>> {
+    let mut end = prefix.to_vec();
+    while let Some(last) = end.pop() {
+        if last < u8::MAX {
+            end.push(last + 1);
+            return Some(end);
+        }
+    }
+    None
+}
+
+/// Key filing `item` under its namespace, if `field` gives it one
+fn item_namespace_key(field: Option<&str>, item: &VectorItem) -> Option<Vec<u8>> {
+    let namespace = namespace_of(field?, item)?;
+    let mut key = hash_prefix(&namespace).to_vec();
+    key.extend_from_slice(item.id.as_bytes());
+    Some(key)
+}
+
+/// A number's bytes in an order that sorts like the numbers: the sign bit
+/// flipped for positives, and every bit for negatives
+fn range_value_bytes(value: f64) -> [u8; 8] {
+    // -0.0 compares equal to 0.0, so it must sort with it
+    let bits = if value == 0.0 { 0 } else { value.to_bits() };
+    let ordered = if bits >> 63 == 1 {
+        !bits
+    } else {
+        bits | 1 << 63
+    };
+    ordered.to_be_bytes()
+}
+
+/// Start of the keys holding `value` in range field `field`
+fn range_value_prefix(field: &str, value: f64) -> Vec<u8> {
+    let mut prefix = hash_prefix(field).to_vec();
+    prefix.extend_from_slice(&range_value_bytes(value));
+    prefix
+}
+
+/// Key filing `item` under the number in range field `field`, a dotted
+/// path, if it holds one
+fn item_range_key(field: &str, item: &VectorItem) -> Option<Vec<u8>> {
+    let value = metadata_at(item, field)?.as_f64()?;
+    let mut key = range_value_prefix(field, value);
+    key.extend_from_slice(item.id.as_bytes());
+    Some(key)
+}
+
+/// Value at the dotted path `field` of an item's metadata
+fn metadata_at<'a>(item: &'a VectorItem, field: &str) -> Option<&'a serde_json::Value> {
+    field
+        .split('.')
+        .try_fold(&item.metadata, |value, segment| value.get(segment))
+}
+
+/// Hash of a string, number or boolean that values equal under filters
+/// share, numbers comparing by value
+fn composite_value_hash(value: &serde_json::Value) -> Option<[u8; 8]> {
+    let mut hasher = blake3::Hasher::new();
+    match value {
+        serde_json::Value::String(s) => hasher.update(b"s").update(s.as_bytes()),
+        serde_json::Value::Number(n) => {
+            let n = n.as_f64()?;
+            let bits = if n == 0.0 { 0 } else { n.to_bits() };
+            hasher.update(b"n").update(&bits.to_be_bytes())
+        }
+        serde_json::Value::Bool(b) => hasher.update(b"b").update(&[u8::from(*b)]),
+        _ => return None,
+    };
+    let mut hash = [0; 8];
+    hash.copy_from_slice(&hasher.finalize().as_bytes()[..8]);
+    Some(hash)
+}
+
+/// Start of the keys of composite `index` whose leading fields hold `values`
+fn composite_prefix<'a>(
+    index: &CompositeIndex,
+    values: impl IntoIterator<Item = &'a serde_json::Value>,
+) -> Option<Vec<u8>> {
+    let mut prefix = hash_prefix(&index.fields.join("\0")).to_vec();
+    for value in values {
+        prefix.extend_from_slice(&composite_value_hash(value)?);
+    }
+    Some(prefix)
+}
+
+/// Key filing `item` under composite `index`, if it holds a string, number
+/// or boolean in every field
+fn item_composite_key(index: &CompositeIndex, item: &VectorItem) -> Option<Vec<u8>> {
+    let values: Option<Vec<&serde_json::Value>> = index
+        .fields
+        .iter()
+        .map(|field| metadata_at(item, field))
+        .collect();
+    let mut key = composite_prefix(index, values?)?;
+    key.extend_from_slice(item.id.as_bytes());
+    Some(key)
+}
+
+/// An entry in a secondary index: its column family and key
+type SecondaryKey = (&'static str, Vec<u8>);
+
+/// Fields items are filed under in the secondary indexes
+#[derive(Debug, Clone, Default)]
+struct KeyFields {
+    /// Namespace field of [`NAMESPACE_CF`]
+    namespace: Option<String>,
+    /// Numeric fields of [`RANGE_CF`]
+    ranges: Vec<String>,
+    /// Indexes of [`COMPOSITE_CF`]
+    composites: Vec<CompositeIndex>,
+}
+
+impl KeyFields {
+    /// The fields of an index with `manifest`, less any whose keys it
+    /// doesn't keep
+    fn of(manifest: &Manifest) -> Self {
+        let keeps = |feature| has_feature(&manifest.features.write, feature);
+        Self {
+            namespace: manifest
+                .namespace_field
+                .clone()
+                .filter(|_| keeps(NAMESPACE_KEYS_FEATURE)),
+            ranges: match keeps(RANGE_KEYS_FEATURE) {
+                true => manifest.range_fields.clone(),
+                false => Vec::new(),
+            },
+            composites: match keeps(COMPOSITE_KEYS_FEATURE) {
+                true => manifest.composite_indexes.clone(),
+                false => Vec::new(),
+            },
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.namespace.is_none() && self.ranges.is_empty() && self.composites.is_empty()
+    }
+
+    /// Every secondary key filing `item`
+    fn keys(&self, item: &VectorItem) -> Vec<SecondaryKey> {
+        let namespace = item_namespace_key(self.namespace.as_deref(), item);
+        let ranges = self
+            .ranges
+            .iter()
+            .filter_map(|field| item_range_key(field, item));
+        let composites = self
+            .composites
+            .iter()
+            .filter_map(|index| item_composite_key(index, item));
+        namespace
+            .map(|key| (NAMESPACE_CF, key))
+            .into_iter()
+            .chain(ranges.map(|key| (RANGE_CF, key)))
+            .chain(composites.map(|key| (COMPOSITE_CF, key)))
+            .collect()
+    }
+}
+
+/// Secondary keys of the stored copy of `item` that writing `item` would
+/// leave behind
+fn stale_keys(
+    db: &DB,
+    metadata_cf: &impl rocksdb::AsColumnFamilyRef,
+    fields: &KeyFields,
+    item: &VectorItem,
+) -> Result<Vec<SecondaryKey>> {
+    if fields.is_empty() {
+        return Ok(Vec::new());
+    }
+    let Some(stored) = db.get_cf(metadata_cf, item.id.as_bytes())? else {
+        return Ok(Vec::new());
+    };
+    let current = fields.keys(item);
+    let mut old = fields.keys(&decode_metadata(&stored)?);
+    old.retain(|key| !current.contains(key));
+    Ok(old)
+}
+
+fn put_keys(db: &DB, batch: &mut rocksdb::WriteBatch, keys: Vec<SecondaryKey>) {
+    for (cf, key) in keys {
+        batch.put_cf(&db.cf_handle(cf).unwrap(), key, []);
+    }
+}
+
+fn delete_keys(db: &DB, batch: &mut rocksdb::WriteBatch, keys: Vec<SecondaryKey>) {
+    for (cf, key) in keys {
+        batch.delete_cf(&db.cf_handle(cf).unwrap(), key);
+    }
+}
+
+/// Add `feature` to or remove it from a manifest feature list
+fn set_feature(features: &mut Vec<String>, feature: &str, enabled: bool) {
+    let present = has_feature(features, feature);
+    if enabled && !present {
+        features.push(feature.to_string());
+    } else if !enabled && present {
+        features.retain(|f| f != feature);
+    }
+}
+
+/// A metadata record: the item without its vector, as JSON or CBOR
+fn encode_metadata(item: &VectorItem, encoding: MetadataEncoding) -> Result<Vec<u8>> {
+    match encoding {
+        MetadataEncoding::Json => Ok(serde_json::to_vec(item)?),
+        MetadataEncoding::Cbor => {
+            let mut bytes = Vec::new();
+            ciborium::into_writer(item, &mut bytes).map_err(|e| VectraError::Storage {
+                message: format!("Failed to encode metadata of {}: {}", item.id, e),
+            })?;
+            Ok(bytes)
+        }
+    }
+}
+
+/// Decode a metadata record in either encoding. A JSON record is an
+/// object and so starts with `{`, which never starts an encoded item in
+/// CBOR, so an index can hold both while it is being re-encoded.
+fn decode_metadata(bytes: &[u8]) -> Result<VectorItem> {
+    if bytes.first() == Some(&b'{') {
+        return Ok(serde_json::from_slice(bytes)?);
+    }
+    ciborium::from_reader(bytes).map_err(|e| VectraError::Storage {
+        message: format!("Unreadable metadata record: {}", e),
+    })
+}
+
+/// The vector in the record at `offset` of `map`, which must have `expected_dims`
+fn decode_vector(map: &[u8], offset: u64, expected_dims: usize) -> Result<Vec<f32>> {
+    let header = map_range(offset, VECTOR_HEADER_SIZE, map.len())?;
+    let start = header.start;
+
+    // Read dimensions count
+    let mut dim_bytes = [0u8; 8];
+    dim_bytes.copy_from_slice(&map[header]);
+    let dimensions = u64::from_le_bytes(dim_bytes) as usize;
+
+    if dimensions != expected_dims {
+        return Err(VectraError::VectorValidation {
+            message: format!(
+                "Dimension mismatch: expected {}, got {}",
+                expected_dims, dimensions
+            ),
+        });
+    }
+
+    // Read vector data
+    map_range(offset, VECTOR_HEADER_SIZE + dimensions * 4, map.len())?;
+    let mut vector = Vec::with_capacity(dimensions);
+    let vector_start = start + VECTOR_HEADER_SIZE;
+
+    for i in 0..dimensions {
+        let pos = vector_start + (i * 4);
+        let mut value_bytes = [0u8; 4];
+        value_bytes.copy_from_slice(&map[pos..pos + 4]);
+        vector.push(f32::from_le_bytes(value_bytes));
+    }
+
+    Ok(vector)
+}
+
+/// Refuse vector files larger than `limit`, rather than mapping them truncated
+fn check_map_len(len: u64, limit: u64) -> Result<()> {
+    if len > limit {
+        return Err(VectraError::StorageError {
+            message: format!(
+                "Vector file needs {} bytes but this {}-bit build can map at most {}; \
+                 open the index from a 64-bit build",
+                len,
+                usize::BITS,
+                limit
+            ),
+        });
+    }
+    Ok(())
+}
+
+/// Name of generation `generation` of the vector file with extension
+/// `ext`: `vectors.{ext}` first, then `vectors.{generation}.{ext}`
+fn generation_file_name(ext: &str, generation: u64) -> String {
+    if generation == 0 {
+        format!("vectors.{}", ext)
+    } else {
+        format!("vectors.{}.{}", generation, ext)
+    }
+}
+
+/// Generation of the `ext` vector file called `name`, if it is one
+fn file_generation(name: &str, ext: &str) -> Option<u64> {
+    let rest = name.strip_prefix("vectors.")?;
+    if rest == ext {
+        return Some(0);
+    }
+    rest.strip_suffix(ext)?.strip_suffix('.')?.parse().ok()
+}
+
+/// Remove `ext` vector files of generations other than `current`, which a
+/// swap interrupted before or after it committed leaves behind
+fn remove_stale_generations(dir: &Path, ext: &str, current: u64) -> Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let stale = entry
+            .file_name()
+            .to_str()
+            .and_then(|name| file_generation(name, ext))
+            .is_some_and(|generation| generation != current);
+        if stale {
+            std::fs::remove_file(entry.path())?;
+        }
+    }
+    Ok(())
+}
+
+/// Generation saved under `key`, 0 until the first swap
+fn read_generation(db: &DB, key: &[u8]) -> Result<u64> {
+    Ok(db
+        .get(key)?
+        .and_then(|bytes| <[u8; 8]>::try_from(bytes).ok())
+        .map_or(0, u64::from_le_bytes))
+}
+
+fn map_vector_file(file: &std::fs::File) -> Result<MmapMut> {
+    check_map_len(file.metadata()?.len(), MAX_MAP_LEN)?;
+    Ok(unsafe { MmapOptions::new().map_mut(file)? })
+}
+
+/// Every vector record in `db`, split into live records and tombstones
+fn read_vector_records(db: &DB) -> Result<(Vec<VectorRecord>, Vec<VectorRecord>)> {
+    let mut live = Vec::new();
+    let mut deleted = Vec::new();
+    let vector_index_cf = db.cf_handle(VECTOR_INDEX_CF).unwrap();
+    for entry in db.iterator_cf(&vector_index_cf, rocksdb::IteratorMode::Start) {
+        let (_, value) = entry?;
+        let record: VectorRecord = bincode::deserialize(&value)?;
+        if record.deleted {
+            deleted.push(record);
+        } else {
+            live.push(record);
+        }
     }
+    Ok((live, deleted))
 }
 
 #[async_trait]
@@ -444,13 +1843,51 @@ impl StorageBackend for OptimizedStorage {
             });
         }
 
-        // Clean up existing files if delete_if_exists is true
+        // Clean up existing files if delete_if_exists is true. Open handles
+        // go first: Windows refuses to remove files that are open or mapped.
+        self.db.take();
+        *self.vector_mmap.write().await = None;
+        *self.cold_mmap.write().await = None;
         if config.delete_if_exists && self.path.exists() {
             fs::remove_dir_all(&self.path).await.ok();
         }
-
+        *self.vector_slots.write().await = None;
+        self.spare_slots.lock().unwrap().clear();
+        self.quarantine.write().unwrap().clear();
+        self.access_counts.lock().unwrap().clear();
+        self.next_offset.store(0, Ordering::Release);
+        self.total_items.store(0, Ordering::Release);
+        self.vector_generation.store(0, Ordering::Release);
+        self.cold_generation.store(0, Ordering::Release);
+
+        let mut features =
+            FormatFeatures::for_index(config.embedding_model.as_ref(), config.limits.as_ref());
+        if config.dedup_vectors {
+            features.write.push(VECTOR_DEDUP_FEATURE.to_string());
+        }
+        if config.metadata_encoding == MetadataEncoding::Cbor {
+            features.required.push(METADATA_CBOR_FEATURE.to_string());
+        }
+        if config.namespace_field.is_some() {
+            features.write.push(NAMESPACE_KEYS_FEATURE.to_string());
+        }
+        if !config.range_fields.is_empty() {
+            features.write.push(RANGE_KEYS_FEATURE.to_string());
+        }
+        let composite_indexes = config.metadata_config.composite_indexes.clone();
+        if !composite_indexes.is_empty() {
+            features.write.push(COMPOSITE_KEYS_FEATURE.to_string());
+        }
+        if config.vector_transform.is_some() {
+            features.write.push(VECTOR_TRANSFORM_FEATURE.to_string());
+        }
+        if config.vector_validation.is_some() {
+            features.write.push(VECTOR_VALIDATION_FEATURE.to_string());
+        }
+        // Nothing has been written yet that could need reconciling
+        features.write.push(CLEAN_SHUTDOWN_FEATURE.to_string());
         let manifest = Manifest {
-            version: 2,
+            version: FORMAT_VERSION,
             format: "optimized".to_string(),
             created_at: chrono::Utc::now(),
             dimensions: None,
@@ -458,6 +1895,20 @@ impl StorageBackend for OptimizedStorage {
             total_items: 0,
             vector_file_size: 0,
             next_vector_offset: 0,
+            compaction: config.compaction.clone(),
+            embedding_model: config.embedding_model.clone(),
+            limits: config.limits.clone(),
+            vector_transform: config.vector_transform.clone(),
+            vector_validation: config.vector_validation.clone(),
+            namespace_field: config.namespace_field.clone(),
+            range_fields: config.range_fields.clone(),
+            composite_indexes,
+            dedup_vectors: config.dedup_vectors,
+            metadata_encoding: config.metadata_encoding,
+            rocksdb_tuning: config.rocksdb_tuning.clone(),
+            features,
+            clean_shutdown: true,
+            extra: serde_json::Map::new(),
         };
 
         self.save_manifest(&manifest).await?;
@@ -476,15 +1927,18 @@ impl StorageBackend for OptimizedStorage {
     }
 
     async fn get_item(&self, id: &Uuid) -> Result<Option<VectorItem>> {
+        if self.is_quarantined(id) {
+            return Ok(None);
+        }
+
         // Ensure storage is initialized for read operations
-        if self.db.read().await.is_none() {
+        if self.db.get().is_none() {
             self.initialize_storage().await?;
         }
 
         // Read from RocksDB synchronously (cf handles are not Send)
         let read_result = {
-            let db_guard = self.db.read().await;
-            if let Some(ref db) = *db_guard {
+            if let Some(db) = self.db.get() {
                 let metadata_cf = db.cf_handle(METADATA_CF).unwrap();
                 let vector_index_cf = db.cf_handle(VECTOR_INDEX_CF).unwrap();
                 let id_bytes = id.as_bytes();
@@ -498,7 +1952,7 @@ impl StorageBackend for OptimizedStorage {
         };
 
         if let Some((Some(metadata_bytes), Some(vector_record_bytes))) = read_result {
-            let mut item: VectorItem = serde_json::from_slice(&metadata_bytes)?;
+            let mut item = decode_metadata(&metadata_bytes)?;
             let vector_record: VectorRecord = bincode::deserialize(&vector_record_bytes)?;
 
             if !vector_record.deleted {
@@ -512,83 +1966,130 @@ impl StorageBackend for OptimizedStorage {
         Ok(None)
     }
 
-    async fn insert_item(&mut self, item: &VectorItem) -> Result<()> {
-        // Ensure storage is initialized
-        if self.db.read().await.is_none() {
+    async fn get_items(&self, ids: &[Uuid]) -> Result<Vec<Option<VectorItem>>> {
+        if self.db.get().is_none() {
             self.initialize_storage().await?;
         }
 
+        // Where each found item goes in the result, with its vector record
+        let found = {
+            let mut found = Vec::new();
+            if let Some(db) = self.db.get() {
+                let metadata_cf = db.cf_handle(METADATA_CF).unwrap();
+                let vector_index_cf = db.cf_handle(VECTOR_INDEX_CF).unwrap();
+                for (chunk_index, chunk) in ids.chunks(MULTI_GET_BATCH).enumerate() {
+                    let keys = || chunk.iter().map(|id| id.as_bytes());
+                    let metadata = db.batched_multi_get_cf(&metadata_cf, keys(), false);
+                    let vector_records = db.batched_multi_get_cf(&vector_index_cf, keys(), false);
+                    for (i, (metadata, vector_record)) in
+                        metadata.into_iter().zip(vector_records).enumerate()
+                    {
+                        if self.is_quarantined(&chunk[i]) {
+                            continue;
+                        }
+                        let (Some(metadata_bytes), Some(vector_record_bytes)) =
+                            (metadata?, vector_record?)
+                        else {
+                            continue;
+                        };
+                        let vector_record: VectorRecord =
+                            bincode::deserialize(&vector_record_bytes)?;
+                        if !vector_record.deleted {
+                            let item = decode_metadata(&metadata_bytes)?;
+                            found.push((chunk_index * MULTI_GET_BATCH + i, item, vector_record));
+                        }
+                    }
+                }
+            }
+            found
+        };
+
+        let vector_records: Vec<VectorRecord> = found.iter().map(|(_, _, r)| r.clone()).collect();
+        let vectors = self.read_vectors(&vector_records).await?;
+        let mut items = vec![None; ids.len()];
+        for ((position, mut item, _), vector) in found.into_iter().zip(vectors) {
+            item.vector = vector;
+            items[position] = Some(item);
+        }
+        Ok(items)
+    }
+
+    async fn insert_item(&mut self, item: &VectorItem) -> Result<()> {
+        // Ensure storage is initialized
+        self.ensure_writable().await?;
+        self.check_new_ids(std::slice::from_ref(item)).await?;
+
         // Add timing for debugging
         let start_total = std::time::Instant::now();
 
         // Debug: count inserts
         static DEBUG_COUNT: std::sync::atomic::AtomicU32 = std::sync::atomic::AtomicU32::new(0);
         let debug_count = DEBUG_COUNT.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
-        if debug_count % 50 == 0 {
+        if debug_count.is_multiple_of(50) {
             println!("DEBUG: insert_item called {} times", debug_count + 1);
         }
 
         let dimensions = item.vector.len();
 
-        // Set dimensions if this is the first item
-        let mut needs_manifest_update = false;
-        {
-            let mut dims_guard = self.dimensions.write().await;
-            if dims_guard.is_none() {
-                *dims_guard = Some(dimensions);
-                needs_manifest_update = true;
-            } else if let Some(existing_dims) = *dims_guard {
-                if existing_dims != dimensions {
-                    return Err(VectraError::VectorValidation {
-                        message: format!(
-                            "Vector dimension mismatch: expected {}, got {}",
-                            existing_dims, dimensions
-                        ),
-                    });
-                }
-            }
-        }
+        self.claim_dimensions(dimensions).await?;
 
-        // Update manifest outside of the dimensions lock to avoid deadlock
-        if needs_manifest_update {
-            let mut manifest_guard = self.manifest.write().await;
-            if let Some(ref mut manifest) = *manifest_guard {
-                manifest.dimensions = Some(dimensions);
-                // Save manifest to disk only, we already have the in-memory version updated
-                self.save_manifest_to_disk(manifest).await?;
-            }
-        }
+        // A deduplicating index points the record at an existing copy
+        let hash = if self.dedup_vectors().await {
+            self.load_vector_slots().await?;
+            Some(vector_hash(&item.vector))
+        } else {
+            None
+        };
+        let shared = match hash {
+            Some(ref hash) => self
+                .vector_slots
+                .read()
+                .await
+                .as_ref()
+                .and_then(|slots| slots.find(hash)),
+            None => None,
+        };
 
-        // Get offset for vector storage
-        let start = std::time::Instant::now();
-        let vector_offset = self
-            .get_next_vector_offset_and_mark_dirty(dimensions)
-            .await?;
-        let offset_time = start.elapsed();
+        let mut offset_time = std::time::Duration::ZERO;
+        let mut mmap_time = std::time::Duration::ZERO;
+        let vector_offset = match shared {
+            Some(offset) => offset,
+            None => {
+                // Get offset for vector storage
+                let start = std::time::Instant::now();
+                let offset = self
+                    .get_next_vector_offset_and_mark_dirty(dimensions)
+                    .await?;
+                offset_time = start.elapsed();
 
-        // Write vector to memory-mapped file
-        let start = std::time::Instant::now();
-        self.write_vector_to_file(&item.vector, vector_offset)
-            .await?;
-        let mmap_time = start.elapsed();
+                // Write vector to memory-mapped file
+                let start = std::time::Instant::now();
+                self.write_vector_to_file(&item.vector, offset).await?;
+                mmap_time = start.elapsed();
+                self.failpoints.hit(INSERT_AFTER_VECTOR_WRITE)?;
+                offset
+            }
+        };
 
         // Store metadata and vector record in RocksDB
         // Scoped to drop cf handles (non-Send) before any .await
+        let encoding = self.metadata_encoding().await;
+        let secondary_keys = self.key_fields().await.keys(item);
         let db_time = {
-            let db_guard = self.db.read().await;
-            if let Some(ref db) = *db_guard {
+            if let Some(db) = self.db.get() {
                 let metadata_cf = db.cf_handle(METADATA_CF).unwrap();
                 let vector_index_cf = db.cf_handle(VECTOR_INDEX_CF).unwrap();
 
                 let id_bytes = item.id.as_bytes();
                 let mut metadata_item = item.clone();
                 metadata_item.vector = Vec::new();
-                let metadata_bytes = serde_json::to_vec(&metadata_item)?;
+                let metadata_bytes = encode_metadata(&metadata_item, encoding)?;
                 let mut write_opts = rocksdb::WriteOptions::default();
                 write_opts.disable_wal(true);
 
                 let start = std::time::Instant::now();
-                db.put_cf_opt(&metadata_cf, id_bytes, metadata_bytes, &write_opts)?;
+                let mut batch = rocksdb::WriteBatch::default();
+                batch.put_cf(&metadata_cf, id_bytes, metadata_bytes);
 
                 let vector_record = VectorRecord {
                     id: item.id,
@@ -597,7 +2098,9 @@ impl StorageBackend for OptimizedStorage {
                     deleted: false,
                 };
                 let vector_record_bytes = bincode::serialize(&vector_record)?;
-                db.put_cf_opt(&vector_index_cf, id_bytes, vector_record_bytes, &write_opts)?;
+                batch.put_cf(&vector_index_cf, id_bytes, vector_record_bytes);
+                put_keys(db, &mut batch, secondary_keys);
+                db.write_opt(batch, &write_opts)?;
                 start.elapsed()
             } else {
                 return Err(VectraError::StorageError {
@@ -605,20 +2108,17 @@ impl StorageBackend for OptimizedStorage {
                 });
             }
         };
+        self.failpoints.hit(INSERT_AFTER_DB_WRITE)?;
+
+        if let Some(hash) = hash {
+            if let Some(ref mut slots) = *self.vector_slots.write().await {
+                slots.acquire(hash, vector_offset);
+            }
+        }
 
         {
-            // Update manifest and log timing
-            let total_items = {
-                let mut manifest_guard = self.manifest.write().await;
-                if let Some(ref mut manifest) = *manifest_guard {
-                    manifest.total_items += 1;
-                    manifest.total_items
-                } else {
-                    return Err(VectraError::StorageError {
-                        message: "Manifest not initialized".to_string(),
-                    });
-                }
-            };
+            // Count the item and log timing
+            let total_items = self.total_items.fetch_add(1, Ordering::AcqRel) + 1;
 
             // Mark manifest dirty for batched saving
             self.mark_manifest_dirty().await?;
@@ -640,215 +2140,273 @@ impl StorageBackend for OptimizedStorage {
                          total_time.as_micros() - offset_time.as_micros() - mmap_time.as_micros() - db_time.as_micros());
             }
         }
+        self.release_quarantine([&item.id]);
 
         Ok(())
     }
 
     async fn insert_items(&mut self, items: &[VectorItem]) -> Result<()> {
+        self.ensure_writable().await?;
+        self.check_new_ids(items).await?;
+        self.write_items(items, Vec::new()).await
+    }
+
+    async fn update_item(&mut self, item: &VectorItem) -> Result<()> {
+        self.update_items(std::slice::from_ref(item)).await
+    }
+
+    /// Vectors keeping their length are written to a slot an earlier
+    /// update left spare, and their records move there in the batch that
+    /// writes the metadata, so a crash leaves either the old vector and
+    /// metadata or the new ones. The slot moved out of is kept for the next
+    /// update, so update-heavy workloads don't grow the file. Other
+    /// vectors, cold ones, and every vector in a deduplicating index where
+    /// slots may be shared, are appended.
+    async fn update_items(&mut self, items: &[VectorItem]) -> Result<()> {
         if items.is_empty() {
             return Ok(());
         }
-
-        // Ensure storage is initialized
-        if self.db.read().await.is_none() {
-            self.initialize_storage().await?;
+        self.ensure_writable().await?;
+        let dedup = self.dedup_vectors().await;
+
+        // An appended item's record is overwritten by the insert's write
+        // batch, so rather than tombstoning each one first, note what the
+        // records held
+        let mut respaced = Vec::new();
+        let mut appended = Vec::new();
+        let mut old_offsets = Vec::new();
+        // Secondary keys the items are moving away from
+        let key_fields = self.key_fields().await;
+        let mut respaced_stale = Vec::new();
+        let mut appended_stale = Vec::new();
+        {
+            if let Some(db) = self.db.get() {
+                let metadata_cf = db.cf_handle(METADATA_CF).unwrap();
+                let vector_index_cf = db.cf_handle(VECTOR_INDEX_CF).unwrap();
+                for item in items {
+                    let record = match db.get_cf(&vector_index_cf, item.id.as_bytes())? {
+                        Some(bytes) => match bincode::deserialize::<VectorRecord>(&bytes) {
+                            Ok(record) if !record.deleted => Some(record),
+                            Ok(_) => return Err(VectraError::ItemNotFound),
+                            // A damaged record is replaced by appending the item afresh
+                            Err(_) if self.is_quarantined(&item.id) => None,
+                            Err(e) => return Err(e.into()),
+                        },
+                        None => return Err(VectraError::ItemNotFound),
+                    };
+                    let stale = stale_keys(db, &metadata_cf, &key_fields, item)?;
+                    if let Some(record) = record {
+                        if !dedup
+                            && record.offset & COLD_TIER == 0
+                            && record.dimensions == item.vector.len()
+                        {
+                            respaced.push((item, record));
+                            respaced_stale.extend(stale);
+                            continue;
+                        }
+                        old_offsets.push(record.offset);
+                    }
+                    appended.push(item.clone());
+                    appended_stale.extend(stale);
+                }
+            }
         }
 
-        // Validate all items have same dimensions
-        let first_dimensions = items[0].vector.len();
-        for item in items {
-            if item.vector.len() != first_dimensions {
+        // Fail before writing anything if an appended vector can't be stored
+        let dimensions = self
+            .manifest
+            .read()
+            .await
+            .as_ref()
+            .and_then(|m| m.dimensions);
+        if let Some(dimensions) = dimensions {
+            if let Some(item) = appended.iter().find(|i| i.vector.len() != dimensions) {
                 return Err(VectraError::VectorValidation {
                     message: format!(
-                        "All vectors must have same dimensions. Expected {}, got {}",
-                        first_dimensions,
+                        "Vector dimension mismatch: expected {}, got {}",
+                        dimensions,
                         item.vector.len()
                     ),
                 });
             }
         }
 
-        // Set dimensions if this is the first batch
-        let mut needs_manifest_update = false;
-        {
-            let mut dims_guard = self.dimensions.write().await;
-            if dims_guard.is_none() {
-                *dims_guard = Some(first_dimensions);
-                needs_manifest_update = true;
-            } else if let Some(existing_dims) = *dims_guard {
-                if existing_dims != first_dimensions {
-                    return Err(VectraError::VectorValidation {
-                        message: format!(
-                            "Vector dimension mismatch: expected {}, got {}",
-                            existing_dims, first_dimensions
-                        ),
-                    });
+        if !respaced.is_empty() {
+            let mut moved = Vec::with_capacity(respaced.len());
+            for (item, record) in &respaced {
+                let offset = self.spare_slot(record.dimensions).await?;
+                self.write_vector_to_file(&item.vector, offset).await?;
+                moved.push(VectorRecord {
+                    offset,
+                    ..record.clone()
+                });
+            }
+            self.failpoints.hit(UPDATE_AFTER_VECTOR_WRITE)?;
+            let encoding = self.metadata_encoding().await;
+            if let Some(db) = self.db.get() {
+                let metadata_cf = db.cf_handle(METADATA_CF).unwrap();
+                let vector_index_cf = db.cf_handle(VECTOR_INDEX_CF).unwrap();
+                let mut batch = rocksdb::WriteBatch::default();
+                delete_keys(db, &mut batch, respaced_stale);
+                for ((item, _), record) in respaced.iter().zip(&moved) {
+                    let mut metadata_item = (*item).clone();
+                    metadata_item.vector = Vec::new();
+                    batch.put_cf(
+                        &metadata_cf,
+                        item.id.as_bytes(),
+                        encode_metadata(&metadata_item, encoding)?,
+                    );
+                    batch.put_cf(
+                        &vector_index_cf,
+                        item.id.as_bytes(),
+                        bincode::serialize(record)?,
+                    );
+                    put_keys(db, &mut batch, key_fields.keys(item));
                 }
+                db.write(batch)?;
             }
-        }
 
-        // Update manifest dimensions if needed
-        if needs_manifest_update {
-            let mut manifest_guard = self.manifest.write().await;
-            if let Some(ref mut manifest) = *manifest_guard {
-                manifest.dimensions = Some(first_dimensions);
-                self.save_manifest_to_disk(manifest).await?;
+            // An id given twice moved out of its old slot once
+            let mut spare_slots = self.spare_slots.lock().unwrap();
+            let mut freed = HashSet::new();
+            for (_, record) in &respaced {
+                if freed.insert(record.offset) {
+                    let size = (VECTOR_HEADER_SIZE + record.dimensions * 4) as u64;
+                    spare_slots.entry(size).or_default().push(record.offset);
+                }
             }
         }
 
-        // Calculate total space needed for all vectors
-        let record_size = VECTOR_HEADER_SIZE + (first_dimensions * 4);
-        let total_space_needed = items.len() * record_size;
+        self.write_items(&appended, appended_stale).await?;
 
-        // Pre-check and grow file if needed before any writes
-        {
-            let manifest = self.manifest.read().await;
-            if let Some(ref m) = *manifest {
-                let current_offset = m.next_vector_offset;
-                let final_size = current_offset + total_space_needed as u64;
-                drop(manifest); // Release the lock before calling ensure_vector_file_capacity
-
-                // Only print for large batches
-                if items.len() >= 2500 {
-                    println!(
-                        "DEBUG: Batch size: {}, Current offset: {}, Final size needed: {}",
-                        items.len(),
-                        current_offset,
-                        final_size
-                    );
-                }
-
-                self.ensure_vector_file_capacity(final_size).await?;
+        if let Some(ref mut slots) = *self.vector_slots.write().await {
+            for offset in old_offsets {
+                slots.release(offset);
             }
         }
-
-        // Pre-allocate ALL vector offsets at once to avoid repeated lock acquisition
-        let record_size = VECTOR_HEADER_SIZE + (first_dimensions * 4);
-        let mut offsets = Vec::with_capacity(items.len());
-        {
-            let mut manifest_guard = self.manifest.write().await;
-            if let Some(ref mut manifest) = *manifest_guard {
-                let mut current_offset = manifest.next_vector_offset;
-                for _ in 0..items.len() {
-                    offsets.push(current_offset);
-                    current_offset += record_size as u64;
-                }
-                manifest.next_vector_offset = current_offset;
-                manifest.vector_file_size = current_offset;
-            } else {
-                return Err(VectraError::StorageError {
-                    message: "Manifest not initialized".to_string(),
+        // Appending counted the items again
+        let replaced = appended.len();
+        if replaced > 0 {
+            let _ = self
+                .total_items
+                .fetch_update(Ordering::AcqRel, Ordering::Acquire, |n| {
+                    Some(n.saturating_sub(replaced))
                 });
-            }
+            self.mark_manifest_dirty().await?;
         }
 
-        // Now write vectors and prepare data without repeated lock acquisition
-        let mut prepared_data = Vec::with_capacity(items.len());
-        for (idx, item) in items.iter().enumerate() {
-            let vector_offset = offsets[idx];
-            self.write_vector_to_file(&item.vector, vector_offset)
-                .await?;
-
-            // Prepare metadata (without vector data) using JSON
-            let mut metadata_item = item.clone();
-            metadata_item.vector = Vec::new();
-            let metadata_bytes = serde_json::to_vec(&metadata_item)?;
-
-            // Prepare vector record
-            let vector_record = VectorRecord {
-                id: item.id,
-                offset: vector_offset,
-                dimensions: first_dimensions,
-                deleted: false,
-            };
-            let vector_record_bytes = bincode::serialize(&vector_record)?;
+        Ok(())
+    }
 
-            prepared_data.push((
-                item.id.as_bytes().to_vec(),
-                metadata_bytes,
-                vector_record_bytes,
-            ));
+    async fn update_metadata(&mut self, items: &[VectorItem]) -> Result<()> {
+        if items.is_empty() {
+            return Ok(());
         }
-
-        // Bulk write to database
-        let total_items_added = prepared_data.len();
-        {
-            let db_guard = self.db.read().await;
-            if let Some(ref db) = *db_guard {
-                let metadata_cf = db.cf_handle(METADATA_CF).unwrap();
-                let vector_index_cf = db.cf_handle(VECTOR_INDEX_CF).unwrap();
-
-                // Use RocksDB write batch for better performance
-                let mut batch = rocksdb::WriteBatch::default();
-
-                for (id_bytes, metadata_bytes, vector_record_bytes) in prepared_data {
-                    batch.put_cf(&metadata_cf, &id_bytes, metadata_bytes);
-                    batch.put_cf(&vector_index_cf, &id_bytes, vector_record_bytes);
+        self.ensure_writable().await?;
+
+        // The vector records and vectors.dat are left as they are
+        let encoding = self.metadata_encoding().await;
+        let key_fields = self.key_fields().await;
+        if let Some(db) = self.db.get() {
+            let metadata_cf = db.cf_handle(METADATA_CF).unwrap();
+            let mut batch = rocksdb::WriteBatch::default();
+            for item in items {
+                let id_bytes = item.id.as_bytes();
+                if db.get_cf(&metadata_cf, id_bytes)?.is_none() {
+                    return Err(VectraError::ItemNotFound);
                 }
-
-                // Execute batch write
-                db.write(batch)?;
-            }
-        }
-
-        // Update manifest
-        {
-            let mut manifest_guard = self.manifest.write().await;
-            if let Some(ref mut manifest) = *manifest_guard {
-                manifest.total_items += total_items_added;
+                delete_keys(
+                    db,
+                    &mut batch,
+                    stale_keys(db, &metadata_cf, &key_fields, item)?,
+                );
+                put_keys(db, &mut batch, key_fields.keys(item));
+                let mut metadata_item = item.clone();
+                metadata_item.vector = Vec::new();
+                batch.put_cf(
+                    &metadata_cf,
+                    id_bytes,
+                    encode_metadata(&metadata_item, encoding)?,
+                );
             }
+            db.write(batch)?;
         }
 
-        // Mark manifest dirty for batched saving
-        self.mark_manifest_dirty().await?;
-
-        Ok(())
-    }
-
-    async fn update_item(&mut self, item: &VectorItem) -> Result<()> {
-        // For now, implement as delete + insert
-        self.delete_item(&item.id).await?;
-        self.insert_item(item).await?;
         Ok(())
     }
 
     async fn delete_item(&mut self, id: &Uuid) -> Result<()> {
-        // Scope cf handles before any .await (BoundColumnFamily is not Send)
-        {
-            let db_guard = self.db.read().await;
-            if let Some(ref db) = *db_guard {
+        self.ensure_writable().await?;
+        let key_fields = self.key_fields().await;
+
+        // Scope cf handles before any .await (BoundColumnFamily is not Send).
+        // `removed` says whether a live item went, `live_offset` where its
+        // vector was when the record was readable.
+        let (removed, live_offset) = {
+            if let Some(db) = self.db.get() {
                 let metadata_cf = db.cf_handle(METADATA_CF).unwrap();
                 let vector_index_cf = db.cf_handle(VECTOR_INDEX_CF).unwrap();
                 let id_bytes = id.as_bytes();
+                let mut batch = rocksdb::WriteBatch::default();
 
-                if let Some(vector_record_bytes) = db.get_cf(&vector_index_cf, id_bytes)? {
-                    let mut vector_record: VectorRecord =
-                        bincode::deserialize(&vector_record_bytes)?;
-                    vector_record.deleted = true;
-                    let updated_bytes = bincode::serialize(&vector_record)?;
-                    db.put_cf(&vector_index_cf, id_bytes, updated_bytes)?;
+                // Unreadable metadata leaves its keys for secondary reads to
+                // skip, until the secondary indexes are next rebuilt
+                if !key_fields.is_empty() {
+                    if let Some(stored) = db.get_cf(&metadata_cf, id_bytes)? {
+                        if let Ok(item) = decode_metadata(&stored) {
+                            delete_keys(db, &mut batch, key_fields.keys(&item));
+                        }
+                    }
                 }
-                db.delete_cf(&metadata_cf, id_bytes)?;
-            }
-        }
 
-        // Update manifest (safe to await now — cf handles are dropped)
-        {
-            let should_mark_dirty = {
-                let mut manifest_guard = self.manifest.write().await;
-                if let Some(ref mut manifest) = *manifest_guard {
-                    if manifest.total_items > 0 {
-                        manifest.total_items -= 1;
-                        true
-                    } else {
-                        false
+                let mut removed = false;
+                let mut live_offset = None;
+                if let Some(vector_record_bytes) = db.get_cf(&vector_index_cf, id_bytes)? {
+                    match bincode::deserialize::<VectorRecord>(&vector_record_bytes) {
+                        Ok(mut vector_record) => {
+                            if !vector_record.deleted {
+                                removed = true;
+                                live_offset = Some(vector_record.offset);
+                            }
+                            vector_record.deleted = true;
+                            let updated_bytes = bincode::serialize(&vector_record)?;
+                            batch.put_cf(&vector_index_cf, id_bytes, updated_bytes);
+                        }
+                        // A damaged record can't become a tombstone; its
+                        // vector is reclaimed whenever compaction next runs
+                        Err(_) if self.is_quarantined(id) => {
+                            removed = true;
+                            batch.delete_cf(&vector_index_cf, id_bytes);
+                        }
+                        Err(e) => return Err(e.into()),
                     }
-                } else {
-                    false
                 }
-            };
+                batch.delete_cf(&metadata_cf, id_bytes);
+                batch.delete_cf(&db.cf_handle(PAYLOAD_CF).unwrap(), id_bytes);
+                db.write(batch)?;
+                (removed, live_offset)
+            } else {
+                (false, None)
+            }
+        };
+        self.failpoints.hit(DELETE_AFTER_DB_WRITE)?;
+        self.release_quarantine([id]);
 
-            if should_mark_dirty {
+        // A shared vector stays in its slot while other items point at it
+        if let Some(offset) = live_offset {
+            if let Some(ref mut slots) = *self.vector_slots.write().await {
+                slots.release(offset);
+            }
+        }
+
+        // Count the removal (safe to await now — cf handles are dropped).
+        // Deleting a missing or already deleted id must not change the count.
+        if removed {
+            let counted = self
+                .total_items
+                .fetch_update(Ordering::AcqRel, Ordering::Acquire, |n| n.checked_sub(1))
+                .is_ok();
+            if counted {
                 self.mark_manifest_dirty().await?;
             }
         }
@@ -857,62 +2415,15 @@ impl StorageBackend for OptimizedStorage {
     }
 
     async fn list_items(&self, options: Option<ListOptions>) -> Result<Vec<VectorItem>> {
-        // Ensure storage is initialized for read operations
-        if self.db.read().await.is_none() {
-            self.initialize_storage().await?;
-        }
-
-        // Collect all the metadata records first without holding DB references
-        let metadata_records = {
-            let db_guard = self.db.read().await;
-            if let Some(ref db) = *db_guard {
-                let metadata_cf = db.cf_handle(METADATA_CF).unwrap();
-                let vector_index_cf = db.cf_handle(VECTOR_INDEX_CF).unwrap();
-
-                let mut records = Vec::new();
-                let iter = db.iterator_cf(&metadata_cf, rocksdb::IteratorMode::Start);
-
-                for item in iter {
-                    let (key, value) = item?;
-
-                    // Check if item is not deleted
-                    if let Some(vector_record_bytes) = db.get_cf(&vector_index_cf, &key)? {
-                        let vector_record: VectorRecord =
-                            bincode::deserialize(&vector_record_bytes)?;
-
-                        if !vector_record.deleted {
-                            let metadata_item: VectorItem = serde_json::from_slice(&value)?;
-                            records.push((metadata_item, vector_record));
-
-                            // Apply limit if specified
-                            if let Some(ref opts) = options {
-                                if let Some(limit) = opts.limit {
-                                    if records.len() >= limit {
-                                        break;
-                                    }
-                                }
-                            }
-                        }
-                    }
-                }
-
-                records
-            } else {
-                Vec::new()
-            }
-        };
-
-        // Now load vectors without holding DB guard
-        let mut items = Vec::new();
-        for (mut metadata_item, vector_record) in metadata_records {
-            // Load vector from file
-            metadata_item.vector = self
-                .read_vector_from_file(vector_record.offset, vector_record.dimensions)
-                .await?;
-            items.push(metadata_item);
-        }
+        self.scan_items(options, None).await
+    }
 
-        Ok(items)
+    async fn list_matching(
+        &self,
+        options: Option<ListOptions>,
+        matches: &MetadataPredicate,
+    ) -> Result<Vec<VectorItem>> {
+        self.scan_items(options, Some(matches)).await
     }
 
     async fn query_items(&self, query: &Query) -> Result<Vec<QueryResult>> {
@@ -928,12 +2439,14 @@ impl StorageBackend for OptimizedStorage {
                     results.push(QueryResult {
                         item,
                         score: similarity,
+                        highlights: Vec::new(),
+                        payload: None,
                     });
                 }
             }
 
             // Sort by similarity (descending)
-            results.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap());
+            results.sort_by(QueryResult::rank_cmp);
 
             // Apply limit
             results.truncate(query.top_k);
@@ -951,44 +2464,809 @@ impl StorageBackend for OptimizedStorage {
     }
 
     async fn commit_transaction(&mut self) -> Result<()> {
-        // Flush manifest to disk
-        self.flush_manifest_if_dirty().await?;
+        self.flush().await
+    }
+
+    async fn rollback_transaction(&mut self) -> Result<()> {
+        // In a full implementation, we'd rollback pending changes
+        // For now, just return success
+        Ok(())
+    }
+
+    async fn delete_index(&mut self) -> Result<()> {
+        // Close database and memory map first
+        self.db.take();
+        *self.vector_mmap.write().await = None;
+        *self.cold_mmap.write().await = None;
+        *self.manifest.write().await = None;
+        *self.vector_slots.write().await = None;
+        self.spare_slots.lock().unwrap().clear();
+        self.quarantine.write().unwrap().clear();
+        self.access_counts.lock().unwrap().clear();
+        self.next_offset.store(0, Ordering::Release);
+        self.total_items.store(0, Ordering::Release);
+        self.vector_generation.store(0, Ordering::Release);
+        self.cold_generation.store(0, Ordering::Release);
+        *self.compaction_copy.lock().unwrap() = None;
 
-        // Flush any pending writes
-        let db_guard = self.db.read().await;
-        if let Some(ref db) = *db_guard {
-            db.flush()?;
+        // Remove all files in the index directory
+        if self.path.exists() {
+            fs::remove_dir_all(&self.path).await?;
+        }
+        Ok(())
+    }
+
+    async fn compaction_stats(&self) -> Result<CompactionStats> {
+        let (live, deleted) = self.scan_vector_records().await?;
+        // A slot shared by several items counts once. Cold vectors are
+        // outside vectors.dat and are repacked by retiering instead.
+        let live_bytes: u64 = live
+            .iter()
+            .filter(|r| r.offset & COLD_TIER == 0)
+            .map(|r| (r.offset, (VECTOR_HEADER_SIZE + r.dimensions * 4) as u64))
+            .collect::<HashMap<u64, u64>>()
+            .values()
+            .sum();
+
+        // Updates rewrite the record in place, so superseded vectors have no
+        // tombstone; everything below the write offset that isn't live is dead
+        let used_bytes = match *self.manifest.read().await {
+            Some(_) => self.next_offset.load(Ordering::Acquire),
+            None => live_bytes,
+        };
+
+        Ok(CompactionStats {
+            live_items: live.len(),
+            deleted_items: deleted.len(),
+            live_bytes,
+            reclaimable_bytes: used_bytes.saturating_sub(live_bytes),
+        })
+    }
+
+    async fn prepare_compaction(&self, max_bytes: u64) -> Result<u64> {
+        if self.compaction_copy.lock().unwrap().is_none() {
+            let stats = self.compaction_stats().await?;
+            if stats.reclaimable_bytes == 0 && stats.deleted_items == 0 {
+                return Ok(0);
+            }
+            self.access.read().unwrap().check_writable(&self.path)?;
         }
+        self.copy_for_compaction(max_bytes).await
+    }
 
-        if let Some(ref mut mmap_guard) = *self.vector_mmap.write().await {
-            mmap_guard.flush()?;
+    async fn compact(&mut self, max_bytes_per_sec: Option<u64>) -> Result<CompactionStats> {
+        let quarantined = self.quarantine.read().unwrap().len();
+        if quarantined > 0 {
+            return Err(VectraError::Storage {
+                message: format!(
+                    "{} quarantined items must be repaired or deleted before compacting",
+                    quarantined
+                ),
+            });
+        }
+        let before = self.compaction_stats().await?;
+        if before.reclaimable_bytes > 0 || before.deleted_items > 0 {
+            self.ensure_writable().await?;
+        }
+        if before.reclaimable_bytes == 0 && before.deleted_items == 0 {
+            return Ok(before);
+        }
+
+        // Copy live vectors into the next generation's file, packed from
+        // offset 0, keeping whatever prepare_compaction copied already
+        let started = std::time::Instant::now();
+        let mut written = 0u64;
+        loop {
+            let copied = self
+                .copy_for_compaction(COMPACTION_CHUNK_SIZE as u64)
+                .await?;
+            if copied == 0 {
+                break;
+            }
+            written += copied;
+            if let Some(rate) = max_bytes_per_sec.filter(|r| *r > 0) {
+                let target = std::time::Duration::from_secs_f64(written as f64 / rate as f64);
+                if let Some(wait) = target.checked_sub(started.elapsed()) {
+                    tokio::time::sleep(wait).await;
+                }
+            }
+        }
+        let mut copy = self.compaction_copy.lock().unwrap().take().ok_or_else(|| {
+            VectraError::StorageError {
+                message: "Compaction copy was discarded".to_string(),
+            }
+        })?;
+
+        // Records written since the copy began point at slots it hasn't seen
+        let (mut live, deleted) = self.scan_vector_records().await?;
+        live.sort_by_key(|r| r.offset);
+        {
+            let mmap_guard = self.vector_mmap.read().await;
+            let mmap = mmap_guard
+                .as_ref()
+                .ok_or_else(|| VectraError::StorageError {
+                    message: "Vector file not initialized".to_string(),
+                })?;
+            let slots = live
+                .iter()
+                .filter(|r| r.offset & COLD_TIER == 0)
+                .map(|r| (r.offset, VECTOR_HEADER_SIZE + r.dimensions * 4));
+            copy.append(mmap, slots, u64::MAX)?;
+        }
+        copy.file.set_len(copy.written + (10 * 1024 * 1024))?;
+        copy.file.sync_all()?;
+        let generation = copy.generation;
+        let compact_path = self.path.join(generation_file_name("dat", generation));
+        let old_path = self.vector_path();
+
+        self.failpoints.hit(COMPACT_BEFORE_SWAP)?;
+
+        // Point records at their new offsets, drop tombstones and switch
+        // to the new file in one synced batch. Until it lands the old file
+        // is the one in use, and after it the new one; the next open
+        // removes whichever is left over.
+        if let Some(db) = self.db.get() {
+            let vector_index_cf = db.cf_handle(VECTOR_INDEX_CF).unwrap();
+            let mut batch = rocksdb::WriteBatch::default();
+
+            for record in &mut live {
+                if let Some(offset) = copy.copied.get(&record.offset) {
+                    record.offset = *offset;
+                }
+                batch.put_cf(
+                    &vector_index_cf,
+                    record.id.as_bytes(),
+                    bincode::serialize(record)?,
+                );
+            }
+            for record in &deleted {
+                batch.delete_cf(&vector_index_cf, record.id.as_bytes());
+            }
+            batch.put(VECTOR_GENERATION_KEY, generation.to_le_bytes());
+
+            let mut options = rocksdb::WriteOptions::default();
+            options.set_sync(true);
+            db.write_opt(batch, &options)?;
+        }
+        self.vector_generation.store(generation, Ordering::Release);
+
+        // Slots move, so the slot map is rebuilt on the next write
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(&compact_path)?;
+        *self.vector_mmap.write().await = Some(map_vector_file(&file)?);
+        *self.vector_slots.write().await = None;
+        self.spare_slots.lock().unwrap().clear();
+        self.failpoints.hit(COMPACT_AFTER_SWAP)?;
+        // Unmapped first, as Windows won't remove a mapped file
+        std::fs::remove_file(&old_path)?;
+
+        self.next_offset.store(copy.written, Ordering::Release);
+        let manifest = {
+            let mut manifest_guard = self.manifest.write().await;
+            manifest_guard.as_mut().map(|manifest| {
+                self.reconcile_counters(manifest);
+                manifest.clone()
+            })
+        };
+        if let Some(manifest) = manifest {
+            self.save_manifest_to_disk(&manifest).await?;
+        }
+
+        Ok(before)
+    }
+
+    /// Records are rewritten in batches. Reads accept either encoding, so
+    /// an interrupted run leaves a readable index that the next run finishes.
+    async fn set_metadata_encoding(&mut self, encoding: MetadataEncoding) -> Result<usize> {
+        self.ensure_writable().await?;
+        let cbor = encoding == MetadataEncoding::Cbor;
+        let update_manifest = |manifest: &mut Manifest| {
+            manifest.metadata_encoding = encoding;
+            set_feature(&mut manifest.features.required, METADATA_CBOR_FEATURE, cbor);
+            manifest.clone()
+        };
+
+        // Builds without CBOR support must stop opening the index before
+        // the first CBOR record lands, and may open it again only once the
+        // last one is gone
+        if cbor {
+            let manifest = self.manifest.write().await.as_mut().map(update_manifest);
+            if let Some(manifest) = manifest {
+                self.save_manifest_to_disk(&manifest).await?;
+            }
+        }
+
+        let mut rewritten = 0;
+        {
+            if let Some(db) = self.db.get() {
+                let metadata_cf = db.cf_handle(METADATA_CF).unwrap();
+                let mut batch = rocksdb::WriteBatch::default();
+                for entry in db.iterator_cf(&metadata_cf, rocksdb::IteratorMode::Start) {
+                    let (key, value) = entry?;
+                    let json = value.first() == Some(&b'{');
+                    if json != cbor {
+                        continue;
+                    }
+                    let item = decode_metadata(&value)?;
+                    batch.put_cf(&metadata_cf, &key, encode_metadata(&item, encoding)?);
+                    rewritten += 1;
+                    if batch.len() >= MULTI_GET_BATCH {
+                        db.write(std::mem::take(&mut batch))?;
+                    }
+                }
+                db.write(batch)?;
+            }
+        }
+
+        if !cbor {
+            let manifest = self.manifest.write().await.as_mut().map(update_manifest);
+            if let Some(manifest) = manifest {
+                self.save_manifest_to_disk(&manifest).await?;
+            }
+        }
+        Ok(rewritten)
+    }
+
+    fn record_access(&self, ids: &[Uuid]) {
+        let mut counts = self.access_counts.lock().unwrap();
+        for id in ids {
+            let count = counts.entry(*id).or_default();
+            *count = count.saturating_add(1);
+        }
+    }
+
+    /// Each pass rewrites vectors.cold with only the live cold vectors, so
+    /// it also reclaims cold vectors that were deleted or updated since.
+    /// Demoted vectors leave their slots in vectors.dat for compaction.
+    async fn retier(&mut self, policy: &TieringPolicy) -> Result<TieringStats> {
+        self.ensure_writable().await?;
+        if self.dedup_vectors().await {
+            return Err(VectraError::Storage {
+                message: "Vectors of a deduplicating index can't be tiered".to_string(),
+            });
+        }
+        let quarantined = self.quarantine.read().unwrap().len();
+        if quarantined > 0 {
+            return Err(VectraError::Storage {
+                message: format!(
+                    "{} quarantined items must be repaired or deleted before retiering",
+                    quarantined
+                ),
+            });
+        }
+
+        let (live, _) = self.scan_vector_records().await?;
+        let counts = self.access_counts.lock().unwrap().clone();
+        let reads = |id: &Uuid| counts.get(id).copied().unwrap_or(0);
+
+        // Promoted vectors are appended to vectors.dat, and the cold tier
+        // is written afresh as its next generation
+        let old_path = self.cold_path();
+        let generation = self.cold_generation.load(Ordering::Acquire) + 1;
+        let cold_path = self.path.join(generation_file_name("cold", generation));
+        let mut writer = std::io::BufWriter::new(std::fs::File::create(&cold_path)?);
+        let mut written = 0u64;
+        let mut stats = TieringStats::default();
+        let mut moved = Vec::new();
+        for mut record in live {
+            let cold = record.offset & COLD_TIER != 0;
+            if cold && reads(&record.id) >= policy.promote_min_reads {
+                let vector = self
+                    .read_vector_from_file(record.offset, record.dimensions)
+                    .await?;
+                let offset = self.get_next_vector_offset(record.dimensions).await?;
+                let end = offset + (VECTOR_HEADER_SIZE + record.dimensions * 4) as u64;
+                self.ensure_vector_file_capacity(end).await?;
+                self.write_vector_to_file(&vector, offset).await?;
+                record.offset = offset;
+                stats.promoted += 1;
+                stats.hot_items += 1;
+            } else if cold || reads(&record.id) <= policy.demote_max_reads {
+                let block = if cold {
+                    self.read_cold_block(record.offset).await?
+                } else {
+                    stats.demoted += 1;
+                    self.compress_hot_record(record.offset, record.dimensions)
+                        .await?
+                };
+                writer.write_all(&(block.len() as u32).to_le_bytes())?;
+                writer.write_all(&block)?;
+                record.offset = written | COLD_TIER;
+                written += (COLD_BLOCK_HEADER_SIZE + block.len()) as u64;
+                stats.cold_items += 1;
+            } else {
+                stats.hot_items += 1;
+                continue;
+            }
+            moved.push(record);
+        }
+        writer
+            .into_inner()
+            .map_err(|e| e.into_error())?
+            .sync_all()?;
+        if let Some(ref mmap) = *self.vector_mmap.read().await {
+            mmap.flush()?;
+        }
+        stats.cold_bytes = written;
+
+        // Builds that don't know the tier must stop opening the index
+        // before any record points into it
+        if stats.cold_items > 0 {
+            let manifest = self.manifest.write().await.as_mut().map(|manifest| {
+                set_feature(&mut manifest.features.required, COLD_TIER_FEATURE, true);
+                manifest.clone()
+            });
+            if let Some(manifest) = manifest {
+                self.save_manifest_to_disk(&manifest).await?;
+            }
+        }
+
+        self.failpoints.hit(RETIER_BEFORE_SWAP)?;
+
+        // As with compaction, the moved records and the switch to the new
+        // cold file land in one synced batch, and the next open removes
+        // whichever file a crash leaves over
+        if let Some(db) = self.db.get() {
+            let vector_index_cf = db.cf_handle(VECTOR_INDEX_CF).unwrap();
+            let mut batch = rocksdb::WriteBatch::default();
+            for record in &moved {
+                batch.put_cf(
+                    &vector_index_cf,
+                    record.id.as_bytes(),
+                    bincode::serialize(record)?,
+                );
+            }
+            batch.put(COLD_GENERATION_KEY, generation.to_le_bytes());
+
+            let mut options = rocksdb::WriteOptions::default();
+            options.set_sync(true);
+            db.write_opt(batch, &options)?;
+        }
+        self.cold_generation.store(generation, Ordering::Release);
+        self.map_cold_file().await?;
+        self.failpoints.hit(RETIER_AFTER_SWAP)?;
+        // Unmapped first, as Windows won't remove a mapped file. There is
+        // none before the first pass.
+        if old_path.exists() {
+            std::fs::remove_file(&old_path)?;
+        }
+
+        let manifest = self.manifest.write().await.as_mut().map(|manifest| {
+            set_feature(
+                &mut manifest.features.required,
+                COLD_TIER_FEATURE,
+                stats.cold_items > 0,
+            );
+            manifest.clone()
+        });
+        if let Some(manifest) = manifest {
+            self.save_manifest_to_disk(&manifest).await?;
+        }
+        self.access_counts.lock().unwrap().clear();
+
+        Ok(stats)
+    }
+
+    fn set_flush_interval(&self, operations: u32) {
+        self.manifest_save_interval
+            .store(operations.max(1), Ordering::Relaxed);
+    }
+
+    fn lock_waits(&self) -> LockWaitStats {
+        self.lock_waits.snapshot()
+    }
+
+    fn reset_lock_waits(&self) {
+        self.lock_waits.reset();
+    }
+
+    async fn compaction_policy(&self) -> Result<Option<CompactionPolicy>> {
+        Ok(self.current_manifest().await?.and_then(|m| m.compaction))
+    }
+
+    async fn is_read_only(&self) -> Result<bool> {
+        self.current_manifest().await?;
+        Ok(*self.access.read().unwrap() != Access::ReadWrite)
+    }
+
+    async fn embedding_model(&self) -> Result<Option<EmbeddingModel>> {
+        Ok(self
+            .current_manifest()
+            .await?
+            .and_then(|m| m.embedding_model))
+    }
+
+    async fn limits(&self) -> Result<Option<IndexLimits>> {
+        Ok(self.current_manifest().await?.and_then(|m| m.limits))
+    }
+
+    async fn vector_transform(&self) -> Result<Option<VectorTransform>> {
+        Ok(self
+            .current_manifest()
+            .await?
+            .and_then(|m| m.vector_transform))
+    }
+
+    async fn vector_validation(&self) -> Result<Option<VectorValidation>> {
+        Ok(self
+            .current_manifest()
+            .await?
+            .and_then(|m| m.vector_validation))
+    }
+
+    async fn namespace_field(&self) -> Result<Option<String>> {
+        Ok(self
+            .current_manifest()
+            .await?
+            .and_then(|m| m.namespace_field))
+    }
+
+    /// Reads only `namespace`'s keys, unless the index predates them
+    async fn list_namespace(&self, namespace: &str) -> Result<Vec<VectorItem>> {
+        if self.db.get().is_none() {
+            self.initialize_storage().await?;
+        }
+        let Some(field) = self.key_fields().await.namespace else {
+            let Some(field) = self.namespace_field().await? else {
+                return Ok(Vec::new());
+            };
+            let mut items = self.list_items(None).await?;
+            items.retain(|item| namespace_of(&field, item).as_deref() == Some(namespace));
+            return Ok(items);
+        };
+
+        let ids = {
+            let mut ids = Vec::new();
+            if let Some(db) = self.db.get() {
+                let namespace_cf = db.cf_handle(NAMESPACE_CF).unwrap();
+                let prefix = hash_prefix(namespace);
+                let mut read_opts = rocksdb::ReadOptions::default();
+                read_opts.set_prefix_same_as_start(true);
+                read_opts.set_iterate_lower_bound(prefix.to_vec());
+                if let Some(end) = prefix_successor(&prefix) {
+                    read_opts.set_iterate_upper_bound(end);
+                }
+                let mode = rocksdb::IteratorMode::From(&prefix, rocksdb::Direction::Forward);
+                for entry in db.iterator_cf_opt(&namespace_cf, read_opts, mode) {
+                    let (key, _) = entry?;
+                    if let Ok(id) = Uuid::from_slice(&key[NAMESPACE_PREFIX_LEN..]) {
+                        ids.push(id);
+                    }
+                }
+            }
+            ids
+        };
+
+        // Namespaces whose hashes share a prefix share a key range
+        let mut items: Vec<VectorItem> =
+            self.get_items(&ids).await?.into_iter().flatten().collect();
+        items.retain(|item| namespace_of(&field, item).as_deref() == Some(namespace));
+        Ok(items)
+    }
+
+    /// Reads only the keys between the bounds, if `field` is a range field
+    async fn list_range(
+        &self,
+        field: &str,
+        range: &NumericRange,
+    ) -> Result<Option<Vec<VectorItem>>> {
+        if self.db.get().is_none() {
+            self.initialize_storage().await?;
+        }
+        if !self.key_fields().await.ranges.iter().any(|f| f == field) {
+            return Ok(None);
+        }
+
+        let ids = {
+            let mut ids = Vec::new();
+            if let Some(db) = self.db.get() {
+                let range_cf = db.cf_handle(RANGE_CF).unwrap();
+                let prefix = hash_prefix(field);
+                let field_end = prefix_successor(&prefix);
+                // Keys holding a value continue with the item id, so the
+                // keys past a value start at its prefix's successor
+                let past = |value| prefix_successor(&range_value_prefix(field, value));
+                let start = match range.min {
+                    Bound::Included(min) => Some(range_value_prefix(field, min)),
+                    Bound::Excluded(min) => past(min),
+                    Bound::Unbounded => Some(prefix.to_vec()),
+                };
+                let end = match range.max {
+                    Bound::Included(max) => past(max),
+                    Bound::Excluded(max) => Some(range_value_prefix(field, max)),
+                    Bound::Unbounded => field_end.clone(),
+                };
+                if let Some(start) =
+                    start.filter(|start| end.as_ref().is_none_or(|end| start < end))
+                {
+                    let mut read_opts = rocksdb::ReadOptions::default();
+                    read_opts.set_prefix_same_as_start(true);
+                    read_opts.set_iterate_lower_bound(start.clone());
+                    if let Some(end) = end.or(field_end) {
+                        read_opts.set_iterate_upper_bound(end);
+                    }
+                    let mode = rocksdb::IteratorMode::From(&start, rocksdb::Direction::Forward);
+                    for entry in db.iterator_cf_opt(&range_cf, read_opts, mode) {
+                        let (key, _) = entry?;
+                        if let Ok(id) = Uuid::from_slice(&key[RANGE_PREFIX_LEN + 8..]) {
+                            ids.push(id);
+                        }
+                    }
+                }
+            }
+            ids
+        };
+
+        // Fields whose hashes share a prefix share a key range, so callers
+        // still test the values
+        Ok(Some(
+            self.get_items(&ids).await?.into_iter().flatten().collect(),
+        ))
+    }
+
+    async fn range_fields(&self) -> Result<Vec<String>> {
+        Ok(self
+            .current_manifest()
+            .await?
+            .map(|m| m.range_fields)
+            .unwrap_or_default())
+    }
+
+    async fn composite_indexes(&self) -> Result<Vec<CompositeIndex>> {
+        Ok(self
+            .current_manifest()
+            .await?
+            .map(|m| m.composite_indexes)
+            .unwrap_or_default())
+    }
+
+    async fn add_composite_index(&mut self, index: &CompositeIndex) -> Result<()> {
+        index.validate()?;
+        self.ensure_writable().await?;
+        let Some(mut manifest) = self.current_manifest().await? else {
+            return Err(VectraError::IndexNotFound {
+                path: self.path.to_string_lossy().to_string(),
+            });
+        };
+        if manifest.composite_indexes.contains(index) {
+            return Ok(());
+        }
+        // Items are filed before the manifest names the index, so it is
+        // never read while incomplete
+        let fields = KeyFields {
+            composites: vec![index.clone()],
+            ..Default::default()
+        };
+        if let Some(db) = self.db.get() {
+            file_secondary_keys(db, &fields)?;
+        }
+        manifest.composite_indexes.push(index.clone());
+        set_feature(&mut manifest.features.write, COMPOSITE_KEYS_FEATURE, true);
+        self.save_manifest(&manifest).await
+    }
+
+    /// Reads only the keys under the pinned values' prefix
+    async fn list_composite(
+        &self,
+        pinned: &[(String, serde_json::Value)],
+    ) -> Result<Option<Vec<VectorItem>>> {
+        if self.db.get().is_none() {
+            self.initialize_storage().await?;
+        }
+        let value_of = |field: &String| {
+            pinned
+                .iter()
+                .find(|(pinned, _)| pinned == field)
+                .map(|(_, value)| value)
+        };
+        // The index covering the most leading fields wins
+        let best = self
+            .key_fields()
+            .await
+            .composites
+            .into_iter()
+            .filter_map(|index| {
+                let values: Vec<&serde_json::Value> =
+                    index.fields.iter().map_while(value_of).collect();
+                let prefix = composite_prefix(&index, values.iter().copied())?;
+                (!values.is_empty()).then_some((values.len(), prefix))
+            })
+            .max_by_key(|(covered, _)| *covered);
+        let Some((_, prefix)) = best else {
+            return Ok(None);
+        };
+
+        let ids = {
+            let mut ids = Vec::new();
+            if let Some(db) = self.db.get() {
+                let composite_cf = db.cf_handle(COMPOSITE_CF).unwrap();
+                let mut read_opts = rocksdb::ReadOptions::default();
+                read_opts.set_prefix_same_as_start(true);
+                read_opts.set_iterate_lower_bound(prefix.clone());
+                if let Some(end) = prefix_successor(&prefix) {
+                    read_opts.set_iterate_upper_bound(end);
+                }
+                let mode = rocksdb::IteratorMode::From(&prefix, rocksdb::Direction::Forward);
+                for entry in db.iterator_cf_opt(&composite_cf, read_opts, mode) {
+                    let (key, _) = entry?;
+                    if let Ok(id) = Uuid::from_slice(&key[key.len() - 16..]) {
+                        ids.push(id);
+                    }
+                }
+            }
+            ids
+        };
+        Ok(Some(
+            self.get_items(&ids).await?.into_iter().flatten().collect(),
+        ))
+    }
+
+    async fn verify_secondary_indexes(&self) -> Result<IndexDivergence> {
+        if self.db.get().is_none() {
+            self.initialize_storage().await?;
+        }
+        let key_fields = self.key_fields().await;
+        if key_fields.is_empty() {
+            return Ok(IndexDivergence::default());
+        }
+        let Some(db) = self.db.get() else {
+            return Ok(IndexDivergence::default());
+        };
+        let metadata_cf = db.cf_handle(METADATA_CF).unwrap();
+
+        let mut expected = HashSet::new();
+        for entry in db.iterator_cf(&metadata_cf, rocksdb::IteratorMode::Start) {
+            let (_, value) = entry?;
+            // Damaged records are reported by quarantine_damaged instead
+            if let Ok(item) = decode_metadata(&value) {
+                expected.extend(key_fields.keys(&item));
+            }
+        }
+        let mut divergence = IndexDivergence::default();
+        for cf in SECONDARY_CFS {
+            let handle = db.cf_handle(cf).unwrap();
+            for entry in db.iterator_cf(&handle, rocksdb::IteratorMode::Start) {
+                let (key, _) = entry?;
+                if !expected.remove(&(cf, key.to_vec())) {
+                    divergence.stale += 1;
+                }
+            }
+        }
+        divergence.missing = expected.len();
+        Ok(divergence)
+    }
+
+    async fn rebuild_secondary_indexes(&mut self) -> Result<()> {
+        self.ensure_writable().await?;
+        let Some(mut manifest) = self.current_manifest().await? else {
+            return Ok(());
+        };
+        let upgrade = manifest.namespace_field.is_some()
+            && !has_feature(&manifest.features.write, NAMESPACE_KEYS_FEATURE);
+        if upgrade {
+            set_feature(&mut manifest.features.write, NAMESPACE_KEYS_FEATURE, true);
+        }
+        if let Some(db) = self.db.get() {
+            build_secondary_keys(db, &KeyFields::of(&manifest))?;
+        }
+        if upgrade {
+            self.save_manifest(&manifest).await?;
+        }
+        Ok(())
+    }
+
+    async fn set_payloads(&mut self, payloads: &[(Uuid, Option<Vec<u8>>)]) -> Result<()> {
+        self.ensure_writable().await?;
+        let Some(db) = self.db.get() else {
+            return Err(VectraError::StorageError {
+                message: "Database not initialized".to_string(),
+            });
+        };
+        let metadata_cf = db.cf_handle(METADATA_CF).unwrap();
+        let payload_cf = db.cf_handle(PAYLOAD_CF).unwrap();
+        let mut batch = rocksdb::WriteBatch::default();
+        for (id, payload) in payloads {
+            if db.get_pinned_cf(&metadata_cf, id.as_bytes())?.is_none() {
+                return Err(VectraError::Storage {
+                    message: format!("Item {} not found", id),
+                });
+            }
+            match payload {
+                Some(payload) => batch.put_cf(&payload_cf, id.as_bytes(), payload),
+                None => batch.delete_cf(&payload_cf, id.as_bytes()),
+            }
         }
-
+        db.write(batch)?;
         Ok(())
     }
 
-    async fn rollback_transaction(&mut self) -> Result<()> {
-        // In a full implementation, we'd rollback pending changes
-        // For now, just return success
-        Ok(())
+    async fn get_payloads(&self, ids: &[Uuid]) -> Result<Vec<Option<Vec<u8>>>> {
+        if self.db.get().is_none() {
+            self.initialize_storage().await?;
+        }
+        let Some(db) = self.db.get() else {
+            return Ok(vec![None; ids.len()]);
+        };
+        let payload_cf = db.cf_handle(PAYLOAD_CF).unwrap();
+        let mut payloads = Vec::with_capacity(ids.len());
+        for chunk in ids.chunks(MULTI_GET_BATCH) {
+            let keys = chunk.iter().map(|id| id.as_bytes());
+            for payload in db.batched_multi_get_cf(&payload_cf, keys, false) {
+                payloads.push(payload?.map(|payload| payload.to_vec()));
+            }
+        }
+        Ok(payloads)
     }
 
-    async fn delete_index(&mut self) -> Result<()> {
-        // Close database and memory map first
-        *self.db.write().await = None;
-        *self.vector_file.write().await = None;
-        *self.vector_mmap.write().await = None;
-        *self.manifest.write().await = None;
+    async fn quarantine_damaged(&self) -> Result<Vec<QuarantinedItem>> {
+        // Damage the open-time check would refuse is what this looks for
+        if self.db.get().is_none() {
+            self.open_storage(false).await?;
+        }
 
-        // Remove all files in the index directory
-        if self.path.exists() {
-            fs::remove_dir_all(&self.path).await?;
+        // Records that decode, whose vectors are checked once the DB guard is gone
+        let (mut damaged, readable) = {
+            let mut damaged = Vec::new();
+            let mut readable = Vec::new();
+            if let Some(db) = self.db.get() {
+                let metadata_cf = db.cf_handle(METADATA_CF).unwrap();
+                let vector_index_cf = db.cf_handle(VECTOR_INDEX_CF).unwrap();
+                for entry in db.iterator_cf(&metadata_cf, rocksdb::IteratorMode::Start) {
+                    let (key, value) = entry?;
+                    let Ok(id) = Uuid::from_slice(&key) else {
+                        continue;
+                    };
+                    let Some(record_bytes) = db.get_cf(&vector_index_cf, &key)? else {
+                        continue;
+                    };
+                    let record = match bincode::deserialize::<VectorRecord>(&record_bytes) {
+                        Ok(record) if record.deleted => continue,
+                        Ok(record) => record,
+                        Err(e) => {
+                            damaged.push((id, format!("unreadable vector record: {}", e)));
+                            continue;
+                        }
+                    };
+                    match decode_metadata(&value) {
+                        Ok(_) => readable.push((id, record)),
+                        Err(e) => damaged.push((id, format!("unreadable metadata: {}", e))),
+                    }
+                }
+            }
+            (damaged, readable)
+        };
+
+        for (id, record) in readable {
+            match self
+                .read_vector_from_file(record.offset, record.dimensions)
+                .await
+            {
+                Ok(vector) if VectorOps::is_valid_vector(&vector) => {}
+                Ok(_) => damaged.push((id, "vector holds NaN or infinite values".to_string())),
+                Err(e) => damaged.push((id, format!("unreadable vector: {}", e))),
+            }
         }
-        Ok(())
+
+        self.quarantine.write().unwrap().extend(damaged);
+        Ok(self.quarantined())
+    }
+
+    fn quarantined(&self) -> Vec<QuarantinedItem> {
+        self.quarantine
+            .read()
+            .unwrap()
+            .iter()
+            .map(|(id, reason)| QuarantinedItem {
+                id: *id,
+                reason: reason.clone(),
+            })
+            .collect()
     }
 
     async fn get_stats(&self) -> Result<IndexStats> {
-        if let Some(manifest) = self.load_manifest().await? {
+        if let Some(manifest) = self.current_manifest().await? {
             let size = if self.path.exists() {
                 // Calculate directory size
                 let mut total_size = 0u64;
@@ -1008,6 +3286,9 @@ impl StorageBackend for OptimizedStorage {
                 size,
                 dimensions: manifest.dimensions,
                 distance_metric: manifest.distance_metric,
+                namespaces: Default::default(),
+                ann_lag: Default::default(),
+                lock_waits: self.lock_waits.snapshot(),
             })
         } else {
             Ok(IndexStats {
@@ -1015,11 +3296,69 @@ impl StorageBackend for OptimizedStorage {
                 size: 0,
                 dimensions: None,
                 distance_metric: DistanceMetric::Cosine,
+                namespaces: Default::default(),
+                ann_lag: Default::default(),
+                lock_waits: self.lock_waits.snapshot(),
             })
         }
     }
 }
 
+impl Drop for OptimizedStorage {
+    /// Persist a manifest still waiting for its batched save, so a handle
+    /// dropped without a commit doesn't reopen with stale counts and
+    /// offsets, and record a clean shutdown once the files it vouches for
+    /// are flushed
+    fn drop(&mut self) {
+        // A simulated crash must leave disk exactly as the crash found it
+        if self.failpoints.crashed() {
+            return;
+        }
+        // Nothing has been written since the last clean shutdown
+        if self.clean_on_disk.load(Ordering::Acquire) {
+            return;
+        }
+        if self
+            .access
+            .read()
+            .unwrap()
+            .check_writable(&self.path)
+            .is_err()
+        {
+            return;
+        }
+        let flushed = self
+            .vector_mmap
+            .try_read()
+            .is_ok_and(|mmap| mmap.as_ref().is_none_or(|mmap| mmap.flush().is_ok()))
+            && self
+                .db
+                .get()
+                .is_none_or(|db| flush_column_families(db).is_ok());
+        if !flushed && !self.manifest_dirty.load(Ordering::Acquire) {
+            return;
+        }
+        if let Ok(guard) = self.manifest.try_read() {
+            if let Some(manifest) = guard.as_ref() {
+                let mut manifest = manifest.clone();
+                self.reconcile_counters(&mut manifest);
+                manifest.clean_shutdown = flushed;
+                set_feature(
+                    &mut manifest.features.write,
+                    CLEAN_SHUTDOWN_FEATURE,
+                    flushed,
+                );
+                if let Ok(content) = serde_json::to_string_pretty(&manifest) {
+                    let temp_path = self.manifest_path().with_extension("json.tmp");
+                    if std::fs::write(&temp_path, content).is_ok() {
+                        let _ = std::fs::rename(&temp_path, self.manifest_path());
+                    }
+                }
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1064,6 +3403,164 @@ mod tests {
         assert_eq!(retrieved_item.vector, item.vector);
     }
 
+    #[tokio::test]
+    async fn test_unlocked_store_and_manifest_dimensions() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut storage = OptimizedStorage::new(temp_dir.path()).unwrap();
+        storage
+            .create_index(&CreateIndexConfig::default())
+            .await
+            .unwrap();
+        storage.reset_lock_waits();
+
+        let items: Vec<VectorItem> = (0..10)
+            .map(|i| VectorItem {
+                id: Uuid::new_v4(),
+                vector: vec![i as f32, 1.0, 0.0],
+                ..Default::default()
+            })
+            .collect();
+        for item in &items {
+            storage.insert_item(item).await.unwrap();
+        }
+        storage.get_items(&[items[0].id]).await.unwrap();
+
+        // No lock guards the open store
+        assert!(!storage.lock_waits().contains_key("db"));
+        assert_eq!(
+            storage
+                .current_manifest()
+                .await
+                .unwrap()
+                .unwrap()
+                .dimensions,
+            Some(3)
+        );
+
+        // The dimensions come back from the manifest on reopen
+        drop(storage);
+        let mut storage = OptimizedStorage::new(temp_dir.path()).unwrap();
+        let wrong = VectorItem {
+            id: Uuid::new_v4(),
+            vector: vec![1.0, 0.0],
+            ..Default::default()
+        };
+        assert!(matches!(
+            storage.insert_item(&wrong).await,
+            Err(VectraError::VectorValidation { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_offsets_reconciled_into_manifest_on_save() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut storage = OptimizedStorage::new(temp_dir.path()).unwrap();
+        storage
+            .create_index(&CreateIndexConfig::default())
+            .await
+            .unwrap();
+        storage.reset_lock_waits();
+
+        let record_size = (VECTOR_HEADER_SIZE + 3 * 4) as u64;
+        let items: Vec<VectorItem> = (0..5)
+            .map(|i| VectorItem {
+                id: Uuid::new_v4(),
+                vector: vec![i as f32, 1.0, 0.0],
+                ..Default::default()
+            })
+            .collect();
+        for item in &items {
+            storage.insert_item(item).await.unwrap();
+        }
+
+        // Only clearing the clean-shutdown mark and fixing the dimensions
+        // take the manifest exclusively
+        let writes = storage.lock_waits()["manifest"]["write"].acquisitions;
+        assert_eq!(writes, 2);
+
+        // The live manifest is ahead of the saved one until a flush
+        let live = storage.current_manifest().await.unwrap().unwrap();
+        assert_eq!(live.next_vector_offset, 5 * record_size);
+        assert_eq!(live.total_items, items.len());
+        let saved = storage.load_manifest().await.unwrap().unwrap();
+        assert_eq!(saved.next_vector_offset, 0);
+        assert_eq!(saved.total_items, 0);
+        storage.flush_manifest_if_dirty().await.unwrap();
+        let saved = storage.load_manifest().await.unwrap().unwrap();
+        assert_eq!(saved.next_vector_offset, 5 * record_size);
+        assert_eq!(saved.vector_file_size, 5 * record_size);
+        assert_eq!(saved.total_items, items.len());
+
+        // Deletes count down the same way
+        storage.reset_lock_waits();
+        storage.delete_item(&items[0].id).await.unwrap();
+        let waits = storage.lock_waits();
+        assert!(waits
+            .get("manifest")
+            .is_none_or(|manifest| !manifest.contains_key("write")));
+        assert_eq!(storage.get_stats().await.unwrap().items, items.len() - 1);
+
+        // Reopened, slots are handed out after the ones in use
+        drop(storage);
+        let mut storage = OptimizedStorage::new(temp_dir.path()).unwrap();
+        let more = VectorItem {
+            id: Uuid::new_v4(),
+            vector: vec![9.0, 9.0, 9.0],
+            ..Default::default()
+        };
+        storage.insert_item(&more).await.unwrap();
+        for item in items[1..].iter().chain([&more]) {
+            let stored = storage.get_item(&item.id).await.unwrap().unwrap();
+            assert_eq!(stored.vector, item.vector);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_clean_shutdown_skips_reconciling() {
+        let temp_dir = TempDir::new().unwrap();
+        let manifest_path = temp_dir.path().join("manifest.json");
+        let saved_manifest = || -> serde_json::Value {
+            serde_json::from_slice(&std::fs::read(&manifest_path).unwrap()).unwrap()
+        };
+        let mut storage = OptimizedStorage::new(temp_dir.path()).unwrap();
+        storage
+            .create_index(&CreateIndexConfig::default())
+            .await
+            .unwrap();
+        assert_eq!(saved_manifest()["clean_shutdown"], true);
+        let items: Vec<VectorItem> = (0..3)
+            .map(|i| VectorItem {
+                id: Uuid::new_v4(),
+                vector: vec![i as f32, 1.0],
+                ..Default::default()
+            })
+            .collect();
+
+        // The first write clears the mark before it touches anything else
+        storage.insert_item(&items[0]).await.unwrap();
+        assert_eq!(saved_manifest()["clean_shutdown"], false);
+        storage.insert_items(&items[1..]).await.unwrap();
+        drop(storage);
+        let mut saved = saved_manifest();
+        assert_eq!(saved["clean_shutdown"], true);
+        assert_eq!(saved["total_items"], 3);
+
+        // After a clean shutdown the saved counts are taken as they stand
+        saved["total_items"] = serde_json::json!(7);
+        std::fs::write(&manifest_path, saved.to_string()).unwrap();
+        let storage = OptimizedStorage::new(temp_dir.path()).unwrap();
+        assert!(storage.get_item(&items[0].id).await.unwrap().is_some());
+        assert_eq!(storage.get_stats().await.unwrap().items, 7);
+        drop(storage);
+
+        // After any other they are rebuilt from the vector records
+        saved["clean_shutdown"] = serde_json::json!(false);
+        std::fs::write(&manifest_path, saved.to_string()).unwrap();
+        let storage = OptimizedStorage::new(temp_dir.path()).unwrap();
+        assert!(storage.get_item(&items[0].id).await.unwrap().is_some());
+        assert_eq!(storage.get_stats().await.unwrap().items, 3);
+    }
+
     #[tokio::test]
     async fn test_optimized_storage_query() {
         let temp_dir = TempDir::new().unwrap();
@@ -1094,6 +3591,7 @@ mod tests {
             text: None,
             top_k: 2,
             filter: None,
+            ..Default::default()
         };
 
         let results = storage.query_items(&query).await.unwrap();
@@ -1103,4 +3601,962 @@ mod tests {
         assert_eq!(results[0].item.id, item1.id);
         assert!(results[0].score > results[1].score);
     }
+
+    #[tokio::test]
+    async fn test_updates_reuse_spare_slots() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut storage = OptimizedStorage::new(temp_dir.path()).unwrap();
+        storage
+            .create_index(&CreateIndexConfig::default())
+            .await
+            .unwrap();
+        let items: Vec<VectorItem> = (0..3)
+            .map(|i| VectorItem {
+                id: Uuid::new_v4(),
+                vector: vec![i as f32, 1.0],
+                ..Default::default()
+            })
+            .collect();
+        storage.insert_items(&items).await.unwrap();
+        let size = std::fs::metadata(temp_dir.path().join("vectors.dat"))
+            .unwrap()
+            .len();
+
+        let mut updated = items.clone();
+        for round in 0..50 {
+            let item = &mut updated[round % 3];
+            item.vector = vec![round as f32, 2.0];
+            storage
+                .update_items(std::slice::from_ref(item))
+                .await
+                .unwrap();
+        }
+        // The first update took a new slot; every later one reused the
+        // slot the one before moved out of
+        let stats = storage.compaction_stats().await.unwrap();
+        assert_eq!(stats.live_items, 3);
+        assert_eq!(stats.reclaimable_bytes, (VECTOR_HEADER_SIZE + 2 * 4) as u64);
+        let grown = std::fs::metadata(temp_dir.path().join("vectors.dat"))
+            .unwrap()
+            .len();
+        assert_eq!(grown, size);
+        for item in &updated {
+            let fetched = storage.get_item(&item.id).await.unwrap().unwrap();
+            assert_eq!(fetched.vector, item.vector);
+        }
+
+        // A vector of another length can't reuse the slot and is refused
+        // before anything in the batch is written
+        let mut wrong = updated.clone();
+        wrong[0].vector = vec![7.0, 7.0];
+        wrong[1].vector = vec![1.0, 2.0, 3.0];
+        assert!(storage.update_items(&wrong).await.is_err());
+        let fetched = storage.get_item(&items[0].id).await.unwrap().unwrap();
+        assert_eq!(fetched.vector, updated[0].vector);
+        assert_eq!(storage.get_stats().await.unwrap().items, 3);
+
+        // Updates neither create missing items nor bring back deleted ones
+        let missing = VectorItem {
+            id: Uuid::new_v4(),
+            vector: vec![1.0, 1.0],
+            ..Default::default()
+        };
+        assert!(matches!(
+            storage.update_item(&missing).await,
+            Err(VectraError::ItemNotFound)
+        ));
+        storage.delete_item(&items[2].id).await.unwrap();
+        assert!(matches!(
+            storage.update_items(&updated[1..]).await,
+            Err(VectraError::ItemNotFound)
+        ));
+        assert!(storage.get_item(&items[2].id).await.unwrap().is_none());
+        assert_eq!(storage.get_stats().await.unwrap().items, 2);
+    }
+
+    #[tokio::test]
+    async fn test_optimized_storage_compaction() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut storage = OptimizedStorage::new(temp_dir.path()).unwrap();
+        storage
+            .create_index(&CreateIndexConfig::default())
+            .await
+            .unwrap();
+
+        let items: Vec<VectorItem> = (0..4)
+            .map(|i| VectorItem {
+                id: Uuid::new_v4(),
+                vector: vec![i as f32, 1.0, 0.0],
+                ..Default::default()
+            })
+            .collect();
+        storage.insert_items(&items).await.unwrap();
+
+        storage.delete_item(&items[0].id).await.unwrap();
+        let mut updated = items[1].clone();
+        updated.vector = vec![9.0, 9.0, 9.0];
+        storage.update_item(&updated).await.unwrap();
+
+        // The deleted item's slot and the one the update moved out of are dead space
+        let record_size = (VECTOR_HEADER_SIZE + 3 * 4) as u64;
+        let stats = storage.compaction_stats().await.unwrap();
+        assert_eq!(stats.live_items, 3);
+        assert_eq!(stats.deleted_items, 1);
+        assert_eq!(stats.reclaimable_bytes, 2 * record_size);
+
+        storage.compact(None).await.unwrap();
+
+        let stats = storage.compaction_stats().await.unwrap();
+        assert_eq!(stats.deleted_items, 0);
+        assert_eq!(stats.reclaimable_bytes, 0);
+        assert_eq!(stats.live_bytes, 3 * record_size);
+
+        assert!(storage.get_item(&items[0].id).await.unwrap().is_none());
+        let fetched = storage.get_item(&items[1].id).await.unwrap().unwrap();
+        assert_eq!(fetched.vector, updated.vector);
+        let fetched = storage.get_item(&items[3].id).await.unwrap().unwrap();
+        assert_eq!(fetched.vector, items[3].vector);
+
+        // New writes land after the compacted data
+        let extra = VectorItem {
+            id: Uuid::new_v4(),
+            vector: vec![0.0, 0.0, 1.0],
+            ..Default::default()
+        };
+        storage.insert_item(&extra).await.unwrap();
+        assert_eq!(storage.list_items(None).await.unwrap().len(), 4);
+        let fetched = storage.get_item(&items[2].id).await.unwrap().unwrap();
+        assert_eq!(fetched.vector, items[2].vector);
+    }
+
+    #[tokio::test]
+    async fn test_compaction_after_prepared_copy() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut storage = OptimizedStorage::new(temp_dir.path()).unwrap();
+        storage
+            .create_index(&CreateIndexConfig::default())
+            .await
+            .unwrap();
+
+        let items: Vec<VectorItem> = (0..4)
+            .map(|i| VectorItem {
+                id: Uuid::new_v4(),
+                vector: vec![i as f32, 1.0, 0.0],
+                ..Default::default()
+            })
+            .collect();
+        storage.insert_items(&items).await.unwrap();
+        storage.delete_item(&items[0].id).await.unwrap();
+
+        let record_size = (VECTOR_HEADER_SIZE + 3 * 4) as u64;
+        assert_eq!(
+            storage.prepare_compaction(u64::MAX).await.unwrap(),
+            3 * record_size
+        );
+        assert_eq!(storage.prepare_compaction(u64::MAX).await.unwrap(), 0);
+
+        // The first update leaves a copied slot spare and the second
+        // rewrites it, so its copy is stale by the time compaction runs
+        let mut moved = items[1].clone();
+        moved.vector = vec![5.0, 5.0, 5.0];
+        storage.update_item(&moved).await.unwrap();
+        let mut rewritten = items[2].clone();
+        rewritten.vector = vec![7.0, 7.0, 7.0];
+        storage.update_item(&rewritten).await.unwrap();
+        let extra = VectorItem {
+            id: Uuid::new_v4(),
+            vector: vec![0.0, 0.0, 1.0],
+            ..Default::default()
+        };
+        storage.insert_item(&extra).await.unwrap();
+
+        storage.compact(None).await.unwrap();
+
+        for item in [&moved, &rewritten, &items[3], &extra] {
+            let fetched = storage.get_item(&item.id).await.unwrap().unwrap();
+            assert_eq!(fetched.vector, item.vector);
+        }
+        assert!(storage.get_item(&items[0].id).await.unwrap().is_none());
+        assert_eq!(storage.compaction_stats().await.unwrap().deleted_items, 0);
+    }
+
+    #[tokio::test]
+    async fn test_batched_hydration() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut storage = OptimizedStorage::new(temp_dir.path()).unwrap();
+        storage
+            .create_index(&CreateIndexConfig::default())
+            .await
+            .unwrap();
+        // More than two multi-get batches, inserted out of id order
+        let mut items: Vec<VectorItem> = (0..2 * MULTI_GET_BATCH + 10)
+            .map(|i| VectorItem {
+                id: Uuid::from_u128((i as u128 * 7919) % 100_003 + 1),
+                vector: vec![i as f32, 1.0],
+                ..Default::default()
+            })
+            .collect();
+        storage.insert_items(&items).await.unwrap();
+        storage.delete_item(&items[5].id).await.unwrap();
+
+        let listed = storage.list_items(None).await.unwrap();
+        assert_eq!(listed.len(), items.len() - 1);
+        assert!(listed.windows(2).all(|w| w[0].id < w[1].id));
+        let limited = storage
+            .list_items(Some(ListOptions {
+                limit: Some(MULTI_GET_BATCH + 1),
+                offset: None,
+                filter: None,
+            }))
+            .await
+            .unwrap();
+        assert_eq!(limited.len(), MULTI_GET_BATCH + 1);
+        let ids = |items: &[VectorItem]| items.iter().map(|item| item.id).collect::<Vec<_>>();
+        assert_eq!(ids(&limited), ids(&listed[..MULTI_GET_BATCH + 1]));
+
+        // Results follow the ids asked for, with gaps for missing ones
+        items.truncate(MULTI_GET_BATCH + 3);
+        let mut ids: Vec<Uuid> = items.iter().rev().map(|item| item.id).collect();
+        ids.push(Uuid::new_v4());
+        let fetched = storage.get_items(&ids).await.unwrap();
+        assert_eq!(fetched.len(), ids.len());
+        for (item, fetched) in items.iter().rev().zip(&fetched) {
+            match fetched {
+                Some(fetched) => assert_eq!(fetched.vector, item.vector),
+                None => assert_eq!(item.id, items[5].id),
+            }
+        }
+        assert!(fetched[items.len() - 6].is_none());
+        assert!(fetched.last().unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_rocksdb_tuning() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut tuning = RocksDbTuning {
+            profile: TuningProfile::LowMemory,
+            block_cache_bytes: Some(1 << 20),
+            ..Default::default()
+        };
+        tuning
+            .options
+            .insert("level0_file_num_compaction_trigger".into(), "2".into());
+        let config = CreateIndexConfig {
+            rocksdb_tuning: tuning.clone(),
+            ..Default::default()
+        };
+        let mut storage = OptimizedStorage::new(temp_dir.path()).unwrap();
+        storage.create_index(&config).await.unwrap();
+        let item = VectorItem {
+            id: Uuid::new_v4(),
+            vector: vec![1.0, 2.0],
+            ..Default::default()
+        };
+        storage.insert_item(&item).await.unwrap();
+        storage.flush_manifest_if_dirty().await.unwrap();
+        drop(storage);
+
+        // Reopening applies the saved settings again
+        let storage = OptimizedStorage::new(temp_dir.path()).unwrap();
+        assert!(storage.get_item(&item.id).await.unwrap().is_some());
+        assert!(storage.get_item(&Uuid::new_v4()).await.unwrap().is_none());
+        let manifest = storage.current_manifest().await.unwrap().unwrap();
+        assert_eq!(manifest.rocksdb_tuning, tuning);
+        // Indexes saved before the settings existed get bloom filters too
+        let saved: RocksDbTuning = serde_json::from_str("{}").unwrap();
+        assert_eq!(saved, RocksDbTuning::default());
+        assert_eq!(saved.bloom_bits_per_key, 10);
+
+        // An override RocksDB rejects fails the open
+        let other_dir = TempDir::new().unwrap();
+        let mut config = CreateIndexConfig::default();
+        config
+            .rocksdb_tuning
+            .options
+            .insert("no_such_option".into(), "1".into());
+        let mut storage = OptimizedStorage::new(other_dir.path()).unwrap();
+        let err = storage.create_index(&config).await.unwrap_err();
+        assert!(err.to_string().contains("no_such_option"));
+    }
+
+    #[tokio::test]
+    async fn test_cbor_metadata() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut storage = OptimizedStorage::new(temp_dir.path()).unwrap();
+        let config = CreateIndexConfig {
+            metadata_encoding: MetadataEncoding::Cbor,
+            ..Default::default()
+        };
+        storage.create_index(&config).await.unwrap();
+        let items: Vec<VectorItem> = (0..4)
+            .map(|i| VectorItem {
+                id: Uuid::new_v4(),
+                vector: vec![i as f32, 1.0],
+                metadata: serde_json::json!({ "n": i, "tags": ["a", "b"], "score": 0.5 }),
+                ..Default::default()
+            })
+            .collect();
+        storage.insert_items(&items).await.unwrap();
+        let required = |manifest: Manifest| {
+            manifest
+                .features
+                .required
+                .contains(&METADATA_CBOR_FEATURE.to_string())
+        };
+        assert!(required(storage.current_manifest().await.unwrap().unwrap()));
+        let fetched = storage.get_item(&items[1].id).await.unwrap().unwrap();
+        assert_eq!(fetched.metadata, items[1].metadata);
+
+        // Back to JSON, with one record left in CBOR as if interrupted
+        let rewritten = storage
+            .set_metadata_encoding(MetadataEncoding::Json)
+            .await
+            .unwrap();
+        assert_eq!(rewritten, 4);
+        assert!(!required(
+            storage.current_manifest().await.unwrap().unwrap()
+        ));
+        {
+            let db = storage.db.get().unwrap();
+            let metadata_cf = db.cf_handle(METADATA_CF).unwrap();
+            let key = items[2].id.as_bytes();
+            let record = db.get_cf(&metadata_cf, key).unwrap().unwrap();
+            assert_eq!(record[0], b'{');
+            let item = decode_metadata(&record).unwrap();
+            let cbor = encode_metadata(&item, MetadataEncoding::Cbor).unwrap();
+            db.put_cf(&metadata_cf, key, cbor).unwrap();
+        }
+        let listed = storage.list_items(None).await.unwrap();
+        assert_eq!(listed.len(), 4);
+        for item in &items {
+            let listed = listed.iter().find(|l| l.id == item.id).unwrap();
+            assert_eq!(listed.metadata, item.metadata);
+        }
+        let rewritten = storage
+            .set_metadata_encoding(MetadataEncoding::Json)
+            .await
+            .unwrap();
+        assert_eq!(rewritten, 1);
+    }
+
+    #[tokio::test]
+    async fn test_namespace_keys() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut storage = OptimizedStorage::new(temp_dir.path()).unwrap();
+        let config = CreateIndexConfig {
+            namespace_field: Some("tenant".to_string()),
+            ..Default::default()
+        };
+        storage.create_index(&config).await.unwrap();
+        let item = |tenant: serde_json::Value, x: f32| VectorItem {
+            id: Uuid::new_v4(),
+            vector: vec![x, 1.0],
+            metadata: serde_json::json!({ "tenant": tenant }),
+            ..Default::default()
+        };
+        let mut acme = item("acme".into(), 0.0);
+        storage.insert_item(&acme).await.unwrap();
+        let others = vec![
+            item("acme".into(), 1.0),
+            item("globex".into(), 2.0),
+            item(7.into(), 3.0),
+            item(serde_json::Value::Null, 4.0),
+        ];
+        storage.insert_items(&others).await.unwrap();
+        let key_count = |storage: &OptimizedStorage| {
+            let db = storage.db.get().unwrap();
+            let namespace_cf = db.cf_handle(NAMESPACE_CF).unwrap();
+            db.iterator_cf(&namespace_cf, rocksdb::IteratorMode::Start)
+                .count()
+        };
+        assert_eq!(key_count(&storage), 4);
+        assert_eq!(storage.list_namespace("acme").await.unwrap().len(), 2);
+        assert_eq!(
+            storage.list_namespace("7").await.unwrap()[0].id,
+            others[2].id
+        );
+        assert!(storage.list_namespace("initech").await.unwrap().is_empty());
+
+        // Moving an item, in place or not, refiles it
+        acme.metadata = serde_json::json!({ "tenant": "globex" });
+        storage.update_metadata(&[acme.clone()]).await.unwrap();
+        let mut moved = others[0].clone();
+        moved.metadata = serde_json::json!({ "tenant": "initech" });
+        moved.vector = vec![1.0, 2.0];
+        storage.update_items(&[moved.clone()]).await.unwrap();
+        assert_eq!(key_count(&storage), 4);
+        assert!(storage.list_namespace("acme").await.unwrap().is_empty());
+        assert_eq!(storage.list_namespace("globex").await.unwrap().len(), 2);
+        let listed = storage.list_namespace("initech").await.unwrap();
+        assert_eq!(listed[0].vector, moved.vector);
+
+        storage.delete_item(&acme.id).await.unwrap();
+        assert_eq!(key_count(&storage), 3);
+        assert_eq!(storage.list_namespace("globex").await.unwrap().len(), 1);
+        storage.commit_transaction().await.unwrap();
+        drop(storage);
+
+        // An index from before namespace keys gets them when next opened
+        let manifest_path = temp_dir.path().join("manifest.json");
+        let mut manifest: serde_json::Value =
+            serde_json::from_slice(&std::fs::read(&manifest_path).unwrap()).unwrap();
+        manifest["features"] = serde_json::json!({});
+        std::fs::write(&manifest_path, manifest.to_string()).unwrap();
+        {
+            let db = DB::open_cf(
+                &rocksdb::Options::default(),
+                temp_dir.path().join("metadata"),
+                [
+                    METADATA_CF,
+                    VECTOR_INDEX_CF,
+                    NAMESPACE_CF,
+                    RANGE_CF,
+                    COMPOSITE_CF,
+                    PAYLOAD_CF,
+                ],
+            )
+            .unwrap();
+            let namespace_cf = db.cf_handle(NAMESPACE_CF).unwrap();
+            let key = item_namespace_key(Some("tenant"), &others[1]).unwrap();
+            db.delete_cf(&namespace_cf, key).unwrap();
+        }
+        let mut storage = OptimizedStorage::new(temp_dir.path()).unwrap();
+        assert_eq!(storage.list_namespace("globex").await.unwrap().len(), 1);
+        assert_eq!(key_count(&storage), 3);
+        let manifest = storage.current_manifest().await.unwrap().unwrap();
+        assert!(has_feature(
+            &manifest.features.write,
+            NAMESPACE_KEYS_FEATURE
+        ));
+        assert!(storage
+            .verify_secondary_indexes()
+            .await
+            .unwrap()
+            .is_consistent());
+
+        // Divergence is reported, and repaired by a rebuild
+        {
+            let db = storage.db.get().unwrap();
+            let namespace_cf = db.cf_handle(NAMESPACE_CF).unwrap();
+            let key = item_namespace_key(Some("tenant"), &others[1]).unwrap();
+            db.delete_cf(&namespace_cf, key).unwrap();
+            let key = item_namespace_key(Some("tenant"), &acme).unwrap();
+            db.put_cf(&namespace_cf, key, []).unwrap();
+        }
+        let divergence = storage.verify_secondary_indexes().await.unwrap();
+        assert_eq!(
+            divergence,
+            IndexDivergence {
+                missing: 1,
+                stale: 1
+            }
+        );
+        storage.rebuild_secondary_indexes().await.unwrap();
+        assert!(storage
+            .verify_secondary_indexes()
+            .await
+            .unwrap()
+            .is_consistent());
+        assert_eq!(key_count(&storage), 3);
+    }
+
+    /// Scores of the items `storage` holds within `range`, in order
+    async fn range_scores(storage: &OptimizedStorage, range: NumericRange) -> Vec<f64> {
+        let items = storage.list_range("score", &range).await.unwrap().unwrap();
+        let mut scores: Vec<f64> = items
+            .iter()
+            .map(|item| item.metadata["score"].as_f64().unwrap())
+            .collect();
+        scores.sort_by(f64::total_cmp);
+        scores
+    }
+
+    #[tokio::test]
+    async fn test_range_keys() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut storage = OptimizedStorage::new(temp_dir.path()).unwrap();
+        let config = CreateIndexConfig {
+            range_fields: vec!["score".to_string(), "doc.year".to_string()],
+            ..Default::default()
+        };
+        storage.create_index(&config).await.unwrap();
+        let item = |score: serde_json::Value, year: i64| VectorItem {
+            id: Uuid::new_v4(),
+            vector: vec![1.0, 1.0],
+            metadata: serde_json::json!({ "score": score, "doc": { "year": year } }),
+            ..Default::default()
+        };
+        let mut items = vec![
+            item((-2.5).into(), 2020),
+            item((-0.0).into(), 2021),
+            item(0.into(), 2022),
+            item(0.75.into(), 2023),
+            item(3.into(), 2024),
+            item("high".into(), 2025),
+        ];
+        storage.insert_items(&items).await.unwrap();
+        let range = |min, max| NumericRange { min, max };
+
+        // Bounds hold across signs, with -0.0 filed alongside 0
+        assert_eq!(
+            range_scores(&storage, NumericRange::default()).await.len(),
+            5
+        );
+        assert_eq!(
+            range_scores(&storage, range(Bound::Included(0.0), Bound::Included(0.0))).await,
+            vec![-0.0, 0.0]
+        );
+        assert_eq!(
+            range_scores(&storage, range(Bound::Excluded(-2.5), Bound::Excluded(3.0))).await,
+            vec![-0.0, 0.0, 0.75]
+        );
+        assert_eq!(
+            range_scores(&storage, range(Bound::Unbounded, Bound::Included(-1.0))).await,
+            vec![-2.5]
+        );
+        assert!(
+            range_scores(&storage, range(Bound::Included(1.0), Bound::Excluded(1.0)))
+                .await
+                .is_empty()
+        );
+        let years = storage
+            .list_range(
+                "doc.year",
+                &range(Bound::Included(2024.0), Bound::Unbounded),
+            )
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(years.len(), 2);
+        assert!(storage
+            .list_range("year", &NumericRange::default())
+            .await
+            .unwrap()
+            .is_none());
+
+        // Updates refile items and deletes drop them
+        items[0].metadata["score"] = 10.into();
+        storage.update_metadata(&items[..1]).await.unwrap();
+        items[1].metadata["score"] = "none".into();
+        items[1].vector = vec![2.0, 1.0];
+        storage.update_items(&items[1..2]).await.unwrap();
+        storage.delete_item(&items[4].id).await.unwrap();
+        assert_eq!(
+            range_scores(&storage, range(Bound::Included(-5.0), Bound::Unbounded)).await,
+            vec![0.0, 0.75, 10.0]
+        );
+        assert!(storage
+            .verify_secondary_indexes()
+            .await
+            .unwrap()
+            .is_consistent());
+
+        {
+            let db = storage.db.get().unwrap();
+            let range_cf = db.cf_handle(RANGE_CF).unwrap();
+            let key = item_range_key("score", &items[3]).unwrap();
+            db.delete_cf(&range_cf, key).unwrap();
+        }
+        assert_eq!(storage.verify_secondary_indexes().await.unwrap().missing, 1);
+        storage.rebuild_secondary_indexes().await.unwrap();
+        assert_eq!(
+            range_scores(&storage, NumericRange::default()).await,
+            vec![0.0, 0.75, 10.0]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_composite_keys() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut storage = OptimizedStorage::new(temp_dir.path()).unwrap();
+        let mut config = CreateIndexConfig::default();
+        config.metadata_config.composite_indexes = vec![CompositeIndex::new(["tenant", "kind"])];
+        storage.create_index(&config).await.unwrap();
+        let item = |tenant: &str, kind: serde_json::Value| VectorItem {
+            id: Uuid::new_v4(),
+            vector: vec![1.0, 1.0],
+            metadata: serde_json::json!({ "tenant": tenant, "kind": kind }),
+            ..Default::default()
+        };
+        let mut items = vec![
+            item("acme", 1.into()),
+            item("acme", 1.0.into()),
+            item("acme", 2.into()),
+            item("globex", 1.into()),
+        ];
+        storage.insert_items(&items).await.unwrap();
+        let pinned = |values: &[(&str, serde_json::Value)]| -> Vec<(String, serde_json::Value)> {
+            values
+                .iter()
+                .map(|(field, value)| (field.to_string(), value.clone()))
+                .collect()
+        };
+        let acme_one = pinned(&[("tenant", "acme".into()), ("kind", 1.into())]);
+
+        // Numbers are filed by value, and a leading field alone is enough
+        let listed = storage.list_composite(&acme_one).await.unwrap().unwrap();
+        assert_eq!(listed.len(), 2);
+        let listed = storage
+            .list_composite(&pinned(&[("tenant", "acme".into())]))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(listed.len(), 3);
+        assert!(storage
+            .list_composite(&pinned(&[("kind", 1.into())]))
+            .await
+            .unwrap()
+            .is_none());
+
+        // Updates refile items and deletes drop them
+        items[0].metadata["kind"] = 2.into();
+        storage.update_metadata(&items[..1]).await.unwrap();
+        storage.delete_item(&items[1].id).await.unwrap();
+        assert!(storage
+            .list_composite(&acme_one)
+            .await
+            .unwrap()
+            .unwrap()
+            .is_empty());
+        assert!(storage
+            .verify_secondary_indexes()
+            .await
+            .unwrap()
+            .is_consistent());
+
+        {
+            let db = storage.db.get().unwrap();
+            let composite_cf = db.cf_handle(COMPOSITE_CF).unwrap();
+            let index = CompositeIndex::new(["tenant", "kind"]);
+            let key = item_composite_key(&index, &items[2]).unwrap();
+            db.delete_cf(&composite_cf, key).unwrap();
+        }
+        assert_eq!(storage.verify_secondary_indexes().await.unwrap().missing, 1);
+        storage.rebuild_secondary_indexes().await.unwrap();
+        let acme_two = pinned(&[("tenant", "acme".into()), ("kind", 2.into())]);
+        assert_eq!(
+            storage
+                .list_composite(&acme_two)
+                .await
+                .unwrap()
+                .unwrap()
+                .len(),
+            2
+        );
+    }
+
+    #[tokio::test]
+    async fn test_vector_tiering() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut storage = OptimizedStorage::new(temp_dir.path()).unwrap();
+        storage
+            .create_index(&CreateIndexConfig::default())
+            .await
+            .unwrap();
+        let items: Vec<VectorItem> = (0..6)
+            .map(|i| VectorItem {
+                id: Uuid::new_v4(),
+                vector: vec![i as f32, 0.0, 0.0, 0.0, 1.0],
+                ..Default::default()
+            })
+            .collect();
+        storage.insert_items(&items).await.unwrap();
+
+        // Only the first two were read, so the rest go cold
+        storage.record_access(&[items[0].id, items[1].id, items[1].id]);
+        let stats = storage.retier(&TieringPolicy::default()).await.unwrap();
+        assert_eq!((stats.hot_items, stats.cold_items), (2, 4));
+        assert_eq!(stats.demoted, 4);
+        assert!(stats.cold_bytes > 0);
+        let manifest = storage.current_manifest().await.unwrap().unwrap();
+        assert!(manifest
+            .features
+            .required
+            .contains(&COLD_TIER_FEATURE.to_string()));
+
+        // Cold vectors read back transparently
+        for item in &items {
+            let fetched = storage.get_item(&item.id).await.unwrap().unwrap();
+            assert_eq!(fetched.vector, item.vector);
+        }
+        // The demoted slots are dead space in vectors.dat
+        let record_size = (VECTOR_HEADER_SIZE + 5 * 4) as u64;
+        let before = storage.compaction_stats().await.unwrap();
+        assert_eq!(before.live_bytes, 2 * record_size);
+        assert_eq!(before.reclaimable_bytes, 4 * record_size);
+
+        // Updating a cold item writes it hot; deleting one leaves cold garbage
+        let mut updated = items[2].clone();
+        updated.vector = vec![7.0, 7.0, 7.0, 7.0, 7.0];
+        storage.update_item(&updated).await.unwrap();
+        storage.delete_item(&items[3].id).await.unwrap();
+        storage.compact(None).await.unwrap();
+        let fetched = storage.get_item(&items[4].id).await.unwrap().unwrap();
+        assert_eq!(fetched.vector, items[4].vector);
+        assert_eq!(
+            storage
+                .get_item(&items[2].id)
+                .await
+                .unwrap()
+                .unwrap()
+                .vector,
+            updated.vector
+        );
+
+        // Cold records survive a reopen
+        storage.flush().await.unwrap();
+        drop(storage);
+        let mut storage = OptimizedStorage::new(temp_dir.path()).unwrap();
+        let fetched = storage.get_item(&items[5].id).await.unwrap().unwrap();
+        assert_eq!(fetched.vector, items[5].vector);
+
+        // A cold item read again is promoted; the hot ones weren't read
+        // this time and everything else stays cold
+        storage.record_access(&[items[5].id]);
+        let policy = TieringPolicy {
+            demote_max_reads: 0,
+            promote_min_reads: 1,
+        };
+        let stats = storage.retier(&policy).await.unwrap();
+        assert_eq!(stats.promoted, 1);
+        assert_eq!(stats.demoted, 3);
+        assert_eq!((stats.hot_items, stats.cold_items), (1, 4));
+        assert_eq!(storage.list_items(None).await.unwrap().len(), 5);
+        for item in [&items[0], &items[1], &items[4], &items[5]] {
+            let fetched = storage.get_item(&item.id).await.unwrap().unwrap();
+            assert_eq!(fetched.vector, item.vector);
+        }
+
+        // Once nothing is cold the format requirement goes
+        storage.record_access(&[items[5].id]);
+        let stats = storage
+            .retier(&TieringPolicy {
+                demote_max_reads: 0,
+                promote_min_reads: 0,
+            })
+            .await
+            .unwrap();
+        assert_eq!((stats.hot_items, stats.cold_items), (5, 0));
+        let manifest = storage.current_manifest().await.unwrap().unwrap();
+        assert!(manifest.features.required.is_empty());
+        let fetched = storage.get_item(&items[0].id).await.unwrap().unwrap();
+        assert_eq!(fetched.vector, items[0].vector);
+    }
+
+    #[tokio::test]
+    async fn test_dedup_vectors_share_slots() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut storage = OptimizedStorage::new(temp_dir.path()).unwrap();
+        let config = CreateIndexConfig {
+            dedup_vectors: true,
+            ..Default::default()
+        };
+        storage.create_index(&config).await.unwrap();
+
+        let item = |vector: Vec<f32>| VectorItem {
+            id: Uuid::new_v4(),
+            vector,
+            ..Default::default()
+        };
+        // Repeats within a batch and across writes share one slot
+        let batch: Vec<VectorItem> = (0..6)
+            .map(|i| item(vec![(i % 2) as f32, 1.0, 0.0]))
+            .collect();
+        storage.insert_items(&batch).await.unwrap();
+        let single = item(vec![0.0, 1.0, 0.0]);
+        storage.insert_item(&single).await.unwrap();
+
+        let record_size = (VECTOR_HEADER_SIZE + 3 * 4) as u64;
+        let stats = storage.compaction_stats().await.unwrap();
+        assert_eq!(stats.live_items, 7);
+        assert_eq!(stats.live_bytes, 2 * record_size);
+        assert_eq!(stats.reclaimable_bytes, 0);
+
+        // Deleting some holders of a vector leaves it readable for the rest
+        for doomed in [&batch[0], &batch[2], &batch[4]] {
+            storage.delete_item(&doomed.id).await.unwrap();
+        }
+        let fetched = storage.get_item(&single.id).await.unwrap().unwrap();
+        assert_eq!(fetched.vector, vec![0.0, 1.0, 0.0]);
+        assert_eq!(
+            storage.compaction_stats().await.unwrap().reclaimable_bytes,
+            0
+        );
+
+        // Once the last holder goes the slot is dead and a new copy is stored
+        storage.delete_item(&single.id).await.unwrap();
+        assert_eq!(
+            storage.compaction_stats().await.unwrap().reclaimable_bytes,
+            record_size
+        );
+        let again = item(vec![0.0, 1.0, 0.0]);
+        storage.insert_item(&again).await.unwrap();
+
+        storage.compact(None).await.unwrap();
+        let stats = storage.compaction_stats().await.unwrap();
+        assert_eq!(stats.live_items, 4);
+        assert_eq!(stats.live_bytes, 2 * record_size);
+
+        // The slot map is rebuilt from the records after compaction and reopening
+        drop(storage);
+        let mut storage = OptimizedStorage::new(temp_dir.path()).unwrap();
+        storage
+            .insert_item(&item(vec![1.0, 1.0, 0.0]))
+            .await
+            .unwrap();
+        assert_eq!(
+            storage.compaction_stats().await.unwrap().live_bytes,
+            2 * record_size
+        );
+        for kept in [&batch[1], &batch[3], &batch[5]] {
+            let fetched = storage.get_item(&kept.id).await.unwrap().unwrap();
+            assert_eq!(fetched.vector, vec![1.0, 1.0, 0.0]);
+        }
+        let fetched = storage.get_item(&again.id).await.unwrap().unwrap();
+        assert_eq!(fetched.vector, vec![0.0, 1.0, 0.0]);
+    }
+
+    #[tokio::test]
+    async fn test_optimized_storage_item_count() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut storage = OptimizedStorage::new(temp_dir.path()).unwrap();
+        storage
+            .create_index(&CreateIndexConfig::default())
+            .await
+            .unwrap();
+
+        let item = VectorItem {
+            id: Uuid::new_v4(),
+            vector: vec![1.0, 0.0, 0.0],
+            ..Default::default()
+        };
+        storage.insert_item(&item).await.unwrap();
+        storage.update_item(&item).await.unwrap();
+
+        // Counted before the batched manifest save reaches disk
+        assert_eq!(storage.get_stats().await.unwrap().items, 1);
+
+        // Deleting twice, or an unknown id, only counts once
+        storage.delete_item(&item.id).await.unwrap();
+        storage.delete_item(&item.id).await.unwrap();
+        storage.delete_item(&Uuid::new_v4()).await.unwrap();
+        storage
+            .insert_item(&VectorItem {
+                id: Uuid::new_v4(),
+                vector: vec![0.0, 1.0, 0.0],
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(storage.get_stats().await.unwrap().items, 1);
+
+        // Dropping without a commit still persists the count
+        drop(storage);
+        let reopened = OptimizedStorage::new(temp_dir.path()).unwrap();
+        assert_eq!(reopened.get_stats().await.unwrap().items, 1);
+    }
+
+    #[tokio::test]
+    async fn test_manifest_feature_negotiation() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut storage = OptimizedStorage::new(temp_dir.path()).unwrap();
+        storage
+            .create_index(&CreateIndexConfig::default())
+            .await
+            .unwrap();
+        let kept = VectorItem {
+            id: Uuid::new_v4(),
+            vector: vec![1.0, 0.0],
+            ..Default::default()
+        };
+        storage.insert_item(&kept).await.unwrap();
+        storage.commit_transaction().await.unwrap();
+        drop(storage);
+
+        // Pretend a newer version added a write feature and a field
+        let manifest_path = temp_dir.path().join("manifest.json");
+        let mut manifest: serde_json::Value =
+            serde_json::from_slice(&std::fs::read(&manifest_path).unwrap()).unwrap();
+        manifest["features"] = serde_json::json!({ "write": ["sparse_vectors"] });
+        manifest["sparse_dimensions"] = serde_json::json!(30522);
+        std::fs::write(&manifest_path, manifest.to_string()).unwrap();
+
+        let mut storage = OptimizedStorage::new(temp_dir.path()).unwrap();
+        assert!(storage.is_read_only().await.unwrap());
+        assert!(storage.get_item(&kept.id).await.unwrap().is_some());
+        assert!(storage.insert_item(&kept).await.is_err());
+        assert!(storage.delete_item(&kept.id).await.is_err());
+        drop(storage);
+
+        // Once the feature is understood the unknown field survives a save
+        manifest["features"] = serde_json::json!({});
+        std::fs::write(&manifest_path, manifest.to_string()).unwrap();
+        let mut storage = OptimizedStorage::new(temp_dir.path()).unwrap();
+        assert!(!storage.is_read_only().await.unwrap());
+        storage.delete_item(&kept.id).await.unwrap();
+        storage.commit_transaction().await.unwrap();
+        drop(storage);
+        let saved: serde_json::Value =
+            serde_json::from_slice(&std::fs::read(&manifest_path).unwrap()).unwrap();
+        assert_eq!(saved["sparse_dimensions"], 30522);
+        assert_eq!(saved["total_items"], 0);
+
+        // Newer format versions are refused outright
+        manifest["version"] = serde_json::json!(crate::manifest::FORMAT_VERSION + 1);
+        std::fs::write(&manifest_path, manifest.to_string()).unwrap();
+        let storage = OptimizedStorage::new(temp_dir.path()).unwrap();
+        assert!(storage.get_item(&kept.id).await.is_err());
+    }
+
+    #[test]
+    fn test_map_range_bounds() {
+        assert_eq!(map_range(92, 8, 100).unwrap(), 92..100);
+        assert!(map_range(96, 8, 100).is_err());
+        // Offsets past usize (all of them above 4 GiB on 32-bit) never wrap
+        assert!(map_range(u64::MAX, 8, usize::MAX).is_err());
+        assert!(map_range(usize::MAX as u64, 8, usize::MAX).is_err());
+    }
+
+    #[test]
+    fn test_map_len_limit() {
+        let four_gib = 4 * 1024 * 1024 * 1024;
+        assert!(check_map_len(four_gib, u32::MAX as u64).is_err());
+        assert!(check_map_len(1024, u32::MAX as u64).is_ok());
+        assert!(check_map_len(MAX_MAP_LEN, MAX_MAP_LEN).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_recreate_over_open_index() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut storage = OptimizedStorage::new(temp_dir.path()).unwrap();
+        storage
+            .create_index(&CreateIndexConfig::default())
+            .await
+            .unwrap();
+        storage
+            .insert_item(&VectorItem {
+                id: Uuid::new_v4(),
+                vector: vec![1.0, 0.0],
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+
+        // Replacing the index while its files are open and mapped
+        storage
+            .create_index(&CreateIndexConfig {
+                delete_if_exists: true,
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(storage.get_stats().await.unwrap().items, 0);
+        assert!(storage.list_items(None).await.unwrap().is_empty());
+
+        // The preallocated file is sized without being written
+        let len = std::fs::metadata(temp_dir.path().join("vectors.dat"))
+            .unwrap()
+            .len();
+        assert_eq!(len, 10 * 1024 * 1024);
+    }
 }
@@ -0,0 +1,897 @@
+// Copyright 2024-2026 Andrey Vasilevsky <anvanster@gmail.com>
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::manifest::{
+    damaged_manifest, parse_manifest, Access, FormatFeatures, Negotiated, FORMAT_VERSION,
+    VECTOR_TRANSFORM_FEATURE, VECTOR_VALIDATION_FEATURE,
+};
+use async_trait::async_trait;
+use redb::{
+    Database, Durability, ReadableTable, ReadableTableMetadata, TableDefinition, WriteTransaction,
+};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::fs;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+use vectrust_core::*;
+
+/// Pure-Rust optimized storage (v2) built on redb.
+///
+/// Same on-disk role as [`OptimizedStorage`](crate::OptimizedStorage) for targets
+/// where RocksDB can't be linked (musl static builds, minimal CI images).
+/// Metadata and vectors live in separate tables of a single `index.redb` file.
+pub struct RedbStorage {
+    path: PathBuf,
+    db: Arc<RwLock<Option<Database>>>,
+    /// Held while opening the database, so concurrent first accesses open it once
+    opening: tokio::sync::Mutex<()>,
+    manifest: Arc<RwLock<Option<RedbManifest>>>,
+    /// Whether this build may write the index, from the manifest's features
+    access: std::sync::RwLock<Access>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RedbManifest {
+    pub version: u32,
+    pub format: String,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub dimensions: Option<usize>,
+    pub distance_metric: DistanceMetric,
+    #[serde(default)]
+    pub compaction: Option<CompactionPolicy>,
+    #[serde(default)]
+    pub embedding_model: Option<EmbeddingModel>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub limits: Option<IndexLimits>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vector_transform: Option<VectorTransform>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vector_validation: Option<VectorValidation>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub namespace_field: Option<String>,
+    #[serde(default)]
+    pub features: FormatFeatures,
+    /// Fields from newer versions, kept when this build saves the manifest
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+/// Value of `manifest.json`'s `format` field for redb-backed indexes
+pub const REDB_FORMAT: &str = "redb";
+
+const METADATA_TABLE: TableDefinition<&[u8], &[u8]> = TableDefinition::new("metadata");
+const VECTORS_TABLE: TableDefinition<&[u8], &[u8]> = TableDefinition::new("vectors");
+const PAYLOADS_TABLE: TableDefinition<&[u8], &[u8]> = TableDefinition::new("payloads");
+
+fn redb_error(err: impl std::fmt::Display) -> VectraError {
+    VectraError::StorageError {
+        message: err.to_string(),
+    }
+}
+
+fn encode_vector(vector: &[f32]) -> Vec<u8> {
+    vector.iter().flat_map(|v| v.to_le_bytes()).collect()
+}
+
+fn decode_vector(bytes: &[u8]) -> Vec<f32> {
+    bytes
+        .chunks_exact(4)
+        .map(|chunk| f32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+        .collect()
+}
+
+impl RedbStorage {
+    pub fn new(path: &Path) -> Result<Self> {
+        Ok(Self {
+            path: path.to_path_buf(),
+            db: Arc::new(RwLock::new(None)),
+            opening: tokio::sync::Mutex::new(()),
+            manifest: Arc::new(RwLock::new(None)),
+            access: std::sync::RwLock::new(Access::ReadWrite),
+        })
+    }
+
+    fn manifest_path(&self) -> PathBuf {
+        self.path.join("manifest.json")
+    }
+
+    fn db_path(&self) -> PathBuf {
+        self.path.join("index.redb")
+    }
+
+    /// Parse the manifest on disk, upgrading it and recording whether this
+    /// build may write the index
+    async fn read_manifest(&self) -> Result<Option<Negotiated<RedbManifest>>> {
+        let manifest_path = self.manifest_path();
+
+        if !manifest_path.exists() {
+            return Ok(None);
+        }
+
+        let content = fs::read_to_string(manifest_path).await?;
+        let negotiated: Negotiated<RedbManifest> =
+            parse_manifest(&content).map_err(|e| damaged_manifest(&self.path, e))?;
+        *self.access.write().unwrap() = negotiated.access.clone();
+        Ok(Some(negotiated))
+    }
+
+    async fn load_manifest(&self) -> Result<Option<RedbManifest>> {
+        Ok(self.read_manifest().await?.map(|n| n.manifest))
+    }
+
+    /// Open the index if needed and fail unless this build may write it
+    async fn ensure_writable(&self) -> Result<()> {
+        self.initialize_storage().await?;
+        self.access.read().unwrap().check_writable(&self.path)
+    }
+
+    async fn save_manifest(&self, manifest: &RedbManifest) -> Result<()> {
+        fs::create_dir_all(&self.path).await?;
+        let content = serde_json::to_string_pretty(manifest)?;
+
+        // Write then rename, so a crash never leaves a torn manifest
+        let manifest_path = self.manifest_path();
+        let temp_path = manifest_path.with_extension("json.tmp");
+        fs::write(&temp_path, content).await?;
+        fs::rename(&temp_path, &manifest_path).await?;
+
+        *self.manifest.write().await = Some(manifest.clone());
+        Ok(())
+    }
+
+    /// Live items in id order, paged by `options`, reading only the
+    /// vectors of items whose metadata `matches`
+    async fn scan_items(
+        &self,
+        options: Option<ListOptions>,
+        matches: Option<&MetadataPredicate>,
+    ) -> Result<Vec<VectorItem>> {
+        self.initialize_storage().await?;
+
+        let db_guard = self.db.read().await;
+        let Some(ref db) = *db_guard else {
+            return Ok(Vec::new());
+        };
+
+        let txn = db.begin_read().map_err(redb_error)?;
+        let metadata_table = txn.open_table(METADATA_TABLE).map_err(redb_error)?;
+        let vectors_table = txn.open_table(VECTORS_TABLE).map_err(redb_error)?;
+
+        let (mut skip, limit) = options.map_or((0, None), |o| (o.offset.unwrap_or(0), o.limit));
+        let mut items = Vec::new();
+
+        for entry in metadata_table.iter().map_err(redb_error)? {
+            if limit.is_some_and(|limit| items.len() >= limit) {
+                break;
+            }
+
+            let (key, value) = entry.map_err(redb_error)?;
+            let mut item: VectorItem = serde_json::from_slice(value.value())?;
+            if matches.is_some_and(|matches| !matches(&item.metadata)) {
+                continue;
+            }
+            if skip > 0 {
+                skip -= 1;
+                continue;
+            }
+            if let Some(vector) = vectors_table.get(key.value()).map_err(redb_error)? {
+                item.vector = decode_vector(vector.value());
+            }
+            items.push(item);
+        }
+
+        Ok(items)
+    }
+
+    async fn initialize_storage(&self) -> Result<()> {
+        if self.db.read().await.is_some() {
+            return Ok(());
+        }
+        let _opening = self.opening.lock().await;
+        if self.db.read().await.is_some() {
+            return Ok(());
+        }
+
+        let negotiated = self
+            .read_manifest()
+            .await?
+            .ok_or_else(|| VectraError::IndexNotFound {
+                path: self.manifest_path().to_string_lossy().to_string(),
+            })?;
+        let manifest = negotiated.manifest;
+        if negotiated.upgraded && negotiated.access == Access::ReadWrite {
+            self.save_manifest(&manifest).await?;
+        }
+
+        let db = Database::create(self.db_path()).map_err(redb_error)?;
+
+        // Make sure every table exists so read transactions can open them
+        let txn = db.begin_write().map_err(redb_error)?;
+        txn.open_table(METADATA_TABLE).map_err(redb_error)?;
+        txn.open_table(VECTORS_TABLE).map_err(redb_error)?;
+        txn.open_table(PAYLOADS_TABLE).map_err(redb_error)?;
+        txn.commit().map_err(redb_error)?;
+
+        *self.manifest.write().await = Some(manifest);
+        *self.db.write().await = Some(db);
+        Ok(())
+    }
+
+    /// Check dimensions against the manifest, recording them on first insert
+    async fn check_dimensions(&self, dimensions: usize) -> Result<()> {
+        let mut manifest_guard = self.manifest.write().await;
+        let manifest = manifest_guard
+            .as_mut()
+            .ok_or_else(|| VectraError::StorageError {
+                message: "Manifest not initialized".to_string(),
+            })?;
+
+        match manifest.dimensions {
+            Some(existing) if existing != dimensions => Err(VectraError::VectorValidation {
+                message: format!(
+                    "Vector dimension mismatch: expected {}, got {}",
+                    existing, dimensions
+                ),
+            }),
+            Some(_) => Ok(()),
+            None => {
+                manifest.dimensions = Some(dimensions);
+                let manifest = manifest.clone();
+                drop(manifest_guard);
+                self.save_manifest(&manifest).await
+            }
+        }
+    }
+
+    /// Write `items` in one transaction, which fails without writing
+    /// anything if `on_conflict` is `Error` and an id is already stored
+    async fn put_items(&self, items: &[VectorItem], on_conflict: OnConflict) -> Result<()> {
+        if items.is_empty() {
+            return Ok(());
+        }
+
+        self.ensure_writable().await?;
+
+        let first_dimensions = items[0].vector.len();
+        for item in items {
+            if item.vector.len() != first_dimensions {
+                return Err(VectraError::VectorValidation {
+                    message: format!(
+                        "All vectors must have same dimensions. Expected {}, got {}",
+                        first_dimensions,
+                        item.vector.len()
+                    ),
+                });
+            }
+        }
+        self.check_dimensions(first_dimensions).await?;
+
+        let db_guard = self.db.read().await;
+        if let Some(ref db) = *db_guard {
+            // Like the RocksDB backend's disabled WAL, writes become durable on flush
+            let mut txn = db.begin_write().map_err(redb_error)?;
+            txn.set_durability(Durability::Eventual);
+            match on_conflict {
+                OnConflict::Error => Self::check_new_ids(&txn, items)?,
+                // Only updates replace, and they need every item stored
+                OnConflict::Replace => Self::check_stored_ids(&txn, items)?,
+            }
+            Self::write_items(&txn, items)?;
+            txn.commit().map_err(redb_error)?;
+        }
+
+        Ok(())
+    }
+
+    fn check_new_ids(txn: &WriteTransaction, items: &[VectorItem]) -> Result<()> {
+        let metadata_table = txn.open_table(METADATA_TABLE).map_err(redb_error)?;
+        let mut ids = std::collections::HashSet::new();
+        for item in items {
+            let stored = metadata_table
+                .get(item.id.as_bytes().as_slice())
+                .map_err(redb_error)?
+                .is_some();
+            if stored || !ids.insert(item.id) {
+                return Err(VectraError::ItemAlreadyExists { id: item.id });
+            }
+        }
+        Ok(())
+    }
+
+    fn check_stored_ids(txn: &WriteTransaction, items: &[VectorItem]) -> Result<()> {
+        let metadata_table = txn.open_table(METADATA_TABLE).map_err(redb_error)?;
+        for item in items {
+            if metadata_table
+                .get(item.id.as_bytes().as_slice())
+                .map_err(redb_error)?
+                .is_none()
+            {
+                return Err(VectraError::ItemNotFound);
+            }
+        }
+        Ok(())
+    }
+
+    fn write_items(txn: &WriteTransaction, items: &[VectorItem]) -> Result<()> {
+        Self::write_metadata(txn, items)?;
+        let mut vectors_table = txn.open_table(VECTORS_TABLE).map_err(redb_error)?;
+
+        for item in items {
+            vectors_table
+                .insert(
+                    item.id.as_bytes().as_slice(),
+                    encode_vector(&item.vector).as_slice(),
+                )
+                .map_err(redb_error)?;
+        }
+
+        Ok(())
+    }
+
+    fn write_metadata(txn: &WriteTransaction, items: &[VectorItem]) -> Result<()> {
+        let mut metadata_table = txn.open_table(METADATA_TABLE).map_err(redb_error)?;
+
+        for item in items {
+            let mut metadata_item = item.clone();
+            metadata_item.vector = Vec::new();
+            let metadata_bytes = serde_json::to_vec(&metadata_item)?;
+            metadata_table
+                .insert(item.id.as_bytes().as_slice(), metadata_bytes.as_slice())
+                .map_err(redb_error)?;
+        }
+
+        Ok(())
+    }
+
+    /// Ensure all pending changes are durably written to disk
+    pub async fn flush(&self) -> Result<()> {
+        if let Some(ref db) = *self.db.read().await {
+            // An empty immediate commit persists any preceding eventual commits
+            let mut txn = db.begin_write().map_err(redb_error)?;
+            txn.set_durability(Durability::Immediate);
+            txn.commit().map_err(redb_error)?;
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl StorageBackend for RedbStorage {
+    async fn exists(&self) -> bool {
+        self.manifest_path().exists()
+    }
+
+    async fn create_index(&mut self, config: &CreateIndexConfig) -> Result<()> {
+        let manifest_path = self.manifest_path();
+
+        if manifest_path.exists() && !config.delete_if_exists {
+            return Err(VectraError::IndexAlreadyExists {
+                path: manifest_path.to_string_lossy().to_string(),
+            });
+        }
+
+        if config.delete_if_exists && self.path.exists() {
+            *self.db.write().await = None;
+            fs::remove_dir_all(&self.path).await.ok();
+        }
+
+        let mut features =
+            FormatFeatures::for_index(config.embedding_model.as_ref(), config.limits.as_ref());
+        if config.vector_transform.is_some() {
+            features.write.push(VECTOR_TRANSFORM_FEATURE.to_string());
+        }
+        if config.vector_validation.is_some() {
+            features.write.push(VECTOR_VALIDATION_FEATURE.to_string());
+        }
+        let manifest = RedbManifest {
+            version: FORMAT_VERSION,
+            format: REDB_FORMAT.to_string(),
+            created_at: chrono::Utc::now(),
+            dimensions: None,
+            distance_metric: config.distance_metric.clone(),
+            compaction: config.compaction.clone(),
+            embedding_model: config.embedding_model.clone(),
+            limits: config.limits.clone(),
+            vector_transform: config.vector_transform.clone(),
+            vector_validation: config.vector_validation.clone(),
+            namespace_field: config.namespace_field.clone(),
+            features,
+            extra: serde_json::Map::new(),
+        };
+
+        self.save_manifest(&manifest).await?;
+        self.initialize_storage().await
+    }
+
+    async fn get_item(&self, id: &Uuid) -> Result<Option<VectorItem>> {
+        self.initialize_storage().await?;
+
+        let db_guard = self.db.read().await;
+        let Some(ref db) = *db_guard else {
+            return Ok(None);
+        };
+
+        let txn = db.begin_read().map_err(redb_error)?;
+        let metadata_table = txn.open_table(METADATA_TABLE).map_err(redb_error)?;
+        let vectors_table = txn.open_table(VECTORS_TABLE).map_err(redb_error)?;
+        let id_bytes = id.as_bytes().as_slice();
+
+        let Some(metadata) = metadata_table.get(id_bytes).map_err(redb_error)? else {
+            return Ok(None);
+        };
+        let mut item: VectorItem = serde_json::from_slice(metadata.value())?;
+
+        if let Some(vector) = vectors_table.get(id_bytes).map_err(redb_error)? {
+            item.vector = decode_vector(vector.value());
+        }
+
+        Ok(Some(item))
+    }
+
+    async fn insert_item(&mut self, item: &VectorItem) -> Result<()> {
+        self.insert_items(std::slice::from_ref(item)).await
+    }
+
+    async fn insert_items(&mut self, items: &[VectorItem]) -> Result<()> {
+        self.put_items(items, OnConflict::Error).await
+    }
+
+    async fn update_item(&mut self, item: &VectorItem) -> Result<()> {
+        self.ensure_writable().await?;
+        self.check_dimensions(item.vector.len()).await?;
+
+        let db_guard = self.db.read().await;
+        if let Some(ref db) = *db_guard {
+            let mut txn = db.begin_write().map_err(redb_error)?;
+            txn.set_durability(Durability::Eventual);
+            Self::check_stored_ids(&txn, std::slice::from_ref(item))?;
+            Self::write_items(&txn, std::slice::from_ref(item))?;
+            txn.commit().map_err(redb_error)?;
+        }
+
+        Ok(())
+    }
+
+    async fn update_items(&mut self, items: &[VectorItem]) -> Result<()> {
+        self.put_items(items, OnConflict::Replace).await
+    }
+
+    async fn update_metadata(&mut self, items: &[VectorItem]) -> Result<()> {
+        if items.is_empty() {
+            return Ok(());
+        }
+        self.ensure_writable().await?;
+
+        let db_guard = self.db.read().await;
+        if let Some(ref db) = *db_guard {
+            let mut txn = db.begin_write().map_err(redb_error)?;
+            txn.set_durability(Durability::Eventual);
+            Self::check_stored_ids(&txn, items)?;
+            Self::write_metadata(&txn, items)?;
+            txn.commit().map_err(redb_error)?;
+        }
+
+        Ok(())
+    }
+
+    async fn delete_item(&mut self, id: &Uuid) -> Result<()> {
+        self.ensure_writable().await?;
+
+        let db_guard = self.db.read().await;
+        if let Some(ref db) = *db_guard {
+            let mut txn = db.begin_write().map_err(redb_error)?;
+            txn.set_durability(Durability::Eventual);
+            {
+                let mut metadata_table = txn.open_table(METADATA_TABLE).map_err(redb_error)?;
+                let mut vectors_table = txn.open_table(VECTORS_TABLE).map_err(redb_error)?;
+                let mut payloads_table = txn.open_table(PAYLOADS_TABLE).map_err(redb_error)?;
+                let id_bytes = id.as_bytes().as_slice();
+                metadata_table.remove(id_bytes).map_err(redb_error)?;
+                vectors_table.remove(id_bytes).map_err(redb_error)?;
+                payloads_table.remove(id_bytes).map_err(redb_error)?;
+            }
+            txn.commit().map_err(redb_error)?;
+        }
+
+        Ok(())
+    }
+
+    async fn list_items(&self, options: Option<ListOptions>) -> Result<Vec<VectorItem>> {
+        self.scan_items(options, None).await
+    }
+
+    async fn list_matching(
+        &self,
+        options: Option<ListOptions>,
+        matches: &MetadataPredicate,
+    ) -> Result<Vec<VectorItem>> {
+        self.scan_items(options, Some(matches)).await
+    }
+
+    async fn query_items(&self, query: &Query) -> Result<Vec<QueryResult>> {
+        if let Some(ref query_vector) = query.vector {
+            let all_items = self.list_items(None).await?;
+            let metric = self
+                .manifest
+                .read()
+                .await
+                .as_ref()
+                .map_or(DistanceMetric::Cosine, |m| m.distance_metric.clone());
+            let mut results = Vec::new();
+
+            for item in all_items {
+                if item.vector.len() == query_vector.len() {
+                    let similarity =
+                        VectorOps::calculate_similarity(query_vector, &item.vector, &metric);
+                    results.push(QueryResult {
+                        item,
+                        score: similarity,
+                        highlights: Vec::new(),
+                        payload: None,
+                    });
+                }
+            }
+
+            results.sort_by(QueryResult::rank_cmp);
+            results.truncate(query.top_k);
+
+            Ok(results)
+        } else {
+            Ok(Vec::new())
+        }
+    }
+
+    async fn begin_transaction(&mut self) -> Result<()> {
+        // Every write is already its own redb transaction
+        Ok(())
+    }
+
+    async fn commit_transaction(&mut self) -> Result<()> {
+        self.flush().await
+    }
+
+    async fn rollback_transaction(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    async fn delete_index(&mut self) -> Result<()> {
+        *self.db.write().await = None;
+        *self.manifest.write().await = None;
+
+        if self.path.exists() {
+            fs::remove_dir_all(&self.path).await?;
+        }
+        Ok(())
+    }
+
+    async fn compaction_stats(&self) -> Result<CompactionStats> {
+        self.initialize_storage().await?;
+
+        let db_guard = self.db.read().await;
+        let Some(ref db) = *db_guard else {
+            return Ok(CompactionStats::default());
+        };
+
+        let live_items = {
+            let txn = db.begin_read().map_err(redb_error)?;
+            let table = txn.open_table(METADATA_TABLE).map_err(redb_error)?;
+            table.len().map_err(redb_error)? as usize
+        };
+
+        let txn = db.begin_write().map_err(redb_error)?;
+        let stats = txn.stats().map_err(redb_error)?;
+        txn.abort().map_err(redb_error)?;
+
+        Ok(CompactionStats {
+            live_items,
+            deleted_items: 0,
+            live_bytes: stats.stored_bytes(),
+            reclaimable_bytes: stats.fragmented_bytes(),
+        })
+    }
+
+    async fn compact(&mut self, _max_bytes_per_sec: Option<u64>) -> Result<CompactionStats> {
+        // redb compacts in place and can't be throttled
+        let before = self.compaction_stats().await?;
+        self.flush().await?;
+
+        if let Some(ref mut db) = *self.db.write().await {
+            db.compact().map_err(redb_error)?;
+        }
+
+        Ok(before)
+    }
+
+    async fn compaction_policy(&self) -> Result<Option<CompactionPolicy>> {
+        Ok(self.load_manifest().await?.and_then(|m| m.compaction))
+    }
+
+    async fn embedding_model(&self) -> Result<Option<EmbeddingModel>> {
+        Ok(self.load_manifest().await?.and_then(|m| m.embedding_model))
+    }
+
+    async fn limits(&self) -> Result<Option<IndexLimits>> {
+        Ok(self.load_manifest().await?.and_then(|m| m.limits))
+    }
+
+    async fn vector_transform(&self) -> Result<Option<VectorTransform>> {
+        Ok(self.load_manifest().await?.and_then(|m| m.vector_transform))
+    }
+
+    async fn vector_validation(&self) -> Result<Option<VectorValidation>> {
+        Ok(self
+            .load_manifest()
+            .await?
+            .and_then(|m| m.vector_validation))
+    }
+
+    async fn namespace_field(&self) -> Result<Option<String>> {
+        Ok(self.load_manifest().await?.and_then(|m| m.namespace_field))
+    }
+
+    async fn is_read_only(&self) -> Result<bool> {
+        self.load_manifest().await?;
+        Ok(*self.access.read().unwrap() != Access::ReadWrite)
+    }
+
+    async fn get_stats(&self) -> Result<IndexStats> {
+        let Some(manifest) = self.load_manifest().await? else {
+            return Ok(IndexStats {
+                items: 0,
+                size: 0,
+                dimensions: None,
+                distance_metric: DistanceMetric::Cosine,
+                namespaces: Default::default(),
+                ann_lag: Default::default(),
+                lock_waits: Default::default(),
+            });
+        };
+
+        self.initialize_storage().await?;
+        let items = {
+            let db_guard = self.db.read().await;
+            match *db_guard {
+                Some(ref db) => {
+                    let txn = db.begin_read().map_err(redb_error)?;
+                    let table = txn.open_table(METADATA_TABLE).map_err(redb_error)?;
+                    table.len().map_err(redb_error)? as usize
+                }
+                None => 0,
+            }
+        };
+
+        let mut size = 0u64;
+        let mut entries = fs::read_dir(&self.path).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            if let Ok(metadata) = entry.metadata().await {
+                size += metadata.len();
+            }
+        }
+
+        Ok(IndexStats {
+            items,
+            size,
+            dimensions: manifest.dimensions,
+            distance_metric: manifest.distance_metric,
+            namespaces: Default::default(),
+            ann_lag: Default::default(),
+            lock_waits: Default::default(),
+        })
+    }
+
+    async fn set_payloads(&mut self, payloads: &[(Uuid, Option<Vec<u8>>)]) -> Result<()> {
+        self.ensure_writable().await?;
+
+        let db_guard = self.db.read().await;
+        if let Some(ref db) = *db_guard {
+            let txn = db.begin_write().map_err(redb_error)?;
+            {
+                let metadata_table = txn.open_table(METADATA_TABLE).map_err(redb_error)?;
+                let mut payloads_table = txn.open_table(PAYLOADS_TABLE).map_err(redb_error)?;
+                for (id, payload) in payloads {
+                    let id_bytes = id.as_bytes().as_slice();
+                    if metadata_table.get(id_bytes).map_err(redb_error)?.is_none() {
+                        return Err(VectraError::Storage {
+                            message: format!("Item {} not found", id),
+                        });
+                    }
+                    match payload {
+                        Some(payload) => {
+                            payloads_table
+                                .insert(id_bytes, payload.as_slice())
+                                .map_err(redb_error)?;
+                        }
+                        None => {
+                            payloads_table.remove(id_bytes).map_err(redb_error)?;
+                        }
+                    }
+                }
+            }
+            txn.commit().map_err(redb_error)?;
+        }
+
+        Ok(())
+    }
+
+    async fn get_payloads(&self, ids: &[Uuid]) -> Result<Vec<Option<Vec<u8>>>> {
+        self.initialize_storage().await?;
+
+        let db_guard = self.db.read().await;
+        let Some(ref db) = *db_guard else {
+            return Ok(vec![None; ids.len()]);
+        };
+        let txn = db.begin_read().map_err(redb_error)?;
+        let payloads_table = txn.open_table(PAYLOADS_TABLE).map_err(redb_error)?;
+        ids.iter()
+            .map(|id| {
+                let payload = payloads_table
+                    .get(id.as_bytes().as_slice())
+                    .map_err(redb_error)?;
+                Ok(payload.map(|payload| payload.value().to_vec()))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn item(vector: Vec<f32>, name: &str) -> VectorItem {
+        VectorItem {
+            id: Uuid::new_v4(),
+            vector,
+            metadata: serde_json::json!({ "name": name }),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_redb_storage_insert_query_delete() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut storage = RedbStorage::new(temp_dir.path()).unwrap();
+
+        assert!(!storage.exists().await);
+        storage
+            .create_index(&CreateIndexConfig::default())
+            .await
+            .unwrap();
+        assert!(storage.exists().await);
+
+        let item1 = item(vec![1.0, 0.0, 0.0], "item1");
+        let item2 = item(vec![0.0, 1.0, 0.0], "item2");
+        storage
+            .insert_items(&[item1.clone(), item2.clone()])
+            .await
+            .unwrap();
+
+        let retrieved = storage.get_item(&item1.id).await.unwrap().unwrap();
+        assert_eq!(retrieved.vector, item1.vector);
+        assert_eq!(retrieved.metadata["name"], "item1");
+
+        let query = Query {
+            vector: Some(vec![1.0, 0.1, 0.0]),
+            text: None,
+            top_k: 2,
+            filter: None,
+            ..Default::default()
+        };
+        let results = storage.query_items(&query).await.unwrap();
+        assert_eq!(results[0].item.id, item1.id);
+
+        let named_item2 = |metadata: &serde_json::Value| metadata["name"] == "item2";
+        let matching = storage.list_matching(None, &named_item2).await.unwrap();
+        assert_eq!(matching.len(), 1);
+        assert_eq!(matching[0].vector, item2.vector);
+
+        let mismatched = item(vec![1.0, 0.0], "bad");
+        assert!(storage.insert_item(&mismatched).await.is_err());
+
+        // Inserts never overwrite; updates do
+        let item3 = item(vec![0.0, 0.0, 1.0], "item3");
+        let taken = VectorItem {
+            id: item1.id,
+            ..item(vec![0.0, 0.0, 1.0], "taken")
+        };
+        assert!(matches!(
+            storage.insert_items(&[item3.clone(), taken.clone()]).await,
+            Err(VectraError::ItemAlreadyExists { id }) if id == item1.id
+        ));
+        assert!(storage.get_item(&item3.id).await.unwrap().is_none());
+        storage.update_items(&[taken]).await.unwrap();
+        let retrieved = storage.get_item(&item1.id).await.unwrap().unwrap();
+        assert_eq!(retrieved.metadata["name"], "taken");
+        assert!(matches!(
+            storage.update_item(&item3).await,
+            Err(VectraError::ItemNotFound)
+        ));
+        // Batch updates of an unknown id write nothing, not a ghost item
+        for outcome in [
+            storage.update_items(std::slice::from_ref(&item3)).await,
+            storage.update_metadata(std::slice::from_ref(&item3)).await,
+        ] {
+            assert!(matches!(outcome, Err(VectraError::ItemNotFound)));
+        }
+        assert!(storage.get_item(&item3.id).await.unwrap().is_none());
+
+        storage
+            .set_payloads(&[(item1.id, Some(b"text".to_vec()))])
+            .await
+            .unwrap();
+        assert!(storage
+            .set_payloads(&[(mismatched.id, Some(Vec::new()))])
+            .await
+            .is_err());
+        let payloads = storage.get_payloads(&[item1.id, item2.id]).await.unwrap();
+        assert_eq!(payloads, vec![Some(b"text".to_vec()), None]);
+
+        storage.delete_item(&item1.id).await.unwrap();
+        assert!(storage.get_item(&item1.id).await.unwrap().is_none());
+        assert_eq!(storage.get_payloads(&[item1.id]).await.unwrap(), vec![None]);
+        assert_eq!(storage.get_stats().await.unwrap().items, 1);
+    }
+
+    #[tokio::test]
+    async fn test_redb_storage_concurrent_open_and_metric() {
+        let temp_dir = TempDir::new().unwrap();
+        {
+            let mut storage = RedbStorage::new(temp_dir.path()).unwrap();
+            storage
+                .create_index(&CreateIndexConfig {
+                    distance_metric: DistanceMetric::Euclidean,
+                    ..Default::default()
+                })
+                .await
+                .unwrap();
+        }
+
+        // Concurrent first accesses open the database once
+        let mut storage = RedbStorage::new(temp_dir.path()).unwrap();
+        let missing = Uuid::new_v4();
+        let (a, b) = tokio::join!(storage.get_item(&missing), storage.list_items(None));
+        a.unwrap();
+        b.unwrap();
+
+        // Euclidean ranks the nearer short vector above the aligned long one
+        let near = item(vec![1.0, 1.0], "near");
+        let far = item(vec![10.0, 10.0], "far");
+        storage
+            .insert_items(&[near.clone(), far.clone()])
+            .await
+            .unwrap();
+        let query = Query {
+            vector: Some(vec![1.0, 1.0]),
+            top_k: 2,
+            ..Default::default()
+        };
+        let results = storage.query_items(&query).await.unwrap();
+        assert_eq!(results[0].item.id, near.id);
+        assert_eq!(results[0].score, 1.0);
+        assert!(!temp_dir.path().join("manifest.json.tmp").exists());
+    }
+
+    #[tokio::test]
+    async fn test_redb_storage_persists_across_reopen() {
+        let temp_dir = TempDir::new().unwrap();
+        let stored = item(vec![0.5, 0.5], "kept");
+
+        {
+            let mut storage = RedbStorage::new(temp_dir.path()).unwrap();
+            storage
+                .create_index(&CreateIndexConfig::default())
+                .await
+                .unwrap();
+            storage.insert_item(&stored).await.unwrap();
+            storage.commit_transaction().await.unwrap();
+        }
+
+        let storage = RedbStorage::new(temp_dir.path()).unwrap();
+        let items = storage.list_items(None).await.unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].vector, stored.vector);
+        assert_eq!(storage.get_stats().await.unwrap().dimensions, Some(2));
+    }
+}
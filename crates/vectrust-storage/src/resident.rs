@@ -0,0 +1,341 @@
+// Copyright 2024-2026 Andrey Vasilevsky <anvanster@gmail.com>
+// SPDX-License-Identifier: Apache-2.0
+
+//! A copy of a small index held in RAM.
+//!
+//! [`ResidentStorage`] wraps another backend and answers every read from
+//! memory, without touching the vector file or the metadata store. Writes
+//! go through the wrapped backend first, so they persist exactly as before,
+//! and reach the copy only once they have succeeded.
+
+use async_trait::async_trait;
+use std::collections::BTreeMap;
+use std::sync::RwLock;
+use uuid::Uuid;
+use vectrust_core::*;
+
+/// A backend whose items are all kept in memory for reads
+pub struct ResidentStorage {
+    inner: Box<dyn StorageBackend>,
+    items: RwLock<BTreeMap<Uuid, VectorItem>>,
+}
+
+impl ResidentStorage {
+    /// Serve reads of `inner` from `items`, which must be every item it holds
+    pub fn new(inner: Box<dyn StorageBackend>, items: Vec<VectorItem>) -> Self {
+        Self {
+            inner,
+            items: RwLock::new(items.into_iter().map(|item| (item.id, item)).collect()),
+        }
+    }
+
+    fn upsert(&self, items: &[VectorItem]) {
+        let mut resident = self.items.write().unwrap();
+        for item in items {
+            resident.insert(item.id, item.clone());
+        }
+    }
+
+    /// Copy everything from the wrapped backend again
+    async fn reload(&self) -> Result<()> {
+        let items = if self.inner.exists().await {
+            self.inner.list_items(None).await?
+        } else {
+            Vec::new()
+        };
+        *self.items.write().unwrap() = items.into_iter().map(|item| (item.id, item)).collect();
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl StorageBackend for ResidentStorage {
+    async fn exists(&self) -> bool {
+        self.inner.exists().await
+    }
+
+    async fn create_index(&mut self, config: &CreateIndexConfig) -> Result<()> {
+        self.inner.create_index(config).await?;
+        self.items.write().unwrap().clear();
+        Ok(())
+    }
+
+    async fn get_item(&self, id: &Uuid) -> Result<Option<VectorItem>> {
+        Ok(self.items.read().unwrap().get(id).cloned())
+    }
+
+    async fn get_items(&self, ids: &[Uuid]) -> Result<Vec<Option<VectorItem>>> {
+        let resident = self.items.read().unwrap();
+        Ok(ids.iter().map(|id| resident.get(id).cloned()).collect())
+    }
+
+    async fn insert_item(&mut self, item: &VectorItem) -> Result<()> {
+        self.inner.insert_item(item).await?;
+        self.upsert(std::slice::from_ref(item));
+        Ok(())
+    }
+
+    async fn insert_items(&mut self, items: &[VectorItem]) -> Result<()> {
+        self.inner.insert_items(items).await?;
+        self.upsert(items);
+        Ok(())
+    }
+
+    async fn update_item(&mut self, item: &VectorItem) -> Result<()> {
+        self.inner.update_item(item).await?;
+        self.upsert(std::slice::from_ref(item));
+        Ok(())
+    }
+
+    async fn update_items(&mut self, items: &[VectorItem]) -> Result<()> {
+        self.inner.update_items(items).await?;
+        self.upsert(items);
+        Ok(())
+    }
+
+    async fn update_metadata(&mut self, items: &[VectorItem]) -> Result<()> {
+        self.inner.update_metadata(items).await?;
+        let mut resident = self.items.write().unwrap();
+        for item in items {
+            if let Some(existing) = resident.get_mut(&item.id) {
+                let vector = std::mem::take(&mut existing.vector);
+                *existing = VectorItem {
+                    vector,
+                    ..item.clone()
+                };
+            }
+        }
+        Ok(())
+    }
+
+    async fn delete_item(&mut self, id: &Uuid) -> Result<()> {
+        self.inner.delete_item(id).await?;
+        self.items.write().unwrap().remove(id);
+        Ok(())
+    }
+
+    async fn list_items(&self, options: Option<ListOptions>) -> Result<Vec<VectorItem>> {
+        let resident = self.items.read().unwrap();
+        let (offset, limit) = options.map_or((0, None), |o| (o.offset.unwrap_or(0), o.limit));
+        Ok(resident
+            .values()
+            .skip(offset)
+            .take(limit.unwrap_or(usize::MAX))
+            .cloned()
+            .collect())
+    }
+
+    async fn list_matching(
+        &self,
+        options: Option<ListOptions>,
+        matches: &MetadataPredicate,
+    ) -> Result<Vec<VectorItem>> {
+        let resident = self.items.read().unwrap();
+        let (offset, limit) = options.map_or((0, None), |o| (o.offset.unwrap_or(0), o.limit));
+        Ok(resident
+            .values()
+            .filter(|item| matches(&item.metadata))
+            .skip(offset)
+            .take(limit.unwrap_or(usize::MAX))
+            .cloned()
+            .collect())
+    }
+
+    async fn query_items(&self, query: &Query) -> Result<Vec<QueryResult>> {
+        let Some(ref query_vector) = query.vector else {
+            return Ok(Vec::new());
+        };
+        let mut results: Vec<QueryResult> = self
+            .items
+            .read()
+            .unwrap()
+            .values()
+            .filter(|item| item.vector.len() == query_vector.len())
+            .map(|item| QueryResult {
+                score: VectorOps::cosine_similarity(query_vector, &item.vector),
+                item: item.clone(),
+                highlights: Vec::new(),
+                payload: None,
+            })
+            .collect();
+        results.sort_by(QueryResult::rank_cmp);
+        results.truncate(query.top_k);
+        Ok(results)
+    }
+
+    async fn begin_transaction(&mut self) -> Result<()> {
+        self.inner.begin_transaction().await
+    }
+
+    async fn commit_transaction(&mut self) -> Result<()> {
+        self.inner.commit_transaction().await
+    }
+
+    /// Writes the wrapped backend undoes are undone here by copying its
+    /// items again
+    async fn rollback_transaction(&mut self) -> Result<()> {
+        self.inner.rollback_transaction().await?;
+        self.reload().await
+    }
+
+    async fn delete_index(&mut self) -> Result<()> {
+        self.inner.delete_index().await?;
+        self.items.write().unwrap().clear();
+        Ok(())
+    }
+
+    async fn get_stats(&self) -> Result<IndexStats> {
+        self.inner.get_stats().await
+    }
+
+    async fn compaction_stats(&self) -> Result<CompactionStats> {
+        self.inner.compaction_stats().await
+    }
+
+    async fn compact(&mut self, max_bytes_per_sec: Option<u64>) -> Result<CompactionStats> {
+        self.inner.compact(max_bytes_per_sec).await
+    }
+
+    async fn prepare_compaction(&self, max_bytes: u64) -> Result<u64> {
+        self.inner.prepare_compaction(max_bytes).await
+    }
+
+    fn record_access(&self, ids: &[Uuid]) {
+        self.inner.record_access(ids);
+    }
+
+    async fn retier(&mut self, policy: &TieringPolicy) -> Result<TieringStats> {
+        self.inner.retier(policy).await
+    }
+
+    async fn set_metadata_encoding(&mut self, encoding: MetadataEncoding) -> Result<usize> {
+        self.inner.set_metadata_encoding(encoding).await
+    }
+
+    async fn compaction_policy(&self) -> Result<Option<CompactionPolicy>> {
+        self.inner.compaction_policy().await
+    }
+
+    async fn embedding_model(&self) -> Result<Option<EmbeddingModel>> {
+        self.inner.embedding_model().await
+    }
+
+    async fn limits(&self) -> Result<Option<IndexLimits>> {
+        self.inner.limits().await
+    }
+
+    async fn vector_transform(&self) -> Result<Option<VectorTransform>> {
+        self.inner.vector_transform().await
+    }
+
+    async fn vector_validation(&self) -> Result<Option<VectorValidation>> {
+        self.inner.vector_validation().await
+    }
+
+    async fn namespace_field(&self) -> Result<Option<String>> {
+        self.inner.namespace_field().await
+    }
+
+    async fn range_fields(&self) -> Result<Vec<String>> {
+        self.inner.range_fields().await
+    }
+
+    async fn composite_indexes(&self) -> Result<Vec<CompositeIndex>> {
+        self.inner.composite_indexes().await
+    }
+
+    async fn add_composite_index(&mut self, index: &CompositeIndex) -> Result<()> {
+        self.inner.add_composite_index(index).await
+    }
+
+    async fn is_read_only(&self) -> Result<bool> {
+        self.inner.is_read_only().await
+    }
+
+    fn set_flush_interval(&self, operations: u32) {
+        self.inner.set_flush_interval(operations);
+    }
+
+    /// Items found damaged on disk are dropped from memory too, so reads
+    /// agree with the index's health
+    async fn quarantine_damaged(&self) -> Result<Vec<QuarantinedItem>> {
+        let quarantined = self.inner.quarantine_damaged().await?;
+        let mut resident = self.items.write().unwrap();
+        for item in &quarantined {
+            resident.remove(&item.id);
+        }
+        Ok(quarantined)
+    }
+
+    fn quarantined(&self) -> Vec<QuarantinedItem> {
+        self.inner.quarantined()
+    }
+
+    async fn verify_secondary_indexes(&self) -> Result<IndexDivergence> {
+        self.inner.verify_secondary_indexes().await
+    }
+
+    async fn rebuild_secondary_indexes(&mut self) -> Result<()> {
+        self.inner.rebuild_secondary_indexes().await
+    }
+
+    async fn set_payloads(&mut self, payloads: &[(Uuid, Option<Vec<u8>>)]) -> Result<()> {
+        self.inner.set_payloads(payloads).await
+    }
+
+    async fn get_payloads(&self, ids: &[Uuid]) -> Result<Vec<Option<Vec<u8>>>> {
+        self.inner.get_payloads(ids).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_resident_reads_follow_writes() {
+        let temp_dir = TempDir::new().unwrap();
+        let inner = crate::Storage::auto_detect(temp_dir.path(), "index.json").unwrap();
+        let mut storage = ResidentStorage::new(inner, Vec::new());
+        storage
+            .create_index(&CreateIndexConfig::default())
+            .await
+            .unwrap();
+
+        let items: Vec<VectorItem> = (0..3)
+            .map(|i| VectorItem {
+                vector: vec![i as f32, 1.0],
+                metadata: serde_json::json!({ "n": i }),
+                ..Default::default()
+            })
+            .collect();
+        storage.insert_items(&items).await.unwrap();
+        let mut renamed = items[0].clone();
+        renamed.metadata = serde_json::json!({ "n": 10 });
+        renamed.vector = Vec::new();
+        storage.update_metadata(&[renamed]).await.unwrap();
+        storage.delete_item(&items[1].id).await.unwrap();
+
+        let fetched = storage.get_item(&items[0].id).await.unwrap().unwrap();
+        assert_eq!(fetched.vector, items[0].vector);
+        assert_eq!(fetched.metadata["n"], 10);
+        assert!(storage.get_item(&items[1].id).await.unwrap().is_none());
+
+        // Everything reached disk as well
+        let page = ListOptions {
+            limit: Some(1),
+            offset: Some(1),
+            filter: None,
+        };
+        let listed = storage.list_items(Some(page)).await.unwrap();
+        let mut on_disk = storage.inner.list_items(None).await.unwrap();
+        on_disk.sort_by_key(|item| item.id);
+        assert_eq!(on_disk.len(), 2);
+        assert_eq!(listed[0].id, on_disk[1].id);
+        assert_eq!(
+            on_disk[0].metadata,
+            storage.list_items(None).await.unwrap()[0].metadata
+        );
+    }
+}
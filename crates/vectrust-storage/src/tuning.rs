@@ -0,0 +1,145 @@
+// Copyright 2024-2026 Andrey Vasilevsky <anvanster@gmail.com>
+// SPDX-License-Identifier: Apache-2.0
+
+//! RocksDB options for each [`TuningProfile`], and raw overrides on top.
+
+use rocksdb::{BlockBasedOptions, Cache, ColumnFamilyDescriptor, Options, SliceTransform, DB};
+use vectrust_core::*;
+
+const MB: usize = 1024 * 1024;
+
+/// Options to open a database with under `tuning`'s profile
+pub(crate) fn db_options(tuning: &RocksDbTuning) -> Options {
+    let mut opts = Options::default();
+    opts.create_if_missing(true);
+    opts.create_missing_column_families(true);
+    opts.set_level_compaction_dynamic_level_bytes(true);
+
+    let cores = std::thread::available_parallelism().map_or(4, |n| n.get()) as i32;
+    // (write buffer, buffers, target file, level base, background jobs, bytes per sync)
+    let (buffer, buffers, file, level_base, jobs, sync) = match tuning.profile {
+        TuningProfile::SmallIndex => (8 * MB, 2, 8 * MB, 32 * MB, 2, MB),
+        TuningProfile::Balanced => (64 * MB, 4, 64 * MB, 256 * MB, 4, 64 * MB),
+        TuningProfile::BulkIngest => (256 * MB, 6, 256 * MB, 1024 * MB, cores.max(4), 64 * MB),
+        TuningProfile::LowMemory => (4 * MB, 2, 4 * MB, 16 * MB, 1, MB / 2),
+    };
+    opts.set_write_buffer_size(buffer);
+    opts.set_max_write_buffer_number(buffers);
+    opts.set_target_file_size_base(file as u64);
+    opts.set_max_bytes_for_level_base(level_base as u64);
+    opts.set_max_background_jobs(jobs);
+    opts.set_bytes_per_sync(sync as u64);
+
+    match tuning.profile {
+        TuningProfile::BulkIngest => {
+            // Merge memtables before flushing and let level 0 pile up
+            // rather than stall writers while compaction catches up
+            opts.increase_parallelism(cores);
+            opts.set_min_write_buffer_number_to_merge(2);
+            opts.set_level_zero_file_num_compaction_trigger(8);
+            opts.set_level_zero_slowdown_writes_trigger(32);
+            opts.set_level_zero_stop_writes_trigger(64);
+        }
+        TuningProfile::LowMemory => {
+            opts.set_max_open_files(64);
+            opts.set_db_write_buffer_size(16 * MB);
+        }
+        TuningProfile::SmallIndex | TuningProfile::Balanced => {}
+    }
+    opts
+}
+
+/// Descriptors for `names`, the column families items are looked up in by
+/// id: `base` plus a block cache they share and bloom filters
+pub(crate) fn lookup_column_families(
+    tuning: &RocksDbTuning,
+    base: &Options,
+    names: &[&str],
+) -> Vec<ColumnFamilyDescriptor> {
+    let cache_bytes = tuning.block_cache_bytes.map_or(
+        match tuning.profile {
+            TuningProfile::SmallIndex => 8 * MB,
+            TuningProfile::Balanced => 64 * MB,
+            TuningProfile::BulkIngest => 256 * MB,
+            TuningProfile::LowMemory => 4 * MB,
+        },
+        |bytes| bytes as usize,
+    );
+    let cache = Cache::new_lru_cache(cache_bytes);
+
+    let mut table = BlockBasedOptions::default();
+    table.set_block_cache(&cache);
+    if tuning.bloom_bits_per_key > 0 {
+        table.set_bloom_filter(tuning.bloom_bits_per_key as f64, false);
+        // Keep filters and indexes in the cache, within its budget, with
+        // the newest files' pinned so lookups of fresh writes stay cheap
+        table.set_cache_index_and_filter_blocks(true);
+        table.set_pin_l0_filter_and_index_blocks_in_cache(true);
+    }
+
+    let mut opts = base.clone();
+    opts.set_block_based_table_factory(&table);
+    names
+        .iter()
+        .map(|name| ColumnFamilyDescriptor::new(*name, opts.clone()))
+        .collect()
+}
+
+/// Descriptor for `name`, a column family scanned by key prefixes of
+/// `prefix_len` bytes: `base` with bloom filters over the prefixes, so
+/// scans skip files and memtables without keys under theirs
+pub(crate) fn prefix_column_family(
+    tuning: &RocksDbTuning,
+    base: &Options,
+    name: &str,
+    prefix_len: usize,
+) -> ColumnFamilyDescriptor {
+    let mut opts = base.clone();
+    opts.set_prefix_extractor(SliceTransform::create_fixed_prefix(prefix_len));
+    if tuning.bloom_bits_per_key > 0 {
+        let mut table = BlockBasedOptions::default();
+        table.set_bloom_filter(tuning.bloom_bits_per_key as f64, false);
+        table.set_whole_key_filtering(false);
+        opts.set_block_based_table_factory(&table);
+        opts.set_memtable_prefix_bloom_ratio(0.1);
+    }
+    ColumnFamilyDescriptor::new(name, opts)
+}
+
+/// Descriptor for `name`, a column family of values too large to keep
+/// inline: `base` with values of 4 KiB or more kept in blob files, so
+/// compactions move small pointers rather than the values themselves
+pub(crate) fn blob_column_family(base: &Options, name: &str) -> ColumnFamilyDescriptor {
+    let mut opts = base.clone();
+    opts.set_enable_blob_files(true);
+    opts.set_min_blob_size(4096);
+    opts.set_enable_blob_gc(true);
+    ColumnFamilyDescriptor::new(name, opts)
+}
+
+/// Apply `tuning`'s raw overrides to the default column family and each
+/// of `column_families`
+pub(crate) fn apply_overrides(
+    db: &DB,
+    tuning: &RocksDbTuning,
+    column_families: &[&str],
+) -> Result<()> {
+    if tuning.options.is_empty() {
+        return Ok(());
+    }
+    let options: Vec<(&str, &str)> = tuning
+        .options
+        .iter()
+        .map(|(name, value)| (name.as_str(), value.as_str()))
+        .collect();
+    let invalid = |e: rocksdb::Error| VectraError::Storage {
+        message: format!("Invalid RocksDB option override: {}", e),
+    };
+    db.set_options(&options).map_err(invalid)?;
+    for name in column_families {
+        if let Some(cf) = db.cf_handle(name) {
+            db.set_options_cf(&cf, &options).map_err(invalid)?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,504 @@
+// Copyright 2024-2026 Andrey Vasilevsky <anvanster@gmail.com>
+// SPDX-License-Identifier: Apache-2.0
+
+//! A backend owned by one writer thread.
+//!
+//! [`SingleWriter`] moves another backend onto a dedicated thread and runs
+//! every call against it there, one at a time and in the order they were
+//! sent. Nothing else ever touches the wrapped backend, so its files and
+//! handles have a single owner and calls never wait on each other's locks,
+//! only in the queue. A read sees every write sent before it.
+//!
+//! Calls go through a [`WriterHandle`], itself a backend, so any number of
+//! tasks can send them without sharing a lock. Writes can also be queued
+//! without waiting for them: each returns a [`Completion`] to await when the
+//! outcome is needed.
+
+use async_trait::async_trait;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use tokio::sync::{mpsc, oneshot};
+use uuid::Uuid;
+use vectrust_core::*;
+
+/// Future of a call running on the writer, borrowing its backend
+pub type WriterFuture<'a, T> = Pin<Box<dyn Future<Output = Result<T>> + Send + 'a>>;
+
+type Job = Box<dyn for<'a> FnOnce(&'a mut dyn StorageBackend) -> WriterFuture<'a, ()> + Send>;
+
+enum Message {
+    Run(Job),
+    Stop,
+}
+
+/// A backend whose calls all run on one writer thread
+pub struct SingleWriter {
+    handle: WriterHandle,
+    thread: Option<std::thread::JoinHandle<()>>,
+}
+
+/// What the synchronous calls report, copied from the wrapped backend by
+/// the writer thread after each call it runs
+#[derive(Default)]
+struct Snapshot {
+    lock_waits: Mutex<LockWaitStats>,
+    quarantined: Mutex<Vec<QuarantinedItem>>,
+}
+
+impl Snapshot {
+    fn refresh(&self, storage: &dyn StorageBackend) {
+        *self.lock_waits.lock().unwrap() = storage.lock_waits();
+        *self.quarantined.lock().unwrap() = storage.quarantined();
+    }
+}
+
+impl SingleWriter {
+    /// Start a writer thread owning `inner`
+    pub fn new(inner: Box<dyn StorageBackend>) -> Result<Self> {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?;
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+        let snapshot = Arc::new(Snapshot::default());
+        snapshot.refresh(inner.as_ref());
+        let refreshed = snapshot.clone();
+        let thread = std::thread::Builder::new()
+            .name("vectrust-writer".to_string())
+            .spawn(move || {
+                let mut storage = inner;
+                runtime.block_on(async move {
+                    while let Some(Message::Run(job)) = receiver.recv().await {
+                        let _ = job(storage.as_mut()).await;
+                        refreshed.refresh(storage.as_ref());
+                    }
+                });
+            })?;
+        Ok(Self {
+            handle: WriterHandle { sender, snapshot },
+            thread: Some(thread),
+        })
+    }
+
+    /// A handle sending calls to the writer
+    pub fn handle(&self) -> WriterHandle {
+        self.handle.clone()
+    }
+}
+
+impl Drop for SingleWriter {
+    /// Let the writer finish what is queued and drop the wrapped backend,
+    /// so its files are closed once this returns
+    fn drop(&mut self) {
+        let _ = self.handle.sender.send(Message::Stop);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Sends calls to a [`SingleWriter`]'s thread. Calls sent after the writer
+/// is dropped fail.
+#[derive(Clone)]
+pub struct WriterHandle {
+    sender: mpsc::UnboundedSender<Message>,
+    snapshot: Arc<Snapshot>,
+}
+
+impl WriterHandle {
+    /// Queue `call` against the wrapped backend
+    pub fn submit<T, F>(&self, call: F) -> Completion<T>
+    where
+        T: Send + 'static,
+        F: for<'a> FnOnce(&'a mut dyn StorageBackend) -> WriterFuture<'a, T> + Send + 'static,
+    {
+        let (sender, receiver) = oneshot::channel();
+        self.run(move |storage| {
+            Box::pin(async move {
+                let _ = sender.send(call(storage).await);
+                Ok(())
+            })
+        });
+        Completion(receiver)
+    }
+
+    fn run<F>(&self, call: F)
+    where
+        F: for<'a> FnOnce(&'a mut dyn StorageBackend) -> WriterFuture<'a, ()> + Send + 'static,
+    {
+        // A writer that is gone drops the call, failing its completion
+        let _ = self.sender.send(Message::Run(Box::new(call)));
+    }
+
+    pub fn insert_items(&self, items: Vec<VectorItem>) -> Completion<()> {
+        self.submit(move |storage| Box::pin(async move { storage.insert_items(&items).await }))
+    }
+
+    pub fn update_items(&self, items: Vec<VectorItem>) -> Completion<()> {
+        self.submit(move |storage| Box::pin(async move { storage.update_items(&items).await }))
+    }
+
+    pub fn delete_items(&self, ids: Vec<Uuid>) -> Completion<()> {
+        self.submit(move |storage| {
+            Box::pin(async move {
+                for id in &ids {
+                    storage.delete_item(id).await?;
+                }
+                Ok(())
+            })
+        })
+    }
+}
+
+/// Outcome of a call queued on a [`SingleWriter`], once it has run
+pub struct Completion<T>(oneshot::Receiver<Result<T>>);
+
+impl<T> Future for Completion<T> {
+    type Output = Result<T>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        Pin::new(&mut self.0).poll(cx).map(|outcome| {
+            outcome.unwrap_or_else(|_| {
+                Err(VectraError::Storage {
+                    message: "The index writer has stopped".to_string(),
+                })
+            })
+        })
+    }
+}
+
+/// Calls taking borrowed arguments send copies of them to the writer.
+/// `list_matching` keeps the default, filtering a full listing here, as its
+/// predicate can't be sent. `lock_waits` and `quarantined` answer without
+/// waiting, as of the last call the writer finished.
+#[async_trait]
+impl StorageBackend for WriterHandle {
+    async fn exists(&self) -> bool {
+        self.submit(|storage| Box::pin(async move { Ok(storage.exists().await) }))
+            .await
+            .unwrap_or(false)
+    }
+
+    async fn create_index(&mut self, config: &CreateIndexConfig) -> Result<()> {
+        let config = config.clone();
+        self.submit(move |storage| Box::pin(async move { storage.create_index(&config).await }))
+            .await
+    }
+
+    async fn get_item(&self, id: &Uuid) -> Result<Option<VectorItem>> {
+        let id = *id;
+        self.submit(move |storage| Box::pin(async move { storage.get_item(&id).await }))
+            .await
+    }
+
+    async fn get_items(&self, ids: &[Uuid]) -> Result<Vec<Option<VectorItem>>> {
+        let ids = ids.to_vec();
+        self.submit(move |storage| Box::pin(async move { storage.get_items(&ids).await }))
+            .await
+    }
+
+    async fn insert_item(&mut self, item: &VectorItem) -> Result<()> {
+        let item = item.clone();
+        self.submit(move |storage| Box::pin(async move { storage.insert_item(&item).await }))
+            .await
+    }
+
+    async fn insert_items(&mut self, items: &[VectorItem]) -> Result<()> {
+        WriterHandle::insert_items(self, items.to_vec()).await
+    }
+
+    async fn update_item(&mut self, item: &VectorItem) -> Result<()> {
+        let item = item.clone();
+        self.submit(move |storage| Box::pin(async move { storage.update_item(&item).await }))
+            .await
+    }
+
+    async fn update_items(&mut self, items: &[VectorItem]) -> Result<()> {
+        WriterHandle::update_items(self, items.to_vec()).await
+    }
+
+    async fn update_metadata(&mut self, items: &[VectorItem]) -> Result<()> {
+        let items = items.to_vec();
+        self.submit(move |storage| Box::pin(async move { storage.update_metadata(&items).await }))
+            .await
+    }
+
+    async fn delete_item(&mut self, id: &Uuid) -> Result<()> {
+        let id = *id;
+        self.submit(move |storage| Box::pin(async move { storage.delete_item(&id).await }))
+            .await
+    }
+
+    async fn list_items(&self, options: Option<ListOptions>) -> Result<Vec<VectorItem>> {
+        self.submit(move |storage| Box::pin(async move { storage.list_items(options).await }))
+            .await
+    }
+
+    async fn query_items(&self, query: &Query) -> Result<Vec<QueryResult>> {
+        let query = query.clone();
+        self.submit(move |storage| Box::pin(async move { storage.query_items(&query).await }))
+            .await
+    }
+
+    async fn begin_transaction(&mut self) -> Result<()> {
+        self.submit(|storage| Box::pin(async move { storage.begin_transaction().await }))
+            .await
+    }
+
+    async fn commit_transaction(&mut self) -> Result<()> {
+        self.submit(|storage| Box::pin(async move { storage.commit_transaction().await }))
+            .await
+    }
+
+    async fn rollback_transaction(&mut self) -> Result<()> {
+        self.submit(|storage| Box::pin(async move { storage.rollback_transaction().await }))
+            .await
+    }
+
+    async fn delete_index(&mut self) -> Result<()> {
+        self.submit(|storage| Box::pin(async move { storage.delete_index().await }))
+            .await
+    }
+
+    async fn get_stats(&self) -> Result<IndexStats> {
+        self.submit(|storage| Box::pin(async move { storage.get_stats().await }))
+            .await
+    }
+
+    async fn compaction_stats(&self) -> Result<CompactionStats> {
+        self.submit(|storage| Box::pin(async move { storage.compaction_stats().await }))
+            .await
+    }
+
+    async fn compact(&mut self, max_bytes_per_sec: Option<u64>) -> Result<CompactionStats> {
+        self.submit(move |storage| {
+            Box::pin(async move { storage.compact(max_bytes_per_sec).await })
+        })
+        .await
+    }
+
+    async fn prepare_compaction(&self, max_bytes: u64) -> Result<u64> {
+        self.submit(move |storage| {
+            Box::pin(async move { storage.prepare_compaction(max_bytes).await })
+        })
+        .await
+    }
+
+    async fn set_metadata_encoding(&mut self, encoding: MetadataEncoding) -> Result<usize> {
+        self.submit(move |storage| {
+            Box::pin(async move { storage.set_metadata_encoding(encoding).await })
+        })
+        .await
+    }
+
+    fn record_access(&self, ids: &[Uuid]) {
+        let ids = ids.to_vec();
+        self.run(move |storage| {
+            storage.record_access(&ids);
+            Box::pin(async { Ok(()) })
+        });
+    }
+
+    async fn retier(&mut self, policy: &TieringPolicy) -> Result<TieringStats> {
+        let policy = policy.clone();
+        self.submit(move |storage| Box::pin(async move { storage.retier(&policy).await }))
+            .await
+    }
+
+    async fn compaction_policy(&self) -> Result<Option<CompactionPolicy>> {
+        self.submit(|storage| Box::pin(async move { storage.compaction_policy().await }))
+            .await
+    }
+
+    async fn embedding_model(&self) -> Result<Option<EmbeddingModel>> {
+        self.submit(|storage| Box::pin(async move { storage.embedding_model().await }))
+            .await
+    }
+
+    async fn limits(&self) -> Result<Option<IndexLimits>> {
+        self.submit(|storage| Box::pin(async move { storage.limits().await }))
+            .await
+    }
+
+    async fn vector_transform(&self) -> Result<Option<VectorTransform>> {
+        self.submit(|storage| Box::pin(async move { storage.vector_transform().await }))
+            .await
+    }
+
+    async fn vector_validation(&self) -> Result<Option<VectorValidation>> {
+        self.submit(|storage| Box::pin(async move { storage.vector_validation().await }))
+            .await
+    }
+
+    async fn namespace_field(&self) -> Result<Option<String>> {
+        self.submit(|storage| Box::pin(async move { storage.namespace_field().await }))
+            .await
+    }
+
+    async fn list_namespace(&self, namespace: &str) -> Result<Vec<VectorItem>> {
+        let namespace = namespace.to_string();
+        self.submit(move |storage| {
+            Box::pin(async move { storage.list_namespace(&namespace).await })
+        })
+        .await
+    }
+
+    async fn list_range(
+        &self,
+        field: &str,
+        range: &NumericRange,
+    ) -> Result<Option<Vec<VectorItem>>> {
+        let field = field.to_string();
+        let range = *range;
+        self.submit(move |storage| {
+            Box::pin(async move { storage.list_range(&field, &range).await })
+        })
+        .await
+    }
+
+    async fn range_fields(&self) -> Result<Vec<String>> {
+        self.submit(|storage| Box::pin(async move { storage.range_fields().await }))
+            .await
+    }
+
+    async fn composite_indexes(&self) -> Result<Vec<CompositeIndex>> {
+        self.submit(|storage| Box::pin(async move { storage.composite_indexes().await }))
+            .await
+    }
+
+    async fn add_composite_index(&mut self, index: &CompositeIndex) -> Result<()> {
+        let index = index.clone();
+        self.submit(move |storage| {
+            Box::pin(async move { storage.add_composite_index(&index).await })
+        })
+        .await
+    }
+
+    async fn list_composite(
+        &self,
+        pinned: &[(String, serde_json::Value)],
+    ) -> Result<Option<Vec<VectorItem>>> {
+        let pinned = pinned.to_vec();
+        self.submit(move |storage| Box::pin(async move { storage.list_composite(&pinned).await }))
+            .await
+    }
+
+    async fn is_read_only(&self) -> Result<bool> {
+        self.submit(|storage| Box::pin(async move { storage.is_read_only().await }))
+            .await
+    }
+
+    fn set_flush_interval(&self, operations: u32) {
+        self.run(move |storage| {
+            storage.set_flush_interval(operations);
+            Box::pin(async { Ok(()) })
+        });
+    }
+
+    fn lock_waits(&self) -> LockWaitStats {
+        self.snapshot.lock_waits.lock().unwrap().clone()
+    }
+
+    fn reset_lock_waits(&self) {
+        self.run(|storage| {
+            storage.reset_lock_waits();
+            Box::pin(async { Ok(()) })
+        });
+    }
+
+    async fn quarantine_damaged(&self) -> Result<Vec<QuarantinedItem>> {
+        self.submit(|storage| Box::pin(async move { storage.quarantine_damaged().await }))
+            .await
+    }
+
+    fn quarantined(&self) -> Vec<QuarantinedItem> {
+        self.snapshot.quarantined.lock().unwrap().clone()
+    }
+
+    async fn verify_secondary_indexes(&self) -> Result<IndexDivergence> {
+        self.submit(|storage| Box::pin(async move { storage.verify_secondary_indexes().await }))
+            .await
+    }
+
+    async fn rebuild_secondary_indexes(&mut self) -> Result<()> {
+        self.submit(|storage| Box::pin(async move { storage.rebuild_secondary_indexes().await }))
+            .await
+    }
+
+    async fn set_payloads(&mut self, payloads: &[(Uuid, Option<Vec<u8>>)]) -> Result<()> {
+        let payloads = payloads.to_vec();
+        self.submit(move |storage| Box::pin(async move { storage.set_payloads(&payloads).await }))
+            .await
+    }
+
+    async fn get_payloads(&self, ids: &[Uuid]) -> Result<Vec<Option<Vec<u8>>>> {
+        let ids = ids.to_vec();
+        self.submit(move |storage| Box::pin(async move { storage.get_payloads(&ids).await }))
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_single_writer_applies_calls_in_order() {
+        let temp_dir = TempDir::new().unwrap();
+        let inner = crate::Storage::auto_detect(temp_dir.path(), "index.json").unwrap();
+        let writer = SingleWriter::new(inner).unwrap();
+        let mut storage = writer.handle();
+        storage
+            .create_index(&CreateIndexConfig::default())
+            .await
+            .unwrap();
+
+        // Writes queued from several tasks land without waiting on each other
+        let handle = writer.handle();
+        let items: Vec<VectorItem> = (0..4)
+            .map(|i| VectorItem {
+                id: Uuid::new_v4(),
+                vector: vec![i as f32, 1.0],
+                metadata: serde_json::json!({ "n": i }),
+                ..Default::default()
+            })
+            .collect();
+        let completions: Vec<_> = items
+            .iter()
+            .map(|item| handle.insert_items(vec![item.clone()]))
+            .collect();
+        let deleted = handle.delete_items(vec![items[3].id]);
+        let tasks: Vec<_> = completions.into_iter().map(tokio::spawn).collect();
+        for task in tasks {
+            task.await.unwrap().unwrap();
+        }
+        deleted.await.unwrap();
+
+        // A duplicate fails through its completion, leaving the rest alone
+        let duplicate = handle.insert_items(vec![items[0].clone()]);
+        assert!(duplicate.await.is_err());
+
+        let listed = storage.list_items(None).await.unwrap();
+        assert_eq!(listed.len(), 3);
+        // Answered from the writer's last snapshot, without queueing
+        assert!(storage.quarantined().is_empty());
+        assert!(storage.get_item(&items[3].id).await.unwrap().is_none());
+        assert_eq!(
+            storage
+                .get_item(&items[1].id)
+                .await
+                .unwrap()
+                .unwrap()
+                .vector,
+            items[1].vector
+        );
+
+        // Dropping the writer closes the index; the handle's calls then fail
+        drop(writer);
+        assert!(handle.insert_items(Vec::new()).await.is_err());
+        let reopened = crate::Storage::auto_detect(temp_dir.path(), "index.json").unwrap();
+        assert_eq!(reopened.list_items(None).await.unwrap().len(), 3);
+    }
+}
@@ -0,0 +1,156 @@
+// Copyright 2024-2026 Andrey Vasilevsky <anvanster@gmail.com>
+// SPDX-License-Identifier: Apache-2.0
+
+//! Alias names for index directories.
+//!
+//! Applications open an index by alias (`prod-docs`) while the directory
+//! behind it changes: build a replacement index next to the live one, then
+//! [`IndexAliases::set`] switches the alias in a single rename. Readers see
+//! either the old target or the new one, never a partial file. Handles
+//! opened before the switch keep using the old index until reopened.
+
+use crate::LocalIndex;
+use fs2::FileExt;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use vectrust_core::*;
+
+/// File inside the root that maps alias names to index directories
+const ALIASES_FILE: &str = "aliases.json";
+/// Serializes read-modify-write of the aliases file across processes
+const ALIASES_LOCK: &str = "aliases.lock";
+
+/// Alias table stored in a root directory that holds the aliased indexes.
+///
+/// Relative targets resolve against the root, so the whole root can be
+/// moved or copied without breaking its aliases.
+#[derive(Debug, Clone)]
+pub struct IndexAliases {
+    root: PathBuf,
+}
+
+impl IndexAliases {
+    pub fn new<P: AsRef<Path>>(root: P) -> Self {
+        Self {
+            root: root.as_ref().to_path_buf(),
+        }
+    }
+
+    /// Directory holding the alias table; relative targets resolve against it
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Point `alias` at the index directory `target`, returning the previous target
+    pub fn set<P: AsRef<Path>>(&self, alias: &str, target: P) -> Result<Option<PathBuf>> {
+        if alias.is_empty() {
+            return Err(VectraError::Storage {
+                message: "Alias name must not be empty".to_string(),
+            });
+        }
+        let target = target.as_ref();
+        if !self.root.join(target).is_dir() {
+            return Err(VectraError::IndexNotFound {
+                path: target.to_string_lossy().to_string(),
+            });
+        }
+
+        self.update(|aliases| aliases.insert(alias.to_string(), target.to_path_buf()))
+            .map(|previous| previous.map(|p| self.root.join(p)))
+    }
+
+    /// Remove `alias`, returning the target it pointed at
+    pub fn remove(&self, alias: &str) -> Result<Option<PathBuf>> {
+        self.update(|aliases| aliases.remove(alias))
+            .map(|previous| previous.map(|p| self.root.join(p)))
+    }
+
+    /// Index directory `alias` currently points at
+    pub fn resolve(&self, alias: &str) -> Result<Option<PathBuf>> {
+        Ok(self.read()?.remove(alias).map(|p| self.root.join(p)))
+    }
+
+    /// Every alias and its target, as stored
+    pub fn list(&self) -> Result<BTreeMap<String, PathBuf>> {
+        self.read()
+    }
+
+    /// Open the index `alias` currently points at
+    pub fn open(&self, alias: &str) -> Result<LocalIndex> {
+        let target = self
+            .resolve(alias)?
+            .ok_or_else(|| VectraError::IndexNotFound {
+                path: format!("alias '{}'", alias),
+            })?;
+        LocalIndex::new(target, None)
+    }
+
+    fn read(&self) -> Result<BTreeMap<String, PathBuf>> {
+        match std::fs::read(self.root.join(ALIASES_FILE)) {
+            Ok(bytes) => Ok(serde_json::from_slice(&bytes)?),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(BTreeMap::new()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Apply `change` under the lock and publish the result with a rename
+    fn update<T>(&self, change: impl FnOnce(&mut BTreeMap<String, PathBuf>) -> T) -> Result<T> {
+        std::fs::create_dir_all(&self.root)?;
+        let lock = std::fs::OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(self.root.join(ALIASES_LOCK))?;
+        lock.lock_exclusive()?;
+
+        let mut aliases = self.read()?;
+        let result = change(&mut aliases);
+        let temp_path = self.root.join(format!("{}.tmp", ALIASES_FILE));
+        std::fs::write(&temp_path, serde_json::to_vec_pretty(&aliases)?)?;
+        std::fs::rename(&temp_path, self.root.join(ALIASES_FILE))?;
+
+        lock.unlock()?;
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_alias_switch() {
+        let root = TempDir::new().unwrap();
+        let aliases = IndexAliases::new(root.path());
+
+        for (dir, x) in [("docs-v1", 1.0), ("docs-v2", 2.0)] {
+            let index = LocalIndex::new(root.path().join(dir), None).unwrap();
+            index.create_index(None).await.unwrap();
+            let item = VectorItem {
+                vector: vec![x, 0.0],
+                ..Default::default()
+            };
+            index.insert_item(item).await.unwrap();
+        }
+
+        assert!(aliases.set("prod-docs", "missing").is_err());
+        assert_eq!(aliases.set("prod-docs", "docs-v1").unwrap(), None);
+        let index = aliases.open("prod-docs").unwrap();
+        let items = index.list_items(None).await.unwrap();
+        assert_eq!(items[0].vector, vec![1.0, 0.0]);
+        drop(index);
+
+        // Switching returns the old target so it can be cleaned up
+        let previous = aliases.set("prod-docs", "docs-v2").unwrap();
+        assert_eq!(previous, Some(root.path().join("docs-v1")));
+        let index = aliases.open("prod-docs").unwrap();
+        let items = index.list_items(None).await.unwrap();
+        assert_eq!(items[0].vector, vec![2.0, 0.0]);
+
+        assert_eq!(aliases.list().unwrap().len(), 1);
+        assert!(aliases.remove("prod-docs").unwrap().is_some());
+        assert!(aliases.resolve("prod-docs").unwrap().is_none());
+        assert!(aliases.open("prod-docs").is_err());
+    }
+}
@@ -0,0 +1,633 @@
+// Copyright 2024-2026 Andrey Vasilevsky <anvanster@gmail.com>
+// SPDX-License-Identifier: Apache-2.0
+
+//! Per-query choice between exact scoring and the HNSW graph.
+//!
+//! With [`RuntimeConfig::ann`] set, [`LocalIndex::execute_query`] plans
+//! each query once its filter has run: few candidates, or rankings that
+//! depend on more than similarity, are scored exactly; otherwise the
+//! graph supplies a shortlist that is scored exactly in their place. The
+//! plan is recorded with the query's timings.
+//!
+//! Writes don't wait on the graph: written items are queued, and a
+//! background task adds them to it in batches. Until it has, shortlists
+//! include every queued item, so new items are found all the same.
+
+use crate::LocalIndex;
+#[cfg(feature = "ann")]
+use std::collections::VecDeque;
+#[cfg(feature = "ann")]
+use std::sync::{Arc, Mutex};
+use vectrust_core::*;
+
+/// Queued items added to the graph per hold of its lock
+#[cfg(feature = "ann")]
+const APPLY_BATCH: usize = 256;
+
+#[cfg(feature = "ann")]
+const TRACING_TARGET: &str = "vectrust::ann";
+
+/// How a query will be scored, and why
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct SearchPlan {
+    pub strategy: SearchStrategy,
+    pub reason: &'static str,
+    /// Graph results to take before filtering, for [`SearchStrategy::Ann`]
+    pub shortlist: usize,
+}
+
+impl SearchPlan {
+    fn exact(reason: &'static str) -> Self {
+        Self {
+            strategy: SearchStrategy::Exact,
+            reason,
+            shortlist: 0,
+        }
+    }
+}
+
+/// Plan `query`, which `matched` of the index's `indexed` items passed
+/// the filter of. `scored` is whether a scoring hook reranks the results.
+pub(crate) fn plan(
+    config: Option<&AnnConfig>,
+    query: &Query,
+    scored: bool,
+    matched: usize,
+    indexed: usize,
+) -> SearchPlan {
+    let Some(config) = config else {
+        return SearchPlan::exact("approximate search is off");
+    };
+    if cfg!(not(feature = "ann")) {
+        return SearchPlan::exact("built without the ann feature");
+    }
+    if query.vector.is_none() || query.text.is_some() {
+        return SearchPlan::exact("keyword ranking needs every candidate");
+    }
+    if scored || !query.boosts.is_empty() || query.recency.is_some() {
+        return SearchPlan::exact("ranking depends on more than similarity");
+    }
+    if query.search_after.is_some() {
+        return SearchPlan::exact("cursor pages need the full ranking");
+    }
+
+    let window = query.window().max(1);
+    if matched <= config.exact_threshold || matched < window.saturating_mul(config.exact_k_factor) {
+        return SearchPlan::exact("few enough candidates to score exactly");
+    }
+    // Widen the shortlist by the share of the index the filter excludes,
+    // so enough of it survives the filter
+    let shortlist =
+        (window as u128 * config.oversample.max(1) as u128 * indexed.max(matched) as u128)
+            .div_ceil(matched as u128);
+    if shortlist >= matched as u128 {
+        return SearchPlan::exact("filter too selective for the graph");
+    }
+    SearchPlan {
+        strategy: SearchStrategy::Ann,
+        reason: "many candidates",
+        shortlist: shortlist as usize,
+    }
+}
+
+/// Items the graph returned for a query
+pub(crate) struct Shortlist {
+    pub ids: std::collections::HashSet<uuid::Uuid>,
+    /// Whether a score floor ended the walk before the shortlist filled
+    pub exhausted: bool,
+}
+
+/// The graph and the settings it was built with
+#[cfg(feature = "ann")]
+pub(crate) struct AnnIndex {
+    graph: vectrust_index::HnswIndex,
+    config: HnswConfig,
+    /// Items written since the graph was built and not yet added to it,
+    /// oldest first
+    pending: VecDeque<PendingItem>,
+    /// Whether a task is adding `pending` to the graph
+    applying: bool,
+}
+
+/// A written item queued for the graph
+#[cfg(feature = "ann")]
+struct PendingItem {
+    id: uuid::Uuid,
+    vector: Vec<f32>,
+    queued_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Add queued items to the graph in batches until none are left
+#[cfg(feature = "ann")]
+async fn apply_pending(ann_index: Arc<Mutex<Option<AnnIndex>>>, threads: Option<usize>) {
+    loop {
+        let batch_index = ann_index.clone();
+        let applied = crate::scoring_pool::run(threads, move || {
+            let mut ann_index = batch_index.lock().unwrap();
+            let Some(index) = ann_index.as_mut() else {
+                return false;
+            };
+            if index.pending.is_empty() {
+                index.applying = false;
+                return false;
+            }
+            let batch = index.pending.len().min(APPLY_BATCH);
+            for item in index.pending.drain(..batch).collect::<Vec<_>>() {
+                if let Err(e) = index.graph.insert(item.id, &item.vector) {
+                    tracing::warn!(target: TRACING_TARGET, id = %item.id, "failed to add item to the HNSW graph: {}", e);
+                }
+            }
+            true
+        })
+        .await;
+        match applied {
+            Ok(true) => continue,
+            Ok(false) => break,
+            Err(e) => {
+                // Queued items stay in shortlists, and the next write
+                // starts another task
+                tracing::warn!(target: TRACING_TARGET, "failed to update the HNSW graph: {}", e);
+                if let Some(index) = ann_index.lock().unwrap().as_mut() {
+                    index.applying = false;
+                }
+                break;
+            }
+        }
+    }
+}
+
+impl LocalIndex {
+    /// Narrow `candidates` to the graph's shortlist when `plan` walks the
+    /// graph, building it first if needed. Falls back to exact scoring
+    /// when too little of the shortlist passed the filter to fill the page.
+    pub(crate) async fn apply_search_plan(
+        &self,
+        storage: &dyn StorageBackend,
+        plan: SearchPlan,
+        query: &Query,
+        distance: Distance,
+        mut candidates: Vec<VectorItem>,
+    ) -> Result<(SearchPlan, Vec<VectorItem>)> {
+        if plan.strategy != SearchStrategy::Ann {
+            return Ok((plan, candidates));
+        }
+        let Some(shortlist) = self.ann_shortlist(storage, plan, query, distance).await? else {
+            return Ok((plan, candidates));
+        };
+        let kept = candidates
+            .iter()
+            .filter(|item| shortlist.ids.contains(&item.id))
+            .count();
+        if kept < query.window() && !shortlist.exhausted {
+            return Ok((
+                SearchPlan::exact("graph shortlist too small after the filter"),
+                candidates,
+            ));
+        }
+        candidates.retain(|item| shortlist.ids.contains(&item.id));
+        Ok((plan, candidates))
+    }
+
+    /// The graph's shortlist for a filtered `query`, taken before the
+    /// filter runs, when `estimated` of the index's `indexed` items are
+    /// expected to pass it and that many would be worth walking the graph
+    /// for. `None` when every item should be loaded and filtered instead,
+    /// including when too little of the shortlist passes the filter to
+    /// fill the page.
+    pub(crate) async fn post_filter_candidates(
+        &self,
+        storage: &dyn StorageBackend,
+        query: &Query,
+        scored: bool,
+        estimated: usize,
+        indexed: usize,
+        distance: Distance,
+    ) -> Result<Option<(SearchPlan, Vec<VectorItem>)>> {
+        let Some(filter) = &query.filter else {
+            return Ok(None);
+        };
+        let unfiltered = Query {
+            filter: None,
+            ..query.clone()
+        };
+        let config = self.runtime_config.lock().unwrap().ann.clone();
+        let plan = plan(config.as_ref(), &unfiltered, scored, estimated, indexed);
+        if plan.strategy != SearchStrategy::Ann {
+            return Ok(None);
+        }
+        let Some(shortlist) = self
+            .ann_shortlist(storage, plan, &unfiltered, distance)
+            .await?
+        else {
+            return Ok(None);
+        };
+        let ids: Vec<uuid::Uuid> = shortlist.ids.into_iter().collect();
+        let items: Vec<VectorItem> = storage
+            .get_items(&ids)
+            .await?
+            .into_iter()
+            .flatten()
+            .collect();
+        // Errors a strict filter raises are reported when the query
+        // filters these for real
+        let check = self.filter_check(filter.clone());
+        let kept = items.iter().filter(|item| check.matches(item)).count();
+        if kept < query.window() && !shortlist.exhausted {
+            return Ok(None);
+        }
+        Ok(Some((plan, items)))
+    }
+
+    /// The graph's `plan.shortlist` nearest items to `query`, building the
+    /// graph first if needed, or `None` when approximate search is off
+    pub(crate) async fn ann_shortlist(
+        &self,
+        storage: &dyn StorageBackend,
+        plan: SearchPlan,
+        query: &Query,
+        distance: Distance,
+    ) -> Result<Option<Shortlist>> {
+        #[cfg(feature = "ann")]
+        {
+            let (ann, threads) = {
+                let runtime_config = self.runtime_config.lock().unwrap();
+                (runtime_config.ann.clone(), runtime_config.scoring_threads)
+            };
+            if let Some(config) = ann {
+                // A score floor bounds the walk, so a query with nothing
+                // relevant stops early instead of filling the shortlist
+                let bound = query
+                    .min_score
+                    .and_then(|min| VectorOps::distance_bound(min, distance.metric()))
+                    .unwrap_or(f32::INFINITY);
+                let config = HnswConfig {
+                    distance_metric: distance.metric().clone(),
+                    ..config.hnsw
+                };
+                let stale = self
+                    .ann_index
+                    .lock()
+                    .unwrap()
+                    .as_ref()
+                    .is_none_or(|index| index.config != config);
+                if stale {
+                    // Writes wait on the storage lock held by the caller, so
+                    // none are missed between listing and caching the graph
+                    let items = storage.list_items(None).await?;
+                    let hnsw = config.clone();
+                    let graph = crate::scoring_pool::run(threads, move || {
+                        let mut graph =
+                            vectrust_index::HnswIndex::new(hnsw)?.with_distance(distance);
+                        for item in &items {
+                            graph.insert(item.id, &item.vector)?;
+                        }
+                        Ok::<_, VectraError>(graph)
+                    })
+                    .await??;
+                    *self.ann_index.lock().unwrap() = Some(AnnIndex {
+                        graph,
+                        config,
+                        pending: VecDeque::new(),
+                        applying: false,
+                    });
+                }
+
+                let vector = query.vector.clone().unwrap_or_default();
+                let ann_index = self.ann_index.clone();
+                let size = plan.shortlist;
+                let freshness = query.freshness;
+                let (ids, found) = crate::scoring_pool::run(threads, move || {
+                    let ann_index = ann_index.lock().unwrap();
+                    let Some(index) = ann_index.as_ref() else {
+                        return Ok((Vec::new(), 0));
+                    };
+                    let mut ids: Vec<uuid::Uuid> = index
+                        .graph
+                        .search_within(&vector, size, bound)?
+                        .into_iter()
+                        .map(|(id, _)| id)
+                        .collect();
+                    let found = ids.len();
+                    // Items still queued for the graph are scored exactly
+                    if freshness == Freshness::Strict {
+                        ids.extend(index.pending.iter().map(|item| item.id));
+                    }
+                    Ok::<_, VectraError>((ids, found))
+                })
+                .await??;
+                // A bounded walk that ended short found everything above the
+                // floor, so a small shortlist is the answer, not a miss
+                let exhausted = bound.is_finite() && found < size;
+                return Ok(Some(Shortlist {
+                    ids: ids.into_iter().collect(),
+                    exhausted,
+                }));
+            }
+        }
+        let _ = (storage, plan, query, distance);
+        Ok(None)
+    }
+
+    /// Queue written items for the graph, if one is built, and start a
+    /// task adding them unless one is running. Removed items stay in the
+    /// graph and are dropped when a shortlist meets the stored candidates.
+    pub(crate) fn index_ann(&self, items: &[VectorItem]) -> Result<()> {
+        #[cfg(feature = "ann")]
+        {
+            let threads = self.runtime_config.lock().unwrap().scoring_threads;
+            let mut ann_index = self.ann_index.lock().unwrap();
+            if let Some(index) = ann_index.as_mut() {
+                match tokio::runtime::Handle::try_current() {
+                    Ok(runtime) => {
+                        let queued_at = chrono::Utc::now();
+                        index.pending.extend(items.iter().map(|item| PendingItem {
+                            id: item.id,
+                            vector: item.vector.clone(),
+                            queued_at,
+                        }));
+                        if !index.applying && !index.pending.is_empty() {
+                            index.applying = true;
+                            runtime.spawn(apply_pending(self.ann_index.clone(), threads));
+                        }
+                    }
+                    // Nothing to run the task on, so add them now
+                    Err(_) => {
+                        for item in items {
+                            index.graph.insert(item.id, &item.vector)?;
+                        }
+                    }
+                }
+            }
+        }
+        let _ = items;
+        Ok(())
+    }
+
+    /// Written items waiting to be added to the HNSW graph, and since when.
+    /// Queries walking the graph score these exactly unless they ask for
+    /// [`Freshness::Fast`].
+    pub fn ann_lag(&self) -> AnnLag {
+        #[cfg(feature = "ann")]
+        if let Some(index) = self.ann_index.lock().unwrap().as_ref() {
+            return AnnLag {
+                pending: index.pending.len(),
+                oldest_pending: index.pending.front().map(|item| item.queued_at),
+            };
+        }
+        AnnLag::default()
+    }
+
+    /// Drop the graph, to be rebuilt by the next query that walks it
+    pub(crate) fn reset_ann_index(&self) {
+        #[cfg(feature = "ann")]
+        self.ann_index.lock().unwrap().take();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn query(top_k: usize) -> Query {
+        Query {
+            vector: Some(vec![1.0, 0.0]),
+            top_k,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_plan() {
+        let config = AnnConfig::default();
+        let config = Some(&config);
+        assert_eq!(
+            plan(None, &query(10), false, 50_000, 50_000).reason,
+            "approximate search is off"
+        );
+
+        // A filter leaving a few hundred candidates is scored exactly
+        let small = plan(config, &query(10), false, 300, 50_000);
+        assert_eq!(small.strategy, SearchStrategy::Exact);
+        // So is a large k relative to the candidates
+        assert_eq!(
+            plan(config, &query(500), false, 4000, 50_000).strategy,
+            SearchStrategy::Exact
+        );
+        // And a scoring hook, which can reorder anything
+        assert_eq!(
+            plan(config, &query(10), true, 50_000, 50_000).strategy,
+            SearchStrategy::Exact
+        );
+
+        let wide = plan(config, &query(10), false, 50_000, 50_000);
+        if cfg!(feature = "ann") {
+            assert_eq!(wide.strategy, SearchStrategy::Ann);
+            assert_eq!(wide.shortlist, 40);
+            // A filter keeping a tenth of the index widens the shortlist tenfold
+            assert_eq!(plan(config, &query(10), false, 5000, 50_000).shortlist, 400);
+            // Beyond that, walking the graph would visit more than scoring does
+            let narrow = plan(config, &query(10), false, 1500, 500_000);
+            assert_eq!(narrow.strategy, SearchStrategy::Exact);
+        }
+    }
+
+    #[cfg(feature = "ann")]
+    #[tokio::test]
+    async fn test_adaptive_query_strategy() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let index = LocalIndex::new(dir.path(), None).unwrap();
+        index.create_index(None).await.unwrap();
+        let items: Vec<VectorItem> = (0..300)
+            .map(|i| {
+                let angle = i as f32 * 0.7;
+                VectorItem {
+                    id: uuid::Uuid::from_u128(i + 1),
+                    vector: vec![angle.cos(), angle.sin(), (i % 13) as f32 / 13.0],
+                    metadata: serde_json::json!({ "group": i % 20 }),
+                    ..Default::default()
+                }
+            })
+            .collect();
+        index.insert_items(items.clone()).await.unwrap();
+
+        let log = dir.path().join("queries.jsonl");
+        let mut config = index.runtime_config();
+        config.slow_query_log = Some(SlowQueryLog {
+            threshold_ms: 0,
+            path: Some(log.clone()),
+        });
+        config.ann = Some(AnnConfig {
+            exact_threshold: 50,
+            ..Default::default()
+        });
+        index.set_runtime_config(config).await.unwrap();
+
+        let target = items[150].vector.clone();
+        let results = index
+            .query_items(target.clone(), Some(3), None)
+            .await
+            .unwrap();
+        assert_eq!(results[0].item.id, items[150].id);
+        let filtered = index
+            .query_items(
+                target.clone(),
+                Some(3),
+                Some(serde_json::json!({ "group": 10 })),
+            )
+            .await
+            .unwrap();
+        assert_eq!(filtered[0].item.id, items[150].id);
+
+        // Written after the graph was built
+        let late = VectorItem {
+            vector: vec![-1.0, 0.0, 0.5],
+            ..Default::default()
+        };
+        let late = index.insert_item(late).await.unwrap();
+        let results = index
+            .query_items(late.vector.clone(), Some(1), None)
+            .await
+            .unwrap();
+        assert_eq!(results[0].item.id, late.id);
+
+        let strategies: Vec<String> = std::fs::read_to_string(&log)
+            .unwrap()
+            .lines()
+            .map(|line| {
+                let entry: serde_json::Value = serde_json::from_str(line).unwrap();
+                entry["strategy"].as_str().unwrap().to_string()
+            })
+            .collect();
+        assert_eq!(strategies, ["ann", "exact", "ann"]);
+    }
+
+    #[cfg(feature = "ann")]
+    #[tokio::test]
+    async fn test_queued_graph_updates() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let index = LocalIndex::new(dir.path(), None).unwrap();
+        index.create_index(None).await.unwrap();
+        let item = |i: u32| {
+            let angle = i as f32 * 0.7;
+            VectorItem {
+                vector: vec![angle.cos(), angle.sin(), (i % 13) as f32 / 13.0],
+                ..Default::default()
+            }
+        };
+        index
+            .insert_items((0..300).map(item).collect())
+            .await
+            .unwrap();
+        let mut config = index.runtime_config();
+        config.ann = Some(AnnConfig {
+            exact_threshold: 50,
+            ..Default::default()
+        });
+        index.set_runtime_config(config).await.unwrap();
+        let nearest = |vector: Vec<f32>| {
+            let index = &index;
+            async move {
+                let query = Query {
+                    vector: Some(vector),
+                    top_k: 1,
+                    ..Default::default()
+                };
+                index.explain_query(&query).await.unwrap()
+            }
+        };
+        // Builds the graph
+        assert_eq!(
+            nearest(vec![1.0, 0.0, 0.0]).await.strategy,
+            SearchStrategy::Ann
+        );
+
+        // Held in the queue while a task is marked as running
+        index.ann_index.lock().unwrap().as_mut().unwrap().applying = true;
+        let late = index
+            .insert_items((300..340).map(item).collect())
+            .await
+            .unwrap();
+        let lag = index.ann_lag();
+        assert_eq!(lag.pending, 40);
+        assert!(lag.oldest_pending.is_some());
+        assert_eq!(index.health().await.unwrap().ann_lag, lag);
+        assert_eq!(index.get_stats().await.unwrap().ann_lag, lag);
+        let explanation = nearest(late[7].vector.clone()).await;
+        assert_eq!(explanation.strategy, SearchStrategy::Ann);
+        assert_eq!(explanation.results[0].item.id, late[7].id);
+        // Fast queries skip the queue
+        let fast = Query {
+            vector: Some(late[7].vector.clone()),
+            top_k: 1,
+            freshness: Freshness::Fast,
+            ..Default::default()
+        };
+        let results = index.execute_query(&fast, None).await.unwrap();
+        assert_ne!(results[0].item.id, late[7].id);
+
+        // The next write starts a task, which empties the queue
+        index.ann_index.lock().unwrap().as_mut().unwrap().applying = false;
+        let last = index.insert_item(item(340)).await.unwrap();
+        for _ in 0..500 {
+            if index.ann_lag().pending == 0 {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        assert_eq!(index.ann_lag(), AnnLag::default());
+        assert_eq!(
+            nearest(late[7].vector.clone()).await.results[0].item.id,
+            late[7].id
+        );
+        assert_eq!(
+            nearest(last.vector.clone()).await.results[0].item.id,
+            last.id
+        );
+    }
+
+    #[tokio::test]
+    async fn test_min_score() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let index = LocalIndex::new(dir.path(), None).unwrap();
+        index.create_index(None).await.unwrap();
+        let items: Vec<VectorItem> = (0..300)
+            .map(|i| {
+                let angle = i as f32 * 0.02;
+                VectorItem {
+                    id: uuid::Uuid::from_u128(i + 1),
+                    vector: vec![angle.cos(), angle.sin(), 0.0],
+                    ..Default::default()
+                }
+            })
+            .collect();
+        index.insert_items(items.clone()).await.unwrap();
+
+        let floored = |vector: Vec<f32>, min_score: f32| Query {
+            vector: Some(vector),
+            top_k: 10,
+            min_score: Some(min_score),
+            ..Default::default()
+        };
+        let close = floored(items[150].vector.clone(), 0.999);
+        let nothing = floored(vec![0.0, 0.0, -1.0], 0.5);
+        // Scored exactly, then by walking the graph
+        for ann in [None, Some(AnnConfig::default())] {
+            let mut config = index.runtime_config();
+            config.ann = ann.map(|ann| AnnConfig {
+                exact_threshold: 50,
+                ..ann
+            });
+            index.set_runtime_config(config).await.unwrap();
+
+            let results = index.execute_query(&close, None).await.unwrap();
+            assert_eq!(results[0].item.id, items[150].id);
+            assert!(results.len() < 10);
+            assert!(results.iter().all(|result| result.score >= 0.999));
+            assert!(index
+                .execute_query(&nothing, None)
+                .await
+                .unwrap()
+                .is_empty());
+        }
+    }
+}
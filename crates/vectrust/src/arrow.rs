@@ -0,0 +1,381 @@
+// Copyright 2024-2026 Andrey Vasilevsky <anvanster@gmail.com>
+// SPDX-License-Identifier: Apache-2.0
+
+//! Arrow `RecordBatch` and IPC interchange, behind the `arrow` feature.
+//!
+//! A batch holds one item per row: an optional `id` column of UUID
+//! strings, a `vector` column of `FixedSizeList<Float32>`, and one column
+//! per top-level metadata field. Vectors are read straight from the
+//! list's `f32` values buffer and written into a single contiguous one,
+//! so Polars, pandas and other Arrow consumers exchange them without
+//! per-item conversion. Metadata values that aren't booleans, numbers or
+//! strings are written as JSON text in a column tagged
+//! [`JSON_COLUMN_METADATA`], which import parses back.
+
+pub use arrow_array::RecordBatch;
+
+use crate::LocalIndex;
+use arrow_array::cast::AsArray;
+use arrow_array::types::*;
+use arrow_array::{
+    Array, ArrayRef, BooleanArray, FixedSizeListArray, Float32Array, Float64Array, Int64Array,
+    RecordBatchReader, StringArray,
+};
+use arrow_ipc::reader::{FileReader, StreamReader};
+use arrow_ipc::writer::FileWriter;
+use arrow_schema::{ArrowError, DataType, Field, Schema};
+use serde_json::{Map, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+use std::sync::Arc;
+use vectrust_core::*;
+
+/// Column holding item ids as UUID strings
+pub const ID_COLUMN: &str = "id";
+
+/// Column holding item vectors as `FixedSizeList<Float32>`
+pub const VECTOR_COLUMN: &str = "vector";
+
+/// Field metadata key marking a string column whose values are JSON text
+pub const JSON_COLUMN_METADATA: &str = "vectrust.json";
+
+/// Items per batch in [`LocalIndex::scan_record_batches`] by default
+pub const DEFAULT_ARROW_BATCH_SIZE: usize = 8192;
+
+fn arrow_error(error: ArrowError) -> VectraError {
+    VectraError::Storage {
+        message: format!("Arrow: {error}"),
+    }
+}
+
+fn invalid_column(column: &str, message: impl std::fmt::Display) -> VectraError {
+    VectraError::MetadataValidation {
+        message: format!("column {column}: {message}"),
+    }
+}
+
+/// Convert a batch into items. Rows with a null or missing id get one on
+/// insert; null metadata values are left out of the item's metadata.
+pub fn record_batch_to_items(batch: &RecordBatch) -> Result<Vec<VectorItem>> {
+    let schema = batch.schema();
+    let vectors = batch
+        .column_by_name(VECTOR_COLUMN)
+        .ok_or_else(|| invalid_column(VECTOR_COLUMN, "missing"))?;
+    let vectors = vectors
+        .as_fixed_size_list_opt()
+        .ok_or_else(|| invalid_column(VECTOR_COLUMN, "must be FixedSizeList<Float32>"))?;
+    let values = vectors
+        .values()
+        .as_primitive_opt::<Float32Type>()
+        .ok_or_else(|| invalid_column(VECTOR_COLUMN, "must be FixedSizeList<Float32>"))?;
+    if vectors.null_count() > 0 || values.null_count() > 0 {
+        return Err(invalid_column(VECTOR_COLUMN, "holds nulls"));
+    }
+    let dimensions = vectors.value_length() as usize;
+    let values = values.values();
+
+    let ids = batch.column_by_name(ID_COLUMN);
+    let metadata_columns: Vec<(&Field, &ArrayRef)> = schema
+        .fields()
+        .iter()
+        .zip(batch.columns())
+        .filter(|(field, _)| field.name() != ID_COLUMN && field.name() != VECTOR_COLUMN)
+        .map(|(field, column)| (field.as_ref(), column))
+        .collect();
+
+    let mut items = Vec::with_capacity(batch.num_rows());
+    for row in 0..batch.num_rows() {
+        let id = match ids {
+            Some(ids) => read_id(ids, row)?,
+            None => uuid::Uuid::nil(),
+        };
+        let mut metadata = Map::new();
+        for (field, column) in &metadata_columns {
+            if let Some(value) = read_value(field, column, row)? {
+                metadata.insert(field.name().clone(), value);
+            }
+        }
+        let start = vectors.value_offset(row) as usize;
+        items.push(VectorItem {
+            id,
+            vector: values[start..start + dimensions].to_vec(),
+            metadata: Value::Object(metadata),
+            ..Default::default()
+        });
+    }
+    Ok(items)
+}
+
+fn read_id(ids: &ArrayRef, row: usize) -> Result<uuid::Uuid> {
+    if ids.is_null(row) {
+        return Ok(uuid::Uuid::nil());
+    }
+    let id = match ids.data_type() {
+        DataType::Utf8 => uuid::Uuid::parse_str(ids.as_string::<i32>().value(row))?,
+        DataType::LargeUtf8 => uuid::Uuid::parse_str(ids.as_string::<i64>().value(row))?,
+        DataType::FixedSizeBinary(16) => {
+            uuid::Uuid::from_slice(ids.as_fixed_size_binary().value(row))?
+        }
+        other => {
+            return Err(invalid_column(
+                ID_COLUMN,
+                format!("unsupported type {other}"),
+            ))
+        }
+    };
+    Ok(id)
+}
+
+fn read_value(field: &Field, column: &ArrayRef, row: usize) -> Result<Option<Value>> {
+    if column.is_null(row) {
+        return Ok(None);
+    }
+    let value = match column.data_type() {
+        DataType::Boolean => Value::Bool(column.as_boolean().value(row)),
+        DataType::Int8 => column.as_primitive::<Int8Type>().value(row).into(),
+        DataType::Int16 => column.as_primitive::<Int16Type>().value(row).into(),
+        DataType::Int32 => column.as_primitive::<Int32Type>().value(row).into(),
+        DataType::Int64 => column.as_primitive::<Int64Type>().value(row).into(),
+        DataType::UInt8 => column.as_primitive::<UInt8Type>().value(row).into(),
+        DataType::UInt16 => column.as_primitive::<UInt16Type>().value(row).into(),
+        DataType::UInt32 => column.as_primitive::<UInt32Type>().value(row).into(),
+        DataType::UInt64 => column.as_primitive::<UInt64Type>().value(row).into(),
+        DataType::Float32 => column.as_primitive::<Float32Type>().value(row).into(),
+        DataType::Float64 => column.as_primitive::<Float64Type>().value(row).into(),
+        DataType::Utf8 | DataType::LargeUtf8 => {
+            let text = match column.data_type() {
+                DataType::Utf8 => column.as_string::<i32>().value(row),
+                _ => column.as_string::<i64>().value(row),
+            };
+            if field.metadata().contains_key(JSON_COLUMN_METADATA) {
+                serde_json::from_str(text)?
+            } else {
+                Value::String(text.to_string())
+            }
+        }
+        other => {
+            return Err(invalid_column(
+                field.name(),
+                format!("unsupported type {other}"),
+            ))
+        }
+    };
+    Ok(Some(value))
+}
+
+/// How a metadata field is written, from the values it holds
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ColumnKind {
+    Boolean,
+    Int64,
+    Float64,
+    Utf8,
+    Json,
+}
+
+impl ColumnKind {
+    fn of(value: &Value) -> Self {
+        match value {
+            Value::Bool(_) => ColumnKind::Boolean,
+            Value::Number(n) if n.is_i64() => ColumnKind::Int64,
+            Value::Number(_) => ColumnKind::Float64,
+            Value::String(_) => ColumnKind::Utf8,
+            _ => ColumnKind::Json,
+        }
+    }
+
+    /// The kind holding values of both kinds; integers widen to floats
+    /// and anything else mixed falls back to JSON
+    fn merge(self, other: Self) -> Self {
+        match (self, other) {
+            (a, b) if a == b => a,
+            (ColumnKind::Int64, ColumnKind::Float64) | (ColumnKind::Float64, ColumnKind::Int64) => {
+                ColumnKind::Float64
+            }
+            _ => ColumnKind::Json,
+        }
+    }
+}
+
+/// Convert items into one batch. Every vector must have the same length,
+/// and a metadata field named `id` or `vector` can't be written.
+pub fn items_to_record_batch(items: &[VectorItem]) -> Result<RecordBatch> {
+    let dimensions = items.first().map_or(0, |item| item.vector.len());
+    let mut values = Vec::with_capacity(items.len() * dimensions);
+    for item in items {
+        if item.vector.len() != dimensions {
+            return Err(VectraError::InvalidDimensions {
+                expected: dimensions,
+                actual: item.vector.len(),
+            });
+        }
+        values.extend_from_slice(&item.vector);
+    }
+
+    let mut kinds: BTreeMap<&str, ColumnKind> = BTreeMap::new();
+    for item in items {
+        for (key, value) in item.metadata.as_object().into_iter().flatten() {
+            if value.is_null() {
+                continue;
+            }
+            let kind = ColumnKind::of(value);
+            kinds
+                .entry(key)
+                .and_modify(|existing| *existing = existing.merge(kind))
+                .or_insert(kind);
+        }
+    }
+    if let Some(key) = [ID_COLUMN, VECTOR_COLUMN]
+        .into_iter()
+        .find(|key| kinds.contains_key(key))
+    {
+        return Err(invalid_column(
+            key,
+            "metadata field clashes with a reserved column",
+        ));
+    }
+
+    let item_field = Arc::new(Field::new_list_field(DataType::Float32, false));
+    let vectors = FixedSizeListArray::try_new(
+        item_field,
+        dimensions as i32,
+        Arc::new(Float32Array::from(values)),
+        None,
+    )
+    .map_err(arrow_error)?;
+    let ids = StringArray::from_iter_values(items.iter().map(|item| item.id.to_string()));
+
+    let mut fields = vec![
+        Field::new(ID_COLUMN, DataType::Utf8, false),
+        Field::new(VECTOR_COLUMN, vectors.data_type().clone(), false),
+    ];
+    let mut columns: Vec<ArrayRef> = vec![Arc::new(ids), Arc::new(vectors)];
+    for (key, kind) in kinds {
+        let cells = items
+            .iter()
+            .map(|item| item.metadata.get(key).filter(|v| !v.is_null()));
+        let (field, column): (Field, ArrayRef) = match kind {
+            ColumnKind::Boolean => (
+                Field::new(key, DataType::Boolean, true),
+                Arc::new(
+                    cells
+                        .map(|v| v.and_then(Value::as_bool))
+                        .collect::<BooleanArray>(),
+                ),
+            ),
+            ColumnKind::Int64 => (
+                Field::new(key, DataType::Int64, true),
+                Arc::new(
+                    cells
+                        .map(|v| v.and_then(Value::as_i64))
+                        .collect::<Int64Array>(),
+                ),
+            ),
+            ColumnKind::Float64 => (
+                Field::new(key, DataType::Float64, true),
+                Arc::new(
+                    cells
+                        .map(|v| v.and_then(Value::as_f64))
+                        .collect::<Float64Array>(),
+                ),
+            ),
+            ColumnKind::Utf8 => (
+                Field::new(key, DataType::Utf8, true),
+                Arc::new(
+                    cells
+                        .map(|v| v.and_then(Value::as_str))
+                        .collect::<StringArray>(),
+                ),
+            ),
+            ColumnKind::Json => (
+                Field::new(key, DataType::Utf8, true).with_metadata(HashMap::from([(
+                    JSON_COLUMN_METADATA.to_string(),
+                    "true".to_string(),
+                )])),
+                Arc::new(
+                    cells
+                        .map(|v| v.map(Value::to_string))
+                        .collect::<StringArray>(),
+                ),
+            ),
+        };
+        fields.push(field);
+        columns.push(column);
+    }
+
+    RecordBatch::try_new(Arc::new(Schema::new(fields)), columns).map_err(arrow_error)
+}
+
+impl LocalIndex {
+    /// Insert every row of `batch`, returning the number inserted
+    pub async fn insert_record_batch(&self, batch: &RecordBatch) -> Result<usize> {
+        let items = record_batch_to_items(batch)?;
+        Ok(self.insert_items(items).await?.len())
+    }
+
+    /// The items `options` selects as batches of up to `batch_size` rows
+    /// sharing one schema; the batches are slices of a single batch, so
+    /// they share its buffers.
+    pub async fn scan_record_batches(
+        &self,
+        options: Option<ListOptions>,
+        batch_size: usize,
+    ) -> Result<Vec<RecordBatch>> {
+        let items = self.list_items(options).await?;
+        let batch = items_to_record_batch(&items)?;
+        let batch_size = batch_size.max(1);
+        Ok((0..batch.num_rows())
+            .step_by(batch_size)
+            .map(|offset| batch.slice(offset, batch_size.min(batch.num_rows() - offset)))
+            .collect())
+    }
+
+    /// Insert every row of an Arrow IPC file or stream, returning the
+    /// number inserted. Batches are inserted as they are read.
+    pub async fn import_arrow_ipc(&self, path: impl AsRef<Path>) -> Result<usize> {
+        let mut file = File::open(path.as_ref())?;
+        let mut magic = [0u8; 6];
+        let is_file = file.read_exact(&mut magic).is_ok() && &magic == b"ARROW1";
+        file.seek(SeekFrom::Start(0))?;
+        let reader: Box<dyn RecordBatchReader> = if is_file {
+            Box::new(FileReader::try_new(file, None).map_err(arrow_error)?)
+        } else {
+            Box::new(StreamReader::try_new(BufReader::new(file), None).map_err(arrow_error)?)
+        };
+
+        let mut imported = 0;
+        for batch in reader {
+            imported += self
+                .insert_record_batch(&batch.map_err(arrow_error)?)
+                .await?;
+        }
+        Ok(imported)
+    }
+
+    /// Write the items `options` selects to an Arrow IPC file at `path`,
+    /// returning the number written
+    pub async fn export_arrow_ipc(
+        &self,
+        path: impl AsRef<Path>,
+        options: Option<ListOptions>,
+    ) -> Result<usize> {
+        let batches = self
+            .scan_record_batches(options, DEFAULT_ARROW_BATCH_SIZE)
+            .await?;
+        let schema = match batches.first() {
+            Some(batch) => batch.schema(),
+            None => items_to_record_batch(&[])?.schema(),
+        };
+        let mut writer =
+            FileWriter::try_new(File::create(path.as_ref())?, &schema).map_err(arrow_error)?;
+        let mut written = 0;
+        for batch in &batches {
+            writer.write(batch).map_err(arrow_error)?;
+            written += batch.num_rows();
+        }
+        writer.finish().map_err(arrow_error)?;
+        Ok(written)
+    }
+}
@@ -0,0 +1,260 @@
+// Copyright 2024-2026 Andrey Vasilevsky <anvanster@gmail.com>
+// SPDX-License-Identifier: Apache-2.0
+
+//! External blobs referenced from item metadata.
+//!
+//! Payloads too large to store in the index stay where they are, and items
+//! name them by URI in a metadata field set with
+//! [`LocalIndex::set_attachments`]. A [`BlobStore`] for the URI's scheme
+//! then deletes the blob once its item is deleted, and copies it into each
+//! [`snapshot`](LocalIndex::snapshot), so source artifacts live and die
+//! with the vectors made from them. [`FileBlobStore`] handles local paths;
+//! object stores such as `s3://` plug in their own.
+
+use crate::LocalIndex;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use uuid::Uuid;
+use vectrust_core::*;
+
+/// Folder inside a snapshot that attachments are copied into
+pub(crate) const ATTACHMENTS_DIR: &str = "attachments";
+
+/// File in [`ATTACHMENTS_DIR`] mapping each URI to its copy
+const ATTACHMENTS_INDEX_FILE: &str = "attachments.json";
+
+const TRACING_TARGET: &str = "vectrust::attachments";
+
+/// Deletes and copies the blobs behind one kind of URI
+pub trait BlobStore: Send + Sync {
+    /// Whether this store handles `uri`, usually judged by its scheme
+    fn handles(&self, uri: &str) -> bool;
+
+    /// Delete the blob at `uri`. A blob that is already gone is not an error.
+    fn delete(&self, uri: &str) -> Result<()>;
+
+    /// Copy the blob at `uri` into the folder `dir`, returning the copy's
+    /// file name within it
+    fn copy_to(&self, uri: &str, dir: &Path) -> Result<String>;
+}
+
+/// Blobs on the local filesystem, named by absolute path or `file://` URI
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FileBlobStore;
+
+impl FileBlobStore {
+    fn path(uri: &str) -> &Path {
+        Path::new(uri.strip_prefix("file://").unwrap_or(uri))
+    }
+}
+
+impl BlobStore for FileBlobStore {
+    fn handles(&self, uri: &str) -> bool {
+        uri.starts_with("file://") || Path::new(uri).is_absolute()
+    }
+
+    fn delete(&self, uri: &str) -> Result<()> {
+        match std::fs::remove_file(Self::path(uri)) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+
+    fn copy_to(&self, uri: &str, dir: &Path) -> Result<String> {
+        let path = Self::path(uri);
+        // Prefixed with a hash of the URI so same-named files don't collide
+        let hash = blake3::hash(uri.as_bytes()).to_hex();
+        let name = match path.file_name() {
+            Some(file_name) => format!("{}-{}", &hash[..16], file_name.to_string_lossy()),
+            None => hash[..16].to_string(),
+        };
+        std::fs::copy(path, dir.join(&name))?;
+        Ok(name)
+    }
+}
+
+/// The metadata field naming attachments, and the stores handling them
+#[derive(Clone)]
+pub(crate) struct Attachments {
+    field: String,
+    stores: Vec<Arc<dyn BlobStore>>,
+}
+
+/// Where a snapshot's copy of each attachment is, relative to [`ATTACHMENTS_DIR`]
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct AttachmentsIndex {
+    copies: BTreeMap<String, String>,
+}
+
+impl Attachments {
+    /// URIs `item` refers to: the field's string, or strings in its array
+    fn uris<'a>(&self, item: &'a VectorItem) -> Vec<&'a str> {
+        match item.metadata.get(&self.field) {
+            Some(serde_json::Value::String(uri)) => vec![uri.as_str()],
+            Some(serde_json::Value::Array(values)) => {
+                values.iter().filter_map(|value| value.as_str()).collect()
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    fn store(&self, uri: &str) -> Option<&Arc<dyn BlobStore>> {
+        let store = self.stores.iter().find(|store| store.handles(uri));
+        if store.is_none() {
+            tracing::warn!(target: TRACING_TARGET, uri, "no blob store handles attachment");
+        }
+        store
+    }
+
+    /// Delete the blobs of items that have been deleted. The items are
+    /// gone already, so failures are logged rather than returned.
+    pub(crate) fn release(&self, uris: &[String]) {
+        for uri in uris {
+            if let Some(store) = self.store(uri) {
+                if let Err(e) = store.delete(uri) {
+                    tracing::warn!(target: TRACING_TARGET, uri, "failed to delete attachment: {}", e);
+                }
+            }
+        }
+    }
+}
+
+impl LocalIndex {
+    /// Treat the URIs in metadata `field`, a string or an array of them, as
+    /// each item's external blobs, handled by the first of `stores` that
+    /// handles each. Replaces any earlier setting on this handle.
+    pub fn set_attachments(&self, field: &str, stores: Vec<Arc<dyn BlobStore>>) {
+        *self.attachments.lock().unwrap() = Some(Attachments {
+            field: field.to_string(),
+            stores,
+        });
+    }
+
+    /// Stop managing attachments; blobs are then left alone
+    pub fn clear_attachments(&self) {
+        *self.attachments.lock().unwrap() = None;
+    }
+
+    fn attachment_config(&self) -> Option<Attachments> {
+        self.attachments.lock().unwrap().clone()
+    }
+
+    /// Attachments of the stored items `ids`, to release once they're deleted
+    pub(crate) async fn attachments_of(
+        &self,
+        storage: &dyn StorageBackend,
+        ids: &[Uuid],
+    ) -> Result<Vec<String>> {
+        let Some(attachments) = self.attachment_config() else {
+            return Ok(Vec::new());
+        };
+        let items = storage.get_items(ids).await?;
+        Ok(items
+            .iter()
+            .flatten()
+            .flat_map(|item| attachments.uris(item))
+            .map(str::to_string)
+            .collect())
+    }
+
+    /// Delete the blobs behind `uris`, whose items have been deleted
+    pub(crate) fn release_attachments(&self, uris: &[String]) {
+        if let Some(attachments) = self.attachment_config() {
+            attachments.release(uris);
+        }
+    }
+
+    /// Copy every attachment into `snapshot`, alongside an index of where
+    /// each URI's copy went
+    pub(crate) async fn copy_attachments(
+        &self,
+        storage: &dyn StorageBackend,
+        snapshot: &Path,
+    ) -> Result<()> {
+        let Some(attachments) = self.attachment_config() else {
+            return Ok(());
+        };
+        let field = attachments.field.clone();
+        let has_field = move |metadata: &serde_json::Value| metadata.get(&field).is_some();
+        let items = storage.list_matching(None, &has_field).await?;
+
+        let dir: PathBuf = snapshot.join(ATTACHMENTS_DIR);
+        std::fs::create_dir_all(&dir)?;
+        let mut index = AttachmentsIndex::default();
+        for uri in items.iter().flat_map(|item| attachments.uris(item)) {
+            if index.copies.contains_key(uri) {
+                continue;
+            }
+            if let Some(store) = attachments.store(uri) {
+                let copy = store.copy_to(uri, &dir)?;
+                index.copies.insert(uri.to_string(), copy);
+            }
+        }
+        std::fs::write(
+            dir.join(ATTACHMENTS_INDEX_FILE),
+            serde_json::to_vec_pretty(&index)?,
+        )?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_attachments_follow_items() {
+        let temp_dir = TempDir::new().unwrap();
+        let blobs = TempDir::new().unwrap();
+        let index = LocalIndex::new(temp_dir.path().join("index"), None).unwrap();
+        index.create_index(None).await.unwrap();
+        index.set_attachments("source", vec![Arc::new(FileBlobStore)]);
+
+        let blob = |name: &str| {
+            let path = blobs.path().join(name);
+            std::fs::write(&path, name).unwrap();
+            path.to_string_lossy().to_string()
+        };
+        let (report, scan, notes) = (blob("report.pdf"), blob("scan.png"), blob("notes.txt"));
+        let item = |source: serde_json::Value| VectorItem {
+            vector: vec![1.0, 0.0],
+            metadata: serde_json::json!({ "source": source }),
+            ..Default::default()
+        };
+        let first = index
+            .insert_item(item(serde_json::json!(report)))
+            .await
+            .unwrap();
+        index
+            .insert_item(item(serde_json::json!([format!("file://{}", scan), notes])))
+            .await
+            .unwrap();
+
+        // Snapshots carry a copy of every attachment
+        let snapshot = index
+            .snapshot(temp_dir.path().join("snapshots"))
+            .await
+            .unwrap();
+        let copies: AttachmentsIndex = serde_json::from_slice(
+            &std::fs::read(snapshot.join(ATTACHMENTS_DIR).join(ATTACHMENTS_INDEX_FILE)).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(copies.copies.len(), 3);
+        let copy = snapshot.join(ATTACHMENTS_DIR).join(&copies.copies[&report]);
+        assert_eq!(std::fs::read_to_string(copy).unwrap(), "report.pdf");
+
+        // Deleting items deletes their blobs
+        index.delete_item(&first.id).await.unwrap();
+        assert!(!Path::new(&report).exists());
+        assert!(Path::new(&scan).exists());
+        index
+            .delete_by_filter(serde_json::json!({ "source": { "$exists": true } }))
+            .await
+            .unwrap();
+        assert!(!Path::new(&scan).exists());
+        assert!(!Path::new(&notes).exists());
+    }
+}
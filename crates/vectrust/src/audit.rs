@@ -0,0 +1,221 @@
+// Copyright 2024-2026 Andrey Vasilevsky <anvanster@gmail.com>
+// SPDX-License-Identifier: Apache-2.0
+
+//! Audit log of mutating operations.
+//!
+//! With an [`AuditLog`] in the runtime config, every write through
+//! [`LocalIndex`](crate::LocalIndex) appends a JSON line saying who did
+//! what to which items, and whether it succeeded. Entries are written
+//! under the storage write lock, so they appear in the order the changes
+//! were applied. A failure to write the log is reported through `tracing`
+//! and never undoes or fails the operation itself.
+
+use serde::Serialize;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+use vectrust_core::*;
+
+/// Target of the `tracing` events, for routing them separately
+pub(crate) const TRACING_TARGET: &str = "vectrust::audit";
+
+/// Kind of change an entry records
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum AuditOperation {
+    CreateIndex,
+    Insert,
+    Update,
+    Delete,
+    DeleteIndex,
+}
+
+/// One line of the audit log
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct AuditEntry<'a> {
+    timestamp: chrono::DateTime<chrono::Utc>,
+    index: &'a Path,
+    actor: Option<&'a str>,
+    operation: AuditOperation,
+    #[serde(skip_serializing_if = "<[Uuid]>::is_empty")]
+    ids: &'a [Uuid],
+    success: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// Append an entry for `operation` on `ids`, which ended in `outcome`
+pub(crate) fn record<T>(
+    log: &AuditLog,
+    index: &Path,
+    operation: AuditOperation,
+    ids: &[Uuid],
+    outcome: &Result<T>,
+) {
+    let entry = AuditEntry {
+        timestamp: chrono::Utc::now(),
+        index,
+        actor: log.actor.as_deref(),
+        operation,
+        ids,
+        success: outcome.is_ok(),
+        error: outcome.as_ref().err().map(|e| e.to_string()),
+    };
+    let written = serde_json::to_string(&entry)
+        .map_err(std::io::Error::other)
+        .and_then(|line| append(log, &line));
+    if let Err(e) = written {
+        tracing::error!(
+            target: TRACING_TARGET,
+            "failed to write audit log {}: {}",
+            log.path.display(),
+            e
+        );
+    }
+}
+
+fn append(log: &AuditLog, line: &str) -> std::io::Result<()> {
+    if let Some(max) = log.max_bytes {
+        let size = match std::fs::metadata(&log.path) {
+            Ok(metadata) => metadata.len(),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => 0,
+            Err(e) => return Err(e),
+        };
+        if size > 0 && size + line.len() as u64 + 1 > max {
+            rotate(log)?;
+        }
+    }
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&log.path)?;
+    writeln!(file, "{}", line)
+}
+
+/// Move the current file to `path.1`, shifting older ones up and dropping
+/// the one past `max_files`
+fn rotate(log: &AuditLog) -> std::io::Result<()> {
+    if log.max_files == 0 {
+        return std::fs::remove_file(&log.path);
+    }
+    let oldest = rotated_path(&log.path, log.max_files);
+    if oldest.exists() {
+        std::fs::remove_file(&oldest)?;
+    }
+    for n in (1..log.max_files).rev() {
+        let from = rotated_path(&log.path, n);
+        if from.exists() {
+            std::fs::rename(&from, rotated_path(&log.path, n + 1))?;
+        }
+    }
+    std::fs::rename(&log.path, rotated_path(&log.path, 1))
+}
+
+fn rotated_path(path: &Path, n: usize) -> PathBuf {
+    let mut name = path.as_os_str().to_os_string();
+    name.push(format!(".{}", n));
+    PathBuf::from(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::LocalIndex;
+    use tempfile::TempDir;
+
+    fn entries(path: &Path) -> Vec<serde_json::Value> {
+        std::fs::read_to_string(path)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_audit_log_records_writes() {
+        let temp_dir = TempDir::new().unwrap();
+        let log_path = temp_dir.path().join("audit.jsonl");
+        let index = LocalIndex::new(temp_dir.path().join("index"), None).unwrap();
+        let mut config = index.runtime_config();
+        config.audit_log = Some(AuditLog {
+            actor: Some("ingest".to_string()),
+            ..AuditLog::new(&log_path)
+        });
+        index.set_runtime_config(config).await.unwrap();
+
+        index.create_index(None).await.unwrap();
+        let item = index
+            .insert_item(VectorItem {
+                vector: vec![1.0, 0.0],
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        index
+            .update_item(UpdateRequest {
+                id: item.id,
+                vector: None,
+                metadata: Some(serde_json::json!({ "a": 1 })),
+            })
+            .await
+            .unwrap();
+        let missing = Uuid::new_v4();
+        assert!(index
+            .update_item(UpdateRequest {
+                id: missing,
+                vector: None,
+                metadata: None,
+            })
+            .await
+            .is_err());
+        index.delete_item(&item.id).await.unwrap();
+        index.delete_index().await.unwrap();
+
+        let entries = entries(&log_path);
+        let operations: Vec<&str> = entries
+            .iter()
+            .map(|e| e["operation"].as_str().unwrap())
+            .collect();
+        assert_eq!(
+            operations,
+            vec![
+                "createIndex",
+                "insert",
+                "update",
+                "update",
+                "delete",
+                "deleteIndex"
+            ]
+        );
+        assert!(entries.iter().all(|e| e["actor"] == "ingest"));
+        assert_eq!(entries[1]["ids"][0], item.id.to_string());
+        assert_eq!(entries[3]["ids"][0], missing.to_string());
+        assert_eq!(entries[3]["success"], false);
+        assert!(entries[3]["error"].is_string());
+    }
+
+    #[test]
+    fn test_audit_log_rotation() {
+        let temp_dir = TempDir::new().unwrap();
+        let log = AuditLog {
+            max_bytes: Some(300),
+            max_files: 2,
+            ..AuditLog::new(temp_dir.path().join("audit.jsonl"))
+        };
+        for _ in 0..12 {
+            record(
+                &log,
+                temp_dir.path(),
+                AuditOperation::Delete,
+                &[Uuid::new_v4()],
+                &Ok(()),
+            );
+        }
+        assert!(std::fs::metadata(&log.path).unwrap().len() <= 300);
+        assert!(rotated_path(&log.path, 1).exists());
+        assert!(rotated_path(&log.path, 2).exists());
+        assert!(!rotated_path(&log.path, 3).exists());
+        assert!(!entries(&rotated_path(&log.path, 2)).is_empty());
+    }
+}
@@ -0,0 +1,553 @@
+// Copyright 2024-2026 Andrey Vasilevsky <anvanster@gmail.com>
+// SPDX-License-Identifier: Apache-2.0
+
+//! Synchronous wrapper around [`crate::LocalIndex`].
+//!
+//! Each [`LocalIndex`] owns a small tokio runtime and drives the async API to
+//! completion on it, so callers don't need a runtime of their own. A single
+//! worker thread keeps background tasks such as auto-compaction running
+//! between calls.
+//!
+//! ```ignore
+//! let index = vectrust::blocking::LocalIndex::new("./data", None)?;
+//! index.create_index(None)?;
+//! let results = index.query_items(vec![0.1, 0.2, 0.3], Some(5), None)?;
+//! ```
+//!
+//! Like `reqwest::blocking`, these methods must not be called from within an
+//! async context; doing so panics when the inner runtime tries to block.
+
+use crate::{ScoringFn, TextIndexConfig};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::Arc;
+use tokio::runtime::Runtime;
+use vectrust_core::*;
+
+/// Blocking counterpart of [`crate::LocalIndex`]
+pub struct LocalIndex {
+    inner: crate::LocalIndex,
+    runtime: Runtime,
+}
+
+impl LocalIndex {
+    /// Create a new LocalIndex with auto-detected storage backend
+    pub fn new<P: AsRef<Path>>(folder_path: P, index_name: Option<String>) -> Result<Self> {
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(1)
+            .enable_all()
+            .build()?;
+        let inner = crate::LocalIndex::new(folder_path, index_name)?;
+
+        Ok(Self { inner, runtime })
+    }
+
+    /// Open an index with every item held in RAM if it takes at most
+    /// `max_bytes`, as [`crate::LocalIndex::open_in_memory`]
+    pub fn open_in_memory<P: AsRef<Path>>(
+        folder_path: P,
+        index_name: Option<String>,
+        max_bytes: u64,
+    ) -> Result<Self> {
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(1)
+            .enable_all()
+            .build()?;
+        let inner = runtime.block_on(crate::LocalIndex::open_in_memory(
+            folder_path,
+            index_name,
+            max_bytes,
+        ))?;
+
+        Ok(Self { inner, runtime })
+    }
+
+    /// Open an index whose storage runs on a dedicated writer thread, as
+    /// [`crate::LocalIndex::open_single_writer`]
+    pub fn open_single_writer<P: AsRef<Path>>(
+        folder_path: P,
+        index_name: Option<String>,
+    ) -> Result<Self> {
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(1)
+            .enable_all()
+            .build()?;
+        let inner = crate::LocalIndex::open_single_writer(folder_path, index_name)?;
+
+        Ok(Self { inner, runtime })
+    }
+
+    /// Register a function for `DistanceMetric::Custom(name)` on this handle
+    pub fn register_distance(&self, name: &str, distance: Arc<dyn DistanceFn>) -> Result<()> {
+        self.inner.register_distance(name, distance)
+    }
+
+    /// Create an index with configuration
+    pub fn create_index(&self, config: Option<CreateIndexConfig>) -> Result<()> {
+        self.runtime.block_on(self.inner.create_index(config))
+    }
+
+    /// Check if index exists
+    pub fn is_index_created(&self) -> bool {
+        self.runtime.block_on(self.inner.is_index_created())
+    }
+
+    /// Insert a new item
+    pub fn insert_item(&self, item: VectorItem) -> Result<VectorItem> {
+        self.runtime.block_on(self.inner.insert_item(item))
+    }
+
+    /// Insert an item, doing as `on_conflict` says if its id is already stored
+    pub fn insert_item_on_conflict(
+        &self,
+        item: VectorItem,
+        on_conflict: OnConflict,
+    ) -> Result<VectorItem> {
+        self.runtime
+            .block_on(self.inner.insert_item_on_conflict(item, on_conflict))
+    }
+
+    /// Insert multiple items in batch
+    pub fn insert_items(&self, items: Vec<VectorItem>) -> Result<Vec<VectorItem>> {
+        self.runtime.block_on(self.inner.insert_items(items))
+    }
+
+    /// Insert multiple items, doing as `on_conflict` says with ids already stored
+    pub fn insert_items_on_conflict(
+        &self,
+        items: Vec<VectorItem>,
+        on_conflict: OnConflict,
+    ) -> Result<Vec<VectorItem>> {
+        self.runtime
+            .block_on(self.inner.insert_items_on_conflict(items, on_conflict))
+    }
+
+    /// Insert multiple items, calling `progress` after each chunk
+    pub fn insert_items_with_progress(
+        &self,
+        items: Vec<VectorItem>,
+        progress: impl FnMut(InsertProgress) + Send,
+    ) -> Result<Vec<VectorItem>> {
+        self.runtime
+            .block_on(self.inner.insert_items_with_progress(items, progress))
+    }
+
+    /// Insert every row of an embedding matrix, returning the number inserted
+    pub fn import_embeddings(&self, import: crate::EmbeddingImport) -> Result<usize> {
+        self.runtime.block_on(self.inner.import_embeddings(import))
+    }
+
+    /// Get an item by ID
+    pub fn get_item(&self, id: &uuid::Uuid) -> Result<Option<VectorItem>> {
+        self.runtime.block_on(self.inner.get_item(id))
+    }
+
+    /// Get several items by ID, `None` for each id that isn't stored
+    pub fn get_items(&self, ids: &[uuid::Uuid]) -> Result<Vec<Option<VectorItem>>> {
+        self.runtime.block_on(self.inner.get_items(ids))
+    }
+
+    /// Store a document payload for an item
+    pub fn set_payload(&self, id: &uuid::Uuid, payload: impl Into<Vec<u8>>) -> Result<()> {
+        self.runtime.block_on(self.inner.set_payload(id, payload))
+    }
+
+    /// Remove an item's payload, keeping the item
+    pub fn remove_payload(&self, id: &uuid::Uuid) -> Result<()> {
+        self.runtime.block_on(self.inner.remove_payload(id))
+    }
+
+    /// Payload stored for an item, if any
+    pub fn get_payload(&self, id: &uuid::Uuid) -> Result<Option<Vec<u8>>> {
+        self.runtime.block_on(self.inner.get_payload(id))
+    }
+
+    /// Update an existing item
+    pub fn update_item(&self, update: UpdateRequest) -> Result<UpdateResult> {
+        self.runtime.block_on(self.inner.update_item(update))
+    }
+
+    /// Apply several updates in one storage batch
+    pub fn update_items(&self, updates: Vec<UpdateRequest>) -> Result<Vec<UpdateResult>> {
+        self.runtime.block_on(self.inner.update_items(updates))
+    }
+
+    /// Delete an item
+    pub fn delete_item(&self, id: &uuid::Uuid) -> Result<()> {
+        self.runtime.block_on(self.inner.delete_item(id))
+    }
+
+    /// Delete every item matching `filter`, returning how many were deleted
+    pub fn delete_by_filter(&self, filter: impl Into<serde_json::Value>) -> Result<usize> {
+        self.runtime.block_on(self.inner.delete_by_filter(filter))
+    }
+
+    /// Every item in `namespace`
+    pub fn list_namespace(&self, namespace: &str) -> Result<Vec<VectorItem>> {
+        self.runtime.block_on(self.inner.list_namespace(namespace))
+    }
+
+    /// Delete every item in `namespace`, returning how many were deleted
+    pub fn delete_namespace(&self, namespace: &str) -> Result<usize> {
+        self.runtime
+            .block_on(self.inner.delete_namespace(namespace))
+    }
+
+    /// Add a composite index over several metadata fields
+    pub fn add_composite_index(&self, index: CompositeIndex) -> Result<()> {
+        self.runtime.block_on(self.inner.add_composite_index(index))
+    }
+
+    /// Composite indexes the storage keeps
+    pub fn composite_indexes(&self) -> Result<Vec<CompositeIndex>> {
+        self.runtime.block_on(self.inner.composite_indexes())
+    }
+
+    /// List all items
+    pub fn list_items(&self, options: Option<ListOptions>) -> Result<Vec<VectorItem>> {
+        self.runtime.block_on(self.inner.list_items(options))
+    }
+
+    /// Query items with vector similarity
+    pub fn query_items(
+        &self,
+        vector: Vec<f32>,
+        top_k: Option<u32>,
+        filter: Option<serde_json::Value>,
+    ) -> Result<Vec<QueryResult>> {
+        self.runtime
+            .block_on(self.inner.query_items(vector, top_k, filter))
+    }
+
+    /// Extended query with text search
+    pub fn query_items_extended(
+        &self,
+        vector: Vec<f32>,
+        text_query: Option<String>,
+        top_k: Option<u32>,
+        filter: Option<serde_json::Value>,
+    ) -> Result<Vec<QueryResult>> {
+        self.runtime.block_on(
+            self.inner
+                .query_items_extended(vector, text_query, top_k, filter),
+        )
+    }
+
+    /// Query with a user-defined scoring hook combining similarity with metadata signals
+    pub fn query_items_scored(
+        &self,
+        vector: Vec<f32>,
+        top_k: Option<u32>,
+        filter: Option<serde_json::Value>,
+        scoring: &dyn ScoringFn,
+    ) -> Result<Vec<QueryResult>> {
+        self.runtime.block_on(
+            self.inner
+                .query_items_scored(vector, top_k, filter, scoring),
+        )
+    }
+
+    /// Run a fully specified query, applying its filter, boosts and an optional scoring hook
+    pub fn execute_query(
+        &self,
+        query: &Query,
+        scoring: Option<&dyn ScoringFn>,
+    ) -> Result<Vec<QueryResult>> {
+        self.runtime
+            .block_on(self.inner.execute_query(query, scoring))
+    }
+
+    /// Run a query and report how it was planned and executed
+    pub fn explain_query(&self, query: &Query) -> Result<crate::QueryExplanation> {
+        self.runtime.block_on(self.inner.explain_query(query))
+    }
+
+    /// Written items waiting to be added to the HNSW graph, and since when
+    pub fn ann_lag(&self) -> AnnLag {
+        self.inner.ann_lag()
+    }
+
+    /// Sample statistics for the query planner now
+    pub fn analyze(&self) -> Result<crate::PlannerStats> {
+        self.runtime.block_on(self.inner.analyze())
+    }
+
+    /// Statistics the query planner last sampled, if any
+    pub fn planner_stats(&self) -> Option<crate::PlannerStats> {
+        self.inner.planner_stats()
+    }
+
+    /// Insert an item whose metadata is a serializable Rust type
+    pub fn insert_typed<T: Serialize>(
+        &self,
+        vector: Vec<f32>,
+        metadata: T,
+    ) -> Result<TypedItem<T>> {
+        self.runtime
+            .block_on(self.inner.insert_typed(vector, metadata))
+    }
+
+    /// Get an item by ID with its metadata deserialized into `T`
+    pub fn get_typed<T: DeserializeOwned>(&self, id: &uuid::Uuid) -> Result<Option<TypedItem<T>>> {
+        self.runtime.block_on(self.inner.get_typed(id))
+    }
+
+    /// Query items with vector similarity, deserializing each result's metadata into `T`
+    pub fn query_typed<T: DeserializeOwned>(
+        &self,
+        vector: Vec<f32>,
+        top_k: Option<u32>,
+        filter: Option<serde_json::Value>,
+    ) -> Result<Vec<TypedQueryResult<T>>> {
+        self.runtime
+            .block_on(self.inner.query_typed(vector, top_k, filter))
+    }
+
+    /// Copy or move every item matching `filter` into a new index at `target_path`
+    pub fn split_by_filter<P: AsRef<Path>>(
+        &self,
+        filter: &serde_json::Value,
+        target_path: P,
+        mode: SplitMode,
+    ) -> Result<usize> {
+        self.runtime
+            .block_on(self.inner.split_by_filter(filter, target_path, mode))
+    }
+
+    /// Build an in-memory geohash index over a `{"lat", "lon"}` metadata field
+    pub fn enable_geo_index(&self, field: &str, precision: Option<usize>) -> Result<()> {
+        self.runtime
+            .block_on(self.inner.enable_geo_index(field, precision))
+    }
+
+    /// Drop the geo index, returning queries to full scans
+    pub fn disable_geo_index(&self) {
+        self.inner.disable_geo_index()
+    }
+
+    /// Build a keyword index over the configured metadata fields
+    pub fn enable_text_index(&self, config: TextIndexConfig) -> Result<()> {
+        self.runtime.block_on(self.inner.enable_text_index(config))
+    }
+
+    /// Drop the text index and delete its persisted data
+    pub fn disable_text_index(&self) -> Result<()> {
+        self.inner.disable_text_index()
+    }
+
+    /// Keyword search over the text index, scored by BM25
+    pub fn text_search(
+        &self,
+        text: &str,
+        top_k: Option<u32>,
+        filter: Option<serde_json::Value>,
+    ) -> Result<Vec<QueryResult>> {
+        self.runtime
+            .block_on(self.inner.text_search(text, top_k, filter))
+    }
+
+    /// Save a query under `name`, replacing any query already saved with it
+    pub fn save_named_query(&self, name: &str, query: NamedQuery) -> Result<()> {
+        self.inner.save_named_query(name, query)
+    }
+
+    /// Look up a saved query by name
+    pub fn named_query(&self, name: &str) -> Option<NamedQuery> {
+        self.inner.named_query(name)
+    }
+
+    /// All saved queries by name
+    pub fn named_queries(&self) -> BTreeMap<String, NamedQuery> {
+        self.inner.named_queries()
+    }
+
+    /// Remove a saved query, returning whether it existed
+    pub fn delete_named_query(&self, name: &str) -> Result<bool> {
+        self.inner.delete_named_query(name)
+    }
+
+    /// Run a saved query, overriding its vector and `top_k` when given
+    pub fn run_named_query(
+        &self,
+        name: &str,
+        vector: Option<Vec<f32>>,
+        top_k: Option<u32>,
+        scoring: Option<&dyn ScoringFn>,
+    ) -> Result<Vec<QueryResult>> {
+        self.runtime
+            .block_on(self.inner.run_named_query(name, vector, top_k, scoring))
+    }
+
+    /// Report how much space compaction could reclaim
+    pub fn compaction_stats(&self) -> Result<CompactionStats> {
+        self.runtime.block_on(self.inner.compaction_stats())
+    }
+
+    /// Compact storage now, returning the stats from before compaction
+    pub fn compact(&self, max_bytes_per_sec: Option<u64>) -> Result<CompactionStats> {
+        self.runtime.block_on(self.inner.compact(max_bytes_per_sec))
+    }
+
+    /// Compare the storage's secondary indexes with its items
+    pub fn verify_secondary_indexes(&self) -> Result<IndexDivergence> {
+        self.runtime.block_on(self.inner.verify_secondary_indexes())
+    }
+
+    /// Rebuild the storage's secondary indexes from its items
+    pub fn rebuild_secondary_indexes(&self) -> Result<()> {
+        self.runtime
+            .block_on(self.inner.rebuild_secondary_indexes())
+    }
+
+    /// Sort vectors between the hot and cold tiers by how often they were read
+    pub fn retier(&self, policy: &TieringPolicy) -> Result<TieringStats> {
+        self.runtime.block_on(self.inner.retier(policy))
+    }
+
+    /// Rewrite every metadata record in `encoding`
+    pub fn set_metadata_encoding(&self, encoding: MetadataEncoding) -> Result<usize> {
+        self.runtime
+            .block_on(self.inner.set_metadata_encoding(encoding))
+    }
+
+    /// Start background auto-compaction
+    pub fn start_auto_compaction(&self, policy: Option<CompactionPolicy>) -> Result<bool> {
+        self.runtime
+            .block_on(self.inner.start_auto_compaction(policy))
+    }
+
+    /// Stop background auto-compaction if it is running
+    pub fn stop_auto_compaction(&self) {
+        self.inner.stop_auto_compaction()
+    }
+
+    /// Runtime settings currently in effect
+    pub fn runtime_config(&self) -> RuntimeConfig {
+        self.inner.runtime_config()
+    }
+
+    /// Apply new runtime settings without reopening the index
+    pub fn set_runtime_config(&self, config: RuntimeConfig) -> Result<()> {
+        self.runtime.block_on(self.inner.set_runtime_config(config))
+    }
+
+    /// User-defined index properties
+    pub fn get_index_metadata(&self) -> BTreeMap<String, serde_json::Value> {
+        self.inner.get_index_metadata()
+    }
+
+    /// Set an index property, returning its previous value
+    pub fn set_index_metadata(
+        &self,
+        key: &str,
+        value: serde_json::Value,
+    ) -> Result<Option<serde_json::Value>> {
+        self.inner.set_index_metadata(key, value)
+    }
+
+    /// Remove an index property, returning its value
+    pub fn remove_index_metadata(&self, key: &str) -> Result<Option<serde_json::Value>> {
+        self.inner.remove_index_metadata(key)
+    }
+
+    /// Get index statistics
+    pub fn get_stats(&self) -> Result<IndexStats> {
+        self.runtime.block_on(self.inner.get_stats())
+    }
+
+    /// Time spent waiting for locks, by lock and operation
+    pub fn lock_waits(&self) -> LockWaitStats {
+        self.runtime.block_on(self.inner.lock_waits())
+    }
+
+    /// Start counting lock waits afresh
+    pub fn reset_lock_waits(&self) {
+        self.runtime.block_on(self.inner.reset_lock_waits())
+    }
+
+    /// Whether a newer vectrust wrote features this build can only read
+    pub fn is_read_only(&self) -> Result<bool> {
+        self.runtime.block_on(self.inner.is_read_only())
+    }
+
+    /// Size and quota limits the index was created with, if any
+    pub fn limits(&self) -> Result<Option<IndexLimits>> {
+        self.runtime.block_on(self.inner.limits())
+    }
+
+    /// Transform the index applies to vectors written and queried, if any
+    pub fn vector_transform(&self) -> Result<Option<VectorTransform>> {
+        self.runtime.block_on(self.inner.vector_transform())
+    }
+
+    /// Validation policy the index holds written vectors to, if any
+    pub fn vector_validation(&self) -> Result<Option<VectorValidation>> {
+        self.runtime.block_on(self.inner.vector_validation())
+    }
+
+    /// Compute and store the `size` nearest neighbours of every item
+    pub fn build_neighbors(&self, size: usize) -> Result<usize> {
+        self.runtime.block_on(self.inner.build_neighbors(size))
+    }
+
+    /// Precomputed nearest neighbours of `id`, after applying pending writes
+    pub fn get_neighbors(&self, id: &uuid::Uuid) -> Result<Option<Vec<crate::Neighbor>>> {
+        self.runtime.block_on(self.inner.get_neighbors(id))
+    }
+
+    /// Delete the entire index
+    pub fn delete_index(&self) -> Result<()> {
+        self.runtime.block_on(self.inner.delete_index())
+    }
+
+    /// Begin transaction
+    pub fn begin_update(&self) -> Result<()> {
+        self.runtime.block_on(self.inner.begin_update())
+    }
+
+    /// End transaction
+    pub fn end_update(&self) -> Result<()> {
+        self.runtime.block_on(self.inner.end_update())
+    }
+
+    /// Cancel transaction
+    pub fn cancel_update(&self) -> Result<()> {
+        self.runtime.block_on(self.inner.cancel_update())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_blocking_roundtrip() {
+        let temp_dir = TempDir::new().unwrap();
+        let index = LocalIndex::new(temp_dir.path(), None).unwrap();
+
+        index.create_index(None).unwrap();
+        assert!(index.is_index_created());
+
+        let item = VectorItem {
+            vector: vec![1.0, 0.0, 0.0],
+            metadata: serde_json::json!({"name": "a"}),
+            ..Default::default()
+        };
+        let inserted = index.insert_item(item).unwrap();
+
+        let fetched = index.get_item(&inserted.id).unwrap().unwrap();
+        assert_eq!(fetched.metadata["name"], "a");
+
+        let results = index
+            .query_items(vec![1.0, 0.0, 0.0], Some(1), None)
+            .unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].item.id, inserted.id);
+
+        index.delete_item(&inserted.id).unwrap();
+        assert!(index.get_item(&inserted.id).unwrap().is_none());
+    }
+}
@@ -0,0 +1,209 @@
+// Copyright 2024-2026 Andrey Vasilevsky <anvanster@gmail.com>
+// SPDX-License-Identifier: Apache-2.0
+
+//! Offline bulk loading of a new index.
+//!
+//! [`BulkLoader`] writes items into a staging directory next to the target
+//! instead of the target itself. Manifest saves are deferred to the end
+//! and the keyword index is built once over the loaded items rather than
+//! updated per item. [`BulkLoader::finish`] then attaches the staging
+//! directory with a single rename, so the target either doesn't exist or
+//! holds the complete load; a crash mid-load only leaves staging behind,
+//! which the next loader for the same target discards.
+
+use crate::LocalIndex;
+use std::path::{Path, PathBuf};
+use vectrust_core::*;
+use vectrust_query::TextIndexConfig;
+
+/// Items written per storage batch while loading
+const LOAD_CHUNK_SIZE: usize = 10_000;
+
+/// Builds a new index offline and attaches it when complete
+pub struct BulkLoader {
+    target: PathBuf,
+    staging: PathBuf,
+    index: LocalIndex,
+    text_fields: Vec<TextFieldConfig>,
+    loaded: usize,
+}
+
+impl BulkLoader {
+    /// Start loading the index at `target`. The target must not exist yet,
+    /// or be an index without items, which is removed.
+    pub async fn new<P: AsRef<Path>>(target: P, config: Option<CreateIndexConfig>) -> Result<Self> {
+        let target = target.as_ref().to_path_buf();
+        clear_empty_target(&target).await?;
+
+        let staging = staging_path(&target);
+        if staging.exists() {
+            // Left behind by a load that never finished
+            std::fs::remove_dir_all(&staging)?;
+        }
+
+        // The keyword index is built once in `finish`, not per item
+        let mut config = config.unwrap_or_default();
+        let text_fields = std::mem::take(&mut config.metadata_config.text_fields);
+        let index = LocalIndex::new(&staging, None)?;
+        index.create_index(Some(config)).await?;
+
+        let mut runtime = index.runtime_config();
+        runtime.flush_interval = u32::MAX;
+        runtime.insert_chunk_size = LOAD_CHUNK_SIZE;
+        index.set_runtime_config(runtime).await?;
+
+        Ok(Self {
+            target,
+            staging,
+            index,
+            text_fields,
+            loaded: 0,
+        })
+    }
+
+    /// Stage a batch of items, returning the number loaded so far
+    pub async fn add(&mut self, items: Vec<VectorItem>) -> Result<usize> {
+        self.loaded += self.index.insert_items(items).await?.len();
+        Ok(self.loaded)
+    }
+
+    /// Items staged so far
+    pub fn loaded(&self) -> usize {
+        self.loaded
+    }
+
+    /// Build the keyword index, flush the staged data and move it into
+    /// place, returning the opened index. Fails without touching the
+    /// target if something has been created there since the load began.
+    pub async fn finish(self) -> Result<LocalIndex> {
+        let text_config =
+            (!self.text_fields.is_empty()).then(|| TextIndexConfig::from_fields(&self.text_fields));
+        if let Some(ref config) = text_config {
+            self.index.enable_text_index(config.clone()).await?;
+        }
+        self.index.end_update().await?;
+        // Close the staged storage before its directory moves
+        drop(self.index);
+
+        if self.target.exists() {
+            return Err(VectraError::IndexAlreadyExists {
+                path: self.target.to_string_lossy().to_string(),
+            });
+        }
+        std::fs::rename(&self.staging, &self.target)?;
+
+        let index = LocalIndex::new(&self.target, None)?;
+        // Only an on-disk keyword index survives the move
+        if let Some(config) = text_config {
+            if index.text_index.lock().unwrap().is_none() {
+                index.enable_text_index(config).await?;
+            }
+        }
+        Ok(index)
+    }
+
+    /// Discard everything staged; the target is left untouched
+    pub fn abort(self) -> Result<()> {
+        drop(self.index);
+        if self.staging.exists() {
+            std::fs::remove_dir_all(&self.staging)?;
+        }
+        Ok(())
+    }
+}
+
+/// Sibling directory the load is staged in
+fn staging_path(target: &Path) -> PathBuf {
+    let mut name = target
+        .file_name()
+        .map(|name| name.to_os_string())
+        .unwrap_or_else(|| "index".into());
+    name.push(".bulk-staging");
+    target.with_file_name(name)
+}
+
+/// Remove a target that holds nothing yet, so the staged index can be
+/// renamed into its place
+async fn clear_empty_target(target: &Path) -> Result<()> {
+    if !target.exists() {
+        return Ok(());
+    }
+    if std::fs::read_dir(target)?.next().is_none() {
+        std::fs::remove_dir(target)?;
+        return Ok(());
+    }
+
+    let index = LocalIndex::new(target, None)?;
+    if !index.is_index_created().await || index.get_stats().await?.items > 0 {
+        return Err(VectraError::IndexAlreadyExists {
+            path: target.to_string_lossy().to_string(),
+        });
+    }
+    index.delete_index().await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn items(range: std::ops::Range<usize>) -> Vec<VectorItem> {
+        range
+            .map(|i| VectorItem {
+                vector: vec![i as f32, 1.0],
+                metadata: serde_json::json!({ "title": format!("doc {}", i) }),
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_bulk_load_attaches_on_finish() {
+        let root = TempDir::new().unwrap();
+        let target = root.path().join("docs");
+
+        // A crashed load leaves only staging, which the next loader replaces
+        let crashed = BulkLoader::new(&target, None).await.unwrap();
+        drop(crashed);
+        assert!(!target.exists());
+
+        let mut config = CreateIndexConfig::default();
+        config.metadata_config.text_fields =
+            vec![TextFieldConfig::new("title", TokenizerConfig::default())];
+        let mut loader = BulkLoader::new(&target, Some(config)).await.unwrap();
+        loader.add(items(0..50)).await.unwrap();
+        assert_eq!(loader.add(items(50..120)).await.unwrap(), 120);
+        assert!(!target.exists());
+
+        let index = loader.finish().await.unwrap();
+        assert!(!staging_path(&target).exists());
+        assert_eq!(index.get_stats().await.unwrap().items, 120);
+        let hits = index.text_search("doc", Some(5), None).await.unwrap();
+        assert_eq!(hits.len(), 5);
+        drop(index);
+
+        // Populated targets are refused
+        assert!(BulkLoader::new(&target, None).await.is_err());
+        let reopened = LocalIndex::new(&target, None).unwrap();
+        assert_eq!(reopened.get_stats().await.unwrap().items, 120);
+    }
+
+    #[tokio::test]
+    async fn test_bulk_load_abort_and_empty_target() {
+        let root = TempDir::new().unwrap();
+        let target = root.path().join("docs");
+        let empty = LocalIndex::new(&target, None).unwrap();
+        empty.create_index(None).await.unwrap();
+        drop(empty);
+
+        let mut loader = BulkLoader::new(&target, None).await.unwrap();
+        loader.add(items(0..10)).await.unwrap();
+        loader.abort().unwrap();
+        assert!(!staging_path(&target).exists());
+
+        let mut loader = BulkLoader::new(&target, None).await.unwrap();
+        loader.add(items(0..10)).await.unwrap();
+        let index = loader.finish().await.unwrap();
+        assert_eq!(index.list_items(None).await.unwrap().len(), 10);
+    }
+}
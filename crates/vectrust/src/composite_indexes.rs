@@ -0,0 +1,189 @@
+// Copyright 2024-2026 Andrey Vasilevsky <anvanster@gmail.com>
+// SPDX-License-Identifier: Apache-2.0
+
+//! Composite secondary indexes over several metadata fields.
+//!
+//! Declared in [`MetadataConfig::composite_indexes`] or added to an
+//! existing index with [`LocalIndex::add_composite_index`], an index such
+//! as `(tenant, category)` files each item under its values of those
+//! fields. A filter pinning a leading run of them with equality, at its
+//! top level or in a top-level `$and`, then reads only the items holding
+//! those values instead of testing every item's metadata. The whole filter
+//! still runs on the items read.
+
+use crate::LocalIndex;
+use serde_json::Value;
+use vectrust_core::*;
+
+/// Fields a filter pins to a string, number or boolean, in the order they
+/// first appear
+pub(crate) fn pinned_in_filter(filter: &Value) -> Vec<(String, Value)> {
+    let clauses = filter.as_object().into_iter().flatten().chain(
+        filter
+            .get("$and")
+            .and_then(|and| and.as_array())
+            .into_iter()
+            .flatten()
+            .filter_map(|clause| clause.as_object())
+            .flatten(),
+    );
+    let mut pinned: Vec<(String, Value)> = Vec::new();
+    for (field, condition) in clauses.filter(|(key, _)| !key.starts_with('$')) {
+        let value = match condition {
+            Value::Object(ops) => ops.get("$eq"),
+            value => Some(value),
+        };
+        let Some(value) = value.filter(|v| v.is_string() || v.is_number() || v.is_boolean()) else {
+            continue;
+        };
+        if !pinned.iter().any(|(known, _)| known == field) {
+            pinned.push((field.clone(), value.clone()));
+        }
+    }
+    pinned
+}
+
+impl LocalIndex {
+    /// Add a composite index over `index.fields`, filing every stored item
+    /// under it. Adding an index the storage already keeps does nothing.
+    pub async fn add_composite_index(&self, index: CompositeIndex) -> Result<()> {
+        index.validate()?;
+        self.storage
+            .write_for("secondary_index")
+            .await
+            .add_composite_index(&index)
+            .await
+    }
+
+    /// Composite indexes the storage keeps
+    pub async fn composite_indexes(&self) -> Result<Vec<CompositeIndex>> {
+        self.storage
+            .read_for("config")
+            .await
+            .composite_indexes()
+            .await
+    }
+
+    /// Items a composite index holds for the values `filter` pins, or
+    /// `None` when it pins none an index covers
+    pub(crate) async fn composite_candidates(
+        storage: &dyn StorageBackend,
+        filter: &Value,
+    ) -> Result<Option<Vec<VectorItem>>> {
+        let pinned = pinned_in_filter(filter);
+        if pinned.is_empty() {
+            return Ok(None);
+        }
+        storage.list_composite(&pinned).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "rocksdb")]
+    use crate::CandidateSource;
+    #[cfg(feature = "rocksdb")]
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_pinned_in_filter() {
+        let pinned = pinned_in_filter(&serde_json::json!({
+            "tenant": "acme",
+            "score": {"$gt": 1},
+            "deleted": null,
+            "$and": [{"category": {"$eq": 3}}, {"tenant": "globex"}]
+        }));
+        assert_eq!(
+            pinned,
+            vec![
+                ("tenant".to_string(), serde_json::json!("acme")),
+                ("category".to_string(), serde_json::json!(3)),
+            ]
+        );
+    }
+
+    #[cfg(feature = "rocksdb")]
+    #[tokio::test]
+    async fn test_composite_indexes() {
+        let temp_dir = TempDir::new().unwrap();
+        let index = LocalIndex::new(temp_dir.path(), None).unwrap();
+        let mut config = CreateIndexConfig::default();
+        config.metadata_config.composite_indexes =
+            vec![CompositeIndex::new(["tenant", "category"])];
+        index.create_index(Some(config)).await.unwrap();
+        let items: Vec<VectorItem> = [
+            ("acme", "news", 2024),
+            ("acme", "news", 2025),
+            ("acme", "blog", 2025),
+            ("globex", "news", 2025),
+        ]
+        .iter()
+        .map(|(tenant, category, year)| VectorItem {
+            vector: vec![1.0, 1.0],
+            metadata: serde_json::json!({ "tenant": tenant, "category": category, "year": year }),
+            ..Default::default()
+        })
+        .collect();
+        index.insert_items(items).await.unwrap();
+
+        let explain = |filter: Value| {
+            let index = &index;
+            async move {
+                let query = Query {
+                    vector: Some(vec![1.0, 1.0]),
+                    top_k: 10,
+                    filter: Some(filter),
+                    ..Default::default()
+                };
+                index.explain_query(&query).await.unwrap()
+            }
+        };
+        let explanation = explain(serde_json::json!({
+            "category": "news", "tenant": "acme", "year": {"$gte": 2025}
+        }))
+        .await;
+        assert_eq!(
+            explanation.candidate_source,
+            CandidateSource::CompositeIndex
+        );
+        assert_eq!(explanation.candidates, 2);
+        assert_eq!(explanation.results.len(), 1);
+
+        // A leading field alone uses the index; a trailing one can't
+        let explanation = explain(serde_json::json!({ "tenant": "acme" })).await;
+        assert_eq!(
+            explanation.candidate_source,
+            CandidateSource::CompositeIndex
+        );
+        assert_eq!(explanation.candidates, 3);
+        let explanation = explain(serde_json::json!({ "category": "news" })).await;
+        assert_eq!(explanation.candidate_source, CandidateSource::Scan);
+
+        // Indexes added later cover the items already stored
+        index
+            .add_composite_index(CompositeIndex::new(["category", "year"]))
+            .await
+            .unwrap();
+        assert_eq!(index.composite_indexes().await.unwrap().len(), 2);
+        let explanation = explain(serde_json::json!({ "category": "news", "year": 2025 })).await;
+        assert_eq!(
+            explanation.candidate_source,
+            CandidateSource::CompositeIndex
+        );
+        assert_eq!(explanation.candidates, 2);
+        let listed = index
+            .list_items(Some(ListOptions {
+                limit: None,
+                offset: None,
+                filter: Some(serde_json::json!({ "category": "news", "year": 2025.0 })),
+            }))
+            .await
+            .unwrap();
+        assert_eq!(listed.len(), 2);
+        assert!(index
+            .add_composite_index(CompositeIndex::new(["tenant", "tenant"]))
+            .await
+            .is_err());
+    }
+}
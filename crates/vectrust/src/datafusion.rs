@@ -0,0 +1,343 @@
+// Copyright 2024-2026 Andrey Vasilevsky <anvanster@gmail.com>
+// SPDX-License-Identifier: Apache-2.0
+
+//! DataFusion `TableProvider` over an index, behind the `datafusion` feature.
+//!
+//! [`IndexTable`] serves the rows [`crate::arrow`] writes: an `id` column,
+//! a `vector` column of `FixedSizeList<Float32>` and one column per
+//! top-level metadata field. Its schema is taken from the items when the
+//! table is created; every scan reads the index afresh, leaving fields an
+//! item lacks null and dropping fields added since. Equality filters, and
+//! range filters on numeric columns, are passed to the index as metadata
+//! filters. With [`cosine_sim_udf`] registered,
+//! `ORDER BY cosine_sim(vector, [..]) DESC LIMIT 10` ranks rows by their
+//! similarity to a query vector.
+
+use crate::arrow::{
+    items_to_record_batch, DEFAULT_ARROW_BATCH_SIZE, ID_COLUMN, JSON_COLUMN_METADATA, VECTOR_COLUMN,
+};
+use crate::LocalIndex;
+use ::datafusion::arrow::array::{new_null_array, Array, ArrayRef, AsArray, Float32Array};
+use ::datafusion::arrow::compute::cast;
+use ::datafusion::arrow::datatypes::{DataType, Field, Float32Type, SchemaRef};
+use ::datafusion::arrow::record_batch::RecordBatch;
+use ::datafusion::catalog::Session;
+use ::datafusion::common::{plan_err, DataFusionError, ScalarValue};
+use ::datafusion::datasource::{TableProvider, TableType};
+use ::datafusion::logical_expr::{
+    BinaryExpr, ColumnarValue, Expr, Operator, ScalarFunctionArgs, ScalarUDF, ScalarUDFImpl,
+    Signature, TableProviderFilterPushDown, Volatility,
+};
+use ::datafusion::physical_plan::memory::MemoryExec;
+use ::datafusion::physical_plan::ExecutionPlan;
+use serde_json::{json, Value};
+use std::any::Any;
+use std::sync::Arc;
+use vectrust_core::*;
+
+type DataFusionResult<T> = std::result::Result<T, DataFusionError>;
+
+fn external(error: VectraError) -> DataFusionError {
+    DataFusionError::External(Box::new(error))
+}
+
+/// An index as a DataFusion table
+pub struct IndexTable {
+    index: Arc<LocalIndex>,
+    schema: SchemaRef,
+}
+
+impl std::fmt::Debug for IndexTable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("IndexTable")
+            .field("schema", &self.schema)
+            .finish_non_exhaustive()
+    }
+}
+
+impl IndexTable {
+    /// A table over `index`, with a column for each metadata field its
+    /// items hold now
+    pub async fn try_new(index: Arc<LocalIndex>) -> Result<Self> {
+        let batches = index
+            .scan_record_batches(None, DEFAULT_ARROW_BATCH_SIZE)
+            .await?;
+        let schema = match batches.first() {
+            Some(batch) => batch.schema(),
+            None => items_to_record_batch(&[])?.schema(),
+        };
+        Ok(Self { index, schema })
+    }
+
+    /// `filter` as a metadata filter clause, when the index can apply it.
+    /// The clause may pass rows the SQL filter doesn't, such as ones where
+    /// the field is null, so DataFusion applies the filter again.
+    fn metadata_clause(&self, filter: &Expr) -> Option<Value> {
+        let Expr::BinaryExpr(BinaryExpr { left, op, right }) = filter else {
+            return None;
+        };
+        let (column, op, literal) = match (left.as_ref(), right.as_ref()) {
+            (Expr::Column(column), Expr::Literal(literal)) => (column, *op, literal),
+            (Expr::Literal(literal), Expr::Column(column)) => (column, op.swap()?, literal),
+            _ => return None,
+        };
+        let field = self.schema.field_with_name(&column.name).ok()?;
+        let name = field.name();
+        // Dots would be read as a path into nested metadata
+        if name == ID_COLUMN
+            || name == VECTOR_COLUMN
+            || name.contains('.')
+            || field.metadata().contains_key(JSON_COLUMN_METADATA)
+        {
+            return None;
+        }
+
+        let numeric = matches!(field.data_type(), DataType::Int64 | DataType::Float64);
+        let value = match (field.data_type(), literal) {
+            (DataType::Utf8, ScalarValue::Utf8(Some(text))) => json!(text),
+            (DataType::Boolean, ScalarValue::Boolean(Some(flag))) => json!(flag),
+            (_, literal) if numeric => numeric_literal(literal)?,
+            _ => return None,
+        };
+        let operator = match op {
+            Operator::Eq => "$eq",
+            // Strings and booleans are only matched for equality
+            _ if !numeric => return None,
+            Operator::NotEq => "$ne",
+            Operator::Lt => "$lt",
+            Operator::LtEq => "$lte",
+            Operator::Gt => "$gt",
+            Operator::GtEq => "$gte",
+            _ => return None,
+        };
+        Some(json!({ name: { operator: value } }))
+    }
+}
+
+fn numeric_literal(literal: &ScalarValue) -> Option<Value> {
+    let value = match *literal {
+        ScalarValue::Int8(Some(v)) => json!(v),
+        ScalarValue::Int16(Some(v)) => json!(v),
+        ScalarValue::Int32(Some(v)) => json!(v),
+        ScalarValue::Int64(Some(v)) => json!(v),
+        ScalarValue::UInt8(Some(v)) => json!(v),
+        ScalarValue::UInt16(Some(v)) => json!(v),
+        ScalarValue::UInt32(Some(v)) => json!(v),
+        ScalarValue::UInt64(Some(v)) => json!(v),
+        ScalarValue::Float32(Some(v)) if v.is_finite() => json!(v),
+        ScalarValue::Float64(Some(v)) if v.is_finite() => json!(v),
+        _ => return None,
+    };
+    Some(value)
+}
+
+/// `batch`'s columns in `schema`'s order and types, with null columns for
+/// fields none of its items hold
+fn conform(batch: &RecordBatch, schema: &SchemaRef) -> DataFusionResult<RecordBatch> {
+    let columns = schema
+        .fields()
+        .iter()
+        .map(|field| match batch.column_by_name(field.name()) {
+            Some(column) if column.data_type() == field.data_type() => Ok(column.clone()),
+            Some(column) => Ok(cast(column, field.data_type())?),
+            None => Ok(new_null_array(field.data_type(), batch.num_rows())),
+        })
+        .collect::<DataFusionResult<Vec<ArrayRef>>>()?;
+    Ok(RecordBatch::try_new(schema.clone(), columns)?)
+}
+
+#[async_trait::async_trait]
+impl TableProvider for IndexTable {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn schema(&self) -> SchemaRef {
+        self.schema.clone()
+    }
+
+    fn table_type(&self) -> TableType {
+        TableType::Base
+    }
+
+    fn supports_filters_pushdown(
+        &self,
+        filters: &[&Expr],
+    ) -> DataFusionResult<Vec<TableProviderFilterPushDown>> {
+        Ok(filters
+            .iter()
+            .map(|filter| match self.metadata_clause(filter) {
+                Some(_) => TableProviderFilterPushDown::Inexact,
+                None => TableProviderFilterPushDown::Unsupported,
+            })
+            .collect())
+    }
+
+    async fn scan(
+        &self,
+        _state: &dyn Session,
+        projection: Option<&Vec<usize>>,
+        filters: &[Expr],
+        limit: Option<usize>,
+    ) -> DataFusionResult<Arc<dyn ExecutionPlan>> {
+        let clauses: Vec<Value> = filters
+            .iter()
+            .filter_map(|filter| self.metadata_clause(filter))
+            .collect();
+        // DataFusion only passes a limit when no filter is left to apply
+        let limit = limit.filter(|_| filters.is_empty());
+        let options = ListOptions {
+            limit,
+            offset: None,
+            filter: (!clauses.is_empty()).then(|| json!({ "$and": clauses })),
+        };
+        let batches = self
+            .index
+            .scan_record_batches(Some(options), DEFAULT_ARROW_BATCH_SIZE)
+            .await
+            .map_err(external)?
+            .iter()
+            .map(|batch| conform(batch, &self.schema))
+            .collect::<DataFusionResult<Vec<_>>>()?;
+        let plan = MemoryExec::try_new(&[batches], self.schema.clone(), projection.cloned())?;
+        Ok(Arc::new(plan))
+    }
+}
+
+/// `cosine_sim(a, b)`: cosine similarity of two vector columns or
+/// literals, such as the `vector` column and an array of the query's
+/// values. Null when either side is.
+pub fn cosine_sim_udf() -> ScalarUDF {
+    ScalarUDF::from(CosineSim {
+        signature: Signature::any(2, Volatility::Immutable),
+    })
+}
+
+#[derive(Debug)]
+struct CosineSim {
+    signature: Signature,
+}
+
+impl ScalarUDFImpl for CosineSim {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn name(&self) -> &str {
+        "cosine_sim"
+    }
+
+    fn signature(&self) -> &Signature {
+        &self.signature
+    }
+
+    fn return_type(&self, arg_types: &[DataType]) -> DataFusionResult<DataType> {
+        let is_list = |data_type: &DataType| {
+            matches!(
+                data_type,
+                DataType::List(_) | DataType::LargeList(_) | DataType::FixedSizeList(..)
+            )
+        };
+        if !arg_types.iter().all(is_list) {
+            return plan_err!("cosine_sim takes two lists of numbers, got {arg_types:?}");
+        }
+        Ok(DataType::Float32)
+    }
+
+    fn invoke_with_args(&self, args: ScalarFunctionArgs) -> DataFusionResult<ColumnarValue> {
+        let arrays = ColumnarValue::values_to_arrays(&args.args)?;
+        let a = vectors(&arrays[0])?;
+        let b = vectors(&arrays[1])?;
+        let scores: Float32Array = a
+            .iter()
+            .zip(&b)
+            .map(|(a, b)| Some(VectorOps::cosine_similarity(a.as_ref()?, b.as_ref()?)))
+            .collect();
+        Ok(ColumnarValue::Array(Arc::new(scores)))
+    }
+}
+
+/// Each row of a list column as `f32`s
+fn vectors(array: &ArrayRef) -> DataFusionResult<Vec<Option<Vec<f32>>>> {
+    let item = Arc::new(Field::new_list_field(DataType::Float32, true));
+    let lists = cast(array, &DataType::List(item))?;
+    let lists = lists.as_list::<i32>();
+    Ok((0..lists.len())
+        .map(|row| {
+            (!lists.is_null(row)).then(|| {
+                lists
+                    .value(row)
+                    .as_primitive::<Float32Type>()
+                    .values()
+                    .to_vec()
+            })
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ::datafusion::arrow::datatypes::Int64Type;
+    use ::datafusion::prelude::SessionContext;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_sql_over_index() {
+        let temp_dir = TempDir::new().unwrap();
+        let index = Arc::new(LocalIndex::new(temp_dir.path(), None).unwrap());
+        index.create_index(None).await.unwrap();
+        let items = (0..6)
+            .map(|i| VectorItem {
+                vector: vec![1.0, i as f32],
+                metadata: json!({ "lang": if i % 2 == 0 { "en" } else { "de" }, "year": 2020 + i }),
+                ..Default::default()
+            })
+            .collect();
+        index.insert_items(items).await.unwrap();
+
+        let table = IndexTable::try_new(index.clone()).await.unwrap();
+        let lang = Expr::Column("lang".into()).eq(Expr::Literal(ScalarValue::from("en")));
+        let year = Expr::Column("year".into()).gt(Expr::Literal(ScalarValue::Int64(Some(2021))));
+        let vector = Expr::Column("vector".into()).is_not_null();
+        assert_eq!(
+            table
+                .supports_filters_pushdown(&[&lang, &year, &vector])
+                .unwrap(),
+            [
+                TableProviderFilterPushDown::Inexact,
+                TableProviderFilterPushDown::Inexact,
+                TableProviderFilterPushDown::Unsupported,
+            ]
+        );
+
+        let ctx = SessionContext::new();
+        ctx.register_table("items", Arc::new(table)).unwrap();
+        ctx.register_udf(cosine_sim_udf());
+        let batches = ctx
+            .sql(
+                "SELECT year, cosine_sim(vector, [0.0, 1.0]) AS score FROM items \
+                 WHERE lang = 'en' AND year >= 2021 ORDER BY score DESC LIMIT 1",
+            )
+            .await
+            .unwrap()
+            .collect()
+            .await
+            .unwrap();
+        assert_eq!(batches.iter().map(|b| b.num_rows()).sum::<usize>(), 1);
+        let years = batches[0].column(0).as_primitive::<Int64Type>();
+        assert_eq!(years.value(0), 2024);
+        let scores = batches[0].column(1).as_primitive::<Float32Type>();
+        assert!(scores.value(0) > 0.9);
+
+        let count = ctx
+            .sql("SELECT count(*) FROM items WHERE lang = 'de'")
+            .await
+            .unwrap()
+            .collect()
+            .await
+            .unwrap();
+        let count = count[0].column(0).as_primitive::<Int64Type>();
+        assert_eq!(count.value(0), 3);
+    }
+}
@@ -0,0 +1,149 @@
+// Copyright 2024-2026 Andrey Vasilevsky <anvanster@gmail.com>
+// SPDX-License-Identifier: Apache-2.0
+
+//! Finding and fixing items whose vectors have the wrong length.
+//!
+//! Legacy `index.json` files don't enforce a dimension, so indexes written
+//! across a model change can hold vectors of several lengths, and queries
+//! silently skip every item that doesn't match the query.
+//! [`LocalIndex::dimension_report`] counts items per length,
+//! [`LocalIndex::open_degraded`] quarantines the odd ones out, and
+//! [`LocalIndex::fix_dimensions`] re-embeds or deletes them.
+
+use crate::reindex::embed_items;
+use crate::{EmbeddingFn, LocalIndex, RepairReport};
+use vectrust_core::*;
+
+/// Items re-embedded per call of the embedding function
+const FIX_BATCH_SIZE: usize = 256;
+
+impl LocalIndex {
+    /// Items per vector length, and those not of the most common length.
+    /// Quarantined items aren't counted.
+    pub async fn dimension_report(&self) -> Result<DimensionReport> {
+        let items = self.storage.read_for("list").await.list_items(None).await?;
+        Ok(DimensionReport::from_items(&items))
+    }
+
+    /// Give every item whose vector isn't the most common length a new
+    /// vector from `embedder`, or delete it when there is none. Fails
+    /// without writing a batch whose new vectors still have the wrong
+    /// length.
+    ///
+    /// Works on the items this handle serves; open the index normally
+    /// rather than with [`open_degraded`](Self::open_degraded), which
+    /// hides them.
+    pub async fn fix_dimensions(&self, embedder: Option<&dyn EmbeddingFn>) -> Result<RepairReport> {
+        let items = self.storage.read_for("list").await.list_items(None).await?;
+        let report = DimensionReport::from_items(&items);
+        let Some(expected) = report.expected else {
+            return Ok(RepairReport::default());
+        };
+        let mismatched: Vec<VectorItem> = items
+            .into_iter()
+            .filter(|item| item.vector.len() != expected)
+            .collect();
+
+        let mut fixed = RepairReport::default();
+        let Some(embedder) = embedder else {
+            for item in mismatched {
+                self.remove_item(&item.id).await?;
+                fixed.dropped.push(item.id);
+            }
+            return Ok(fixed);
+        };
+
+        let model = self.embedding_model().await?;
+        for batch in mismatched.chunks(FIX_BATCH_SIZE) {
+            let mut batch = embed_items(embedder, model.as_ref(), batch)?;
+            if let Some(item) = batch.iter().find(|item| item.vector.len() != expected) {
+                return Err(VectraError::InvalidDimensions {
+                    expected,
+                    actual: item.vector.len(),
+                });
+            }
+            let now = chrono::Utc::now();
+            for item in &mut batch {
+                item.updated_at = now;
+            }
+            fixed.restored.extend(batch.iter().map(|item| item.id));
+            self.put_items(batch).await?;
+        }
+        Ok(fixed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    async fn mixed_index(dir: &std::path::Path) -> (LocalIndex, Vec<VectorItem>) {
+        // Legacy storage accepts mixed dimensions, as older indexes may hold
+        let storage = vectrust_storage::LegacyStorage::new(dir, "index.json").unwrap();
+        let index =
+            LocalIndex::with_storage(dir.into(), "index.json".into(), Box::new(storage)).unwrap();
+        index.create_index(None).await.unwrap();
+        let lengths = [3, 3, 3, 2, 4];
+        let items: Vec<VectorItem> = lengths
+            .iter()
+            .map(|&len| VectorItem {
+                vector: vec![1.0; len],
+                metadata: serde_json::json!({ "len": len }),
+                ..Default::default()
+            })
+            .collect();
+        let items = index.insert_items(items).await.unwrap();
+        (index, items)
+    }
+
+    #[tokio::test]
+    async fn test_dimension_report_and_quarantine() {
+        let temp_dir = TempDir::new().unwrap();
+        let (index, items) = mixed_index(temp_dir.path()).await;
+
+        let report = index.dimension_report().await.unwrap();
+        assert!(report.is_mixed());
+        assert_eq!(report.expected, Some(3));
+        assert_eq!(report.counts[&3], 3);
+        assert_eq!(report.counts[&2], 1);
+        let mut mismatched = vec![items[3].id, items[4].id];
+        mismatched.sort();
+        assert_eq!(report.mismatched, mismatched);
+        assert_eq!(index.get_stats().await.unwrap().dimensions, Some(3));
+
+        let health = index.quarantine_damaged().await.unwrap();
+        assert_eq!(health.quarantined.len(), 2);
+        assert!(health.quarantined[0].reason.contains("most items have 3"));
+        assert!(!index.dimension_report().await.unwrap().is_mixed());
+        assert_eq!(index.list_items(None).await.unwrap().len(), 3);
+        assert!(index.get_item(&items[4].id).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_fix_dimensions() {
+        let temp_dir = TempDir::new().unwrap();
+        let (index, items) = mixed_index(&temp_dir.path().join("embed")).await;
+        let embed =
+            |items: &[VectorItem]| -> Result<Vec<Vec<f32>>> { Ok(vec![vec![0.5; 3]; items.len()]) };
+        let fixed = index.fix_dimensions(Some(&embed)).await.unwrap();
+        assert_eq!(fixed.restored.len(), 2);
+        assert!(!index.dimension_report().await.unwrap().is_mixed());
+        let refreshed = index.get_item(&items[3].id).await.unwrap().unwrap();
+        assert_eq!(refreshed.vector, vec![0.5; 3]);
+        assert_eq!(refreshed.metadata["len"], 2);
+
+        let wrong =
+            |items: &[VectorItem]| -> Result<Vec<Vec<f32>>> { Ok(vec![vec![0.5; 5]; items.len()]) };
+        let (index, _) = mixed_index(&temp_dir.path().join("wrong")).await;
+        let err = index.fix_dimensions(Some(&wrong)).await.unwrap_err();
+        assert!(
+            matches!(err, VectraError::InvalidDimensions { .. }),
+            "{err}"
+        );
+
+        let fixed = index.fix_dimensions(None).await.unwrap();
+        assert_eq!(fixed.dropped.len(), 2);
+        assert_eq!(index.get_stats().await.unwrap().items, 3);
+    }
+}
@@ -0,0 +1,118 @@
+// Copyright 2024-2026 Andrey Vasilevsky <anvanster@gmail.com>
+// SPDX-License-Identifier: Apache-2.0
+
+//! Disk space checks before writes.
+//!
+//! Bulk inserts, compaction and snapshots estimate the space they need
+//! and fail up front with [`VectraError::DiskSpace`] rather than running
+//! out part way through. With [`RuntimeConfig::min_free_disk_bytes`] set,
+//! every write also keeps that much space free, and the index reports
+//! itself read-only while free space is below the floor, accepting
+//! writes again once space is freed.
+
+use crate::LocalIndex;
+use std::path::Path;
+use vectrust_core::*;
+
+/// Stored bytes per byte of raw item data, allowing for the log, the
+/// metadata store and vector file padding
+const WRITE_OVERHEAD: u64 = 2;
+
+/// Free space on the file system holding `path`, which may not exist yet
+fn available_space(path: &Path) -> Result<u64> {
+    let existing = path
+        .ancestors()
+        .find(|dir| dir.exists())
+        .unwrap_or_else(|| Path::new("."));
+    Ok(fs2::available_space(existing)?)
+}
+
+impl LocalIndex {
+    /// Fail unless `needed` bytes can be written while keeping the
+    /// configured floor free
+    pub(crate) fn ensure_disk_space(&self, needed: u64) -> Result<()> {
+        let floor = self.disk_space_floor().unwrap_or(0);
+        let needed = needed.saturating_add(floor);
+        let available = available_space(&self.path)?;
+        if available < needed {
+            return Err(VectraError::DiskSpace {
+                path: self.path.to_string_lossy().to_string(),
+                needed,
+                available,
+            });
+        }
+        Ok(())
+    }
+
+    /// [`ensure_disk_space`](Self::ensure_disk_space) for writing `items`,
+    /// skipped when no floor is configured so small writes don't pay for
+    /// the check
+    pub(crate) fn ensure_space_for_items(&self, items: &[VectorItem]) -> Result<()> {
+        if self.disk_space_floor().is_none() {
+            return Ok(());
+        }
+        self.ensure_disk_space(estimate_items(items))
+    }
+
+    /// Whether free space is under the configured floor
+    pub(crate) fn is_low_on_disk_space(&self) -> Result<bool> {
+        match self.disk_space_floor() {
+            Some(floor) => Ok(available_space(&self.path)? < floor),
+            None => Ok(false),
+        }
+    }
+
+    fn disk_space_floor(&self) -> Option<u64> {
+        self.runtime_config.lock().unwrap().min_free_disk_bytes
+    }
+}
+
+/// Disk space writing `items` is expected to take
+pub(crate) fn estimate_items(items: &[VectorItem]) -> u64 {
+    items.iter().map(crate::approximate_size).sum::<u64>() * WRITE_OVERHEAD
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn item() -> VectorItem {
+        VectorItem {
+            vector: vec![1.0, 0.0],
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_disk_space_floor() {
+        let temp_dir = TempDir::new().unwrap();
+        let index = LocalIndex::new(temp_dir.path(), None).unwrap();
+        index.create_index(None).await.unwrap();
+        index.insert_items(vec![item()]).await.unwrap();
+        assert!(!index.is_read_only().await.unwrap());
+
+        // A floor no disk can meet refuses writes and turns the index read-only
+        let mut config = index.runtime_config();
+        config.min_free_disk_bytes = Some(u64::MAX);
+        index.set_runtime_config(config.clone()).await.unwrap();
+        let err = index.insert_item(item()).await.unwrap_err();
+        assert!(matches!(err, VectraError::DiskSpace { .. }), "{err}");
+        assert!(index.insert_items(vec![item()]).await.is_err());
+        assert!(index.compact(None).await.is_err());
+        assert!(index.is_read_only().await.unwrap());
+        assert_eq!(index.get_stats().await.unwrap().items, 1);
+
+        config.min_free_disk_bytes = None;
+        index.set_runtime_config(config).await.unwrap();
+        index.insert_item(item()).await.unwrap();
+        assert!(!index.is_read_only().await.unwrap());
+    }
+
+    #[test]
+    fn test_available_space_of_missing_path() {
+        let temp_dir = TempDir::new().unwrap();
+        let missing = temp_dir.path().join("not").join("yet");
+        assert!(available_space(&missing).unwrap() > 0);
+    }
+}
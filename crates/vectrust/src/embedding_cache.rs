@@ -0,0 +1,362 @@
+// Copyright 2024-2026 Andrey Vasilevsky <anvanster@gmail.com>
+// SPDX-License-Identifier: Apache-2.0
+
+//! Persistent cache of computed embeddings.
+//!
+//! [`CachedEmbedder`] wraps an [`EmbeddingFn`] and keeps each vector it
+//! computes in the index folder, keyed by a hash of the model name and the
+//! item's content. Embedding an item whose content was seen before, under
+//! whatever id, reuses the stored vector instead of calling the model, so
+//! recurrent crawls only pay for documents that changed.
+
+use crate::{EmbeddingFn, LocalIndex};
+use std::collections::{HashMap, HashSet};
+use std::fs::OpenOptions;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use vectrust_core::*;
+
+/// File holding cached embeddings inside an index folder
+pub(crate) const EMBEDDING_CACHE_FILE: &str = "embedding_cache.bin";
+
+/// Hash of a model name and an item's content
+type ContentHash = [u8; 32];
+
+/// Counters for a [`CachedEmbedder`] since it was opened
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct EmbeddingCacheStats {
+    /// Items whose vector came from the cache
+    pub hits: usize,
+    /// Items sent to the wrapped embedding function
+    pub misses: usize,
+    /// Vectors in the cache, for every model
+    pub entries: usize,
+}
+
+/// An [`EmbeddingFn`] that only calls the one it wraps for content it
+/// hasn't embedded before.
+///
+/// An item's content is its metadata, or just the metadata field set with
+/// [`with_content_field`](Self::with_content_field), ignoring the
+/// embedding model tag. Vectors are appended to the cache file as they are
+/// computed; a record cut short by a crash is dropped on the next open.
+pub struct CachedEmbedder {
+    inner: Arc<dyn EmbeddingFn>,
+    model: String,
+    content_field: Option<String>,
+    file: PathBuf,
+    vectors: Mutex<HashMap<ContentHash, Vec<f32>>>,
+    hits: AtomicUsize,
+    misses: AtomicUsize,
+}
+
+impl CachedEmbedder {
+    /// Open the cache in `folder` for vectors `inner` computes with `model`.
+    /// Caches for different models share the file without mixing.
+    pub fn open(folder: &Path, model: &str, inner: Arc<dyn EmbeddingFn>) -> Result<Self> {
+        let file = folder.join(EMBEDDING_CACHE_FILE);
+        let vectors = match std::fs::read(&file) {
+            Ok(bytes) => {
+                let (vectors, valid) = read_records(&bytes);
+                // Cut off a torn record so later appends start on a boundary
+                if valid < bytes.len() {
+                    OpenOptions::new()
+                        .write(true)
+                        .open(&file)?
+                        .set_len(valid as u64)?;
+                }
+                vectors
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => HashMap::new(),
+            Err(e) => return Err(e.into()),
+        };
+        Ok(Self {
+            inner,
+            model: model.to_string(),
+            content_field: None,
+            file,
+            vectors: Mutex::new(vectors),
+            hits: AtomicUsize::new(0),
+            misses: AtomicUsize::new(0),
+        })
+    }
+
+    /// Key items by the metadata field `field` alone, such as the document
+    /// text, so edits to other metadata don't cost an embedding call
+    pub fn with_content_field(mut self, field: &str) -> Self {
+        self.content_field = Some(field.to_string());
+        self
+    }
+
+    pub fn stats(&self) -> EmbeddingCacheStats {
+        EmbeddingCacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            entries: self.vectors.lock().unwrap().len(),
+        }
+    }
+
+    /// Drop every cached vector, for every model
+    pub fn clear(&self) -> Result<()> {
+        let mut vectors = self.vectors.lock().unwrap();
+        match std::fs::remove_file(&self.file) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+            _ => {}
+        }
+        vectors.clear();
+        Ok(())
+    }
+
+    fn content_hash(&self, item: &VectorItem) -> ContentHash {
+        let mut hasher = blake3::Hasher::new();
+        hasher.update(self.model.as_bytes());
+        hasher.update(&[0]);
+        match self.content_field {
+            Some(ref field) => hash_value(&mut hasher, &item.metadata[field.as_str()]),
+            None => {
+                let mut metadata = item.metadata.clone();
+                if let Some(object) = metadata.as_object_mut() {
+                    object.remove(EMBEDDING_MODEL_KEY);
+                }
+                hash_value(&mut hasher, &metadata);
+            }
+        }
+        *hasher.finalize().as_bytes()
+    }
+
+    /// Append newly computed vectors to the cache file
+    fn persist(&self, records: &[(ContentHash, &[f32])]) -> Result<()> {
+        if let Some(parent) = self.file.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.file)?;
+        let mut writer = BufWriter::new(file);
+        for (hash, vector) in records {
+            writer.write_all(hash)?;
+            writer.write_all(&(vector.len() as u32).to_le_bytes())?;
+            for value in vector.iter() {
+                writer.write_all(&value.to_le_bytes())?;
+            }
+        }
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+impl EmbeddingFn for CachedEmbedder {
+    fn embed(&self, items: &[VectorItem]) -> Result<Vec<Vec<f32>>> {
+        let hashes: Vec<ContentHash> = items.iter().map(|item| self.content_hash(item)).collect();
+
+        // Each distinct uncached content is embedded once per batch
+        let mut vectors: Vec<Option<Vec<f32>>> = Vec::with_capacity(items.len());
+        let mut missing: Vec<usize> = Vec::new();
+        {
+            let cached = self.vectors.lock().unwrap();
+            let mut seen = HashSet::new();
+            for (index, hash) in hashes.iter().enumerate() {
+                let vector = cached.get(hash).cloned();
+                if vector.is_none() && seen.insert(*hash) {
+                    missing.push(index);
+                }
+                vectors.push(vector);
+            }
+        }
+        self.misses.fetch_add(missing.len(), Ordering::Relaxed);
+        self.hits
+            .fetch_add(items.len() - missing.len(), Ordering::Relaxed);
+        if missing.is_empty() {
+            return Ok(vectors.into_iter().flatten().collect());
+        }
+
+        let batch: Vec<VectorItem> = missing.iter().map(|&i| items[i].clone()).collect();
+        let computed = self.inner.embed(&batch)?;
+        if computed.len() != batch.len() {
+            return Err(VectraError::VectorValidation {
+                message: format!(
+                    "Embedding function returned {} vectors for {} items",
+                    computed.len(),
+                    batch.len()
+                ),
+            });
+        }
+        let computed: HashMap<ContentHash, Vec<f32>> =
+            missing.iter().map(|&i| hashes[i]).zip(computed).collect();
+        {
+            let mut cached = self.vectors.lock().unwrap();
+            let records: Vec<(ContentHash, &[f32])> = computed
+                .iter()
+                .map(|(hash, vector)| (*hash, vector.as_slice()))
+                .collect();
+            self.persist(&records)?;
+            cached.extend(
+                computed
+                    .iter()
+                    .map(|(hash, vector)| (*hash, vector.clone())),
+            );
+        }
+        Ok(vectors
+            .into_iter()
+            .zip(&hashes)
+            .map(|(vector, hash)| vector.unwrap_or_else(|| computed[hash].clone()))
+            .collect())
+    }
+}
+
+impl LocalIndex {
+    /// Wrap `inner` in an embedding cache kept in this index's folder; see
+    /// [`CachedEmbedder`]
+    pub fn cached_embedder(
+        &self,
+        model: &str,
+        inner: Arc<dyn EmbeddingFn>,
+    ) -> Result<CachedEmbedder> {
+        CachedEmbedder::open(&self.path, model, inner)
+    }
+}
+
+/// Hash `value` with object keys in sorted order, so the same content
+/// hashes the same however its keys were ordered
+fn hash_value(hasher: &mut blake3::Hasher, value: &serde_json::Value) {
+    match value {
+        serde_json::Value::Object(object) => {
+            let mut keys: Vec<&String> = object.keys().collect();
+            keys.sort();
+            hasher.update(b"{");
+            for key in keys {
+                hash_value(hasher, &serde_json::Value::String(key.clone()));
+                hash_value(hasher, &object[key]);
+            }
+            hasher.update(b"}");
+        }
+        serde_json::Value::Array(values) => {
+            hasher.update(b"[");
+            for value in values {
+                hash_value(hasher, value);
+            }
+            hasher.update(b"]");
+        }
+        scalar => {
+            let encoded = scalar.to_string();
+            hasher.update(&(encoded.len() as u64).to_le_bytes());
+            hasher.update(encoded.as_bytes());
+        }
+    }
+}
+
+/// Decode cache records up to the end of `bytes` or a truncated record,
+/// returning them with the length of the bytes they take up
+fn read_records(mut bytes: &[u8]) -> (HashMap<ContentHash, Vec<f32>>, usize) {
+    let total = bytes.len();
+    let mut vectors = HashMap::new();
+    while bytes.len() >= 36 {
+        let (hash, rest) = bytes.split_at(32);
+        let (len, rest) = rest.split_at(4);
+        let len = u32::from_le_bytes([len[0], len[1], len[2], len[3]]) as usize;
+        if rest.len() / 4 < len {
+            break;
+        }
+        let (vector, rest) = rest.split_at(len * 4);
+        let mut key = [0u8; 32];
+        key.copy_from_slice(hash);
+        let vector = vector
+            .chunks_exact(4)
+            .map(|chunk| f32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+            .collect();
+        vectors.insert(key, vector);
+        bytes = rest;
+    }
+    (vectors, total - bytes.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    /// Embeds the length of each item's `text`, counting the items it sees
+    fn counting_embedder(calls: Arc<AtomicUsize>) -> Arc<dyn EmbeddingFn> {
+        Arc::new(move |items: &[VectorItem]| {
+            calls.fetch_add(items.len(), Ordering::Relaxed);
+            Ok(items
+                .iter()
+                .map(|item| vec![item.metadata["text"].as_str().unwrap_or("").len() as f32])
+                .collect())
+        })
+    }
+
+    fn doc(text: &str, source: &str) -> VectorItem {
+        VectorItem {
+            metadata: serde_json::json!({ "text": text, "source": source }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_cached_embedder_skips_seen_content() {
+        let temp_dir = TempDir::new().unwrap();
+        let calls = Arc::new(AtomicUsize::new(0));
+        let cache =
+            CachedEmbedder::open(temp_dir.path(), "model-a", counting_embedder(calls.clone()))
+                .unwrap();
+
+        let vectors = cache
+            .embed(&[doc("one", "a"), doc("three", "a"), doc("one", "a")])
+            .unwrap();
+        assert_eq!(vectors, vec![vec![3.0], vec![5.0], vec![3.0]]);
+        assert_eq!(calls.load(Ordering::Relaxed), 2);
+
+        // New ids, same content
+        cache.embed(&[doc("three", "a")]).unwrap();
+        assert_eq!(calls.load(Ordering::Relaxed), 2);
+        assert_eq!(
+            cache.stats(),
+            EmbeddingCacheStats {
+                hits: 2,
+                misses: 2,
+                entries: 2
+            }
+        );
+        drop(cache);
+
+        // Vectors survive reopening, and a torn last record is ignored
+        let file = temp_dir.path().join(EMBEDDING_CACHE_FILE);
+        let mut bytes = std::fs::read(&file).unwrap();
+        bytes.extend_from_slice(&[7; 10]);
+        std::fs::write(&file, bytes).unwrap();
+        let cache =
+            CachedEmbedder::open(temp_dir.path(), "model-a", counting_embedder(calls.clone()))
+                .unwrap();
+        cache.embed(&[doc("one", "a")]).unwrap();
+        assert_eq!(calls.load(Ordering::Relaxed), 2);
+
+        // Other metadata counts as content unless a content field is set
+        cache.embed(&[doc("one", "b")]).unwrap();
+        assert_eq!(calls.load(Ordering::Relaxed), 3);
+        let cache = cache.with_content_field("text");
+        cache.embed(&[doc("one", "c")]).unwrap();
+        cache.embed(&[doc("one", "d")]).unwrap();
+        assert_eq!(calls.load(Ordering::Relaxed), 4);
+
+        // Vectors appended after the torn record was cut off read back
+        let reopened =
+            CachedEmbedder::open(temp_dir.path(), "model-a", counting_embedder(calls.clone()))
+                .unwrap();
+        assert_eq!(reopened.stats().entries, 4);
+
+        // Another model doesn't see these vectors
+        let other =
+            CachedEmbedder::open(temp_dir.path(), "model-b", counting_embedder(calls.clone()))
+                .unwrap();
+        other.embed(&[doc("one", "a")]).unwrap();
+        assert_eq!(calls.load(Ordering::Relaxed), 5);
+
+        other.clear().unwrap();
+        assert_eq!(other.stats().entries, 0);
+        assert!(!file.exists());
+    }
+}
@@ -0,0 +1,488 @@
+// Copyright 2024-2026 Andrey Vasilevsky <anvanster@gmail.com>
+// SPDX-License-Identifier: Apache-2.0
+
+//! Importing embeddings straight from the files embedding jobs write.
+//!
+//! An [`EmbeddingImport`] pairs a row-major `.npy` or `.safetensors`
+//! matrix with a JSONL file holding one record per row, either
+//! `{"id": ..., "metadata": ...}` (both optional) or a bare id string.
+//! Rows are decoded from the matrix bytes directly into item vectors, so
+//! vectors never pass through JSON, and only one batch is held in memory.
+//! [`LocalIndex::import_embeddings`] inserts the batches into an open
+//! index and [`BulkLoader::import_embeddings`] stages them for a new one.
+
+use crate::{BulkLoader, LocalIndex};
+use serde::Deserialize;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Lines, Read, Seek, SeekFrom};
+use std::path::Path;
+use vectrust_core::*;
+
+/// Rows read and inserted per batch
+const IMPORT_BATCH_SIZE: usize = 10_000;
+
+const NPY_MAGIC: &[u8] = b"\x93NUMPY";
+
+/// Element types the matrix may be stored in; all are little-endian
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Dtype {
+    F16,
+    BF16,
+    F32,
+    F64,
+}
+
+impl Dtype {
+    fn size(self) -> usize {
+        match self {
+            Dtype::F16 | Dtype::BF16 => 2,
+            Dtype::F32 => 4,
+            Dtype::F64 => 8,
+        }
+    }
+
+    fn decode(self, bytes: &[u8], out: &mut Vec<f32>) {
+        let chunks = bytes.chunks_exact(self.size());
+        match self {
+            Dtype::F16 => out.extend(chunks.map(|b| f16_to_f32(u16::from_le_bytes([b[0], b[1]])))),
+            Dtype::BF16 => out.extend(
+                chunks.map(|b| f32::from_bits((u16::from_le_bytes([b[0], b[1]]) as u32) << 16)),
+            ),
+            Dtype::F32 => out.extend(chunks.map(|b| f32::from_le_bytes(b.try_into().unwrap()))),
+            Dtype::F64 => {
+                out.extend(chunks.map(|b| f64::from_le_bytes(b.try_into().unwrap()) as f32))
+            }
+        }
+    }
+}
+
+fn f16_to_f32(bits: u16) -> f32 {
+    let exponent = (bits >> 10) & 0x1f;
+    let fraction = (bits & 0x3ff) as u32;
+    let magnitude = match exponent {
+        0 => fraction as f32 * 2f32.powi(-24),
+        0x1f if fraction == 0 => f32::INFINITY,
+        0x1f => f32::NAN,
+        _ => f32::from_bits(((exponent as u32 + 112) << 23) | (fraction << 13)),
+    };
+    if bits & 0x8000 != 0 {
+        -magnitude
+    } else {
+        magnitude
+    }
+}
+
+/// One line of the records file
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Record {
+    Id(uuid::Uuid),
+    Item {
+        #[serde(default)]
+        id: Option<uuid::Uuid>,
+        #[serde(default)]
+        metadata: serde_json::Value,
+    },
+}
+
+/// Where the matrix is in its file, and its layout
+struct MatrixLayout {
+    offset: u64,
+    dtype: Dtype,
+    rows: usize,
+    dimensions: usize,
+}
+
+/// A matrix of embeddings and the records naming its rows, read in batches
+pub struct EmbeddingImport {
+    matrix: BufReader<File>,
+    records: Lines<BufReader<File>>,
+    dtype: Dtype,
+    rows: usize,
+    dimensions: usize,
+    row: usize,
+    buffer: Vec<u8>,
+}
+
+impl EmbeddingImport {
+    /// Open `matrix`, a 2-D `.npy` or `.safetensors` file with one
+    /// embedding per row, and `records`, its JSONL records. A safetensors
+    /// file holding several tensors needs `tensor` to name one; the format
+    /// is told by the file's contents, not its extension.
+    pub fn open(
+        matrix: impl AsRef<Path>,
+        records: impl AsRef<Path>,
+        tensor: Option<&str>,
+    ) -> Result<Self> {
+        let mut file = BufReader::new(File::open(matrix.as_ref())?);
+        let mut magic = [0u8; 6];
+        file.read_exact(&mut magic)?;
+        file.seek(SeekFrom::Start(0))?;
+        let layout = if magic == NPY_MAGIC {
+            read_npy_header(&mut file)?
+        } else {
+            read_safetensors_header(&mut file, tensor)?
+        };
+        file.seek(SeekFrom::Start(layout.offset))?;
+
+        Ok(Self {
+            matrix: file,
+            records: BufReader::new(File::open(records.as_ref())?).lines(),
+            dtype: layout.dtype,
+            rows: layout.rows,
+            dimensions: layout.dimensions,
+            row: 0,
+            buffer: vec![0; layout.dimensions * layout.dtype.size()],
+        })
+    }
+
+    /// Rows in the matrix
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    /// Length of each embedding
+    pub fn dimensions(&self) -> usize {
+        self.dimensions
+    }
+
+    /// The next `max` rows or fewer as items, empty once every row has
+    /// been read. Items without an id in their record get one on insert.
+    /// Fails when the records file has fewer or more records than rows.
+    pub fn next_batch(&mut self, max: usize) -> Result<Vec<VectorItem>> {
+        let count = max.min(self.rows - self.row);
+        let mut items = Vec::with_capacity(count);
+        for _ in 0..count {
+            let (id, metadata) = match self.next_record()? {
+                Some(Record::Id(id)) => (Some(id), serde_json::Value::Null),
+                Some(Record::Item { id, metadata }) => (id, metadata),
+                None => return Err(self.count_mismatch()),
+            };
+            self.matrix.read_exact(&mut self.buffer)?;
+            let mut vector = Vec::with_capacity(self.dimensions);
+            self.dtype.decode(&self.buffer, &mut vector);
+            items.push(VectorItem {
+                id: id.unwrap_or_default(),
+                vector,
+                metadata,
+                ..Default::default()
+            });
+            self.row += 1;
+        }
+
+        if self.row == self.rows && self.next_record()?.is_some() {
+            return Err(self.count_mismatch());
+        }
+        Ok(items)
+    }
+
+    /// The next non-blank record
+    fn next_record(&mut self) -> Result<Option<Record>> {
+        for line in self.records.by_ref() {
+            let line = line?;
+            if !line.trim().is_empty() {
+                return Ok(Some(serde_json::from_str(&line)?));
+            }
+        }
+        Ok(None)
+    }
+
+    fn count_mismatch(&self) -> VectraError {
+        VectraError::MetadataValidation {
+            message: format!(
+                "records file doesn't have one record per matrix row ({} rows)",
+                self.rows
+            ),
+        }
+    }
+}
+
+fn invalid_matrix(message: impl Into<String>) -> VectraError {
+    VectraError::VectorValidation {
+        message: message.into(),
+    }
+}
+
+/// Parse the header of a `.npy` file, whose dictionary reads like
+/// `{'descr': '<f4', 'fortran_order': False, 'shape': (1000, 384), }`
+fn read_npy_header(file: &mut BufReader<File>) -> Result<MatrixLayout> {
+    let mut preamble = [0u8; 8];
+    file.read_exact(&mut preamble)?;
+    let header_len = if preamble[6] == 1 {
+        let mut len = [0u8; 2];
+        file.read_exact(&mut len)?;
+        u16::from_le_bytes(len) as usize
+    } else {
+        let mut len = [0u8; 4];
+        file.read_exact(&mut len)?;
+        u32::from_le_bytes(len) as usize
+    };
+    let prefix_len = if preamble[6] == 1 { 10 } else { 12 };
+    let mut header = vec![0u8; header_len];
+    file.read_exact(&mut header)?;
+    let header = String::from_utf8_lossy(&header);
+
+    let field = |key: &str| {
+        let start = header
+            .find(&format!("'{key}':"))
+            .ok_or_else(|| invalid_matrix(format!("npy header has no '{key}'")))?;
+        Ok::<_, VectraError>(header[start + key.len() + 3..].trim_start())
+    };
+    let descr = field("descr")?
+        .trim_start_matches('\'')
+        .split('\'')
+        .next()
+        .unwrap_or_default();
+    let dtype = match descr {
+        "<f2" => Dtype::F16,
+        "<f4" => Dtype::F32,
+        "<f8" => Dtype::F64,
+        other => return Err(invalid_matrix(format!("unsupported npy dtype {other}"))),
+    };
+    if field("fortran_order")?.starts_with("True") {
+        return Err(invalid_matrix("npy matrix must be in row-major order"));
+    }
+    let shape = field("shape")?;
+    let shape: Vec<usize> = shape[1..shape.find(')').unwrap_or(1)]
+        .split(',')
+        .map(str::trim)
+        .filter(|dim| !dim.is_empty())
+        .map(|dim| dim.parse())
+        .collect::<std::result::Result<_, _>>()
+        .map_err(|_| invalid_matrix("npy header has a malformed shape"))?;
+    let [rows, dimensions] = shape[..] else {
+        return Err(invalid_matrix(format!(
+            "npy matrix must be 2-D, not {}-D",
+            shape.len()
+        )));
+    };
+
+    Ok(MatrixLayout {
+        offset: (prefix_len + header_len) as u64,
+        dtype,
+        rows,
+        dimensions,
+    })
+}
+
+#[derive(Deserialize)]
+struct TensorInfo {
+    dtype: String,
+    shape: Vec<usize>,
+    data_offsets: (u64, u64),
+}
+
+/// Parse the JSON header of a `.safetensors` file and locate `tensor`
+fn read_safetensors_header(
+    file: &mut BufReader<File>,
+    tensor: Option<&str>,
+) -> Result<MatrixLayout> {
+    let mut len = [0u8; 8];
+    file.read_exact(&mut len)?;
+    let header_len = u64::from_le_bytes(len);
+    let mut header = vec![0u8; header_len as usize];
+    file.read_exact(&mut header)?;
+    let mut tensors: serde_json::Map<String, serde_json::Value> = serde_json::from_slice(&header)?;
+    tensors.remove("__metadata__");
+
+    let name = match tensor {
+        Some(name) => name.to_string(),
+        None if tensors.len() == 1 => tensors.keys().next().unwrap().clone(),
+        None => {
+            let names: Vec<&str> = tensors.keys().map(String::as_str).collect();
+            return Err(invalid_matrix(format!(
+                "safetensors file holds several tensors; name one of {}",
+                names.join(", ")
+            )));
+        }
+    };
+    let info: TensorInfo = tensors
+        .remove(&name)
+        .map(serde_json::from_value)
+        .transpose()?
+        .ok_or_else(|| invalid_matrix(format!("no tensor named {name}")))?;
+
+    let dtype = match info.dtype.as_str() {
+        "F16" => Dtype::F16,
+        "BF16" => Dtype::BF16,
+        "F32" => Dtype::F32,
+        "F64" => Dtype::F64,
+        other => return Err(invalid_matrix(format!("unsupported tensor dtype {other}"))),
+    };
+    let [rows, dimensions] = info.shape[..] else {
+        return Err(invalid_matrix(format!(
+            "tensor {name} must be 2-D, not {}-D",
+            info.shape.len()
+        )));
+    };
+    let (begin, end) = info.data_offsets;
+    if end - begin != (rows * dimensions * dtype.size()) as u64 {
+        return Err(invalid_matrix(format!(
+            "tensor {name} data doesn't match its shape"
+        )));
+    }
+
+    Ok(MatrixLayout {
+        offset: 8 + header_len + begin,
+        dtype,
+        rows,
+        dimensions,
+    })
+}
+
+impl LocalIndex {
+    /// Insert every row of `import`, returning the number inserted.
+    /// Batches are inserted as they are read, so a failure part way
+    /// leaves the earlier batches in the index.
+    pub async fn import_embeddings(&self, mut import: EmbeddingImport) -> Result<usize> {
+        let mut imported = 0;
+        loop {
+            let batch = import.next_batch(IMPORT_BATCH_SIZE)?;
+            if batch.is_empty() {
+                return Ok(imported);
+            }
+            imported += self.insert_items(batch).await?.len();
+        }
+    }
+}
+
+impl BulkLoader {
+    /// Stage every row of `import`, returning the number loaded so far
+    pub async fn import_embeddings(&mut self, mut import: EmbeddingImport) -> Result<usize> {
+        loop {
+            let batch = import.next_batch(IMPORT_BATCH_SIZE)?;
+            if batch.is_empty() {
+                return Ok(self.loaded());
+            }
+            self.add(batch).await?;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn write_npy(path: &Path, descr: &str, shape: &str, data: &[u8]) {
+        let mut header =
+            format!("{{'descr': '{descr}', 'fortran_order': False, 'shape': {shape}, }}");
+        // Pad so the data starts on a 64-byte boundary, as numpy does
+        while (10 + header.len() + 1) % 64 != 0 {
+            header.push(' ');
+        }
+        header.push('\n');
+        let mut bytes = NPY_MAGIC.to_vec();
+        bytes.extend([1, 0]);
+        bytes.extend((header.len() as u16).to_le_bytes());
+        bytes.extend(header.as_bytes());
+        bytes.extend(data);
+        std::fs::write(path, bytes).unwrap();
+    }
+
+    fn write_safetensors(path: &Path, header: serde_json::Value, data: &[u8]) {
+        let header = header.to_string();
+        let mut bytes = (header.len() as u64).to_le_bytes().to_vec();
+        bytes.extend(header.as_bytes());
+        bytes.extend(data);
+        std::fs::write(path, bytes).unwrap();
+    }
+
+    fn le_bytes(values: &[f32]) -> Vec<u8> {
+        values.iter().flat_map(|v| v.to_le_bytes()).collect()
+    }
+
+    #[tokio::test]
+    async fn test_import_npy_with_records() {
+        let temp_dir = TempDir::new().unwrap();
+        let matrix = temp_dir.path().join("embeddings.npy");
+        let records = temp_dir.path().join("records.jsonl");
+        write_npy(
+            &matrix,
+            "<f4",
+            "(3, 2)",
+            &le_bytes(&[1.0, 0.0, 0.0, 1.0, 0.5, 0.5]),
+        );
+        let known = uuid::Uuid::new_v4();
+        std::fs::write(
+            &records,
+            format!(
+                "{{\"id\": \"{known}\", \"metadata\": {{\"title\": \"first\"}}}}\n\"{}\"\n\n{{\"metadata\": {{\"title\": \"third\"}}}}\n",
+                uuid::Uuid::new_v4()
+            ),
+        )
+        .unwrap();
+
+        let index = LocalIndex::new(temp_dir.path().join("index"), None).unwrap();
+        index.create_index(None).await.unwrap();
+        let import = EmbeddingImport::open(&matrix, &records, None).unwrap();
+        assert_eq!((import.rows(), import.dimensions()), (3, 2));
+        assert_eq!(index.import_embeddings(import).await.unwrap(), 3);
+
+        let first = index.get_item(&known).await.unwrap().unwrap();
+        assert_eq!(first.vector, vec![1.0, 0.0]);
+        assert_eq!(first.metadata["title"], "first");
+        let results = index
+            .query_items(vec![0.5, 0.5], Some(1), None)
+            .await
+            .unwrap();
+        assert_eq!(results[0].item.metadata["title"], "third");
+
+        // One record too few
+        std::fs::write(&records, format!("\"{}\"\n", uuid::Uuid::new_v4())).unwrap();
+        let mut import = EmbeddingImport::open(&matrix, &records, None).unwrap();
+        assert!(matches!(
+            import.next_batch(10),
+            Err(VectraError::MetadataValidation { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_import_safetensors_into_bulk_loader() {
+        let temp_dir = TempDir::new().unwrap();
+        let matrix = temp_dir.path().join("embeddings.safetensors");
+        let records = temp_dir.path().join("records.jsonl");
+        // A bf16 tensor of two rows following an unrelated f32 tensor
+        let bf16: Vec<u8> = [1.0f32, 2.0, -0.5, 0.25]
+            .iter()
+            .flat_map(|v| ((v.to_bits() >> 16) as u16).to_le_bytes())
+            .collect();
+        let mut data = le_bytes(&[9.0]);
+        data.extend(&bf16);
+        write_safetensors(
+            &matrix,
+            serde_json::json!({
+                "__metadata__": {"model": "test"},
+                "scale": {"dtype": "F32", "shape": [1], "data_offsets": [0, 4]},
+                "embeddings": {"dtype": "BF16", "shape": [2, 2], "data_offsets": [4, 12]}
+            }),
+            &data,
+        );
+        std::fs::write(
+            &records,
+            "{\"metadata\": {\"n\": 0}}\n{\"metadata\": {\"n\": 1}}\n",
+        )
+        .unwrap();
+
+        assert!(EmbeddingImport::open(&matrix, &records, None).is_err());
+        let import = EmbeddingImport::open(&matrix, &records, Some("embeddings")).unwrap();
+        let mut loader = BulkLoader::new(temp_dir.path().join("index"), None)
+            .await
+            .unwrap();
+        assert_eq!(loader.import_embeddings(import).await.unwrap(), 2);
+        let index = loader.finish().await.unwrap();
+
+        let mut items = index.list_items(None).await.unwrap();
+        items.sort_by_key(|item| item.metadata["n"].as_u64());
+        assert_eq!(items[0].vector, vec![1.0, 2.0]);
+        assert_eq!(items[1].vector, vec![-0.5, 0.25]);
+    }
+
+    #[test]
+    fn test_f16_decoding() {
+        assert_eq!(f16_to_f32(0x3c00), 1.0);
+        assert_eq!(f16_to_f32(0xc000), -2.0);
+        assert_eq!(f16_to_f32(0x0001), 2f32.powi(-24));
+        assert!(f16_to_f32(0x7c00).is_infinite());
+    }
+}
@@ -0,0 +1,191 @@
+// Copyright 2024-2026 Andrey Vasilevsky <anvanster@gmail.com>
+// SPDX-License-Identifier: Apache-2.0
+
+//! Query explanations: how a query was planned and what each stage cost,
+//! for tuning and for reporting wrong or slow results.
+
+use crate::LocalIndex;
+use serde::Serialize;
+use std::time::{Duration, Instant};
+use vectrust_core::*;
+
+/// Where a query's candidates were loaded from
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum CandidateSource {
+    /// Every stored item
+    #[default]
+    Scan,
+    /// Items the geo index matched for a `$near` or `$within` clause
+    GeoIndex,
+    /// Items the storage's range index held within a range field's bounds
+    RangeIndex,
+    /// Items a composite index held for the values the filter pins
+    CompositeIndex,
+    /// The HNSW graph's shortlist, taken before the filter ran because
+    /// the planner statistics expected it to pass most items
+    GraphShortlist,
+}
+
+/// When the metadata filter ran relative to scoring
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum FilterMode {
+    /// Before scoring, so only matching items were scored
+    PreFilter,
+    /// On the HNSW shortlist, which was taken from the whole index
+    PostFilter,
+}
+
+/// Settings a query ran with
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueryParameters {
+    pub top_k: usize,
+    pub offset: usize,
+    /// Whether the query continued from a `search_after` cursor
+    pub cursor: bool,
+    pub dimensions: Option<usize>,
+    pub distance_metric: DistanceMetric,
+    pub filter: Option<serde_json::Value>,
+    pub text: Option<String>,
+    pub boosts: usize,
+    pub recency: bool,
+    pub freshness: Freshness,
+    /// Approximate search settings in effect, if enabled
+    pub ann: Option<AnnConfig>,
+}
+
+/// How [`LocalIndex::explain_query`] ran a query
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueryExplanation {
+    pub strategy: SearchStrategy,
+    /// Why the strategy was chosen
+    pub strategy_reason: String,
+    /// `None` when the query has no filter
+    pub filter_mode: Option<FilterMode>,
+    pub candidate_source: CandidateSource,
+    /// Candidates expected before loading any: the geo, composite or range
+    /// index's matches, the graph's shortlist, or every item in the index
+    pub estimated_candidates: usize,
+    /// Items the planner statistics expected the filter to pass, when
+    /// there are statistics on a field it tests
+    pub estimated_matches: Option<usize>,
+    /// Candidates loaded, less items from another embedding model
+    pub candidates: usize,
+    /// Candidates that passed the filter
+    pub matched: usize,
+    /// Results asked of the HNSW graph, when it was walked
+    pub shortlist: Option<usize>,
+    /// Items whose similarity was computed
+    pub scored: usize,
+    pub hydrate_ms: f64,
+    pub filter_ms: f64,
+    pub scan_ms: f64,
+    pub total_ms: f64,
+    pub parameters: QueryParameters,
+    pub results: Vec<QueryResult>,
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+impl LocalIndex {
+    /// Run `query` and report how it was planned and executed.
+    ///
+    /// The query bypasses the query cache and isn't counted in namespace
+    /// stats or the slow-query log, so the timings are of a real run.
+    pub async fn explain_query(&self, query: &Query) -> Result<QueryExplanation> {
+        self.validate_query(query)?;
+        let started = Instant::now();
+        let storage = self.storage.read_for("query").await;
+        let (results, timings) = self
+            .run_query(storage.as_ref(), query, None, started)
+            .await?;
+        let total = started.elapsed();
+        let distance_metric = storage.get_stats().await?.distance_metric;
+
+        Ok(QueryExplanation {
+            strategy: timings.strategy,
+            strategy_reason: timings.strategy_reason.to_string(),
+            filter_mode: query.filter.as_ref().map(|_| match timings.strategy {
+                SearchStrategy::Exact => FilterMode::PreFilter,
+                SearchStrategy::Ann => FilterMode::PostFilter,
+            }),
+            candidate_source: timings.source,
+            estimated_candidates: timings.estimated,
+            estimated_matches: timings.estimated_matches,
+            candidates: timings.candidates,
+            matched: timings.matched,
+            shortlist: timings.shortlist,
+            scored: timings.scored,
+            hydrate_ms: millis(timings.hydrate),
+            filter_ms: millis(timings.filter),
+            scan_ms: millis(timings.scan),
+            total_ms: millis(total),
+            parameters: QueryParameters {
+                top_k: query.top_k,
+                offset: query.offset,
+                cursor: query.search_after.is_some(),
+                dimensions: query.vector.as_ref().map(|v| v.len()),
+                distance_metric,
+                filter: query.filter.clone(),
+                text: query.text.clone(),
+                boosts: query.boosts.len(),
+                recency: query.recency.is_some(),
+                freshness: query.freshness,
+                ann: self.runtime_config.lock().unwrap().ann.clone(),
+            },
+            results,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_explain_query() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let index = LocalIndex::new(dir.path(), None).unwrap();
+        index.create_index(None).await.unwrap();
+        let items = (0..40)
+            .map(|i| VectorItem {
+                vector: vec![i as f32, 1.0, 0.5],
+                metadata: serde_json::json!({ "even": i % 2 == 0 }),
+                ..Default::default()
+            })
+            .collect();
+        index.insert_items(items).await.unwrap();
+
+        let query = Query {
+            vector: Some(vec![3.0, 1.0, 0.5]),
+            top_k: 5,
+            filter: Some(serde_json::json!({ "even": true })),
+            ..Default::default()
+        };
+        let explanation = index.explain_query(&query).await.unwrap();
+        assert_eq!(explanation.strategy, SearchStrategy::Exact);
+        assert_eq!(explanation.strategy_reason, "approximate search is off");
+        assert_eq!(explanation.filter_mode, Some(FilterMode::PreFilter));
+        assert_eq!(explanation.candidate_source, CandidateSource::Scan);
+        assert_eq!(explanation.estimated_candidates, 40);
+        assert_eq!(explanation.candidates, 40);
+        assert_eq!(explanation.matched, 20);
+        assert_eq!(explanation.scored, 20);
+        assert_eq!(explanation.shortlist, None);
+        assert_eq!(explanation.parameters.dimensions, Some(3));
+        assert!(explanation.total_ms >= explanation.scan_ms);
+
+        let results = index.execute_query(&query, None).await.unwrap();
+        let ids = |results: &[QueryResult]| results.iter().map(|r| r.item.id).collect::<Vec<_>>();
+        assert_eq!(ids(&explanation.results), ids(&results));
+
+        let json = serde_json::to_value(&explanation).unwrap();
+        assert_eq!(json["strategy"], "exact");
+        assert_eq!(json["filterMode"], "preFilter");
+    }
+}
@@ -0,0 +1,216 @@
+// Copyright 2024-2026 Andrey Vasilevsky <anvanster@gmail.com>
+// SPDX-License-Identifier: Apache-2.0
+
+//! Lifecycle hooks for applications embedding an index.
+//!
+//! [`IndexHooks`] attached with [`LocalIndex::add_hooks`] hear about the
+//! handle opening and closing, every write through it and every
+//! compaction, so an application can keep something of its own in step,
+//! such as an external cache, without wrapping the storage layer. Write
+//! hooks run on the writing task under the storage write lock, in the
+//! order writes are applied, so they should be quick.
+
+use crate::LocalIndex;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use uuid::Uuid;
+use vectrust_core::*;
+
+/// Kind of write a hook is told about
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WriteKind {
+    Insert,
+    Update,
+    Delete,
+}
+
+/// A write through a [`LocalIndex`]
+#[derive(Debug, Clone, Copy)]
+pub struct WriteEvent<'a> {
+    pub kind: WriteKind,
+    /// Items written or deleted
+    pub ids: &'a [Uuid],
+}
+
+/// Callbacks on an index handle's lifecycle. Every method does nothing by
+/// default, so implementations override only those they need.
+pub trait IndexHooks: Send + Sync {
+    /// The handle for the index at `path` is open. Runs as the hooks are
+    /// attached.
+    fn on_open(&self, _path: &Path) {}
+
+    /// A write is about to be applied. An error rejects it, before
+    /// anything is written, and is returned to the writer.
+    fn before_write(&self, _event: &WriteEvent) -> Result<()> {
+        Ok(())
+    }
+
+    /// A write has been applied; reads through the handle now see it
+    fn after_commit(&self, _event: &WriteEvent) {}
+
+    /// Compaction finished, whether called for or run in the background,
+    /// with the stats from before it
+    fn on_compaction(&self, _stats: &CompactionStats) {}
+
+    /// The handle for the index at `path` is being dropped
+    fn on_close(&self, _path: &Path) {}
+}
+
+/// Hooks attached to one handle, shared with its background tasks
+#[derive(Clone, Default)]
+pub(crate) struct Hooks(Arc<Mutex<Vec<Arc<dyn IndexHooks>>>>);
+
+impl Hooks {
+    /// Copied out so hooks can call back into the index
+    fn attached(&self) -> Vec<Arc<dyn IndexHooks>> {
+        self.0.lock().unwrap().clone()
+    }
+
+    pub(crate) fn before_write(&self, kind: WriteKind, ids: &[Uuid]) -> Result<()> {
+        let event = WriteEvent { kind, ids };
+        self.attached()
+            .iter()
+            .try_for_each(|hooks| hooks.before_write(&event))
+    }
+
+    pub(crate) fn before_write_items(&self, kind: WriteKind, items: &[VectorItem]) -> Result<()> {
+        if self.0.lock().unwrap().is_empty() {
+            return Ok(());
+        }
+        let ids: Vec<Uuid> = items.iter().map(|item| item.id).collect();
+        self.before_write(kind, &ids)
+    }
+
+    pub(crate) fn after_commit(&self, kind: WriteKind, ids: &[Uuid]) {
+        let event = WriteEvent { kind, ids };
+        for hooks in self.attached() {
+            hooks.after_commit(&event);
+        }
+    }
+
+    pub(crate) fn compacted(&self, stats: &CompactionStats) {
+        for hooks in self.attached() {
+            hooks.on_compaction(stats);
+        }
+    }
+
+    pub(crate) fn closed(&self, path: &Path) {
+        for hooks in self.attached() {
+            hooks.on_close(path);
+        }
+    }
+}
+
+impl LocalIndex {
+    /// Attach `hooks` to this handle, calling their
+    /// [`on_open`](IndexHooks::on_open) straight away. Hooks attached
+    /// earlier run first.
+    pub fn add_hooks(&self, hooks: Arc<dyn IndexHooks>) {
+        hooks.on_open(&self.path);
+        self.hooks.0.lock().unwrap().push(hooks);
+    }
+}
+
+#[cfg(all(test, feature = "rocksdb"))]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    /// Records every call, rejecting writes to `reject`
+    #[derive(Default)]
+    struct Recorder {
+        calls: Mutex<Vec<String>>,
+        reject: Mutex<Option<Uuid>>,
+    }
+
+    impl Recorder {
+        fn log(&self, call: String) {
+            self.calls.lock().unwrap().push(call);
+        }
+    }
+
+    impl IndexHooks for Recorder {
+        fn on_open(&self, _path: &Path) {
+            self.log("open".to_string());
+        }
+
+        fn before_write(&self, event: &WriteEvent) -> Result<()> {
+            if event
+                .ids
+                .contains(&self.reject.lock().unwrap().unwrap_or_default())
+            {
+                return Err(VectraError::Storage {
+                    message: "rejected".to_string(),
+                });
+            }
+            self.log(format!("before {:?} {}", event.kind, event.ids.len()));
+            Ok(())
+        }
+
+        fn after_commit(&self, event: &WriteEvent) {
+            self.log(format!("after {:?} {}", event.kind, event.ids.len()));
+        }
+
+        fn on_compaction(&self, stats: &CompactionStats) {
+            self.log(format!("compacted {}", stats.deleted_items));
+        }
+
+        fn on_close(&self, _path: &Path) {
+            self.log("close".to_string());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_hooks_see_lifecycle_and_writes() {
+        let temp_dir = TempDir::new().unwrap();
+        let index = LocalIndex::new(temp_dir.path(), None).unwrap();
+        index.create_index(None).await.unwrap();
+        let recorder = Arc::new(Recorder::default());
+        index.add_hooks(recorder.clone());
+
+        let item = |x: f32| VectorItem {
+            vector: vec![x, 1.0],
+            ..Default::default()
+        };
+        let first = index.insert_item(item(0.0)).await.unwrap();
+        index
+            .insert_items(vec![item(1.0), item(2.0)])
+            .await
+            .unwrap();
+        index
+            .update_item(UpdateRequest {
+                id: first.id,
+                vector: None,
+                metadata: Some(serde_json::json!({ "n": 1 })),
+            })
+            .await
+            .unwrap();
+
+        // A rejected write changes nothing and isn't reported as committed
+        *recorder.reject.lock().unwrap() = Some(first.id);
+        assert!(index.delete_item(&first.id).await.is_err());
+        assert!(index.get_item(&first.id).await.unwrap().is_some());
+        *recorder.reject.lock().unwrap() = None;
+
+        index.delete_item(&first.id).await.unwrap();
+        index.compact(None).await.unwrap();
+        drop(index);
+
+        assert_eq!(
+            *recorder.calls.lock().unwrap(),
+            [
+                "open",
+                "before Insert 1",
+                "after Insert 1",
+                "before Insert 2",
+                "after Insert 2",
+                "before Update 1",
+                "after Update 1",
+                "before Delete 1",
+                "after Delete 1",
+                "compacted 1",
+                "close",
+            ]
+        );
+    }
+}
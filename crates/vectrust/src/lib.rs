@@ -3,20 +3,142 @@
 
 pub use vectrust_core::*;
 
+mod aliases;
+mod ann;
+#[cfg(feature = "arrow")]
+pub mod arrow;
+mod attachments;
+mod audit;
+pub mod blocking;
+mod bulk;
+mod composite_indexes;
+#[cfg(feature = "datafusion")]
+pub mod datafusion;
+mod dimensions;
+mod disk_space;
+mod embedding_cache;
+mod embedding_import;
+mod explain;
+#[cfg(feature = "graph")]
 mod graph_index;
+mod hooks;
+mod maintenance;
+mod namespaces;
+mod neighbors;
+mod outliers;
+mod payloads;
+mod planner_stats;
+mod quarantine;
+mod query_cache;
+mod range_filters;
+mod reindex;
+mod resident;
+mod scoring_pool;
+mod secondary_indexes;
+mod single_writer;
+mod slow_query;
+#[cfg(feature = "graph")]
 pub use graph_index::{EdgeJson, GraphIndex, GraphJson, NodeJson};
 
+pub use aliases::IndexAliases;
+pub use attachments::{BlobStore, FileBlobStore};
+pub use bulk::BulkLoader;
+pub use embedding_cache::{CachedEmbedder, EmbeddingCacheStats};
+pub use embedding_import::EmbeddingImport;
+pub use explain::{CandidateSource, FilterMode, QueryExplanation, QueryParameters};
+pub use hooks::{IndexHooks, WriteEvent, WriteKind};
+pub use maintenance::{CronSchedule, MaintenanceJob, Schedule, ScheduledJob};
+pub use neighbors::Neighbor;
+pub use outliers::{Outlier, OutlierMethod};
+pub use planner_stats::{FieldStats, PlannerStats};
+pub use quarantine::RepairReport;
+pub use reindex::{EmbeddingFn, Reindexed};
+
+use audit::AuditOperation;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::collections::HashSet;
 use std::path::Path;
-use std::sync::Arc;
-use tokio::sync::RwLock;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::task::JoinHandle;
+use tracing::Instrument;
+use vectrust_index::{GeoBounds, GeohashIndex};
+pub use vectrust_query::{
+    FieldFilter, Filter, FilterCheck, HybridSearch, MetadataFilter, QueryDsl, ScoringFn, TextIndex,
+    TextIndexConfig, TextQuery, Tokenizer, VectorSearch,
+};
+
+/// A parsed keyword query and its hits by descending BM25 score
+type TextHits = (TextQuery, Vec<(uuid::Uuid, f32)>);
+
+/// Rank `candidates` for `query`, fused with its keyword `hits` if it has
+/// text
+fn rank_candidates(
+    search: &VectorSearch,
+    query: &Query,
+    candidates: Vec<VectorItem>,
+    hits: Option<&[(uuid::Uuid, f32)]>,
+) -> Result<Vec<QueryResult>> {
+    let Some(hits) = hits else {
+        return search.search(query, candidates);
+    };
+    // Rank every candidate by vector score, then fuse with the keyword ranking
+    let vector_query = Query {
+        top_k: candidates.len(),
+        offset: 0,
+        search_after: None,
+        ..query.clone()
+    };
+    let vector_results = search.search(&vector_query, candidates)?;
+    let fused = HybridSearch::fuse(vector_results, hits, query.window());
+    Ok(query.paginate(fused))
+}
+
+/// Directory of the persisted keyword index inside an index folder
+#[cfg(feature = "rocksdb")]
+const TEXT_INDEX_DIR: &str = "text_index";
+
+/// File holding saved queries inside an index folder
+const NAMED_QUERIES_FILE: &str = "named_queries.json";
+
+/// File holding user-defined index properties inside an index folder
+const INDEX_METADATA_FILE: &str = "index_metadata.json";
+
+/// Live data compaction copies per turn of the shared storage lock
+const COMPACTION_CHUNK_SIZE: u64 = 1024 * 1024;
 
 /// High-level LocalIndex that integrates all components
 pub struct LocalIndex {
-    storage: Arc<RwLock<Box<dyn StorageBackend>>>,
+    storage: Arc<TimedRwLock<Box<dyn StorageBackend>>>,
+    /// Time operations spent waiting for `storage`
+    lock_waits: Arc<LockWaits>,
     #[allow(dead_code)]
     path: std::path::PathBuf,
     #[allow(dead_code)]
     index_name: String,
+    compaction_task: Mutex<Option<JoinHandle<()>>>,
+    maintenance_task: Mutex<Option<JoinHandle<()>>>,
+    geo_index: Mutex<Option<GeohashIndex>>,
+    text_index: Mutex<Option<TextIndex>>,
+    named_queries: Mutex<BTreeMap<String, NamedQuery>>,
+    index_metadata: Mutex<BTreeMap<String, serde_json::Value>>,
+    runtime_config: Mutex<RuntimeConfig>,
+    query_cache: Mutex<Option<query_cache::QueryCache>>,
+    neighbors: Mutex<Option<neighbors::NeighborGraph>>,
+    namespaces: Mutex<namespaces::NamespaceTracker>,
+    planner_stats: Mutex<planner_stats::StatsTracker>,
+    dual_write: Mutex<Option<Arc<reindex::DualWrite>>>,
+    hooks: hooks::Hooks,
+    /// Functions for a `DistanceMetric::Custom` metric, registered on this handle
+    distances: DistanceRegistry,
+    attachments: Mutex<Option<attachments::Attachments>>,
+    /// Thread owning the storage, for an index opened with
+    /// [`LocalIndex::open_single_writer`]
+    writer: Option<vectrust_storage::SingleWriter>,
+    #[cfg(feature = "ann")]
+    ann_index: Arc<Mutex<Option<ann::AnnIndex>>>,
 }
 
 impl LocalIndex {
@@ -27,29 +149,169 @@ impl LocalIndex {
 
         // Auto-detect storage format and create appropriate backend
         let storage = vectrust_storage::Storage::auto_detect(&path, &index_name)?;
+        Self::with_storage(path, index_name, storage)
+    }
+
+    /// Open or create a legacy JSON index that the Node.js library also
+    /// writes to. Items are written with Node's field names and unknown
+    /// fields survive the round-trip (see [`LegacyStorage::with_strict_compat`]).
+    ///
+    /// [`LegacyStorage::with_strict_compat`]: vectrust_storage::LegacyStorage::with_strict_compat
+    pub fn new_node_compatible<P: AsRef<Path>>(
+        folder_path: P,
+        index_name: Option<String>,
+    ) -> Result<Self> {
+        let path = folder_path.as_ref().to_path_buf();
+        let index_name = index_name.unwrap_or_else(|| "index.json".to_string());
+        let storage =
+            vectrust_storage::LegacyStorage::new(&path, &index_name)?.with_strict_compat();
+        Self::with_storage(path, index_name, Box::new(storage))
+    }
+
+    /// Open or create an index with the third-party backend registered as
+    /// `backend` (see [`Storage::register_backend`]), skipping detection.
+    /// Once created, [`LocalIndex::new`] finds it through the backend's
+    /// detection predicate.
+    ///
+    /// [`Storage::register_backend`]: vectrust_storage::Storage::register_backend
+    pub fn new_with_backend<P: AsRef<Path>>(
+        folder_path: P,
+        index_name: Option<String>,
+        backend: &str,
+    ) -> Result<Self> {
+        let path = folder_path.as_ref().to_path_buf();
+        let index_name = index_name.unwrap_or_else(|| "index.json".to_string());
+        let storage = vectrust_storage::Storage::open_registered(backend, &path, &index_name)?;
+        Self::with_storage(path, index_name, storage)
+    }
+
+    fn with_storage(
+        path: std::path::PathBuf,
+        index_name: String,
+        storage: Box<dyn StorageBackend>,
+    ) -> Result<Self> {
+        // Reattach a keyword index persisted by an earlier enable_text_index
+        #[cfg(feature = "rocksdb")]
+        let text_index = {
+            let dir = path.join(TEXT_INDEX_DIR);
+            if dir.exists() {
+                Some(TextIndex::open(&dir)?)
+            } else {
+                None
+            }
+        };
+        #[cfg(not(feature = "rocksdb"))]
+        let text_index = None;
+
+        let named_queries = read_map_file(&path.join(NAMED_QUERIES_FILE))?;
+        let index_metadata = read_map_file(&path.join(INDEX_METADATA_FILE))?;
+        let neighbors = neighbors::NeighborGraph::load(&path)?;
+        let lock_waits = Arc::new(LockWaits::default());
 
         Ok(Self {
-            storage: Arc::new(RwLock::new(storage)),
+            storage: Arc::new(TimedRwLock::new("storage", storage, &lock_waits)),
+            lock_waits,
             path,
             index_name,
+            compaction_task: Mutex::new(None),
+            maintenance_task: Mutex::new(None),
+            geo_index: Mutex::new(None),
+            text_index: Mutex::new(text_index),
+            named_queries: Mutex::new(named_queries),
+            index_metadata: Mutex::new(index_metadata),
+            runtime_config: Mutex::new(RuntimeConfig::default()),
+            query_cache: Mutex::new(None),
+            neighbors: Mutex::new(neighbors),
+            namespaces: Mutex::new(namespaces::NamespaceTracker::default()),
+            planner_stats: Mutex::new(planner_stats::StatsTracker::default()),
+            dual_write: Mutex::new(None),
+            hooks: hooks::Hooks::default(),
+            distances: DistanceRegistry::default(),
+            attachments: Mutex::new(None),
+            writer: None,
+            #[cfg(feature = "ann")]
+            ann_index: Arc::new(Mutex::new(None)),
         })
     }
 
-    /// Create an index with configuration
+    /// Register `distance` under `name` for this handle, so an index
+    /// created with `DistanceMetric::Custom(name)` can be created and
+    /// queried through it. The manifest keeps only the name, so register
+    /// the function on every handle that opens the index. Fails if this
+    /// handle already has a function under `name`.
+    pub fn register_distance(&self, name: &str, distance: Arc<dyn DistanceFn>) -> Result<()> {
+        self.distances.register(name, distance)
+    }
+
+    /// Create an index with configuration.
+    ///
+    /// When `config.compaction` is set, the policy is persisted with the index
+    /// and background auto-compaction starts immediately.
+    #[tracing::instrument(name = "vectrust.create_index", skip_all, fields(index = %self.path.display()))]
     pub async fn create_index(&self, config: Option<CreateIndexConfig>) -> Result<()> {
         let config = config.unwrap_or_default();
-        let mut storage = self.storage.write().await;
-        storage.create_index(&config).await
+        let namespace_limits = config
+            .limits
+            .as_ref()
+            .is_some_and(|limits| limits.has_namespace_limits());
+        if namespace_limits && config.namespace_field.is_none() {
+            return Err(VectraError::Storage {
+                message: "Namespace quotas need a namespace_field".to_string(),
+            });
+        }
+        self.distances.resolve(&config.distance_metric)?;
+        if let Some(transform) = &config.vector_transform {
+            transform.validate()?;
+        }
+        if let Some(validation) = &config.vector_validation {
+            validation.validate()?;
+        }
+        for index in &config.metadata_config.composite_indexes {
+            index.validate()?;
+        }
+        {
+            let mut storage = self.storage.write_for("create").await;
+            let outcome = storage.create_index(&config).await;
+            self.audit(AuditOperation::CreateIndex, &[], &outcome);
+            outcome?;
+            self.clear_query_cache();
+            self.reset_ann_index();
+            self.namespaces.lock().unwrap().clear();
+            self.planner_stats.lock().unwrap().clear();
+        }
+
+        let text_fields = &config.metadata_config.text_fields;
+        if !text_fields.is_empty() {
+            self.enable_text_index(TextIndexConfig::from_fields(text_fields))
+                .await?;
+        }
+
+        if let Some(policy) = config.compaction {
+            self.start_auto_compaction(Some(policy)).await?;
+        }
+        Ok(())
     }
 
     /// Check if index exists
     pub async fn is_index_created(&self) -> bool {
-        let storage = self.storage.read().await;
+        let storage = self.storage.read_for("config").await;
         storage.exists().await
     }
 
-    /// Insert a new item
-    pub async fn insert_item(&self, mut item: VectorItem) -> Result<VectorItem> {
+    /// Insert a new item, failing with [`VectraError::ItemAlreadyExists`]
+    /// if its id is already stored
+    pub async fn insert_item(&self, item: VectorItem) -> Result<VectorItem> {
+        self.insert_item_on_conflict(item, OnConflict::Error).await
+    }
+
+    /// Insert an item, doing as `on_conflict` says if its id is already
+    /// stored
+    #[tracing::instrument(name = "vectrust.insert", skip_all, fields(index = %self.path.display()))]
+    pub async fn insert_item_on_conflict(
+        &self,
+        mut item: VectorItem,
+        on_conflict: OnConflict,
+    ) -> Result<VectorItem> {
         // Ensure ID is set
         if item.id == uuid::Uuid::default() {
             item.id = uuid::Uuid::new_v4();
@@ -67,14 +329,87 @@ impl LocalIndex {
         item.created_at = now;
         item.updated_at = now;
 
-        let mut storage = self.storage.write().await;
-        storage.insert_item(&item).await?;
+        let mut storage = self.write_storage("insert").await;
+        prepare_vectors(storage.as_ref(), std::slice::from_mut(&mut item)).await?;
+        let outcome = async {
+            self.hooks.before_write(WriteKind::Insert, &[item.id])?;
+            let replaced = find_replaced(
+                storage.as_ref(),
+                std::slice::from_mut(&mut item),
+                on_conflict,
+            )
+            .await?[0];
+            check_embedding_model(storage.as_ref(), std::slice::from_ref(&item)).await?;
+            let added = usize::from(!replaced);
+            check_limits(
+                storage.as_ref(),
+                &self.path,
+                std::slice::from_ref(&item),
+                added,
+            )
+            .await?;
+            self.ensure_space_for_items(std::slice::from_ref(&item))?;
+            self.check_namespace_limits(storage.as_ref(), std::slice::from_ref(&item))
+                .await?;
+            if replaced {
+                storage.update_item(&item).await?;
+            } else {
+                storage.insert_item(&item).await?;
+            }
+            self.index_secondary(&[item.clone()])
+        }
+        .await;
+        self.audit(AuditOperation::Insert, &[item.id], &outcome);
+        outcome?;
+        self.forward_items(&[item.clone()]).await;
 
         Ok(item)
     }
 
-    /// Insert multiple items efficiently using bulk operations
-    pub async fn insert_items(&self, mut items: Vec<VectorItem>) -> Result<Vec<VectorItem>> {
+    /// Insert multiple items efficiently using bulk operations.
+    ///
+    /// Items are written in chunks of the runtime config's
+    /// `insert_chunk_size`, releasing the write lock between chunks so
+    /// concurrent queries aren't starved by a large batch. Chunks written
+    /// before a failing one stay in the index. A chunk holding an id that
+    /// is already stored, or repeated, fails with
+    /// [`VectraError::ItemAlreadyExists`].
+    pub async fn insert_items(&self, items: Vec<VectorItem>) -> Result<Vec<VectorItem>> {
+        self.insert_items_with_progress(items, |_| {}).await
+    }
+
+    /// [`insert_items`](Self::insert_items), doing as `on_conflict` says
+    /// with ids already stored. With [`OnConflict::Replace`] an id repeated
+    /// in `items` is written once, from its last occurrence.
+    pub async fn insert_items_on_conflict(
+        &self,
+        items: Vec<VectorItem>,
+        on_conflict: OnConflict,
+    ) -> Result<Vec<VectorItem>> {
+        self.write_new_items(items, on_conflict, |_| {}).await
+    }
+
+    /// [`insert_items`](Self::insert_items), calling `progress` after each chunk
+    pub async fn insert_items_with_progress(
+        &self,
+        items: Vec<VectorItem>,
+        progress: impl FnMut(InsertProgress) + Send,
+    ) -> Result<Vec<VectorItem>> {
+        self.write_new_items(items, OnConflict::Error, progress)
+            .await
+    }
+
+    #[tracing::instrument(
+        name = "vectrust.insert_items",
+        skip_all,
+        fields(index = %self.path.display(), items = items.len())
+    )]
+    async fn write_new_items(
+        &self,
+        mut items: Vec<VectorItem>,
+        on_conflict: OnConflict,
+        mut progress: impl FnMut(InsertProgress) + Send,
+    ) -> Result<Vec<VectorItem>> {
         if items.is_empty() {
             return Ok(items);
         }
@@ -101,66 +436,482 @@ impl LocalIndex {
             }
             item.updated_at = now;
         }
+        if on_conflict == OnConflict::Replace {
+            // Keep only the last item written under each id
+            let last: std::collections::HashMap<uuid::Uuid, usize> = items
+                .iter()
+                .enumerate()
+                .map(|(position, item)| (item.id, position))
+                .collect();
+            let mut position = 0;
+            items.retain(|item| {
+                let keep = last[&item.id] == position;
+                position += 1;
+                keep
+            });
+        }
+
+        prepare_vectors(self.storage.read_for("insert").await.as_ref(), &mut items).await?;
+
+        // Fail before the first chunk rather than part way through
+        self.ensure_disk_space(disk_space::estimate_items(&items))?;
 
-        let mut storage = self.storage.write().await;
-        storage.insert_items(&items).await?;
+        let chunk_size = self.runtime_config.lock().unwrap().insert_chunk_size.max(1);
+        let total = items.len();
+        let mut inserted = 0;
+        for chunk in items.chunks_mut(chunk_size) {
+            {
+                let mut storage = self.write_storage("insert").await;
+                let size = chunk.len();
+                let outcome = async {
+                    self.hooks.before_write_items(WriteKind::Insert, chunk)?;
+                    let replaced = find_replaced(storage.as_ref(), chunk, on_conflict).await?;
+                    let chunk = &*chunk;
+                    check_embedding_model(storage.as_ref(), chunk).await?;
+                    let added = replaced.iter().filter(|&&replaced| !replaced).count();
+                    check_limits(storage.as_ref(), &self.path, chunk, added).await?;
+                    self.ensure_space_for_items(chunk)?;
+                    self.check_namespace_limits(storage.as_ref(), chunk).await?;
+                    if replaced.contains(&true) {
+                        let mut old = Vec::new();
+                        let mut new = Vec::new();
+                        for (item, &replaced) in chunk.iter().zip(&replaced) {
+                            if replaced {
+                                old.push(item.clone());
+                            } else {
+                                new.push(item.clone());
+                            }
+                        }
+                        storage.update_items(&old).await?;
+                        storage.insert_items(&new).await?;
+                    } else {
+                        storage.insert_items(chunk).await?;
+                    }
+                    self.index_secondary(chunk)
+                }
+                .instrument(tracing::info_span!("vectrust.storage.write", items = size))
+                .await;
+                self.audit_items(AuditOperation::Insert, chunk, &outcome);
+                outcome?;
+                self.forward_items(chunk).await;
+            }
+            inserted += chunk.len();
+            progress(InsertProgress { inserted, total });
+            // The lock is fair, so queued queries run before the next chunk
+            tokio::task::yield_now().await;
+        }
 
         Ok(items)
     }
 
     /// Get an item by ID
     pub async fn get_item(&self, id: &uuid::Uuid) -> Result<Option<VectorItem>> {
-        let storage = self.storage.read().await;
-        storage.get_item(id).await
+        let storage = self.storage.read_for("get").await;
+        let item = storage.get_item(id).await?;
+        if item.is_some() {
+            storage.record_access(std::slice::from_ref(id));
+        }
+        Ok(item)
+    }
+
+    /// Get several items by ID in one storage read, `None` for each id
+    /// that isn't stored
+    pub async fn get_items(&self, ids: &[uuid::Uuid]) -> Result<Vec<Option<VectorItem>>> {
+        let storage = self.storage.read_for("get").await;
+        let items = storage.get_items(ids).await?;
+        let found: Vec<uuid::Uuid> = items.iter().flatten().map(|item| item.id).collect();
+        storage.record_access(&found);
+        Ok(items)
     }
 
     /// Update an existing item
     pub async fn update_item(&self, update: UpdateRequest) -> Result<UpdateResult> {
-        let mut storage = self.storage.write().await;
+        let mut results = self.update_items(vec![update]).await?;
+        Ok(results.remove(0))
+    }
 
-        // Get existing item
-        let mut item = storage
-            .get_item(&update.id)
-            .await?
-            .ok_or(VectraError::ItemNotFound)?;
+    /// Apply several updates under one write lock.
+    ///
+    /// Updates to the same id are applied in order and count as one new
+    /// version, so there is a result per distinct id, in the order the ids
+    /// first appear. Nothing is written if any item is missing or any
+    /// update is invalid.
+    ///
+    /// Items given new vectors are written in one storage batch and items
+    /// keeping theirs in a second, metadata-only one. If the second batch
+    /// fails the first stays written, so a failed call may leave the
+    /// updates with new vectors applied.
+    #[tracing::instrument(name = "vectrust.update", skip_all, fields(index = %self.path.display()))]
+    pub async fn update_items(&self, updates: Vec<UpdateRequest>) -> Result<Vec<UpdateResult>> {
+        let mut storage = self.write_storage("update").await;
+        let ids: Vec<uuid::Uuid> = updates.iter().map(|update| update.id).collect();
+        let outcome = match self.hooks.before_write(WriteKind::Update, &ids) {
+            Ok(()) => self.apply_updates(storage.as_mut(), updates).await,
+            Err(e) => Err(e),
+        };
+        self.audit(AuditOperation::Update, &ids, &outcome);
+        let items = outcome?;
+        self.forward_items(&items).await;
 
-        // Apply updates
-        if let Some(vector) = update.vector {
-            if !VectorOps::is_valid_vector(&vector) {
-                return Err(VectraError::VectorValidation {
-                    message: "Vector contains NaN or infinite values".to_string(),
-                });
+        Ok(items
+            .iter()
+            .map(|item| UpdateResult {
+                id: item.id,
+                version: item.version,
+            })
+            .collect())
+    }
+
+    async fn apply_updates(
+        &self,
+        storage: &mut dyn StorageBackend,
+        updates: Vec<UpdateRequest>,
+    ) -> Result<Vec<VectorItem>> {
+        let mut items: Vec<VectorItem> = Vec::new();
+        // Per item, whether any update gave it a new vector
+        let mut new_vector: Vec<bool> = Vec::new();
+        let mut positions: std::collections::HashMap<uuid::Uuid, usize> =
+            std::collections::HashMap::new();
+        let transform = storage.vector_transform().await?;
+        let validation = storage.vector_validation().await?;
+        for update in updates {
+            let position = match positions.get(&update.id) {
+                Some(&position) => position,
+                None => {
+                    let item = storage
+                        .get_item(&update.id)
+                        .await?
+                        .ok_or(VectraError::ItemNotFound)?;
+                    items.push(item);
+                    new_vector.push(false);
+                    positions.insert(update.id, items.len() - 1);
+                    items.len() - 1
+                }
+            };
+            let item = &mut items[position];
+
+            if let Some(vector) = update.vector {
+                if !VectorOps::is_valid_vector(&vector) {
+                    return Err(VectraError::VectorValidation {
+                        message: "Vector contains NaN or infinite values".to_string(),
+                    });
+                }
+                let mut vector = match &transform {
+                    Some(transform) => transform.apply(&vector)?,
+                    None => vector,
+                };
+                if let Some(validation) = &validation {
+                    validation.apply(&mut vector)?;
+                }
+                new_vector[position] |= item.vector != vector;
+                item.vector = vector;
             }
-            item.vector = vector;
-        }
 
-        if let Some(metadata) = update.metadata {
-            merge_json(&mut item.metadata, metadata);
+            if let Some(metadata) = update.metadata {
+                merge_json(&mut item.metadata, metadata);
+            }
         }
 
         // Update version and timestamp
-        item.version += 1;
-        item.updated_at = chrono::Utc::now();
+        let now = chrono::Utc::now();
+        for item in &mut items {
+            item.version += 1;
+            item.updated_at = now;
+        }
 
         // Save
-        storage.update_item(&item).await?;
-
-        Ok(UpdateResult {
-            id: item.id,
-            version: item.version,
-        })
+        check_embedding_model(storage, &items).await?;
+        check_limits(storage, &self.path, &items, 0).await?;
+        self.ensure_space_for_items(&items)?;
+        self.check_namespace_limits(storage, &items).await?;
+        // Items keeping their vectors only need their metadata rewritten
+        let mut vectors = Vec::new();
+        let mut metadata = Vec::new();
+        for (item, &changed) in items.iter().zip(&new_vector) {
+            if changed {
+                vectors.push(item.clone());
+            } else {
+                metadata.push(item.clone());
+            }
+        }
+        storage.update_items(&vectors).await?;
+        storage.update_metadata(&metadata).await?;
+        self.index_secondary(&items)?;
+        Ok(items)
     }
 
     /// Delete an item
+    #[tracing::instrument(name = "vectrust.delete", skip_all, fields(index = %self.path.display()))]
     pub async fn delete_item(&self, id: &uuid::Uuid) -> Result<()> {
-        let mut storage = self.storage.write().await;
-        storage.delete_item(id).await
+        let mut storage = self.write_storage("delete").await;
+        let outcome = async {
+            self.hooks.before_write(WriteKind::Delete, &[*id])?;
+            let attachments = self.attachments_of(storage.as_ref(), &[*id]).await?;
+            storage.delete_item(id).await?;
+            self.unindex_secondary(id)?;
+            Ok(attachments)
+        }
+        .await;
+        self.audit(AuditOperation::Delete, &[*id], &outcome);
+        self.release_attachments(&outcome?);
+        if let Some(dual) = self.dual_writer() {
+            dual.forward_delete(id).await;
+        }
+        Ok(())
+    }
+
+    /// Delete every item matching `filter`, a JSON filter or a [`Filter`],
+    /// returning how many were deleted
+    #[tracing::instrument(name = "vectrust.delete", skip_all, fields(index = %self.path.display()))]
+    pub async fn delete_by_filter(&self, filter: impl Into<serde_json::Value>) -> Result<usize> {
+        let filter = filter.into();
+        let storage = self.write_storage("delete").await;
+        let check = Arc::new(self.filter_check(filter));
+        let matching = check.clone();
+        let matches = move |metadata: &serde_json::Value| matching.matches_value(metadata);
+        let ids: Vec<uuid::Uuid> = storage
+            .list_matching(None, &matches)
+            .await?
+            .into_iter()
+            .map(|item| item.id)
+            .collect();
+        check.finish()?;
+        self.delete_ids(storage, ids).await
+    }
+
+    /// Delete `ids`, all of them stored, releasing `storage` before
+    /// forwarding the deletes to any dual-write target
+    pub(crate) async fn delete_ids(
+        &self,
+        mut storage: single_writer::StorageWrite<'_>,
+        ids: Vec<uuid::Uuid>,
+    ) -> Result<usize> {
+        if ids.is_empty() {
+            return Ok(0);
+        }
+        let outcome = async {
+            self.hooks.before_write(WriteKind::Delete, &ids)?;
+            let attachments = self.attachments_of(storage.as_ref(), &ids).await?;
+            for id in &ids {
+                storage.delete_item(id).await?;
+                self.unindex_secondary(id)?;
+            }
+            Ok(attachments)
+        }
+        .await;
+        self.audit(AuditOperation::Delete, &ids, &outcome);
+        self.release_attachments(&outcome?);
+        drop(storage);
+        if let Some(dual) = self.dual_writer() {
+            for id in &ids {
+                dual.forward_delete(id).await;
+            }
+        }
+        Ok(ids.len())
+    }
+
+    /// Write items exactly as given, replacing any stored under the same ids.
+    /// Unlike the public writers this keeps versions and timestamps and
+    /// never forwards to a dual-write target.
+    pub(crate) async fn put_items(&self, items: Vec<VectorItem>) -> Result<()> {
+        if items
+            .iter()
+            .any(|item| !VectorOps::is_valid_vector(&item.vector))
+        {
+            return Err(VectraError::VectorValidation {
+                message: "Vector contains NaN or infinite values".to_string(),
+            });
+        }
+
+        let mut storage = self.write_storage("upsert").await;
+        let outcome = self.apply_put(storage.as_mut(), &items).await;
+        self.audit_items(AuditOperation::Insert, &items, &outcome);
+        outcome
+    }
+
+    async fn apply_put(
+        &self,
+        storage: &mut dyn StorageBackend,
+        items: &[VectorItem],
+    ) -> Result<()> {
+        self.hooks.before_write_items(WriteKind::Insert, items)?;
+        check_embedding_model(storage, items).await?;
+        let mut existing = Vec::new();
+        let mut fresh = Vec::new();
+        for item in items {
+            if storage.get_item(&item.id).await?.is_some() {
+                existing.push(item);
+            } else {
+                fresh.push(item.clone());
+            }
+        }
+        check_limits(storage, &self.path, items, fresh.len()).await?;
+        self.ensure_space_for_items(items)?;
+        self.check_namespace_limits(storage, items).await?;
+        for item in existing {
+            storage.update_item(item).await?;
+        }
+        if !fresh.is_empty() {
+            storage.insert_items(&fresh).await?;
+        }
+        self.index_secondary(items)
+    }
+
+    /// Delete without forwarding to a dual-write target
+    pub(crate) async fn remove_item(&self, id: &uuid::Uuid) -> Result<()> {
+        let mut storage = self.write_storage("delete").await;
+        let outcome = async {
+            self.hooks.before_write(WriteKind::Delete, &[*id])?;
+            storage.delete_item(id).await?;
+            self.unindex_secondary(id)
+        }
+        .await;
+        self.audit(AuditOperation::Delete, &[*id], &outcome);
+        outcome
+    }
+
+    /// Record a mutation of `ids` in the audit log, if one is configured,
+    /// and tell hooks about it once it has succeeded
+    fn audit<T>(&self, operation: AuditOperation, ids: &[uuid::Uuid], outcome: &Result<T>) {
+        let log = self.runtime_config.lock().unwrap().audit_log.clone();
+        if let Some(log) = log {
+            audit::record(&log, &self.path, operation, ids, outcome);
+        }
+        let kind = match operation {
+            AuditOperation::Insert => WriteKind::Insert,
+            AuditOperation::Update => WriteKind::Update,
+            AuditOperation::Delete => WriteKind::Delete,
+            AuditOperation::CreateIndex | AuditOperation::DeleteIndex => return,
+        };
+        if outcome.is_ok() {
+            self.hooks.after_commit(kind, ids);
+        }
+    }
+
+    fn audit_items<T>(&self, operation: AuditOperation, items: &[VectorItem], outcome: &Result<T>) {
+        let ids: Vec<uuid::Uuid> = items.iter().map(|item| item.id).collect();
+        self.audit(operation, &ids, outcome);
+    }
+
+    fn dual_writer(&self) -> Option<Arc<reindex::DualWrite>> {
+        self.dual_write.lock().unwrap().clone()
+    }
+
+    /// Called with the storage lock still held, so the target sees writes
+    /// to the same item in the order they were applied here
+    async fn forward_items(&self, items: &[VectorItem]) {
+        if let Some(dual) = self.dual_writer() {
+            dual.forward_items(items).await;
+        }
+    }
+
+    /// Embedding model the index was created with, if any
+    pub async fn embedding_model(&self) -> Result<Option<EmbeddingModel>> {
+        let storage = self.storage.read_for("config").await;
+        storage.embedding_model().await
+    }
+
+    /// Size and quota limits the index was created with, if any
+    pub async fn limits(&self) -> Result<Option<IndexLimits>> {
+        let storage = self.storage.read_for("config").await;
+        storage.limits().await
+    }
+
+    /// Transform the index applies to vectors written and queried, if any
+    pub async fn vector_transform(&self) -> Result<Option<VectorTransform>> {
+        let storage = self.storage.read_for("config").await;
+        storage.vector_transform().await
     }
 
-    /// List all items
+    /// Validation policy the index holds written vectors to, if any
+    pub async fn vector_validation(&self) -> Result<Option<VectorValidation>> {
+        let storage = self.storage.read_for("config").await;
+        storage.vector_validation().await
+    }
+
+    /// Whether a newer vectrust wrote features this build can only read,
+    /// or free disk space is under the runtime config's
+    /// `min_free_disk_bytes`. Writes to a read-only index fail; queries
+    /// work as usual.
+    pub async fn is_read_only(&self) -> Result<bool> {
+        if self.is_low_on_disk_space()? {
+            return Ok(true);
+        }
+        let storage = self.storage.read_for("config").await;
+        storage.is_read_only().await
+    }
+
+    /// List items in id order, paged by `options`.
+    ///
+    /// `options.filter` takes the same filters as queries, with `offset`
+    /// and `limit` counting matching items. A filter pinning the leading
+    /// fields of a composite index reads only their items, one pinning the
+    /// namespace field reads only that namespace, one requiring a prefix of
+    /// it reads only the namespaces with that prefix, one bounding a range
+    /// field or constraining the geo index's field reads only its
+    /// candidates, and otherwise storage tests each item's metadata before
+    /// reading its vector.
     pub async fn list_items(&self, options: Option<ListOptions>) -> Result<Vec<VectorItem>> {
-        let storage = self.storage.read().await;
-        storage.list_items(options).await
+        let storage = self.storage.read_for("list").await;
+        let Some(mut options) = options else {
+            return storage.list_items(None).await;
+        };
+        let Some(filter) = options.filter.take() else {
+            return storage.list_items(Some(options)).await;
+        };
+
+        let candidates = Self::composite_candidates(storage.as_ref(), &filter).await?;
+        let candidates = match (candidates, storage.namespace_field().await?) {
+            (Some(items), _) => Some(items),
+            (None, Some(field)) => match namespaces::namespace_in_filter(&field, &filter) {
+                Some(namespace) => Some(storage.list_namespace(&namespace).await?),
+                None => match namespaces::namespace_prefix_in_filter(&field, &filter) {
+                    Some(prefix) => Some(
+                        self.list_namespace_prefix(storage.as_ref(), &prefix)
+                            .await?,
+                    ),
+                    None => None,
+                },
+            },
+            (None, None) => None,
+        };
+        let candidates = match candidates {
+            Some(items) => Some(items),
+            None => Self::range_candidates(storage.as_ref(), &filter).await?,
+        };
+        let candidates = match candidates {
+            Some(items) => Some(items),
+            None => match self.geo_candidates(&filter) {
+                Some(ids) => {
+                    let ids: Vec<uuid::Uuid> = ids.into_iter().collect();
+                    Some(
+                        storage
+                            .get_items(&ids)
+                            .await?
+                            .into_iter()
+                            .flatten()
+                            .collect(),
+                    )
+                }
+                None => None,
+            },
+        };
+        let check = Arc::new(self.filter_check(filter));
+        let Some(mut items) = candidates else {
+            let matching = check.clone();
+            let matches = move |metadata: &serde_json::Value| matching.matches_value(metadata);
+            let items = storage.list_matching(Some(options), &matches).await?;
+            check.finish()?;
+            return Ok(items);
+        };
+        items.retain(|item| check.matches(item));
+        check.finish()?;
+        items.sort_by_key(|item| item.id);
+        Ok(items
+            .into_iter()
+            .skip(options.offset.unwrap_or(0))
+            .take(options.limit.unwrap_or(usize::MAX))
+            .collect())
     }
 
     /// Query items with vector similarity
@@ -177,15 +928,15 @@ impl LocalIndex {
             });
         }
 
-        let storage = self.storage.read().await;
         let query = Query {
             vector: Some(vector),
             text: None,
-            top_k: top_k.unwrap_or(10) as usize,
+            top_k: self.top_k_or_default(top_k),
             filter,
+            ..Default::default()
         };
 
-        storage.query_items(&query).await
+        self.execute_query(&query, None).await
     }
 
     /// Extended query with text search
@@ -203,102 +954,1249 @@ impl LocalIndex {
             });
         }
 
-        let storage = self.storage.read().await;
         let query = Query {
             vector: Some(vector),
             text: text_query,
-            top_k: top_k.unwrap_or(10) as usize,
+            top_k: self.top_k_or_default(top_k),
             filter,
+            ..Default::default()
         };
 
-        storage.query_items(&query).await
+        self.execute_query(&query, None).await
     }
 
-    /// Get index statistics
-    pub async fn get_stats(&self) -> Result<IndexStats> {
-        let storage = self.storage.read().await;
-        storage.get_stats().await
-    }
+    /// Query with a user-defined scoring hook combining similarity with metadata signals
+    pub async fn query_items_scored(
+        &self,
+        vector: Vec<f32>,
+        top_k: Option<u32>,
+        filter: Option<serde_json::Value>,
+        scoring: &dyn ScoringFn,
+    ) -> Result<Vec<QueryResult>> {
+        let query = Query {
+            vector: Some(vector),
+            text: None,
+            top_k: self.top_k_or_default(top_k),
+            filter,
+            ..Default::default()
+        };
 
-    /// Delete the entire index
-    pub async fn delete_index(&self) -> Result<()> {
-        let mut storage = self.storage.write().await;
-        storage.delete_index().await
+        self.execute_query(&query, Some(scoring)).await
     }
 
-    /// Begin transaction
-    pub async fn begin_update(&self) -> Result<()> {
-        let mut storage = self.storage.write().await;
-        storage.begin_transaction().await
-    }
+    /// Run a fully specified query, applying its filter, boosts and an optional scoring hook.
+    ///
+    /// Runs in a `vectrust.query` tracing span with a child span per stage,
+    /// nested under whatever span the caller has entered, so an
+    /// OpenTelemetry layer places them inside the caller's trace.
+    #[tracing::instrument(
+        name = "vectrust.query",
+        skip_all,
+        fields(
+            index = %self.path.display(),
+            top_k = query.top_k,
+            cached = false,
+            strategy = tracing::field::Empty,
+            results = tracing::field::Empty,
+        )
+    )]
+    pub async fn execute_query(
+        &self,
+        query: &Query,
+        scoring: Option<&dyn ScoringFn>,
+    ) -> Result<Vec<QueryResult>> {
+        self.validate_query(query)?;
 
-    /// End transaction
-    pub async fn end_update(&self) -> Result<()> {
-        let mut storage = self.storage.write().await;
-        storage.commit_transaction().await
+        let started = Instant::now();
+        let storage = self.storage.read_for("query").await;
+        self.count_namespace_query(storage.as_ref(), query).await?;
+        // A scoring hook can't be part of the key, so those queries aren't cached
+        let cacheable = scoring.is_none();
+        if cacheable {
+            let cached = self
+                .query_cache
+                .lock()
+                .unwrap()
+                .as_mut()
+                .and_then(|cache| cache.get(query));
+            if let Some(results) = cached {
+                let span = tracing::Span::current();
+                span.record("cached", true);
+                span.record("results", results.len());
+                storage.record_access(&results.iter().map(|r| r.item.id).collect::<Vec<_>>());
+                return Self::attach_payloads(storage.as_ref(), query, results).await;
+            }
+        }
+        let (results, timings) = self
+            .run_query(storage.as_ref(), query, scoring, started)
+            .await?;
+        tracing::Span::current().record("results", results.len());
+        storage.record_access(&results.iter().map(|r| r.item.id).collect::<Vec<_>>());
+
+        // Still under the storage lock, so no write can slip in between
+        // computing the results and caching them
+        if cacheable {
+            if let Some(cache) = self.query_cache.lock().unwrap().as_mut() {
+                cache.insert(query, &results);
+            }
+        }
+
+        let slow_query_log = self.runtime_config.lock().unwrap().slow_query_log.clone();
+        if let Some(log) = slow_query_log {
+            slow_query::record(&log, &self.path, query, &timings, started.elapsed());
+        }
+        Self::attach_payloads(storage.as_ref(), query, results).await
     }
 
-    /// Cancel transaction
-    pub async fn cancel_update(&self) -> Result<()> {
-        let mut storage = self.storage.write().await;
-        storage.rollback_transaction().await
+    fn validate_query(&self, query: &Query) -> Result<()> {
+        self.check_top_k(query.offset.saturating_add(query.top_k))?;
+        match query.vector {
+            Some(ref vector) if !VectorOps::is_valid_vector(vector) => {
+                Err(VectraError::VectorValidation {
+                    message: "Query vector contains NaN or infinite values".to_string(),
+                })
+            }
+            _ => Ok(()),
+        }
     }
-}
 
-/// Helper function to merge JSON objects
-fn merge_json(target: &mut serde_json::Value, source: serde_json::Value) {
-    if let (Some(target_obj), Some(source_obj)) = (target.as_object_mut(), source.as_object()) {
-        for (key, value) in source_obj {
-            target_obj.insert(key.clone(), value.clone());
+    /// Fail a query that would rank more than `max_top_k` results
+    fn check_top_k(&self, ranked: usize) -> Result<()> {
+        let max_top_k = self.runtime_config.lock().unwrap().max_top_k;
+        if ranked > max_top_k {
+            return Err(VectraError::Query {
+                message: format!(
+                    "Query ranks {} results, more than max_top_k ({}); page with search_after instead",
+                    ranked, max_top_k
+                ),
+            });
         }
-    } else {
-        *target = source;
+        Ok(())
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use tempfile::TempDir;
-    use uuid::Uuid;
+    /// Hydrate, filter, plan and score `query`, timing each stage from
+    /// `started`
+    async fn run_query(
+        &self,
+        storage: &dyn StorageBackend,
+        query: &Query,
+        scoring: Option<&dyn ScoringFn>,
+        started: Instant,
+    ) -> Result<(Vec<QueryResult>, slow_query::QueryTimings)> {
+        let stats = storage.get_stats().await?;
+        let distance = self.distances.resolve(&stats.distance_metric)?;
+        // Stored vectors are reduced, so the query vector must be too
+        let transformed;
+        let query = match (storage.vector_transform().await?, &query.vector) {
+            (Some(transform), Some(vector)) => {
+                transformed = Query {
+                    vector: Some(transform.apply(vector)?),
+                    ..query.clone()
+                };
+                &transformed
+            }
+            _ => query,
+        };
+        let estimated_matches = self
+            .estimate_matches(storage, query.filter.as_ref(), stats.items)
+            .await?;
+        let mut post_filter = None;
+        let (source, estimated, candidates) = async {
+            let geo = query.filter.as_ref().and_then(|f| self.geo_candidates(f));
+            let mut indexed = None;
+            if let (None, Some(filter)) = (&geo, &query.filter) {
+                indexed = Self::composite_candidates(storage, filter)
+                    .await?
+                    .map(|items| (CandidateSource::CompositeIndex, items));
+                if indexed.is_none() {
+                    indexed = Self::range_candidates(storage, filter)
+                        .await?
+                        .map(|items| (CandidateSource::RangeIndex, items));
+                }
+            }
+            let (source, estimated, candidates) = match (geo, indexed) {
+                (Some(ids), _) => {
+                    let estimated = ids.len();
+                    let ids: Vec<uuid::Uuid> = ids.into_iter().collect();
+                    let items = storage.get_items(&ids).await?.into_iter().flatten();
+                    (CandidateSource::GeoIndex, estimated, items.collect())
+                }
+                (None, Some((source, items))) => (source, items.len(), items),
+                (None, None) => {
+                    let shortlist = match estimated_matches {
+                        Some(matches) => {
+                            self.post_filter_candidates(
+                                storage,
+                                query,
+                                scoring.is_some(),
+                                matches,
+                                stats.items,
+                                distance.clone(),
+                            )
+                            .await?
+                        }
+                        None => None,
+                    };
+                    match shortlist {
+                        Some((plan, items)) => {
+                            post_filter = Some(plan);
+                            (CandidateSource::GraphShortlist, items.len(), items)
+                        }
+                        None => (
+                            CandidateSource::Scan,
+                            stats.items,
+                            storage.list_items(None).await?,
+                        ),
+                    }
+                }
+            };
+            // Items tagged with another model are stored but never ranked
+            let mut candidates: Vec<VectorItem> = candidates;
+            if let Some(model) = storage.embedding_model().await? {
+                candidates.retain(|item| model.matches(item));
+            }
+            Ok::<_, VectraError>((source, estimated, candidates))
+        }
+        .instrument(tracing::info_span!("vectrust.query.hydrate"))
+        .await?;
+        let mut timings = slow_query::QueryTimings {
+            hydrate: started.elapsed(),
+            source,
+            estimated,
+            estimated_matches,
+            candidates: candidates.len(),
+            ..Default::default()
+        };
 
-    #[tokio::test]
-    async fn test_local_index_creation() {
-        let temp_dir = TempDir::new().unwrap();
-        let index = LocalIndex::new(temp_dir.path(), None).unwrap();
+        // Filter up front rather than inside the search so its cost is
+        // measured on its own
+        let stage = Instant::now();
+        let mut candidates = candidates;
+        tracing::info_span!("vectrust.query.filter").in_scope(|| {
+            if let Some(ref filter) = query.filter {
+                let check = self.filter_check(filter.clone());
+                candidates.retain(|item| check.matches(item));
+                check.finish()?;
+            }
+            Ok::<_, VectraError>(())
+        })?;
+        let unfiltered = Query {
+            filter: None,
+            ..query.clone()
+        };
+        timings.filter = stage.elapsed();
+        timings.matched = candidates.len();
 
-        assert!(!index.is_index_created().await);
+        let (plan, candidates) = match post_filter {
+            // The candidates are the graph's shortlist already
+            Some(plan) => (plan, candidates),
+            None => {
+                let plan = ann::plan(
+                    self.runtime_config.lock().unwrap().ann.as_ref(),
+                    &unfiltered,
+                    scoring.is_some(),
+                    timings.matched,
+                    stats.items,
+                );
+                self.apply_search_plan(storage, plan, &unfiltered, distance.clone(), candidates)
+                    .await?
+            }
+        };
+        timings.strategy = plan.strategy;
+        timings.strategy_reason = plan.reason;
+        timings.shortlist = (plan.strategy == SearchStrategy::Ann).then_some(plan.shortlist);
+        timings.scored = candidates.len();
+        tracing::Span::current().record("strategy", tracing::field::debug(plan.strategy));
 
-        index.create_index(None).await.unwrap();
-        assert!(index.is_index_created().await);
+        let stage = Instant::now();
+        let span = tracing::info_span!("vectrust.query.scan");
+        let text_hits = match unfiltered.text {
+            Some(ref text) => span.in_scope(|| self.text_hits(text))?,
+            None => None,
+        };
+        let (text_query, hits) = text_hits.unzip();
+        let (threads, deterministic) = {
+            let config = self.runtime_config.lock().unwrap();
+            (config.scoring_threads, config.deterministic_scoring)
+        };
+        let mut results = match scoring {
+            // The hook is borrowed from the caller, so it scores in place
+            Some(scoring) => span.in_scope(|| {
+                let search = VectorSearch::new(distance)
+                    .with_scoring(Some(scoring))
+                    .with_deterministic(deterministic);
+                rank_candidates(&search, &unfiltered, candidates, hits.as_deref())
+            })?,
+            None => {
+                let span = span.clone();
+                scoring_pool::run(threads, move || {
+                    span.in_scope(|| {
+                        let search = VectorSearch::new(distance).with_deterministic(deterministic);
+                        rank_candidates(&search, &unfiltered, candidates, hits.as_deref())
+                    })
+                })
+                .await??
+            }
+        };
+        if let Some(ref text_query) = text_query {
+            span.in_scope(|| self.attach_highlights(text_query, &mut results));
+        }
+        timings.scan = stage.elapsed();
+        timings.results = results.len();
+        Ok((results, timings))
     }
 
-    #[tokio::test]
-    async fn test_insert_and_get_item() {
-        let temp_dir = TempDir::new().unwrap();
-        let index = LocalIndex::new(temp_dir.path(), None).unwrap();
-        index.create_index(None).await.unwrap();
+    /// Evaluation of `filter` under the runtime config's `strict_filters`
+    fn filter_check(&self, filter: serde_json::Value) -> FilterCheck {
+        FilterCheck::new(filter, self.runtime_config.lock().unwrap().strict_filters)
+    }
 
-        let item = VectorItem {
-            id: Uuid::new_v4(),
-            vector: vec![1.0, 0.0, 0.0],
-            metadata: serde_json::json!({"test": "data"}),
-            ..Default::default()
+    /// Keyword search over the text index, scored by BM25.
+    ///
+    /// Supports phrases, `OR`, `NOT`/`-` and grouping (see [`TextQuery`]).
+    /// `filter` is applied to the matching items, and each result carries
+    /// highlights for the terms that matched. Fails if no text index has
+    /// been enabled.
+    #[tracing::instrument(name = "vectrust.text_search", skip_all, fields(index = %self.path.display()))]
+    pub async fn text_search(
+        &self,
+        text: &str,
+        top_k: Option<u32>,
+        filter: Option<serde_json::Value>,
+    ) -> Result<Vec<QueryResult>> {
+        let top_k = self.top_k_or_default(top_k);
+        self.check_top_k(top_k)?;
+        let (text_query, hits) = self.text_hits(text)?.ok_or_else(|| VectraError::Query {
+            message: "Text index is not enabled".to_string(),
+        })?;
+
+        let check = filter.map(|filter| self.filter_check(filter));
+        let storage = self.storage.read_for("query").await;
+        let mut results = Vec::new();
+        for (id, score) in hits {
+            if results.len() == top_k {
+                break;
+            }
+            let Some(item) = storage.get_item(&id).await? else {
+                continue;
+            };
+            if check.as_ref().is_none_or(|check| check.matches(&item)) {
+                results.push(QueryResult {
+                    item,
+                    score,
+                    highlights: Vec::new(),
+                    payload: None,
+                });
+            }
+        }
+        if let Some(check) = check {
+            check.finish()?;
+        }
+        self.attach_highlights(&text_query, &mut results);
+        Ok(results)
+    }
+
+    /// Parse and run a keyword query against the text index, if one is enabled
+    fn text_hits(&self, text: &str) -> Result<Option<TextHits>> {
+        let guard = self.text_index.lock().unwrap();
+        let Some(index) = guard.as_ref() else {
+            return Ok(None);
         };
 
-        let inserted = index.insert_item(item.clone()).await.unwrap();
-        assert_eq!(inserted.id, item.id);
+        let query = index.parse(text)?;
+        let hits = index.search_parsed(&query, usize::MAX);
+        Ok(Some((query, hits)))
+    }
 
-        let retrieved = index.get_item(&item.id).await.unwrap();
-        assert!(retrieved.is_some());
-        assert_eq!(retrieved.unwrap().id, item.id);
+    fn attach_highlights(&self, query: &TextQuery, results: &mut [QueryResult]) {
+        if let Some(index) = self.text_index.lock().unwrap().as_ref() {
+            for result in results {
+                result.highlights = index.highlights(&result.item.id, query);
+            }
+        }
     }
 
-    #[tokio::test]
-    async fn test_vector_similarity_query() {
-        let temp_dir = TempDir::new().unwrap();
-        let index = LocalIndex::new(temp_dir.path(), None).unwrap();
-        index.create_index(None).await.unwrap();
+    /// Insert an item whose metadata is a serializable Rust type
+    pub async fn insert_typed<T: Serialize>(
+        &self,
+        vector: Vec<f32>,
+        metadata: T,
+    ) -> Result<TypedItem<T>> {
+        let typed = TypedItem::new(vector, metadata);
+        let inserted = self.insert_item(typed.to_item()?).await?;
+
+        Ok(TypedItem {
+            created_at: inserted.created_at,
+            updated_at: inserted.updated_at,
+            ..typed
+        })
+    }
+
+    /// Get an item by ID with its metadata deserialized into `T`
+    pub async fn get_typed<T: DeserializeOwned>(
+        &self,
+        id: &uuid::Uuid,
+    ) -> Result<Option<TypedItem<T>>> {
+        self.get_item(id)
+            .await?
+            .map(TypedItem::try_from)
+            .transpose()
+    }
+
+    /// Query items with vector similarity, deserializing each result's metadata into `T`
+    pub async fn query_typed<T: DeserializeOwned>(
+        &self,
+        vector: Vec<f32>,
+        top_k: Option<u32>,
+        filter: Option<serde_json::Value>,
+    ) -> Result<Vec<TypedQueryResult<T>>> {
+        self.query_items(vector, top_k, filter)
+            .await?
+            .into_iter()
+            .map(TypedQueryResult::try_from)
+            .collect()
+    }
+
+    /// Copy or move every item matching `filter` into a new index at `target_path`.
+    ///
+    /// The target is created with this index's distance metric and must not
+    /// already exist. Items keep their IDs and timestamps. Returns the number
+    /// of items transferred.
+    pub async fn split_by_filter<P: AsRef<Path>>(
+        &self,
+        filter: &serde_json::Value,
+        target_path: P,
+        mode: SplitMode,
+    ) -> Result<usize> {
+        let target = LocalIndex::new(target_path, None)?;
+        let stats = self.get_stats().await?;
+        target
+            .create_index(Some(CreateIndexConfig {
+                distance_metric: stats.distance_metric,
+                ..Default::default()
+            }))
+            .await?;
+
+        // Hold the write lock so nothing changes between copy and delete
+        let mut storage = self.storage.write_for("split").await;
+        let matching: Vec<VectorItem> = storage
+            .list_items(None)
+            .await?
+            .into_iter()
+            .filter(|item| MetadataFilter::matches(item, filter))
+            .collect();
+
+        target
+            .storage
+            .write_for("split")
+            .await
+            .insert_items(&matching)
+            .await?;
+        target.index_secondary(&matching)?;
+
+        if mode == SplitMode::Move {
+            let outcome = async {
+                self.hooks
+                    .before_write_items(WriteKind::Delete, &matching)?;
+                for item in &matching {
+                    storage.delete_item(&item.id).await?;
+                    self.unindex_secondary(&item.id)?;
+                }
+                Ok(())
+            }
+            .await;
+            self.audit_items(AuditOperation::Delete, &matching, &outcome);
+            outcome?;
+        }
+
+        Ok(matching.len())
+    }
+
+    /// Build an in-memory geohash index over a `{"lat", "lon"}` metadata field.
+    ///
+    /// Queries whose filter has a top-level (or `$and`) `$near` / `$within`
+    /// clause on `field` then fetch only items in the covering geohash cells
+    /// instead of scanning the whole index. The index is kept up to date by
+    /// this handle's writes but is not persisted; call this again after
+    /// reopening. Replaces any previous geo index.
+    pub async fn enable_geo_index(&self, field: &str, precision: Option<usize>) -> Result<()> {
+        let mut index = GeohashIndex::new(field, precision.unwrap_or(6));
+        let storage = self.storage.read_for("secondary_index").await;
+        for item in storage.list_items(None).await? {
+            index.insert(item.id, &item.metadata);
+        }
+        *self.geo_index.lock().unwrap() = Some(index);
+        Ok(())
+    }
+
+    /// Build a keyword index over the configured metadata fields.
+    ///
+    /// With the `rocksdb` feature the index is persisted inside the index
+    /// folder and reattached automatically on open; otherwise it lives in
+    /// memory. Once enabled, [`LocalIndex::text_search`] runs keyword
+    /// queries and a query's `text` is fused with vector similarity.
+    /// Replaces any previous text index.
+    pub async fn enable_text_index(&self, config: TextIndexConfig) -> Result<()> {
+        self.text_index.lock().unwrap().take();
+
+        #[cfg(feature = "rocksdb")]
+        let mut index = TextIndex::create(&self.path.join(TEXT_INDEX_DIR), config)?;
+        #[cfg(not(feature = "rocksdb"))]
+        let mut index = TextIndex::in_memory(config);
+
+        let storage = self.storage.read_for("secondary_index").await;
+        for item in storage.list_items(None).await? {
+            index.index_item(&item)?;
+        }
+        *self.text_index.lock().unwrap() = Some(index);
+        Ok(())
+    }
+
+    /// Drop the text index and delete its persisted data
+    pub fn disable_text_index(&self) -> Result<()> {
+        self.text_index.lock().unwrap().take();
+
+        #[cfg(feature = "rocksdb")]
+        {
+            let dir = self.path.join(TEXT_INDEX_DIR);
+            if dir.exists() {
+                std::fs::remove_dir_all(dir)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Drop the geo index, returning queries to full scans
+    pub fn disable_geo_index(&self) {
+        self.geo_index.lock().unwrap().take();
+    }
+
+    /// Save a query under `name`, replacing any query already saved with it.
+    ///
+    /// Saved queries are stored in the index folder and available to every
+    /// handle opened on it afterwards.
+    pub fn save_named_query(&self, name: &str, query: NamedQuery) -> Result<()> {
+        if name.is_empty() {
+            return Err(VectraError::Query {
+                message: "Named query needs a non-empty name".to_string(),
+            });
+        }
+        let mut queries = self.named_queries.lock().unwrap();
+        queries.insert(name.to_string(), query);
+        self.write_named_queries(&queries)
+    }
+
+    /// Look up a saved query by name
+    pub fn named_query(&self, name: &str) -> Option<NamedQuery> {
+        self.named_queries.lock().unwrap().get(name).cloned()
+    }
+
+    /// All saved queries by name
+    pub fn named_queries(&self) -> BTreeMap<String, NamedQuery> {
+        self.named_queries.lock().unwrap().clone()
+    }
+
+    /// Remove a saved query, returning whether it existed
+    pub fn delete_named_query(&self, name: &str) -> Result<bool> {
+        let mut queries = self.named_queries.lock().unwrap();
+        if queries.remove(name).is_none() {
+            return Ok(false);
+        }
+        self.write_named_queries(&queries)?;
+        Ok(true)
+    }
+
+    /// Run a saved query.
+    ///
+    /// `vector` and `top_k` override the saved values. A query with text but
+    /// no vector runs as a keyword search over the text index.
+    pub async fn run_named_query(
+        &self,
+        name: &str,
+        vector: Option<Vec<f32>>,
+        top_k: Option<u32>,
+        scoring: Option<&dyn ScoringFn>,
+    ) -> Result<Vec<QueryResult>> {
+        let saved = self.named_query(name).ok_or_else(|| VectraError::Query {
+            message: format!("No named query '{name}'"),
+        })?;
+        let top_k = top_k.map(|k| k as usize).or(saved.top_k);
+        let query = saved.to_query(vector, Some(top_k.unwrap_or_else(|| self.default_top_k())));
+
+        match (&query.vector, &query.text) {
+            (Some(_), _) => self.execute_query(&query, scoring).await,
+            (None, Some(text)) => {
+                self.text_search(text, Some(query.top_k as u32), query.filter)
+                    .await
+            }
+            (None, None) => Err(VectraError::Query {
+                message: format!("Named query '{name}' needs a vector or text"),
+            }),
+        }
+    }
+
+    fn write_named_queries(&self, queries: &BTreeMap<String, NamedQuery>) -> Result<()> {
+        self.write_map_file(NAMED_QUERIES_FILE, queries)
+    }
+
+    /// User-defined index properties, such as a description, owner or the
+    /// parameters the index was built with. They are stored in the index
+    /// folder, so they travel with it and `vectrust stats` shows them.
+    pub fn get_index_metadata(&self) -> BTreeMap<String, serde_json::Value> {
+        self.index_metadata.lock().unwrap().clone()
+    }
+
+    /// Set an index property, returning its previous value
+    pub fn set_index_metadata(
+        &self,
+        key: &str,
+        value: serde_json::Value,
+    ) -> Result<Option<serde_json::Value>> {
+        if key.is_empty() {
+            return Err(VectraError::Storage {
+                message: "Index metadata key must not be empty".to_string(),
+            });
+        }
+        let mut properties = self.index_metadata.lock().unwrap();
+        let previous = properties.insert(key.to_string(), value);
+        self.write_map_file(INDEX_METADATA_FILE, &properties)?;
+        Ok(previous)
+    }
+
+    /// Remove an index property, returning its value
+    pub fn remove_index_metadata(&self, key: &str) -> Result<Option<serde_json::Value>> {
+        let mut properties = self.index_metadata.lock().unwrap();
+        let previous = properties.remove(key);
+        if previous.is_some() {
+            self.write_map_file(INDEX_METADATA_FILE, &properties)?;
+        }
+        Ok(previous)
+    }
+
+    /// Persist a map in the index folder, removing the file once it's empty
+    fn write_map_file<V: Serialize>(&self, name: &str, map: &BTreeMap<String, V>) -> Result<()> {
+        let file = self.path.join(name);
+        if map.is_empty() {
+            if file.exists() {
+                std::fs::remove_file(file)?;
+            }
+            return Ok(());
+        }
+        std::fs::create_dir_all(&self.path)?;
+        std::fs::write(file, serde_json::to_vec_pretty(map)?)?;
+        Ok(())
+    }
+
+    /// Bring the HNSW graph, geo and text indexes, query cache, neighbour
+    /// namespace tallies and planner statistics up to date with written items
+    fn index_secondary(&self, items: &[VectorItem]) -> Result<()> {
+        self.index_ann(items)?;
+        if let Some(cache) = self.query_cache.lock().unwrap().as_mut() {
+            cache.invalidate_written(items);
+        }
+        self.namespaces.lock().unwrap().written(items);
+        self.planner_stats.lock().unwrap().written(items.len());
+        if let Some(graph) = self.neighbors.lock().unwrap().as_mut() {
+            graph.mark_written(items);
+        }
+        if let Some(index) = self.geo_index.lock().unwrap().as_mut() {
+            for item in items {
+                index.insert(item.id, &item.metadata);
+            }
+        }
+        if let Some(index) = self.text_index.lock().unwrap().as_mut() {
+            for item in items {
+                index.index_item(item)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn unindex_secondary(&self, id: &uuid::Uuid) -> Result<()> {
+        if let Some(cache) = self.query_cache.lock().unwrap().as_mut() {
+            cache.invalidate_removed(id);
+        }
+        self.namespaces.lock().unwrap().removed(id);
+        self.planner_stats.lock().unwrap().written(1);
+        if let Some(graph) = self.neighbors.lock().unwrap().as_mut() {
+            graph.mark_removed(id);
+        }
+        if let Some(index) = self.geo_index.lock().unwrap().as_mut() {
+            index.remove(id);
+        }
+        if let Some(index) = self.text_index.lock().unwrap().as_mut() {
+            index.remove_item(id)?;
+        }
+        Ok(())
+    }
+
+    /// Candidate IDs from the geo index when the filter constrains the indexed field
+    fn geo_candidates(&self, filter: &serde_json::Value) -> Option<HashSet<uuid::Uuid>> {
+        let guard = self.geo_index.lock().unwrap();
+        let index = guard.as_ref()?;
+
+        let clauses = filter.as_object()?.iter().chain(
+            filter
+                .get("$and")
+                .and_then(|and| and.as_array())
+                .into_iter()
+                .flatten()
+                .filter_map(|sub| sub.as_object())
+                .flatten(),
+        );
+
+        clauses
+            .filter(|(key, _)| key.as_str() == index.field())
+            .find_map(|(_, condition)| {
+                if let Some((center, radius)) =
+                    condition.get("$near").and_then(MetadataFilter::parse_near)
+                {
+                    Some(index.candidates_near(center, radius))
+                } else {
+                    condition
+                        .get("$within")
+                        .and_then(GeoBounds::from_json)
+                        .map(|bounds| index.candidates_within(&bounds))
+                }
+            })
+    }
+
+    /// Report how much space compaction could reclaim
+    pub async fn compaction_stats(&self) -> Result<CompactionStats> {
+        let storage = self.storage.read_for("compaction").await;
+        storage.compaction_stats().await
+    }
+
+    /// Compact storage now, returning the stats from before compaction.
+    /// Reads and writes go on while live data is copied. Fails with
+    /// [`VectraError::DiskSpace`] unless there is room for a copy of the
+    /// live data.
+    #[tracing::instrument(name = "vectrust.compact", skip_all, fields(index = %self.path.display()))]
+    pub async fn compact(&self, max_bytes_per_sec: Option<u64>) -> Result<CompactionStats> {
+        let live_bytes = self.compaction_stats().await?.live_bytes;
+        self.ensure_disk_space(live_bytes)?;
+        let stats = compact_storage(&self.storage, max_bytes_per_sec).await?;
+        self.hooks.compacted(&stats);
+        Ok(stats)
+    }
+
+    /// Move vectors read at most `policy.demote_max_reads` times since the
+    /// last pass to compressed cold storage, and cold vectors read at least
+    /// `policy.promote_min_reads` times back. Cold vectors are still
+    /// returned by every read, only slower. Reads are counted by
+    /// [`LocalIndex::get_item`] and for query results, in memory.
+    #[tracing::instrument(name = "vectrust.retier", skip_all, fields(index = %self.path.display()))]
+    pub async fn retier(&self, policy: &TieringPolicy) -> Result<TieringStats> {
+        let mut storage = self.storage.write_for("retier").await;
+        storage.retier(policy).await
+    }
+
+    /// Rewrite every metadata record in `encoding`, returning how many
+    /// changed. Items read the same either way; CBOR records are smaller
+    /// and skip JSON text parsing on reads.
+    #[tracing::instrument(name = "vectrust.set_metadata_encoding", skip_all, fields(index = %self.path.display()))]
+    pub async fn set_metadata_encoding(&self, encoding: MetadataEncoding) -> Result<usize> {
+        let mut storage = self.storage.write_for("maintenance").await;
+        storage.set_metadata_encoding(encoding).await
+    }
+
+    /// Start background auto-compaction.
+    ///
+    /// Uses `policy` if given, otherwise the policy persisted at index creation.
+    /// Reopened indexes need this call to resume auto-compaction. Returns false
+    /// when no policy is available. Must be called within a tokio runtime.
+    pub async fn start_auto_compaction(&self, policy: Option<CompactionPolicy>) -> Result<bool> {
+        let policy = match policy {
+            Some(policy) => policy,
+            None => match self
+                .storage
+                .read_for("config")
+                .await
+                .compaction_policy()
+                .await?
+            {
+                Some(policy) => policy,
+                None => return Ok(false),
+            },
+        };
+
+        self.runtime_config.lock().unwrap().compaction = Some(policy.clone());
+        let storage = Arc::downgrade(&self.storage);
+        let hooks = self.hooks.clone();
+        let interval = Duration::from_secs(policy.check_interval_secs.max(1));
+        let task = tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(interval).await;
+
+                // Stop once the index has been dropped
+                let Some(storage) = storage.upgrade() else {
+                    break;
+                };
+
+                let stats = match storage
+                    .read_for("compaction")
+                    .await
+                    .compaction_stats()
+                    .await
+                {
+                    Ok(stats) => stats,
+                    Err(_) => continue,
+                };
+                if policy.should_compact(&stats) {
+                    if let Ok(stats) = compact_storage(&storage, policy.max_bytes_per_sec).await {
+                        hooks.compacted(&stats);
+                    }
+                }
+            }
+        });
+
+        if let Some(previous) = self.compaction_task.lock().unwrap().replace(task) {
+            previous.abort();
+        }
+        Ok(true)
+    }
+
+    /// Stop background auto-compaction if it is running
+    pub fn stop_auto_compaction(&self) {
+        if let Some(task) = self.compaction_task.lock().unwrap().take() {
+            task.abort();
+        }
+        self.runtime_config.lock().unwrap().compaction = None;
+    }
+
+    /// Runtime settings currently in effect
+    pub fn runtime_config(&self) -> RuntimeConfig {
+        self.runtime_config.lock().unwrap().clone()
+    }
+
+    /// Apply new runtime settings without reopening the index.
+    ///
+    /// The default top-k, flush interval, slow-query log and query cache
+    /// apply from the next call on, a changed cache starting empty;
+    /// auto-compaction restarts under the new policy, or stops when it is
+    /// `None`. Must be called within a tokio runtime.
+    pub async fn set_runtime_config(&self, config: RuntimeConfig) -> Result<()> {
+        self.storage
+            .read_for("config")
+            .await
+            .set_flush_interval(config.flush_interval);
+        match config.compaction.clone() {
+            Some(policy) => {
+                self.start_auto_compaction(Some(policy)).await?;
+            }
+            None => self.stop_auto_compaction(),
+        }
+        // Cached scores were summed the other way
+        let rescored = config.deterministic_scoring
+            != self.runtime_config.lock().unwrap().deterministic_scoring;
+        {
+            let mut cache = self.query_cache.lock().unwrap();
+            if cache.as_ref().map(|c| c.config()) != config.query_cache.as_ref() {
+                *cache = config.query_cache.clone().map(query_cache::QueryCache::new);
+            } else if let (true, Some(cache)) = (rescored, cache.as_mut()) {
+                cache.clear();
+            }
+        }
+        *self.runtime_config.lock().unwrap() = config;
+        Ok(())
+    }
+
+    fn clear_query_cache(&self) {
+        if let Some(cache) = self.query_cache.lock().unwrap().as_mut() {
+            cache.clear();
+        }
+    }
+
+    fn default_top_k(&self) -> usize {
+        self.runtime_config.lock().unwrap().default_top_k
+    }
+
+    fn top_k_or_default(&self, top_k: Option<u32>) -> usize {
+        top_k.map_or_else(|| self.default_top_k(), |k| k as usize)
+    }
+
+    /// Get index statistics, broken down by namespace when the index was
+    /// created with a `namespace_field`, with how far the HNSW graph trails
+    /// writes and how long operations have waited for locks
+    pub async fn get_stats(&self) -> Result<IndexStats> {
+        let storage = self.storage.read_for("stats").await;
+        let mut stats = storage.get_stats().await?;
+        stats.namespaces = self.namespace_stats(storage.as_ref()).await?;
+        stats.ann_lag = self.ann_lag();
+        merge_lock_waits(&mut stats.lock_waits, self.lock_waits.snapshot());
+        Ok(stats)
+    }
+
+    /// Time spent waiting for locks since the index was opened or the
+    /// counts were last reset.
+    ///
+    /// The `storage` lock is broken down by the kind of operation that took
+    /// it, such as `insert`, `query` or `compaction`; a backend's internal
+    /// locks, such as `db` and `manifest` in the optimized format, by
+    /// whether they were read or written. Waits that stay small next to an
+    /// operation's latency point at IO rather than contention.
+    pub async fn lock_waits(&self) -> LockWaitStats {
+        let mut waits = self.storage.read_for("stats").await.lock_waits();
+        merge_lock_waits(&mut waits, self.lock_waits.snapshot());
+        waits
+    }
+
+    /// Start counting lock waits afresh
+    pub async fn reset_lock_waits(&self) {
+        self.storage.read_for("stats").await.reset_lock_waits();
+        self.lock_waits.reset();
+    }
+
+    /// Delete the entire index
+    #[tracing::instrument(name = "vectrust.delete_index", skip_all, fields(index = %self.path.display()))]
+    pub async fn delete_index(&self) -> Result<()> {
+        self.stop_auto_compaction();
+        self.disable_geo_index();
+        self.disable_text_index()?;
+        self.drop_neighbors()?;
+        {
+            let mut queries = self.named_queries.lock().unwrap();
+            queries.clear();
+            self.write_named_queries(&queries)?;
+        }
+        {
+            let mut properties = self.index_metadata.lock().unwrap();
+            properties.clear();
+            self.write_map_file(INDEX_METADATA_FILE, &properties)?;
+        }
+        let mut storage = self.storage.write_for("delete_index").await;
+        self.clear_query_cache();
+        self.reset_ann_index();
+        self.namespaces.lock().unwrap().clear();
+        self.planner_stats.lock().unwrap().clear();
+        let outcome = storage.delete_index().await;
+        self.audit(AuditOperation::DeleteIndex, &[], &outcome);
+        outcome
+    }
+
+    /// Begin transaction
+    pub async fn begin_update(&self) -> Result<()> {
+        let mut storage = self.storage.write_for("transaction").await;
+        storage.begin_transaction().await
+    }
+
+    /// End transaction
+    pub async fn end_update(&self) -> Result<()> {
+        let mut storage = self.storage.write_for("transaction").await;
+        storage.commit_transaction().await
+    }
+
+    /// Cancel transaction
+    pub async fn cancel_update(&self) -> Result<()> {
+        let mut storage = self.storage.write_for("transaction").await;
+        self.clear_query_cache();
+        self.reset_ann_index();
+        storage.rollback_transaction().await
+    }
+}
+
+impl Drop for LocalIndex {
+    fn drop(&mut self) {
+        self.stop_auto_compaction();
+        self.stop_maintenance();
+        self.hooks.closed(&self.path);
+    }
+}
+
+/// Helper function to merge JSON objects
+/// Read a map persisted in the index folder, empty if the file doesn't exist
+fn read_map_file<V: DeserializeOwned>(file: &Path) -> Result<BTreeMap<String, V>> {
+    match std::fs::read(file) {
+        Ok(bytes) => Ok(serde_json::from_slice(&bytes)?),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(BTreeMap::new()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Compact `storage`, copying live data a chunk at a time under the shared
+/// lock and at most `max_bytes_per_sec`, so reads and writes go on
+/// meanwhile. The write lock is only held to copy what changed since and
+/// switch over to the copy.
+async fn compact_storage(
+    storage: &TimedRwLock<Box<dyn StorageBackend>>,
+    max_bytes_per_sec: Option<u64>,
+) -> Result<CompactionStats> {
+    let started = Instant::now();
+    let mut copied = 0u64;
+    loop {
+        let chunk = storage
+            .read_for("compaction")
+            .await
+            .prepare_compaction(COMPACTION_CHUNK_SIZE)
+            .await?;
+        if chunk == 0 {
+            break;
+        }
+        copied += chunk;
+        if let Some(rate) = max_bytes_per_sec.filter(|r| *r > 0) {
+            let target = Duration::from_secs_f64(copied as f64 / rate as f64);
+            if let Some(wait) = target.checked_sub(started.elapsed()) {
+                tokio::time::sleep(wait).await;
+            }
+        }
+    }
+    storage.write_for("compaction").await.compact(None).await
+}
+
+/// Which of `items` replace a stored item, always none unless
+/// `on_conflict` is [`OnConflict::Replace`]. Replacements take over the
+/// stored item's creation time and next version.
+async fn find_replaced(
+    storage: &dyn StorageBackend,
+    items: &mut [VectorItem],
+    on_conflict: OnConflict,
+) -> Result<Vec<bool>> {
+    if on_conflict == OnConflict::Error {
+        return Ok(vec![false; items.len()]);
+    }
+    let ids: Vec<uuid::Uuid> = items.iter().map(|item| item.id).collect();
+    let stored = storage.get_items(&ids).await?;
+    Ok(items
+        .iter_mut()
+        .zip(stored)
+        .map(|(item, stored)| match stored {
+            Some(stored) => {
+                item.created_at = stored.created_at;
+                item.version = stored.version + 1;
+                true
+            }
+            None => false,
+        })
+        .collect())
+}
+
+/// Reduce the vectors of `items` with the index's transform, if it has
+/// one, then hold them to its validation policy
+async fn prepare_vectors(storage: &dyn StorageBackend, items: &mut [VectorItem]) -> Result<()> {
+    if let Some(transform) = storage.vector_transform().await? {
+        for item in items.iter_mut() {
+            item.vector = transform.apply(&item.vector)?;
+        }
+    }
+    if let Some(validation) = storage.vector_validation().await? {
+        for item in items {
+            validation.apply(&mut item.vector)?;
+        }
+    }
+    Ok(())
+}
+
+/// Reject items tagged with a model other than the index's, unless the
+/// index segregates them instead
+async fn check_embedding_model(storage: &dyn StorageBackend, items: &[VectorItem]) -> Result<()> {
+    let Some(model) = storage.embedding_model().await? else {
+        return Ok(());
+    };
+    if model.on_mismatch == ModelMismatch::Segregate {
+        return Ok(());
+    }
+    match items.iter().find(|item| !model.matches(item)) {
+        Some(item) => Err(VectraError::VectorValidation {
+            message: format!(
+                "Item {} was embedded with model '{}' but the index uses '{}'",
+                item.id,
+                EmbeddingModel::of_item(item).unwrap_or_default(),
+                model.name
+            ),
+        }),
+        None => Ok(()),
+    }
+}
+
+/// Enforce the index's limits on a write of `items`, `added` of which
+/// are new
+async fn check_limits(
+    storage: &dyn StorageBackend,
+    path: &Path,
+    items: &[VectorItem],
+    added: usize,
+) -> Result<()> {
+    let Some(limits) = storage.limits().await? else {
+        return Ok(());
+    };
+    for item in items {
+        limits.check_item(item)?;
+    }
+
+    if let Some(max) = limits.max_items {
+        let count = storage.get_stats().await?.items;
+        if count + added > max {
+            return Err(VectraError::QuotaExceeded {
+                message: format!(
+                    "Writing {} new items would bring the index to {}, over its limit of {}",
+                    added,
+                    count + added,
+                    max
+                ),
+            });
+        }
+    }
+
+    if let Some(max) = limits.max_disk_bytes {
+        let incoming: u64 = items.iter().map(approximate_size).sum();
+        let used = disk_usage(path)?;
+        if used + incoming > max {
+            return Err(VectraError::QuotaExceeded {
+                message: format!(
+                    "Index uses {} bytes on disk; writing about {} more would pass its limit of {}",
+                    used, incoming, max
+                ),
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Rough stored size of an item: its raw vector plus metadata JSON
+fn approximate_size(item: &VectorItem) -> u64 {
+    let metadata = serde_json::to_vec(&item.metadata).map_or(0, |m| m.len());
+    (item.vector.len() * std::mem::size_of::<f32>() + metadata) as u64
+}
+
+/// Bytes used by every file under `path`
+fn disk_usage(path: &Path) -> Result<u64> {
+    let mut total = 0;
+    let entries = match std::fs::read_dir(path) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+        Err(e) => return Err(e.into()),
+    };
+    for entry in entries {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        total += if metadata.is_dir() {
+            disk_usage(&entry.path())?
+        } else {
+            metadata.len()
+        };
+    }
+    Ok(total)
+}
+
+fn merge_json(target: &mut serde_json::Value, source: serde_json::Value) {
+    if let (Some(target_obj), Some(source_obj)) = (target.as_object_mut(), source.as_object()) {
+        for (key, value) in source_obj {
+            target_obj.insert(key.clone(), value.clone());
+        }
+    } else {
+        *target = source;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+    use uuid::Uuid;
+
+    /// A span's name and its parent's
+    type SpanEdge = (String, Option<String>);
+
+    /// Records every new span with the name of its parent
+    #[derive(Clone, Default)]
+    struct SpanRecorder(Arc<Mutex<Vec<SpanEdge>>>);
+
+    impl<S> tracing_subscriber::Layer<S> for SpanRecorder
+    where
+        S: tracing::Subscriber + for<'a> tracing_subscriber::registry::LookupSpan<'a>,
+    {
+        fn on_new_span(
+            &self,
+            _attrs: &tracing::span::Attributes<'_>,
+            id: &tracing::span::Id,
+            ctx: tracing_subscriber::layer::Context<'_, S>,
+        ) {
+            let span = ctx.span(id).unwrap();
+            let parent = span.parent().map(|parent| parent.name().to_string());
+            self.0
+                .lock()
+                .unwrap()
+                .push((span.name().to_string(), parent));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_query_spans_nest_under_caller() {
+        use tracing_subscriber::layer::SubscriberExt;
+        let recorder = SpanRecorder::default();
+        let _guard =
+            tracing::subscriber::set_default(tracing_subscriber::registry().with(recorder.clone()));
+
+        let temp_dir = TempDir::new().unwrap();
+        let index = LocalIndex::new(temp_dir.path(), None).unwrap();
+        index.create_index(None).await.unwrap();
+        index
+            .insert_item(VectorItem {
+                vector: vec![1.0, 0.0],
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+
+        let request = tracing::info_span!("http.request");
+        index
+            .query_items(vec![1.0, 0.0], Some(1), None)
+            .instrument(request)
+            .await
+            .unwrap();
+
+        let spans = recorder.0.lock().unwrap().clone();
+        let has = |name: &str, parent: &str| {
+            spans.contains(&(name.to_string(), Some(parent.to_string())))
+        };
+        assert!(has("vectrust.query", "http.request"), "{:?}", spans);
+        for stage in ["hydrate", "filter", "scan"] {
+            assert!(has(&format!("vectrust.query.{}", stage), "vectrust.query"));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_local_index_creation() {
+        let temp_dir = TempDir::new().unwrap();
+        let index = LocalIndex::new(temp_dir.path(), None).unwrap();
+
+        assert!(!index.is_index_created().await);
+
+        index.create_index(None).await.unwrap();
+        assert!(index.is_index_created().await);
+    }
+
+    #[tokio::test]
+    async fn test_insert_and_get_item() {
+        let temp_dir = TempDir::new().unwrap();
+        let index = LocalIndex::new(temp_dir.path(), None).unwrap();
+        index.create_index(None).await.unwrap();
+
+        let item = VectorItem {
+            id: Uuid::new_v4(),
+            vector: vec![1.0, 0.0, 0.0],
+            metadata: serde_json::json!({"test": "data"}),
+            ..Default::default()
+        };
+
+        let inserted = index.insert_item(item.clone()).await.unwrap();
+        assert_eq!(inserted.id, item.id);
+
+        let retrieved = index.get_item(&item.id).await.unwrap();
+        assert!(retrieved.is_some());
+        assert_eq!(retrieved.unwrap().id, item.id);
+
+        let missing = Uuid::new_v4();
+        let batch = index.get_items(&[missing, item.id]).await.unwrap();
+        assert!(batch[0].is_none());
+        assert_eq!(batch[1].as_ref().unwrap().metadata, item.metadata);
+    }
+
+    #[tokio::test]
+    async fn test_vector_similarity_query() {
+        let temp_dir = TempDir::new().unwrap();
+        let index = LocalIndex::new(temp_dir.path(), None).unwrap();
+        index.create_index(None).await.unwrap();
 
         // Insert test vectors
         let item1 = VectorItem {
@@ -308,26 +2206,1537 @@ mod tests {
             ..Default::default()
         };
 
-        let item2 = VectorItem {
-            id: Uuid::new_v4(),
-            vector: vec![0.0, 1.0, 0.0],
-            metadata: serde_json::json!({"name": "item2"}),
+        let item2 = VectorItem {
+            id: Uuid::new_v4(),
+            vector: vec![0.0, 1.0, 0.0],
+            metadata: serde_json::json!({"name": "item2"}),
+            ..Default::default()
+        };
+
+        index.insert_item(item1.clone()).await.unwrap();
+        index.insert_item(item2.clone()).await.unwrap();
+
+        // Query with vector similar to item1
+        let results = index
+            .query_items(vec![1.0, 0.1, 0.0], Some(2), None)
+            .await
+            .unwrap();
+
+        assert_eq!(results.len(), 2);
+        // First result should be more similar to item1
+        assert_eq!(results[0].item.id, item1.id);
+        assert!(results[0].score > results[1].score);
+    }
+
+    #[tokio::test]
+    async fn test_custom_distance_metric() {
+        /// Similarity along the second dimension only
+        struct SecondAxis;
+        impl DistanceFn for SecondAxis {
+            fn similarity(&self, a: &[f32], b: &[f32]) -> f32 {
+                a[1] * b[1]
+            }
+        }
+
+        let temp_dir = TempDir::new().unwrap();
+        let index = LocalIndex::new(temp_dir.path(), None).unwrap();
+        let config = CreateIndexConfig {
+            distance_metric: DistanceMetric::Custom("second-axis".to_string()),
+            ..Default::default()
+        };
+        assert!(index.create_index(Some(config.clone())).await.is_err());
+
+        index
+            .register_distance("second-axis", Arc::new(SecondAxis))
+            .unwrap();
+        assert!(index
+            .register_distance("second-axis", Arc::new(SecondAxis))
+            .is_err());
+        index.create_index(Some(config)).await.unwrap();
+        let first = index
+            .insert_item(VectorItem {
+                vector: vec![1.0, 0.0],
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        let second = index
+            .insert_item(VectorItem {
+                vector: vec![0.0, 1.0],
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        drop(index);
+
+        // The metric's name survives reopening, but each handle needs the
+        // function registered; cosine would rank `first` first
+        let index = LocalIndex::new(temp_dir.path(), None).unwrap();
+        assert!(index
+            .query_items(vec![1.0, 0.5], Some(2), None)
+            .await
+            .is_err());
+        index
+            .register_distance("second-axis", Arc::new(SecondAxis))
+            .unwrap();
+        let results = index
+            .query_items(vec![1.0, 0.5], Some(2), None)
+            .await
+            .unwrap();
+        assert_eq!(results[0].item.id, second.id);
+        assert_eq!(results[0].score, 0.5);
+        assert_eq!(results[1].item.id, first.id);
+    }
+
+    #[tokio::test]
+    async fn test_typed_metadata_roundtrip() {
+        #[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+        struct DocMeta {
+            title: String,
+            page: u32,
+        }
+
+        let temp_dir = TempDir::new().unwrap();
+        let index = LocalIndex::new(temp_dir.path(), None).unwrap();
+        index.create_index(None).await.unwrap();
+
+        let meta = DocMeta {
+            title: "Intro".to_string(),
+            page: 3,
+        };
+        let inserted = index.insert_typed(vec![1.0, 0.0, 0.0], meta).await.unwrap();
+
+        let fetched = index
+            .get_typed::<DocMeta>(&inserted.id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(fetched.metadata, inserted.metadata);
+
+        let results = index
+            .query_typed::<DocMeta>(vec![1.0, 0.0, 0.0], Some(1), None)
+            .await
+            .unwrap();
+        assert_eq!(results[0].item.metadata.title, "Intro");
+
+        // Metadata that doesn't fit the requested type is a serialization error
+        let mismatch = index.get_typed::<Vec<String>>(&inserted.id).await;
+        assert!(matches!(mismatch, Err(VectraError::Serialization(_))));
+    }
+
+    #[cfg(feature = "rocksdb")]
+    #[tokio::test]
+    async fn test_auto_compaction_reclaims_deleted_space() {
+        let temp_dir = TempDir::new().unwrap();
+        let index = LocalIndex::new(temp_dir.path(), None).unwrap();
+        let config = CreateIndexConfig {
+            compaction: Some(CompactionPolicy {
+                max_deleted_ratio: 0.1,
+                check_interval_secs: 1,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        index.create_index(Some(config)).await.unwrap();
+
+        let mut ids = Vec::new();
+        for i in 0..4 {
+            let item = VectorItem {
+                vector: vec![i as f32, 1.0, 0.0],
+                ..Default::default()
+            };
+            ids.push(index.insert_item(item).await.unwrap().id);
+        }
+        index.delete_item(&ids[0]).await.unwrap();
+        index.delete_item(&ids[1]).await.unwrap();
+        assert!(index.compaction_stats().await.unwrap().reclaimable_bytes > 0);
+
+        let mut reclaimed = false;
+        for _ in 0..30 {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            if index.compaction_stats().await.unwrap().reclaimable_bytes == 0 {
+                reclaimed = true;
+                break;
+            }
+        }
+        assert!(reclaimed, "background compaction did not run");
+        assert_eq!(index.list_items(None).await.unwrap().len(), 2);
+
+        // The policy is persisted, so a reopened index can resume it
+        drop(index);
+        let reopened = LocalIndex::new(temp_dir.path(), None).unwrap();
+        assert!(reopened.start_auto_compaction(None).await.unwrap());
+    }
+
+    #[cfg(feature = "rocksdb")]
+    #[tokio::test]
+    async fn test_throttled_compaction_leaves_storage_unlocked() {
+        let temp_dir = TempDir::new().unwrap();
+        let index = Arc::new(LocalIndex::new(temp_dir.path(), None).unwrap());
+        index.create_index(None).await.unwrap();
+
+        let mut ids = Vec::new();
+        for i in 0..100 {
+            let item = VectorItem {
+                vector: vec![i as f32, 1.0, 0.0],
+                ..Default::default()
+            };
+            ids.push(index.insert_item(item).await.unwrap().id);
+        }
+        index.delete_item(&ids[0]).await.unwrap();
+
+        // About a second's worth of copying at this rate, spent sleeping
+        // between chunks rather than holding the storage lock
+        let compaction = tokio::spawn({
+            let index = index.clone();
+            async move { index.compact(Some(1500)).await }
+        });
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        let write = index.delete_item(&ids[1]);
+        tokio::time::timeout(Duration::from_millis(500), write)
+            .await
+            .expect("the delete waited for compaction")
+            .unwrap();
+
+        compaction.await.unwrap().unwrap();
+        assert!(index.get_item(&ids[1]).await.unwrap().is_none());
+        assert_eq!(index.list_items(None).await.unwrap().len(), 98);
+    }
+
+    #[cfg(feature = "rocksdb")]
+    #[tokio::test]
+    async fn test_retier_counts_reads() {
+        let temp_dir = TempDir::new().unwrap();
+        let index = LocalIndex::new(temp_dir.path(), None).unwrap();
+        index.create_index(None).await.unwrap();
+        let items = (0..5)
+            .map(|i| VectorItem {
+                vector: vec![i as f32, 1.0, 0.0],
+                ..Default::default()
+            })
+            .collect();
+        let items = index.insert_items(items).await.unwrap();
+
+        // One query result and one lookup are read; the other three go cold
+        let results = index
+            .query_items(vec![4.0, 1.0, 0.0], Some(1), None)
+            .await
+            .unwrap();
+        index.get_item(&items[0].id).await.unwrap();
+        let stats = index.retier(&TieringPolicy::default()).await.unwrap();
+        assert_eq!((stats.hot_items, stats.demoted), (2, 3));
+
+        let again = index
+            .query_items(vec![4.0, 1.0, 0.0], Some(5), None)
+            .await
+            .unwrap();
+        assert_eq!(again.len(), 5);
+        assert_eq!(again[0].item.id, results[0].item.id);
+    }
+
+    #[tokio::test]
+    async fn test_lock_waits_by_operation() {
+        let temp_dir = TempDir::new().unwrap();
+        let index = Arc::new(LocalIndex::new(temp_dir.path(), None).unwrap());
+        index.create_index(None).await.unwrap();
+
+        let writers: Vec<_> = (0..4)
+            .map(|i| {
+                let index = index.clone();
+                tokio::spawn(async move {
+                    for j in 0..10 {
+                        let item = VectorItem {
+                            vector: vec![i as f32, j as f32, 1.0],
+                            ..Default::default()
+                        };
+                        index.insert_item(item).await.unwrap();
+                    }
+                })
+            })
+            .collect();
+        for writer in writers {
+            writer.await.unwrap();
+        }
+        index
+            .query_items(vec![1.0, 1.0, 1.0], Some(3), None)
+            .await
+            .unwrap();
+
+        let waits = index.get_stats().await.unwrap().lock_waits;
+        let storage = &waits["storage"];
+        assert_eq!(storage["insert"].acquisitions, 40);
+        assert!(storage["insert"].contended <= 40);
+        assert!(storage["query"].acquisitions >= 1);
+        #[cfg(feature = "rocksdb")]
+        assert!(waits["vector_mmap"]["read"].acquisitions > 0);
+
+        index.reset_lock_waits().await;
+        let waits = index.lock_waits().await;
+        assert_eq!(waits.len(), 1);
+        assert_eq!(waits["storage"].keys().collect::<Vec<_>>(), ["stats"]);
+    }
+
+    #[cfg(feature = "rocksdb")]
+    #[tokio::test]
+    async fn test_runtime_config_reload() {
+        let temp_dir = TempDir::new().unwrap();
+        let index = LocalIndex::new(temp_dir.path(), None).unwrap();
+        index.create_index(None).await.unwrap();
+        for i in 0..5 {
+            let item = VectorItem {
+                vector: vec![i as f32, 1.0],
+                ..Default::default()
+            };
+            index.insert_item(item).await.unwrap();
+        }
+        assert_eq!(index.runtime_config().default_top_k, 10);
+        assert!(index.runtime_config().compaction.is_none());
+
+        let mut config = index.runtime_config();
+        config.default_top_k = 2;
+        config.flush_interval = 1;
+        config.compaction = Some(CompactionPolicy {
+            check_interval_secs: 1,
+            ..Default::default()
+        });
+        index.set_runtime_config(config).await.unwrap();
+
+        let results = index.query_items(vec![1.0, 1.0], None, None).await.unwrap();
+        assert_eq!(results.len(), 2);
+        assert!(index.compaction_task.lock().unwrap().is_some());
+
+        // A flush interval of one saves the manifest on every write
+        let item = VectorItem {
+            vector: vec![9.0, 1.0],
+            ..Default::default()
+        };
+        index.insert_item(item).await.unwrap();
+        let manifest: serde_json::Value =
+            serde_json::from_slice(&std::fs::read(temp_dir.path().join("manifest.json")).unwrap())
+                .unwrap();
+        assert_eq!(manifest["total_items"], 6);
+
+        let mut config = index.runtime_config();
+        config.compaction = None;
+        index.set_runtime_config(config).await.unwrap();
+        assert!(index.compaction_task.lock().unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_slow_query_log() {
+        let temp_dir = TempDir::new().unwrap();
+        let index = LocalIndex::new(temp_dir.path().join("index"), None).unwrap();
+        index.create_index(None).await.unwrap();
+        for i in 0..5 {
+            let item = VectorItem {
+                vector: vec![i as f32, 1.0],
+                metadata: serde_json::json!({ "even": i % 2 == 0 }),
+                ..Default::default()
+            };
+            index.insert_item(item).await.unwrap();
+        }
+
+        let log_path = temp_dir.path().join("slow.log");
+        let mut config = index.runtime_config();
+        config.slow_query_log = Some(SlowQueryLog {
+            threshold_ms: 60_000,
+            path: Some(log_path.clone()),
+        });
+        index.set_runtime_config(config.clone()).await.unwrap();
+        let filter = Some(serde_json::json!({ "even": true }));
+        index
+            .query_items(vec![1.0, 1.0], Some(2), filter.clone())
+            .await
+            .unwrap();
+        assert!(!log_path.exists());
+
+        // A zero threshold logs every query
+        config.slow_query_log.as_mut().unwrap().threshold_ms = 0;
+        index.set_runtime_config(config).await.unwrap();
+        let results = index
+            .query_items(vec![1.0, 1.0], Some(2), filter)
+            .await
+            .unwrap();
+        assert_eq!(results.len(), 2);
+        assert!(results.iter().all(|r| r.item.metadata["even"] == true));
+
+        let log = std::fs::read_to_string(&log_path).unwrap();
+        let entry: serde_json::Value = serde_json::from_str(log.trim()).unwrap();
+        assert_eq!(entry["candidates"], 5);
+        assert_eq!(entry["matched"], 3);
+        assert_eq!(entry["results"], 2);
+        assert_eq!(entry["topK"], 2);
+        assert_eq!(entry["filter"]["even"], true);
+        assert!(entry["scanMs"].as_f64().unwrap() >= 0.0);
+    }
+
+    #[tokio::test]
+    async fn test_query_cache_invalidation() {
+        let temp_dir = TempDir::new().unwrap();
+        let index = LocalIndex::new(temp_dir.path(), None).unwrap();
+        index.create_index(None).await.unwrap();
+        let item = |x: f32, lang: &str| VectorItem {
+            vector: vec![x, 1.0],
+            metadata: serde_json::json!({ "lang": lang }),
+            ..Default::default()
+        };
+        let first = index.insert_item(item(1.0, "en")).await.unwrap();
+        index.insert_item(item(2.0, "de")).await.unwrap();
+
+        let mut config = index.runtime_config();
+        config.query_cache = Some(QueryCacheConfig::default());
+        index.set_runtime_config(config).await.unwrap();
+
+        let en = Some(serde_json::json!({ "lang": "en" }));
+        let query = || index.query_items(vec![1.0, 1.0], Some(5), en.clone());
+        assert_eq!(query().await.unwrap().len(), 1);
+
+        // Bypass the facade so the cache can't know: the stale result shows it was cached
+        let hidden = item(1.5, "en");
+        index
+            .storage
+            .write()
+            .await
+            .insert_item(&hidden)
+            .await
+            .unwrap();
+        assert_eq!(query().await.unwrap().len(), 1);
+
+        // A write the filter doesn't match leaves the entry alone
+        index.insert_item(item(3.0, "de")).await.unwrap();
+        assert_eq!(query().await.unwrap().len(), 1);
+
+        // One it matches drops it
+        index.insert_item(item(4.0, "en")).await.unwrap();
+        assert_eq!(query().await.unwrap().len(), 3);
+
+        // So does deleting a returned item
+        index.delete_item(&first.id).await.unwrap();
+        assert_eq!(query().await.unwrap().len(), 2);
+
+        // Or an update that moves it out of the filter
+        let returned = query().await.unwrap()[0].item.id;
+        index
+            .update_item(UpdateRequest {
+                id: returned,
+                vector: None,
+                metadata: Some(serde_json::json!({ "lang": "fr" })),
+            })
+            .await
+            .unwrap();
+        assert_eq!(query().await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_chunked_insert_progress() {
+        let temp_dir = TempDir::new().unwrap();
+        let index = Arc::new(LocalIndex::new(temp_dir.path(), None).unwrap());
+        index.create_index(None).await.unwrap();
+        let mut config = index.runtime_config();
+        config.insert_chunk_size = 3;
+        index.set_runtime_config(config).await.unwrap();
+
+        let items: Vec<VectorItem> = (0..7)
+            .map(|i| VectorItem {
+                vector: vec![i as f32, 1.0],
+                ..Default::default()
+            })
+            .collect();
+        let mut reports = Vec::new();
+        let inserted = index
+            .insert_items_with_progress(items, |p| reports.push(p))
+            .await
+            .unwrap();
+        assert_eq!(inserted.len(), 7);
+        let done: Vec<usize> = reports.iter().map(|p| p.inserted).collect();
+        assert_eq!(done, vec![3, 6, 7]);
+        assert!(reports.iter().all(|p| p.total == 7));
+
+        // Queries can run while a chunked bulk insert is under way
+        let mut config = index.runtime_config();
+        config.insert_chunk_size = 1;
+        index.set_runtime_config(config).await.unwrap();
+        let items: Vec<VectorItem> = (0..200)
+            .map(|i| VectorItem {
+                vector: vec![i as f32, 2.0],
+                ..Default::default()
+            })
+            .collect();
+        let writer = {
+            let index = index.clone();
+            tokio::spawn(async move { index.insert_items(items).await })
+        };
+        index
+            .query_items(vec![1.0, 1.0], Some(1), None)
+            .await
+            .unwrap();
+        writer.await.unwrap().unwrap();
+        assert_eq!(index.get_stats().await.unwrap().items, 207);
+    }
+
+    #[tokio::test]
+    async fn test_index_limits() {
+        let temp_dir = TempDir::new().unwrap();
+        let index = LocalIndex::new(temp_dir.path(), None).unwrap();
+        let config = CreateIndexConfig {
+            limits: Some(IndexLimits {
+                max_dimensions: Some(3),
+                max_metadata_bytes: Some(32),
+                max_items: Some(3),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        index.create_index(Some(config)).await.unwrap();
+        let item = |dims: usize, note: &str| VectorItem {
+            vector: vec![1.0; dims],
+            metadata: serde_json::json!({ "note": note }),
+            ..Default::default()
+        };
+
+        assert!(matches!(
+            index.insert_item(item(4, "")).await,
+            Err(VectraError::VectorValidation { .. })
+        ));
+        assert!(matches!(
+            index.insert_item(item(3, &"x".repeat(40))).await,
+            Err(VectraError::MetadataValidation { .. })
+        ));
+
+        let first = index.insert_item(item(3, "a")).await.unwrap();
+        index
+            .insert_items(vec![item(3, "b"), item(3, "c")])
+            .await
+            .unwrap();
+        assert!(matches!(
+            index.insert_item(item(3, "d")).await,
+            Err(VectraError::QuotaExceeded { .. })
+        ));
+        assert_eq!(index.get_stats().await.unwrap().items, 3);
+
+        // Updates don't add items, but still respect the per-item limits
+        let update = |note: &str| UpdateRequest {
+            id: first.id,
+            vector: None,
+            metadata: Some(serde_json::json!({ "note": note })),
+        };
+        index.update_item(update("changed")).await.unwrap();
+        assert!(index.update_item(update(&"x".repeat(40))).await.is_err());
+
+        // Limits are persisted with the index
+        drop(index);
+        let reopened = LocalIndex::new(temp_dir.path(), None).unwrap();
+        assert_eq!(reopened.limits().await.unwrap().unwrap().max_items, Some(3));
+
+        let disk_dir = TempDir::new().unwrap();
+        let index = LocalIndex::new(disk_dir.path(), None).unwrap();
+        let config = CreateIndexConfig {
+            limits: Some(IndexLimits {
+                max_disk_bytes: Some(64 * 1024),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        index.create_index(Some(config)).await.unwrap();
+        let large = || VectorItem {
+            vector: vec![0.5; 1024],
+            ..Default::default()
+        };
+        let mut result = Ok(large());
+        for _ in 0..100 {
+            result = index.insert_item(large()).await;
+            if result.is_err() {
+                break;
+            }
+        }
+        assert!(matches!(result, Err(VectraError::QuotaExceeded { .. })));
+    }
+
+    #[tokio::test]
+    async fn test_vector_transform() {
+        let temp_dir = TempDir::new().unwrap();
+        let index = LocalIndex::new(temp_dir.path(), None).unwrap();
+        let mut transform = VectorTransform::truncate(2);
+        transform.version = 3;
+        transform.normalize = true;
+        let config = CreateIndexConfig {
+            vector_transform: Some(transform.clone()),
+            ..Default::default()
+        };
+        index.create_index(Some(config)).await.unwrap();
+
+        // Vectors are truncated and normalized on the way in
+        let item = |vector: Vec<f32>| VectorItem {
+            vector,
+            ..Default::default()
+        };
+        let first = index.insert_item(item(vec![3.0, 4.0, 9.0])).await.unwrap();
+        assert_eq!(first.vector, vec![0.6, 0.8]);
+        let inserted = index
+            .insert_items(vec![item(vec![0.0, 2.0, 1.0, 1.0])])
+            .await
+            .unwrap();
+        assert_eq!(inserted[0].vector, vec![0.0, 1.0]);
+        assert!(matches!(
+            index.insert_item(item(vec![1.0])).await,
+            Err(VectraError::InvalidDimensions {
+                expected: 2,
+                actual: 1
+            })
+        ));
+        let update = UpdateRequest {
+            id: first.id,
+            vector: Some(vec![5.0, 0.0, 7.0]),
+            metadata: None,
+        };
+        index.update_item(update).await.unwrap();
+        let stored = index.get_item(&first.id).await.unwrap().unwrap();
+        assert_eq!(stored.vector, vec![1.0, 0.0]);
+
+        // ...and so are query vectors
+        let results = index
+            .query_items(vec![2.0, 0.1, -8.0], Some(1), None)
+            .await
+            .unwrap();
+        assert_eq!(results[0].item.id, first.id);
+
+        // The transform survives reopening
+        drop(index);
+        let index = LocalIndex::new(temp_dir.path(), None).unwrap();
+        assert_eq!(index.vector_transform().await.unwrap(), Some(transform));
+
+        // Projections subtract the mean before projecting
+        let pca = VectorTransform::project(vec![1.0, 1.0], vec![vec![1.0, -1.0]]);
+        assert_eq!(pca.apply(&[3.0, 2.0]).unwrap(), vec![1.0]);
+        assert!(pca.apply(&[3.0, 2.0, 1.0]).is_err());
+        let empty = VectorTransform::project(Vec::new(), Vec::new());
+        let temp_dir = TempDir::new().unwrap();
+        let index = LocalIndex::new(temp_dir.path(), None).unwrap();
+        let config = CreateIndexConfig {
+            vector_transform: Some(empty),
+            ..Default::default()
+        };
+        assert!(index.create_index(Some(config)).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_vector_validation() {
+        let temp_dir = TempDir::new().unwrap();
+        let index = LocalIndex::new(temp_dir.path(), None).unwrap();
+        let validation = VectorValidation {
+            reject_zero: true,
+            min_norm: Some(0.5),
+            max_norm: Some(10.0),
+            normalize: true,
+        };
+        let config = CreateIndexConfig {
+            vector_validation: Some(validation.clone()),
+            ..Default::default()
+        };
+        index.create_index(Some(config)).await.unwrap();
+
+        let item = |vector: Vec<f32>| VectorItem {
+            vector,
+            ..Default::default()
+        };
+        let rejected = |result: Result<VectorItem>| {
+            matches!(result, Err(VectraError::VectorValidation { .. }))
+        };
+        assert!(rejected(index.insert_item(item(vec![0.0, 0.0])).await));
+        assert!(rejected(index.insert_item(item(vec![0.1, 0.1])).await));
+        assert!(rejected(index.insert_item(item(vec![30.0, 40.0])).await));
+        assert!(index
+            .insert_items(vec![item(vec![1.0, 0.0]), item(vec![0.0, 0.0])])
+            .await
+            .is_err());
+        assert!(index.list_items(None).await.unwrap().is_empty());
+
+        // Vectors that pass are stored at unit length
+        let first = index.insert_item(item(vec![3.0, 4.0])).await.unwrap();
+        assert_eq!(first.vector, vec![0.6, 0.8]);
+        let update = |vector: Vec<f32>| UpdateRequest {
+            id: first.id,
+            vector: Some(vector),
+            metadata: None,
+        };
+        assert!(matches!(
+            index.update_item(update(vec![0.0, 0.0])).await,
+            Err(VectraError::VectorValidation { .. })
+        ));
+        index.update_item(update(vec![0.0, 2.0])).await.unwrap();
+        let stored = index.get_item(&first.id).await.unwrap().unwrap();
+        assert_eq!(stored.vector, vec![0.0, 1.0]);
+
+        // The policy survives reopening
+        drop(index);
+        let index = LocalIndex::new(temp_dir.path(), None).unwrap();
+        assert_eq!(index.vector_validation().await.unwrap(), Some(validation));
+
+        let temp_dir = TempDir::new().unwrap();
+        let index = LocalIndex::new(temp_dir.path(), None).unwrap();
+        let config = CreateIndexConfig {
+            vector_validation: Some(VectorValidation {
+                min_norm: Some(2.0),
+                max_norm: Some(1.0),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        assert!(index.create_index(Some(config)).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_embedding_model_enforcement() {
+        let tagged = |model: &str, x: f32| VectorItem {
+            vector: vec![x, 1.0],
+            metadata: serde_json::json!({ EMBEDDING_MODEL_KEY: model }),
             ..Default::default()
         };
 
-        index.insert_item(item1.clone()).await.unwrap();
-        index.insert_item(item2.clone()).await.unwrap();
+        let temp_dir = TempDir::new().unwrap();
+        let index = LocalIndex::new(temp_dir.path(), None).unwrap();
+        let config = CreateIndexConfig {
+            embedding_model: Some(EmbeddingModel::new("minilm@2")),
+            ..Default::default()
+        };
+        index.create_index(Some(config)).await.unwrap();
 
-        // Query with vector similar to item1
+        index.insert_item(tagged("minilm@2", 1.0)).await.unwrap();
+        let untagged = VectorItem {
+            vector: vec![2.0, 1.0],
+            ..Default::default()
+        };
+        let untagged = index.insert_item(untagged).await.unwrap();
+        assert!(index.insert_item(tagged("minilm@1", 3.0)).await.is_err());
+        assert!(index
+            .insert_items(vec![tagged("minilm@2", 4.0), tagged("ada", 5.0)])
+            .await
+            .is_err());
+        let retag = UpdateRequest {
+            id: untagged.id,
+            vector: None,
+            metadata: Some(serde_json::json!({ EMBEDDING_MODEL_KEY: "ada" })),
+        };
+        assert!(index.update_item(retag).await.is_err());
+        assert_eq!(index.get_stats().await.unwrap().items, 2);
+
+        // The model survives reopening
+        drop(index);
+        let index = LocalIndex::new(temp_dir.path(), None).unwrap();
+        let model = index.embedding_model().await.unwrap().unwrap();
+        assert_eq!(model.name, "minilm@2");
+
+        // Segregated indexes store mismatched items but never rank them
+        let temp_dir = TempDir::new().unwrap();
+        let index = LocalIndex::new(temp_dir.path(), None).unwrap();
+        let config = CreateIndexConfig {
+            embedding_model: Some(EmbeddingModel {
+                name: "minilm@2".to_string(),
+                on_mismatch: ModelMismatch::Segregate,
+            }),
+            ..Default::default()
+        };
+        index.create_index(Some(config)).await.unwrap();
+        let kept = index.insert_item(tagged("minilm@2", 1.0)).await.unwrap();
+        index.insert_item(tagged("minilm@1", 1.0)).await.unwrap();
+        assert_eq!(index.list_items(None).await.unwrap().len(), 2);
+        let results = index.query_items(vec![1.0, 1.0], None, None).await.unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].item.id, kept.id);
+    }
+
+    #[tokio::test]
+    async fn test_query_with_filter_and_scoring_hook() {
+        let temp_dir = TempDir::new().unwrap();
+        let index = LocalIndex::new(temp_dir.path(), None).unwrap();
+        index.create_index(None).await.unwrap();
+
+        for (vector, lang, views) in [
+            (vec![1.0, 0.0], "en", 1),
+            (vec![0.9, 0.1], "en", 500),
+            (vec![1.0, 0.0], "de", 0),
+        ] {
+            let item = VectorItem {
+                vector,
+                metadata: serde_json::json!({ "lang": lang, "views": views }),
+                ..Default::default()
+            };
+            index.insert_item(item).await.unwrap();
+        }
+
+        let filter = Some(serde_json::json!({ "lang": "en" }));
         let results = index
-            .query_items(vec![1.0, 0.1, 0.0], Some(2), None)
+            .query_items(vec![1.0, 0.0], Some(10), filter.clone())
+            .await
+            .unwrap();
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].item.metadata["views"], 1);
+
+        let popularity = |item: &VectorItem, score: f32| {
+            score + item.metadata["views"].as_f64().unwrap_or(0.0) as f32 / 1000.0
+        };
+        let results = index
+            .query_items_scored(vec![1.0, 0.0], Some(10), filter, &popularity)
+            .await
+            .unwrap();
+        assert_eq!(results[0].item.metadata["views"], 500);
+    }
+
+    #[tokio::test]
+    async fn test_query_pagination() {
+        let temp_dir = TempDir::new().unwrap();
+        let index = LocalIndex::new(temp_dir.path(), None).unwrap();
+        index.create_index(None).await.unwrap();
+        // Pairs of equal vectors, so pages have to split ties consistently
+        for i in 0..10 {
+            let item = VectorItem {
+                vector: vec![1.0, (i / 2) as f32],
+                ..Default::default()
+            };
+            index.insert_item(item).await.unwrap();
+        }
+        let query = |offset, search_after| Query {
+            vector: Some(vec![1.0, 0.0]),
+            top_k: 3,
+            offset,
+            search_after,
+            ..Default::default()
+        };
+        let ids = |results: &[QueryResult]| results.iter().map(|r| r.item.id).collect::<Vec<_>>();
+        let all = index
+            .execute_query(
+                &Query {
+                    top_k: 10,
+                    ..query(0, None)
+                },
+                None,
+            )
+            .await
+            .unwrap();
+
+        let mut by_offset = Vec::new();
+        let mut by_cursor = Vec::new();
+        let mut cursor = None;
+        for page in 0..4 {
+            let results = index
+                .execute_query(&query(page * 3, None), None)
+                .await
+                .unwrap();
+            by_offset.extend(ids(&results));
+            let results = index.execute_query(&query(0, cursor), None).await.unwrap();
+            cursor = results.last().map(QueryResult::cursor);
+            by_cursor.extend(ids(&results));
+        }
+        assert_eq!(by_offset, ids(&all));
+        assert_eq!(by_cursor, ids(&all));
+
+        // Deleting a result already seen doesn't shift the cursor's next page
+        let cursor = all[2].cursor();
+        index.delete_item(&all[0].item.id).await.unwrap();
+        let next = index
+            .execute_query(&query(0, Some(cursor)), None)
+            .await
+            .unwrap();
+        assert_eq!(ids(&next), ids(&all[3..6]));
+        let shifted = index.execute_query(&query(3, None), None).await.unwrap();
+        assert_eq!(ids(&shifted), ids(&all[4..7]));
+
+        // A top_k beyond the item count returns them all
+        let everything = Query {
+            top_k: 1000,
+            ..query(0, None)
+        };
+        assert_eq!(
+            index.execute_query(&everything, None).await.unwrap().len(),
+            9
+        );
+        // And one beyond max_top_k, counting the offset, is refused
+        let mut config = index.runtime_config();
+        config.max_top_k = 1000;
+        index.set_runtime_config(config).await.unwrap();
+        let beyond = Query {
+            offset: 1,
+            ..everything
+        };
+        assert!(matches!(
+            index.execute_query(&beyond, None).await,
+            Err(VectraError::Query { .. })
+        ));
+        assert!(index.explain_query(&beyond).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_ties_and_listing_ordered_by_id() {
+        let temp_dir = TempDir::new().unwrap();
+        let legacy = vectrust_storage::LegacyStorage::new(temp_dir.path(), "index.json").unwrap();
+        let legacy = LocalIndex::with_storage(
+            temp_dir.path().into(),
+            "index.json".into(),
+            Box::new(legacy),
+        )
+        .unwrap();
+        let optimized = LocalIndex::new(temp_dir.path().join("optimized"), None).unwrap();
+
+        for index in [legacy, optimized] {
+            index.create_index(None).await.unwrap();
+            for _ in 0..8 {
+                let item = VectorItem {
+                    vector: vec![1.0, 1.0],
+                    ..Default::default()
+                };
+                index.insert_item(item).await.unwrap();
+            }
+
+            let listed: Vec<_> = index
+                .list_items(None)
+                .await
+                .unwrap()
+                .iter()
+                .map(|item| item.id)
+                .collect();
+            let mut sorted = listed.clone();
+            sorted.sort();
+            assert_eq!(listed, sorted);
+
+            let results = index
+                .query_items(vec![1.0, 1.0], Some(8), None)
+                .await
+                .unwrap();
+            let ranked: Vec<_> = results.iter().map(|r| r.item.id).collect();
+            assert_eq!(ranked, sorted);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_insert_conflicts() {
+        let temp_dir = TempDir::new().unwrap();
+        let legacy = vectrust_storage::LegacyStorage::new(temp_dir.path(), "index.json").unwrap();
+        let legacy = LocalIndex::with_storage(
+            temp_dir.path().into(),
+            "index.json".into(),
+            Box::new(legacy),
+        )
+        .unwrap();
+        let optimized = LocalIndex::new(temp_dir.path().join("optimized"), None).unwrap();
+
+        for index in [legacy, optimized] {
+            index.create_index(None).await.unwrap();
+            let item = |x: f32, label: &str| VectorItem {
+                vector: vec![x, 1.0],
+                metadata: serde_json::json!({ "label": label }),
+                ..Default::default()
+            };
+            let first = index.insert_item(item(1.0, "first")).await.unwrap();
+            let again = VectorItem {
+                id: first.id,
+                ..item(0.0, "again")
+            };
+            assert!(matches!(
+                index.insert_item(again.clone()).await,
+                Err(VectraError::ItemAlreadyExists { id }) if id == first.id
+            ));
+
+            // A batch with a taken or repeated id writes nothing
+            let fresh = item(2.0, "fresh");
+            let batches = [
+                vec![fresh.clone(), again.clone()],
+                vec![fresh.clone(), fresh.clone()],
+            ];
+            for batch in batches {
+                assert!(matches!(
+                    index.insert_items(batch).await,
+                    Err(VectraError::ItemAlreadyExists { .. })
+                ));
+                assert_eq!(index.list_items(None).await.unwrap().len(), 1);
+            }
+
+            // Replacing overwrites the stored item as an update would
+            let replaced = index
+                .insert_item_on_conflict(again.clone(), OnConflict::Replace)
+                .await
+                .unwrap();
+            assert_eq!(replaced.version, first.version + 1);
+            assert_eq!(replaced.created_at, first.created_at);
+            let stored = index.get_item(&first.id).await.unwrap().unwrap();
+            assert_eq!(stored.vector, vec![0.0, 1.0]);
+            assert_eq!(stored.metadata["label"], "again");
+
+            let last = VectorItem {
+                id: fresh.id,
+                ..item(3.0, "last")
+            };
+            let written = index
+                .insert_items_on_conflict(vec![fresh, again, last], OnConflict::Replace)
+                .await
+                .unwrap();
+            assert_eq!(written.len(), 2);
+            assert_eq!(index.list_items(None).await.unwrap().len(), 2);
+            assert_eq!(index.get_stats().await.unwrap().items, 2);
+            let stored = index.get_item(&written[1].id).await.unwrap().unwrap();
+            assert_eq!(stored.metadata["label"], "last");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_list_items_filter() {
+        let temp_dir = TempDir::new().unwrap();
+        let legacy = vectrust_storage::LegacyStorage::new(temp_dir.path(), "index.json").unwrap();
+        let legacy = LocalIndex::with_storage(
+            temp_dir.path().into(),
+            "index.json".into(),
+            Box::new(legacy),
+        )
+        .unwrap();
+        let optimized = LocalIndex::new(temp_dir.path().join("optimized"), None).unwrap();
+        let config = CreateIndexConfig {
+            namespace_field: Some("tenant".to_string()),
+            ..Default::default()
+        };
+
+        for index in [legacy, optimized] {
+            index.create_index(Some(config.clone())).await.unwrap();
+            let items = (0..9)
+                .map(|i| {
+                    let tenant = ["acme", "globex", "initech"][i % 3];
+                    let source = if i < 4 { "web" } else { "mail" };
+                    VectorItem {
+                        vector: vec![i as f32, 1.0],
+                        metadata: serde_json::json!({ "tenant": tenant, "source": source }),
+                        ..Default::default()
+                    }
+                })
+                .collect();
+            index.insert_items(items).await.unwrap();
+
+            let list = |filter: serde_json::Value, offset, limit| ListOptions {
+                offset,
+                limit,
+                filter: Some(filter),
+            };
+            let web = index
+                .list_items(Some(list(
+                    serde_json::json!({ "source": "web" }),
+                    None,
+                    None,
+                )))
+                .await
+                .unwrap();
+            assert_eq!(web.len(), 4);
+            assert!(web.windows(2).all(|pair| pair[0].id < pair[1].id));
+            assert!(web.iter().all(|item| item.vector.len() == 2));
+
+            // Pages count matching items only
+            let page = index
+                .list_items(Some(list(
+                    serde_json::json!({ "source": "web" }),
+                    Some(1),
+                    Some(2),
+                )))
+                .await
+                .unwrap();
+            assert_eq!(
+                page.iter().map(|item| item.id).collect::<Vec<_>>(),
+                web[1..3].iter().map(|item| item.id).collect::<Vec<_>>()
+            );
+
+            // Pinned to a namespace, with further conditions
+            let filter = serde_json::json!({ "tenant": "acme", "source": { "$eq": "web" } });
+            let acme = index
+                .list_items(Some(list(filter, None, None)))
+                .await
+                .unwrap();
+            assert_eq!(acme.len(), 2);
+            assert!(acme.windows(2).all(|pair| pair[0].id < pair[1].id));
+
+            // Or to the namespaces sharing a prefix
+            let filter = serde_json::json!({ "tenant": { "$startsWith": "gl" } });
+            let globex = index
+                .list_items(Some(list(filter, None, Some(2))))
+                .await
+                .unwrap();
+            assert_eq!(globex.len(), 2);
+            assert!(globex
+                .iter()
+                .all(|item| item.metadata["tenant"] == "globex"));
+            assert!(globex.windows(2).all(|pair| pair[0].id < pair[1].id));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_split_by_filter() {
+        let source_dir = TempDir::new().unwrap();
+        let target_dir = TempDir::new().unwrap();
+        let target_path = target_dir.path().join("acme");
+
+        let index = LocalIndex::new(source_dir.path(), None).unwrap();
+        index.create_index(None).await.unwrap();
+        for (i, tenant) in ["acme", "globex", "acme"].iter().enumerate() {
+            let item = VectorItem {
+                vector: vec![i as f32, 1.0, 0.0],
+                metadata: serde_json::json!({ "tenant": tenant }),
+                ..Default::default()
+            };
+            index.insert_item(item).await.unwrap();
+        }
+
+        let filter = serde_json::json!({ "tenant": "acme" });
+        let moved = index
+            .split_by_filter(&filter, &target_path, SplitMode::Move)
+            .await
+            .unwrap();
+        assert_eq!(moved, 2);
+
+        let remaining = index.list_items(None).await.unwrap();
+        assert_eq!(remaining.len(), 1);
+        assert_eq!(remaining[0].metadata["tenant"], "globex");
+
+        let target = LocalIndex::new(&target_path, None).unwrap();
+        let extracted = target.list_items(None).await.unwrap();
+        assert_eq!(extracted.len(), 2);
+        assert!(extracted.iter().all(|i| i.metadata["tenant"] == "acme"));
+
+        // Splitting into an existing index is refused
+        let again = index
+            .split_by_filter(&filter, &target_path, SplitMode::Copy)
+            .await;
+        assert!(matches!(again, Err(VectraError::IndexAlreadyExists { .. })));
+    }
+
+    #[tokio::test]
+    async fn test_delete_by_filter() {
+        let temp_dir = TempDir::new().unwrap();
+        let index = LocalIndex::new(temp_dir.path(), None).unwrap();
+        index.create_index(None).await.unwrap();
+        for (i, category) in ["fruit", "fruit", "veg"].iter().enumerate() {
+            let item = VectorItem {
+                vector: vec![i as f32, 1.0],
+                metadata: serde_json::json!({ "category": category, "score": i as f64 / 2.0 }),
+                ..Default::default()
+            };
+            index.insert_item(item).await.unwrap();
+        }
+
+        let filter = Filter::field("category")
+            .eq("fruit")
+            .and(Filter::field("score").gt(0.25));
+        let results = index
+            .query_items(vec![1.0, 1.0], Some(5), Some(filter.to_json()))
+            .await
+            .unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(index.delete_by_filter(filter).await.unwrap(), 1);
+        assert!(index.get_item(&results[0].item.id).await.unwrap().is_none());
+
+        let deleted = index
+            .delete_by_filter(serde_json::json!({ "category": "veg" }))
+            .await
+            .unwrap();
+        assert_eq!(deleted, 1);
+        assert_eq!(
+            index
+                .delete_by_filter(Filter::field("x").exists(true))
+                .await
+                .unwrap(),
+            0
+        );
+        let remaining = index.list_items(None).await.unwrap();
+        assert_eq!(remaining.len(), 1);
+        assert_eq!(remaining[0].metadata["score"], 0.0);
+    }
+
+    #[tokio::test]
+    async fn test_deterministic_scoring_is_per_index() {
+        let fast_dir = TempDir::new().unwrap();
+        let exact_dir = TempDir::new().unwrap();
+        let fast = LocalIndex::new(fast_dir.path(), None).unwrap();
+        let exact = LocalIndex::new(exact_dir.path(), None).unwrap();
+        // The dot product cancels to 0 when summed in f32
+        for index in [&fast, &exact] {
+            index.create_index(None).await.unwrap();
+            index
+                .insert_item(VectorItem {
+                    vector: vec![1e8, 1.0, -1e8],
+                    ..Default::default()
+                })
+                .await
+                .unwrap();
+        }
+        let mut config = exact.runtime_config();
+        config.deterministic_scoring = true;
+        exact.set_runtime_config(config).await.unwrap();
+
+        let query = vec![1.0, 1.0, 1.0];
+        let fast_results = fast
+            .query_items(query.clone(), Some(1), None)
+            .await
+            .unwrap();
+        let exact_results = exact.query_items(query, Some(1), None).await.unwrap();
+        assert_eq!(fast_results[0].score, 0.0);
+        assert!(exact_results[0].score > 0.0);
+    }
+
+    #[tokio::test]
+    async fn test_strict_filters() {
+        let temp_dir = TempDir::new().unwrap();
+        let index = LocalIndex::new(temp_dir.path(), None).unwrap();
+        index.create_index(None).await.unwrap();
+        let item = VectorItem {
+            vector: vec![1.0, 1.0],
+            metadata: serde_json::json!({ "year": 2024, "title": "rust" }),
+            ..Default::default()
+        };
+        index.insert_item(item).await.unwrap();
+        index
+            .enable_text_index(TextIndexConfig {
+                fields: vec!["title".to_string()],
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+
+        // A string compared with a number matches nothing...
+        let mistyped = serde_json::json!({ "year": { "$gte": "2024" } });
+        let results = index
+            .query_items(vec![1.0, 1.0], Some(5), Some(mistyped.clone()))
+            .await
+            .unwrap();
+        assert!(results.is_empty());
+
+        // ...or fails once filters are strict, deleting nothing
+        let mut config = index.runtime_config();
+        config.strict_filters = true;
+        index.set_runtime_config(config).await.unwrap();
+        assert!(matches!(
+            index
+                .query_items(vec![1.0, 1.0], Some(5), Some(mistyped.clone()))
+                .await,
+            Err(VectraError::Query { .. })
+        ));
+        let mistyped_delete = serde_json::json!({ "year": { "$ne": "2024" } });
+        assert!(index.delete_by_filter(mistyped_delete).await.is_err());
+        let listing = ListOptions {
+            limit: None,
+            offset: None,
+            filter: Some(mistyped),
+        };
+        assert!(index.list_items(Some(listing)).await.is_err());
+        let mistyped = serde_json::json!({ "year": { "$gte": "2024" } });
+        assert!(matches!(
+            index.text_search("rust", Some(5), Some(mistyped)).await,
+            Err(VectraError::Query { .. })
+        ));
+        assert_eq!(index.get_stats().await.unwrap().items, 1);
+    }
+
+    #[cfg(feature = "rocksdb")]
+    #[tokio::test]
+    async fn test_update_items() {
+        let temp_dir = TempDir::new().unwrap();
+        let index = LocalIndex::new(temp_dir.path(), None).unwrap();
+        index.create_index(None).await.unwrap();
+        let items: Vec<VectorItem> = (0..3)
+            .map(|i| VectorItem {
+                vector: vec![i as f32, 1.0],
+                metadata: serde_json::json!({ "n": i }),
+                ..Default::default()
+            })
+            .collect();
+        let items = index.insert_items(items).await.unwrap();
+        let retag = |id, tag: &str| UpdateRequest {
+            id,
+            vector: None,
+            metadata: Some(serde_json::json!({ "tag": tag })),
+        };
+
+        let results = index
+            .update_items(vec![
+                retag(items[0].id, "a"),
+                retag(items[1].id, "b"),
+                UpdateRequest {
+                    id: items[0].id,
+                    vector: Some(vec![5.0, 5.0]),
+                    metadata: None,
+                },
+            ])
+            .await
+            .unwrap();
+        let ids: Vec<_> = results.iter().map(|r| r.id).collect();
+        assert_eq!(ids, vec![items[0].id, items[1].id]);
+        assert!(results.iter().all(|r| r.version == 2));
+
+        let first = index.get_item(&items[0].id).await.unwrap().unwrap();
+        assert_eq!(first.vector, vec![5.0, 5.0]);
+        assert_eq!(first.metadata, serde_json::json!({ "n": 0, "tag": "a" }));
+        assert_eq!(index.get_stats().await.unwrap().items, 3);
+        assert_eq!(index.compaction_stats().await.unwrap().live_items, 3);
+
+        // A missing item fails the whole batch
+        let err = index
+            .update_items(vec![
+                retag(items[2].id, "c"),
+                retag(uuid::Uuid::new_v4(), "d"),
+            ])
+            .await
+            .unwrap_err();
+        assert!(matches!(err, VectraError::ItemNotFound));
+        let third = index.get_item(&items[2].id).await.unwrap().unwrap();
+        assert_eq!(third.version, 1);
+        assert!(third.metadata.get("tag").is_none());
+
+        // Metadata-only updates leave the vector file alone
+        let before = index.compaction_stats().await.unwrap().reclaimable_bytes;
+        index
+            .update_items(vec![retag(items[2].id, "c")])
             .await
             .unwrap();
+        let after = index.compaction_stats().await.unwrap().reclaimable_bytes;
+        assert_eq!(after, before);
+        let third = index.get_item(&items[2].id).await.unwrap().unwrap();
+        assert_eq!(third.vector, items[2].vector);
+        assert_eq!(third.metadata["tag"], "c");
+    }
+
+    #[tokio::test]
+    async fn test_named_queries() {
+        let temp_dir = TempDir::new().unwrap();
+        let index = LocalIndex::new(temp_dir.path(), None).unwrap();
+        index.create_index(None).await.unwrap();
+
+        for (vector, tenant) in [
+            (vec![1.0, 0.0], "acme"),
+            (vec![0.9, 0.1], "globex"),
+            (vec![0.0, 1.0], "acme"),
+        ] {
+            let item = VectorItem {
+                vector,
+                metadata: serde_json::json!({ "tenant": tenant }),
+                ..Default::default()
+            };
+            index.insert_item(item).await.unwrap();
+        }
+
+        let saved = NamedQuery {
+            filter: Some(serde_json::json!({ "tenant": "acme" })),
+            top_k: Some(5),
+            ..Default::default()
+        };
+        index.save_named_query("acme", saved).unwrap();
 
+        // Saved queries survive reopening and take a vector at run time
+        drop(index);
+        let reopened = LocalIndex::new(temp_dir.path(), None).unwrap();
+        assert_eq!(
+            reopened.named_queries().keys().collect::<Vec<_>>(),
+            vec!["acme"]
+        );
+        let results = reopened
+            .run_named_query("acme", Some(vec![1.0, 0.0]), None, None)
+            .await
+            .unwrap();
         assert_eq!(results.len(), 2);
-        // First result should be more similar to item1
-        assert_eq!(results[0].item.id, item1.id);
-        assert!(results[0].score > results[1].score);
+        assert!(results.iter().all(|r| r.item.metadata["tenant"] == "acme"));
+
+        assert!(reopened
+            .run_named_query("acme", None, None, None)
+            .await
+            .is_err());
+        assert!(reopened
+            .run_named_query("missing", Some(vec![1.0, 0.0]), None, None)
+            .await
+            .is_err());
+
+        assert!(reopened.delete_named_query("acme").unwrap());
+        assert!(!reopened.delete_named_query("acme").unwrap());
+        assert!(!temp_dir.path().join(NAMED_QUERIES_FILE).exists());
+    }
+
+    #[tokio::test]
+    async fn test_index_metadata() {
+        let temp_dir = TempDir::new().unwrap();
+        let index = LocalIndex::new(temp_dir.path(), None).unwrap();
+        index.create_index(None).await.unwrap();
+        assert!(index.get_index_metadata().is_empty());
+
+        let owner = serde_json::json!("search-team");
+        assert_eq!(
+            index.set_index_metadata("owner", owner.clone()).unwrap(),
+            None
+        );
+        index
+            .set_index_metadata("build", serde_json::json!({ "hnsw_m": 16 }))
+            .unwrap();
+        assert!(index.set_index_metadata("", owner.clone()).is_err());
+
+        // Properties travel with the index folder
+        drop(index);
+        let index = LocalIndex::new(temp_dir.path(), None).unwrap();
+        let properties = index.get_index_metadata();
+        assert_eq!(properties["owner"], owner);
+        assert_eq!(properties["build"]["hnsw_m"], 16);
+
+        assert_eq!(index.remove_index_metadata("owner").unwrap(), Some(owner));
+        assert_eq!(index.remove_index_metadata("owner").unwrap(), None);
+        index.delete_index().await.unwrap();
+        assert!(!temp_dir.path().join(INDEX_METADATA_FILE).exists());
+    }
+
+    #[tokio::test]
+    async fn test_text_fields_from_metadata_config() {
+        let temp_dir = TempDir::new().unwrap();
+        let index = LocalIndex::new(temp_dir.path(), None).unwrap();
+
+        let mut config = CreateIndexConfig::default();
+        config.metadata_config.text_fields = vec![TextFieldConfig::new(
+            "body",
+            TokenizerConfig::for_language(TextLanguage::English),
+        )];
+        index.create_index(Some(config)).await.unwrap();
+
+        let item = VectorItem {
+            vector: vec![1.0, 0.0],
+            metadata: serde_json::json!({ "body": "Indexing the documents", "tag": "draft" }),
+            ..Default::default()
+        };
+        let id = index.insert_item(item).await.unwrap().id;
+
+        let hits = index.text_search("document", None, None).await.unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].item.id, id);
+        assert!(index
+            .text_search("draft", None, None)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[cfg(feature = "rocksdb")]
+    #[tokio::test]
+    async fn test_text_index_search_and_hybrid_fusion() {
+        let temp_dir = TempDir::new().unwrap();
+        let index = LocalIndex::new(temp_dir.path(), None).unwrap();
+        index.create_index(None).await.unwrap();
+
+        let mut ids = Vec::new();
+        for (vector, title) in [
+            (vec![1.0, 0.0], "cooking pasta at home"),
+            (vec![0.9, 0.1], "rust vector database internals"),
+            (vec![0.0, 1.0], "a database of recipes"),
+        ] {
+            let item = VectorItem {
+                vector,
+                metadata: serde_json::json!({ "title": title }),
+                ..Default::default()
+            };
+            ids.push(index.insert_item(item).await.unwrap().id);
+        }
+
+        assert!(index.text_search("rust", None, None).await.is_err());
+        index
+            .enable_text_index(TextIndexConfig {
+                fields: vec!["title".to_string()],
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+
+        let hits = index
+            .text_search("database -recipes", None, None)
+            .await
+            .unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].item.id, ids[1]);
+
+        // The keyword match outranks the closer vector
+        let results = index
+            .query_items_extended(
+                vec![1.0, 0.0],
+                Some("\"vector database\"".to_string()),
+                Some(2),
+                None,
+            )
+            .await
+            .unwrap();
+        assert_eq!(results[0].item.id, ids[1]);
+
+        // Matched terms come back with character offsets into the field
+        let spans: Vec<(&str, usize, usize)> = results[0]
+            .highlights
+            .iter()
+            .map(|h| (h.term.as_str(), h.start, h.end))
+            .collect();
+        assert_eq!(spans, vec![("vector", 5, 11), ("database", 12, 20)]);
+        assert!(results[1].highlights.is_empty());
+
+        // Items written after enabling are indexed too
+        let item = VectorItem {
+            vector: vec![0.5, 0.5],
+            metadata: serde_json::json!({ "title": "rust cookbook" }),
+            ..Default::default()
+        };
+        index.insert_item(item).await.unwrap();
+        index.delete_item(&ids[1]).await.unwrap();
+        let hits = index.text_search("rust", None, None).await.unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].item.metadata["title"], "rust cookbook");
+
+        // The persisted index is reattached on open
+        drop(index);
+        let reopened = LocalIndex::new(temp_dir.path(), None).unwrap();
+        let hits = reopened.text_search("cookbook", None, None).await.unwrap();
+        assert_eq!(hits.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_geo_filtered_query() {
+        let temp_dir = TempDir::new().unwrap();
+        let index = LocalIndex::new(temp_dir.path(), None).unwrap();
+        index.create_index(None).await.unwrap();
+
+        let mut ids = Vec::new();
+        for (lat, lon, vector) in [
+            (52.52, 13.405, vec![0.8, 0.6]),
+            (52.39, 13.06, vec![0.6, 0.8]),
+            (48.85, 2.35, vec![1.0, 0.0]),
+        ] {
+            let item = VectorItem {
+                vector,
+                metadata: serde_json::json!({ "loc": { "lat": lat, "lon": lon } }),
+                ..Default::default()
+            };
+            ids.push(index.insert_item(item).await.unwrap().id);
+        }
+        let (berlin, potsdam) = (ids[0], ids[1]);
+
+        let filter = serde_json::json!({
+            "loc": { "$near": { "lat": 52.5, "lon": 13.4, "radiusMeters": 50_000 } }
+        });
+        let scanned = index
+            .query_items(vec![1.0, 0.0], Some(10), Some(filter.clone()))
+            .await
+            .unwrap();
+
+        index.enable_geo_index("loc", None).await.unwrap();
+        let indexed = index
+            .query_items(vec![1.0, 0.0], Some(10), Some(filter.clone()))
+            .await
+            .unwrap();
+        assert_eq!(indexed.len(), 2);
+        let ids = |results: &[QueryResult]| results.iter().map(|r| r.item.id).collect::<Vec<_>>();
+        assert_eq!(ids(&indexed), ids(&scanned));
+        assert_eq!(ids(&indexed), vec![berlin, potsdam]);
+
+        // Writes after enabling keep the index in sync
+        index.delete_item(&berlin).await.unwrap();
+        let within = serde_json::json!({
+            "$and": [{ "loc": { "$within": {
+                "minLat": 52.0, "minLon": 12.5, "maxLat": 53.0, "maxLon": 14.0
+            } } }]
+        });
+        let results = index
+            .query_items(vec![1.0, 0.0], Some(10), Some(within))
+            .await
+            .unwrap();
+        assert_eq!(ids(&results), vec![potsdam]);
     }
 
     #[test]
@@ -0,0 +1,579 @@
+// Copyright 2024-2026 Andrey Vasilevsky <anvanster@gmail.com>
+// SPDX-License-Identifier: Apache-2.0
+
+//! Scheduled maintenance for long-running embedders.
+//!
+//! An index opened with [`LocalIndex::open_managed`], or one handed to
+//! [`LocalIndex::start_maintenance`], runs its [`ScheduledJob`]s from a
+//! background tokio task: flushing, compaction, snapshots, purging expired
+//! items and refreshing neighbour lists. Jobs run one at a time, each at
+//! the next time its [`Schedule`] fires after the previous run finished,
+//! so a slow job delays the rest instead of piling up. Failures are
+//! reported through `tracing` under the `vectrust::maintenance` target and
+//! the job runs again at its next time.
+
+use crate::LocalIndex;
+use chrono::{DateTime, Datelike, NaiveDate, NaiveDateTime, Timelike, Utc};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+use vectrust_core::*;
+
+/// Target of the `tracing` events, for routing them separately
+const TRACING_TARGET: &str = "vectrust::maintenance";
+
+/// Name format of snapshot directories; sorts by time
+const SNAPSHOT_FORMAT: &str = "%Y%m%dT%H%M%S%.3fZ";
+
+/// How far ahead a cron schedule is searched for its next time
+const CRON_HORIZON_DAYS: i64 = 5 * 366;
+
+/// Work the scheduler can run
+#[derive(Debug, Clone, PartialEq)]
+pub enum MaintenanceJob {
+    /// Write buffered bookkeeping to disk, as [`LocalIndex::end_update`]
+    Flush,
+    /// Compact storage if anything is reclaimable
+    Compact { max_bytes_per_sec: Option<u64> },
+    /// Copy the index into a new timestamped directory under `dir`,
+    /// keeping the newest `keep` snapshots there (all when 0)
+    Snapshot { dir: PathBuf, keep: usize },
+    /// Delete items whose metadata `field` holds a time that has passed,
+    /// as an RFC 3339 string or Unix seconds
+    PurgeExpired { field: String },
+    /// Recompute stale neighbour lists; skipped if they were never built
+    RefreshNeighbors,
+    /// Sort vectors between the hot and cold tiers, as [`LocalIndex::retier`]
+    Retier { policy: TieringPolicy },
+}
+
+/// A job and when to run it
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScheduledJob {
+    pub schedule: Schedule,
+    pub job: MaintenanceJob,
+}
+
+impl ScheduledJob {
+    pub fn new(schedule: Schedule, job: MaintenanceJob) -> Self {
+        Self { schedule, job }
+    }
+}
+
+/// When a job runs. Parsed from a five-field cron expression
+/// (`"*/15 * * * *"`, in UTC), a shorthand such as `"@daily"`, or a fixed
+/// interval such as `"@every 30s"`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Schedule {
+    /// Repeatedly, this long after the previous run
+    Every(Duration),
+    Cron(CronSchedule),
+}
+
+impl Schedule {
+    /// First time the schedule fires after `after`; `None` if it never does
+    pub fn next_after(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        match self {
+            Schedule::Every(interval) => Some(after + chrono::Duration::from_std(*interval).ok()?),
+            Schedule::Cron(cron) => cron.next_after(after),
+        }
+    }
+}
+
+impl FromStr for Schedule {
+    type Err = VectraError;
+
+    fn from_str(spec: &str) -> Result<Self> {
+        let spec = spec.trim();
+        let cron = match spec {
+            "@hourly" => "0 * * * *",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@weekly" => "0 0 * * 0",
+            "@monthly" => "0 0 1 * *",
+            _ => match spec.strip_prefix("@every ") {
+                Some(interval) => return parse_interval(interval.trim()).map(Schedule::Every),
+                None => spec,
+            },
+        };
+        CronSchedule::parse(cron).map(Schedule::Cron)
+    }
+}
+
+fn parse_interval(interval: &str) -> Result<Duration> {
+    let invalid = || invalid_schedule(interval, "expected an interval such as 30s, 5m or 1h");
+    let unit_at = interval
+        .find(|c: char| !c.is_ascii_digit())
+        .ok_or_else(invalid)?;
+    let count: u64 = interval[..unit_at].parse().map_err(|_| invalid())?;
+    let secs = match &interval[unit_at..] {
+        "s" => count,
+        "m" => count * 60,
+        "h" => count * 3600,
+        "d" => count * 86_400,
+        _ => return Err(invalid()),
+    };
+    if secs == 0 {
+        return Err(invalid());
+    }
+    Ok(Duration::from_secs(secs))
+}
+
+/// Minute, hour, day of month, month and day of week, each a set of
+/// allowed values
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CronSchedule {
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    /// Cron matches either day field when both are restricted
+    days_restricted: bool,
+    weekdays_restricted: bool,
+}
+
+impl CronSchedule {
+    /// Parse `minute hour day-of-month month day-of-week`, where each field
+    /// is `*` or a comma-separated list of values, `a-b` ranges and `/step`s.
+    /// Day of week runs from 0 (Sunday) to 6, with 7 also Sunday.
+    pub fn parse(spec: &str) -> Result<Self> {
+        let fields: Vec<&str> = spec.split_whitespace().collect();
+        let [minute, hour, day, month, weekday] = fields[..] else {
+            return Err(invalid_schedule(spec, "expected five fields"));
+        };
+        let mut weekdays = parse_field(weekday, 0, 7)?;
+        if weekdays & (1 << 7) != 0 {
+            weekdays = (weekdays & !(1 << 7)) | 1;
+        }
+        Ok(Self {
+            minutes: parse_field(minute, 0, 59)?,
+            hours: parse_field(hour, 0, 23)?,
+            days: parse_field(day, 1, 31)?,
+            months: parse_field(month, 1, 12)?,
+            weekdays,
+            days_restricted: !day.starts_with('*'),
+            weekdays_restricted: !weekday.starts_with('*'),
+        })
+    }
+
+    pub fn next_after(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let start = after.naive_utc().with_second(0)?.with_nanosecond(0)?;
+        let mut t = start + chrono::Duration::minutes(1);
+        let horizon = start + chrono::Duration::days(CRON_HORIZON_DAYS);
+        while t < horizon {
+            if !allows(self.months, t.month()) {
+                let (year, month) = if t.month() == 12 {
+                    (t.year() + 1, 1)
+                } else {
+                    (t.year(), t.month() + 1)
+                };
+                t = NaiveDate::from_ymd_opt(year, month, 1)?.and_hms_opt(0, 0, 0)?;
+            } else if !self.day_matches(&t) {
+                t = (t.date() + chrono::Duration::days(1)).and_hms_opt(0, 0, 0)?;
+            } else if !allows(self.hours, t.hour()) {
+                t = t.with_minute(0)? + chrono::Duration::hours(1);
+            } else if !allows(self.minutes, t.minute()) {
+                t += chrono::Duration::minutes(1);
+            } else {
+                return Some(t.and_utc());
+            }
+        }
+        None
+    }
+
+    fn day_matches(&self, t: &NaiveDateTime) -> bool {
+        let day = allows(self.days, t.day());
+        let weekday = allows(self.weekdays, t.weekday().num_days_from_sunday());
+        if self.days_restricted && self.weekdays_restricted {
+            day || weekday
+        } else {
+            day && weekday
+        }
+    }
+}
+
+fn allows(set: u64, value: u32) -> bool {
+    set & (1 << value) != 0
+}
+
+/// Bit set of the values `field` allows within `min..=max`
+fn parse_field(field: &str, min: u32, max: u32) -> Result<u64> {
+    let invalid = || invalid_schedule(field, &format!("expected values from {} to {}", min, max));
+    let mut set = 0u64;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (range, step.parse::<u32>().map_err(|_| invalid())?),
+            None => (part, 1),
+        };
+        let (low, high) = if range == "*" {
+            (min, max)
+        } else if let Some((low, high)) = range.split_once('-') {
+            (
+                low.parse().map_err(|_| invalid())?,
+                high.parse().map_err(|_| invalid())?,
+            )
+        } else {
+            let value: u32 = range.parse().map_err(|_| invalid())?;
+            // `5/10` means every tenth value from 5
+            (value, if part.contains('/') { max } else { value })
+        };
+        if step == 0 || low < min || high > max || low > high {
+            return Err(invalid());
+        }
+        for value in (low..=high).step_by(step as usize) {
+            set |= 1 << value;
+        }
+    }
+    Ok(set)
+}
+
+fn invalid_schedule(spec: &str, reason: &str) -> VectraError {
+    VectraError::Storage {
+        message: format!("Invalid maintenance schedule '{}': {}", spec, reason),
+    }
+}
+
+/// Time an item's expiry field holds
+fn expiry(value: &serde_json::Value) -> Option<DateTime<Utc>> {
+    match value {
+        serde_json::Value::String(s) => DateTime::parse_from_rfc3339(s)
+            .ok()
+            .map(|t| t.with_timezone(&Utc)),
+        serde_json::Value::Number(n) => {
+            DateTime::from_timestamp_millis((n.as_f64()? * 1000.0) as i64)
+        }
+        _ => None,
+    }
+}
+
+impl LocalIndex {
+    /// Open the index at `folder_path` in managed mode, running `jobs` in
+    /// the background for as long as it stays open. Must be called within
+    /// a tokio runtime.
+    pub fn open_managed<P: AsRef<Path>>(
+        folder_path: P,
+        index_name: Option<String>,
+        jobs: Vec<ScheduledJob>,
+    ) -> Result<Arc<Self>> {
+        let index = Arc::new(Self::new(folder_path, index_name)?);
+        index.start_maintenance(jobs)?;
+        Ok(index)
+    }
+
+    /// Run `jobs` on their schedules until the index is dropped or
+    /// [`stop_maintenance`](Self::stop_maintenance) is called, replacing
+    /// any jobs started before. Fails if a schedule never fires. Must be
+    /// called within a tokio runtime.
+    pub fn start_maintenance(self: &Arc<Self>, jobs: Vec<ScheduledJob>) -> Result<()> {
+        let now = Utc::now();
+        let mut due = Vec::with_capacity(jobs.len());
+        for job in &jobs {
+            due.push(
+                job.schedule
+                    .next_after(now)
+                    .ok_or_else(|| VectraError::Storage {
+                        message: format!("Maintenance schedule {:?} never fires", job.schedule),
+                    })?,
+            );
+        }
+
+        let index = Arc::downgrade(self);
+        let task = tokio::spawn(async move {
+            // Jobs whose schedule has run out drop out with `None`
+            let mut due: Vec<Option<DateTime<Utc>>> = due.into_iter().map(Some).collect();
+            while let Some((next, at)) = due
+                .iter()
+                .enumerate()
+                .filter_map(|(i, at)| Some((i, (*at)?)))
+                .min_by_key(|&(_, at)| at)
+            {
+                let wait = (at - Utc::now()).to_std().unwrap_or_default();
+                tokio::time::sleep(wait).await;
+
+                // Stop once the index has been dropped
+                let Some(index) = index.upgrade() else {
+                    break;
+                };
+                let job = &jobs[next].job;
+                match index.run_maintenance(job).await {
+                    Ok(summary) => tracing::info!(target: TRACING_TARGET, ?job, "{}", summary),
+                    Err(e) => tracing::warn!(target: TRACING_TARGET, ?job, "failed: {}", e),
+                }
+                drop(index);
+                due[next] = jobs[next].schedule.next_after(Utc::now());
+            }
+        });
+
+        if let Some(previous) = self.maintenance_task.lock().unwrap().replace(task) {
+            previous.abort();
+        }
+        Ok(())
+    }
+
+    /// Stop scheduled maintenance if it is running. A job already under
+    /// way is abandoned at its next await point.
+    pub fn stop_maintenance(&self) {
+        if let Some(task) = self.maintenance_task.lock().unwrap().take() {
+            task.abort();
+        }
+    }
+
+    /// Run one maintenance job now, returning a short description of what
+    /// it did
+    #[tracing::instrument(name = "vectrust.maintenance", skip_all, fields(index = %self.path.display(), ?job))]
+    pub async fn run_maintenance(&self, job: &MaintenanceJob) -> Result<String> {
+        match job {
+            MaintenanceJob::Flush => {
+                self.end_update().await?;
+                Ok("flushed".to_string())
+            }
+            MaintenanceJob::Compact { max_bytes_per_sec } => {
+                if self.compaction_stats().await?.reclaimable_bytes == 0 {
+                    return Ok("nothing to compact".to_string());
+                }
+                let before = self.compact(*max_bytes_per_sec).await?;
+                Ok(format!("reclaimed {} bytes", before.reclaimable_bytes))
+            }
+            MaintenanceJob::Snapshot { dir, keep } => {
+                let snapshot = self.snapshot(dir).await?;
+                prune_snapshots(dir, *keep)?;
+                Ok(format!("snapshot at {}", snapshot.display()))
+            }
+            MaintenanceJob::PurgeExpired { field } => {
+                let purged = self.purge_expired(field).await?;
+                Ok(format!("purged {} expired items", purged))
+            }
+            MaintenanceJob::RefreshNeighbors => {
+                if self.neighbors.lock().unwrap().is_none() {
+                    return Ok("no neighbour lists to refresh".to_string());
+                }
+                let refreshed = self.refresh_neighbors().await?;
+                Ok(format!("refreshed {} neighbour lists", refreshed))
+            }
+            MaintenanceJob::Retier { policy } => {
+                let stats = self.retier(policy).await?;
+                Ok(format!(
+                    "demoted {} and promoted {} vectors",
+                    stats.demoted, stats.promoted
+                ))
+            }
+        }
+    }
+
+    /// Flush the index and copy its folder into a new directory under
+    /// `dir` named after the current time, returning that directory.
+    /// Writes wait until the copy is done; queries don't. Fails with
+    /// [`VectraError::DiskSpace`] unless there is room for the copy.
+    pub async fn snapshot<P: AsRef<Path>>(&self, dir: P) -> Result<PathBuf> {
+        let dir = dir.as_ref();
+        let mut storage = self.storage.write_for("snapshot").await;
+        storage.commit_transaction().await?;
+        let storage = storage.downgrade();
+        self.ensure_disk_space(crate::disk_usage(&self.path)?)?;
+
+        std::fs::create_dir_all(dir)?;
+        let target = dir.join(Utc::now().format(SNAPSHOT_FORMAT).to_string());
+        // Snapshots kept inside the index folder aren't copied into each other
+        let skip = std::fs::canonicalize(dir)?;
+        copy_dir(&self.path, &target, &skip)?;
+        self.copy_attachments(storage.as_ref(), &target).await?;
+        Ok(target)
+    }
+
+    /// Delete the items whose `field` holds a time that has passed,
+    /// returning how many were deleted
+    pub async fn purge_expired(&self, field: &str) -> Result<usize> {
+        let now = Utc::now();
+        let expired: Vec<uuid::Uuid> = self
+            .storage
+            .read()
+            .await
+            .list_items(None)
+            .await?
+            .into_iter()
+            .filter(|item| {
+                item.metadata
+                    .get(field)
+                    .and_then(expiry)
+                    .is_some_and(|at| at <= now)
+            })
+            .map(|item| item.id)
+            .collect();
+        for id in &expired {
+            self.delete_item(id).await?;
+        }
+        Ok(expired.len())
+    }
+}
+
+fn copy_dir(from: &Path, to: &Path, skip: &Path) -> std::io::Result<()> {
+    std::fs::create_dir_all(to)?;
+    for entry in std::fs::read_dir(from)? {
+        let entry = entry?;
+        let path = entry.path();
+        if entry.file_type()?.is_dir() {
+            if std::fs::canonicalize(&path)? != skip {
+                copy_dir(&path, &to.join(entry.file_name()), skip)?;
+            }
+        } else {
+            std::fs::copy(&path, to.join(entry.file_name()))?;
+        }
+    }
+    Ok(())
+}
+
+/// Remove all but the newest `keep` snapshots in `dir`
+fn prune_snapshots(dir: &Path, keep: usize) -> Result<()> {
+    if keep == 0 {
+        return Ok(());
+    }
+    let mut snapshots: Vec<PathBuf> = std::fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| {
+            let name = entry.file_name();
+            name.to_str()
+                .is_some_and(|name| NaiveDateTime::parse_from_str(name, SNAPSHOT_FORMAT).is_ok())
+        })
+        .map(|entry| entry.path())
+        .collect();
+    snapshots.sort();
+    let excess = snapshots.len().saturating_sub(keep);
+    for old in &snapshots[..excess] {
+        std::fs::remove_dir_all(old)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn at(spec: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(spec)
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    fn next(schedule: &str, after: &str) -> Option<DateTime<Utc>> {
+        schedule.parse::<Schedule>().unwrap().next_after(at(after))
+    }
+
+    #[test]
+    fn test_schedule_next_after() {
+        let from = "2026-03-14T10:07:30Z";
+        assert_eq!(next("*/15 * * * *", from), Some(at("2026-03-14T10:15:00Z")));
+        assert_eq!(next("@daily", from), Some(at("2026-03-15T00:00:00Z")));
+        assert_eq!(next("30 2 1 * *", from), Some(at("2026-04-01T02:30:00Z")));
+        // 2026-03-16 is a Monday; both day fields restricted matches either
+        assert_eq!(next("0 9 * * 1-5", from), Some(at("2026-03-16T09:00:00Z")));
+        assert_eq!(next("0 9 20 * 7", from), Some(at("2026-03-15T09:00:00Z")));
+        assert_eq!(next("5/20 8 * * *", from), Some(at("2026-03-15T08:05:00Z")));
+        assert_eq!(next("@every 90s", from), Some(at("2026-03-14T10:09:00Z")));
+        assert_eq!(next("0 0 30 2 *", from), None);
+
+        for invalid in [
+            "* * * *",
+            "60 * * * *",
+            "*/0 * * * *",
+            "@every 0s",
+            "@every 5x",
+        ] {
+            assert!(invalid.parse::<Schedule>().is_err(), "{}", invalid);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_maintenance_jobs() {
+        let temp_dir = TempDir::new().unwrap();
+        let index = LocalIndex::new(temp_dir.path().join("index"), None).unwrap();
+        index.create_index(None).await.unwrap();
+        let expiries = [
+            serde_json::json!("2000-01-01T00:00:00Z"),
+            serde_json::json!(946_684_800),
+            serde_json::json!("2999-01-01T00:00:00Z"),
+            serde_json::json!("not a time"),
+        ];
+        for (i, expires) in expiries.into_iter().enumerate() {
+            index
+                .insert_item(VectorItem {
+                    vector: vec![i as f32, 1.0],
+                    metadata: serde_json::json!({ "expires": expires }),
+                    ..Default::default()
+                })
+                .await
+                .unwrap();
+        }
+
+        let purge = MaintenanceJob::PurgeExpired {
+            field: "expires".to_string(),
+        };
+        assert_eq!(
+            index.run_maintenance(&purge).await.unwrap(),
+            "purged 2 expired items"
+        );
+        assert_eq!(index.get_stats().await.unwrap().items, 2);
+        index.run_maintenance(&MaintenanceJob::Flush).await.unwrap();
+        index
+            .run_maintenance(&MaintenanceJob::RefreshNeighbors)
+            .await
+            .unwrap();
+
+        let snapshots = temp_dir.path().join("index").join("snapshots");
+        let snapshot = MaintenanceJob::Snapshot {
+            dir: snapshots.clone(),
+            keep: 2,
+        };
+        for _ in 0..3 {
+            index.run_maintenance(&snapshot).await.unwrap();
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        let kept: Vec<PathBuf> = std::fs::read_dir(&snapshots)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect();
+        assert_eq!(kept.len(), 2);
+        // Snapshots open as indexes in their own right and don't nest
+        assert!(!kept[0].join("snapshots").exists());
+        let copy = LocalIndex::new(&kept[0], None).unwrap();
+        assert_eq!(copy.get_stats().await.unwrap().items, 2);
+    }
+
+    #[tokio::test]
+    async fn test_managed_index_runs_jobs() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("index");
+        let index = LocalIndex::new(&path, None).unwrap();
+        index.create_index(None).await.unwrap();
+        index
+            .insert_item(VectorItem {
+                vector: vec![1.0, 0.0],
+                metadata: serde_json::json!({ "expires": 0 }),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        drop(index);
+
+        let jobs = vec![ScheduledJob::new(
+            "@every 1s".parse().unwrap(),
+            MaintenanceJob::PurgeExpired {
+                field: "expires".to_string(),
+            },
+        )];
+        let index = LocalIndex::open_managed(&path, None, jobs).unwrap();
+        for _ in 0..50 {
+            if index.get_stats().await.unwrap().items == 0 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        assert_eq!(index.get_stats().await.unwrap().items, 0);
+
+        let never = ScheduledJob::new("0 0 31 2 *".parse().unwrap(), MaintenanceJob::Flush);
+        assert!(index.start_maintenance(vec![never]).is_err());
+        index.stop_maintenance();
+        assert!(index.maintenance_task.lock().unwrap().is_none());
+    }
+}
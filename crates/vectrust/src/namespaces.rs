@@ -0,0 +1,445 @@
+// Copyright 2024-2026 Andrey Vasilevsky <anvanster@gmail.com>
+// SPDX-License-Identifier: Apache-2.0
+
+//! Per-namespace statistics and quotas.
+//!
+//! An index created with a `namespace_field` groups its items by that
+//! metadata field's value, such as a tenant or collection name.
+//! [`LocalIndex::get_stats`] then breaks item counts, approximate stored
+//! bytes and query counts down by namespace, and
+//! [`IndexLimits::namespaces`] caps each namespace's share.
+//!
+//! Usage is tallied from a scan on first use and kept current by this
+//! handle's writes. Query counts cover this handle since it was opened.
+//!
+//! [`LocalIndex::list_namespace`] and [`LocalIndex::delete_namespace`]
+//! read just the namespace's items where the storage files them under it,
+//! as the RocksDB backend does, instead of filtering every item. Listings
+//! filtered by a `$startsWith` on the namespace field read each matching
+//! namespace the same way.
+
+use crate::LocalIndex;
+use std::collections::{BTreeMap, HashMap};
+use uuid::Uuid;
+use vectrust_core::*;
+
+/// Namespace tallies for one index handle
+#[derive(Debug, Default)]
+pub(crate) struct NamespaceTracker {
+    /// Built on first use
+    usage: Option<NamespaceUsage>,
+    /// Queries filtered to each namespace
+    queries: HashMap<String, u64>,
+}
+
+#[derive(Debug)]
+struct NamespaceUsage {
+    field: String,
+    /// Namespace and approximate size of every item that has one
+    items: HashMap<Uuid, (String, u64)>,
+    /// Item count and bytes per namespace
+    totals: HashMap<String, (usize, u64)>,
+}
+
+impl NamespaceUsage {
+    fn new(field: &str, items: &[VectorItem]) -> Self {
+        let mut usage = Self {
+            field: field.to_string(),
+            items: HashMap::new(),
+            totals: HashMap::new(),
+        };
+        usage.record(items);
+        usage
+    }
+
+    fn record(&mut self, items: &[VectorItem]) {
+        for item in items {
+            self.remove(&item.id);
+            if let Some(namespace) = namespace_of(&self.field, item) {
+                let size = crate::approximate_size(item);
+                let total = self.totals.entry(namespace.clone()).or_default();
+                total.0 += 1;
+                total.1 += size;
+                self.items.insert(item.id, (namespace, size));
+            }
+        }
+    }
+
+    fn remove(&mut self, id: &Uuid) {
+        if let Some((namespace, size)) = self.items.remove(id) {
+            if let Some(total) = self.totals.get_mut(&namespace) {
+                total.0 -= 1;
+                total.1 -= size;
+                if total.0 == 0 {
+                    self.totals.remove(&namespace);
+                }
+            }
+        }
+    }
+}
+
+impl NamespaceTracker {
+    pub(crate) fn written(&mut self, items: &[VectorItem]) {
+        if let Some(usage) = self.usage.as_mut() {
+            usage.record(items);
+        }
+    }
+
+    pub(crate) fn removed(&mut self, id: &Uuid) {
+        if let Some(usage) = self.usage.as_mut() {
+            usage.remove(id);
+        }
+    }
+
+    pub(crate) fn clear(&mut self) {
+        *self = Self::default();
+    }
+}
+
+/// Namespace a query filter pins `field` to, through a top-level (or
+/// `$and`) equality
+pub(crate) fn namespace_in_filter(field: &str, filter: &serde_json::Value) -> Option<String> {
+    let pinned = |condition: &serde_json::Value| match condition.get("$eq") {
+        Some(value) => namespace_name(value),
+        None => namespace_name(condition),
+    };
+    filter.get(field).and_then(pinned).or_else(|| {
+        filter
+            .get("$and")?
+            .as_array()?
+            .iter()
+            .find_map(|clause| clause.get(field).and_then(pinned))
+    })
+}
+
+/// Prefix a query filter requires of `field` through a top-level (or
+/// `$and`) `$startsWith`
+pub(crate) fn namespace_prefix_in_filter(
+    field: &str,
+    filter: &serde_json::Value,
+) -> Option<String> {
+    let prefix =
+        |condition: &serde_json::Value| Some(condition.get("$startsWith")?.as_str()?.to_string());
+    filter.get(field).and_then(prefix).or_else(|| {
+        filter
+            .get("$and")?
+            .as_array()?
+            .iter()
+            .find_map(|clause| clause.get(field).and_then(prefix))
+    })
+}
+
+impl LocalIndex {
+    /// Items of every namespace starting with `prefix`, read namespace by
+    /// namespace
+    pub(crate) async fn list_namespace_prefix(
+        &self,
+        storage: &dyn StorageBackend,
+        prefix: &str,
+    ) -> Result<Vec<VectorItem>> {
+        self.load_namespace_usage(storage).await?;
+        let namespaces: Vec<String> = match self.namespaces.lock().unwrap().usage.as_ref() {
+            Some(usage) => usage
+                .totals
+                .keys()
+                .filter(|namespace| namespace.starts_with(prefix))
+                .cloned()
+                .collect(),
+            None => Vec::new(),
+        };
+        let mut items = Vec::new();
+        for namespace in namespaces {
+            items.extend(storage.list_namespace(&namespace).await?);
+        }
+        Ok(items)
+    }
+
+    /// Tally items by namespace if the index has a namespace field and
+    /// they haven't been yet, returning the field
+    async fn load_namespace_usage(&self, storage: &dyn StorageBackend) -> Result<Option<String>> {
+        let Some(field) = storage.namespace_field().await? else {
+            return Ok(None);
+        };
+        let loaded = self
+            .namespaces
+            .lock()
+            .unwrap()
+            .usage
+            .as_ref()
+            .is_some_and(|usage| usage.field == field);
+        if !loaded {
+            let items = storage.list_items(None).await?;
+            self.namespaces.lock().unwrap().usage = Some(NamespaceUsage::new(&field, &items));
+        }
+        Ok(Some(field))
+    }
+
+    /// Enforce per-namespace quotas on a write of `items`, which replace
+    /// any stored items with the same ids
+    pub(crate) async fn check_namespace_limits(
+        &self,
+        storage: &dyn StorageBackend,
+        items: &[VectorItem],
+    ) -> Result<()> {
+        let Some(limits) = storage.limits().await? else {
+            return Ok(());
+        };
+        if !limits.has_namespace_limits() {
+            return Ok(());
+        }
+        let Some(field) = self.load_namespace_usage(storage).await? else {
+            return Ok(());
+        };
+
+        let tracker = self.namespaces.lock().unwrap();
+        let Some(usage) = tracker.usage.as_ref() else {
+            return Ok(());
+        };
+        // Net change in items and bytes per namespace
+        let mut changes: BTreeMap<&str, (i64, i64)> = BTreeMap::new();
+        for item in items {
+            if let Some((namespace, size)) = usage.items.get(&item.id) {
+                let change = changes.entry(namespace.as_str()).or_default();
+                change.0 -= 1;
+                change.1 -= *size as i64;
+            }
+        }
+        let incoming: Vec<(String, u64)> = items
+            .iter()
+            .filter_map(|item| Some((namespace_of(&field, item)?, crate::approximate_size(item))))
+            .collect();
+        for (namespace, size) in &incoming {
+            let change = changes.entry(namespace.as_str()).or_default();
+            change.0 += 1;
+            change.1 += *size as i64;
+        }
+
+        for (namespace, (item_change, byte_change)) in changes {
+            let Some(quota) = limits.for_namespace(namespace) else {
+                continue;
+            };
+            let (items_now, bytes_now) = usage.totals.get(namespace).copied().unwrap_or_default();
+            if let Some(max) = quota.max_items {
+                let projected = (items_now as i64 + item_change) as usize;
+                if item_change > 0 && projected > max {
+                    return Err(VectraError::QuotaExceeded {
+                        message: format!(
+                            "Namespace '{}' would hold {} items, over its limit of {}",
+                            namespace, projected, max
+                        ),
+                    });
+                }
+            }
+            if let Some(max) = quota.max_bytes {
+                let projected = (bytes_now as i64 + byte_change) as u64;
+                if byte_change > 0 && projected > max {
+                    return Err(VectraError::QuotaExceeded {
+                        message: format!(
+                            "Namespace '{}' would use about {} bytes, over its limit of {}",
+                            namespace, projected, max
+                        ),
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Count `query` against the namespace its filter is pinned to
+    pub(crate) async fn count_namespace_query(
+        &self,
+        storage: &dyn StorageBackend,
+        query: &Query,
+    ) -> Result<()> {
+        let Some(ref filter) = query.filter else {
+            return Ok(());
+        };
+        let Some(field) = storage.namespace_field().await? else {
+            return Ok(());
+        };
+        if let Some(namespace) = namespace_in_filter(&field, filter) {
+            *self
+                .namespaces
+                .lock()
+                .unwrap()
+                .queries
+                .entry(namespace)
+                .or_default() += 1;
+        }
+        Ok(())
+    }
+
+    /// Every item in `namespace`; empty when the index has no namespace
+    /// field
+    pub async fn list_namespace(&self, namespace: &str) -> Result<Vec<VectorItem>> {
+        self.storage
+            .read_for("list")
+            .await
+            .list_namespace(namespace)
+            .await
+    }
+
+    /// Delete every item in `namespace`, returning how many were deleted
+    #[tracing::instrument(name = "vectrust.delete", skip_all, fields(index = %self.path.display()))]
+    pub async fn delete_namespace(&self, namespace: &str) -> Result<usize> {
+        let storage = self.write_storage("delete").await;
+        let ids = storage
+            .list_namespace(namespace)
+            .await?
+            .into_iter()
+            .map(|item| item.id)
+            .collect();
+        self.delete_ids(storage, ids).await
+    }
+
+    /// Per-namespace breakdown for [`IndexStats::namespaces`]; empty when
+    /// the index has no namespace field
+    pub(crate) async fn namespace_stats(
+        &self,
+        storage: &dyn StorageBackend,
+    ) -> Result<BTreeMap<String, NamespaceStats>> {
+        if self.load_namespace_usage(storage).await?.is_none() {
+            return Ok(BTreeMap::new());
+        }
+        let tracker = self.namespaces.lock().unwrap();
+        let mut stats: BTreeMap<String, NamespaceStats> = BTreeMap::new();
+        if let Some(usage) = tracker.usage.as_ref() {
+            for (namespace, &(items, bytes)) in &usage.totals {
+                let entry = stats.entry(namespace.clone()).or_default();
+                entry.items = items;
+                entry.bytes = bytes;
+            }
+        }
+        for (namespace, &queries) in &tracker.queries {
+            stats.entry(namespace.clone()).or_default().queries = queries;
+        }
+        Ok(stats)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn item(tenant: &str, x: f32) -> VectorItem {
+        VectorItem {
+            vector: vec![x, 1.0],
+            metadata: serde_json::json!({ "tenant": tenant }),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_namespace_stats_and_quotas() {
+        let temp_dir = TempDir::new().unwrap();
+        let index = LocalIndex::new(temp_dir.path(), None).unwrap();
+        let mut limits = IndexLimits::default();
+        limits.namespaces.insert(
+            "acme".to_string(),
+            NamespaceLimits {
+                max_items: Some(2),
+                ..Default::default()
+            },
+        );
+        let config = CreateIndexConfig {
+            namespace_field: Some("tenant".to_string()),
+            limits: Some(limits),
+            ..Default::default()
+        };
+        index.create_index(Some(config)).await.unwrap();
+
+        let first = index.insert_item(item("acme", 0.0)).await.unwrap();
+        index
+            .insert_items(vec![item("acme", 1.0), item("globex", 2.0)])
+            .await
+            .unwrap();
+        let untagged = VectorItem {
+            vector: vec![3.0, 1.0],
+            ..Default::default()
+        };
+        index.insert_item(untagged).await.unwrap();
+
+        // acme is full, but other tenants and rewrites of its items aren't
+        let err = index.insert_item(item("acme", 4.0)).await.unwrap_err();
+        assert!(matches!(err, VectraError::QuotaExceeded { .. }), "{err}");
+        index.insert_item(item("globex", 5.0)).await.unwrap();
+        index
+            .update_item(UpdateRequest {
+                id: first.id,
+                vector: Some(vec![6.0, 1.0]),
+                metadata: None,
+            })
+            .await
+            .unwrap();
+
+        let filter = serde_json::json!({ "$and": [{ "tenant": { "$eq": "globex" } }] });
+        index
+            .query_items(vec![1.0, 1.0], Some(1), Some(filter))
+            .await
+            .unwrap();
+
+        let stats = index.get_stats().await.unwrap();
+        assert_eq!(stats.items, 5);
+        assert_eq!(stats.namespaces.len(), 2);
+        assert_eq!(stats.namespaces["acme"].items, 2);
+        assert_eq!(stats.namespaces["acme"].queries, 0);
+        assert_eq!(stats.namespaces["globex"].items, 2);
+        assert_eq!(stats.namespaces["globex"].queries, 1);
+        assert!(stats.namespaces["globex"].bytes > 0);
+
+        // Deleting frees quota, and a fresh handle tallies from storage
+        index.delete_item(&first.id).await.unwrap();
+        index.insert_item(item("acme", 7.0)).await.unwrap();
+        drop(index);
+        let reopened = LocalIndex::new(temp_dir.path(), None).unwrap();
+        let stats = reopened.get_stats().await.unwrap();
+        assert_eq!(stats.namespaces["acme"].items, 2);
+        assert!(reopened.insert_item(item("acme", 8.0)).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_list_and_delete_namespace() {
+        let temp_dir = TempDir::new().unwrap();
+        let index = LocalIndex::new(temp_dir.path(), None).unwrap();
+        let config = CreateIndexConfig {
+            namespace_field: Some("tenant".to_string()),
+            ..Default::default()
+        };
+        index.create_index(Some(config)).await.unwrap();
+        index
+            .insert_items(vec![
+                item("acme", 0.0),
+                item("acme", 1.0),
+                item("globex", 2.0),
+            ])
+            .await
+            .unwrap();
+
+        assert_eq!(index.list_namespace("acme").await.unwrap().len(), 2);
+        assert_eq!(index.delete_namespace("acme").await.unwrap(), 2);
+        assert!(index.list_namespace("acme").await.unwrap().is_empty());
+        assert_eq!(index.delete_namespace("acme").await.unwrap(), 0);
+        let stats = index.get_stats().await.unwrap();
+        assert_eq!(stats.items, 1);
+        assert!(!stats.namespaces.contains_key("acme"));
+    }
+
+    #[test]
+    fn test_namespace_in_filter() {
+        let pinned = |filter: serde_json::Value| namespace_in_filter("tenant", &filter);
+        assert_eq!(
+            pinned(serde_json::json!({ "tenant": "a" })).as_deref(),
+            Some("a")
+        );
+        assert_eq!(
+            pinned(serde_json::json!({ "tenant": { "$eq": 7 } })).as_deref(),
+            Some("7")
+        );
+        assert_eq!(
+            pinned(serde_json::json!({ "tenant": { "$in": ["a"] } })),
+            None
+        );
+        assert_eq!(pinned(serde_json::json!({ "other": "a" })), None);
+    }
+}
@@ -0,0 +1,304 @@
+// Copyright 2024-2026 Andrey Vasilevsky <anvanster@gmail.com>
+// SPDX-License-Identifier: Apache-2.0
+
+//! Precomputed nearest-neighbour lists.
+//!
+//! [`LocalIndex::build_neighbors`] computes every item's most similar
+//! items once and stores them in the index folder, so "related items"
+//! lookups are a map read instead of a vector query. Writes through the
+//! handle mark the affected lists stale; the next lookup or
+//! [`LocalIndex::refresh_neighbors`] recomputes only those.
+
+use crate::LocalIndex;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use uuid::Uuid;
+use vectrust_core::*;
+
+/// File holding the neighbour lists inside an index folder
+pub(crate) const NEIGHBORS_FILE: &str = "neighbors.json";
+
+/// One entry of an item's neighbour list
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Neighbor {
+    pub id: Uuid,
+    /// Similarity under the index's distance metric; higher is closer
+    pub score: f32,
+}
+
+/// Neighbour lists with the writes not yet applied to them
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub(crate) struct NeighborGraph {
+    /// Neighbours kept per item
+    size: usize,
+    lists: HashMap<Uuid, Vec<Neighbor>>,
+    /// Items written since the last refresh
+    #[serde(default, skip_serializing_if = "HashSet::is_empty")]
+    written: HashSet<Uuid>,
+    /// Items deleted since the last refresh
+    #[serde(default, skip_serializing_if = "HashSet::is_empty")]
+    removed: HashSet<Uuid>,
+}
+
+impl NeighborGraph {
+    pub(crate) fn load(folder: &Path) -> Result<Option<Self>> {
+        match std::fs::read(folder.join(NEIGHBORS_FILE)) {
+            Ok(bytes) => Ok(Some(serde_json::from_slice(&bytes)?)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn save(&self, folder: &Path) -> Result<()> {
+        std::fs::create_dir_all(folder)?;
+        let temp_path = folder.join(format!("{}.tmp", NEIGHBORS_FILE));
+        std::fs::write(&temp_path, serde_json::to_vec(self)?)?;
+        std::fs::rename(&temp_path, folder.join(NEIGHBORS_FILE))?;
+        Ok(())
+    }
+
+    pub(crate) fn mark_written(&mut self, items: &[VectorItem]) {
+        for item in items {
+            self.removed.remove(&item.id);
+            self.written.insert(item.id);
+        }
+    }
+
+    pub(crate) fn mark_removed(&mut self, id: &Uuid) {
+        self.written.remove(id);
+        self.removed.insert(*id);
+    }
+
+    fn is_stale(&self) -> bool {
+        !self.written.is_empty() || !self.removed.is_empty()
+    }
+
+    /// Apply pending writes against the current items, returning how many
+    /// lists were recomputed from scratch
+    fn refresh(&mut self, items: &HashMap<Uuid, VectorItem>, distance: &Distance) -> usize {
+        let written: HashSet<Uuid> = std::mem::take(&mut self.written);
+        let removed: HashSet<Uuid> = std::mem::take(&mut self.removed);
+        // Written items deleted again by a later change are gone too
+        let (written, vanished): (HashSet<Uuid>, HashSet<Uuid>) =
+            written.into_iter().partition(|id| items.contains_key(id));
+        for id in removed.iter().chain(&vanished) {
+            self.lists.remove(id);
+        }
+
+        // A list pointing at a changed or deleted item may now rank others
+        // above it, so it can't be patched in place
+        let mut recompute: HashSet<Uuid> = written.clone();
+        for (id, list) in &self.lists {
+            let touched = list.iter().any(|n| {
+                written.contains(&n.id) || removed.contains(&n.id) || vanished.contains(&n.id)
+            });
+            if touched {
+                recompute.insert(*id);
+            }
+        }
+        recompute.retain(|id| items.contains_key(id));
+        for id in &recompute {
+            let list = nearest(&items[id], items.values(), self.size, distance);
+            self.lists.insert(*id, list);
+        }
+
+        // The rest only need the written items merged in where they now rank
+        for id in &written {
+            let item = &items[id];
+            for (other, list) in self.lists.iter_mut() {
+                if recompute.contains(other) {
+                    continue;
+                }
+                let Some(other_item) = items.get(other) else {
+                    continue;
+                };
+                if other_item.vector.len() != item.vector.len() {
+                    continue;
+                }
+                let score = distance.similarity(&other_item.vector, &item.vector);
+                if list.len() < self.size || list.last().is_some_and(|last| score > last.score) {
+                    let position = list.partition_point(|n| n.score >= score);
+                    list.insert(position, Neighbor { id: *id, score });
+                    list.truncate(self.size);
+                }
+            }
+        }
+        recompute.len()
+    }
+}
+
+/// The `size` items most similar to `item`, best first
+fn nearest<'a>(
+    item: &VectorItem,
+    candidates: impl Iterator<Item = &'a VectorItem>,
+    size: usize,
+    distance: &Distance,
+) -> Vec<Neighbor> {
+    let mut neighbors: Vec<Neighbor> = candidates
+        .filter(|other| other.id != item.id && other.vector.len() == item.vector.len())
+        .map(|other| Neighbor {
+            id: other.id,
+            score: distance.similarity(&item.vector, &other.vector),
+        })
+        .collect();
+    neighbors.sort_by(|a, b| b.score.total_cmp(&a.score).then_with(|| a.id.cmp(&b.id)));
+    neighbors.truncate(size);
+    neighbors
+}
+
+impl LocalIndex {
+    /// Compute and store the `size` nearest neighbours of every item,
+    /// replacing any earlier lists. Takes time quadratic in the item
+    /// count, so run it offline; writes afterwards are applied
+    /// incrementally. Returns the number of lists built.
+    pub async fn build_neighbors(&self, size: usize) -> Result<usize> {
+        let storage = self.storage.read_for("neighbors").await;
+        let distance = self
+            .distances
+            .resolve(&storage.get_stats().await?.distance_metric)?;
+        let items = storage.list_items(None).await?;
+
+        let mut graph = NeighborGraph {
+            size,
+            ..Default::default()
+        };
+        for item in &items {
+            let list = nearest(item, items.iter(), size, &distance);
+            graph.lists.insert(item.id, list);
+        }
+        graph.save(&self.path)?;
+        let built = graph.lists.len();
+        *self.neighbors.lock().unwrap() = Some(graph);
+        Ok(built)
+    }
+
+    /// Recompute the lists affected by writes since the last refresh,
+    /// returning how many had to be rebuilt from scratch. Fails if
+    /// [`build_neighbors`](Self::build_neighbors) was never run.
+    ///
+    /// Only writes through this handle are tracked; rebuild after writing
+    /// through another handle or process.
+    pub async fn refresh_neighbors(&self) -> Result<usize> {
+        // Holding the storage lock keeps writes from marking lists stale
+        // while they're being recomputed
+        let storage = self.storage.read_for("neighbors").await;
+        let stale = match self.neighbors.lock().unwrap().as_ref() {
+            Some(graph) => graph.is_stale(),
+            None => return Err(neighbors_not_built()),
+        };
+        if !stale {
+            return Ok(0);
+        }
+
+        let distance = self
+            .distances
+            .resolve(&storage.get_stats().await?.distance_metric)?;
+        let items: HashMap<Uuid, VectorItem> = storage
+            .list_items(None)
+            .await?
+            .into_iter()
+            .map(|item| (item.id, item))
+            .collect();
+
+        let mut guard = self.neighbors.lock().unwrap();
+        let graph = guard.as_mut().ok_or_else(neighbors_not_built)?;
+        let recomputed = graph.refresh(&items, &distance);
+        graph.save(&self.path)?;
+        Ok(recomputed)
+    }
+
+    /// Precomputed nearest neighbours of `id`, best first, after applying
+    /// any pending writes. `None` when the item has no list.
+    pub async fn get_neighbors(&self, id: &Uuid) -> Result<Option<Vec<Neighbor>>> {
+        self.refresh_neighbors().await?;
+        let guard = self.neighbors.lock().unwrap();
+        let graph = guard.as_ref().ok_or_else(neighbors_not_built)?;
+        Ok(graph.lists.get(id).cloned())
+    }
+
+    /// Discard the neighbour lists and their stored file
+    pub fn drop_neighbors(&self) -> Result<()> {
+        self.neighbors.lock().unwrap().take();
+        let file = self.path.join(NEIGHBORS_FILE);
+        if file.exists() {
+            std::fs::remove_file(file)?;
+        }
+        Ok(())
+    }
+}
+
+fn neighbors_not_built() -> VectraError {
+    VectraError::Query {
+        message: "Neighbor lists have not been built".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn item(x: f32, y: f32) -> VectorItem {
+        VectorItem {
+            vector: vec![x, y],
+            ..Default::default()
+        }
+    }
+
+    fn ids(neighbors: &[Neighbor]) -> Vec<Uuid> {
+        neighbors.iter().map(|n| n.id).collect()
+    }
+
+    #[tokio::test]
+    async fn test_neighbors_follow_writes() {
+        let temp_dir = TempDir::new().unwrap();
+        let index = LocalIndex::new(temp_dir.path(), None).unwrap();
+        let config = CreateIndexConfig {
+            distance_metric: DistanceMetric::Euclidean,
+            ..Default::default()
+        };
+        index.create_index(Some(config)).await.unwrap();
+
+        let mut points = Vec::new();
+        for x in [0.0, 1.0, 2.0, 10.0] {
+            points.push(index.insert_item(item(x, 0.0)).await.unwrap().id);
+        }
+        assert!(index.get_neighbors(&points[0]).await.is_err());
+
+        assert_eq!(index.build_neighbors(2).await.unwrap(), 4);
+        let near = index.get_neighbors(&points[0]).await.unwrap().unwrap();
+        assert_eq!(ids(&near), vec![points[1], points[2]]);
+
+        // A new close item enters existing lists and gets its own
+        let close = index.insert_item(item(0.4, 0.0)).await.unwrap().id;
+        let near = index.get_neighbors(&points[0]).await.unwrap().unwrap();
+        assert_eq!(ids(&near), vec![close, points[1]]);
+        let own = index.get_neighbors(&close).await.unwrap().unwrap();
+        assert_eq!(ids(&own), vec![points[0], points[1]]);
+
+        // Moving an item away drops it from lists that held it
+        index
+            .update_item(UpdateRequest {
+                id: close,
+                vector: Some(vec![20.0, 0.0]),
+                metadata: None,
+            })
+            .await
+            .unwrap();
+        index.delete_item(&points[1]).await.unwrap();
+        let near = index.get_neighbors(&points[0]).await.unwrap().unwrap();
+        assert_eq!(ids(&near), vec![points[2], points[3]]);
+        assert!(index.get_neighbors(&points[1]).await.unwrap().is_none());
+
+        // Lists are persisted with the index
+        drop(index);
+        let reopened = LocalIndex::new(temp_dir.path(), None).unwrap();
+        let near = reopened.get_neighbors(&points[0]).await.unwrap().unwrap();
+        assert_eq!(ids(&near), vec![points[2], points[3]]);
+
+        reopened.drop_neighbors().unwrap();
+        assert!(!temp_dir.path().join(NEIGHBORS_FILE).exists());
+        assert!(reopened.get_neighbors(&points[0]).await.is_err());
+    }
+}
@@ -0,0 +1,291 @@
+// Copyright 2024-2026 Andrey Vasilevsky <anvanster@gmail.com>
+// SPDX-License-Identifier: Apache-2.0
+
+//! Outlier scoring for finding mis-embedded or junk items.
+//!
+//! [`LocalIndex::find_outliers`] scores every item by how far it sits from
+//! the rest of the index and returns the most isolated ones. Both methods
+//! compare all items in memory, so they suit offline audits rather than
+//! request paths.
+
+use crate::LocalIndex;
+use std::collections::HashMap;
+use vectrust_core::*;
+use vectrust_query::MetadataFilter;
+
+/// How an item's outlier score is computed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutlierMethod {
+    /// Distance to the item's `k`th nearest neighbour. Finds isolated
+    /// items even in unevenly sized clusters; quadratic in the item count.
+    KthNeighbor { k: usize },
+    /// Distance to the nearest of `clusters` k-means centroids, after up
+    /// to `iterations` refinement rounds. Linear per round, but an outlier
+    /// group as large as a cluster can claim a centroid of its own.
+    Centroids { clusters: usize, iterations: usize },
+}
+
+/// An item and how far it lies from the rest
+#[derive(Debug, Clone)]
+pub struct Outlier {
+    pub item: VectorItem,
+    /// Distance under the index's metric; higher is more isolated.
+    /// Infinite for items whose dimensions differ from the majority.
+    pub score: f32,
+}
+
+impl LocalIndex {
+    /// The `top_n` items farthest from the rest of the index, most isolated
+    /// first. `filter` restricts both the items scored and the population
+    /// they are compared against.
+    ///
+    /// Items whose vectors have a different length from most of the index
+    /// can't be compared at all and rank first.
+    pub async fn find_outliers(
+        &self,
+        method: OutlierMethod,
+        top_n: usize,
+        filter: Option<&serde_json::Value>,
+    ) -> Result<Vec<Outlier>> {
+        let storage = self.storage.read_for("outliers").await;
+        let distance = self
+            .distances
+            .resolve(&storage.get_stats().await?.distance_metric)?;
+        let items: Vec<VectorItem> = storage
+            .list_items(None)
+            .await?
+            .into_iter()
+            .filter(|item| filter.is_none_or(|f| MetadataFilter::matches(item, f)))
+            .collect();
+        drop(storage);
+
+        let dimensions = most_common_length(&items);
+        let (regular, odd): (Vec<VectorItem>, Vec<VectorItem>) = items
+            .into_iter()
+            .partition(|item| Some(item.vector.len()) == dimensions);
+
+        let vectors: Vec<&[f32]> = regular.iter().map(|item| item.vector.as_slice()).collect();
+        let scores = match method {
+            OutlierMethod::KthNeighbor { k } => kth_neighbor_scores(&vectors, k, &distance),
+            OutlierMethod::Centroids {
+                clusters,
+                iterations,
+            } => centroid_scores(&vectors, clusters, iterations, &distance),
+        };
+
+        let mut outliers: Vec<Outlier> = odd
+            .into_iter()
+            .map(|item| Outlier {
+                item,
+                score: f32::INFINITY,
+            })
+            .chain(
+                regular
+                    .into_iter()
+                    .zip(scores)
+                    .map(|(item, score)| Outlier { item, score }),
+            )
+            .collect();
+        outliers.sort_by(|a, b| {
+            b.score
+                .total_cmp(&a.score)
+                .then_with(|| a.item.id.cmp(&b.item.id))
+        });
+        outliers.truncate(top_n);
+        Ok(outliers)
+    }
+}
+
+fn most_common_length(items: &[VectorItem]) -> Option<usize> {
+    let mut counts: HashMap<usize, usize> = HashMap::new();
+    for item in items {
+        *counts.entry(item.vector.len()).or_default() += 1;
+    }
+    // Ties go to the longer vectors so the result doesn't depend on map order
+    counts
+        .into_iter()
+        .max_by_key(|&(len, count)| (count, len))
+        .map(|(len, _)| len)
+}
+
+fn kth_neighbor_scores(vectors: &[&[f32]], k: usize, distance: &Distance) -> Vec<f32> {
+    let mut distances = Vec::with_capacity(vectors.len());
+    vectors
+        .iter()
+        .enumerate()
+        .map(|(i, vector)| {
+            distances.clear();
+            distances.extend(
+                vectors
+                    .iter()
+                    .enumerate()
+                    .filter(|&(j, _)| j != i)
+                    .map(|(_, other)| distance.distance(vector, other)),
+            );
+            if distances.is_empty() {
+                return 0.0;
+            }
+            // With fewer than k other items, the farthest one stands in
+            let nth = k.clamp(1, distances.len()) - 1;
+            *distances
+                .select_nth_unstable_by(nth, |a, b| a.total_cmp(b))
+                .1
+        })
+        .collect()
+}
+
+fn centroid_scores(
+    vectors: &[&[f32]],
+    clusters: usize,
+    iterations: usize,
+    distance: &Distance,
+) -> Vec<f32> {
+    let clusters = clusters.clamp(1, vectors.len().max(1));
+    if vectors.is_empty() {
+        return Vec::new();
+    }
+    // Cosine only cares about direction, so cluster on the unit sphere
+    let spherical = matches!(distance.metric(), DistanceMetric::Cosine);
+    let points: Vec<Vec<f32>> = vectors
+        .iter()
+        .map(|v| {
+            if spherical {
+                VectorOps::normalized(v)
+            } else {
+                v.to_vec()
+            }
+        })
+        .collect();
+
+    // Spread the initial centroids evenly through the items, which keeps
+    // the result reproducible without a random seed
+    let mut centroids: Vec<Vec<f32>> = (0..clusters)
+        .map(|c| points[c * points.len() / clusters].clone())
+        .collect();
+    let mut assignment = vec![usize::MAX; points.len()];
+
+    for _ in 0..iterations.max(1) {
+        let mut changed = false;
+        for (point, assigned) in points.iter().zip(assignment.iter_mut()) {
+            let nearest = nearest_centroid(point, &centroids);
+            if nearest != *assigned {
+                *assigned = nearest;
+                changed = true;
+            }
+        }
+        if !changed {
+            break;
+        }
+
+        let dims = points[0].len();
+        let mut sums = vec![vec![0.0f32; dims]; clusters];
+        let mut counts = vec![0usize; clusters];
+        for (point, &assigned) in points.iter().zip(&assignment) {
+            counts[assigned] += 1;
+            for (sum, x) in sums[assigned].iter_mut().zip(point) {
+                *sum += x;
+            }
+        }
+        for ((centroid, sum), count) in centroids.iter_mut().zip(sums).zip(counts) {
+            // An empty cluster keeps its previous centroid
+            if count > 0 {
+                *centroid = sum.into_iter().map(|x| x / count as f32).collect();
+                if spherical {
+                    VectorOps::normalize(centroid);
+                }
+            }
+        }
+    }
+
+    vectors
+        .iter()
+        .zip(&points)
+        .map(|(vector, point)| {
+            let centroid = &centroids[nearest_centroid(point, &centroids)];
+            distance.distance(vector, centroid)
+        })
+        .collect()
+}
+
+fn nearest_centroid(point: &[f32], centroids: &[Vec<f32>]) -> usize {
+    centroids
+        .iter()
+        .map(|centroid| VectorOps::euclidean_distance(point, centroid))
+        .enumerate()
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .map_or(0, |(i, _)| i)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_find_outliers() {
+        let temp_dir = TempDir::new().unwrap();
+        // Legacy storage accepts mixed dimensions, as older indexes may hold
+        let storage = vectrust_storage::LegacyStorage::new(temp_dir.path(), "index.json").unwrap();
+        let index = LocalIndex::with_storage(
+            temp_dir.path().into(),
+            "index.json".into(),
+            Box::new(storage),
+        )
+        .unwrap();
+        index.create_index(None).await.unwrap();
+
+        let mut items = Vec::new();
+        for i in 0..10 {
+            let jitter = i as f32 * 0.01;
+            items.push(VectorItem {
+                vector: vec![1.0, jitter],
+                metadata: serde_json::json!({ "kind": "doc" }),
+                ..Default::default()
+            });
+            items.push(VectorItem {
+                vector: vec![jitter, 1.0],
+                metadata: serde_json::json!({ "kind": "doc" }),
+                ..Default::default()
+            });
+        }
+        items.push(VectorItem {
+            vector: vec![-1.0, -1.0],
+            metadata: serde_json::json!({ "kind": "junk" }),
+            ..Default::default()
+        });
+        items.push(VectorItem {
+            vector: vec![1.0, 0.0, 0.0],
+            metadata: serde_json::json!({ "kind": "wrong-model" }),
+            ..Default::default()
+        });
+        // Listings are id-ordered; sequential ids keep the centroid seeds
+        // in insertion order, one in each cluster of docs
+        for (n, item) in items.iter_mut().enumerate() {
+            item.id = uuid::Uuid::from_u128(n as u128 + 1);
+        }
+        index.insert_items(items).await.unwrap();
+
+        let methods = [
+            OutlierMethod::KthNeighbor { k: 3 },
+            OutlierMethod::Centroids {
+                clusters: 2,
+                iterations: 10,
+            },
+        ];
+        for method in methods {
+            let outliers = index.find_outliers(method, 2, None).await.unwrap();
+            assert_eq!(outliers.len(), 2);
+            assert_eq!(outliers[0].item.metadata["kind"], "wrong-model");
+            assert!(outliers[0].score.is_infinite());
+            assert_eq!(outliers[1].item.metadata["kind"], "junk", "{:?}", method);
+        }
+
+        let filter = serde_json::json!({ "kind": "doc" });
+        let outliers = index
+            .find_outliers(OutlierMethod::KthNeighbor { k: 1 }, 100, Some(&filter))
+            .await
+            .unwrap();
+        assert_eq!(outliers.len(), 20);
+        assert!(outliers.iter().all(|o| o.score.is_finite()));
+    }
+}
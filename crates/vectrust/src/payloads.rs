@@ -0,0 +1,113 @@
+// Copyright 2024-2026 Andrey Vasilevsky <anvanster@gmail.com>
+// SPDX-License-Identifier: Apache-2.0
+
+//! Document payloads stored alongside items.
+//!
+//! A payload is an item's original text or a small binary blob, kept apart
+//! from its metadata so scans and filters never read it. Queries return it
+//! only when [`Query::include_payloads`] is set, which saves RAG
+//! applications a second database for chunk contents. Deleting an item
+//! deletes its payload. The optimized and redb backends store payloads;
+//! legacy JSON indexes don't.
+
+use crate::LocalIndex;
+use uuid::Uuid;
+use vectrust_core::*;
+
+impl LocalIndex {
+    /// Store `payload` for the item `id`, replacing any it had. Payloads
+    /// are limited to the index's `max_payload_bytes`, and never exceed
+    /// [`MAX_PAYLOAD_BYTES`].
+    pub async fn set_payload(&self, id: &Uuid, payload: impl Into<Vec<u8>>) -> Result<()> {
+        let payload = payload.into();
+        let mut storage = self.storage.write_for("payload").await;
+        let limits = storage.limits().await?.unwrap_or_default();
+        limits.check_payload(id, payload.len())?;
+        storage.set_payloads(&[(*id, Some(payload))]).await
+    }
+
+    /// Remove the payload of item `id`, keeping the item
+    pub async fn remove_payload(&self, id: &Uuid) -> Result<()> {
+        let mut storage = self.storage.write_for("payload").await;
+        storage.set_payloads(&[(*id, None)]).await
+    }
+
+    /// Payload stored for item `id`, if any
+    pub async fn get_payload(&self, id: &Uuid) -> Result<Option<Vec<u8>>> {
+        let storage = self.storage.read_for("payload").await;
+        Ok(storage.get_payloads(&[*id]).await?.pop().flatten())
+    }
+
+    /// Attach each result's payload when `query` asks for them
+    pub(crate) async fn attach_payloads(
+        storage: &dyn StorageBackend,
+        query: &Query,
+        mut results: Vec<QueryResult>,
+    ) -> Result<Vec<QueryResult>> {
+        if query.include_payloads {
+            let ids: Vec<Uuid> = results.iter().map(|result| result.item.id).collect();
+            let payloads = storage.get_payloads(&ids).await?;
+            for (result, payload) in results.iter_mut().zip(payloads) {
+                result.payload = payload;
+            }
+        }
+        Ok(results)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_payloads_returned_with_results() {
+        let temp_dir = TempDir::new().unwrap();
+        let index = LocalIndex::new(temp_dir.path(), None).unwrap();
+        let config = CreateIndexConfig {
+            limits: Some(IndexLimits {
+                max_payload_bytes: Some(16),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        index.create_index(Some(config)).await.unwrap();
+        let item = |x: f32| VectorItem {
+            vector: vec![x, 1.0],
+            ..Default::default()
+        };
+        let first = index.insert_item(item(1.0)).await.unwrap();
+        let second = index.insert_item(item(0.0)).await.unwrap();
+
+        index.set_payload(&first.id, "chunk text").await.unwrap();
+        assert!(matches!(
+            index.set_payload(&second.id, vec![0u8; 17]).await,
+            Err(VectraError::QuotaExceeded { .. })
+        ));
+        assert!(index.set_payload(&Uuid::new_v4(), "orphan").await.is_err());
+        assert_eq!(
+            index.get_payload(&first.id).await.unwrap().as_deref(),
+            Some(&b"chunk text"[..])
+        );
+
+        // Payloads only come back when asked for
+        let mut query = Query {
+            vector: Some(vec![1.0, 1.0]),
+            top_k: 2,
+            ..Default::default()
+        };
+        let results = index.execute_query(&query, None).await.unwrap();
+        assert!(results.iter().all(|result| result.payload.is_none()));
+        query.include_payloads = true;
+        let results = index.execute_query(&query, None).await.unwrap();
+        assert_eq!(results[0].item.id, first.id);
+        assert_eq!(results[0].payload.as_deref(), Some(&b"chunk text"[..]));
+        assert_eq!(results[1].payload, None);
+
+        index.remove_payload(&first.id).await.unwrap();
+        assert_eq!(index.get_payload(&first.id).await.unwrap(), None);
+        index.set_payload(&first.id, "again").await.unwrap();
+        index.delete_item(&first.id).await.unwrap();
+        assert_eq!(index.get_payload(&first.id).await.unwrap(), None);
+    }
+}
@@ -0,0 +1,643 @@
+// Copyright 2024-2026 Andrey Vasilevsky <anvanster@gmail.com>
+// SPDX-License-Identifier: Apache-2.0
+
+//! Metadata statistics for the query planner.
+//!
+//! The planner samples the values of the namespace, range and composite
+//! index fields, plus any configured in [`PlannerStatsConfig::fields`]:
+//! how many items hold each, how many distinct values it has, its most
+//! common values and, for numbers, an equi-depth histogram. A filter's
+//! share of the index is estimated from these before any item is loaded,
+//! so a filter expected to pass most items can be applied to the HNSW
+//! graph's shortlist instead of every stored item.
+//!
+//! With [`RuntimeConfig::planner_stats`] set, statistics are sampled by
+//! the first filtered query and again once enough items were written;
+//! [`LocalIndex::analyze`] samples them on demand.
+
+use crate::LocalIndex;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::ops::Bound;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use vectrust_core::*;
+
+/// Buckets in each numeric field's histogram
+const HISTOGRAM_BUCKETS: usize = 32;
+
+/// Values kept in each field's most-common list
+const MOST_COMMON_VALUES: usize = 16;
+
+/// Sampled statistics of an index's metadata
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlannerStats {
+    /// Items in the index when it was sampled
+    pub items: usize,
+    /// Items sampled
+    pub sampled: usize,
+    pub fields: BTreeMap<String, FieldStats>,
+}
+
+/// Sampled statistics of one metadata field
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FieldStats {
+    /// Sampled items holding a string, number or boolean in the field
+    pub present: usize,
+    /// Distinct values estimated across the whole index
+    pub distinct: usize,
+    /// The most common values, with how many sampled items hold each
+    pub most_common: Vec<(Value, usize)>,
+    /// Sampled items holding a number in the field
+    pub numbers: usize,
+    /// Bounds of buckets each holding an equal share of the sampled
+    /// numbers, lowest first; empty without numbers
+    pub histogram: Vec<f64>,
+}
+
+/// Sampled statistics and the writes made since, for deciding when to
+/// sample again
+#[derive(Debug, Default)]
+pub(crate) struct StatsTracker {
+    stats: Option<Arc<PlannerStats>>,
+    writes: usize,
+}
+
+impl StatsTracker {
+    pub(crate) fn written(&mut self, items: usize) {
+        self.writes += items;
+    }
+
+    pub(crate) fn clear(&mut self) {
+        *self = Self::default();
+    }
+}
+
+/// Resolve a dotted path like `doc.lang` within the metadata
+fn field_value<'a>(metadata: &'a Value, path: &str) -> Option<&'a Value> {
+    path.split('.')
+        .try_fold(metadata, |value, segment| value.get(segment))
+}
+
+/// Key comparing values as filters do, numbers by value; `None` for
+/// anything but strings, numbers and booleans
+fn value_key(value: &Value) -> Option<String> {
+    match value {
+        Value::Number(n) => n.as_f64().map(|n| format!("{}", n)),
+        Value::String(_) | Value::Bool(_) => Some(value.to_string()),
+        _ => None,
+    }
+}
+
+/// Distinct values among `population` items from those among `sampled` of
+/// them, `singles` of which were seen once (Haas and Stokes' Duj1)
+fn estimate_distinct(seen: usize, singles: usize, sampled: usize, population: usize) -> usize {
+    if sampled == 0 || sampled >= population {
+        return seen;
+    }
+    let (n, f1) = (sampled as f64, singles as f64);
+    let estimate = n * seen as f64 / (n - f1 + f1 * n / population as f64);
+    (estimate.round() as usize).clamp(seen, population)
+}
+
+impl FieldStats {
+    /// Statistics of `values`, the field's values in `sampled` of `items`
+    fn from_values(values: &[&Value], sampled: usize, items: usize) -> Self {
+        let mut counts: HashMap<String, (&Value, usize)> = HashMap::new();
+        for value in values {
+            if let Some(key) = value_key(value) {
+                counts.entry(key).or_insert((value, 0)).1 += 1;
+            }
+        }
+        let present: usize = counts.values().map(|(_, count)| count).sum();
+        let singles = counts.values().filter(|(_, count)| *count == 1).count();
+        let population = (present as f64 * items as f64 / sampled.max(1) as f64).round() as usize;
+        let distinct = estimate_distinct(counts.len(), singles, present, population);
+
+        let mut most_common: Vec<(Value, usize)> = counts
+            .into_values()
+            .map(|(value, count)| (value.clone(), count))
+            .collect();
+        most_common.sort_by(|a, b| {
+            b.1.cmp(&a.1)
+                .then_with(|| a.0.to_string().cmp(&b.0.to_string()))
+        });
+        most_common.truncate(MOST_COMMON_VALUES);
+
+        let mut numbers: Vec<f64> = values.iter().filter_map(|value| value.as_f64()).collect();
+        numbers.sort_by(f64::total_cmp);
+        let histogram = if numbers.is_empty() {
+            Vec::new()
+        } else {
+            (0..=HISTOGRAM_BUCKETS)
+                .map(|bucket| numbers[(bucket * (numbers.len() - 1)).div_ceil(HISTOGRAM_BUCKETS)])
+                .collect()
+        };
+        Self {
+            present,
+            distinct,
+            most_common,
+            numbers: numbers.len(),
+            histogram,
+        }
+    }
+
+    /// Share of `sampled` items holding `value`
+    fn equal_share(&self, value: &Value, sampled: f64) -> Option<f64> {
+        let key = value_key(value)?;
+        if let Some((_, count)) = self
+            .most_common
+            .iter()
+            .find(|(common, _)| value_key(common).as_ref() == Some(&key))
+        {
+            return Some(*count as f64 / sampled);
+        }
+        // Values outside the list share what's left of the field evenly
+        let common: usize = self.most_common.iter().map(|(_, count)| count).sum();
+        let others = self.distinct.saturating_sub(self.most_common.len());
+        if others == 0 {
+            return Some(0.0);
+        }
+        Some(self.present.saturating_sub(common) as f64 / others as f64 / sampled)
+    }
+
+    /// Share of the sampled numbers below `value`, read off the histogram
+    fn share_below(&self, value: f64) -> f64 {
+        let bounds = &self.histogram;
+        match (bounds.first(), bounds.last()) {
+            (Some(first), _) if value <= *first => 0.0,
+            (_, Some(last)) if value < *last => {
+                let bucket = bounds.partition_point(|bound| *bound <= value) - 1;
+                let (low, high) = (bounds[bucket], bounds[bucket + 1]);
+                (bucket as f64 + (value - low) / (high - low)) / (bounds.len() - 1) as f64
+            }
+            _ => 1.0,
+        }
+    }
+
+    /// Share of `sampled` items holding a number within `range`
+    fn range_share(&self, range: &NumericRange, sampled: f64) -> f64 {
+        let low = match range.min {
+            Bound::Included(value) | Bound::Excluded(value) => self.share_below(value),
+            Bound::Unbounded => 0.0,
+        };
+        let high = match range.max {
+            Bound::Included(value) | Bound::Excluded(value) => self.share_below(value),
+            Bound::Unbounded => 1.0,
+        };
+        (high - low).max(0.0) * self.numbers as f64 / sampled
+    }
+}
+
+/// Share passing every clause whose share is known, or `None` if none is
+fn all_of(shares: impl IntoIterator<Item = Option<f64>>) -> Option<f64> {
+    shares.into_iter().flatten().reduce(|a, b| a * b)
+}
+
+impl PlannerStats {
+    /// Estimated share of items `filter` passes, or `None` when it tests no
+    /// field the statistics cover
+    pub fn selectivity(&self, filter: &Value) -> Option<f64> {
+        self.filter_share(filter).map(|share| share.clamp(0.0, 1.0))
+    }
+
+    fn filter_share(&self, filter: &Value) -> Option<f64> {
+        all_of(filter.as_object()?.iter().map(|(key, condition)| {
+            match key.as_str() {
+                "$and" => all_of(condition.as_array()?.iter().map(|c| self.filter_share(c))),
+                // Every branch must be known, or the union could be anything
+                "$or" => condition
+                    .as_array()?
+                    .iter()
+                    .map(|c| self.filter_share(c))
+                    .collect::<Option<Vec<f64>>>()
+                    .map(|shares| 1.0 - shares.iter().map(|share| 1.0 - share).product::<f64>()),
+                key if key.starts_with('$') => None,
+                field => self.field_share(field, condition),
+            }
+        }))
+    }
+
+    fn field_share(&self, field: &str, condition: &Value) -> Option<f64> {
+        let stats = self.fields.get(field)?;
+        let sampled = self.sampled.max(1) as f64;
+        let Value::Object(ops) = condition else {
+            return stats.equal_share(condition, sampled);
+        };
+        let any_of = |operand: &Value| {
+            operand.as_array().map(|values| {
+                values
+                    .iter()
+                    .filter_map(|value| stats.equal_share(value, sampled))
+                    .sum::<f64>()
+                    .min(1.0)
+            })
+        };
+        let mut range: Option<NumericRange> = None;
+        let mut shares = Vec::new();
+        for (op, operand) in ops {
+            let bound = operand.as_f64();
+            let (min, max) = match (op.as_str(), bound) {
+                ("$gt", Some(value)) => (Bound::Excluded(value), Bound::Unbounded),
+                ("$gte", Some(value)) => (Bound::Included(value), Bound::Unbounded),
+                ("$lt", Some(value)) => (Bound::Unbounded, Bound::Excluded(value)),
+                ("$lte", Some(value)) => (Bound::Unbounded, Bound::Included(value)),
+                _ => {
+                    shares.push(match op.as_str() {
+                        "$eq" => stats.equal_share(operand, sampled),
+                        "$ne" => stats.equal_share(operand, sampled).map(|share| 1.0 - share),
+                        "$in" => any_of(operand),
+                        "$nin" => any_of(operand).map(|share| 1.0 - share),
+                        "$exists" => operand.as_bool().map(|exists| {
+                            let share = stats.present as f64 / sampled;
+                            if exists {
+                                share
+                            } else {
+                                1.0 - share
+                            }
+                        }),
+                        _ => None,
+                    });
+                    continue;
+                }
+            };
+            let bound = NumericRange { min, max };
+            range = Some(range.map_or(bound, |range| range.intersect(bound)));
+        }
+        shares.push(range.map(|range| stats.range_share(&range, sampled)));
+        all_of(shares)
+    }
+}
+
+/// Sample `fields` from at most about `sample_size` of the index's `items`
+async fn sample(
+    storage: &dyn StorageBackend,
+    fields: Vec<String>,
+    items: usize,
+    sample_size: usize,
+) -> Result<PlannerStats> {
+    let stride = items.div_ceil(sample_size.max(1)).max(1);
+    let rows: Arc<Mutex<Vec<Vec<Value>>>> = Default::default();
+    let seen = Arc::new(AtomicUsize::new(0));
+    // Only metadata is wanted, so every item is rejected before its vector
+    // is read
+    let collect = {
+        let (fields, rows, seen) = (fields.clone(), rows.clone(), seen.clone());
+        move |metadata: &Value| {
+            if seen.fetch_add(1, Ordering::Relaxed) % stride == 0 {
+                let row = fields
+                    .iter()
+                    .map(|field| field_value(metadata, field).cloned().unwrap_or(Value::Null))
+                    .collect();
+                rows.lock().unwrap().push(row);
+            }
+            false
+        }
+    };
+    storage.list_matching(None, &collect).await?;
+
+    let rows = std::mem::take(&mut *rows.lock().unwrap());
+    let items = seen.load(Ordering::Relaxed);
+    let fields = fields
+        .into_iter()
+        .enumerate()
+        .map(|(column, field)| {
+            let values: Vec<&Value> = rows.iter().map(|row| &row[column]).collect();
+            (field, FieldStats::from_values(&values, rows.len(), items))
+        })
+        .collect();
+    Ok(PlannerStats {
+        items,
+        sampled: rows.len(),
+        fields,
+    })
+}
+
+impl LocalIndex {
+    /// Sample statistics for the query planner now, under the configured
+    /// [`PlannerStatsConfig`] or the default one. Queries estimate their
+    /// filters from these until they are sampled again.
+    pub async fn analyze(&self) -> Result<PlannerStats> {
+        let config = self
+            .runtime_config
+            .lock()
+            .unwrap()
+            .planner_stats
+            .clone()
+            .unwrap_or_default();
+        let storage = self.storage.read_for("analyze").await;
+        let stats = self.sample_planner_stats(storage.as_ref(), &config).await?;
+        Ok(stats.as_ref().clone())
+    }
+
+    /// Statistics the planner last sampled, if any
+    pub fn planner_stats(&self) -> Option<PlannerStats> {
+        self.planner_stats.lock().unwrap().stats.as_deref().cloned()
+    }
+
+    async fn sample_planner_stats(
+        &self,
+        storage: &dyn StorageBackend,
+        config: &PlannerStatsConfig,
+    ) -> Result<Arc<PlannerStats>> {
+        let mut fields: Vec<String> = storage.namespace_field().await?.into_iter().collect();
+        fields.extend(storage.range_fields().await?);
+        for index in storage.composite_indexes().await? {
+            fields.extend(index.fields);
+        }
+        fields.extend(config.fields.iter().cloned());
+        fields.sort();
+        fields.dedup();
+
+        let items = storage.get_stats().await?.items;
+        let stats = Arc::new(sample(storage, fields, items, config.sample_size).await?);
+        *self.planner_stats.lock().unwrap() = StatsTracker {
+            stats: Some(stats.clone()),
+            writes: 0,
+        };
+        Ok(stats)
+    }
+
+    /// Items of the index's `items` the planner statistics expect `filter`
+    /// to pass, sampling them first when the runtime config asks for it and
+    /// they are missing or stale. `None` without statistics covering it.
+    pub(crate) async fn estimate_matches(
+        &self,
+        storage: &dyn StorageBackend,
+        filter: Option<&Value>,
+        items: usize,
+    ) -> Result<Option<usize>> {
+        let Some(filter) = filter else {
+            return Ok(None);
+        };
+        let config = self.runtime_config.lock().unwrap().planner_stats.clone();
+        let (stats, due) = {
+            let tracker = self.planner_stats.lock().unwrap();
+            let due = match (&tracker.stats, &config) {
+                (None, config) => config.is_some(),
+                (Some(stats), Some(config)) => {
+                    tracker.writes as f64 >= config.refresh_ratio * stats.items.max(1) as f64
+                }
+                (Some(_), None) => false,
+            };
+            (tracker.stats.clone(), due)
+        };
+        let stats = match (stats, config) {
+            (_, Some(config)) if due => self.sample_planner_stats(storage, &config).await?,
+            (Some(stats), _) => stats,
+            (None, _) => return Ok(None),
+        };
+        Ok(stats
+            .selectivity(filter)
+            .map(|share| (share * items as f64).round() as usize))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CandidateSource;
+    use tempfile::TempDir;
+
+    fn stats() -> PlannerStats {
+        // 100 items: tenants a (60), b (30), c (10); scores 0..100, half
+        // of them missing a flag
+        let metadata: Vec<Value> = (0..100)
+            .map(|i| {
+                let tenant = match i {
+                    0..60 => "a",
+                    60..90 => "b",
+                    _ => "c",
+                };
+                let mut metadata = serde_json::json!({ "tenant": tenant, "score": i });
+                if i % 2 == 0 {
+                    metadata["flag"] = true.into();
+                }
+                metadata
+            })
+            .collect();
+        let fields = ["flag", "score", "tenant"].map(|field| {
+            let values: Vec<&Value> = metadata
+                .iter()
+                .map(|m| m.get(field).unwrap_or(&Value::Null))
+                .collect();
+            (
+                field.to_string(),
+                FieldStats::from_values(&values, 100, 100),
+            )
+        });
+        PlannerStats {
+            items: 100,
+            sampled: 100,
+            fields: fields.into_iter().collect(),
+        }
+    }
+
+    #[test]
+    fn test_selectivity() {
+        let stats = stats();
+        let estimate = |filter: Value| stats.selectivity(&filter).unwrap();
+        let close = |estimate: f64, expected: f64| {
+            assert!(
+                (estimate - expected).abs() < 0.02,
+                "{} vs {}",
+                estimate,
+                expected
+            );
+        };
+        assert_eq!(stats.fields["tenant"].distinct, 3);
+        assert_eq!(stats.fields["score"].distinct, 100);
+
+        close(estimate(serde_json::json!({ "tenant": "a" })), 0.6);
+        close(
+            estimate(serde_json::json!({ "tenant": { "$ne": "a" } })),
+            0.4,
+        );
+        close(
+            estimate(serde_json::json!({ "tenant": { "$in": ["b", "c"] } })),
+            0.4,
+        );
+        close(estimate(serde_json::json!({ "tenant": "z" })), 0.0);
+        close(
+            estimate(serde_json::json!({ "score": { "$gte": 25, "$lt": 75 } })),
+            0.5,
+        );
+        close(
+            estimate(serde_json::json!({ "score": { "$gt": 1000 } })),
+            0.0,
+        );
+        close(
+            estimate(serde_json::json!({ "flag": { "$exists": true } })),
+            0.5,
+        );
+        // Clauses combine as if independent
+        close(
+            estimate(serde_json::json!({ "tenant": "b", "score": { "$lt": 50 } })),
+            0.15,
+        );
+        close(
+            estimate(serde_json::json!({ "$or": [{ "tenant": "b" }, { "tenant": "c" }] })),
+            0.37,
+        );
+        // Fields without statistics don't narrow the estimate
+        close(
+            estimate(serde_json::json!({ "$and": [{ "tenant": "c" }, { "other": 1 }] })),
+            0.1,
+        );
+        assert!(stats
+            .selectivity(&serde_json::json!({ "other": 1 }))
+            .is_none());
+        assert!(stats
+            .selectivity(&serde_json::json!({ "$or": [{ "tenant": "c" }, { "other": 1 }] }))
+            .is_none());
+    }
+
+    #[test]
+    fn test_estimate_distinct() {
+        // Every sampled value unique: the population is likely all unique
+        assert_eq!(estimate_distinct(100, 100, 100, 10_000), 10_000);
+        // Every value repeated: the sample likely saw them all
+        assert_eq!(estimate_distinct(5, 0, 100, 10_000), 5);
+        assert_eq!(estimate_distinct(7, 7, 7, 7), 7);
+    }
+
+    #[cfg(feature = "rocksdb")]
+    #[tokio::test]
+    async fn test_planner_stats_sampling() {
+        let temp_dir = TempDir::new().unwrap();
+        let index = LocalIndex::new(temp_dir.path(), None).unwrap();
+        let config = CreateIndexConfig {
+            range_fields: vec!["score".to_string()],
+            ..Default::default()
+        };
+        index.create_index(Some(config)).await.unwrap();
+        let items: Vec<VectorItem> = (0..200)
+            .map(|i| VectorItem {
+                id: uuid::Uuid::from_u128(i),
+                vector: vec![1.0, i as f32],
+                metadata: serde_json::json!({ "score": i, "kind": if i < 50 { "a" } else { "b" } }),
+                ..Default::default()
+            })
+            .collect();
+        index.insert_items(items).await.unwrap();
+        let query = Query {
+            vector: Some(vec![1.0, 0.0]),
+            top_k: 5,
+            filter: Some(serde_json::json!({ "kind": "a" })),
+            ..Default::default()
+        };
+
+        // Off by default
+        let explanation = index.explain_query(&query).await.unwrap();
+        assert_eq!(explanation.estimated_matches, None);
+        assert!(index.planner_stats().is_none());
+
+        let mut config = index.runtime_config();
+        config.planner_stats = Some(PlannerStatsConfig {
+            sample_size: 100,
+            fields: vec!["kind".to_string()],
+            ..Default::default()
+        });
+        index.set_runtime_config(config).await.unwrap();
+        let explanation = index.explain_query(&query).await.unwrap();
+        let stats = index.planner_stats().unwrap();
+        assert_eq!((stats.items, stats.sampled), (200, 100));
+        assert_eq!(stats.fields["score"].numbers, 100);
+        assert_eq!(stats.fields["kind"].distinct, 2);
+        assert_eq!(explanation.estimated_matches, Some(50));
+        assert_eq!(explanation.candidate_source, CandidateSource::Scan);
+
+        // Sampled again once a fifth of the index was written
+        let more: Vec<VectorItem> = (1000..1040)
+            .map(|i| VectorItem {
+                id: uuid::Uuid::from_u128(i),
+                vector: vec![1.0, 0.0],
+                metadata: serde_json::json!({ "kind": "a" }),
+                ..Default::default()
+            })
+            .collect();
+        index.insert_items(more).await.unwrap();
+        let explanation = index.explain_query(&query).await.unwrap();
+        assert_eq!(index.planner_stats().unwrap().items, 240);
+        assert_eq!(explanation.estimated_matches, Some(90));
+
+        // Sampled on demand, and used without the runtime config
+        let mut config = index.runtime_config();
+        config.planner_stats = None;
+        index.set_runtime_config(config).await.unwrap();
+        let stats = index.analyze().await.unwrap();
+        assert_eq!(stats.sampled, 240);
+        assert!(!stats.fields.contains_key("kind"));
+        let explanation = index
+            .explain_query(&Query {
+                filter: Some(serde_json::json!({ "score": { "$lt": 100 } })),
+                ..query
+            })
+            .await
+            .unwrap();
+        assert!(explanation.estimated_matches.unwrap().abs_diff(100) <= 10);
+    }
+
+    #[cfg(feature = "ann")]
+    #[tokio::test]
+    async fn test_broad_filters_post_filter_the_graph() {
+        let temp_dir = TempDir::new().unwrap();
+        let index = LocalIndex::new(temp_dir.path(), None).unwrap();
+        index.create_index(None).await.unwrap();
+        let items: Vec<VectorItem> = (0..300)
+            .map(|i| {
+                let angle = i as f32 * 0.7;
+                VectorItem {
+                    id: uuid::Uuid::from_u128(i + 1),
+                    vector: vec![angle.cos(), angle.sin(), (i % 13) as f32 / 13.0],
+                    metadata: serde_json::json!({ "group": i % 20 }),
+                    ..Default::default()
+                }
+            })
+            .collect();
+        index.insert_items(items.clone()).await.unwrap();
+        let mut config = index.runtime_config();
+        config.ann = Some(AnnConfig {
+            exact_threshold: 50,
+            ..Default::default()
+        });
+        config.planner_stats = Some(PlannerStatsConfig {
+            fields: vec!["group".to_string()],
+            ..Default::default()
+        });
+        index.set_runtime_config(config).await.unwrap();
+        let query = |filter: Value| Query {
+            vector: Some(items[150].vector.clone()),
+            top_k: 3,
+            filter: Some(filter),
+            ..Default::default()
+        };
+
+        // Most items pass, so the filter runs on the graph's shortlist
+        let explanation = index
+            .explain_query(&query(serde_json::json!({ "group": { "$ne": 3 } })))
+            .await
+            .unwrap();
+        assert_eq!(explanation.estimated_matches, Some(285));
+        assert_eq!(
+            explanation.candidate_source,
+            CandidateSource::GraphShortlist
+        );
+        assert_eq!(explanation.filter_mode, Some(crate::FilterMode::PostFilter));
+        assert!(explanation.candidates < 100);
+        assert_eq!(explanation.results[0].item.id, items[150].id);
+
+        // Few pass, so every item is filtered and the rest scored exactly
+        let explanation = index
+            .explain_query(&query(serde_json::json!({ "group": 10 })))
+            .await
+            .unwrap();
+        assert_eq!(explanation.estimated_matches, Some(15));
+        assert_eq!(explanation.candidate_source, CandidateSource::Scan);
+        assert_eq!(explanation.strategy, SearchStrategy::Exact);
+        assert_eq!(explanation.results[0].item.id, items[150].id);
+    }
+}
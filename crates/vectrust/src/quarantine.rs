@@ -0,0 +1,222 @@
+// Copyright 2024-2026 Andrey Vasilevsky <anvanster@gmail.com>
+// SPDX-License-Identifier: Apache-2.0
+
+//! Degraded opening of damaged indexes.
+//!
+//! A damaged vector record or metadata entry normally fails every read
+//! that touches it, which for scans and queries means every read.
+//! [`LocalIndex::open_degraded`] instead checks each item up front and
+//! quarantines the ones that can't be read back: they are left out of
+//! reads and reported by [`LocalIndex::health`], while all healthy items
+//! are served as usual. [`LocalIndex::repair_quarantined`] then restores
+//! them from a snapshot or drops them, one item at a time, so the index
+//! stays available while it runs.
+
+use crate::LocalIndex;
+use std::path::Path;
+use uuid::Uuid;
+use vectrust_core::*;
+
+/// What a repair did with each quarantined item
+#[derive(Debug, Clone, Default)]
+pub struct RepairReport {
+    /// Items rewritten with a healthy copy or a fresh vector
+    pub restored: Vec<Uuid>,
+    /// Items deleted because no healthy copy was available
+    pub dropped: Vec<Uuid>,
+}
+
+impl LocalIndex {
+    /// Open the index at `folder_path`, quarantining damaged items rather
+    /// than failing reads on them. Check [`health`](Self::health) for what
+    /// was left out.
+    pub async fn open_degraded<P: AsRef<Path>>(
+        folder_path: P,
+        index_name: Option<String>,
+    ) -> Result<Self> {
+        let index = Self::new(folder_path, index_name)?;
+        if index.is_index_created().await {
+            index.quarantine_damaged().await?;
+        }
+        Ok(index)
+    }
+
+    /// Check every item can be read back and quarantine those that can't,
+    /// returning the resulting health
+    pub async fn quarantine_damaged(&self) -> Result<IndexHealth> {
+        let storage = self.storage.read_for("repair").await;
+        let quarantined = storage.quarantine_damaged().await?;
+        if !quarantined.is_empty() {
+            tracing::warn!(
+                "{} damaged items in {} quarantined",
+                quarantined.len(),
+                self.path.display()
+            );
+            self.clear_query_cache();
+        }
+        Ok(IndexHealth {
+            quarantined,
+            ann_lag: self.ann_lag(),
+        })
+    }
+
+    /// Items left out of reads because they are damaged, and how far the
+    /// HNSW graph trails writes
+    pub async fn health(&self) -> Result<IndexHealth> {
+        let storage = self.storage.read_for("stats").await;
+        Ok(IndexHealth {
+            quarantined: storage.quarantined(),
+            ann_lag: self.ann_lag(),
+        })
+    }
+
+    /// Replace each quarantined item with its copy in `source`, such as an
+    /// index opened from a snapshot, deleting those it doesn't hold. Takes
+    /// the write lock once per item, so queries and writes carry on in
+    /// between; run it from a spawned task to repair in the background.
+    pub async fn repair_quarantined(&self, source: Option<&LocalIndex>) -> Result<RepairReport> {
+        let mut report = RepairReport::default();
+        for damaged in self.health().await?.quarantined {
+            let copy = match source {
+                Some(source) => source.get_item(&damaged.id).await?,
+                None => None,
+            };
+            // The damaged record goes first so the copy is counted once
+            self.remove_item(&damaged.id).await?;
+            match copy {
+                Some(item) => {
+                    self.put_items(vec![item]).await?;
+                    report.restored.push(damaged.id);
+                }
+                None => report.dropped.push(damaged.id),
+            }
+        }
+        Ok(report)
+    }
+}
+
+#[cfg(all(test, feature = "rocksdb"))]
+mod tests {
+    use super::*;
+    use std::io::{Seek, SeekFrom, Write};
+    use tempfile::TempDir;
+
+    fn item(x: f32) -> VectorItem {
+        VectorItem {
+            vector: vec![x, 1.0],
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_degraded_open_and_repair() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("index");
+        let index = LocalIndex::new(&path, None).unwrap();
+        index.create_index(None).await.unwrap();
+        // The first vector sits at the start of the vector file
+        let first = index.insert_item(item(1.0)).await.unwrap();
+        index
+            .insert_items(vec![item(2.0), item(3.0)])
+            .await
+            .unwrap();
+        let snapshot = index
+            .snapshot(temp_dir.path().join("snapshots"))
+            .await
+            .unwrap();
+        drop(index);
+
+        // Overwrite the first vector's dimension header
+        let mut file = std::fs::OpenOptions::new()
+            .write(true)
+            .open(path.join("vectors.dat"))
+            .unwrap();
+        file.seek(SeekFrom::Start(0)).unwrap();
+        file.write_all(&999u64.to_le_bytes()).unwrap();
+        drop(file);
+
+        let strict = LocalIndex::new(&path, None).unwrap();
+        assert!(strict.list_items(None).await.is_err());
+        drop(strict);
+
+        let index = LocalIndex::open_degraded(&path, None).await.unwrap();
+        let health = index.health().await.unwrap();
+        assert!(health.is_degraded());
+        assert_eq!(health.quarantined.len(), 1);
+        assert_eq!(health.quarantined[0].id, first.id);
+        assert_eq!(index.list_items(None).await.unwrap().len(), 2);
+        assert!(index.get_item(&first.id).await.unwrap().is_none());
+        let results = index
+            .query_items(vec![1.0, 1.0], Some(5), None)
+            .await
+            .unwrap();
+        assert_eq!(results.len(), 2);
+        assert!(index.compact(None).await.is_err());
+
+        let backup = LocalIndex::new(&snapshot, None).unwrap();
+        let report = index.repair_quarantined(Some(&backup)).await.unwrap();
+        assert_eq!(report.restored, vec![first.id]);
+        assert!(report.dropped.is_empty());
+        assert!(!index.health().await.unwrap().is_degraded());
+        assert_eq!(
+            index.get_item(&first.id).await.unwrap().unwrap().vector,
+            first.vector
+        );
+        assert_eq!(index.get_stats().await.unwrap().items, 3);
+    }
+
+    #[tokio::test]
+    async fn test_open_checks_integrity() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("index");
+        let index = LocalIndex::new(&path, None).unwrap();
+        index.create_index(None).await.unwrap();
+        index
+            .insert_items((0..4).map(|i| item(i as f32)).collect())
+            .await
+            .unwrap();
+        drop(index);
+
+        // Cut the vector file off part way through the third vector
+        let file = std::fs::OpenOptions::new()
+            .write(true)
+            .open(path.join("vectors.dat"))
+            .unwrap();
+        file.set_len(40).unwrap();
+        drop(file);
+
+        let strict = LocalIndex::new(&path, None).unwrap();
+        let error = strict.list_items(None).await.unwrap_err();
+        assert!(matches!(error, VectraError::IndexDamaged { .. }));
+        assert!(error.to_string().contains("verify"));
+        drop(strict);
+
+        // A degraded open finds the items it cut off, and once they are
+        // dropped and compacted away the index opens again
+        let index = LocalIndex::open_degraded(&path, None).await.unwrap();
+        assert_eq!(index.health().await.unwrap().quarantined.len(), 2);
+        index.repair_quarantined(None).await.unwrap();
+        index.compact(None).await.unwrap();
+        drop(index);
+        let index = LocalIndex::new(&path, None).unwrap();
+        assert_eq!(index.list_items(None).await.unwrap().len(), 2);
+        drop(index);
+
+        // A lost metadata store isn't quietly recreated empty
+        let metadata = temp_dir.path().join("metadata");
+        std::fs::rename(path.join("metadata"), &metadata).unwrap();
+        let strict = LocalIndex::new(&path, None).unwrap();
+        assert!(matches!(
+            strict.list_items(None).await,
+            Err(VectraError::IndexDamaged { .. })
+        ));
+        drop(strict);
+        std::fs::rename(&metadata, path.join("metadata")).unwrap();
+
+        std::fs::write(path.join("manifest.json"), "{ truncated").unwrap();
+        assert!(matches!(
+            LocalIndex::new(&path, None),
+            Err(VectraError::IndexDamaged { .. })
+        ));
+    }
+}
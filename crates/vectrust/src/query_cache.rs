@@ -0,0 +1,173 @@
+// Copyright 2024-2026 Andrey Vasilevsky <anvanster@gmail.com>
+// SPDX-License-Identifier: Apache-2.0
+
+//! Cache of query results.
+//!
+//! Entries are keyed by the exact query vector and the remaining query
+//! parameters. A write drops only the entries it could affect: those whose
+//! results include the written item, and those whose filter the new
+//! version matches. Callers must insert and invalidate while holding the
+//! storage lock, so a result computed before a write is never cached after it.
+
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
+use vectrust_core::*;
+use vectrust_query::MetadataFilter;
+
+#[derive(Clone, PartialEq, Eq, Hash)]
+struct CacheKey {
+    /// Bit patterns, since floats can't be hashed
+    vector: Vec<u32>,
+    /// Text, filter, top-k, boosts, recency, page, score floor and
+    /// freshness serialized as JSON
+    params: String,
+}
+
+impl CacheKey {
+    fn of(query: &Query) -> Option<Self> {
+        let vector = query.vector.iter().flatten().map(|x| x.to_bits()).collect();
+        let params = serde_json::to_string(&(
+            &query.text,
+            &query.filter,
+            query.top_k,
+            &query.boosts,
+            &query.recency,
+            query.offset,
+            &query.search_after,
+            query.min_score,
+            query.freshness,
+        ))
+        .ok()?;
+        Some(Self { vector, params })
+    }
+}
+
+struct CacheEntry {
+    results: Vec<QueryResult>,
+    filter: Option<serde_json::Value>,
+    ids: HashSet<uuid::Uuid>,
+    /// Whether the results start at an offset, which any deletion can shift
+    offset: bool,
+    expires: Instant,
+}
+
+pub(crate) struct QueryCache {
+    config: QueryCacheConfig,
+    entries: HashMap<CacheKey, CacheEntry>,
+}
+
+impl QueryCache {
+    pub(crate) fn new(config: QueryCacheConfig) -> Self {
+        Self {
+            config,
+            entries: HashMap::new(),
+        }
+    }
+
+    pub(crate) fn config(&self) -> &QueryCacheConfig {
+        &self.config
+    }
+
+    pub(crate) fn get(&mut self, query: &Query) -> Option<Vec<QueryResult>> {
+        let key = CacheKey::of(query)?;
+        let entry = self.entries.get(&key)?;
+        if entry.expires <= Instant::now() {
+            self.entries.remove(&key);
+            return None;
+        }
+        Some(entry.results.clone())
+    }
+
+    pub(crate) fn insert(&mut self, query: &Query, results: &[QueryResult]) {
+        let Some(key) = CacheKey::of(query) else {
+            return;
+        };
+        if self.config.max_entries == 0 {
+            return;
+        }
+        let now = Instant::now();
+        if self.entries.len() >= self.config.max_entries && !self.entries.contains_key(&key) {
+            self.entries.retain(|_, entry| entry.expires > now);
+            // Still full: evict the entry closest to expiring, which is the oldest
+            if self.entries.len() >= self.config.max_entries {
+                let oldest = self
+                    .entries
+                    .iter()
+                    .min_by_key(|(_, entry)| entry.expires)
+                    .map(|(key, _)| key.clone());
+                if let Some(oldest) = oldest {
+                    self.entries.remove(&oldest);
+                }
+            }
+        }
+
+        self.entries.insert(
+            key,
+            CacheEntry {
+                results: results.to_vec(),
+                filter: query.filter.clone(),
+                ids: results.iter().map(|r| r.item.id).collect(),
+                offset: query.offset > 0,
+                expires: now + Duration::from_secs(self.config.ttl_secs),
+            },
+        );
+    }
+
+    /// Drop entries that `items`, just written, could change
+    pub(crate) fn invalidate_written(&mut self, items: &[VectorItem]) {
+        self.entries.retain(|_, entry| {
+            !items.iter().any(|item| {
+                entry.ids.contains(&item.id)
+                    || entry
+                        .filter
+                        .as_ref()
+                        .is_none_or(|filter| MetadataFilter::matches(item, filter))
+            })
+        });
+    }
+
+    /// Drop entries that returned the deleted item `id` or are offset pages
+    pub(crate) fn invalidate_removed(&mut self, id: &uuid::Uuid) {
+        self.entries
+            .retain(|_, entry| !entry.offset && !entry.ids.contains(id));
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn query(x: f32) -> Query {
+        Query {
+            vector: Some(vec![x]),
+            top_k: 1,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_eviction_and_expiry() {
+        let mut cache = QueryCache::new(QueryCacheConfig {
+            max_entries: 2,
+            ttl_secs: 60,
+        });
+        for x in [1.0, 2.0, 3.0] {
+            cache.insert(&query(x), &[]);
+        }
+        assert_eq!(cache.entries.len(), 2);
+        assert!(cache.get(&query(1.0)).is_none());
+        assert!(cache.get(&query(3.0)).is_some());
+
+        let mut expired = QueryCache::new(QueryCacheConfig {
+            max_entries: 2,
+            ttl_secs: 0,
+        });
+        expired.insert(&query(1.0), &[]);
+        assert!(expired.get(&query(1.0)).is_none());
+        assert!(expired.entries.is_empty());
+    }
+}
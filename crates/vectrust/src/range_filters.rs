@@ -0,0 +1,173 @@
+// Copyright 2024-2026 Andrey Vasilevsky <anvanster@gmail.com>
+// SPDX-License-Identifier: Apache-2.0
+
+//! Range filters answered from the storage's range index.
+//!
+//! Indexes created with `range_fields` keep those numeric fields, such as
+//! scores or timestamps, in an ordered index. A filter comparing one of
+//! them with numbers at its top level, or in a top-level `$and`, then reads
+//! only the items between the bounds instead of testing every item's
+//! metadata. The whole filter still runs on the items read.
+
+use crate::LocalIndex;
+use serde_json::Value;
+use std::ops::Bound;
+use vectrust_core::*;
+
+/// Bounds a filter's numeric comparisons put on each field, in the order
+/// the fields first appear
+pub(crate) fn ranges_in_filter(filter: &Value) -> Vec<(String, NumericRange)> {
+    let clauses = filter.as_object().into_iter().flatten().chain(
+        filter
+            .get("$and")
+            .and_then(|and| and.as_array())
+            .into_iter()
+            .flatten()
+            .filter_map(|clause| clause.as_object())
+            .flatten(),
+    );
+    let mut ranges: Vec<(String, NumericRange)> = Vec::new();
+    for (field, condition) in clauses.filter(|(key, _)| !key.starts_with('$')) {
+        let Some(range) = condition_range(condition) else {
+            continue;
+        };
+        match ranges.iter_mut().find(|(known, _)| known == field) {
+            Some((_, known)) => *known = known.intersect(range),
+            None => ranges.push((field.clone(), range)),
+        }
+    }
+    ranges
+}
+
+/// Bounds one field's condition puts on it, if any are numeric
+fn condition_range(condition: &Value) -> Option<NumericRange> {
+    if let Some(value) = condition.as_f64() {
+        return Some(NumericRange {
+            min: Bound::Included(value),
+            max: Bound::Included(value),
+        });
+    }
+    let mut range = None;
+    for (op, operand) in condition.as_object()? {
+        let Some(value) = operand.as_f64() else {
+            continue;
+        };
+        let (min, max) = match op.as_str() {
+            "$eq" => (Bound::Included(value), Bound::Included(value)),
+            "$gt" => (Bound::Excluded(value), Bound::Unbounded),
+            "$gte" => (Bound::Included(value), Bound::Unbounded),
+            "$lt" => (Bound::Unbounded, Bound::Excluded(value)),
+            "$lte" => (Bound::Unbounded, Bound::Included(value)),
+            _ => continue,
+        };
+        let bound = NumericRange { min, max };
+        range = Some(range.map_or(bound, |range: NumericRange| range.intersect(bound)));
+    }
+    range
+}
+
+impl LocalIndex {
+    /// Items the storage's range index holds between the bounds `filter`
+    /// puts on a range field, or `None` when it bounds none
+    pub(crate) async fn range_candidates(
+        storage: &dyn StorageBackend,
+        filter: &Value,
+    ) -> Result<Option<Vec<VectorItem>>> {
+        for (field, range) in ranges_in_filter(filter) {
+            if let Some(items) = storage.list_range(&field, &range).await? {
+                return Ok(Some(items));
+            }
+        }
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "rocksdb")]
+    use crate::CandidateSource;
+    #[cfg(feature = "rocksdb")]
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_ranges_in_filter() {
+        let ranges = ranges_in_filter(&serde_json::json!({
+            "score": {"$gte": 0.5, "$lt": 0.9},
+            "tenant": "acme",
+            "$and": [{"score": {"$gt": 0.5}}, {"year": 2024}, {"name": {"$gt": "m"}}]
+        }));
+        assert_eq!(
+            ranges,
+            vec![
+                (
+                    "score".to_string(),
+                    NumericRange {
+                        min: Bound::Excluded(0.5),
+                        max: Bound::Excluded(0.9),
+                    }
+                ),
+                (
+                    "year".to_string(),
+                    NumericRange {
+                        min: Bound::Included(2024.0),
+                        max: Bound::Included(2024.0),
+                    }
+                ),
+            ]
+        );
+        assert!(ranges_in_filter(&serde_json::json!({"$or": [{"score": 1}]})).is_empty());
+    }
+
+    #[cfg(feature = "rocksdb")]
+    #[tokio::test]
+    async fn test_range_filters_read_range_index() {
+        let temp_dir = TempDir::new().unwrap();
+        let index = LocalIndex::new(temp_dir.path(), None).unwrap();
+        let config = CreateIndexConfig {
+            range_fields: vec!["score".to_string()],
+            ..Default::default()
+        };
+        index.create_index(Some(config)).await.unwrap();
+        let items: Vec<VectorItem> = [0.1, 0.5, 0.7, 0.9]
+            .iter()
+            .map(|score| VectorItem {
+                vector: vec![*score, 1.0],
+                metadata: serde_json::json!({ "score": score, "kind": "doc" }),
+                ..Default::default()
+            })
+            .collect();
+        index.insert_items(items).await.unwrap();
+        let filter = serde_json::json!({ "score": { "$gt": 0.3, "$lte": 0.7 } });
+
+        let listed = index
+            .list_items(Some(ListOptions {
+                limit: None,
+                offset: None,
+                filter: Some(filter.clone()),
+            }))
+            .await
+            .unwrap();
+        assert_eq!(listed.len(), 2);
+
+        let query = Query {
+            vector: Some(vec![1.0, 1.0]),
+            top_k: 10,
+            filter: Some(filter),
+            ..Default::default()
+        };
+        let explanation = index.explain_query(&query).await.unwrap();
+        assert_eq!(explanation.candidate_source, CandidateSource::RangeIndex);
+        assert_eq!(explanation.candidates, 2);
+        assert_eq!(explanation.results.len(), 2);
+
+        // Fields without a range index are scanned
+        let query = Query {
+            filter: Some(serde_json::json!({ "kind": "doc" })),
+            ..query
+        };
+        let explanation = index.explain_query(&query).await.unwrap();
+        assert_eq!(explanation.candidate_source, CandidateSource::Scan);
+        assert_eq!(explanation.results.len(), 4);
+    }
+}
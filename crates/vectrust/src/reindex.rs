@@ -0,0 +1,357 @@
+// Copyright 2024-2026 Andrey Vasilevsky <anvanster@gmail.com>
+// SPDX-License-Identifier: Apache-2.0
+
+//! Re-embedding an aliased index with a new model.
+//!
+//! [`IndexAliases::reindex`] creates the target index, turns on dual-write
+//! so the live handle forwards new writes to it, backfills every existing
+//! item through the embedding function, and finally switches the alias.
+
+use crate::{IndexAliases, LocalIndex};
+use std::collections::HashSet;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use vectrust_core::*;
+
+/// Computes vectors for the target index, one per item, in order
+pub trait EmbeddingFn: Send + Sync {
+    fn embed(&self, items: &[VectorItem]) -> Result<Vec<Vec<f32>>>;
+}
+
+impl<F> EmbeddingFn for F
+where
+    F: Fn(&[VectorItem]) -> Result<Vec<Vec<f32>>> + Send + Sync,
+{
+    fn embed(&self, items: &[VectorItem]) -> Result<Vec<Vec<f32>>> {
+        self(items)
+    }
+}
+
+/// Outcome of a completed [`IndexAliases::reindex`]
+pub struct Reindexed {
+    /// Handle to the new index; use it instead of reopening the alias while
+    /// the old handle is still dual-writing into it
+    pub index: Arc<LocalIndex>,
+    /// Items copied by the backfill
+    pub copied: usize,
+    /// Items the backfill left alone because a newer write had reached the target
+    pub skipped: usize,
+}
+
+/// Forwards writes from a live index into its replacement
+pub(crate) struct DualWrite {
+    target: Arc<LocalIndex>,
+    embedder: Arc<dyn EmbeddingFn>,
+    /// Ids written or deleted through dual-write. The backfill's copy of
+    /// these is older, so it must not overwrite them. Holding the lock
+    /// across target writes orders backfill batches and forwarded writes.
+    touched: tokio::sync::Mutex<HashSet<uuid::Uuid>>,
+    failures: AtomicUsize,
+}
+
+impl DualWrite {
+    pub(crate) async fn forward_items(&self, items: &[VectorItem]) {
+        let result = async {
+            let model = self.target.embedding_model().await?;
+            let items = embed_items(self.embedder.as_ref(), model.as_ref(), items)?;
+            let mut touched = self.touched.lock().await;
+            touched.extend(items.iter().map(|item| item.id));
+            self.target.put_items(items).await
+        }
+        .await;
+        if result.is_err() {
+            self.failures.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    pub(crate) async fn forward_delete(&self, id: &uuid::Uuid) {
+        let mut touched = self.touched.lock().await;
+        touched.insert(*id);
+        if self.target.remove_item(id).await.is_err() {
+            self.failures.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Copy a backfill batch, skipping items dual-write already handled
+    async fn backfill(&self, items: &[VectorItem]) -> Result<usize> {
+        let model = self.target.embedding_model().await?;
+        let items = embed_items(self.embedder.as_ref(), model.as_ref(), items)?;
+        let touched = self.touched.lock().await;
+        let fresh: Vec<VectorItem> = items
+            .into_iter()
+            .filter(|item| !touched.contains(&item.id))
+            .collect();
+        let copied = fresh.len();
+        self.target.put_items(fresh).await?;
+        Ok(copied)
+    }
+
+    pub(crate) fn failures(&self) -> usize {
+        self.failures.load(Ordering::Relaxed)
+    }
+}
+
+/// Re-embed `items`, moving any model tag over to the target's model so
+/// the target doesn't reject them as mismatched
+pub(crate) fn embed_items(
+    embedder: &dyn EmbeddingFn,
+    model: Option<&EmbeddingModel>,
+    items: &[VectorItem],
+) -> Result<Vec<VectorItem>> {
+    let vectors = embedder.embed(items)?;
+    if vectors.len() != items.len() {
+        return Err(VectraError::VectorValidation {
+            message: format!(
+                "Embedding function returned {} vectors for {} items",
+                vectors.len(),
+                items.len()
+            ),
+        });
+    }
+    Ok(items
+        .iter()
+        .zip(vectors)
+        .map(|(item, vector)| {
+            let mut item = VectorItem {
+                vector,
+                ..item.clone()
+            };
+            if let (Some(model), Some(metadata)) = (model, item.metadata.as_object_mut()) {
+                if metadata.contains_key(EMBEDDING_MODEL_KEY) {
+                    metadata.insert(EMBEDDING_MODEL_KEY.to_string(), model.name.clone().into());
+                }
+            }
+            item
+        })
+        .collect())
+}
+
+impl LocalIndex {
+    /// Forward every later insert, update and delete on this handle to
+    /// `target`, re-embedded with `embedder`.
+    ///
+    /// Writes to this index never fail because of the target; forwarding
+    /// failures are counted and reported by [`stop_dual_write`](Self::stop_dual_write).
+    pub fn start_dual_write(&self, target: Arc<LocalIndex>, embedder: Arc<dyn EmbeddingFn>) {
+        *self.dual_write.lock().unwrap() = Some(Arc::new(DualWrite {
+            target,
+            embedder,
+            touched: tokio::sync::Mutex::new(HashSet::new()),
+            failures: AtomicUsize::new(0),
+        }));
+    }
+
+    /// Stop forwarding writes. Errors if any forwarded write failed, since
+    /// the target then misses changes made to this index.
+    pub fn stop_dual_write(&self) -> Result<()> {
+        match self.dual_write.lock().unwrap().take() {
+            Some(dual) if dual.failures() > 0 => Err(VectraError::Storage {
+                message: format!("{} dual writes failed to reach the target", dual.failures()),
+            }),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl IndexAliases {
+    /// Re-embed the index behind `alias` into a new index at `target` and
+    /// switch the alias to it.
+    ///
+    /// `source` must be the live handle for the alias: it dual-writes into
+    /// the new index from the start of the backfill, and keeps doing so
+    /// after the switch so writers that haven't moved over yet are not
+    /// lost. Call [`LocalIndex::stop_dual_write`] once they have. If the
+    /// backfill fails the alias is left unchanged and dual-write is stopped.
+    pub async fn reindex<P: AsRef<Path>>(
+        &self,
+        alias: &str,
+        source: &LocalIndex,
+        target: P,
+        config: CreateIndexConfig,
+        embedder: Arc<dyn EmbeddingFn>,
+        batch_size: usize,
+    ) -> Result<Reindexed> {
+        let current = self
+            .resolve(alias)?
+            .ok_or_else(|| VectraError::IndexNotFound {
+                path: format!("alias '{}'", alias),
+            })?;
+        if !same_dir(&current, &source.path) {
+            return Err(VectraError::Storage {
+                message: format!(
+                    "Alias '{}' points at {:?}, not the given source {:?}",
+                    alias, current, source.path
+                ),
+            });
+        }
+
+        let target = target.as_ref();
+        let index = Arc::new(LocalIndex::new(self.root().join(target), None)?);
+        if index.is_index_created().await {
+            return Err(VectraError::IndexAlreadyExists {
+                path: target.to_string_lossy().to_string(),
+            });
+        }
+        index.create_index(Some(config)).await?;
+
+        source.start_dual_write(index.clone(), embedder);
+        match backfill(source, batch_size.max(1)).await {
+            Ok((copied, skipped)) => {
+                index.end_update().await?;
+                self.set(alias, target)?;
+                Ok(Reindexed {
+                    index,
+                    copied,
+                    skipped,
+                })
+            }
+            Err(e) => {
+                let _ = source.stop_dual_write();
+                Err(e)
+            }
+        }
+    }
+}
+
+async fn backfill(source: &LocalIndex, batch_size: usize) -> Result<(usize, usize)> {
+    let dual = source
+        .dual_write
+        .lock()
+        .unwrap()
+        .clone()
+        .expect("dual-write started by reindex");
+
+    // Snapshot ids up front: offset paging would skip items whenever a
+    // concurrent delete shifts the listing. Items inserted after the
+    // snapshot arrive through dual-write instead.
+    let ids: Vec<uuid::Uuid> = source
+        .list_items(None)
+        .await?
+        .into_iter()
+        .map(|item| item.id)
+        .collect();
+
+    let (mut copied, mut skipped) = (0, 0);
+    for chunk in ids.chunks(batch_size) {
+        let mut items = Vec::with_capacity(chunk.len());
+        for id in chunk {
+            // Deleted since the snapshot
+            if let Some(item) = source.get_item(id).await? {
+                items.push(item);
+            }
+        }
+        let batch = items.len();
+        let written = dual.backfill(&items).await?;
+        copied += written;
+        skipped += batch - written;
+    }
+
+    if dual.failures() > 0 {
+        return Err(VectraError::Storage {
+            message: format!("{} dual writes failed during the backfill", dual.failures()),
+        });
+    }
+    Ok((copied, skipped))
+}
+
+fn same_dir(a: &Path, b: &Path) -> bool {
+    match (a.canonicalize(), b.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_reindex_with_dual_write() {
+        let root = TempDir::new().unwrap();
+        let aliases = IndexAliases::new(root.path());
+        let source = LocalIndex::new(root.path().join("v1"), None).unwrap();
+        source.create_index(None).await.unwrap();
+        aliases.set("docs", "v1").unwrap();
+
+        let mut ids = Vec::new();
+        for i in 0..10 {
+            let item = VectorItem {
+                vector: vec![i as f32, 1.0],
+                metadata: serde_json::json!({ "n": i }),
+                ..Default::default()
+            };
+            ids.push(source.insert_item(item).await.unwrap().id);
+        }
+
+        // New model: three dimensions derived from the metadata
+        let embedder: Arc<dyn EmbeddingFn> = Arc::new(|items: &[VectorItem]| {
+            Ok(items
+                .iter()
+                .map(|item| {
+                    let n = item.metadata["n"].as_f64().unwrap_or(-1.0) as f32;
+                    vec![n, 0.0, 1.0]
+                })
+                .collect())
+        });
+
+        // A failing embedder leaves the alias where it was
+        let bad: Arc<dyn EmbeddingFn> = Arc::new(|_: &[VectorItem]| Ok(Vec::new()));
+        assert!(aliases
+            .reindex("docs", &source, "v2", CreateIndexConfig::default(), bad, 4)
+            .await
+            .is_err());
+        assert!(source.dual_write.lock().unwrap().is_none());
+        assert_eq!(
+            aliases.resolve("docs").unwrap(),
+            Some(root.path().join("v1"))
+        );
+
+        let reindexed = aliases
+            .reindex(
+                "docs",
+                &source,
+                "v3",
+                CreateIndexConfig::default(),
+                embedder,
+                4,
+            )
+            .await
+            .unwrap();
+        assert_eq!(reindexed.copied, 10);
+        assert_eq!(
+            aliases.resolve("docs").unwrap(),
+            Some(root.path().join("v3"))
+        );
+
+        let target = reindexed.index;
+        let item = target.get_item(&ids[3]).await.unwrap().unwrap();
+        assert_eq!(item.vector, vec![3.0, 0.0, 1.0]);
+
+        // Dual-write keeps running until stopped
+        let late = VectorItem {
+            vector: vec![0.5, 0.5],
+            metadata: serde_json::json!({ "n": 42 }),
+            ..Default::default()
+        };
+        let late = source.insert_item(late).await.unwrap();
+        source.delete_item(&ids[0]).await.unwrap();
+        source
+            .update_item(UpdateRequest {
+                id: ids[1],
+                vector: None,
+                metadata: Some(serde_json::json!({ "n": 7 })),
+            })
+            .await
+            .unwrap();
+        source.stop_dual_write().unwrap();
+
+        let moved = target.get_item(&late.id).await.unwrap().unwrap();
+        assert_eq!(moved.vector, vec![42.0, 0.0, 1.0]);
+        assert!(target.get_item(&ids[0]).await.unwrap().is_none());
+        let updated = target.get_item(&ids[1]).await.unwrap().unwrap();
+        assert_eq!(updated.vector, vec![7.0, 0.0, 1.0]);
+        assert_eq!(target.get_stats().await.unwrap().items, 10);
+    }
+}
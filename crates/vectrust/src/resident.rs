@@ -0,0 +1,109 @@
+// Copyright 2024-2026 Andrey Vasilevsky <anvanster@gmail.com>
+// SPDX-License-Identifier: Apache-2.0
+
+//! Opening small indexes with every item held in RAM.
+
+use crate::LocalIndex;
+use std::path::Path;
+use vectrust_core::*;
+use vectrust_storage::ResidentStorage;
+
+impl LocalIndex {
+    /// Open an index and, if its vectors and metadata take at most
+    /// `max_bytes`, load them all into memory so reads never touch disk.
+    /// Writes still persist through the index's storage before they are
+    /// applied to the copy. Larger indexes open as [`LocalIndex::new`]
+    /// would; the limit is checked only here, not as the index grows.
+    pub async fn open_in_memory<P: AsRef<Path>>(
+        folder_path: P,
+        index_name: Option<String>,
+        max_bytes: u64,
+    ) -> Result<Self> {
+        let path = folder_path.as_ref().to_path_buf();
+        let index_name = index_name.unwrap_or_else(|| "index.json".to_string());
+        let storage = vectrust_storage::Storage::auto_detect(&path, &index_name)?;
+
+        let storage: Box<dyn StorageBackend> = if !storage.exists().await {
+            Box::new(ResidentStorage::new(storage, Vec::new()))
+        } else {
+            // The vectors alone may rule the index out before reading it
+            let stats = storage.get_stats().await?;
+            let vector_bytes = stats.items as u64
+                * stats.dimensions.unwrap_or(0) as u64
+                * std::mem::size_of::<f32>() as u64;
+            let items = if vector_bytes <= max_bytes {
+                let items = storage.list_items(None).await?;
+                let size: u64 = items.iter().map(crate::approximate_size).sum();
+                (size <= max_bytes).then_some(items)
+            } else {
+                None
+            };
+            match items {
+                Some(items) => Box::new(ResidentStorage::new(storage, items)),
+                None => {
+                    tracing::warn!(
+                        "{} is larger than {} bytes; reading it from disk",
+                        path.display(),
+                        max_bytes
+                    );
+                    storage
+                }
+            }
+        };
+        Self::with_storage(path, index_name, storage)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_open_in_memory() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let index = LocalIndex::new(dir.path(), None).unwrap();
+        index.create_index(None).await.unwrap();
+        let items = (0..20)
+            .map(|i| VectorItem {
+                vector: vec![i as f32, 1.0, 0.0],
+                metadata: serde_json::json!({ "n": i }),
+                ..Default::default()
+            })
+            .collect();
+        index.insert_items(items).await.unwrap();
+        drop(index);
+
+        let index = LocalIndex::open_in_memory(dir.path(), None, 1 << 20)
+            .await
+            .unwrap();
+        let results = index
+            .query_items(
+                vec![7.0, 1.0, 0.0],
+                Some(3),
+                Some(serde_json::json!({ "n": 7 })),
+            )
+            .await
+            .unwrap();
+        assert_eq!(results[0].item.metadata["n"], 7);
+
+        // Writes persist and show up in reads straight away
+        let added = index
+            .insert_item(VectorItem {
+                vector: vec![-1.0, 0.0, 0.0],
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert!(index.get_item(&added.id).await.unwrap().is_some());
+        drop(index);
+        let reopened = LocalIndex::new(dir.path(), None).unwrap();
+        assert_eq!(reopened.list_items(None).await.unwrap().len(), 21);
+        drop(reopened);
+
+        // Too large for the limit: read from disk as usual
+        let index = LocalIndex::open_in_memory(dir.path(), None, 64)
+            .await
+            .unwrap();
+        assert_eq!(index.list_items(None).await.unwrap().len(), 21);
+    }
+}
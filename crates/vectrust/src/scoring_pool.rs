@@ -0,0 +1,81 @@
+// Copyright 2024-2026 Andrey Vasilevsky <anvanster@gmail.com>
+// SPDX-License-Identifier: Apache-2.0
+
+//! Thread pools for CPU-heavy query work.
+//!
+//! Scoring every candidate or walking an HNSW graph can take a runtime
+//! thread for long enough to stall the IO tasks queued behind it. Queries
+//! hand that work to a rayon pool instead and await its result, so the
+//! runtime thread is free in the meantime. Pools are shared by every
+//! index in the process that asks for the same number of threads.
+
+use std::collections::HashMap;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Arc, Mutex, OnceLock};
+use vectrust_core::*;
+
+static POOLS: OnceLock<Mutex<HashMap<usize, Arc<rayon::ThreadPool>>>> = OnceLock::new();
+
+/// The pool with `threads` threads, or one per core when unset
+fn pool(threads: Option<usize>) -> Result<Arc<rayon::ThreadPool>> {
+    let threads = threads.unwrap_or(0);
+    let mut pools = POOLS.get_or_init(Default::default).lock().unwrap();
+    if let Some(pool) = pools.get(&threads) {
+        return Ok(pool.clone());
+    }
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .thread_name(|i| format!("vectrust-scoring-{i}"))
+        .build()
+        .map_err(|e| VectraError::Query {
+            message: format!("Failed to start the scoring pool: {e}"),
+        })?;
+    let pool = Arc::new(pool);
+    pools.insert(threads, pool.clone());
+    Ok(pool)
+}
+
+/// Run `work` on the scoring pool and wait for it without blocking the
+/// calling task's thread. A panic in `work` resumes in the caller.
+pub(crate) async fn run<T, F>(threads: Option<usize>, work: F) -> Result<T>
+where
+    T: Send + 'static,
+    F: FnOnce() -> T + Send + 'static,
+{
+    let (sender, receiver) = tokio::sync::oneshot::channel();
+    pool(threads)?.spawn(move || {
+        let _ = sender.send(panic::catch_unwind(AssertUnwindSafe(work)));
+    });
+    match receiver.await {
+        Ok(Ok(value)) => Ok(value),
+        Ok(Err(payload)) => panic::resume_unwind(payload),
+        Err(_) => Err(VectraError::Query {
+            message: "Scoring pool dropped a query".to_string(),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_runs_off_the_runtime_thread() {
+        let caller = std::thread::current().id();
+        let name = run(Some(2), move || {
+            assert_ne!(std::thread::current().id(), caller);
+            std::thread::current().name().map(str::to_string)
+        })
+        .await
+        .unwrap();
+        assert!(name.unwrap().starts_with("vectrust-scoring-"));
+        assert!(Arc::ptr_eq(
+            &pool(Some(2)).unwrap(),
+            &pool(Some(2)).unwrap()
+        ));
+        assert_eq!(pool(Some(2)).unwrap().current_num_threads(), 2);
+
+        let panicked = tokio::spawn(run(Some(2), || -> usize { panic!("scoring failed") })).await;
+        assert!(panicked.unwrap_err().is_panic());
+    }
+}
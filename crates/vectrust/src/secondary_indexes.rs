@@ -0,0 +1,87 @@
+// Copyright 2024-2026 Andrey Vasilevsky <anvanster@gmail.com>
+// SPDX-License-Identifier: Apache-2.0
+
+//! Checking and rebuilding the storage's secondary indexes.
+//!
+//! Backends keep secondary indexes such as the RocksDB backend's namespace
+//! keys in the same write batch as the items they index, so a write lands
+//! in both or neither. Indexes written by older versions, or edited by
+//! hand, can still drift; [`LocalIndex::verify_secondary_indexes`] finds
+//! that and [`LocalIndex::rebuild_secondary_indexes`] repairs it.
+
+use crate::LocalIndex;
+use vectrust_core::*;
+
+impl LocalIndex {
+    /// Compare the storage's secondary indexes with its items
+    pub async fn verify_secondary_indexes(&self) -> Result<IndexDivergence> {
+        self.storage
+            .read_for("secondary_index")
+            .await
+            .verify_secondary_indexes()
+            .await
+    }
+
+    /// Rebuild the storage's secondary indexes from its items, along with
+    /// the namespace tallies and cached query results derived from them
+    pub async fn rebuild_secondary_indexes(&self) -> Result<()> {
+        let mut storage = self.storage.write_for("secondary_index").await;
+        storage.rebuild_secondary_indexes().await?;
+        self.namespaces.lock().unwrap().clear();
+        self.clear_query_cache();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_verify_and_rebuild_secondary_indexes() {
+        let temp_dir = TempDir::new().unwrap();
+        let index = LocalIndex::new(temp_dir.path(), None).unwrap();
+        let config = CreateIndexConfig {
+            namespace_field: Some("tenant".to_string()),
+            ..Default::default()
+        };
+        index.create_index(Some(config)).await.unwrap();
+        let items: Vec<VectorItem> = ["acme", "acme", "globex"]
+            .iter()
+            .enumerate()
+            .map(|(i, tenant)| VectorItem {
+                vector: vec![i as f32, 1.0],
+                metadata: serde_json::json!({ "tenant": tenant }),
+                ..Default::default()
+            })
+            .collect();
+        let items = index.insert_items(items).await.unwrap();
+        index
+            .update_item(UpdateRequest {
+                id: items[0].id,
+                vector: None,
+                metadata: Some(serde_json::json!({ "tenant": "globex" })),
+            })
+            .await
+            .unwrap();
+        index.delete_item(&items[2].id).await.unwrap();
+        assert!(index
+            .verify_secondary_indexes()
+            .await
+            .unwrap()
+            .is_consistent());
+
+        index.rebuild_secondary_indexes().await.unwrap();
+        assert!(index
+            .verify_secondary_indexes()
+            .await
+            .unwrap()
+            .is_consistent());
+        assert_eq!(
+            index.list_namespace("globex").await.unwrap()[0].id,
+            items[0].id
+        );
+        assert_eq!(index.get_stats().await.unwrap().namespaces["acme"].items, 1);
+    }
+}
@@ -0,0 +1,137 @@
+// Copyright 2024-2026 Andrey Vasilevsky <anvanster@gmail.com>
+// SPDX-License-Identifier: Apache-2.0
+
+//! Opening indexes whose storage is owned by one writer thread.
+
+use crate::LocalIndex;
+use std::ops::{Deref, DerefMut};
+use std::path::Path;
+use tokio::sync::{RwLockReadGuard, RwLockWriteGuard};
+use vectrust_core::*;
+use vectrust_storage::SingleWriter;
+
+/// Storage as a write of items holds it
+pub(crate) enum StorageWrite<'a> {
+    /// The backend, under the storage write lock
+    Locked(RwLockWriteGuard<'a, Box<dyn StorageBackend>>),
+    /// A handle queueing calls on the writer thread. The storage lock is
+    /// held shared, so such writes only wait for each other in the queue
+    /// while operations that need the index to themselves still exclude them.
+    Queued {
+        _shared: RwLockReadGuard<'a, Box<dyn StorageBackend>>,
+        writer: Box<dyn StorageBackend>,
+    },
+}
+
+impl Deref for StorageWrite<'_> {
+    type Target = Box<dyn StorageBackend>;
+
+    fn deref(&self) -> &Self::Target {
+        match self {
+            StorageWrite::Locked(storage) => storage,
+            StorageWrite::Queued { writer, .. } => writer,
+        }
+    }
+}
+
+impl DerefMut for StorageWrite<'_> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        match self {
+            StorageWrite::Locked(storage) => storage,
+            StorageWrite::Queued { writer, .. } => writer,
+        }
+    }
+}
+
+impl LocalIndex {
+    /// Open an index whose storage runs on a dedicated writer thread, as
+    /// [`SingleWriter`] describes: every storage call is sent there and
+    /// applied in order, so the vector file and metadata store have one
+    /// owner and never wait on their own locks. Inserts, updates and
+    /// deletes don't take the storage write lock either; they queue on the
+    /// writer, and reads queue behind writes sent before them.
+    ///
+    /// The checks a write makes before it is queued, such as
+    /// [`IndexLimits`], can race with other writes in flight, so
+    /// concurrent writers may take an index slightly past its limits.
+    pub fn open_single_writer<P: AsRef<Path>>(
+        folder_path: P,
+        index_name: Option<String>,
+    ) -> Result<Self> {
+        let path = folder_path.as_ref().to_path_buf();
+        let index_name = index_name.unwrap_or_else(|| "index.json".to_string());
+        let storage = vectrust_storage::Storage::auto_detect(&path, &index_name)?;
+        let writer = SingleWriter::new(storage)?;
+        let mut index = Self::with_storage(path, index_name, Box::new(writer.handle()))?;
+        index.writer = Some(writer);
+        Ok(index)
+    }
+
+    /// Storage for a write of items: a handle to the writer thread for an
+    /// index opened with [`LocalIndex::open_single_writer`], otherwise the
+    /// backend under the storage write lock
+    pub(crate) async fn write_storage(&self, operation: &'static str) -> StorageWrite<'_> {
+        match &self.writer {
+            Some(writer) => StorageWrite::Queued {
+                _shared: self.storage.read_for(operation).await,
+                writer: Box::new(writer.handle()),
+            },
+            None => StorageWrite::Locked(self.storage.write_for(operation).await),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_open_single_writer() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let index = Arc::new(LocalIndex::open_single_writer(dir.path(), None).unwrap());
+        index.create_index(None).await.unwrap();
+
+        let writers: Vec<_> = (0..4)
+            .map(|i| {
+                let index = index.clone();
+                tokio::spawn(async move {
+                    index
+                        .insert_item(VectorItem {
+                            vector: vec![i as f32, 1.0, 0.0],
+                            metadata: serde_json::json!({ "n": i }),
+                            ..Default::default()
+                        })
+                        .await
+                        .unwrap()
+                })
+            })
+            .collect();
+        let mut ids = Vec::new();
+        for writer in writers {
+            ids.push(writer.await.unwrap().id);
+        }
+
+        let results = index
+            .query_items(vec![2.0, 1.0, 0.0], Some(1), None)
+            .await
+            .unwrap();
+        assert_eq!(results[0].item.metadata["n"], 2);
+        assert_eq!(index.get_stats().await.unwrap().items, 4);
+
+        // Item writes only share the storage lock, so they don't wait for
+        // readers holding it
+        let shared = index.storage.read().await;
+        let deleted = index.delete_item(&ids[0]);
+        tokio::time::timeout(std::time::Duration::from_secs(10), deleted)
+            .await
+            .expect("the delete waited for the storage lock")
+            .unwrap();
+        drop(shared);
+
+        // The writer closes the index when dropped
+        drop(index);
+        let reopened = LocalIndex::new(dir.path(), None).unwrap();
+        assert_eq!(reopened.list_items(None).await.unwrap().len(), 3);
+    }
+}
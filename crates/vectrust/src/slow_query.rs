@@ -0,0 +1,144 @@
+// Copyright 2024-2026 Andrey Vasilevsky <anvanster@gmail.com>
+// SPDX-License-Identifier: Apache-2.0
+
+//! Slow-query log.
+//!
+//! [`LocalIndex::execute_query`](crate::LocalIndex::execute_query) times
+//! each stage of a query. When a query takes at least the configured
+//! threshold, its parameters and timings are reported as a `tracing`
+//! warning and optionally appended to a JSON-lines file.
+
+use crate::CandidateSource;
+use serde::Serialize;
+use std::io::Write;
+use std::path::Path;
+use std::time::Duration;
+use vectrust_core::*;
+
+/// Target of the `tracing` events, for routing them separately
+pub(crate) const TRACING_TARGET: &str = "vectrust::slow_query";
+
+/// Time spent in each stage of one query, with the item counts between them
+#[derive(Debug, Default)]
+pub(crate) struct QueryTimings {
+    /// Loading candidate items and their metadata from storage
+    pub hydrate: Duration,
+    /// Applying the metadata filter
+    pub filter: Duration,
+    /// Scoring, ranking and text fusion
+    pub scan: Duration,
+    pub source: CandidateSource,
+    /// Candidates expected before hydrating
+    pub estimated: usize,
+    /// Items the planner statistics expected the filter to pass
+    pub estimated_matches: Option<usize>,
+    pub candidates: usize,
+    /// Candidates left after the filter
+    pub matched: usize,
+    /// Graph results requested when the HNSW strategy was chosen
+    pub shortlist: Option<usize>,
+    /// Candidates whose similarity was computed
+    pub scored: usize,
+    pub results: usize,
+    pub strategy: SearchStrategy,
+    /// Why the query was scored that way
+    pub strategy_reason: &'static str,
+}
+
+/// One line of the slow-query log
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SlowQueryEntry<'a> {
+    timestamp: chrono::DateTime<chrono::Utc>,
+    index: &'a Path,
+    total_ms: f64,
+    hydrate_ms: f64,
+    filter_ms: f64,
+    scan_ms: f64,
+    candidates: usize,
+    matched: usize,
+    results: usize,
+    strategy: SearchStrategy,
+    strategy_reason: &'a str,
+    top_k: usize,
+    dimensions: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    text: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    filter: Option<&'a serde_json::Value>,
+    boosts: usize,
+    recency: bool,
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+/// Report `query` if it took at least the log's threshold. Never fails the
+/// query: a log file that can't be written is reported through `tracing`.
+pub(crate) fn record(
+    log: &SlowQueryLog,
+    index: &Path,
+    query: &Query,
+    timings: &QueryTimings,
+    total: Duration,
+) {
+    if total < Duration::from_millis(log.threshold_ms) {
+        return;
+    }
+
+    let entry = SlowQueryEntry {
+        timestamp: chrono::Utc::now(),
+        index,
+        total_ms: millis(total),
+        hydrate_ms: millis(timings.hydrate),
+        filter_ms: millis(timings.filter),
+        scan_ms: millis(timings.scan),
+        candidates: timings.candidates,
+        matched: timings.matched,
+        results: timings.results,
+        strategy: timings.strategy,
+        strategy_reason: timings.strategy_reason,
+        top_k: query.top_k,
+        dimensions: query.vector.as_ref().map(|v| v.len()),
+        text: query.text.as_deref(),
+        filter: query.filter.as_ref(),
+        boosts: query.boosts.len(),
+        recency: query.recency.is_some(),
+    };
+    let line = match serde_json::to_string(&entry) {
+        Ok(line) => line,
+        Err(e) => {
+            tracing::warn!(target: TRACING_TARGET, "failed to serialize slow query: {}", e);
+            return;
+        }
+    };
+
+    tracing::warn!(
+        target: TRACING_TARGET,
+        total_ms = entry.total_ms,
+        hydrate_ms = entry.hydrate_ms,
+        filter_ms = entry.filter_ms,
+        scan_ms = entry.scan_ms,
+        candidates = entry.candidates,
+        results = entry.results,
+        "slow query: {}",
+        line
+    );
+
+    if let Some(ref path) = log.path {
+        let written = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .and_then(|mut file| writeln!(file, "{}", line));
+        if let Err(e) = written {
+            tracing::warn!(
+                target: TRACING_TARGET,
+                "failed to write slow query log {}: {}",
+                path.display(),
+                e
+            );
+        }
+    }
+}
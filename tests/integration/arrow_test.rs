@@ -0,0 +1,98 @@
+// Copyright 2024-2026 Andrey Vasilevsky <anvanster@gmail.com>
+// SPDX-License-Identifier: Apache-2.0
+
+use serde_json::json;
+use tempfile::TempDir;
+use uuid::Uuid;
+use vectrust::arrow::{items_to_record_batch, record_batch_to_items};
+use vectrust::{LocalIndex, VectorItem};
+
+fn items() -> Vec<VectorItem> {
+    (0..5)
+        .map(|i| VectorItem {
+            id: Uuid::from_u128(i + 1),
+            vector: vec![i as f32, 1.0, 0.5],
+            metadata: json!({
+                "title": format!("doc {i}"),
+                "rank": i,
+                "score": if i % 2 == 0 { json!(i) } else { json!(0.5) },
+                "draft": i == 3,
+                "tags": ["a", i.to_string()],
+                "only_first": if i == 0 { json!("yes") } else { json!(null) },
+            }),
+            ..Default::default()
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn test_record_batch_round_trip() {
+    let items = items();
+    let batch = items_to_record_batch(&items).unwrap();
+    assert_eq!(batch.num_rows(), 5);
+    let schema = batch.schema();
+    assert_eq!(schema.field(0).name(), "id");
+    assert_eq!(schema.field(1).name(), "vector");
+    assert_eq!(
+        schema
+            .field_with_name("score")
+            .unwrap()
+            .data_type()
+            .to_string(),
+        "Float64"
+    );
+
+    let read = record_batch_to_items(&batch).unwrap();
+    for (read, item) in read.iter().zip(&items) {
+        assert_eq!(read.id, item.id);
+        assert_eq!(read.vector, item.vector);
+        assert_eq!(read.metadata["title"], item.metadata["title"]);
+        assert_eq!(read.metadata["tags"], item.metadata["tags"]);
+        assert_eq!(read.metadata["draft"], item.metadata["draft"]);
+        assert_eq!(
+            read.metadata.get("only_first"),
+            item.metadata.get("only_first").filter(|v| !v.is_null())
+        );
+    }
+
+    // Slices keep their place in the shared vector buffer
+    let tail = record_batch_to_items(&batch.slice(3, 2)).unwrap();
+    assert_eq!(tail[0].id, items[3].id);
+    assert_eq!(tail[1].vector, items[4].vector);
+
+    let mixed = vec![
+        items[0].clone(),
+        VectorItem {
+            vector: vec![1.0],
+            ..Default::default()
+        },
+    ];
+    assert!(items_to_record_batch(&mixed).is_err());
+}
+
+#[tokio::test]
+async fn test_arrow_ipc_import_and_export() {
+    let temp_dir = TempDir::new().unwrap();
+    let source = LocalIndex::new(temp_dir.path().join("source"), None).unwrap();
+    source.create_index(None).await.unwrap();
+    let batch = items_to_record_batch(&items()).unwrap();
+    assert_eq!(source.insert_record_batch(&batch).await.unwrap(), 5);
+
+    let batches = source.scan_record_batches(None, 2).await.unwrap();
+    assert_eq!(
+        batches.iter().map(|b| b.num_rows()).collect::<Vec<_>>(),
+        vec![2, 2, 1]
+    );
+    assert!(batches.iter().all(|b| b.schema() == batches[0].schema()));
+
+    let file = temp_dir.path().join("items.arrow");
+    assert_eq!(source.export_arrow_ipc(&file, None).await.unwrap(), 5);
+
+    let target = LocalIndex::new(temp_dir.path().join("target"), None).unwrap();
+    target.create_index(None).await.unwrap();
+    assert_eq!(target.import_arrow_ipc(&file).await.unwrap(), 5);
+    let copied = target.get_item(&Uuid::from_u128(3)).await.unwrap().unwrap();
+    assert_eq!(copied.vector, vec![2.0, 1.0, 0.5]);
+    assert_eq!(copied.metadata["tags"], json!(["a", "2"]));
+    assert_eq!(copied.metadata["rank"], 2);
+}
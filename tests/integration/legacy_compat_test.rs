@@ -0,0 +1,150 @@
+// Copyright 2024-2026 Andrey Vasilevsky <anvanster@gmail.com>
+// SPDX-License-Identifier: Apache-2.0
+
+//! Round-trips against index directories in the layout the Node.js
+//! library writes: `norm` next to each vector, and large metadata in a
+//! file named by `metadataFile` with indexed fields kept inline.
+
+use std::path::Path;
+use tempfile::TempDir;
+use uuid::Uuid;
+use vectrust::{LocalIndex, UpdateRequest, VectorItem};
+
+const DOCS_ID: &str = "0b7f2f5e-2c8a-4a57-9a0e-3f4c1f0d9a11";
+const BLOG_ID: &str = "5d1c7a3b-8e44-4f1e-b6a2-0c9d2e7f4b22";
+const BLOG_FILE: &str = "9a3e5c71-4b2d-4f08-8c6e-1d7b0a2f3e44.json";
+
+/// Copy the Node fixture, adding fields neither library knows about
+fn node_fixture() -> TempDir {
+    let dir = TempDir::new().unwrap();
+    let fixture = Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures/node");
+    for entry in std::fs::read_dir(fixture).unwrap() {
+        let entry = entry.unwrap();
+        std::fs::copy(entry.path(), dir.path().join(entry.file_name())).unwrap();
+    }
+
+    let mut index = read_index(dir.path());
+    index["generator"] = serde_json::json!({ "name": "vectra", "version": "0.9" });
+    index["items"][0]["tags"] = serde_json::json!(["pinned"]);
+    std::fs::write(
+        dir.path().join("index.json"),
+        serde_json::to_vec_pretty(&index).unwrap(),
+    )
+    .unwrap();
+    dir
+}
+
+fn read_index(dir: &Path) -> serde_json::Value {
+    serde_json::from_slice(&std::fs::read(dir.join("index.json")).unwrap()).unwrap()
+}
+
+fn item_json<'a>(index: &'a serde_json::Value, id: &str) -> &'a serde_json::Value {
+    index["items"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|item| item["id"] == id)
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_reads_node_index() {
+    let dir = node_fixture();
+    let index = LocalIndex::new(dir.path(), None).unwrap();
+
+    let blog = Uuid::parse_str(BLOG_ID).unwrap();
+    let item = index.get_item(&blog).await.unwrap().unwrap();
+    assert_eq!(item.metadata["title"], "Release notes");
+
+    let results = index
+        .query_items(vec![0.0, 0.6, 0.8], Some(1), None)
+        .await
+        .unwrap();
+    assert_eq!(results[0].item.id, blog);
+    assert_eq!(
+        results[0].item.metadata["body"],
+        "Vectors, metadata and everything in between."
+    );
+}
+
+#[tokio::test]
+async fn test_strict_mode_round_trip() {
+    let dir = node_fixture();
+    let index = LocalIndex::new_node_compatible(dir.path(), None).unwrap();
+
+    let docs = Uuid::parse_str(DOCS_ID).unwrap();
+    let update = UpdateRequest {
+        id: docs,
+        vector: Some(vec![3.0, 4.0, 0.0]),
+        metadata: None,
+    };
+    index.update_item(update).await.unwrap();
+    let large = VectorItem {
+        vector: vec![1.0, 0.0, 0.0],
+        metadata: serde_json::json!({ "category": "notes", "body": "x".repeat(2048) }),
+        ..Default::default()
+    };
+    let large = index.insert_item(large).await.unwrap();
+
+    let raw = read_index(dir.path());
+    assert_eq!(raw["generator"]["version"], "0.9");
+    assert_eq!(raw["metadata_config"]["indexed"][0], "category");
+
+    // Unknown fields survive, the cached norm follows the new vector
+    let docs_json = item_json(&raw, DOCS_ID);
+    assert_eq!(docs_json["tags"][0], "pinned");
+    assert_eq!(docs_json["norm"], 5.0);
+    assert!(docs_json.get("createdAt").is_some());
+    assert!(docs_json.get("created_at").is_none());
+
+    // The untouched Node item still points at its metadata file
+    assert_eq!(item_json(&raw, BLOG_ID)["metadataFile"], BLOG_FILE);
+
+    // New large metadata is stored the way Node stores it
+    let large_json = item_json(&raw, &large.id.to_string());
+    let file = large_json["metadataFile"].as_str().unwrap();
+    assert!(dir.path().join(file).exists());
+    assert_eq!(
+        large_json["metadata"],
+        serde_json::json!({ "category": "notes" })
+    );
+    let stored = index.get_item(&large.id).await.unwrap().unwrap();
+    assert_eq!(stored.metadata, large.metadata);
+
+    // Shrinking metadata moves it back inline and drops the pointer
+    let blog = Uuid::parse_str(BLOG_ID).unwrap();
+    let update = UpdateRequest {
+        id: blog,
+        vector: None,
+        metadata: Some(serde_json::json!({ "body": null })),
+    };
+    index.update_item(update).await.unwrap();
+    let raw = read_index(dir.path());
+    assert!(item_json(&raw, BLOG_ID).get("metadataFile").is_none());
+    assert!(!dir.path().join(BLOG_FILE).exists());
+    assert_eq!(
+        item_json(&raw, BLOG_ID)["metadata"]["title"],
+        "Release notes"
+    );
+}
+
+#[tokio::test]
+async fn test_default_mode_keeps_unknown_fields() {
+    let dir = node_fixture();
+    let index = LocalIndex::new(dir.path(), None).unwrap();
+    let docs = Uuid::parse_str(DOCS_ID).unwrap();
+    let update = UpdateRequest {
+        id: docs,
+        vector: None,
+        metadata: Some(serde_json::json!({ "title": "Start here" })),
+    };
+    index.update_item(update).await.unwrap();
+
+    let raw = read_index(dir.path());
+    assert_eq!(raw["generator"]["name"], "vectra");
+    let docs_json = item_json(&raw, DOCS_ID);
+    assert_eq!(docs_json["tags"][0], "pinned");
+    assert!((docs_json["norm"].as_f64().unwrap() - 1.0).abs() < 1e-6);
+    assert!(docs_json.get("created_at").is_some());
+    assert_eq!(item_json(&raw, BLOG_ID)["metadataFile"], BLOG_FILE);
+}
@@ -1,9 +1,19 @@
 // Copyright 2024-2026 Andrey Vasilevsky <anvanster@gmail.com>
 // SPDX-License-Identifier: Apache-2.0
 
+#[cfg(test)]
+mod arrow_test;
 #[cfg(test)]
 mod basic_test;
 #[cfg(test)]
 mod bench_test;
 #[cfg(test)]
+mod crash_test;
+#[cfg(test)]
 mod graph_test;
+#[cfg(test)]
+mod legacy_compat_test;
+#[cfg(test)]
+mod parity_test;
+#[cfg(test)]
+mod stress_test;
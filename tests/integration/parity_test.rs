@@ -0,0 +1,136 @@
+// Copyright 2024-2026 Andrey Vasilevsky <anvanster@gmail.com>
+// SPDX-License-Identifier: Apache-2.0
+
+//! Runs the shared parity script in `fixtures/parity` against the Rust
+//! API. The same script is run against the Node.js binding and
+//! vectra-enhanced by `crates/vectrust-node/test/parity.js`, so all three
+//! are held to the same expected results.
+
+use serde_json::Value;
+use std::collections::BTreeSet;
+use std::path::Path;
+use tempfile::TempDir;
+use uuid::Uuid;
+use vectrust::{LocalIndex, QueryResult, VectorItem};
+
+fn script() -> Value {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures/parity/script.json");
+    serde_json::from_slice(&std::fs::read(path).unwrap()).unwrap()
+}
+
+fn id(value: &Value) -> Uuid {
+    Uuid::parse_str(value.as_str().unwrap()).unwrap()
+}
+
+fn vector(value: &Value) -> Vec<f32> {
+    value
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|x| x.as_f64().unwrap() as f32)
+        .collect()
+}
+
+fn check_results(step: usize, results: &[QueryResult], expected: &Value, epsilon: f64) {
+    let expected = expected.as_array().unwrap();
+    assert_eq!(results.len(), expected.len(), "step {step}: result count");
+    for (result, expected) in results.iter().zip(expected) {
+        assert_eq!(result.item.id, id(&expected["id"]), "step {step}: ranking");
+        let score = expected["score"].as_f64().unwrap();
+        assert!(
+            (result.score as f64 - score).abs() <= epsilon,
+            "step {step}: score {} for {}, expected {score}",
+            result.score,
+            result.item.id
+        );
+    }
+}
+
+fn check_item(step: usize, item: Option<VectorItem>, expected: &Value, epsilon: f64) {
+    let Some(item) = item else {
+        assert!(expected.is_null(), "step {step}: item missing");
+        return;
+    };
+    assert!(
+        !expected.is_null(),
+        "step {step}: unexpected item {}",
+        item.id
+    );
+    assert_eq!(item.id, id(&expected["id"]), "step {step}: id");
+    assert_eq!(item.metadata, expected["metadata"], "step {step}: metadata");
+    let expected_vector = vector(&expected["vector"]);
+    assert_eq!(
+        item.vector.len(),
+        expected_vector.len(),
+        "step {step}: vector"
+    );
+    for (x, y) in item.vector.iter().zip(&expected_vector) {
+        assert!(((x - y) as f64).abs() <= epsilon, "step {step}: vector");
+    }
+}
+
+/// Run the script; `legacy` when `index` uses the index.json format
+async fn run_script(index: &LocalIndex, legacy: bool) {
+    let script = script();
+    let epsilon = script["epsilon"].as_f64().unwrap();
+    for (n, step) in script["steps"].as_array().unwrap().iter().enumerate() {
+        if step["only"] == "legacy" && !legacy {
+            continue;
+        }
+        let expected = &step["expect"];
+        let outcome = match step["op"].as_str().unwrap() {
+            "createIndex" => index.create_index(None).await,
+            "beginUpdate" => index.begin_update().await,
+            "endUpdate" => index.end_update().await,
+            "insertItem" => {
+                let item = VectorItem {
+                    id: id(&step["item"]["id"]),
+                    vector: vector(&step["item"]["vector"]),
+                    metadata: step["item"]["metadata"].clone(),
+                    ..Default::default()
+                };
+                index.insert_item(item).await.map(|_| ())
+            }
+            "deleteItem" => index.delete_item(&id(&step["id"])).await,
+            "getItem" => index
+                .get_item(&id(&step["id"]))
+                .await
+                .map(|item| check_item(n, item, expected, epsilon)),
+            "listItems" => index.list_items(None).await.map(|items| {
+                let ids: BTreeSet<Uuid> = items.iter().map(|item| item.id).collect();
+                let expected: BTreeSet<Uuid> =
+                    expected.as_array().unwrap().iter().map(id).collect();
+                assert_eq!(ids, expected, "step {n}");
+            }),
+            "queryItems" => {
+                let filter = step.get("filter").cloned();
+                let top_k = step["topK"].as_u64().map(|k| k as u32);
+                index
+                    .query_items(vector(&step["vector"]), top_k, filter)
+                    .await
+                    .map(|results| check_results(n, &results, expected, epsilon))
+            }
+            op => panic!("step {n}: unknown op {op}"),
+        };
+
+        if step["expectError"] == true {
+            assert!(outcome.is_err(), "step {n}: expected an error");
+        } else if let Err(e) = outcome {
+            panic!("step {n}: {e}");
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_parity_script() {
+    let dir = TempDir::new().unwrap();
+    let index = LocalIndex::new(dir.path(), None).unwrap();
+    run_script(&index, false).await;
+}
+
+#[tokio::test]
+async fn test_parity_script_node_compatible() {
+    let dir = TempDir::new().unwrap();
+    let index = LocalIndex::new_node_compatible(dir.path(), None).unwrap();
+    run_script(&index, true).await;
+}
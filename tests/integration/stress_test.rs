@@ -0,0 +1,185 @@
+// Copyright 2024-2026 Andrey Vasilevsky <anvanster@gmail.com>
+// SPDX-License-Identifier: Apache-2.0
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tempfile::TempDir;
+use uuid::Uuid;
+use vectrust::{LocalIndex, UpdateRequest, VectorItem};
+
+const DIMENSIONS: usize = 16;
+
+/// What one writer task expects the index to hold for its own ids
+#[derive(Default)]
+struct Expected {
+    live: HashMap<Uuid, Vec<f32>>,
+    deleted: Vec<Uuid>,
+}
+
+/// Deterministic per-task generator so failures reproduce
+struct Lcg(u64);
+
+impl Lcg {
+    fn next(&mut self) -> u64 {
+        self.0 = self
+            .0
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        self.0 >> 33
+    }
+
+    fn vector(&mut self) -> Vec<f32> {
+        (0..DIMENSIONS)
+            .map(|_| (self.next() % 2000) as f32 / 1000.0 - 1.0)
+            .collect()
+    }
+}
+
+/// Mixed inserts, updates, deletes and queries from one task.
+///
+/// Each writer only touches ids it inserted, so its view of the final
+/// state is exact no matter how tasks interleave.
+async fn writer(index: Arc<LocalIndex>, task: u64, ops: usize) -> Expected {
+    let mut rng = Lcg(task + 1);
+    let mut expected = Expected::default();
+    let mut ids: Vec<Uuid> = Vec::new();
+
+    for seq in 0..ops {
+        match rng.next() % 10 {
+            // Inserts dominate so the index keeps growing
+            0..=4 => {
+                let item = VectorItem {
+                    id: Uuid::new_v4(),
+                    vector: rng.vector(),
+                    metadata: serde_json::json!({ "task": task, "seq": seq }),
+                    ..Default::default()
+                };
+                let inserted = index.insert_item(item).await.unwrap();
+                expected.live.insert(inserted.id, inserted.vector);
+                ids.push(inserted.id);
+            }
+            5 | 6 if !ids.is_empty() => {
+                let id = ids[rng.next() as usize % ids.len()];
+                let vector = rng.vector();
+                index
+                    .update_item(UpdateRequest {
+                        id,
+                        vector: Some(vector.clone()),
+                        metadata: Some(serde_json::json!({ "updated": seq })),
+                    })
+                    .await
+                    .unwrap();
+                expected.live.insert(id, vector);
+            }
+            7 if !ids.is_empty() => {
+                let id = ids.swap_remove(rng.next() as usize % ids.len());
+                index.delete_item(&id).await.unwrap();
+                expected.live.remove(&id);
+                expected.deleted.push(id);
+            }
+            _ => {
+                let filter = serde_json::json!({ "task": task });
+                let results = index
+                    .query_items(rng.vector(), Some(5), Some(filter))
+                    .await
+                    .unwrap();
+                for result in results {
+                    assert_eq!(result.item.vector.len(), DIMENSIONS);
+                    assert!(result.score.is_finite());
+                    assert_eq!(result.item.metadata["task"], task);
+                }
+            }
+        }
+    }
+    expected
+}
+
+/// Read-only load running alongside the writers
+async fn reader(index: Arc<LocalIndex>, task: u64, ops: usize) {
+    let mut rng = Lcg(1000 + task);
+    for _ in 0..ops {
+        let results = index
+            .query_items(rng.vector(), Some(10), None)
+            .await
+            .unwrap();
+        assert!(results.len() <= 10);
+        for result in results {
+            assert_eq!(result.item.vector.len(), DIMENSIONS);
+        }
+        let items = index.list_items(None).await.unwrap();
+        assert!(items.iter().all(|item| item.vector.len() == DIMENSIONS));
+    }
+}
+
+/// Hammer one index from many tasks, then check nothing was lost or corrupted.
+///
+/// Debug builds run a short workload; for a longer soak:
+/// ```sh
+/// cargo test --release -p integration-tests -- stress_concurrent --nocapture
+/// ```
+#[tokio::test(flavor = "multi_thread", worker_threads = 8)]
+async fn stress_concurrent_mixed_workload() {
+    let (writers, readers, ops) = if cfg!(debug_assertions) {
+        (8, 4, 60)
+    } else {
+        (32, 8, 500)
+    };
+
+    let temp_dir = TempDir::new().unwrap();
+    let index = Arc::new(LocalIndex::new(temp_dir.path(), None).unwrap());
+    index.create_index(None).await.unwrap();
+
+    let mut writer_handles = Vec::new();
+    for task in 0..writers {
+        writer_handles.push(tokio::spawn(writer(index.clone(), task, ops)));
+    }
+    let mut reader_handles = Vec::new();
+    for task in 0..readers {
+        reader_handles.push(tokio::spawn(reader(index.clone(), task, ops / 4)));
+    }
+
+    // A hang here means a deadlock between index locks
+    let all = async {
+        let mut expected = Vec::new();
+        for handle in writer_handles {
+            expected.push(handle.await.unwrap());
+        }
+        for handle in reader_handles {
+            handle.await.unwrap();
+        }
+        expected
+    };
+    let expected = tokio::time::timeout(Duration::from_secs(120), all)
+        .await
+        .expect("workload did not finish; possible deadlock");
+
+    let live: HashMap<Uuid, Vec<f32>> = expected
+        .iter()
+        .flat_map(|e| e.live.iter().map(|(id, v)| (*id, v.clone())))
+        .collect();
+
+    // Count matches what the writers left behind
+    let stats = index.get_stats().await.unwrap();
+    assert_eq!(stats.items, live.len());
+    let listed = index.list_items(None).await.unwrap();
+    assert_eq!(listed.len(), live.len());
+
+    // Every surviving id is retrievable with its last written vector
+    for (id, vector) in &live {
+        let item = index.get_item(id).await.unwrap().expect("lost item");
+        assert_eq!(item.vector.len(), DIMENSIONS);
+        assert_eq!(&item.vector, vector);
+    }
+    for id in expected.iter().flat_map(|e| &e.deleted) {
+        assert!(index.get_item(id).await.unwrap().is_none());
+    }
+
+    // Reopening sees the same state
+    let index = Arc::try_unwrap(index)
+        .ok()
+        .expect("tasks still hold the index");
+    drop(index);
+    let reopened = LocalIndex::new(temp_dir.path(), None).unwrap();
+    assert_eq!(reopened.get_stats().await.unwrap().items, live.len());
+}